@@ -8,7 +8,11 @@ openssl req -x509 -newkey param:ec_parameters.pem -keyout permissions_ca_private
 
 # Sign configuration documents
 openssl smime -sign -in permissive_governance_unsigned.xml -out permissive_governance.p7s -signer permissions_ca_certificate.pem -inkey permissions_ca_private_key.pem -passin file:password
+openssl smime -sign -in protected_topic_governance_unsigned.xml -out protected_topic_governance.p7s -signer permissions_ca_certificate.pem -inkey permissions_ca_private_key.pem -passin file:password
 openssl smime -sign -in permissive_permissions_unsigned.xml -out permissive_permissions.p7s -signer permissions_ca_certificate.pem -inkey permissions_ca_private_key.pem -passin file:password
+openssl smime -sign -in access_controlled_governance_unsigned.xml -out access_controlled_governance.p7s -signer permissions_ca_certificate.pem -inkey permissions_ca_private_key.pem -passin file:password
+openssl smime -sign -in protected_discovery_governance_unsigned.xml -out protected_discovery_governance.p7s -signer permissions_ca_certificate.pem -inkey permissions_ca_private_key.pem -passin file:password
+openssl smime -sign -in restrictive_permissions_unsigned.xml -out restrictive_permissions.p7s -signer permissions_ca_certificate.pem -inkey permissions_ca_private_key.pem -passin file:password
 
 # Create identity CA
 openssl req -x509 -newkey param:ec_parameters.pem -keyout identity_ca_private_key.pem -passout file:password -out identity_ca_certificate.pem -days 999999 -subj "/O=Example Organization/CN=identity_ca_common_name"
@@ -19,3 +23,23 @@ openssl req -newkey param:ec_parameters.pem -keyout participant1_private_key.pem
 # Sign the certificate request 
 openssl x509 -req -days 999999 -in identity_certificate_requests/participant1.pem -CA identity_ca_certificate.pem -CAkey identity_ca_private_key.pem -passin file:password -out participant1_certificate.pem -set_serial 1
 
+
+# Sign expired and not-yet-valid variants of participant1 identity certificate for validity window tests.
+# The -not_before and -not_after options need OpenSSL 3.4 or newer.
+openssl x509 -req -in identity_certificate_requests/participant1.pem -CA identity_ca_certificate.pem -CAkey identity_ca_private_key.pem -passin file:password -out participant1_expired_certificate.pem -set_serial 2 -not_before 20200101000000Z -not_after 20210101000000Z
+openssl x509 -req -in identity_certificate_requests/participant1.pem -CA identity_ca_certificate.pem -CAkey identity_ca_private_key.pem -passin file:password -out participant1_not_yet_valid_certificate.pem -set_serial 3 -not_before 29990101000000Z -not_after 30000101000000Z
+
+# Create and sign participant2 identity certificate. This one gets revoked in the CRL below.
+openssl req -newkey param:ec_parameters.pem -keyout participant2_private_key.pem -nodes -out identity_certificate_requests/participant2.pem -subj "/O=Example Organization/CN=participant2_common_name"
+openssl x509 -req -days 999999 -in identity_certificate_requests/participant2.pem -CA identity_ca_certificate.pem -CAkey identity_ca_private_key.pem -passin file:password -out participant2_certificate.pem -set_serial 4
+
+# Certificate Revocation Lists from the identity CA: one empty, one revoking participant2.
+# "openssl ca" needs a database, so use a throwaway one. The crlnumber makes these v2 CRLs.
+CA_DIR=$(mktemp -d)
+touch "$CA_DIR/index.txt"
+echo 01 > "$CA_DIR/crlnumber"
+printf "[ ca ]\ndefault_ca = identity_ca\n[ identity_ca ]\ndatabase = $CA_DIR/index.txt\ncrlnumber = $CA_DIR/crlnumber\ndefault_md = sha256\ndefault_crl_days = 999999\n" > "$CA_DIR/ca.conf"
+openssl ca -config "$CA_DIR/ca.conf" -gencrl -cert identity_ca_certificate.pem -keyfile identity_ca_private_key.pem -passin file:password -out identity_ca_empty_crl.pem
+openssl ca -config "$CA_DIR/ca.conf" -revoke participant2_certificate.pem -cert identity_ca_certificate.pem -keyfile identity_ca_private_key.pem -passin file:password
+openssl ca -config "$CA_DIR/ca.conf" -gencrl -cert identity_ca_certificate.pem -keyfile identity_ca_private_key.pem -passin file:password -out identity_ca_revoked_participant2_crl.pem
+rm -r "$CA_DIR"
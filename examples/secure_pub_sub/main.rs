@@ -0,0 +1,123 @@
+//! Secure publish and subscribe between two DomainParticipants
+//!
+//! Both participants are configured from certificate, private key,
+//! governance and permissions files, as a real deployment would be. By
+//! default the files in `example_security_configuration_files` are used.
+//! Another directory with the same file names can be given as the only
+//! command line argument.
+//!
+//! Run with `cargo run --features security --example secure_pub_sub`
+
+use std::{
+  path::{Path, PathBuf},
+  time::{Duration, Instant},
+};
+
+use rustdds::{
+  policy::Reliability, DomainParticipant, DomainParticipantBuilder, QosPolicyBuilder, TopicKind,
+};
+use serde::{Deserialize, Serialize};
+
+const DOMAIN_ID: u16 = 0;
+const TOPIC_NAME: &str = "SecureGreetings";
+const TIMEOUT: Duration = Duration::from_secs(20);
+
+#[derive(Serialize, Deserialize, Debug)]
+struct Greeting {
+  count: i32,
+  text: String,
+}
+
+// The example permissions grant access only to the identity "participant1",
+// so both participants use it. In a real deployment each participant would
+// have a certificate and permissions of its own.
+fn secure_participant(config_dir: &Path) -> DomainParticipant {
+  let mut builder = DomainParticipantBuilder::new(DOMAIN_ID);
+  builder
+    .security_from_files(
+      config_dir.join("identity_ca_certificate.pem"),
+      config_dir.join("participant1_certificate.pem"),
+      config_dir.join("participant1_private_key.pem"),
+      config_dir.join("permissions_ca_certificate.pem"),
+      config_dir.join("permissive_governance.p7s"),
+      config_dir.join("permissive_permissions.p7s"),
+    )
+    .unwrap_or_else(|e| panic!("Bad security configuration: {e:?}"));
+  builder
+    .build()
+    .unwrap_or_else(|e| panic!("Cannot create DomainParticipant: {e:?}"))
+}
+
+fn main() {
+  let config_dir = std::env::args_os().nth(1).map_or_else(
+    || Path::new(env!("CARGO_MANIFEST_DIR")).join("example_security_configuration_files"),
+    PathBuf::from,
+  );
+
+  let publishing_participant = secure_participant(&config_dir);
+  let subscribing_participant = secure_participant(&config_dir);
+
+  let qos = QosPolicyBuilder::new()
+    .reliability(Reliability::Reliable {
+      max_blocking_time: rustdds::Duration::from_secs(1),
+    })
+    .build();
+
+  let writer = {
+    let topic = publishing_participant
+      .create_topic(
+        TOPIC_NAME.to_string(),
+        "Greeting".to_string(),
+        &qos,
+        TopicKind::NoKey,
+      )
+      .unwrap();
+    publishing_participant
+      .create_publisher(&qos)
+      .unwrap()
+      .create_datawriter_no_key_cdr::<Greeting>(&topic, None)
+      .unwrap()
+  };
+  let mut reader = {
+    let topic = subscribing_participant
+      .create_topic(
+        TOPIC_NAME.to_string(),
+        "Greeting".to_string(),
+        &qos,
+        TopicKind::NoKey,
+      )
+      .unwrap();
+    subscribing_participant
+      .create_subscriber(&qos)
+      .unwrap()
+      .create_datareader_no_key_cdr::<Greeting>(&topic, None)
+      .unwrap()
+  };
+
+  // The participants must authenticate each other before the endpoints can
+  // match, so keep writing until something arrives.
+  println!("Waiting for the participants to authenticate each other.");
+  let deadline = Instant::now() + TIMEOUT;
+  let mut count = 0;
+  while Instant::now() < deadline {
+    count += 1;
+    writer
+      .write(
+        Greeting {
+          count,
+          text: "Hello, securely".to_string(),
+        },
+        None,
+      )
+      .unwrap();
+    std::thread::sleep(Duration::from_millis(500));
+
+    if let Some(sample) = reader.take_next_sample().unwrap() {
+      let greeting = sample.into_value();
+      println!("Received #{}: {}", greeting.count, greeting.text);
+      return;
+    }
+  }
+  eprintln!("Nothing received in {TIMEOUT:?}.");
+  std::process::exit(1);
+}
@@ -6,9 +6,11 @@ mod helpers;
 
 pub(crate) mod participant;
 pub use participant::DomainParticipant;
+pub(crate) mod participant_factory;
 
 pub(crate) mod dds_entity;
 pub(crate) mod ddsdata;
+pub(crate) mod metrics;
 pub(crate) mod pubsub;
 pub(crate) mod readcondition;
 pub(crate) mod topic;
@@ -41,3 +43,6 @@ pub mod with_key;
 
 /// Serializer/deserializer adapters to connect serialization to RTPS.
 pub mod adapters;
+
+/// Disk spill for the retained history of DataWriters.
+pub mod sample_store;
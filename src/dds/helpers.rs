@@ -14,28 +14,40 @@ pub fn try_send_timeout<T>(
   t: T,
   timeout_opt: Option<Duration>,
 ) -> Result<(), TrySendError<T>> {
-  match sender.try_send(t) {
-    Ok(()) => Ok(()), // This is expected to be the common case
+  try_send_timeout_when(sender, t, || true, timeout_opt)
+}
 
-    Err(TrySendError::Full(tt)) => {
-      let mut mt = tt;
-      let timeout = timeout_opt.unwrap_or(TIMEOUT_FALLBACK).to_nanoseconds();
-      let mut time_left = timeout;
-      let mut delay = TIMEOUT_EPSILON_NS;
-      while time_left > TIMEOUT_EPSILON_NS {
-        match sender.try_send(mt) {
-          Ok(()) => return Ok(()),
-          Err(TrySendError::Full(tt)) => {
-            thread::sleep(std::time::Duration::from_nanos(delay as u64)); // and try again
-            mt = tt;
-            time_left -= delay;
-            delay *= 2;
-          }
-          Err(other) => return Err(other),
-        }
+// Like try_send_timeout, but does not send before `ready` holds. Waiting for
+// `ready` and for room in the channel share the same timeout.
+pub fn try_send_timeout_when<T>(
+  sender: &SyncSender<T>,
+  t: T,
+  ready: impl Fn() -> bool,
+  timeout_opt: Option<Duration>,
+) -> Result<(), TrySendError<T>> {
+  let mut mt = t;
+  if ready() {
+    match sender.try_send(mt) {
+      Ok(()) => return Ok(()), // This is expected to be the common case
+      Err(TrySendError::Full(tt)) => mt = tt,
+      Err(other) => return Err(other),
+    }
+  }
+  let timeout = timeout_opt.unwrap_or(TIMEOUT_FALLBACK).to_nanoseconds();
+  let mut time_left = timeout;
+  let mut delay = TIMEOUT_EPSILON_NS;
+  while time_left > TIMEOUT_EPSILON_NS {
+    let this_delay = delay.min(time_left);
+    thread::sleep(std::time::Duration::from_nanos(this_delay as u64));
+    time_left -= this_delay;
+    delay *= 2;
+    if ready() {
+      match sender.try_send(mt) {
+        Ok(()) => return Ok(()),
+        Err(TrySendError::Full(tt)) => mt = tt, // and try again
+        Err(other) => return Err(other),
       }
-      Err(TrySendError::Full(mt))
     }
-    Err(other) => Err(other),
   }
+  Err(TrySendError::Full(mt))
 }
@@ -5,7 +5,7 @@ use std::{convert::TryFrom, hash::Hash};
 use byteorder::BigEndian;
 use rand::Rng;
 use log::error;
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Serialize, Serializer};
 pub use cdr_encoding_size::*;
 
 use crate::serialization::cdr_serializer::to_bytes;
@@ -110,7 +110,26 @@ pub trait Key:
 {
   // no methods required
 
-  // provided method:
+  // provided methods:
+
+  /// Serializes the key as it is sent on the wire.
+  ///
+  /// This is used to compute the [`KeyHash`], i.e. the instance handle, and
+  /// the payload of key-only DATA, e.g. dispose and unregister, when using
+  /// [`CDRSerializerAdapter`](crate::CDRSerializerAdapter). The default just
+  /// calls `Serialize::serialize`.
+  ///
+  /// Override this if the wire representation of the key must differ from the
+  /// serde representation of the `Key` type, e.g. to interoperate with another
+  /// DDS implementation, or if the key type carries fields that are not part
+  /// of the key. Remember to implement [`CdrEncodingSize`] to match, because
+  /// it decides if the KeyHash is an MD5 digest. The reading side
+  /// deserializes keys with the DeserializerAdapter, so it must understand
+  /// the same representation.
+  fn serialize_key<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+    self.serialize(serializer)
+  }
+
   fn hash_key(&self, force_md5: bool) -> KeyHash {
     // See RTPS Spec v2.3 Section 9.6.3.8 KeyHash
 
@@ -152,7 +171,7 @@ pub trait Key:
       maximum-size of the serialized key.
     */
 
-    let mut cdr_bytes = to_bytes::<Self, BigEndian>(self).unwrap_or_else(|e| {
+    let mut cdr_bytes = to_bytes::<_, BigEndian>(&SerializeKey(self)).unwrap_or_else(|e| {
       error!("Hashing key {:?} failed!", e);
       // This would cause a lot of hash collisions, but wht else we could do
       // if the key cannot be serialized? Are there any realistic conditions
@@ -174,6 +193,16 @@ pub trait Key:
   }
 }
 
+// Adapter to serialize a Key using Key::serialize_key instead of
+// Serialize::serialize
+pub(crate) struct SerializeKey<'a, K: Key>(pub &'a K);
+
+impl<'a, K: Key> Serialize for SerializeKey<'a, K> {
+  fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+    self.0.serialize_key(serializer)
+  }
+}
+
 impl Key for () {
   fn hash_key(&self, _force_md5: bool) -> KeyHash {
     KeyHash::zero()
@@ -226,3 +255,83 @@ impl BuiltInTopicKey {
     }
   }
 }
+
+#[cfg(test)]
+mod tests {
+  use byteorder::LittleEndian;
+
+  use super::*;
+  use crate::{serialization::CDRSerializerAdapter, with_key::SerializerAdapter};
+
+  // Key whose wire representation is only the id, as a 32-bit integer. The
+  // label is local bookkeeping and not part of the key on the wire.
+  #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+  struct SensorKey {
+    id: u16,
+    label: String,
+  }
+
+  impl CdrEncodingSize for SensorKey {
+    fn cdr_encoding_max_size() -> CdrEncodingMaxSize {
+      CdrEncodingMaxSize::Bytes(4)
+    }
+  }
+
+  impl Key for SensorKey {
+    fn serialize_key<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+      serializer.serialize_u32(u32::from(self.id))
+    }
+  }
+
+  #[derive(Serialize)]
+  struct Sensor {
+    id: u16,
+    label: String,
+    value: f64,
+  }
+
+  impl Keyed for Sensor {
+    type K = SensorKey;
+    fn key(&self) -> SensorKey {
+      SensorKey {
+        id: self.id,
+        label: self.label.clone(),
+      }
+    }
+  }
+
+  #[test]
+  fn custom_key_serialization() {
+    let sample = Sensor {
+      id: 0x0102,
+      label: "thermometer".to_string(),
+      value: 21.5,
+    };
+    let key = sample.key();
+
+    // Plain serde serialization would include the label, and the max size
+    // would be unbounded, so the hash would be an MD5 digest.
+    let mut expected = [0; 16];
+    expected[..4].copy_from_slice(&[0x00, 0x00, 0x01, 0x02]);
+    assert_eq!(key.hash_key(false), KeyHash(expected));
+
+    // Same key, different label: same instance
+    let other_label = SensorKey {
+      label: "hygrometer".to_string(),
+      ..key.clone()
+    };
+    assert_eq!(key.hash_key(false), other_label.hash_key(false));
+    assert_eq!(
+      key.hash_key(true),
+      KeyHash(*md5::compute([0x00, 0x00, 0x01, 0x02]))
+    );
+
+    // Key-only DATA uses the same representation
+    assert_eq!(
+      CDRSerializerAdapter::<Sensor, LittleEndian>::key_to_bytes(&key)
+        .unwrap()
+        .as_ref(),
+      &[0x02, 0x01, 0x00, 0x00]
+    );
+  }
+}
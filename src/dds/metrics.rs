@@ -0,0 +1,133 @@
+// Rendering of participant statistics in the Prometheus text exposition
+// format, see https://prometheus.io/docs/instrumenting/exposition_formats/
+//
+// Only the formatting is done here. Serving the text over HTTP is up to the
+// application.
+
+use std::fmt::Write;
+
+pub(crate) const METRIC_NAME_PREFIX: &str = "rustdds_";
+
+#[derive(Clone, Copy, Debug)]
+pub(crate) enum MetricType {
+  Counter,
+  Gauge,
+}
+
+impl MetricType {
+  fn as_str(&self) -> &'static str {
+    match self {
+      MetricType::Counter => "counter",
+      MetricType::Gauge => "gauge",
+    }
+  }
+}
+
+pub(crate) struct PrometheusText {
+  text: String,
+  // Labels put on every sample, before the labels of the sample itself
+  common_labels: Vec<(String, String)>,
+}
+
+impl PrometheusText {
+  pub fn new() -> Self {
+    Self {
+      text: String::new(),
+      common_labels: Vec::new(),
+    }
+  }
+
+  // Adds a label to all samples written after this call
+  pub fn add_common_label(&mut self, label: &str, value: &str) {
+    self
+      .common_labels
+      .push((label.to_string(), value.to_string()));
+  }
+
+  // Writes the HELP and TYPE lines of a metric family. Its samples must follow
+  // before the next family. Counter names should end in "_total".
+  pub fn family(&mut self, name: &str, metric_type: MetricType, help: &str) {
+    // Writing to a String cannot fail
+    let _ = writeln!(
+      self.text,
+      "# HELP {METRIC_NAME_PREFIX}{name} {}",
+      escape_help(help)
+    );
+    let _ = writeln!(
+      self.text,
+      "# TYPE {METRIC_NAME_PREFIX}{name} {}",
+      metric_type.as_str()
+    );
+  }
+
+  pub fn sample(&mut self, name: &str, labels: &[(&str, &str)], value: u64) {
+    let _ = write!(self.text, "{METRIC_NAME_PREFIX}{name}");
+    let labels: Vec<String> = self
+      .common_labels
+      .iter()
+      .map(|(label, value)| (label.as_str(), value.as_str()))
+      .chain(labels.iter().copied())
+      .map(|(label, value)| format!("{label}=\"{}\"", escape_label_value(value)))
+      .collect();
+    if !labels.is_empty() {
+      let _ = write!(self.text, "{{{}}}", labels.join(","));
+    }
+    let _ = writeln!(self.text, " {value}");
+  }
+
+  // A family with a single, unlabeled sample
+  pub fn single(&mut self, name: &str, metric_type: MetricType, help: &str, value: u64) {
+    self.family(name, metric_type, help);
+    self.sample(name, &[], value);
+  }
+
+  pub fn into_string(self) -> String {
+    self.text
+  }
+}
+
+fn escape_help(help: &str) -> String {
+  help.replace('\\', "\\\\").replace('\n', "\\n")
+}
+
+fn escape_label_value(value: &str) -> String {
+  value
+    .replace('\\', "\\\\")
+    .replace('"', "\\\"")
+    .replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn label_values_are_escaped() {
+    let mut text = PrometheusText::new();
+    text.family("test_total", MetricType::Counter, "Test\ncounter");
+    text.sample(
+      "test_total",
+      &[("topic", "a\"b\\c\nd"), ("kind", "writer")],
+      3,
+    );
+    assert_eq!(
+      text.into_string(),
+      "# HELP rustdds_test_total Test\\ncounter\n# TYPE rustdds_test_total counter\n\
+       rustdds_test_total{topic=\"a\\\"b\\\\c\\nd\",kind=\"writer\"} 3\n"
+    );
+  }
+
+  #[test]
+  fn common_labels_come_first() {
+    let mut text = PrometheusText::new();
+    text.add_common_label("instance_id", "robot-7");
+    text.single("sent_total", MetricType::Counter, "Sent.", 1);
+    text.sample("endpoints", &[("kind", "local_writer")], 2);
+    assert_eq!(
+      text.into_string(),
+      "# HELP rustdds_sent_total Sent.\n# TYPE rustdds_sent_total counter\n\
+       rustdds_sent_total{instance_id=\"robot-7\"} 1\n\
+       rustdds_endpoints{instance_id=\"robot-7\",kind=\"local_writer\"} 2\n"
+    );
+  }
+}
@@ -11,15 +11,15 @@ use crate::{
   dds::{
     adapters::no_key::DeserializerAdapter,
     no_key::datasample::DataSample,
-    qos::{HasQoSPolicy, QosPolicies},
+    qos::{HasQoSPolicy, QosDiff, QosPolicies},
     readcondition::ReadCondition,
-    result::ReadResult,
+    result::{CreateResult, ReadResult},
     statusevents::DataReaderStatus,
     with_key::{
       datareader as datareader_with_key,
       datasample::{DataSample as WithKeyDataSample, Sample},
       DataReader as WithKeyDataReader, DataReaderEventStream as WithKeyDataReaderEventStream,
-      DataReaderStream as WithKeyDataReaderStream,
+      DataReaderStream as WithKeyDataReaderStream, RawSample,
     },
   },
   serialization::CDRDeserializerAdapter,
@@ -35,6 +35,10 @@ pub type DataReaderCdr<D> = DataReader<D, CDRDeserializerAdapter<D>>;
 
 // DataReader for NO_KEY data. Does not require "D: Keyed"
 /// DDS DataReader for no key topics.
+///
+/// As with the [keyed DataReader](crate::with_key::DataReader), having no
+/// samples available is not an error, but an empty result.
+///
 /// # Examples
 ///
 /// ```
@@ -417,6 +421,41 @@ where
   }
   */
 
+  /// Enables a DataReader that was created disabled. See
+  /// [`with_key::SimpleDataReader::enable`](crate::with_key::SimpleDataReader::enable).
+  pub fn enable(&self) -> CreateResult<()> {
+    self.keyed_datareader.enable()
+  }
+
+  /// Tells if this DataReader is enabled.
+  pub fn is_enabled(&self) -> bool {
+    self.keyed_datareader.is_enabled()
+  }
+
+  /// Reads the next unread sample without deserializing it. See
+  /// [`with_key::DataReader::read_raw`](crate::with_key::DataReader::read_raw).
+  pub fn read_raw(&mut self) -> Option<RawSample<'_>> {
+    self.keyed_datareader.read_raw()
+  }
+
+  /// Compares the QoS of this DataReader with that of a remote DataWriter.
+  /// See [`with_key::DataReader::qos_diff`](crate::with_key::DataReader::qos_diff).
+  pub fn qos_diff(&self, writer_guid: GUID) -> Option<QosDiff> {
+    self.keyed_datareader.qos_diff(writer_guid)
+  }
+
+  /// The QoS this DataReader actually uses. See
+  /// [`with_key::DataReader::effective_qos`](crate::with_key::DataReader::effective_qos).
+  pub fn effective_qos(&self) -> QosPolicies {
+    self.keyed_datareader.effective_qos()
+  }
+
+  /// The QoS offered by a matched remote DataWriter. See
+  /// [`with_key::DataReader::matched_writer_qos`](crate::with_key::DataReader::matched_writer_qos).
+  pub fn matched_writer_qos(&self, writer_guid: GUID) -> Option<QosPolicies> {
+    self.keyed_datareader.matched_writer_qos(writer_guid)
+  }
+
   /// An async stream for reading the (bare) data samples
   pub fn async_sample_stream(self) -> DataReaderStream<D, DA> {
     DataReaderStream {
@@ -7,8 +7,8 @@ use crate::{
     adapters::no_key::SerializerAdapter,
     dds_entity::DDSEntity,
     pubsub::Publisher,
-    qos::{HasQoSPolicy, QosPolicies},
-    result::{unwrap_no_key_write_error, WriteResult},
+    qos::{HasQoSPolicy, QosDiff, QosPolicies},
+    result::{unwrap_no_key_write_error, CreateResult, WriteResult},
     statusevents::{DataWriterStatus, StatusReceiverStream},
     topic::Topic,
     with_key::datawriter as datawriter_with_key,
@@ -129,6 +129,21 @@ where
   pub fn wait_for_acknowledgments(&self, max_wait: Duration) -> WriteResult<bool, ()> {
     self.keyed_datawriter.wait_for_acknowledgments(max_wait)
   }
+
+  /// Reports the reliability protocol state of a matched reader.
+  /// See [`crate::with_key::DataWriter::reader_reliability_state`].
+  pub fn reader_reliability_state(
+    &self,
+    reader_guid: GUID,
+  ) -> Option<datawriter_with_key::ReaderReliabilityState> {
+    self.keyed_datawriter.reader_reliability_state(reader_guid)
+  }
+
+  /// Compares the QoS of this DataWriter with that of a remote DataReader.
+  /// See [`crate::with_key::DataWriter::qos_diff`].
+  pub fn qos_diff(&self, reader_guid: GUID) -> Option<QosDiff> {
+    self.keyed_datawriter.qos_diff(reader_guid)
+  }
   /*
   // status queries
   /// Unimplemented. <b>Do not use</b>.
@@ -305,6 +320,17 @@ where
     self.keyed_datawriter.publisher()
   }
 
+  /// Enables a DataWriter that was created disabled. See
+  /// [`with_key::DataWriter::enable`](crate::with_key::DataWriter::enable).
+  pub fn enable(&self) -> CreateResult<()> {
+    self.keyed_datawriter.enable()
+  }
+
+  /// Tells if this DataWriter is enabled.
+  pub fn is_enabled(&self) -> bool {
+    self.keyed_datawriter.is_enabled()
+  }
+
   /// Manually asserts liveliness if QoS agrees
   ///
   /// # Examples
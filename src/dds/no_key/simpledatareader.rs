@@ -8,8 +8,12 @@ use mio_08;
 
 use crate::{
   dds::{
-    adapters::no_key::*, no_key::datasample::DeserializedCacheChange, qos::*, result::ReadResult,
-    statusevents::*, with_key,
+    adapters::no_key::*,
+    no_key::datasample::DeserializedCacheChange,
+    qos::*,
+    result::{CreateResult, ReadResult},
+    statusevents::*,
+    with_key,
   },
   serialization::CDRDeserializerAdapter,
   structure::entity::RTPSEntity,
@@ -68,6 +72,17 @@ where
     self.keyed_simpledatareader.guid()
   }
 
+  /// Enables a SimpleDataReader that was created disabled. See
+  /// [`with_key::SimpleDataReader::enable`](crate::with_key::SimpleDataReader::enable).
+  pub fn enable(&self) -> CreateResult<()> {
+    self.keyed_simpledatareader.enable()
+  }
+
+  /// Tells if this SimpleDataReader is enabled.
+  pub fn is_enabled(&self) -> bool {
+    self.keyed_simpledatareader.is_enabled()
+  }
+
   pub fn as_async_stream(
     &self,
   ) -> impl Stream<Item = ReadResult<DeserializedCacheChange<D>>> + FusedStream + '_ {
@@ -1,13 +1,18 @@
 // use mio::Token;
 use std::{
-  collections::HashMap,
+  collections::{BTreeMap, BTreeSet, HashMap},
   io::ErrorKind,
-  net::Ipv4Addr,
-  sync::{atomic, Arc, Mutex, RwLock, Weak},
+  net::{Ipv4Addr, SocketAddr},
+  sync::{
+    atomic::{self, AtomicU64},
+    Arc, Mutex, RwLock, Weak,
+  },
   thread,
   thread::JoinHandle,
   time::{Duration, Instant},
 };
+#[cfg(feature = "security")]
+use std::path::Path;
 
 use mio_extras::channel as mio_channel;
 use mio_06::Token;
@@ -15,33 +20,60 @@ use mio_06::Token;
 use log::{debug, error, info, trace, warn};
 
 use crate::{
-  create_error_out_of_resources, create_error_poisoned,
-  dds::{pubsub::*, qos::*, result::*, topic::*, typedesc::TypeDesc},
+  create_error_bad_parameter, create_error_out_of_resources, create_error_poisoned,
+  dds::{
+    metrics::{MetricType, PrometheusText},
+    pubsub::*,
+    qos::*,
+    result::*,
+    topic::*,
+    typedesc::TypeDesc,
+  },
   discovery::{
     discovery::{Discovery, DiscoveryCommand},
-    discovery_db::DiscoveryDB,
-    sedp_messages::DiscoveredTopicData,
+    discovery_backend::{DiscoveryBackend, NoExtraPeers},
+    discovery_db::{discovery_db_write, DiscoveryDB, DEFAULT_MAX_LOCATORS_PER_LIST},
+    discovery_events::{discovery_event_channel, DiscoveryEventReceiver},
+    participant_scan::{self, ScannedParticipant},
+    sedp_messages::{DiscoveredReaderData, DiscoveredTopicData, DiscoveredWriterData},
+    spdp_participant_data::DiscoveredParticipantInfo,
+  },
+  network::{
+    constant::*,
+    locator_selector::LocatorSelector,
+    message_tap::MessageTap,
+    traffic_counters::TrafficCounters,
+    udp_listener::{SocketReuse, UDPListener},
   },
-  network::{constant::*, udp_listener::UDPListener},
   rtps::{
     constant::*,
     dp_event_loop::{DPEventLoop, DomainInfo, EventLoopCommand},
     reader::*,
+    message::MessageLimits,
     writer::WriterIngredients,
   },
-  structure::{dds_cache::DDSCache, entity::RTPSEntity, guid::*, locator::Locator},
+  structure::{
+    dds_cache::{DDSCache, TopicCacheUsage, DEFAULT_MAX_CACHE_TOPICS},
+    entity::RTPSEntity,
+    guid::*,
+    locator::Locator,
+  },
 };
 #[cfg(feature = "security")]
 use crate::{
-  create_error_internal, create_error_not_allowed_by_security,
+  create_error_dropped, create_error_internal, create_error_not_allowed_by_security,
+  discovery::{discovery_db::discovery_db_read, secure_discovery::AuthenticationStatus},
   security::{
     self,
+    authentication::authentication_builtin::DiscHandshakeState,
     security_plugins::{SecurityPlugins, SecurityPluginsHandle},
     AccessControl, Authentication, Cryptographic,
   },
 };
 #[cfg(not(feature = "security"))]
 use crate::no_security::SecurityPluginsHandle;
+#[cfg(feature = "test-util")]
+use crate::test_util::{InjectedPacket, PacketInjector};
 
 pub struct DomainParticipantBuilder {
   domain_id: u16,
@@ -50,6 +82,31 @@ pub struct DomainParticipantBuilder {
   which interfaces the DomainParticiapnt will talk to. */
   only_networks: Option<Vec<String>>, // if specified, run RTPS only over these interfaces
 
+  max_locators_per_list: usize,
+  max_cache_topics: usize,
+  max_cache_bytes: Option<usize>,
+  fragment_size: usize,
+  message_limits: MessageLimits,
+  heartbeat_response_delay: Duration,
+  send_retry_delay: Duration,
+  multicast_group: Ipv4Addr,
+  multicast_disabled: bool,
+  lazy_user_traffic_listeners: bool,
+  unicast_socket_reuse: SocketReuse,
+  multicast_socket_reuse: SocketReuse,
+  discard_untimestamped_data: bool,
+  defer_on_lock_contention: bool,
+  announced_locators: HashMap<Token, Vec<Locator>>,
+  guid_prefix: Option<GuidPrefix>,
+  instance_id: Option<String>,
+  passive: bool,
+  spdp_announcement_period: Duration,
+  discovery_backend: Box<dyn DiscoveryBackend>,
+  message_tap: Option<Arc<dyn MessageTap>>,
+  locator_selector: Option<Arc<dyn LocatorSelector>>,
+  max_receive_message_size: Option<usize>,
+  message_integrity: bool,
+
   #[cfg(feature = "security")]
   security_plugins: Option<SecurityPlugins>,
   #[cfg(feature = "security")]
@@ -61,6 +118,30 @@ impl DomainParticipantBuilder {
     DomainParticipantBuilder {
       domain_id,
       only_networks: None,
+      max_locators_per_list: DEFAULT_MAX_LOCATORS_PER_LIST,
+      max_cache_topics: DEFAULT_MAX_CACHE_TOPICS,
+      max_cache_bytes: None,
+      fragment_size: DEFAULT_FRAGMENT_SIZE,
+      message_limits: MessageLimits::default(),
+      heartbeat_response_delay: Duration::ZERO,
+      send_retry_delay: DEFAULT_SEND_RETRY_DELAY,
+      multicast_group: DEFAULT_MULTICAST_GROUP,
+      multicast_disabled: false,
+      lazy_user_traffic_listeners: false,
+      unicast_socket_reuse: SocketReuse::NONE,
+      multicast_socket_reuse: SocketReuse::ALL,
+      discard_untimestamped_data: false,
+      defer_on_lock_contention: false,
+      announced_locators: HashMap::new(),
+      guid_prefix: None,
+      instance_id: None,
+      passive: false,
+      spdp_announcement_period: Discovery::SEND_PARTICIPANT_INFO_PERIOD,
+      discovery_backend: Box::new(NoExtraPeers),
+      message_tap: None,
+      locator_selector: None,
+      max_receive_message_size: None,
+      message_integrity: false,
       #[cfg(feature = "security")]
       security_plugins: None,
       #[cfg(feature = "security")]
@@ -68,6 +149,344 @@ impl DomainParticipantBuilder {
     }
   }
 
+  /// Limits how many locators are accepted in each locator list (unicast,
+  /// multicast, metatraffic, ...) announced by a discovered participant or
+  /// its readers and writers. Locators beyond the limit are dropped with a
+  /// warning. The default is 16.
+  pub fn max_locators_per_list(&mut self, max: usize) -> &mut DomainParticipantBuilder {
+    self.max_locators_per_list = max;
+    self
+  }
+
+  /// Limits how many topics the DomainParticipant keeps a sample cache for.
+  /// When the limit is reached, writers on new topics announced by remote
+  /// participants are ignored with a warning. Topics of local DataReaders and
+  /// DataWriters are not limited, but they count towards the limit.
+  /// The default is 1024.
+  pub fn max_cache_topics(&mut self, max: usize) -> &mut DomainParticipantBuilder {
+    self.max_cache_topics = max;
+    self
+  }
+
+  /// Limits the total size of samples, in bytes, that the DomainParticipant
+  /// keeps in its sample cache over all topics. When the limit is exceeded,
+  /// the oldest samples of topics with VOLATILE durability are evicted,
+  /// preferring samples that have already been acknowledged to their
+  /// DataWriter. Samples of other topics are not evicted.
+  ///
+  /// DataReaders report evicted samples as
+  /// [`SampleLost`](crate::dds::statusevents::DataReaderStatus::SampleLost).
+  /// See also [`DomainParticipant::cache_evicted_sample_count`].
+  /// The default is no limit.
+  pub fn max_cache_bytes(&mut self, max: usize) -> &mut DomainParticipantBuilder {
+    self.max_cache_bytes = Some(max);
+    self
+  }
+
+  /// Sets the fragment size of DataWriters, in bytes. Samples whose
+  /// serialized size is larger than this are sent in fragments, using
+  /// DATA_FRAG submessages. The default is 1024. The size must be at least 1
+  /// and at most 60000, so that a fragment fits into a UDP datagram.
+  pub fn fragment_size(&mut self, fragment_size: usize) -> &mut DomainParticipantBuilder {
+    self.fragment_size = fragment_size;
+    self
+  }
+
+  /// Limits how many submessages DataWriters put into one RTPS message. A
+  /// message that would have more is split into several. Submessages such as
+  /// INFO_DST and INFO_TS are repeated in each part, so the limit must be at
+  /// least 3. The limit applies before any security encoding. The default is
+  /// no limit.
+  pub fn max_submessages_per_message(&mut self, max: usize) -> &mut DomainParticipantBuilder {
+    self.message_limits.max_submessages = Some(max);
+    self
+  }
+
+  /// Limits the size, in bytes, of RTPS messages that DataWriters send. A
+  /// message that would be larger is split into several. A single submessage
+  /// larger than the limit is still sent, in a message of its own, so the
+  /// limit should leave room for a full fragment (see
+  /// [`fragment_size`](DomainParticipantBuilder::fragment_size)). The limit
+  /// applies before any security encoding. The default is 64000 bytes, so
+  /// that every message fits into a UDP datagram. Larger values have no
+  /// effect. Messages are also kept within the maximum message size that
+  /// each remote RustDDS reader advertises. Both limits include the checksum
+  /// added by [`message_integrity_check`](DomainParticipantBuilder::message_integrity_check).
+  pub fn max_message_size(&mut self, max: usize) -> &mut DomainParticipantBuilder {
+    self.message_limits.max_size = Some(max);
+    self
+  }
+
+  /// Advertises to remote participants the size, in bytes, of the largest
+  /// RTPS message that this participant can receive, e.g. because of a small
+  /// receive buffer on the path. RustDDS Writers in remote participants then
+  /// fragment payloads so that the messages to this participant fit. Other
+  /// implementations ignore this. By default nothing is advertised, and
+  /// messages up to the UDP maximum are accepted.
+  ///
+  /// Likewise, Writers of this participant fragment payloads to fit the
+  /// limits advertised by remote RustDDS participants.
+  pub fn max_receive_message_size(&mut self, max: usize) -> &mut DomainParticipantBuilder {
+    self.max_receive_message_size = Some(max);
+    self
+  }
+
+  /// Sets the maximum delay of reliable DataReaders' ACKNACK responses to
+  /// HEARTBEATs. Each response is delayed by a random time between zero and
+  /// this, so that when many DataReaders receive the same multicast
+  /// HEARTBEAT, they do not all respond at once. If the missing samples are
+  /// received, e.g. as multicast repairs requested by another DataReader,
+  /// before the response is due, the redundant request is not sent.
+  ///
+  /// This is the `heartbeatResponseDelay` of the RTPS specification. The default
+  /// is zero, i.e. respond immediately, which is best when there is no
+  /// multicast. Something like 200 ms is suitable for multicast.
+  pub fn heartbeat_response_delay(&mut self, max_delay: Duration) -> &mut DomainParticipantBuilder {
+    self.heartbeat_response_delay = max_delay;
+    self
+  }
+
+  /// Sets how soon a DataWriter sends data again to readers, if sending
+  /// failed locally, e.g. because the socket buffer was full on a congested
+  /// host. The delay doubles on each retry, and the data is given up after
+  /// a few retries. See also [`DomainParticipant::udp_send_failure_count`]
+  /// and [`DomainParticipant::udp_send_drop_count`].
+  /// The default is 10 ms.
+  pub fn send_retry_delay(&mut self, delay: Duration) -> &mut DomainParticipantBuilder {
+    self.send_retry_delay = delay;
+    self
+  }
+
+  /// Sets the IPv4 multicast group that the DomainParticipant uses for both
+  /// discovery and user traffic. The group is announced in discovery as the
+  /// participant's multicast locators. Participants that use different groups
+  /// do not discover each other over multicast, but can still do so over
+  /// unicast, if configured with each other's addresses.
+  ///
+  /// The address must be in the multicast range 224.0.0.0/4. An
+  /// administratively scoped group, i.e. one in 239.0.0.0/8, is a good
+  /// choice. The default is 239.255.0.1, as specified by RTPS.
+  pub fn multicast_group(&mut self, multicast_group: Ipv4Addr) -> &mut DomainParticipantBuilder {
+    self.multicast_group = multicast_group;
+    self
+  }
+
+  /// Disables multicast altogether, for networks where it is blocked.
+  ///
+  /// No multicast listening sockets are opened, and no multicast locators are
+  /// announced, so SPDP reaches only the peers that are known otherwise,
+  /// e.g. configured with [`discovery_backend`](Self::discovery_backend).
+  /// The default is to use multicast.
+  pub fn disable_multicast(&mut self) -> &mut DomainParticipantBuilder {
+    self.multicast_disabled = true;
+    self
+  }
+
+  /// Defers opening the user traffic listening sockets until the first
+  /// DataReader or DataWriter is created. Until then, the DomainParticipant
+  /// only binds the discovery ports, and announces no user traffic locators.
+  /// This saves sockets and ports in participants that only do discovery,
+  /// e.g. for monitoring. The default is `false`, i.e. open at startup.
+  pub fn lazy_user_traffic_listeners(&mut self, lazy: bool) -> &mut DomainParticipantBuilder {
+    self.lazy_user_traffic_listeners = lazy;
+    self
+  }
+
+  /// Sets the address reuse options (`SO_REUSEADDR`, `SO_REUSEPORT`) of the
+  /// unicast discovery and user traffic listening sockets. See
+  /// [`SocketReuse`] for the tradeoffs.
+  ///
+  /// The default is [`SocketReuse::NONE`]. Enabling reuse may help binding
+  /// the well-known ports when a restarted process races with a lingering
+  /// one, but then several DomainParticipants on the host may select the
+  /// same ParticipantId and share its ports.
+  pub fn unicast_socket_reuse(&mut self, reuse: SocketReuse) -> &mut DomainParticipantBuilder {
+    self.unicast_socket_reuse = reuse;
+    self
+  }
+
+  /// Sets the address reuse options of the multicast discovery and user
+  /// traffic listening sockets. See [`SocketReuse`].
+  ///
+  /// The default is [`SocketReuse::ALL`], i.e. reuse the address, and the
+  /// port where the platform supports it. Without reuse, only one
+  /// DomainParticipant per host can receive the multicast traffic of a domain.
+  pub fn multicast_socket_reuse(&mut self, reuse: SocketReuse) -> &mut DomainParticipantBuilder {
+    self.multicast_socket_reuse = reuse;
+    self
+  }
+
+  /// Discards user DATA that is not preceded by a valid INFO_TS in its RTPS
+  /// message.
+  ///
+  /// RTPS requires INFO_TS to come before the DATA it applies to, so a DATA
+  /// before INFO_TS has no source timestamp. By default, such samples are
+  /// accepted with reception time only, and their source timestamp is `None`.
+  /// Set this to `true` to drop them instead. The default is `false`.
+  pub fn discard_untimestamped_data(&mut self, discard: bool) -> &mut DomainParticipantBuilder {
+    self.discard_untimestamped_data = discard;
+    self
+  }
+
+  /// Lets the background event loop go on with other work, if the sample
+  /// cache of a topic is locked by another thread, e.g. an application
+  /// thread reading a DataReader.
+  ///
+  /// Received samples are then stored in the cache a bit later, in the order
+  /// they were received. This reduces the worst-case latency of traffic on
+  /// other topics under lock contention. The default is `false`, i.e. the
+  /// event loop waits for the lock.
+  ///
+  /// Only the topic sample caches are covered. The event loop still waits
+  /// for other locks, such as the discovery database and the security
+  /// plugins.
+  pub fn defer_on_lock_contention(&mut self, defer: bool) -> &mut DomainParticipantBuilder {
+    self.defer_on_lock_contention = defer;
+    self
+  }
+
+  /// Sets the unicast addresses that the DomainParticipant announces in
+  /// discovery, instead of the addresses of its own network interfaces. This
+  /// is needed e.g. behind NAT, where remote participants cannot reach the
+  /// private addresses, but can reach a public address that is forwarded to
+  /// the participant's ports.
+  ///
+  /// `metatraffic` addresses are for discovery traffic, and `user_traffic`
+  /// addresses for DataReaders and DataWriters. An empty list means that the
+  /// interface addresses are announced, as by default. Multicast locators are
+  /// not affected.
+  pub fn announced_locators(
+    &mut self,
+    metatraffic: Vec<SocketAddr>,
+    user_traffic: Vec<SocketAddr>,
+  ) -> &mut DomainParticipantBuilder {
+    self.announced_locators.clear();
+    for (token, addresses) in [
+      (DISCOVERY_LISTENER_TOKEN, metatraffic),
+      (USER_TRAFFIC_LISTENER_TOKEN, user_traffic),
+    ] {
+      if !addresses.is_empty() {
+        self
+          .announced_locators
+          .insert(token, addresses.into_iter().map(Locator::from).collect());
+      }
+    }
+    self
+  }
+
+  /// Sets the GUID prefix of the DomainParticipant, instead of a random one.
+  /// This gives the participant a stable identity, e.g. across restarts.
+  ///
+  /// The prefix must be unique in the domain. Building fails if another
+  /// DomainParticipant in this process already has the same prefix, but
+  /// participants in other processes cannot be checked.
+  ///
+  /// With security, the builtin authentication plugin derives the actual
+  /// prefix from this one and the identity certificate, as required by the
+  /// DDS Security specification. The resulting prefix is then different from
+  /// the one given here, but still deterministic.
+  pub fn guid_prefix(&mut self, guid_prefix: GuidPrefix) -> &mut DomainParticipantBuilder {
+    self.guid_prefix = Some(guid_prefix);
+    self
+  }
+
+  /// Sets a human-readable identifier for the DomainParticipant, e.g. the name
+  /// of the application instance. It is not used in the protocol, but it is
+  /// shown in diagnostics: in the names of the background threads, in the log
+  /// message that announces the participant with its GUID, and in security
+  /// log events. This makes logs of many participants easier to correlate.
+  pub fn instance_id(&mut self, instance_id: &str) -> &mut DomainParticipantBuilder {
+    self.instance_id = Some(instance_id.to_string());
+    self
+  }
+
+  /// Makes the DomainParticipant passive, i.e. listen-only. A passive
+  /// participant does not announce itself, its DataReaders, or its
+  /// DataWriters in discovery, but it still receives the announcements of
+  /// other participants and builds its view of the domain from them, e.g.
+  /// for [`DomainParticipant::discovered_participants`].
+  ///
+  /// Remote participants do not know about a passive participant, so they do
+  /// not send it any user data, except what is sent to multicast. This is
+  /// mainly useful for monitoring tools. Passive mode cannot be combined with
+  /// security, because authentication requires sending messages.
+  /// The default is `false`.
+  pub fn passive(&mut self, passive: bool) -> &mut DomainParticipantBuilder {
+    self.passive = passive;
+    self
+  }
+
+  /// Sets how often the DomainParticipant announces itself in SPDP
+  /// discovery. Frequent announcements speed up discovery in dynamic
+  /// environments, but they are sent to multicast, so in large deployments
+  /// they add up. The announced lease duration is five periods.
+  ///
+  /// Periods shorter than 100 ms are raised to 100 ms to avoid flooding.
+  /// The default is 2 seconds.
+  pub fn spdp_announcement_period(&mut self, period: Duration) -> &mut DomainParticipantBuilder {
+    if period < Discovery::MIN_SEND_PARTICIPANT_INFO_PERIOD {
+      warn!(
+        "SPDP announcement period {:?} is too short. Using {:?}.",
+        period,
+        Discovery::MIN_SEND_PARTICIPANT_INFO_PERIOD
+      );
+    }
+    self.spdp_announcement_period = period.max(Discovery::MIN_SEND_PARTICIPANT_INFO_PERIOD);
+    self
+  }
+
+  /// Sets an additional source of remote participants, e.g. peers from a
+  /// static configuration. See [`DiscoveryBackend`]. SPDP discovery is always
+  /// active. The default is [`NoExtraPeers`], which adds nothing.
+  ///
+  /// When security is enabled, peers reported by the backend are silently
+  /// ignored. Secure participants are only discovered through SPDP.
+  pub fn discovery_backend(
+    &mut self,
+    backend: Box<dyn DiscoveryBackend>,
+  ) -> &mut DomainParticipantBuilder {
+    self.discovery_backend = backend;
+    self
+  }
+
+  /// Sets an observer that sees a copy of every RTPS message the
+  /// DomainParticipant sends or receives over UDP, e.g. for protocol
+  /// debugging. See [`MessageTap`]. There is no tap by default.
+  pub fn message_tap(&mut self, tap: Arc<dyn MessageTap>) -> &mut DomainParticipantBuilder {
+    self.message_tap = Some(tap);
+    self
+  }
+
+  /// Sets a hook that chooses the locators each message from a user-defined
+  /// writer or reader is actually sent to, e.g. for policy-based routing. See
+  /// [`LocatorSelector`]. By default, messages go to the locators advertised
+  /// by the remote endpoint.
+  pub fn locator_selector(
+    &mut self,
+    selector: Arc<dyn LocatorSelector>,
+  ) -> &mut DomainParticipantBuilder {
+    self.locator_selector = Some(selector);
+    self
+  }
+
+  /// Enables a lightweight integrity check of RTPS messages: A CRC-32C
+  /// checksum is appended to every sent message as a vendor-specific
+  /// submessage, and received messages without a valid checksum are
+  /// dropped. This detects corrupted messages on unreliable links, and works
+  /// without the `security` feature. It is not a substitute for DDS Security,
+  /// since a checksum does not stop deliberate tampering.
+  ///
+  /// All participants in the domain must enable this, or they cannot hear
+  /// each other: this participant drops everything without a checksum.
+  /// Participants that do not check ignore the extra submessage. Dropped
+  /// messages are counted in
+  /// [`DomainParticipant::integrity_check_failure_count`]. Disabled by
+  /// default.
+  pub fn message_integrity_check(&mut self, enable: bool) -> &mut DomainParticipantBuilder {
+    self.message_integrity = enable;
+    self
+  }
+
   #[cfg(feature = "security")]
   pub fn security(
     &mut self,
@@ -83,20 +502,106 @@ impl DomainParticipantBuilder {
 
   #[cfg(feature = "security")]
   pub fn add_builtin_security(&mut self) -> &mut DomainParticipantBuilder {
-    let security_test_configs = security::config::test_config();
+    self.builtin_security(security::config::test_config())
+  }
+
+  /// Enables DDS Security using the builtin plugins, configured from the
+  /// given files. The certificates and the private key are in PEM format,
+  /// governance and permissions documents are S/MIME-signed XML.
+  ///
+  /// The private key must not be encrypted, as decrypting it with a password
+  /// is not yet supported.
+  ///
+  /// The files are checked to be readable. Their contents are validated when
+  /// the DomainParticipant is built.
+  ///
+  /// # Examples
+  /// ```no_run
+  /// # use rustdds::DomainParticipantBuilder;
+  /// let mut builder = DomainParticipantBuilder::new(0);
+  /// builder
+  ///   .security_from_files(
+  ///     "identity_ca_certificate.pem",
+  ///     "participant1_certificate.pem",
+  ///     "participant1_private_key.pem",
+  ///     "permissions_ca_certificate.pem",
+  ///     "governance.p7s",
+  ///     "permissions.p7s",
+  ///   )
+  ///   .unwrap();
+  /// let domain_participant = builder.build().unwrap();
+  /// ```
+  #[cfg(feature = "security")]
+  pub fn security_from_files(
+    &mut self,
+    identity_ca: impl AsRef<Path>,
+    identity_certificate: impl AsRef<Path>,
+    private_key: impl AsRef<Path>,
+    permissions_ca: impl AsRef<Path>,
+    governance: impl AsRef<Path>,
+    permissions: impl AsRef<Path>,
+  ) -> CreateResult<&mut DomainParticipantBuilder> {
+    let config = security::config::config_from_files(&security::config::SecurityConfigFiles {
+      identity_ca: identity_ca.as_ref(),
+      identity_certificate: identity_certificate.as_ref(),
+      private_key: private_key.as_ref(),
+      permissions_ca: permissions_ca.as_ref(),
+      governance: governance.as_ref(),
+      permissions: permissions.as_ref(),
+    })
+    .or_else(|e| create_error_bad_parameter!("Security configuration: {:?}", e))?;
+    Ok(self.builtin_security(config))
+  }
 
-    if security_test_configs.security_enabled {
+  #[cfg(feature = "security")]
+  fn builtin_security(
+    &mut self,
+    config: security::config::SecurityConfig,
+  ) -> &mut DomainParticipantBuilder {
+    if config.security_enabled {
       let auth = Box::new(security::AuthenticationBuiltin::new());
       let access = Box::new(security::AccessControlBuiltin::new());
       let crypto = Box::new(security::CryptographicBuiltin::new());
-      self.security(auth, access, crypto, security_test_configs.properties);
+      self.security(auth, access, crypto, config.properties);
     }
     self
   }
 
   pub fn build(#[allow(unused_mut)] mut self) -> CreateResult<DomainParticipant> {
+    if !(1..=MAX_FRAGMENT_SIZE).contains(&self.fragment_size) {
+      return create_error_bad_parameter!(
+        "Fragment size must be between 1 and {}, not {}",
+        MAX_FRAGMENT_SIZE,
+        self.fragment_size
+      );
+    }
+    if let Some(max) = self.message_limits.max_submessages {
+      if max < 3 {
+        return create_error_bad_parameter!(
+          "Maximum submessages per message must be at least 3, not {}",
+          max
+        );
+      }
+    }
+    if !self.multicast_group.is_multicast() {
+      return create_error_bad_parameter!(
+        "{} is not an IPv4 multicast address",
+        self.multicast_group
+      );
+    }
+    #[cfg(feature = "security")]
+    if self.passive && self.security_plugins.is_some() {
+      return create_error_bad_parameter!("A passive DomainParticipant cannot use security");
+    }
+
     #[allow(unused_mut)] // only security feature mutates this
-    let mut participant_guid = GUID::new_participant_guid();
+    let mut participant_guid = match self.guid_prefix {
+      Some(prefix) => GUID::new(prefix, EntityId::PARTICIPANT),
+      None => GUID::new_participant_guid(),
+    };
+    if participant_guid.prefix == GuidPrefix::UNKNOWN {
+      return create_error_bad_parameter!("GUID prefix must not be GUIDPREFIX_UNKNOWN");
+    }
 
     // QosPolicies with possible security properties, otherwise default
     let participant_qos = QosPolicies {
@@ -211,6 +716,24 @@ impl DomainParticipantBuilder {
       discovery_command_sender,
       spdp_liveness_sender,
       security_plugins_handle.clone(),
+      ParticipantConfig {
+        fragment_size: self.fragment_size,
+        message_limits: self.message_limits,
+        heartbeat_response_delay: self.heartbeat_response_delay,
+        send_retry_delay: self.send_retry_delay,
+        multicast_group: (!self.multicast_disabled).then_some(self.multicast_group),
+        lazy_user_traffic_listeners: self.lazy_user_traffic_listeners,
+        unicast_socket_reuse: self.unicast_socket_reuse,
+        multicast_socket_reuse: self.multicast_socket_reuse,
+        discard_untimestamped_data: self.discard_untimestamped_data,
+        defer_on_lock_contention: self.defer_on_lock_contention,
+        announced_locators: self.announced_locators,
+        instance_id: self.instance_id.clone(),
+        message_tap: self.message_tap,
+        locator_selector: self.locator_selector,
+        max_receive_message_size: self.max_receive_message_size,
+        message_integrity: self.message_integrity,
+      },
     )?;
     let self_locators = dp.self_locators();
 
@@ -218,14 +741,30 @@ impl DomainParticipantBuilder {
     let dp = DomainParticipant {
       dpi: Arc::new(Mutex::new(dp)),
     };
+    discovery_db_write(&dp.discovery_db()).set_max_locators_per_list(self.max_locators_per_list);
+    dp.dds_cache()
+      .write()
+      .unwrap()
+      .set_max_topics(self.max_cache_topics);
+    dp.dds_cache()
+      .write()
+      .unwrap()
+      .set_max_bytes(self.max_cache_bytes);
 
     let (discovery_started_sender, discovery_started_receiver) = std::sync::mpsc::channel();
 
     // Construct and start background thread
     let dp_clone = dp.weak_clone();
     let disc_db_clone = dp.discovery_db();
+    let passive = self.passive;
+    let spdp_announcement_period = self.spdp_announcement_period;
+    let discovery_backend = self.discovery_backend;
+    let log_prefix = log_prefix(&self.instance_id);
     let discovery_handle = thread::Builder::new()
-      .name("RustDDS discovery thread".to_string())
+      .name(match self.instance_id {
+        Some(ref instance_id) => format!("RustDDS {instance_id} discovery thread"),
+        None => "RustDDS discovery thread".to_string(),
+      })
       .spawn(move || {
         if let Ok(mut discovery) = Discovery::new(
           dp_clone,
@@ -236,6 +775,9 @@ impl DomainParticipantBuilder {
           spdp_liveness_receiver,
           self_locators,
           security_plugins_handle,
+          passive,
+          spdp_announcement_period,
+          discovery_backend,
         ) {
           discovery.discovery_event_loop(); // run the event loop
         }
@@ -247,7 +789,7 @@ impl DomainParticipantBuilder {
     match discovery_started_receiver.recv_timeout(Duration::from_secs(10)) {
       Ok(Ok(())) => {
         // normal case
-        info!("Discovery started. Participant constructed.");
+        info!("{log_prefix}Discovery started. Participant constructed.");
         Ok(dp)
       }
       Ok(Err(e)) => {
@@ -298,6 +840,35 @@ impl DomainParticipant {
     dp_builder.build()
   }
 
+  /// Lists DomainParticipants that announce themselves on the network, without
+  /// creating a DomainParticipant.
+  ///
+  /// Listens to participant announcements (SPDP) of the domains in
+  /// `domain_ids` for `duration`, and returns the participants heard. The
+  /// announcements are listened to on the given IPv4 `interfaces`, or on all
+  /// local interfaces if the list is empty. Participants announce themselves
+  /// every few seconds, so `duration` should be at least that long.
+  ///
+  /// Only the default multicast group is listened to.
+  ///
+  /// # Examples
+  ///
+  /// ```no_run
+  /// # use std::time::Duration;
+  /// # use rustdds::DomainParticipant;
+  ///
+  /// for found in DomainParticipant::scan(0..10, &[], Duration::from_secs(3)).unwrap() {
+  ///   println!("domain {}: {:?}", found.domain_id, found.participant.guid);
+  /// }
+  /// ```
+  pub fn scan(
+    domain_ids: std::ops::Range<u16>,
+    interfaces: &[Ipv4Addr],
+    duration: Duration,
+  ) -> CreateResult<Vec<ScannedParticipant>> {
+    participant_scan::scan(domain_ids, interfaces, duration)
+  }
+
   /// Creates DDS Publisher
   ///
   /// # Arguments
@@ -375,8 +946,61 @@ impl DomainParticipant {
   }
 
   pub fn find_topic(&self, name: &str, timeout: Duration) -> CreateResult<Option<Topic>> {
-    let w = self.weak_clone();
-    self.dpi.lock()?.find_topic(&w, name, timeout)
+    match self.wait_for_discovery(timeout, |db| db.get_topic(name).cloned())? {
+      Some(d) => {
+        let w = self.weak_clone();
+        self.dpi.lock()?.topic_from_discovered(&w, &d).map(Some)
+      }
+      None => Ok(None),
+    }
+  }
+
+  /// Blocks until a remote DataWriter on the named topic is discovered, or
+  /// the timeout expires. Returns immediately if such a writer is already
+  /// known. This can be used to wait for a publisher before creating a
+  /// DataReader.
+  ///
+  /// Returns `Ok(None)` on timeout.
+  pub fn wait_for_writer(
+    &self,
+    topic_name: &str,
+    timeout: Duration,
+  ) -> CreateResult<Option<DiscoveredWriterData>> {
+    self.wait_for_discovery(timeout, |db| {
+      db.remote_topic_writers()
+        .find(|w| w.publication_topic_data.topic_name == topic_name)
+        .cloned()
+    })
+  }
+
+  // Repeats `check` whenever the DiscoveryDB reports an update, until it
+  // finds something or the timeout expires. Participant locks must not be held
+  // while waiting, because Discovery needs them to make progress.
+  fn wait_for_discovery<T>(
+    &self,
+    timeout: Duration,
+    mut check: impl FnMut(&DiscoveryDB) -> Option<T>,
+  ) -> CreateResult<Option<T>> {
+    let (discovery_db, waiter) = {
+      let dpi = self.dpi.lock()?;
+      let inner = dpi.dpi.lock()?;
+      (
+        inner.discovery_db.clone(),
+        inner.discovery_db_waiter.clone(),
+      )
+    };
+
+    let find_end = Instant::now() + timeout;
+    loop {
+      if let Some(found) = check(&*discovery_db.read()?) {
+        return Ok(Some(found));
+      }
+      let now = Instant::now();
+      if now >= find_end {
+        return Ok(None);
+      }
+      waiter.lock()?.wait(find_end - now)?;
+    }
   }
 
   /// # Examples
@@ -403,6 +1027,16 @@ impl DomainParticipant {
     self.dpi.lock().unwrap().participant_id()
   }
 
+  /// The identifier set with [`DomainParticipantBuilder::instance_id`], if
+  /// any.
+  pub fn instance_id(&self) -> Option<String> {
+    self.dpi.lock().unwrap().instance_id()
+  }
+
+  pub(crate) fn max_receive_message_size(&self) -> Option<usize> {
+    self.dpi.lock().unwrap().max_receive_message_size()
+  }
+
   /// Gets all DiscoveredTopics from DDS network
   ///
   /// # Examples
@@ -420,36 +1054,115 @@ impl DomainParticipant {
     self.dpi.lock().unwrap().discovered_topics()
   }
 
-  /// Manually asserts liveliness, affecting all writers with
-  /// LIVELINESS QoS of MANUAL_BY_PARTICIPANT created by
-  /// this particular participant.
+  /// Gets all remote DomainParticipants currently known via Discovery
   ///
-  /// # Example
+  /// # Examples
   ///
   /// ```
   /// # use rustdds::DomainParticipant;
   ///
-  /// let domain_participant = DomainParticipant::new(0).expect("Failed to create participant");
-  /// domain_participant.assert_liveliness();
+  /// let domain_participant = DomainParticipant::new(0).unwrap();
+  /// for participant in domain_participant.discovered_participants() {
+  ///   println!("{:?} vendor={:?}", participant.guid, participant.vendor_id);
+  /// }
   /// ```
-  pub fn assert_liveliness(self) -> WriteResult<(), ()> {
-    self.dpi.lock()?.assert_liveliness()
+  pub fn discovered_participants(&self) -> Vec<DiscoveredParticipantInfo> {
+    self.dpi.lock().unwrap().discovered_participants()
   }
 
-  pub(crate) fn weak_clone(&self) -> DomainParticipantWeak {
-    DomainParticipantWeak::new(self)
+  /// Gets all remote DataReaders currently known via Discovery
+  ///
+  /// Each entry is a snapshot of what the remote reader announced: its GUID,
+  /// topic and type names, and requested QoS. Comparing the QoS with that of a
+  /// local DataWriter helps to find out why they do not match.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// # use rustdds::DomainParticipant;
+  ///
+  /// let domain_participant = DomainParticipant::new(0).unwrap();
+  /// for reader in domain_participant.discovered_readers() {
+  ///   let data = &reader.subscription_topic_data;
+  ///   println!("{} {:?}", data.topic_name(), data.qos().reliability());
+  /// }
+  /// ```
+  pub fn discovered_readers(&self) -> Vec<DiscoveredReaderData> {
+    self.dpi.lock().unwrap().discovered_readers()
   }
 
-  pub(crate) fn dds_cache(&self) -> Arc<RwLock<DDSCache>> {
-    self.dpi.lock().unwrap().dds_cache()
+  /// Gets all remote DataWriters currently known via Discovery
+  ///
+  /// Each entry is a snapshot of what the remote writer announced: its GUID,
+  /// topic and type names, and offered QoS. Comparing the QoS with that of a
+  /// local DataReader helps to find out why they do not match.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// # use rustdds::DomainParticipant;
+  ///
+  /// let domain_participant = DomainParticipant::new(0).unwrap();
+  /// for writer in domain_participant.discovered_writers() {
+  ///   let data = &writer.publication_topic_data;
+  ///   println!("{} {:?}", data.topic_name, data.qos().reliability());
+  /// }
+  /// ```
+  pub fn discovered_writers(&self) -> Vec<DiscoveredWriterData> {
+    self.dpi.lock().unwrap().discovered_writers()
   }
 
-  #[cfg(feature = "security")] // just to avoid warning
-  pub(crate) fn qos(&self) -> QosPolicies {
-    self.dpi.lock().unwrap().qos()
+  /// Subscribes to changes in what Discovery knows about remote
+  /// DomainParticipants, DataReaders, DataWriters, and topics.
+  ///
+  /// Only changes after the call are reported. Each call creates a new,
+  /// independent receiver. Discovery never waits for the application: if
+  /// the receiver is not read, the oldest events are dropped.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// # use rustdds::DomainParticipant;
+  /// use rustdds::discovery::DiscoveryEvent;
+  ///
+  /// let domain_participant = DomainParticipant::new(0).unwrap();
+  /// let events = domain_participant.discovery_events();
+  /// while let Some(event) = events.try_recv() {
+  ///   if let DiscoveryEvent::WriterDiscovered { writer } = event {
+  ///     println!("New writer on {}", writer.publication_topic_data.topic_name);
+  ///   }
+  /// }
+  /// ```
+  pub fn discovery_events(&self) -> DiscoveryEventReceiver {
+    self.dpi.lock().unwrap().discovery_events()
   }
 
-  pub(crate) fn discovery_db(&self) -> Arc<RwLock<DiscoveryDB>> {
+  /// Number of topics that this DomainParticipant currently keeps a sample
+  /// cache for. This includes topics of local DataReaders and DataWriters, and
+  /// topics of remote DataWriters. See
+  /// [`DomainParticipantBuilder::max_cache_topics`].
+  pub fn cache_topic_count(&self) -> usize {
+    self.dds_cache().read().unwrap().topic_count()
+  }
+
+  /// Reports the number of samples and their approximate size, as the sum of
+  /// serialized payload lengths, in the sample cache of each topic. This is
+  /// meant for capacity planning, e.g. choosing
+  /// [`DomainParticipantBuilder::max_cache_bytes`].
+  pub fn cache_usage(&self) -> Vec<TopicCacheUsage> {
+    self.dds_cache().read().unwrap().cache_usage()
+  }
+
+  /// Number of samples evicted from the sample cache, because it exceeded
+  /// [`DomainParticipantBuilder::max_cache_bytes`].
+  pub fn cache_evicted_sample_count(&self) -> u64 {
+    self.dds_cache().read().unwrap().evicted_sample_count()
+  }
+
+  /// Number of UDP sends that have failed, e.g. because the socket buffer
+  /// was full. DataWriters send such data again, see
+  /// [`DomainParticipantBuilder::send_retry_delay`].
+  pub fn udp_send_failure_count(&self) -> u64 {
     self
       .dpi
       .lock()
@@ -457,36 +1170,318 @@ impl DomainParticipant {
       .dpi
       .lock()
       .unwrap()
-      .discovery_db
-      .clone()
-  }
-
-  pub(crate) fn new_entity_id(&self, entity_kind: EntityKind) -> EntityId {
-    self.dpi.lock().unwrap().new_entity_id(entity_kind)
+      .udp_send_failure_count()
   }
 
-  pub(crate) fn self_locators(&self) -> HashMap<Token, Vec<Locator>> {
-    self.dpi.lock().unwrap().self_locators()
+  /// Number of messages that DataWriters gave up sending, because sending
+  /// them failed even after a few retries. Reliable DataWriters still repair
+  /// such data when the reader requests it.
+  pub fn udp_send_drop_count(&self) -> u64 {
+    self
+      .dpi
+      .lock()
+      .unwrap()
+      .dpi
+      .lock()
+      .unwrap()
+      .udp_send_drop_count()
   }
-} // end impl DomainParticipant
 
-impl PartialEq for DomainParticipant {
-  fn eq(&self, other: &Self) -> bool {
-    self.guid() == other.guid()
-      && self.domain_id() == other.domain_id()
-      && self.participant_id() == other.participant_id()
+  /// Number of received RTPS messages that were dropped, because they did
+  /// not have a valid integrity checksum. See
+  /// [`DomainParticipantBuilder::message_integrity_check`].
+  pub fn integrity_check_failure_count(&self) -> u64 {
+    self
+      .dpi
+      .lock()
+      .unwrap()
+      .dpi
+      .lock()
+      .unwrap()
+      .integrity_check_failure_count()
   }
-}
 
-#[derive(Clone)]
-pub struct DomainParticipantWeak {
-  dpi: Weak<Mutex<DomainParticipantDisc>>,
+  /// Renders statistics of this DomainParticipant in the Prometheus text
+  /// exposition format, for serving from the application's own HTTP
+  /// endpoint.
+  ///
+  /// Included are the RTPS messages and bytes sent and received, failure
+  /// counts, retransmissions and sample cache sizes by topic, known
+  /// endpoints and possible matches by topic and endpoint kind, and the
+  /// authentication states of remote participants, if security is enabled.
+  /// All metric names start with `rustdds_`. If an
+  /// [`instance_id`](DomainParticipantBuilder::instance_id) is set, every
+  /// sample carries it as the `instance_id` label.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// # use rustdds::DomainParticipant;
+  /// let domain_participant = DomainParticipant::new(0).unwrap();
+  /// let metrics = domain_participant.metrics_text();
+  /// assert!(metrics.contains("rustdds_messages_sent_total"));
+  /// ```
+  pub fn metrics_text(&self) -> String {
+    self.dpi.lock().unwrap().dpi.lock().unwrap().metrics_text()
+  }
+
+  /// Gets a handle for feeding raw RTPS packets into the receive path of this
+  /// participant, as if they had arrived from the network. This is for
+  /// testing, e.g. interoperability or fuzz tests, without a remote
+  /// participant. See the [`test_util`](crate::test_util) module.
+  #[cfg(feature = "test-util")]
+  pub fn packet_injector(&self) -> PacketInjector {
+    self
+      .dpi
+      .lock()
+      .unwrap()
+      .dpi
+      .lock()
+      .unwrap()
+      .packet_injector()
+  }
+
+  /// Manually asserts liveliness, affecting all writers with
+  /// LIVELINESS QoS of MANUAL_BY_PARTICIPANT created by
+  /// this particular participant.
+  ///
+  /// # Example
+  ///
+  /// ```
+  /// # use rustdds::DomainParticipant;
+  ///
+  /// let domain_participant = DomainParticipant::new(0).expect("Failed to create participant");
+  /// domain_participant.assert_liveliness().unwrap();
+  /// ```
+  pub fn assert_liveliness(&self) -> WriteResult<(), ()> {
+    self.dpi.lock()?.assert_liveliness()
+  }
+
+  /// Ignores a remote DomainParticipant, e.g. a noisy or untrusted peer.
+  ///
+  /// Its Readers and Writers are unmatched from ours, and nothing it
+  /// announces is matched later. This cannot be undone during the lifetime of
+  /// this DomainParticipant.
+  pub fn ignore_participant(&self, guid_prefix: GuidPrefix) -> CreateResult<()> {
+    if guid_prefix == self.guid().prefix {
+      return Err(CreateError::BadParameter {
+        reason: "A DomainParticipant cannot ignore itself.".to_string(),
+      });
+    }
+    self
+      .dpi
+      .lock()?
+      .send_discovery_command(DiscoveryCommand::IgnoreParticipant { guid_prefix })
+  }
+
+  /// Ignores remote Readers and Writers on the named Topic. They are unmatched
+  /// from ours, and not matched later. This cannot be undone during the
+  /// lifetime of this DomainParticipant.
+  pub fn ignore_topic(&self, topic_name: &str) -> CreateResult<()> {
+    self
+      .dpi
+      .lock()?
+      .send_discovery_command(DiscoveryCommand::IgnoreTopic {
+        topic_name: topic_name.to_string(),
+      })
+  }
+
+  /// Announces all local Topics, Readers and Writers again over SEDP.
+  ///
+  /// They are re-announced periodically anyway, but this lets remote
+  /// participants that missed the announcements, e.g. due to a network
+  /// change, match our endpoints without waiting for the next round.
+  pub fn refresh_endpoints(&self) -> CreateResult<()> {
+    self
+      .dpi
+      .lock()?
+      .send_discovery_command(DiscoveryCommand::RefreshEndpoints)
+  }
+
+  /// Deletes all Publishers, Subscribers, DataReaders and DataWriters created
+  /// in this participant. The DataReaders and DataWriters are removed from
+  /// the event loop, unregistered from the cryptographic plugin, if security
+  /// is enabled, and unannounced to remote participants. This allows an
+  /// orderly teardown without waiting for the application to drop every
+  /// entity.
+  ///
+  /// The handles of the deleted entities can still be dropped normally, but
+  /// they no longer work: e.g. writing to a deleted DataWriter, or creating a
+  /// DataWriter in a deleted Publisher, returns an error. Topics stay usable,
+  /// and new Publishers and Subscribers can be created afterwards.
+  ///
+  /// The built-in Discovery entities are not deleted.
+  pub fn delete_contained_entities(&self) -> CreateResult<()> {
+    // The participant must not stay locked, because Publishers and Subscribers
+    // lock it while creating entities.
+    let (publishers, subscribers) = self.dpi.lock()?.take_publishers_and_subscribers()?;
+    for publisher in publishers {
+      for guid in publisher.delete()? {
+        self
+          .dpi
+          .lock()?
+          .send_discovery_command(DiscoveryCommand::RemoveLocalWriter { guid })?;
+      }
+    }
+    for subscriber in subscribers {
+      for guid in subscriber.delete()? {
+        self
+          .dpi
+          .lock()?
+          .send_discovery_command(DiscoveryCommand::RemoveLocalReader { guid })?;
+      }
+    }
+    Ok(())
+  }
+
+  /// Reloads the Certificate Revocation List (CRL) of the identity CA.
+  ///
+  /// The CRL is read again from the location given in the `dds.sec.auth.crl`
+  /// property of the security configuration. Remote participants, whose
+  /// identity certificate is listed in the reloaded CRL, are rejected in
+  /// subsequent authentication handshakes. This way revocations take effect
+  /// without restarting the participant.
+  ///
+  /// Returns an error, if security is not enabled for this participant, or
+  /// the CRL cannot be read or verified. In the latter case the previously
+  /// loaded CRL stays in effect.
+  #[cfg(feature = "security")]
+  pub fn reload_certificate_revocation_list(&self) -> CreateResult<()> {
+    self.dpi.lock()?.reload_certificate_revocation_list()
+  }
+
+  /// Re-reads the governance and permissions documents given in the security
+  /// configuration, e.g. the files given to
+  /// [`DomainParticipantBuilder::security_from_files`]. This allows rotating
+  /// permissions without restarting the application.
+  ///
+  /// Local DataReaders and DataWriters, which the new documents no longer
+  /// permit, are removed: they are unannounced and no longer match or
+  /// communicate with remote endpoints. Dropping them is still up to the
+  /// application.
+  ///
+  /// Returns an error, if security is not enabled for this participant, or
+  /// the documents cannot be read or verified. In the latter case the
+  /// previously loaded documents stay in effect.
+  #[cfg(feature = "security")]
+  pub fn reload_permissions(&self) -> CreateResult<()> {
+    self.dpi.lock()?.reload_permissions()
+  }
+
+  /// Lists the remote participants, with which an authentication handshake
+  /// is in progress, and the state of each handshake. Completed handshakes
+  /// are not listed.
+  ///
+  /// The list is empty, if security is not enabled for this participant.
+  #[cfg(feature = "security")]
+  pub fn pending_handshakes(&self) -> Vec<(GuidPrefix, DiscHandshakeState)> {
+    self.dpi.lock().unwrap().pending_handshakes()
+  }
+
+  /// Cancels an in-progress authentication handshake with a remote
+  /// participant, e.g. one that does not respond.
+  ///
+  /// The stored handshake messages are discarded, so they are no longer
+  /// resent, and the remote participant is marked as rejected. A new
+  /// handshake is not started with it, unless it leaves and is discovered
+  /// again.
+  ///
+  /// Returns an error, if there is no handshake in progress with the remote
+  /// participant. See [`pending_handshakes`](Self::pending_handshakes).
+  #[cfg(feature = "security")]
+  pub fn abort_handshake(&self, guid_prefix: GuidPrefix) -> CreateResult<()> {
+    self.dpi.lock()?.abort_handshake(guid_prefix)
+  }
+
+  /// Gets a fingerprint of the key material shared with a remote
+  /// participant, for audit and debugging of secure sessions. The
+  /// fingerprint is a SHA-256 hash of the shared secret and the handshake
+  /// challenges, so it does not reveal the secret. Both participants compute
+  /// the same fingerprint, if they have derived the same key material.
+  ///
+  /// Returns an error, if security is not enabled for this participant, or
+  /// authentication with the remote participant has not completed.
+  #[cfg(feature = "security")]
+  pub fn shared_secret_fingerprint(
+    &self,
+    remote_participant: GuidPrefix,
+  ) -> CreateResult<[u8; 32]> {
+    self
+      .dpi
+      .lock()?
+      .shared_secret_fingerprint(remote_participant)
+  }
+
+  pub(crate) fn weak_clone(&self) -> DomainParticipantWeak {
+    DomainParticipantWeak::new(self)
+  }
+
+  pub(crate) fn dds_cache(&self) -> Arc<RwLock<DDSCache>> {
+    self.dpi.lock().unwrap().dds_cache()
+  }
+
+  pub(crate) fn fragment_size(&self) -> usize {
+    self.dpi.lock().unwrap().fragment_size()
+  }
+
+  pub(crate) fn message_limits(&self) -> MessageLimits {
+    self.dpi.lock().unwrap().message_limits()
+  }
+
+  pub(crate) fn heartbeat_response_delay(&self) -> Duration {
+    self.dpi.lock().unwrap().heartbeat_response_delay()
+  }
+
+  #[cfg(feature = "security")] // just to avoid warning
+  pub(crate) fn qos(&self) -> QosPolicies {
+    self.dpi.lock().unwrap().qos()
+  }
+
+  pub(crate) fn discovery_db(&self) -> Arc<RwLock<DiscoveryDB>> {
+    self
+      .dpi
+      .lock()
+      .unwrap()
+      .dpi
+      .lock()
+      .unwrap()
+      .discovery_db
+      .clone()
+  }
+
+  pub(crate) fn new_entity_id(&self, entity_kind: EntityKind) -> EntityId {
+    self.dpi.lock().unwrap().new_entity_id(entity_kind)
+  }
+
+  pub(crate) fn self_locators(&self) -> HashMap<Token, Vec<Locator>> {
+    self.dpi.lock().unwrap().self_locators()
+  }
+
+  // Called when a user DataReader or DataWriter is created
+  pub(crate) fn open_user_traffic_listeners(&self) -> CreateResult<()> {
+    self.dpi.lock()?.open_user_traffic_listeners()
+  }
+} // end impl DomainParticipant
+
+impl PartialEq for DomainParticipant {
+  fn eq(&self, other: &Self) -> bool {
+    self.guid() == other.guid()
+      && self.domain_id() == other.domain_id()
+      && self.participant_id() == other.participant_id()
+  }
+}
+
+#[derive(Clone)]
+pub struct DomainParticipantWeak {
+  dpi: Weak<Mutex<DomainParticipantDisc>>,
   // This struct caches some items to avoid construction deadlocks
   #[cfg(feature = "security")] // just to avoid warning
   domain_id: u16,
   guid: GUID,
   #[cfg(feature = "security")] // just to avoid warning
   qos: QosPolicies,
+  #[cfg(feature = "security")] // just to avoid warning
+  instance_id: Option<String>,
 }
 
 impl DomainParticipantWeak {
@@ -498,6 +1493,8 @@ impl DomainParticipantWeak {
       guid: dp.guid(),
       #[cfg(feature="security")] // just to avoid warning
       qos: dp.qos(),
+      #[cfg(feature="security")] // just to avoid warning
+      instance_id: dp.instance_id(),
     }
   }
 
@@ -511,6 +1508,19 @@ impl DomainParticipantWeak {
       .and_then(|dpi| dpi.lock()?.create_publisher(self, qos))
   }
 
+  // Publisher that DomainParticipant::delete_contained_entities() does not
+  // touch. Used for the builtin security logging DataWriter.
+  #[cfg(feature = "security")]
+  pub(crate) fn create_builtin_publisher(&self, qos: &QosPolicies) -> CreateResult<Publisher> {
+    self
+      .dpi
+      .upgrade()
+      .ok_or(CreateError::ResourceDropped {
+        reason: "DomainParticipant".to_string(),
+      })
+      .and_then(|dpi| dpi.lock()?.create_builtin_publisher(self, qos))
+  }
+
   pub fn create_subscriber(&self, qos: &QosPolicies) -> CreateResult<Subscriber> {
     self
       .dpi
@@ -531,6 +1541,11 @@ impl DomainParticipantWeak {
     self.qos.clone()
   }
 
+  #[cfg(feature = "security")] // just to avoid warning
+  pub fn instance_id(&self) -> Option<String> {
+    self.instance_id.clone()
+  }
+
   pub fn create_topic(
     &self,
     name: String,
@@ -609,6 +1624,26 @@ pub(crate) struct DomainParticipantDisc {
   entity_id_generator: atomic::AtomicU32,
 }
 
+// Settings from DomainParticipantBuilder for the inner participant layers
+pub(crate) struct ParticipantConfig {
+  pub fragment_size: usize,
+  pub message_limits: MessageLimits,
+  pub heartbeat_response_delay: Duration,
+  pub send_retry_delay: Duration,
+  pub multicast_group: Option<Ipv4Addr>,
+  pub lazy_user_traffic_listeners: bool,
+  pub unicast_socket_reuse: SocketReuse,
+  pub multicast_socket_reuse: SocketReuse,
+  pub discard_untimestamped_data: bool,
+  pub defer_on_lock_contention: bool,
+  pub announced_locators: HashMap<Token, Vec<Locator>>,
+  pub instance_id: Option<String>,
+  pub message_tap: Option<Arc<dyn MessageTap>>,
+  pub locator_selector: Option<Arc<dyn LocatorSelector>>,
+  pub max_receive_message_size: Option<usize>,
+  pub message_integrity: bool,
+}
+
 impl DomainParticipantDisc {
   #[allow(clippy::too_many_arguments)]
   pub fn new(
@@ -620,6 +1655,7 @@ impl DomainParticipantDisc {
     discovery_command_sender: mio_channel::SyncSender<DiscoveryCommand>,
     spdp_liveness_sender: mio_channel::SyncSender<GuidPrefix>,
     security_plugins_handle: Option<SecurityPluginsHandle>,
+    config: ParticipantConfig,
   ) -> CreateResult<Self> {
     let dpi = DomainParticipantInner::new(
       domain_id,
@@ -629,6 +1665,7 @@ impl DomainParticipantDisc {
       discovery_command_sender.clone(),
       spdp_liveness_sender,
       security_plugins_handle,
+      config,
     )?;
 
     Ok(Self {
@@ -671,6 +1708,20 @@ impl DomainParticipantDisc {
       .create_subscriber(dp, qos, self.discovery_command_sender.clone())
   }
 
+  #[cfg(feature = "security")]
+  pub fn create_builtin_publisher(
+    &self,
+    dp: &DomainParticipantWeak,
+    qos: &QosPolicies,
+  ) -> CreateResult<Publisher> {
+    Ok(
+      self
+        .dpi
+        .lock()?
+        .create_builtin_publisher(dp, qos, self.discovery_command_sender.clone()),
+    )
+  }
+
   pub fn create_topic(
     &self,
     dp: &DomainParticipantWeak,
@@ -686,13 +1737,12 @@ impl DomainParticipantDisc {
       .create_topic(dp, name, type_desc, qos, topic_kind)
   }
 
-  pub fn find_topic(
+  pub fn topic_from_discovered(
     &self,
     dp: &DomainParticipantWeak,
-    name: &str,
-    timeout: Duration,
-  ) -> CreateResult<Option<Topic>> {
-    self.dpi.lock()?.find_topic(dp, name, timeout)
+    d: &DiscoveredTopicData,
+  ) -> CreateResult<Topic> {
+    self.dpi.lock()?.topic_from_discovered(dp, d)
   }
 
   pub fn domain_id(&self) -> u16 {
@@ -703,14 +1753,50 @@ impl DomainParticipantDisc {
     self.dpi.lock().unwrap().participant_id()
   }
 
+  pub fn instance_id(&self) -> Option<String> {
+    self.dpi.lock().unwrap().instance_id()
+  }
+
+  pub fn max_receive_message_size(&self) -> Option<usize> {
+    self.dpi.lock().unwrap().max_receive_message_size()
+  }
+
   pub fn discovered_topics(&self) -> Vec<DiscoveredTopicData> {
     self.dpi.lock().unwrap().discovered_topics()
   }
 
+  pub fn discovered_participants(&self) -> Vec<DiscoveredParticipantInfo> {
+    self.dpi.lock().unwrap().discovered_participants()
+  }
+
+  pub fn discovered_readers(&self) -> Vec<DiscoveredReaderData> {
+    self.dpi.lock().unwrap().discovered_readers()
+  }
+
+  pub fn discovered_writers(&self) -> Vec<DiscoveredWriterData> {
+    self.dpi.lock().unwrap().discovered_writers()
+  }
+
+  pub fn discovery_events(&self) -> DiscoveryEventReceiver {
+    self.dpi.lock().unwrap().discovery_events()
+  }
+
   pub(crate) fn dds_cache(&self) -> Arc<RwLock<DDSCache>> {
     self.dpi.lock().unwrap().dds_cache()
   }
 
+  pub(crate) fn fragment_size(&self) -> usize {
+    self.dpi.lock().unwrap().fragment_size()
+  }
+
+  pub(crate) fn message_limits(&self) -> MessageLimits {
+    self.dpi.lock().unwrap().message_limits()
+  }
+
+  pub(crate) fn heartbeat_response_delay(&self) -> Duration {
+    self.dpi.lock().unwrap().heartbeat_response_delay()
+  }
+
   #[cfg(feature = "security")] // just to avoid warning
   pub(crate) fn qos(&self) -> QosPolicies {
     self.dpi.lock().unwrap().qos()
@@ -720,6 +1806,57 @@ impl DomainParticipantDisc {
   //   self.dpi.lock().unwrap().discovery_db.clone()
   // }
 
+  pub fn take_publishers_and_subscribers(&self) -> CreateResult<(Vec<Publisher>, Vec<Subscriber>)> {
+    Ok(self.dpi.lock()?.take_publishers_and_subscribers())
+  }
+
+  #[cfg(feature = "security")]
+  pub fn reload_certificate_revocation_list(&self) -> CreateResult<()> {
+    self.dpi.lock()?.reload_certificate_revocation_list()
+  }
+
+  #[cfg(feature = "security")]
+  pub fn reload_permissions(&self) -> CreateResult<()> {
+    let (removed_readers, removed_writers) = self.dpi.lock()?.reload_permissions()?;
+    for guid in removed_readers {
+      self.send_discovery_command(DiscoveryCommand::RemoveLocalReader { guid })?;
+    }
+    for guid in removed_writers {
+      self.send_discovery_command(DiscoveryCommand::RemoveLocalWriter { guid })?;
+    }
+    Ok(())
+  }
+
+  #[cfg(feature = "security")]
+  pub fn shared_secret_fingerprint(
+    &self,
+    remote_participant: GuidPrefix,
+  ) -> CreateResult<[u8; 32]> {
+    self
+      .dpi
+      .lock()?
+      .shared_secret_fingerprint(remote_participant)
+  }
+
+  #[cfg(feature = "security")]
+  pub fn pending_handshakes(&self) -> Vec<(GuidPrefix, DiscHandshakeState)> {
+    self.dpi.lock().unwrap().pending_handshakes()
+  }
+
+  #[cfg(feature = "security")]
+  pub fn abort_handshake(&self, guid_prefix: GuidPrefix) -> CreateResult<()> {
+    let pending = self
+      .pending_handshakes()
+      .iter()
+      .any(|(prefix, _state)| *prefix == guid_prefix);
+    if !pending {
+      return create_error_bad_parameter!(
+        "No handshake in progress with remote participant {guid_prefix:?}"
+      );
+    }
+    self.send_discovery_command(DiscoveryCommand::AbortHandshake { guid_prefix })
+  }
+
   pub(crate) fn assert_liveliness(&self) -> WriteResult<(), ()> {
     // No point in checking for the LIVELINESS QoS of MANUAL_BY_PARTICIPANT,
     // the discovery command mutates a field which is only read
@@ -731,25 +1868,61 @@ impl DomainParticipantDisc {
       .map_err(|_e| WriteError::WouldBlock { data: () })
   }
 
+  pub(crate) fn send_discovery_command(&self, command: DiscoveryCommand) -> CreateResult<()> {
+    self
+      .discovery_command_sender
+      .send(command)
+      .map_err(|e| CreateError::ResourceDropped {
+        reason: format!("Discovery is not running: {e:?}"),
+      })
+  }
+
   pub(crate) fn self_locators(&self) -> HashMap<Token, Vec<Locator>> {
     self.dpi.lock().unwrap().self_locators.clone()
   }
+
+  pub(crate) fn open_user_traffic_listeners(&self) -> CreateResult<()> {
+    if self.dpi.lock()?.open_user_traffic_listeners()? {
+      // Let Discovery announce the new locators
+      self
+        .discovery_command_sender
+        .try_send(DiscoveryCommand::SelfLocatorsChanged)
+        .unwrap_or_else(|e| warn!("Cannot notify Discovery of new locators: {e:?}"));
+    }
+    Ok(())
+  }
 }
 
 impl Drop for DomainParticipantDisc {
   fn drop(&mut self) {
-    info!("===== RustDDS shutting down ===== .drop() DomainParticipantDisc");
+    let log_prefix = log_prefix(&self.dpi.lock().unwrap().instance_id);
+    info!("{log_prefix}===== RustDDS shutting down ===== .drop() DomainParticipantDisc");
 
     debug!("Wan dp_event_loop about stop.");
+    let (drained_sender, drained_receiver) = std::sync::mpsc::channel();
     if self
       .dpi
       .lock()
       .unwrap()
       .stop_poll_sender
-      .send(EventLoopCommand::PrepareStop)
+      .send(EventLoopCommand::PrepareStop {
+        drained: drained_sender,
+      })
       .is_err()
     {
-      error!("dp_event_loop not responding to prepare stop discovery_command");
+      error!("{log_prefix}dp_event_loop not responding to prepare stop discovery_command");
+    } else {
+      // Give reliable DataWriters a chance to deliver what they have written,
+      // before Discovery tells remote participants that they are gone. The
+      // event loop gives up after PREPARE_STOP_DRAIN_TIMEOUT, so the extra wait
+      // here only covers an event loop that is slow to respond.
+      debug!("Waiting for reliable writers to drain.");
+      if drained_receiver
+        .recv_timeout(2 * PREPARE_STOP_DRAIN_TIMEOUT)
+        .is_err()
+      {
+        warn!("{log_prefix}dp_event_loop did not confirm draining of reliable writers.");
+      }
     }
 
     debug!("Sending Discovery Stop signal.");
@@ -758,76 +1931,270 @@ impl Drop for DomainParticipantDisc {
       .send(DiscoveryCommand::StopDiscovery)
       .is_err()
     {
-      warn!("Failed to send stop signal to Discovery");
+      warn!("{log_prefix}Failed to send stop signal to Discovery");
       return;
     }
 
     debug!("Waiting for Discovery join.");
     if let Ok(handle) = self.discovery_join_handle.try_recv() {
-      handle.join().unwrap();
+      handle
+        .join()
+        .unwrap_or_else(|e| warn!("{log_prefix}Failed to join Discovery: {e:?}"));
       debug!("Joined Discovery.");
     }
   }
 }
 
-// This is the actual working DomainParticipant.
-pub(crate) struct DomainParticipantInner {
+// Start of participant-scoped log lines, so that the logs of several
+// participants in one process can be told apart.
+fn log_prefix(instance_id: &Option<String>) -> String {
+  match instance_id {
+    Some(instance_id) => format!("[{instance_id}] "),
+    None => String::new(),
+  }
+}
+
+// Opens the unicast and multicast listeners for user traffic
+fn open_user_traffic_listeners(
   domain_id: u16,
   participant_id: u16,
+  multicast_group: Option<Ipv4Addr>,
+  unicast_socket_reuse: SocketReuse,
+  multicast_socket_reuse: SocketReuse,
+) -> CreateResult<HashMap<Token, UDPListener>> {
+  let mut listeners = HashMap::new();
+
+  if let Some(multicast_group) = multicast_group {
+    match UDPListener::new_multicast_with_reuse(
+      "0.0.0.0",
+      user_traffic_multicast_port(domain_id),
+      multicast_group,
+      multicast_socket_reuse,
+    ) {
+      Ok(l) => {
+        listeners.insert(USER_TRAFFIC_MUL_LISTENER_TOKEN, l);
+      }
+      Err(e) => warn!("Cannot get multicast user traffic listener: {e:?}"),
+    }
+  }
 
-  my_guid: GUID,
-  #[cfg(feature = "security")] // just to avoid warning
-  my_qos_policies: QosPolicies,
-
-  // Adding Readers
-  sender_add_reader: mio_channel::SyncSender<ReaderIngredients>,
-  sender_remove_reader: mio_channel::SyncSender<GUID>,
+  let user_traffic_listener = UDPListener::new_unicast_with_reuse(
+    "0.0.0.0",
+    user_traffic_unicast_port(domain_id, participant_id),
+    unicast_socket_reuse,
+  )
+  .or_else(|e| {
+    if matches!(e.kind(), ErrorKind::AddrInUse) {
+      // If we do not get the preferred listening port,
+      // try again, with "any" port number.
+      UDPListener::new_unicast_with_reuse("0.0.0.0", 0, unicast_socket_reuse).or_else(|e| {
+        create_error_out_of_resources!(
+          "Could not open unicast user traffic listener, any port number: {:?}",
+          e
+        )
+      })
+    } else {
+      create_error_out_of_resources!("Could not open unicast user traffic listener: {e:?}")
+    }
+  })?;
 
-  // dp_event_loop control
-  stop_poll_sender: mio_channel::Sender<EventLoopCommand>,
-  ev_loop_handle: Option<JoinHandle<()>>, // this is Option, because it needs to be extracted
-  // out of the struct (take) in order to .join() on the handle.
+  listeners.insert(USER_TRAFFIC_LISTENER_TOKEN, user_traffic_listener);
 
-  // Writers
-  add_writer_sender: mio_channel::SyncSender<WriterIngredients>,
-  remove_writer_sender: mio_channel::SyncSender<GUID>,
+  Ok(listeners)
+}
 
-  dds_cache: Arc<RwLock<DDSCache>>,
+// Locators of the listeners. Announced locators, if any, take the place of
+// those of the listener with the same token.
+fn listener_locators(
+  listeners: &HashMap<Token, UDPListener>,
+  announced_locators: &HashMap<Token, Vec<Locator>>,
+) -> HashMap<Token, Vec<Locator>> {
+  listeners
+    .iter()
+    .map(|(t, l)| {
+      if let Some(locs) = announced_locators.get(t) {
+        return (*t, locs.clone());
+      }
+      match l.to_locator_address() {
+        Ok(locs) => (*t, locs),
+        Err(e) => {
+          error!("No local network address for token {:?}: {:?}", t, e);
+          (*t, vec![])
+        }
+      }
+    })
+    .collect()
+}
+
+// GUID prefixes of the DomainParticipants of this process
+static PARTICIPANT_GUID_PREFIXES: Mutex<BTreeSet<GuidPrefix>> = Mutex::new(BTreeSet::new());
+
+// Reserves a GUID prefix for a DomainParticipant as long as this is alive, so
+// that two participants of the process cannot have the same prefix.
+struct GuidPrefixReservation {
+  prefix: GuidPrefix,
+}
+
+impl GuidPrefixReservation {
+  fn new(prefix: GuidPrefix) -> CreateResult<Self> {
+    if PARTICIPANT_GUID_PREFIXES.lock()?.insert(prefix) {
+      Ok(Self { prefix })
+    } else {
+      create_error_bad_parameter!(
+        "GUID prefix {:?} is already used by another DomainParticipant",
+        prefix
+      )
+    }
+  }
+}
+
+impl Drop for GuidPrefixReservation {
+  fn drop(&mut self) {
+    // Ignore poisoning: the set is valid anyway.
+    let mut prefixes = match PARTICIPANT_GUID_PREFIXES.lock() {
+      Ok(guard) => guard,
+      Err(poisoned) => poisoned.into_inner(),
+    };
+    prefixes.remove(&self.prefix);
+  }
+}
+
+// Lets find_topic() and wait_for_writer() sleep until the DiscoveryDB reports
+// an update. The event receiver can be registered only once, so its Poll is
+// kept alongside.
+struct DiscoveryDbWaiter {
+  receiver: mio_channel::Receiver<()>,
+  poll: mio_06::Poll,
+}
+
+impl DiscoveryDbWaiter {
+  // Concurrent waiters share the waiter, so no-one holds it for long.
+  const MAX_WAIT: Duration = Duration::from_millis(100);
+
+  fn new(receiver: mio_channel::Receiver<()>) -> CreateResult<Self> {
+    let poll = mio_06::Poll::new()?;
+    // Level trigger, so that an update between check and wait is not missed.
+    poll.register(
+      &receiver,
+      Token(0),
+      mio_06::Ready::readable(),
+      mio_06::PollOpt::level(),
+    )?;
+    Ok(Self { receiver, poll })
+  }
+
+  fn wait(&self, timeout: Duration) -> CreateResult<()> {
+    let mut events = mio_06::Events::with_capacity(1);
+    self
+      .poll
+      .poll(&mut events, Some(timeout.min(Self::MAX_WAIT)))?;
+    while self.receiver.try_recv().is_ok() {}
+    Ok(())
+  }
+}
+
+// This is the actual working DomainParticipant.
+pub(crate) struct DomainParticipantInner {
+  domain_id: u16,
+  participant_id: u16,
+  instance_id: Option<String>,
+  max_receive_message_size: Option<usize>,
+
+  my_guid: GUID,
+  _guid_prefix_reservation: GuidPrefixReservation,
+  #[cfg(feature = "security")] // just to avoid warning
+  my_qos_policies: QosPolicies,
+
+  // Adding Readers
+  sender_add_reader: mio_channel::SyncSender<ReaderIngredients>,
+  sender_remove_reader: mio_channel::SyncSender<GUID>,
+
+  // dp_event_loop control
+  stop_poll_sender: mio_channel::Sender<EventLoopCommand>,
+  ev_loop_handle: Option<JoinHandle<()>>, // this is Option, because it needs to be extracted
+  // out of the struct (take) in order to .join() on the handle.
+
+  // Writers
+  add_writer_sender: mio_channel::SyncSender<WriterIngredients>,
+  remove_writer_sender: mio_channel::SyncSender<GUID>,
+
+  dds_cache: Arc<RwLock<DDSCache>>,
   discovery_db: Arc<RwLock<DiscoveryDB>>,
-  discovery_db_event_receiver: mio_channel::Receiver<()>,
+  discovery_db_waiter: Arc<Mutex<DiscoveryDbWaiter>>,
 
   // RTPS locators describing how to reach this DP
   self_locators: HashMap<Token, Vec<Locator>>,
+  // None if multicast is disabled
+  multicast_group: Option<Ipv4Addr>,
+  // False if the user traffic listeners are still to be opened on demand
+  user_traffic_listeners_open: bool,
+  unicast_socket_reuse: SocketReuse,
+  multicast_socket_reuse: SocketReuse,
+  // Configured locators that replace those of the listeners in self_locators
+  announced_locators: HashMap<Token, Vec<Locator>>,
+  add_listener_sender: mio_channel::SyncSender<(Token, UDPListener)>,
+  // Packets for the event loop to handle as received, see test_util
+  #[cfg(feature = "test-util")]
+  inject_packet_sender: mio_channel::SyncSender<InjectedPacket>,
+  // Fragment size for new Writers
+  fragment_size: usize,
+  // RTPS message size limits for new Writers
+  message_limits: MessageLimits,
+  // Maximum ACKNACK response delay for new Readers
+  heartbeat_response_delay: Duration,
+  // Number of failed UDP sends, counted by the event loop
+  send_failure_count: Arc<AtomicU64>,
+  // Number of messages Writers gave up sending, counted by the event loop
+  send_drop_count: Arc<AtomicU64>,
+  // Number of received messages failing the integrity check, counted by the
+  // event loop
+  integrity_failure_count: Arc<AtomicU64>,
+  // Sent and received messages and bytes, counted by the event loop
+  traffic_counters: Arc<TrafficCounters>,
   #[allow(dead_code)] // TODO: use or remove
   security_plugins_handle: Option<SecurityPluginsHandle>,
+  // Created Publishers and Subscribers, for delete_contained_entities
+  publishers: Vec<PublisherWeak>,
+  subscribers: Vec<SubscriberWeak>,
 }
 
 impl Drop for DomainParticipantInner {
   fn drop(&mut self) {
-    // if send has an error simply leave as we have lost control of the
-    // ev_loop_thread anyways
+    let log_prefix = log_prefix(&self.instance_id);
+    // if send has an error, we have lost control of the ev_loop_thread anyways
     if self.stop_poll_sender.send(EventLoopCommand::Stop).is_err() {
-      error!("dp_event_loop not responding to stop discovery_command");
-      return;
+      error!("{log_prefix}dp_event_loop not responding to stop discovery_command");
+    } else {
+      debug!("Waiting for dp_event_loop join");
+      match self.ev_loop_handle.take() {
+        Some(join_handle) => {
+          join_handle
+            .join()
+            .unwrap_or_else(|e| warn!("{log_prefix}Failed to join dp_event_loop: {e:?}"));
+        }
+        None => {
+          error!(
+            "{log_prefix}Someone managed to steal dp_event_loop join handle from \
+             DomainParticipantInner."
+          );
+        }
+      }
+      debug!("Joined dp_event_loop");
     }
 
-    debug!("Waiting for dp_event_loop join");
-    match self.ev_loop_handle.take() {
-      Some(join_handle) => {
-        join_handle
-          .join()
-          .unwrap_or_else(|e| warn!("Failed to join dp_event_loop: {e:?}"));
-      }
-      None => {
-        error!("Someone managed to steal dp_event_loop join handle from DomainParticipantInner.");
-      }
+    // The event loop releases the crypto state before closing its sockets.
+    // In case it did not stop cleanly, release the state here. Otherwise this
+    // does nothing.
+    #[cfg(feature = "security")]
+    if let Some(security_plugins_handle) = self.security_plugins_handle.as_ref() {
+      security_plugins_handle.unregister_all_participants();
     }
-    debug!("Joined dp_event_loop");
   }
 }
 
 #[allow(clippy::new_without_default)]
 impl DomainParticipantInner {
+  #[allow(clippy::too_many_arguments)]
   fn new(
     domain_id: u16,
     participant_guid: GUID,
@@ -836,21 +2203,46 @@ impl DomainParticipantInner {
     discovery_command_sender: mio_channel::SyncSender<DiscoveryCommand>,
     spdp_liveness_sender: mio_channel::SyncSender<GuidPrefix>,
     security_plugins_handle: Option<SecurityPluginsHandle>,
+    config: ParticipantConfig,
   ) -> CreateResult<Self> {
     #[cfg(not(feature = "security"))]
     let _dummy = _qos_policies; // to make clippy happy
 
+    let ParticipantConfig {
+      fragment_size,
+      message_limits,
+      heartbeat_response_delay,
+      send_retry_delay,
+      multicast_group,
+      lazy_user_traffic_listeners,
+      unicast_socket_reuse,
+      multicast_socket_reuse,
+      discard_untimestamped_data,
+      defer_on_lock_contention,
+      announced_locators,
+      instance_id,
+      message_tap,
+      locator_selector,
+      max_receive_message_size,
+      message_integrity,
+    } = config;
+
+    let guid_prefix_reservation = GuidPrefixReservation::new(participant_guid.prefix)?;
+
     let mut listeners = HashMap::new();
 
-    match UDPListener::new_multicast(
-      "0.0.0.0",
-      spdp_well_known_multicast_port(domain_id),
-      Ipv4Addr::new(239, 255, 0, 1),
-    ) {
-      Ok(l) => {
-        listeners.insert(DISCOVERY_MUL_LISTENER_TOKEN, l);
+    if let Some(multicast_group) = multicast_group {
+      match UDPListener::new_multicast_with_reuse(
+        "0.0.0.0",
+        spdp_well_known_multicast_port(domain_id),
+        multicast_group,
+        multicast_socket_reuse,
+      ) {
+        Ok(l) => {
+          listeners.insert(DISCOVERY_MUL_LISTENER_TOKEN, l);
+        }
+        Err(e) => warn!("Cannot get multicast discovery listener: {e:?}"),
       }
-      Err(e) => warn!("Cannot get multicast discovery listener: {e:?}"),
     }
 
     let mut participant_id = 0;
@@ -860,9 +2252,10 @@ impl DomainParticipantInner {
     // Magic value 120 below is from RTPS spec 2.5 Section "9.6.2.3 Default Port
     // Numbers"
     while discovery_listener.is_none() && participant_id < 120 {
-      discovery_listener = UDPListener::new_unicast(
+      discovery_listener = UDPListener::new_unicast_with_reuse(
         "0.0.0.0",
         spdp_well_known_unicast_port(domain_id, participant_id),
+        unicast_socket_reuse,
       )
       .ok();
       if discovery_listener.is_none() {
@@ -870,7 +2263,11 @@ impl DomainParticipantInner {
       }
     }
 
-    info!("ParticipantId {} selected.", participant_id);
+    info!(
+      "{}ParticipantId {} selected.",
+      log_prefix(&instance_id),
+      participant_id
+    );
 
     // here discovery_listener is redefined (shadowed)
     let discovery_listener = match discovery_listener {
@@ -879,51 +2276,19 @@ impl DomainParticipantInner {
     };
     listeners.insert(DISCOVERY_LISTENER_TOKEN, discovery_listener);
 
-    // Now the user traffic listeners
-
-    match UDPListener::new_multicast(
-      "0.0.0.0",
-      user_traffic_multicast_port(domain_id),
-      Ipv4Addr::new(239, 255, 0, 1),
-    ) {
-      Ok(l) => {
-        listeners.insert(USER_TRAFFIC_MUL_LISTENER_TOKEN, l);
-      }
-      Err(e) => warn!("Cannot get multicast user traffic listener: {e:?}"),
+    // Now the user traffic listeners, unless they are opened on demand
+    if !lazy_user_traffic_listeners {
+      listeners.extend(open_user_traffic_listeners(
+        domain_id,
+        participant_id,
+        multicast_group,
+        unicast_socket_reuse,
+        multicast_socket_reuse,
+      )?);
     }
 
-    let user_traffic_listener = UDPListener::new_unicast(
-      "0.0.0.0",
-      user_traffic_unicast_port(domain_id, participant_id),
-    )
-    .or_else(|e| {
-      if matches!(e.kind(), ErrorKind::AddrInUse) {
-        // If we do not get the preferred listening port,
-        // try again, with "any" port number.
-        UDPListener::new_unicast("0.0.0.0", 0).or_else(|e| {
-          create_error_out_of_resources!(
-            "Could not open unicast user traffic listener, any port number: {:?}",
-            e
-          )
-        })
-      } else {
-        create_error_out_of_resources!("Could not open unicast user traffic listener: {e:?}")
-      }
-    })?;
-
-    listeners.insert(USER_TRAFFIC_LISTENER_TOKEN, user_traffic_listener);
-
     // construct our own Locators
-    let self_locators: HashMap<Token, Vec<Locator>> = listeners
-      .iter()
-      .map(|(t, l)| match l.to_locator_address() {
-        Ok(locs) => (*t, locs),
-        Err(e) => {
-          error!("No local network address for token {:?}: {:?}", t, e);
-          (*t, vec![])
-        }
-      })
-      .collect();
+    let self_locators = listener_locators(&listeners, &announced_locators);
 
     // Adding readers
     let (sender_add_reader, receiver_add_reader) =
@@ -935,10 +2300,24 @@ impl DomainParticipantInner {
       mio_channel::sync_channel::<WriterIngredients>(10);
     let (remove_writer_sender, remove_writer_receiver) = mio_channel::sync_channel::<GUID>(4);
 
+    let send_failure_count = Arc::new(AtomicU64::new(0));
+    let send_drop_count = Arc::new(AtomicU64::new(0));
+    let integrity_failure_count = Arc::new(AtomicU64::new(0));
+    let traffic_counters = Arc::new(TrafficCounters::default());
     let domain_info = DomainInfo {
       domain_participant_guid: participant_guid,
       domain_id,
       participant_id,
+      discard_untimestamped_data,
+      defer_on_lock_contention,
+      send_retry_delay,
+      send_failure_count: send_failure_count.clone(),
+      send_drop_count: send_drop_count.clone(),
+      message_tap,
+      locator_selector,
+      message_integrity,
+      integrity_failure_count: integrity_failure_count.clone(),
+      traffic_counters: traffic_counters.clone(),
     };
 
     let dds_cache = Arc::new(RwLock::new(DDSCache::new()));
@@ -949,15 +2328,26 @@ impl DomainParticipantInner {
       participant_guid,
       discovery_db_event_sender,
     )));
+    let discovery_db_waiter = Arc::new(Mutex::new(DiscoveryDbWaiter::new(
+      discovery_db_event_receiver,
+    )?));
 
     let (stop_poll_sender, stop_poll_receiver) = mio_channel::channel();
+    let (add_listener_sender, add_listener_receiver) =
+      mio_channel::sync_channel::<(Token, UDPListener)>(2);
+    #[cfg(feature = "test-util")]
+    let (inject_packet_sender, inject_packet_receiver) =
+      mio_channel::sync_channel::<InjectedPacket>(64);
 
     // Launch the background thread for DomainParticipant
     let dds_cache_clone = dds_cache.clone();
     let disc_db_clone = discovery_db.clone();
     let security_plugins_clone = security_plugins_handle.clone();
     let ev_loop_handle = thread::Builder::new()
-      .name(format!("RustDDS Participant {} event loop", participant_id))
+      .name(match instance_id {
+        Some(ref instance_id) => format!("RustDDS {instance_id} event loop"),
+        None => format!("RustDDS Participant {} event loop", participant_id),
+      })
       .spawn(move || {
         let dp_event_loop = DPEventLoop::new(
           domain_info,
@@ -982,25 +2372,36 @@ impl DomainParticipantInner {
             receiver: remove_writer_receiver,
           },
           stop_poll_receiver,
+          add_listener_receiver,
           discovery_update_notification_receiver,
           discovery_command_sender,
           spdp_liveness_sender,
           security_plugins_clone,
         );
+        #[cfg(feature = "test-util")]
+        let dp_event_loop = dp_event_loop.with_packet_injection(inject_packet_receiver);
         dp_event_loop.event_loop();
       })?;
 
     info!(
-      "New DomainParticipantInner: domain_id={:?} participant_id={:?} GUID={:?} security={}",
-      domain_id, participant_id, participant_guid, cfg!(security)
+      "New DomainParticipantInner: domain_id={:?} participant_id={:?} GUID={:?} \
+       instance_id={:?} security={}",
+      domain_id,
+      participant_id,
+      participant_guid,
+      instance_id,
+      cfg!(security)
     );
 
     Ok(Self {
       domain_id,
       participant_id,
+      instance_id,
+      max_receive_message_size,
       #[cfg(feature = "security")]
       my_qos_policies: _qos_policies,
       my_guid: participant_guid,
+      _guid_prefix_reservation: guid_prefix_reservation,
       sender_add_reader,
       sender_remove_reader,
       stop_poll_sender,
@@ -1009,33 +2410,445 @@ impl DomainParticipantInner {
       remove_writer_sender,
       dds_cache,
       discovery_db,
-      discovery_db_event_receiver,
+      discovery_db_waiter,
       self_locators,
+      multicast_group,
+      user_traffic_listeners_open: !lazy_user_traffic_listeners,
+      unicast_socket_reuse,
+      multicast_socket_reuse,
+      announced_locators,
+      add_listener_sender,
+      #[cfg(feature = "test-util")]
+      inject_packet_sender,
+      fragment_size,
+      message_limits,
+      heartbeat_response_delay,
+      send_failure_count,
+      send_drop_count,
+      integrity_failure_count,
+      traffic_counters,
       security_plugins_handle,
+      publishers: Vec::new(),
+      subscribers: Vec::new(),
     })
   }
 
+  // Opens the user traffic listeners, if they were not opened at startup, and
+  // hands them over to the event loop. Returns true if they were opened now.
+  pub(crate) fn open_user_traffic_listeners(&mut self) -> CreateResult<bool> {
+    if self.user_traffic_listeners_open {
+      return Ok(false);
+    }
+    let listeners = open_user_traffic_listeners(
+      self.domain_id,
+      self.participant_id,
+      self.multicast_group,
+      self.unicast_socket_reuse,
+      self.multicast_socket_reuse,
+    )?;
+    self
+      .self_locators
+      .extend(listener_locators(&listeners, &self.announced_locators));
+    for token_listener in listeners {
+      self
+        .add_listener_sender
+        .send(token_listener)
+        .or_else(|e| create_error_poisoned!("Cannot add a listener to the event loop: {}", e))?;
+    }
+    self.user_traffic_listeners_open = true;
+    Ok(true)
+  }
+
   pub fn dds_cache(&self) -> Arc<RwLock<DDSCache>> {
     self.dds_cache.clone()
   }
 
+  pub fn fragment_size(&self) -> usize {
+    self.fragment_size
+  }
+
+  pub fn message_limits(&self) -> MessageLimits {
+    self.message_limits
+  }
+
+  pub fn heartbeat_response_delay(&self) -> Duration {
+    self.heartbeat_response_delay
+  }
+
+  pub fn udp_send_failure_count(&self) -> u64 {
+    self.send_failure_count.load(atomic::Ordering::Relaxed)
+  }
+
+  pub fn udp_send_drop_count(&self) -> u64 {
+    self.send_drop_count.load(atomic::Ordering::Relaxed)
+  }
+
+  pub fn integrity_check_failure_count(&self) -> u64 {
+    self.integrity_failure_count.load(atomic::Ordering::Relaxed)
+  }
+
+  #[cfg(feature = "test-util")]
+  pub fn packet_injector(&self) -> PacketInjector {
+    PacketInjector::new(self.inject_packet_sender.clone())
+  }
+
+  pub fn metrics_text(&self) -> String {
+    let mut text = PrometheusText::new();
+    if let Some(ref instance_id) = self.instance_id {
+      text.add_common_label("instance_id", instance_id);
+    }
+
+    let counters = &self.traffic_counters;
+    text.single(
+      "messages_sent_total",
+      MetricType::Counter,
+      "RTPS messages sent.",
+      counters.messages_sent(),
+    );
+    text.single(
+      "bytes_sent_total",
+      MetricType::Counter,
+      "Bytes of RTPS messages sent.",
+      counters.bytes_sent(),
+    );
+    text.single(
+      "messages_received_total",
+      MetricType::Counter,
+      "RTPS messages received.",
+      counters.messages_received(),
+    );
+    text.single(
+      "bytes_received_total",
+      MetricType::Counter,
+      "Bytes of RTPS messages received.",
+      counters.bytes_received(),
+    );
+    text.single(
+      "udp_send_failures_total",
+      MetricType::Counter,
+      "UDP sends that have failed.",
+      self.udp_send_failure_count(),
+    );
+    text.single(
+      "udp_send_drops_total",
+      MetricType::Counter,
+      "Messages that DataWriters gave up sending after retrying.",
+      self.udp_send_drop_count(),
+    );
+    text.single(
+      "integrity_check_failures_total",
+      MetricType::Counter,
+      "Received messages dropped for failing the integrity check.",
+      self.integrity_check_failure_count(),
+    );
+    text.family(
+      "retransmitted_samples_total",
+      MetricType::Counter,
+      "Samples sent again by reliable DataWriters on request of a DataReader.",
+    );
+    for (topic_name, count) in counters.retransmissions() {
+      text.sample(
+        "retransmitted_samples_total",
+        &[("topic", &topic_name), ("kind", "writer")],
+        count,
+      );
+    }
+
+    {
+      let db = self
+        .discovery_db
+        .read()
+        .unwrap_or_else(|e| panic!("DiscoveryDB is poisoned. {e:?}"));
+      text.single(
+        "discovered_participants",
+        MetricType::Gauge,
+        "Remote DomainParticipants currently known.",
+        db.remote_participants().count() as u64,
+      );
+
+      // Endpoint counts by topic, in the order of ENDPOINT_KINDS
+      const ENDPOINT_KINDS: [&str; 4] = [
+        "local_reader",
+        "local_writer",
+        "remote_reader",
+        "remote_writer",
+      ];
+      let mut endpoints: BTreeMap<String, [u64; 4]> = BTreeMap::new();
+      let mut add = |topic_name: &String, index: usize| {
+        endpoints.entry(topic_name.clone()).or_default()[index] += 1;
+      };
+      for reader in db.get_all_local_topic_readers() {
+        add(reader.subscription_topic_data.topic_name(), 0);
+      }
+      for writer in db.get_all_local_topic_writers() {
+        add(&writer.publication_topic_data.topic_name, 1);
+      }
+      for reader in db.remote_topic_readers() {
+        add(reader.subscription_topic_data.topic_name(), 2);
+      }
+      for writer in db.remote_topic_writers() {
+        add(&writer.publication_topic_data.topic_name, 3);
+      }
+
+      text.family(
+        "endpoints",
+        MetricType::Gauge,
+        "DataReaders and DataWriters currently known, local and remote.",
+      );
+      for (topic_name, counts) in &endpoints {
+        for (kind, count) in ENDPOINT_KINDS.into_iter().zip(counts) {
+          text.sample(
+            "endpoints",
+            &[("topic", topic_name), ("kind", kind)],
+            *count,
+          );
+        }
+      }
+      text.family(
+        "matches",
+        MetricType::Gauge,
+        "Pairs of a local endpoint and a remote endpoint of the opposite kind on the same \
+         topic. QoS compatibility is not considered.",
+      );
+      for (topic_name, [readers, writers, remote_readers, remote_writers]) in &endpoints {
+        text.sample(
+          "matches",
+          &[("topic", topic_name), ("kind", "reader")],
+          readers * remote_writers,
+        );
+        text.sample(
+          "matches",
+          &[("topic", topic_name), ("kind", "writer")],
+          writers * remote_readers,
+        );
+      }
+
+      #[cfg(feature = "security")]
+      if self.security_plugins_handle.is_some() {
+        let mut statuses = [0_u64; 4];
+        for status in db.authentication_statuses() {
+          let index = match status {
+            AuthenticationStatus::Authenticated => 0,
+            AuthenticationStatus::Authenticating => 1,
+            AuthenticationStatus::Unauthenticated => 2,
+            AuthenticationStatus::Rejected => 3,
+          };
+          statuses[index] += 1;
+        }
+        text.family(
+          "participant_authentication_states",
+          MetricType::Gauge,
+          "Remote DomainParticipants by authentication state.",
+        );
+        for (state, count) in [
+          "authenticated",
+          "authenticating",
+          "unauthenticated",
+          "rejected",
+        ]
+        .into_iter()
+        .zip(statuses)
+        {
+          text.sample(
+            "participant_authentication_states",
+            &[("state", state)],
+            count,
+          );
+        }
+        text.single(
+          "pending_handshakes",
+          MetricType::Gauge,
+          "Authentication handshakes in progress.",
+          db.pending_handshakes().len() as u64,
+        );
+      }
+    } // release DiscoveryDB
+
+    let cache = self.dds_cache.read().unwrap();
+    let cache_usage = cache.cache_usage();
+    text.family(
+      "cache_samples",
+      MetricType::Gauge,
+      "Samples in the topic cache.",
+    );
+    for usage in &cache_usage {
+      text.sample(
+        "cache_samples",
+        &[("topic", &usage.topic_name)],
+        usage.sample_count as u64,
+      );
+    }
+    text.family(
+      "cache_bytes",
+      MetricType::Gauge,
+      "Payload bytes of the samples in the topic cache.",
+    );
+    for usage in &cache_usage {
+      text.sample(
+        "cache_bytes",
+        &[("topic", &usage.topic_name)],
+        usage.payload_bytes as u64,
+      );
+    }
+    text.single(
+      "cache_evicted_samples_total",
+      MetricType::Counter,
+      "Samples evicted from the topic cache to keep within its size limit.",
+      cache.evicted_sample_count(),
+    );
+
+    text.into_string()
+  }
+
   #[cfg(feature = "security")] // just to avoid warning
   pub(crate) fn qos(&self) -> QosPolicies {
     self.my_qos_policies.clone()
   }
 
+  #[cfg(feature = "security")]
+  pub fn reload_certificate_revocation_list(&self) -> CreateResult<()> {
+    match self.security_plugins_handle {
+      Some(ref security_plugins_handle) => Ok(
+        security_plugins_handle
+          .get_plugins()
+          .reload_certificate_revocation_list()?,
+      ),
+      None => create_error_bad_parameter!("Security is not enabled for this DomainParticipant"),
+    }
+  }
+
+  // Reloads the permissions and removes the local DataReaders and DataWriters
+  // that are no longer permitted from the event loop. Returns the GUIDs of the
+  // removed DataReaders and DataWriters, so that Discovery can unannounce them.
+  #[cfg(feature = "security")]
+  pub fn reload_permissions(&self) -> CreateResult<(Vec<GUID>, Vec<GUID>)> {
+    let Some(ref security_plugins_handle) = self.security_plugins_handle else {
+      return create_error_bad_parameter!("Security is not enabled for this DomainParticipant");
+    };
+    let mut security_plugins = security_plugins_handle.get_plugins();
+    security_plugins.reload_local_permissions(
+      self.domain_id,
+      self.my_guid.prefix,
+      &self.my_qos_policies,
+    )?;
+
+    let (removed_readers, removed_writers) = {
+      let db = discovery_db_read(&self.discovery_db);
+      // Builtin endpoints are not subject to access control
+      let removed_readers: Vec<GUID> = db
+        .get_all_local_topic_readers()
+        .filter(|reader| {
+          reader
+            .reader_proxy
+            .remote_reader_guid
+            .entity_id
+            .entity_kind
+            .is_user_defined()
+        })
+        .map(|reader| &reader.subscription_topic_data)
+        .filter(|topic_data| {
+          security_plugins
+            .check_create_datareader(
+              self.domain_id,
+              self.my_guid.prefix,
+              topic_data.topic_name().clone(),
+              &topic_data.qos(),
+            )
+            .is_err()
+        })
+        .map(|topic_data| topic_data.key())
+        .collect();
+      let removed_writers: Vec<GUID> = db
+        .get_all_local_topic_writers()
+        .filter(|writer| {
+          writer
+            .writer_proxy
+            .remote_writer_guid
+            .entity_id
+            .entity_kind
+            .is_user_defined()
+        })
+        .map(|writer| &writer.publication_topic_data)
+        .filter(|topic_data| {
+          security_plugins
+            .check_create_datawriter(
+              self.domain_id,
+              self.my_guid.prefix,
+              topic_data.topic_name.clone(),
+              &topic_data.qos(),
+            )
+            .is_err()
+        })
+        .map(|topic_data| topic_data.key)
+        .collect();
+      (removed_readers, removed_writers)
+    };
+    drop(security_plugins);
+
+    // Same as dropping the DataReader or DataWriter
+    for guid in &removed_readers {
+      info!(
+        "{}DataReader {guid:?} is no longer permitted. Removing it.",
+        log_prefix(&self.instance_id)
+      );
+      if let Err(e) = self.sender_remove_reader.send(*guid) {
+        return create_error_dropped!("Cannot remove DataReader {guid:?}: {e:?}");
+      }
+    }
+    for guid in &removed_writers {
+      info!(
+        "{}DataWriter {guid:?} is no longer permitted. Removing it.",
+        log_prefix(&self.instance_id)
+      );
+      if let Err(e) = self.remove_writer_sender.send(*guid) {
+        return create_error_dropped!("Cannot remove DataWriter {guid:?}: {e:?}");
+      }
+    }
+    Ok((removed_readers, removed_writers))
+  }
+
+  #[cfg(feature = "security")]
+  pub fn shared_secret_fingerprint(
+    &self,
+    remote_participant: GuidPrefix,
+  ) -> CreateResult<[u8; 32]> {
+    match self.security_plugins_handle {
+      Some(ref security_plugins_handle) => Ok(
+        security_plugins_handle
+          .get_plugins()
+          .get_shared_secret_fingerprint(remote_participant)?
+          .into(),
+      ),
+      None => create_error_bad_parameter!("Security is not enabled for this DomainParticipant"),
+    }
+  }
+
   // Publisher and subscriber creation
   //
   // There are no delete function for publisher or subscriber. Deletion is
   // performed by deleting the Publisher or Subscriber object, who upon deletion
   // will notify the DomainParticipant.
   pub fn create_publisher(
-    &self,
+    &mut self,
     domain_participant: &DomainParticipantWeak,
     qos: &QosPolicies,
     discovery_command: mio_channel::SyncSender<DiscoveryCommand>,
   ) -> CreateResult<Publisher> {
-    Ok(Publisher::new(
+    let publisher = self.create_builtin_publisher(domain_participant, qos, discovery_command);
+    self.publishers.retain(|p| p.upgrade().is_some());
+    self.publishers.push(publisher.downgrade());
+    Ok(publisher)
+  }
+
+  // Publisher for the participant's own DataWriters, e.g. security logging.
+  // It is not tracked, so delete_contained_entities() leaves it alone.
+  pub fn create_builtin_publisher(
+    &self,
+    domain_participant: &DomainParticipantWeak,
+    qos: &QosPolicies,
+    discovery_command: mio_channel::SyncSender<DiscoveryCommand>,
+  ) -> Publisher {
+    Publisher::new(
       domain_participant.clone(),
       self.discovery_db.clone(),
       qos.clone(),
@@ -1044,16 +2857,16 @@ impl DomainParticipantInner {
       self.remove_writer_sender.clone(),
       discovery_command,
       self.security_plugins_handle.clone(),
-    ))
+    )
   }
 
   pub fn create_subscriber(
-    &self,
+    &mut self,
     domain_participant: &DomainParticipantWeak,
     qos: &QosPolicies,
     discovery_command: mio_channel::SyncSender<DiscoveryCommand>,
   ) -> CreateResult<Subscriber> {
-    Ok(Subscriber::new(
+    let subscriber = Subscriber::new(
       domain_participant.clone(),
       self.discovery_db.clone(),
       qos.clone(),
@@ -1061,7 +2874,25 @@ impl DomainParticipantInner {
       self.sender_remove_reader.clone(),
       discovery_command,
       self.security_plugins_handle.clone(),
-    ))
+    );
+    self.subscribers.retain(|s| s.upgrade().is_some());
+    self.subscribers.push(subscriber.downgrade());
+    Ok(subscriber)
+  }
+
+  // Publishers and Subscribers that still exist. They are no longer tracked
+  // after this, because they are about to be deleted.
+  pub fn take_publishers_and_subscribers(&mut self) -> (Vec<Publisher>, Vec<Subscriber>) {
+    (
+      std::mem::take(&mut self.publishers)
+        .iter()
+        .filter_map(PublisherWeak::upgrade)
+        .collect(),
+      std::mem::take(&mut self.subscribers)
+        .iter()
+        .filter_map(SubscriberWeak::upgrade)
+        .collect(),
+    )
   }
 
   // Topic creation. Data types should be handled as something (potentially) more
@@ -1092,84 +2923,26 @@ impl DomainParticipantInner {
 
   // Do not implement content filtered topics or multi-topics (yet)
 
-  pub fn find_topic(
-    &self,
-    domain_participant_weak: &DomainParticipantWeak,
-    name: &str,
-    timeout: Duration,
-  ) -> CreateResult<Option<Topic>> {
-    use mio_06 as mio;
-
-    let poll = mio::Poll::new()?;
-    let mut events = mio::Events::with_capacity(1);
-    // Should be register before the check and use level trigger to avoid missing
-    // event
-    poll.register(
-      &self.discovery_db_event_receiver,
-      mio::Token(0),
-      mio::Ready::readable(),
-      mio::PollOpt::level(),
-    )?;
-
-    let find_end = Instant::now() + timeout;
-    loop {
-      if let Some(topic) = self.find_topic_in_discovery_db(domain_participant_weak, name)? {
-        return Ok(Some(topic));
-      }
-      let timeout = find_end - Instant::now();
-      poll.poll(&mut events, Some(timeout))?;
-
-      if let Some(_event) = events.iter().next() {
-        if self.discovery_db_event_receiver.try_recv().is_ok() {
-          continue;
-        }
-      }
-
-      if Instant::now() > find_end {
-        break;
-      }
-    }
-
-    Ok(None)
-  }
-
-  fn find_topic_in_discovery_db(
+  // Builds a local Topic matching one found by Discovery
+  pub fn topic_from_discovered(
     &self,
     domain_participant_weak: &DomainParticipantWeak,
-    name: &str,
-  ) -> CreateResult<Option<Topic>> {
-    let db = self
-      .discovery_db
-      .read()
-      .map_err(|_| CreateError::Poisoned {
-        reason: "discovery db".to_string(),
-      })?;
-
-    let build_topic_fn = |d: &DiscoveredTopicData| {
-      let qos = d.topic_data.qos();
-      let topic_kind = match d.topic_data.key {
-        Some(_) => TopicKind::WithKey,
-        None => TopicKind::NoKey,
-      };
-      let name = d.topic_name().clone();
-      let type_desc = d.topic_data.type_name.clone();
-      self.create_topic(domain_participant_weak, name, type_desc, &qos, topic_kind)
+    d: &DiscoveredTopicData,
+  ) -> CreateResult<Topic> {
+    let qos = d.topic_data.qos();
+    let topic_kind = match d.topic_data.key {
+      Some(_) => TopicKind::WithKey,
+      None => TopicKind::NoKey,
     };
-
-    if let Some(d) = db.get_topic(name) {
-      // build a Topic from DiscoveredTopicData
-      build_topic_fn(d).map(Some)
-    } else {
-      Ok(None)
-    }
+    let name = d.topic_name().clone();
+    let type_desc = d.topic_data.type_name.clone();
+    self.create_topic(domain_participant_weak, name, type_desc, &qos, topic_kind)
   }
-  // get_builtin_subscriber (why would we need this?)
 
-  // ignore_* operations. TODO: Do we need any of those?
+  // get_builtin_subscriber (why would we need this?)
 
-  // delete_contained_entities is not needed. Data structures should be designed
-  // so that lifetime of all created objects is within the lifetime of
-  // DomainParticipant. Then such deletion is implicit.
+  // ignore_* operations are implemented in DomainParticipant, because they are
+  // done by Discovery.
 
   // The following methods are not for application use.
 
@@ -1197,6 +2970,14 @@ impl DomainParticipantInner {
     self.participant_id
   }
 
+  pub fn instance_id(&self) -> Option<String> {
+    self.instance_id.clone()
+  }
+
+  pub fn max_receive_message_size(&self) -> Option<usize> {
+    self.max_receive_message_size
+  }
+
   pub fn discovered_topics(&self) -> Vec<DiscoveredTopicData> {
     let db = self
       .discovery_db
@@ -1205,13 +2986,53 @@ impl DomainParticipantInner {
 
     db.all_user_topics().cloned().collect()
   }
-} // impl
 
-impl RTPSEntity for DomainParticipant {
-  fn guid(&self) -> GUID {
-    self.dpi.lock().unwrap().guid()
+  pub fn discovered_participants(&self) -> Vec<DiscoveredParticipantInfo> {
+    let db = self
+      .discovery_db
+      .read()
+      .unwrap_or_else(|e| panic!("DiscoveryDB is poisoned. {e:?}"));
+
+    db.remote_participants()
+      .map(DiscoveredParticipantInfo::from)
+      .collect()
   }
-}
+
+  #[cfg(feature = "security")]
+  pub fn pending_handshakes(&self) -> Vec<(GuidPrefix, DiscHandshakeState)> {
+    discovery_db_read(&self.discovery_db).pending_handshakes()
+  }
+
+  pub fn discovered_readers(&self) -> Vec<DiscoveredReaderData> {
+    let db = self
+      .discovery_db
+      .read()
+      .unwrap_or_else(|e| panic!("DiscoveryDB is poisoned. {e:?}"));
+
+    db.remote_topic_readers().cloned().collect()
+  }
+
+  pub fn discovered_writers(&self) -> Vec<DiscoveredWriterData> {
+    let db = self
+      .discovery_db
+      .read()
+      .unwrap_or_else(|e| panic!("DiscoveryDB is poisoned. {e:?}"));
+
+    db.remote_topic_writers().cloned().collect()
+  }
+
+  pub fn discovery_events(&self) -> DiscoveryEventReceiver {
+    let (sender, receiver) = discovery_event_channel();
+    discovery_db_write(&self.discovery_db).add_event_sender(sender);
+    receiver
+  }
+} // impl
+
+impl RTPSEntity for DomainParticipant {
+  fn guid(&self) -> GUID {
+    self.dpi.lock().unwrap().guid()
+  }
+}
 
 impl RTPSEntity for DomainParticipantDisc {
   fn guid(&self) -> GUID {
@@ -1262,7 +3083,7 @@ mod tests {
       locator::Locator,
       sequence_number::{SequenceNumber, SequenceNumberSet},
     },
-    test::random_data::RandomData,
+    test::{random_data::RandomData, wait_until},
   };
   use super::DomainParticipant;
 
@@ -1365,6 +3186,3119 @@ mod tests {
     let ip = Ipv4Addr::from([0x00, 0x00, 0x00, 0x00]);
     let socket_address = SocketAddrV4::new(ip, port_number);
     let locators = vec![Locator::UdpV4(socket_address)];
-    sender.send_to_locator_list(&_data, &locators);
+    sender.send_to_locator_list(&_data, &locators).unwrap_or(());
+  }
+
+  #[cfg(feature = "security")]
+  #[test]
+  fn dp_security_from_files() {
+    let config_dir = std::path::Path::new("example_security_configuration_files");
+    let mut builder = super::DomainParticipantBuilder::new(0);
+    builder
+      .security_from_files(
+        config_dir.join("identity_ca_certificate.pem"),
+        config_dir.join("participant1_certificate.pem"),
+        config_dir.join("participant1_private_key.pem"),
+        config_dir.join("permissions_ca_certificate.pem"),
+        config_dir.join("permissive_governance.p7s"),
+        config_dir.join("permissive_permissions.p7s"),
+      )
+      .expect("Security configuration from files failed");
+    let dp = builder.build().expect("Secure participant creation failed");
+    assert!(dp.qos().property.is_some());
+  }
+
+  #[cfg(feature = "security")]
+  #[test]
+  fn dp_security_from_missing_file() {
+    let config_dir = std::path::Path::new("example_security_configuration_files");
+    let mut builder = super::DomainParticipantBuilder::new(0);
+    let result = builder.security_from_files(
+      config_dir.join("identity_ca_certificate.pem"),
+      config_dir.join("no_such_certificate.pem"),
+      config_dir.join("participant1_private_key.pem"),
+      config_dir.join("permissions_ca_certificate.pem"),
+      config_dir.join("permissive_governance.p7s"),
+      config_dir.join("permissive_permissions.p7s"),
+    );
+    assert!(matches!(
+      result,
+      Err(super::CreateError::BadParameter { .. })
+    ));
+  }
+
+  #[cfg(feature = "security")]
+  #[test]
+  fn reload_permissions_removes_denied_reader() {
+    use crate::{
+      dds::qos::policy::Reliability,
+      no_key::{DataReader, DataWriter},
+      structure::entity::RTPSEntity,
+      QosPolicyBuilder,
+    };
+
+    let config_dir = std::path::Path::new("example_security_configuration_files");
+    let permissions_path =
+      std::env::temp_dir().join(format!("rustdds_permissions_{}.p7s", std::process::id()));
+    std::fs::copy(
+      config_dir.join("permissive_permissions.p7s"),
+      &permissions_path,
+    )
+    .unwrap();
+
+    let domain_id = 64;
+    let dp1 = DomainParticipant::new(domain_id).expect("Failed to create participant 1");
+    let mut builder = super::DomainParticipantBuilder::new(domain_id);
+    builder
+      .security_from_files(
+        config_dir.join("identity_ca_certificate.pem"),
+        config_dir.join("participant1_certificate.pem"),
+        config_dir.join("participant1_private_key.pem"),
+        config_dir.join("permissions_ca_certificate.pem"),
+        config_dir.join("access_controlled_governance.p7s"),
+        &permissions_path,
+      )
+      .unwrap();
+    let dp2 = builder.build().expect("Secure participant creation failed");
+
+    let qos = QosPolicyBuilder::new()
+      .reliability(Reliability::Reliable {
+        max_blocking_time: crate::Duration::from_secs(1),
+      })
+      .build();
+    let create_topic = |dp: &DomainParticipant| {
+      dp.create_topic(
+        "PermissionsReloadTopic".to_string(),
+        "i32".to_string(),
+        &qos,
+        TopicKind::NoKey,
+      )
+      .unwrap()
+    };
+    let writer: DataWriter<i32> = dp1
+      .create_publisher(&qos)
+      .unwrap()
+      .create_datawriter_no_key_cdr(&create_topic(&dp1), None)
+      .unwrap();
+    let reader: DataReader<i32> = dp2
+      .create_subscriber(&qos)
+      .unwrap()
+      .create_datareader_no_key_cdr(&create_topic(&dp2), None)
+      .unwrap();
+
+    let wait_for_match = |matched: bool| {
+      (0..100).any(|_| {
+        if writer.reader_reliability_state(reader.guid()).is_some() == matched {
+          return true;
+        }
+        std::thread::sleep(std::time::Duration::from_millis(100));
+        false
+      })
+    };
+    assert!(wait_for_match(true), "Reader did not match");
+
+    // The new permissions deny all topics except example_topic
+    std::fs::copy(
+      config_dir.join("restrictive_permissions.p7s"),
+      &permissions_path,
+    )
+    .unwrap();
+    dp2.reload_permissions().unwrap();
+    assert!(wait_for_match(false), "Denied reader is still matched");
+
+    // A missing document is an error
+    std::fs::remove_file(&permissions_path).unwrap();
+    assert!(dp2.reload_permissions().is_err());
+  }
+
+  #[cfg(feature = "security")]
+  #[test]
+  fn abort_handshake_with_unresponsive_participant() {
+    use std::{
+      net::SocketAddr,
+      sync::{Arc, Mutex},
+      time::Duration,
+    };
+
+    use bytes::Bytes;
+
+    use crate::{
+      messages::submessages::submessages::WriterSubmessage,
+      network::constant::spdp_well_known_unicast_port, rtps::submessage::SubmessageBody,
+      structure::entity::RTPSEntity, MessageDirection,
+    };
+
+    // Record the SPDP announcement of a secure participant in another domain,
+    // which then goes away. Replaying it makes the remote look alive, but it
+    // never answers the handshake.
+    let spdp_message = Arc::new(Mutex::new(None));
+    let spdp_message_clone = Arc::clone(&spdp_message);
+    let tap = move |direction: MessageDirection, _peer: SocketAddr, message: &[u8]| {
+      let Ok(parsed) = Message::read_from_buffer(&Bytes::copy_from_slice(message)) else {
+        return;
+      };
+      let is_spdp = parsed.submessages.iter().any(|submessage| {
+        matches!(&submessage.body,
+          SubmessageBody::Writer(WriterSubmessage::Data(data, _))
+            if data.writer_id == EntityId::SPDP_BUILTIN_PARTICIPANT_WRITER)
+      });
+      if direction == MessageDirection::Outbound && is_spdp {
+        spdp_message_clone
+          .lock()
+          .unwrap()
+          .get_or_insert_with(|| message.to_vec());
+      }
+    };
+    let mut builder = super::DomainParticipantBuilder::new(66);
+    builder.add_builtin_security().message_tap(Arc::new(tap));
+    let unresponsive = builder.build().expect("Failed to create participant 2");
+    let unresponsive_prefix = unresponsive.guid().prefix;
+    wait_until(Duration::from_secs(5), || {
+      spdp_message.lock().unwrap().is_some()
+    });
+    drop(unresponsive);
+    let spdp_message = spdp_message
+      .lock()
+      .unwrap()
+      .take()
+      .expect("No SPDP message was sent");
+
+    let domain_id = 65;
+    let mut builder = super::DomainParticipantBuilder::new(domain_id);
+    builder.add_builtin_security();
+    let dp = builder.build().expect("Failed to create participant 1");
+    assert!(dp.abort_handshake(unresponsive_prefix).is_err());
+
+    UDPSender::new_with_random_port().unwrap().send_to_all(
+      &spdp_message,
+      &[SocketAddr::new(
+        "127.0.0.1".parse().unwrap(),
+        spdp_well_known_unicast_port(domain_id, dp.participant_id()),
+      )],
+    );
+    let is_pending = |dp: &DomainParticipant| {
+      dp.pending_handshakes()
+        .iter()
+        .any(|(prefix, _state)| *prefix == unresponsive_prefix)
+    };
+    assert!(
+      wait_until(Duration::from_secs(5), || is_pending(&dp)),
+      "Handshake did not start"
+    );
+
+    dp.abort_handshake(unresponsive_prefix).unwrap();
+    assert!(
+      wait_until(Duration::from_secs(5), || !is_pending(&dp)),
+      "Handshake was not aborted"
+    );
+  }
+
+  #[test]
+  fn large_sample_is_fragmented_and_reassembled() {
+    use crate::{
+      dds::qos::policy::{Durability, History, Reliability},
+      no_key::{DataReader, DataWriter},
+      QosPolicyBuilder,
+    };
+
+    let domain_id = 19;
+    let dp1 = DomainParticipant::new(domain_id).expect("Failed to create participant 1");
+    let dp2 = DomainParticipant::new(domain_id).expect("Failed to create participant 2");
+
+    let qos = QosPolicyBuilder::new()
+      .reliability(Reliability::Reliable {
+        max_blocking_time: crate::Duration::from_secs(1),
+      })
+      .durability(Durability::TransientLocal) // deliver, even if written before matching
+      .history(History::KeepAll)
+      .build();
+    let topic1 = dp1
+      .create_topic(
+        "LargeSampleTopic".to_string(),
+        "ByteVec".to_string(),
+        &qos,
+        TopicKind::NoKey,
+      )
+      .unwrap();
+    let topic2 = dp2
+      .create_topic(
+        "LargeSampleTopic".to_string(),
+        "ByteVec".to_string(),
+        &qos,
+        TopicKind::NoKey,
+      )
+      .unwrap();
+    let writer: DataWriter<Vec<u8>> = dp1
+      .create_publisher(&qos)
+      .unwrap()
+      .create_datawriter_no_key_cdr(&topic1, None)
+      .unwrap();
+    let mut reader: DataReader<Vec<u8>> = dp2
+      .create_subscriber(&qos)
+      .unwrap()
+      .create_datareader_no_key_cdr(&topic2, None)
+      .unwrap();
+
+    let sample: Vec<u8> = (0..200_000).map(|i| (i % 251) as u8).collect();
+    writer.write(sample.clone(), None).unwrap();
+
+    let mut received = None;
+    wait_until(std::time::Duration::from_secs(15), || {
+      received = reader
+        .take_next_sample()
+        .ok()
+        .flatten()
+        .map(|s| s.into_value());
+      received.is_some()
+    });
+    assert_eq!(received, Some(sample));
+  }
+
+  #[test]
+  fn high_priority_writer_is_not_starved() {
+    use std::{
+      sync::atomic::{AtomicBool, Ordering},
+      time::{Duration as StdDuration, Instant},
+    };
+
+    use crate::{
+      dds::qos::policy::{Durability, History, Reliability, TransportPriority},
+      no_key::{DataReader, DataWriter},
+      QosPolicyBuilder,
+    };
+
+    let domain_id = 20;
+    let dp1 = DomainParticipant::new(domain_id).expect("Failed to create participant 1");
+    let dp2 = DomainParticipant::new(domain_id).expect("Failed to create participant 2");
+
+    let bulk_qos = QosPolicyBuilder::new()
+      .reliability(Reliability::BestEffort)
+      .history(History::KeepLast { depth: 1 })
+      .build();
+    let control_qos = QosPolicyBuilder::new()
+      .reliability(Reliability::Reliable {
+        max_blocking_time: crate::Duration::from_secs(1),
+      })
+      .durability(Durability::TransientLocal)
+      .history(History::KeepAll)
+      .build();
+    let control_writer_qos = control_qos.modify_by(
+      &QosPolicyBuilder::new()
+        .transport_priority(TransportPriority { value: 100 })
+        .build(),
+    );
+
+    let topic = |dp: &DomainParticipant, name: &str, qos| {
+      dp.create_topic(
+        name.to_string(),
+        "ByteVec".to_string(),
+        qos,
+        TopicKind::NoKey,
+      )
+      .unwrap()
+    };
+    let bulk_writer: DataWriter<Vec<u8>> = dp1
+      .create_publisher(&bulk_qos)
+      .unwrap()
+      .create_datawriter_no_key_cdr(&topic(&dp1, "BulkTopic", &bulk_qos), None)
+      .unwrap();
+    let _bulk_reader: DataReader<Vec<u8>> = dp2
+      .create_subscriber(&bulk_qos)
+      .unwrap()
+      .create_datareader_no_key_cdr(&topic(&dp2, "BulkTopic", &bulk_qos), None)
+      .unwrap();
+    let control_writer: DataWriter<Vec<u8>> = dp1
+      .create_publisher(&control_qos)
+      .unwrap()
+      .create_datawriter_no_key_cdr(
+        &topic(&dp1, "ControlTopic", &control_qos),
+        Some(control_writer_qos),
+      )
+      .unwrap();
+    let mut control_reader: DataReader<Vec<u8>> = dp2
+      .create_subscriber(&control_qos)
+      .unwrap()
+      .create_datareader_no_key_cdr(&topic(&dp2, "ControlTopic", &control_qos), None)
+      .unwrap();
+
+    let receive = |reader: &mut DataReader<Vec<u8>>, timeout: StdDuration| {
+      let start = Instant::now();
+      while start.elapsed() < timeout {
+        if let Ok(Some(s)) = reader.take_next_sample() {
+          return Some((s.into_value(), start.elapsed()));
+        }
+        std::thread::sleep(StdDuration::from_millis(1));
+      }
+      None
+    };
+
+    // Wait until the control topic is matched and working
+    control_writer.write(vec![0], None).unwrap();
+    assert!(receive(&mut control_reader, StdDuration::from_secs(15)).is_some());
+
+    // Saturate the event loop with bulk data. The bulk writer is stopped before
+    // checking the results, so that a failure does not hang the test.
+    let stop = AtomicBool::new(false);
+    let results = std::thread::scope(|scope| {
+      let stop = &stop;
+      scope.spawn(move || {
+        let bulk_sample = vec![0xAB_u8; 16_000];
+        while !stop.load(Ordering::Relaxed) {
+          let _ = bulk_writer.write(bulk_sample.clone(), None);
+        }
+      });
+      std::thread::sleep(StdDuration::from_millis(200));
+
+      let results: Vec<_> = (1..=10)
+        .map(|i| {
+          control_writer.write(vec![i], None).unwrap();
+          (i, receive(&mut control_reader, StdDuration::from_secs(1)))
+        })
+        .collect();
+      stop.store(true, Ordering::Relaxed);
+      results
+    });
+    for (i, received) in results {
+      let latency = received.as_ref().map(|(_, latency)| *latency);
+      assert_eq!(
+        received.map(|(v, _)| v),
+        Some(vec![i]),
+        "latency {latency:?}"
+      );
+    }
+  }
+
+  #[test]
+  fn fragment_size_is_validated() {
+    let mut builder = super::DomainParticipantBuilder::new(0);
+    builder.fragment_size(0);
+    assert!(matches!(
+      builder.build(),
+      Err(super::CreateError::BadParameter { .. })
+    ));
+  }
+
+  #[test]
+  fn custom_multicast_group_is_used_in_locators() {
+    let group = Ipv4Addr::new(239, 255, 42, 7);
+    let mut builder = super::DomainParticipantBuilder::new(28);
+    builder.multicast_group(group);
+    let dp = builder.build().unwrap();
+
+    let self_locators = dp.self_locators();
+    for token in [
+      super::DISCOVERY_MUL_LISTENER_TOKEN,
+      super::USER_TRAFFIC_MUL_LISTENER_TOKEN,
+    ] {
+      let locators = self_locators.get(&token).unwrap();
+      assert!(!locators.is_empty());
+      for locator in locators {
+        assert_eq!(SocketAddr::from(*locator).ip(), group);
+      }
+    }
+
+    let mut builder = super::DomainParticipantBuilder::new(28);
+    builder.multicast_group(Ipv4Addr::new(10, 0, 0, 1));
+    assert!(matches!(
+      builder.build(),
+      Err(super::CreateError::BadParameter { .. })
+    ));
+  }
+
+  #[test]
+  fn disabled_multicast_opens_no_multicast_listeners() {
+    let mut builder = super::DomainParticipantBuilder::new(61);
+    builder.disable_multicast();
+    let dp = builder.build().unwrap();
+
+    let self_locators = dp.self_locators();
+    assert!(self_locators.contains_key(&super::DISCOVERY_LISTENER_TOKEN));
+    assert!(self_locators.contains_key(&super::USER_TRAFFIC_LISTENER_TOKEN));
+    for token in [
+      super::DISCOVERY_MUL_LISTENER_TOKEN,
+      super::USER_TRAFFIC_MUL_LISTENER_TOKEN,
+    ] {
+      assert!(!self_locators.contains_key(&token));
+    }
+  }
+
+  #[test]
+  fn spdp_announcement_period_sets_rate() {
+    use std::time::{Duration, Instant};
+
+    use crate::{
+      network::{constant::*, udp_listener::UDPListener},
+      structure::entity::RTPSEntity,
+    };
+
+    let domain_id = 39;
+    let mut listener = UDPListener::new_multicast(
+      "0.0.0.0",
+      spdp_well_known_multicast_port(domain_id),
+      DEFAULT_MULTICAST_GROUP,
+    )
+    .unwrap();
+    let mut builder = super::DomainParticipantBuilder::new(domain_id);
+    builder.spdp_announcement_period(Duration::from_millis(200));
+    let dp = builder.build().unwrap();
+
+    // SPDP DATA from dp, i.e. the SPDP writer id in a message from it. The same
+    // message is sent on every multicast interface, so collect distinct ones.
+    let guid_prefix = dp.guid().prefix;
+    let spdp_writer_id = [0x00, 0x01, 0x00, 0xc2];
+    let mut announcements = BTreeSet::new();
+    let receive_announcements = |listener: &mut UDPListener, announcements: &mut BTreeSet<_>| {
+      announcements.extend(listener.messages().into_iter().filter(|msg| {
+        msg.len() > 20
+          && msg[8..20] == *guid_prefix.as_ref()
+          && msg.windows(4).any(|w| w == spdp_writer_id)
+      }));
+    };
+
+    // Skip the announcements at startup
+    std::thread::sleep(Duration::from_millis(300));
+    receive_announcements(&mut listener, &mut BTreeSet::new());
+
+    let start = Instant::now();
+    while start.elapsed() < Duration::from_secs(1) {
+      std::thread::sleep(Duration::from_millis(10));
+      receive_announcements(&mut listener, &mut announcements);
+    }
+    let count = announcements.len();
+    assert!((4..=6).contains(&count), "{count} announcements");
+  }
+
+  #[cfg(feature = "test-util")]
+  #[test]
+  fn injected_spdp_packet_is_discovered() {
+    use std::{
+      net::SocketAddr,
+      sync::Arc,
+      time::{Duration, Instant},
+    };
+
+    use speedy::{Endianness, Writable};
+
+    use crate::{test::test_data::spdp_participant_msg_mod, test_util::PacketCapture};
+
+    let capture = PacketCapture::new();
+    let mut builder = super::DomainParticipantBuilder::new(68);
+    builder.message_tap(Arc::new(capture.clone()));
+    let dp = builder.build().expect("Failed to create participant");
+
+    // A fake remote participant, which claims to listen on this port
+    let fake_port = 11068;
+    let spdp_msg = spdp_participant_msg_mod(fake_port);
+    let fake_prefix = spdp_msg.header.guid_prefix;
+    let fake_address = SocketAddr::new("127.0.0.1".parse().unwrap(), fake_port);
+    dp.packet_injector()
+      .inject(
+        &spdp_msg
+          .write_to_vec_with_ctx(Endianness::LittleEndian)
+          .unwrap(),
+        fake_address,
+      )
+      .unwrap();
+
+    let is_discovered = || {
+      dp.discovered_participants()
+        .iter()
+        .any(|p| p.guid.prefix == fake_prefix)
+    };
+    assert!(
+      wait_until(Duration::from_secs(5), is_discovered),
+      "Injected participant was not discovered"
+    );
+
+    // Discovery talks to the new participant at its announced locator
+    let sent_to_fake = wait_until(Duration::from_secs(5), || {
+      capture
+        .take()
+        .iter()
+        .any(|(destination, _packet)| *destination == fake_address)
+    });
+    assert!(sent_to_fake, "Nothing was sent to the injected participant");
+  }
+
+  #[test]
+  fn refresh_endpoints_reannounces_writers() {
+    use std::time::{Duration, Instant};
+
+    use speedy::{Endianness, Writable};
+
+    use crate::{
+      messages::submessages::submessages::WriterSubmessage,
+      network::{constant::*, udp_listener::UDPListener, udp_sender::UDPSender},
+      rtps::{message::Message, submessage::SubmessageBody},
+      structure::guid::EntityId,
+      test::test_data::spdp_participant_msg_mod,
+    };
+
+    let domain_id = 41;
+    let start = Instant::now();
+    let dp = super::DomainParticipant::new(domain_id).unwrap();
+    let qos = QosPolicies::qos_none();
+    let topic = dp
+      .create_topic(
+        "refresh_endpoints".to_string(),
+        "RefreshType".to_string(),
+        &qos,
+        TopicKind::NoKey,
+      )
+      .unwrap();
+    let _writer = dp
+      .create_publisher(&qos)
+      .unwrap()
+      .create_datawriter_no_key_cdr::<i32>(&topic, None)
+      .unwrap();
+
+    // A late-joining peer, which does not ask for the history of the SEDP
+    // Writers. It only gets what the Writers send on their own.
+    let peer_port = 11041;
+    let mut peer_listener = UDPListener::new_unicast("127.0.0.1", peer_port).unwrap();
+    let spdp_msg = spdp_participant_msg_mod(peer_port)
+      .write_to_vec_with_ctx(Endianness::LittleEndian)
+      .unwrap();
+    UDPSender::new_with_random_port().unwrap().send_to_all(
+      &spdp_msg,
+      &[SocketAddr::new(
+        "127.0.0.1".parse().unwrap(),
+        spdp_well_known_unicast_port(domain_id, dp.participant_id()),
+      )],
+    );
+
+    // Count DATA submessages from the SEDP publications Writer
+    let mut receive = |duration: Duration| {
+      let mut datas = 0;
+      let receive_start = Instant::now();
+      while receive_start.elapsed() < duration {
+        std::thread::sleep(Duration::from_millis(10));
+        for msg in peer_listener.messages() {
+          let Ok(msg) = Message::read_from_buffer(&msg) else {
+            continue;
+          };
+          for submsg in msg.submessages {
+            match submsg.body {
+              SubmessageBody::Writer(WriterSubmessage::Data(data, _))
+                if data.writer_id == EntityId::SEDP_BUILTIN_PUBLICATIONS_WRITER =>
+              {
+                datas += 1;
+              }
+              _ => (),
+            }
+          }
+        }
+      }
+      datas
+    };
+
+    // The peer gets matched, but gets no endpoint data
+    assert_eq!(receive(Duration::from_millis(500)), 0);
+
+    dp.refresh_endpoints().unwrap();
+    let datas = receive(Duration::from_millis(300));
+    // Periodic re-announcement, every 2 seconds, would also do it, but not
+    // this soon
+    assert!(start.elapsed() < Duration::from_secs(2));
+    assert!(datas > 0);
+  }
+
+  #[test]
+  fn lazy_user_traffic_listeners_open_on_demand() {
+    let mut builder = super::DomainParticipantBuilder::new(29);
+    builder.lazy_user_traffic_listeners(true);
+    let dp = builder.build().unwrap();
+    let user_port = user_traffic_unicast_port(29, dp.participant_id());
+    let bind_user_port = || std::net::UdpSocket::bind(("0.0.0.0", user_port));
+
+    assert!(!dp
+      .self_locators()
+      .contains_key(&super::USER_TRAFFIC_LISTENER_TOKEN));
+    // Nobody has bound the port, so we can
+    drop(bind_user_port().unwrap());
+
+    let qos = QosPolicies::qos_none();
+    let topic = dp
+      .create_topic(
+        "lazy_listener_topic".to_string(),
+        "RandomData".to_string(),
+        &qos,
+        TopicKind::NoKey,
+      )
+      .unwrap();
+    let _writer = dp
+      .create_publisher(&qos)
+      .unwrap()
+      .create_datawriter_no_key_cdr::<RandomData>(&topic, None)
+      .unwrap();
+
+    let user_locators = dp
+      .self_locators()
+      .remove(&super::USER_TRAFFIC_LISTENER_TOKEN)
+      .unwrap();
+    assert!(user_locators
+      .iter()
+      .all(|loc| SocketAddr::from(*loc).port() == user_port));
+    assert!(bind_user_port().is_err());
+  }
+
+  #[test]
+  fn announced_locators_replace_interface_addresses() {
+    use crate::structure::entity::RTPSEntity;
+
+    let domain_id = 31;
+    let public_metatraffic = SocketAddr::from(([203, 0, 113, 5], 17410));
+    let public_user_traffic = SocketAddr::from(([203, 0, 113, 5], 17411));
+    let mut builder = super::DomainParticipantBuilder::new(domain_id);
+    builder.announced_locators(vec![public_metatraffic], vec![public_user_traffic]);
+    let dp1 = builder.build().unwrap();
+    let dp2 = DomainParticipant::new(domain_id).expect("Failed to create participant 2");
+
+    let mut found = None;
+    wait_until(std::time::Duration::from_secs(10), || {
+      found = dp2
+        .discovered_participants()
+        .into_iter()
+        .find(|p| p.guid == dp1.guid());
+      found.is_some()
+    });
+
+    let info = found.expect("Participant 1 was not discovered");
+    assert_eq!(
+      info.metatraffic_unicast_locators,
+      vec![Locator::from(public_metatraffic)]
+    );
+    assert_eq!(
+      info.default_unicast_locators,
+      vec![Locator::from(public_user_traffic)]
+    );
+    // Multicast is still local
+    assert!(info
+      .metatraffic_multicast_locators
+      .iter()
+      .all(|loc| SocketAddr::from(*loc).ip().is_multicast()));
+  }
+
+  #[test]
+  fn dropped_participant_is_lost_immediately() {
+    use crate::structure::entity::RTPSEntity;
+
+    let domain_id = 32;
+    let dp_a = DomainParticipant::new(domain_id).expect("Failed to create participant A");
+    let dp_b = DomainParticipant::new(domain_id).expect("Failed to create participant B");
+    let qos = QosPolicies::qos_none();
+    let topic = dp_a
+      .create_topic(
+        "departure_topic".to_string(),
+        "RandomData".to_string(),
+        &qos,
+        TopicKind::NoKey,
+      )
+      .unwrap();
+    let writer = dp_a
+      .create_publisher(&qos)
+      .unwrap()
+      .create_datawriter_no_key_cdr::<RandomData>(&topic, None)
+      .unwrap();
+    let guid_a = dp_a.guid();
+    let writer_guid = writer.guid();
+    let a_is_known = || {
+      dp_b
+        .discovered_participants()
+        .iter()
+        .any(|p| p.guid == guid_a)
+    };
+    let writer_is_known = || {
+      dp_b
+        .discovered_writers()
+        .iter()
+        .any(|w| w.writer_proxy.remote_writer_guid == writer_guid)
+    };
+
+    wait_until(std::time::Duration::from_secs(10), || {
+      a_is_known() && writer_is_known()
+    });
+    assert!(a_is_known(), "Participant A was not discovered");
+    assert!(writer_is_known(), "Writer of A was not discovered");
+
+    // The writer is still alive when the participant is dropped.
+    drop(dp_a);
+    let dropped_at = std::time::Instant::now();
+    wait_until(std::time::Duration::from_secs(5), || {
+      !a_is_known() && !writer_is_known()
+    });
+    // The lease duration is 10 s, so this was not due to lease expiry.
+    assert!(dropped_at.elapsed() < std::time::Duration::from_secs(1));
+    drop(writer);
+  }
+
+  #[test]
+  fn discovered_participants_lists_remote() {
+    use crate::structure::entity::RTPSEntity;
+
+    let domain_id = 18;
+    let dp1 = DomainParticipant::new(domain_id).expect("Failed to create participant 1");
+    let dp2 = DomainParticipant::new(domain_id).expect("Failed to create participant 2");
+
+    let mut found = None;
+    wait_until(std::time::Duration::from_secs(10), || {
+      found = dp1
+        .discovered_participants()
+        .into_iter()
+        .find(|p| p.guid == dp2.guid());
+      found.is_some()
+    });
+
+    let info = found.expect("Participant 2 was not discovered");
+    assert_eq!(info.vendor_id, VendorId::THIS_IMPLEMENTATION);
+    assert!(!info.default_unicast_locators.is_empty());
+    assert_eq!(info.security_enabled, cfg!(feature = "security"));
+    // We do not list ourselves
+    assert!(dp1
+      .discovered_participants()
+      .iter()
+      .all(|p| p.guid != dp1.guid()));
+  }
+
+  #[test]
+  fn discovered_endpoints_include_qos() {
+    use crate::{
+      dds::qos::policy::{Deadline, Durability, Reliability},
+      no_key::{DataReader, DataWriter},
+      structure::entity::RTPSEntity,
+      QosPolicyBuilder,
+    };
+
+    let domain_id = 25;
+    let dp1 = DomainParticipant::new(domain_id).expect("Failed to create participant 1");
+    let dp2 = DomainParticipant::new(domain_id).expect("Failed to create participant 2");
+
+    let writer_qos = QosPolicyBuilder::new()
+      .reliability(Reliability::Reliable {
+        max_blocking_time: crate::Duration::from_millis(250),
+      })
+      .durability(Durability::TransientLocal)
+      .deadline(Deadline(crate::Duration::from_secs(3)))
+      .build();
+    let reader_qos = QosPolicyBuilder::new()
+      .reliability(Reliability::BestEffort)
+      .build();
+    let topic = dp2
+      .create_topic(
+        "DiscoveredEndpointsTopic".to_string(),
+        "i32".to_string(),
+        &writer_qos,
+        TopicKind::NoKey,
+      )
+      .unwrap();
+    let writer: DataWriter<i32> = dp2
+      .create_publisher(&writer_qos)
+      .unwrap()
+      .create_datawriter_no_key_cdr(&topic, None)
+      .unwrap();
+    let reader: DataReader<i32> = dp2
+      .create_subscriber(&reader_qos)
+      .unwrap()
+      .create_datareader_no_key_cdr(&topic, Some(reader_qos.clone()))
+      .unwrap();
+
+    let mut found_writer = None;
+    let mut found_reader = None;
+    wait_until(std::time::Duration::from_secs(10), || {
+      found_writer = dp1
+        .discovered_writers()
+        .into_iter()
+        .find(|w| w.writer_proxy.remote_writer_guid == writer.guid());
+      found_reader = dp1
+        .discovered_readers()
+        .into_iter()
+        .find(|r| r.reader_proxy.remote_reader_guid == reader.guid());
+      found_writer.is_some() && found_reader.is_some()
+    });
+
+    let found_writer = found_writer.expect("Remote writer was not discovered");
+    let publication = &found_writer.publication_topic_data;
+    assert_eq!(publication.topic_name, "DiscoveredEndpointsTopic");
+    assert_eq!(publication.type_name, "i32");
+    let offered = publication.qos();
+    assert_eq!(offered.reliability(), writer_qos.reliability());
+    assert_eq!(offered.durability(), Some(Durability::TransientLocal));
+    assert_eq!(offered.deadline(), writer_qos.deadline());
+
+    let found_reader = found_reader.expect("Remote reader was not discovered");
+    let subscription = &found_reader.subscription_topic_data;
+    assert_eq!(subscription.topic_name(), "DiscoveredEndpointsTopic");
+    assert_eq!(
+      subscription.qos().reliability(),
+      Some(Reliability::BestEffort)
+    );
+
+    // Our own endpoints are not listed
+    assert!(dp2
+      .discovered_writers()
+      .iter()
+      .all(|w| w.writer_proxy.remote_writer_guid != writer.guid()));
+  }
+
+  #[test]
+  fn discovery_events_report_remote_writer() {
+    use crate::{
+      discovery::DiscoveryEvent, no_key::DataWriter, structure::entity::RTPSEntity,
+      QosPolicyBuilder,
+    };
+
+    let domain_id = 63;
+    let dp1 = DomainParticipant::new(domain_id).expect("Failed to create participant 1");
+    let events = dp1.discovery_events();
+    let dp2 = DomainParticipant::new(domain_id).expect("Failed to create participant 2");
+
+    let qos = QosPolicyBuilder::new().build();
+    let topic = dp2
+      .create_topic(
+        "DiscoveryEventsTopic".to_string(),
+        "i32".to_string(),
+        &qos,
+        TopicKind::NoKey,
+      )
+      .unwrap();
+    let writer: DataWriter<i32> = dp2
+      .create_publisher(&qos)
+      .unwrap()
+      .create_datawriter_no_key_cdr(&topic, None)
+      .unwrap();
+
+    let mut participant_discovered = false;
+    let mut topic_discovered = false;
+    let mut discovered_writer = None;
+    let deadline = std::time::Instant::now() + std::time::Duration::from_secs(10);
+    while discovered_writer.is_none() {
+      let remaining = deadline
+        .checked_duration_since(std::time::Instant::now())
+        .expect("Remote writer was not reported");
+      match events.recv_timeout(remaining) {
+        Some(DiscoveryEvent::ParticipantDiscovered { participant }) => {
+          participant_discovered |= participant.guid == dp2.guid();
+        }
+        Some(DiscoveryEvent::TopicDiscovered { topic }) => {
+          topic_discovered |= topic.topic_name() == "DiscoveryEventsTopic";
+        }
+        Some(DiscoveryEvent::WriterDiscovered { writer: w })
+          if w.writer_proxy.remote_writer_guid == writer.guid() =>
+        {
+          discovered_writer = Some(w);
+        }
+        _ => (),
+      }
+    }
+    let discovered_writer = discovered_writer.unwrap();
+    assert_eq!(
+      discovered_writer.publication_topic_data.topic_name,
+      "DiscoveryEventsTopic"
+    );
+    assert!(participant_discovered);
+    assert!(topic_discovered);
+    assert_eq!(events.lost_count(), 0);
+  }
+
+  #[test]
+  fn incompatible_qos_is_reported() {
+    use crate::{
+      dds::{
+        qos::{policy::Reliability, QosPolicyId},
+        statusevents::{DataReaderStatus, DataWriterStatus, StatusEvented},
+      },
+      no_key::{DataReader, DataWriter},
+      QosPolicyBuilder,
+    };
+
+    let domain_id = 27;
+    let dp1 = DomainParticipant::new(domain_id).expect("Failed to create participant 1");
+    let dp2 = DomainParticipant::new(domain_id).expect("Failed to create participant 2");
+
+    let reader_qos = QosPolicyBuilder::new()
+      .reliability(Reliability::Reliable {
+        max_blocking_time: crate::Duration::from_millis(100),
+      })
+      .build();
+    let writer_qos = QosPolicyBuilder::new()
+      .reliability(Reliability::BestEffort)
+      .build();
+    let topic1 = dp1
+      .create_topic(
+        "IncompatibleQosTopic".to_string(),
+        "i32".to_string(),
+        &reader_qos,
+        TopicKind::NoKey,
+      )
+      .unwrap();
+    let topic2 = dp2
+      .create_topic(
+        "IncompatibleQosTopic".to_string(),
+        "i32".to_string(),
+        &writer_qos,
+        TopicKind::NoKey,
+      )
+      .unwrap();
+    let mut reader: DataReader<i32> = dp1
+      .create_subscriber(&reader_qos)
+      .unwrap()
+      .create_datareader_no_key_cdr(&topic1, None)
+      .unwrap();
+    let mut writer: DataWriter<i32> = dp2
+      .create_publisher(&writer_qos)
+      .unwrap()
+      .create_datawriter_no_key_cdr(&topic2, None)
+      .unwrap();
+    reader.as_status_evented(); // enables status reception
+    writer.as_status_evented();
+
+    let mut requested_incompatible = None;
+    let mut offered_incompatible = None;
+    wait_until(std::time::Duration::from_secs(10), || {
+      while let Some(status) = reader.try_recv_status() {
+        if let DataReaderStatus::RequestedIncompatibleQos {
+          last_policy_id,
+          policies,
+          ..
+        } = status
+        {
+          requested_incompatible = Some((last_policy_id, policies));
+        }
+      }
+      while let Some(status) = writer.try_recv_status() {
+        if let DataWriterStatus::OfferedIncompatibleQos { last_policy_id, .. } = status {
+          offered_incompatible = Some(last_policy_id);
+        }
+      }
+      requested_incompatible.is_some() && offered_incompatible.is_some()
+    });
+
+    let (last_policy_id, policies) =
+      requested_incompatible.expect("RequestedIncompatibleQos was not reported");
+    assert_eq!(last_policy_id, QosPolicyId::Reliability);
+    assert_eq!(policies.len(), 1);
+    assert_eq!(policies[0].policy_id(), QosPolicyId::Reliability);
+    assert_eq!(policies[0].count(), 1);
+    assert_eq!(offered_incompatible, Some(QosPolicyId::Reliability));
+  }
+
+  #[test]
+  fn qos_diff_reports_durability_mismatch() {
+    use crate::{
+      dds::qos::{policy::Durability, QosPolicyId},
+      no_key::{DataReader, DataWriter},
+      structure::entity::RTPSEntity,
+      QosPolicyBuilder,
+    };
+
+    let domain_id = 56;
+    let dp1 = DomainParticipant::new(domain_id).expect("Failed to create participant 1");
+    let dp2 = DomainParticipant::new(domain_id).expect("Failed to create participant 2");
+
+    // The reader asks for more durability than the writer offers.
+    let reader_qos = QosPolicyBuilder::new()
+      .durability(Durability::TransientLocal)
+      .build();
+    let writer_qos = QosPolicyBuilder::new()
+      .durability(Durability::Volatile)
+      .build();
+    let topic1 = dp1
+      .create_topic(
+        "QosDiffTopic".to_string(),
+        "i32".to_string(),
+        &reader_qos,
+        TopicKind::NoKey,
+      )
+      .unwrap();
+    let topic2 = dp2
+      .create_topic(
+        "QosDiffTopic".to_string(),
+        "i32".to_string(),
+        &writer_qos,
+        TopicKind::NoKey,
+      )
+      .unwrap();
+    let reader: DataReader<i32> = dp1
+      .create_subscriber(&reader_qos)
+      .unwrap()
+      .create_datareader_no_key_cdr(&topic1, None)
+      .unwrap();
+    let writer: DataWriter<i32> = dp2
+      .create_publisher(&writer_qos)
+      .unwrap()
+      .create_datawriter_no_key_cdr(&topic2, None)
+      .unwrap();
+
+    let mut diffs = (None, None);
+    wait_until(std::time::Duration::from_secs(10), || {
+      diffs = (
+        writer.qos_diff(reader.guid()),
+        reader.qos_diff(writer.guid()),
+      );
+      diffs.0.is_some() && diffs.1.is_some()
+    });
+
+    let writer_diff = diffs.0.expect("Writer did not discover the reader");
+    assert_eq!(writer_diff.local.durability(), Some(Durability::Volatile));
+    assert_eq!(
+      writer_diff.remote.durability(),
+      Some(Durability::TransientLocal)
+    );
+    assert!(writer_diff.differing.contains(&QosPolicyId::Durability));
+    assert_eq!(writer_diff.incompatible, vec![QosPolicyId::Durability]);
+    assert!(!writer_diff.is_compatible());
+
+    let reader_diff = diffs.1.expect("Reader did not discover the writer");
+    assert_eq!(
+      reader_diff.local.durability(),
+      Some(Durability::TransientLocal)
+    );
+    assert_eq!(reader_diff.remote.durability(), Some(Durability::Volatile));
+    assert!(reader_diff.differing.contains(&QosPolicyId::Durability));
+    assert_eq!(reader_diff.incompatible, vec![QosPolicyId::Durability]);
+
+    // Unknown endpoints have no diff.
+    assert!(writer.qos_diff(writer.guid()).is_none());
+  }
+
+  #[test]
+  fn reader_reports_effective_and_matched_writer_qos() {
+    use crate::{
+      dds::qos::policy::{Durability, Reliability},
+      no_key::{DataReader, DataWriter},
+      structure::entity::RTPSEntity,
+      QosPolicyBuilder,
+    };
+
+    let domain_id = 60;
+    let dp1 = DomainParticipant::new(domain_id).expect("Failed to create participant 1");
+    let dp2 = DomainParticipant::new(domain_id).expect("Failed to create participant 2");
+
+    // The reader gets its reliability from the topic only.
+    let topic_qos = QosPolicyBuilder::new()
+      .reliability(Reliability::Reliable {
+        max_blocking_time: crate::Duration::from_millis(100),
+      })
+      .build();
+    let writer_qos = topic_qos.modify_by(
+      &QosPolicyBuilder::new()
+        .durability(Durability::TransientLocal)
+        .build(),
+    );
+    let topic1 = dp1
+      .create_topic(
+        "EffectiveQosTopic".to_string(),
+        "i32".to_string(),
+        &topic_qos,
+        TopicKind::NoKey,
+      )
+      .unwrap();
+    let topic2 = dp2
+      .create_topic(
+        "EffectiveQosTopic".to_string(),
+        "i32".to_string(),
+        &writer_qos,
+        TopicKind::NoKey,
+      )
+      .unwrap();
+    let reader: DataReader<i32> = dp1
+      .create_subscriber(&QosPolicies::qos_none())
+      .unwrap()
+      .create_datareader_no_key_cdr(&topic1, None)
+      .unwrap();
+    let writer: DataWriter<i32> = dp2
+      .create_publisher(&writer_qos)
+      .unwrap()
+      .create_datawriter_no_key_cdr(&topic2, None)
+      .unwrap();
+
+    assert_eq!(
+      reader.effective_qos().reliability(),
+      topic_qos.reliability()
+    );
+
+    let mut matched_qos = None;
+    wait_until(std::time::Duration::from_secs(10), || {
+      matched_qos = reader.matched_writer_qos(writer.guid());
+      matched_qos.is_some()
+    });
+    let matched_qos = matched_qos.expect("Reader did not match the writer");
+    assert_eq!(matched_qos.reliability(), writer_qos.reliability());
+    assert_eq!(matched_qos.durability(), Some(Durability::TransientLocal));
+
+    // Not a matched writer
+    assert!(reader.matched_writer_qos(reader.guid()).is_none());
+  }
+
+  #[cfg(not(feature = "security"))]
+  #[test]
+  fn passive_participant_is_not_discovered() {
+    use crate::structure::entity::RTPSEntity;
+
+    let domain_id = 21;
+    let mut builder = super::DomainParticipantBuilder::new(domain_id);
+    builder.passive(true);
+    let passive = builder
+      .build()
+      .expect("Failed to create passive participant");
+    let active = DomainParticipant::new(domain_id).expect("Failed to create participant");
+
+    let found = wait_until(std::time::Duration::from_secs(10), || {
+      passive
+        .discovered_participants()
+        .iter()
+        .any(|p| p.guid == active.guid())
+    });
+    assert!(found, "Passive participant did not discover the active one");
+
+    // Give the passive participant time to announce itself, if it would.
+    std::thread::sleep(std::time::Duration::from_secs(3));
+    assert!(active
+      .discovered_participants()
+      .iter()
+      .all(|p| p.guid != passive.guid()));
+  }
+
+  #[cfg(feature = "security")]
+  #[test]
+  fn passive_participant_cannot_use_security() {
+    let mut builder = super::DomainParticipantBuilder::new(0);
+    builder.passive(true).add_builtin_security();
+    assert!(matches!(
+      builder.build(),
+      Err(super::CreateError::BadParameter { .. })
+    ));
+  }
+
+  #[cfg(feature = "security")]
+  #[test]
+  fn dropping_secure_participant_clears_crypto_state() {
+    use crate::structure::entity::RTPSEntity;
+
+    let domain_id = 77;
+    let build = || {
+      let mut builder = super::DomainParticipantBuilder::new(domain_id);
+      builder.add_builtin_security();
+      builder.build().expect("Failed to create participant")
+    };
+    let dp1 = build();
+    let dp2 = build();
+    let (prefix1, prefix2) = (dp1.guid().prefix, dp2.guid().prefix);
+
+    let plugins = dp1
+      .dpi
+      .lock()
+      .unwrap()
+      .dpi
+      .lock()
+      .unwrap()
+      .security_plugins_handle
+      .clone()
+      .unwrap();
+    // Key material is registered for the remote participant after
+    // authentication, which may complete a little before that.
+    assert!(
+      wait_until(std::time::Duration::from_secs(10), || {
+        plugins
+          .get_plugins()
+          .participant_crypto_handle(&prefix2)
+          .is_some()
+      }),
+      "Remote participant was not registered to the crypto plugin"
+    );
+    let (local_handle, remote_handle) = {
+      let plugins = plugins.get_plugins();
+      (
+        plugins.participant_crypto_handle(&prefix1).unwrap(),
+        plugins.participant_crypto_handle(&prefix2).unwrap(),
+      )
+    };
+    assert!(plugins
+      .get_plugins()
+      .has_crypto_state(local_handle, remote_handle));
+
+    drop(dp1);
+    assert!(!plugins
+      .get_plugins()
+      .has_crypto_state(local_handle, remote_handle));
+    drop(dp2);
+  }
+
+  #[cfg(feature = "security")]
+  #[test]
+  fn secure_participants_tear_down_cleanly() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use crate::{test::random_data::RandomData, QosPolicyBuilder};
+
+    // Count panics in the threads of the participants created here. Other
+    // panics go to the default hook as usual.
+    static TEARDOWN_PANICS: AtomicUsize = AtomicUsize::new(0);
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+      if std::thread::current()
+        .name()
+        .is_some_and(|name| name.contains("teardown-stress"))
+      {
+        TEARDOWN_PANICS.fetch_add(1, Ordering::SeqCst);
+      }
+      default_hook(info);
+    }));
+
+    let domain_id = 51;
+    let qos = QosPolicyBuilder::new().build();
+    // Drop at different stages of the authentication handshake and key
+    // exchange, while secure messages are in flight.
+    for round in 0..8 {
+      let participants: Vec<DomainParticipant> = (0..2)
+        .map(|i| {
+          let mut builder = super::DomainParticipantBuilder::new(domain_id);
+          builder
+            .add_builtin_security()
+            .instance_id(&format!("teardown-stress-{round}-{i}"));
+          builder.build().expect("Failed to create participant")
+        })
+        .collect();
+      let topic = participants[0]
+        .create_topic(
+          "TeardownTopic".to_string(),
+          "RandomData".to_string(),
+          &qos,
+          TopicKind::WithKey,
+        )
+        .unwrap();
+      let writer = participants[0]
+        .create_publisher(&qos)
+        .unwrap()
+        .create_datawriter_cdr::<RandomData>(&topic, None)
+        .unwrap();
+      for a in 0..(round * 5) {
+        writer
+          .write(
+            RandomData {
+              a,
+              b: "teardown".to_string(),
+            },
+            None,
+          )
+          .unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(20));
+      }
+      drop(writer);
+      drop(participants);
+    }
+
+    assert_eq!(TEARDOWN_PANICS.load(Ordering::SeqCst), 0);
+  }
+
+  #[cfg(feature = "security")]
+  #[test]
+  fn security_log_topic_reports_handshake() {
+    use crate::{
+      no_key::DataReader,
+      structure::entity::RTPSEntity,
+      security_logging::{
+        builtin_logging_topic_qos, msgid, BuiltinLoggingType, BUILTIN_LOGGING_TOPIC_NAME,
+        BUILTIN_LOGGING_TYPE_NAME,
+      },
+    };
+
+    let domain_id = 17;
+    let dp1 = DomainParticipant::new(domain_id).expect("Failed to create participant 1");
+    let qos = builtin_logging_topic_qos();
+    let topic = dp1
+      .create_topic(
+        BUILTIN_LOGGING_TOPIC_NAME.to_string(),
+        BUILTIN_LOGGING_TYPE_NAME.to_string(),
+        &qos,
+        TopicKind::NoKey,
+      )
+      .unwrap();
+    let mut log_reader: DataReader<BuiltinLoggingType> = dp1
+      .create_subscriber(&qos)
+      .unwrap()
+      .create_datareader_no_key_cdr(&topic, None)
+      .unwrap();
+
+    // Participant 2 authenticates with participant 1
+    let dp2 = DomainParticipant::new(domain_id).expect("Failed to create participant 2");
+    let dp2_guid = format!("{:?}", dp2.guid());
+
+    let mut handshake_completed = false;
+    wait_until(std::time::Duration::from_secs(10), || {
+      while let Ok(Some(sample)) = log_reader.take_next_sample() {
+        let log_message = sample.value();
+        if log_message.msgid == msgid::HANDSHAKE_COMPLETED
+          && log_message.structured_value("DDS", "remote_participant_guid") == Some(&dp2_guid)
+        {
+          handshake_completed = true;
+        }
+      }
+      handshake_completed
+    });
+    assert!(handshake_completed);
+  }
+
+  #[cfg(feature = "security")]
+  #[test]
+  fn shared_secret_fingerprints_match() {
+    use crate::structure::entity::RTPSEntity;
+
+    let domain_id = 30;
+    let dp1 = DomainParticipant::new(domain_id).expect("Failed to create participant 1");
+    let dp2 = DomainParticipant::new(domain_id).expect("Failed to create participant 2");
+    let prefix1 = dp1.guid().prefix;
+    let prefix2 = dp2.guid().prefix;
+
+    let mut fingerprints = None;
+    wait_until(std::time::Duration::from_secs(10), || {
+      if let (Ok(fp1), Ok(fp2)) = (
+        dp1.shared_secret_fingerprint(prefix2),
+        dp2.shared_secret_fingerprint(prefix1),
+      ) {
+        fingerprints = Some((fp1, fp2));
+      }
+      fingerprints.is_some()
+    });
+    let (fp1, fp2) = fingerprints.expect("Authentication did not complete");
+    assert_eq!(fp1, fp2);
+
+    // No session with an unknown participant
+    assert!(dp1
+      .shared_secret_fingerprint(GUID::new_participant_guid().prefix)
+      .is_err());
+  }
+
+  #[cfg(feature = "security")]
+  #[test]
+  fn security_log_topic_reports_protection_mismatch() {
+    use crate::{
+      no_key::{DataReader, DataWriter},
+      security_logging::{
+        builtin_logging_topic_qos, msgid, BuiltinLoggingType, BUILTIN_LOGGING_TOPIC_NAME,
+        BUILTIN_LOGGING_TYPE_NAME,
+      },
+      structure::entity::RTPSEntity,
+    };
+
+    let domain_id = 26;
+    // Participant 1 uses the permissive governance, where no topic is protected
+    let dp1 = DomainParticipant::new(domain_id).expect("Failed to create participant 1");
+    let log_qos = builtin_logging_topic_qos();
+    let log_topic = dp1
+      .create_topic(
+        BUILTIN_LOGGING_TOPIC_NAME.to_string(),
+        BUILTIN_LOGGING_TYPE_NAME.to_string(),
+        &log_qos,
+        TopicKind::NoKey,
+      )
+      .unwrap();
+    let mut log_reader: DataReader<BuiltinLoggingType> = dp1
+      .create_subscriber(&log_qos)
+      .unwrap()
+      .create_datareader_no_key_cdr(&log_topic, None)
+      .unwrap();
+
+    let qos = QosPolicies::qos_none();
+    let topic1 = dp1
+      .create_topic(
+        "ProtectedTopic".to_string(),
+        "i32".to_string(),
+        &qos,
+        TopicKind::NoKey,
+      )
+      .unwrap();
+    let _reader: DataReader<i32> = dp1
+      .create_subscriber(&qos)
+      .unwrap()
+      .create_datareader_no_key_cdr(&topic1, None)
+      .unwrap();
+
+    // Participant 2 uses a governance that encrypts the topic
+    let config_dir = std::path::Path::new("example_security_configuration_files");
+    let mut builder = super::DomainParticipantBuilder::new(domain_id);
+    builder
+      .security_from_files(
+        config_dir.join("identity_ca_certificate.pem"),
+        config_dir.join("participant1_certificate.pem"),
+        config_dir.join("participant1_private_key.pem"),
+        config_dir.join("permissions_ca_certificate.pem"),
+        config_dir.join("protected_topic_governance.p7s"),
+        config_dir.join("permissive_permissions.p7s"),
+      )
+      .expect("Security configuration from files failed");
+    let dp2 = builder.build().expect("Failed to create participant 2");
+    let topic2 = dp2
+      .create_topic(
+        "ProtectedTopic".to_string(),
+        "i32".to_string(),
+        &qos,
+        TopicKind::NoKey,
+      )
+      .unwrap();
+    let writer: DataWriter<i32> = dp2
+      .create_publisher(&qos)
+      .unwrap()
+      .create_datawriter_no_key_cdr(&topic2, None)
+      .unwrap();
+    let writer_guid = format!("{:?}", writer.guid());
+
+    let mut mismatch_reported = false;
+    wait_until(std::time::Duration::from_secs(10), || {
+      while let Ok(Some(sample)) = log_reader.take_next_sample() {
+        let log_message = sample.value();
+        if log_message.msgid == msgid::PROTECTION_MISMATCH
+          && log_message.message.contains(&writer_guid)
+        {
+          mismatch_reported = true;
+        }
+      }
+      mismatch_reported
+    });
+    assert!(mismatch_reported);
+  }
+
+  #[test]
+  fn coherent_set_is_delivered_all_or_nothing() {
+    use crate::{
+      dds::qos::policy::{Durability, History, Presentation, PresentationAccessScope, Reliability},
+      no_key::{DataReader, DataWriter},
+      QosPolicyBuilder, ReadCondition,
+    };
+
+    let domain_id = 24;
+    let dp1 = DomainParticipant::new(domain_id).expect("Failed to create participant 1");
+    let dp2 = DomainParticipant::new(domain_id).expect("Failed to create participant 2");
+
+    let qos = QosPolicyBuilder::new()
+      .reliability(Reliability::Reliable {
+        max_blocking_time: crate::Duration::from_secs(1),
+      })
+      .durability(Durability::TransientLocal)
+      .history(History::KeepAll)
+      .presentation(Presentation {
+        access_scope: PresentationAccessScope::Topic,
+        coherent_access: true,
+        ordered_access: false,
+      })
+      .build();
+    let topic1 = dp1
+      .create_topic(
+        "CoherentSetTopic".to_string(),
+        "i32".to_string(),
+        &qos,
+        TopicKind::NoKey,
+      )
+      .unwrap();
+    let topic2 = dp2
+      .create_topic(
+        "CoherentSetTopic".to_string(),
+        "i32".to_string(),
+        &qos,
+        TopicKind::NoKey,
+      )
+      .unwrap();
+    let publisher = dp1.create_publisher(&qos).unwrap();
+    let writer: DataWriter<i32> = publisher
+      .create_datawriter_no_key_cdr(&topic1, None)
+      .unwrap();
+    let mut reader: DataReader<i32> = dp2
+      .create_subscriber(&qos)
+      .unwrap()
+      .create_datareader_no_key_cdr(&topic2, None)
+      .unwrap();
+
+    // Make sure we are matched before starting the set
+    writer.write(0, None).unwrap();
+    let matched = wait_until(std::time::Duration::from_secs(15), || {
+      matches!(reader.take_next_sample(), Ok(Some(_)))
+    });
+    assert!(matched);
+
+    publisher.begin_coherent_changes().unwrap();
+    writer.write(1, None).unwrap();
+    writer.write(2, None).unwrap();
+
+    // Nothing is delivered while the set is open
+    std::thread::sleep(std::time::Duration::from_millis(1000));
+    assert!(reader.take(10, ReadCondition::any()).unwrap().is_empty());
+
+    publisher.end_coherent_changes().unwrap();
+
+    let mut received = Vec::new();
+    wait_until(std::time::Duration::from_secs(5), || {
+      // both or neither
+      received = reader
+        .take(10, ReadCondition::any())
+        .unwrap()
+        .into_iter()
+        .map(|s| s.into_value())
+        .collect();
+      !received.is_empty()
+    });
+    assert_eq!(received, vec![1, 2]);
+  }
+
+  #[test]
+  fn scan_finds_active_participant() {
+    use crate::structure::entity::RTPSEntity;
+
+    let domain_id = 33;
+    let dp = DomainParticipant::new(domain_id).expect("Failed to create participant");
+
+    // Participants announce themselves every 2 seconds
+    let found = DomainParticipant::scan(
+      domain_id - 1..domain_id + 1,
+      &[],
+      std::time::Duration::from_secs(3),
+    )
+    .unwrap();
+
+    let scanned = found
+      .iter()
+      .find(|s| s.participant.guid == dp.guid())
+      .expect("Scan did not find the participant");
+    assert_eq!(scanned.domain_id, domain_id);
+  }
+
+  #[test]
+  fn fixed_guid_prefix() {
+    use crate::structure::entity::RTPSEntity;
+
+    let domain_id = 34;
+    let prefix = super::GuidPrefix::new(b"FixedPrefix1");
+    let build = || {
+      let mut builder = super::DomainParticipantBuilder::new(domain_id);
+      builder.guid_prefix(prefix);
+      builder.build()
+    };
+    let dp = build().expect("Failed to create participant");
+    assert_eq!(dp.guid().prefix, prefix);
+
+    // The prefix is in use
+    assert!(matches!(
+      build(),
+      Err(super::CreateError::BadParameter { .. })
+    ));
+
+    // ... until the participant is dropped
+    drop(dp);
+    let dp = build().expect("Failed to create participant again");
+    assert_eq!(dp.guid().prefix, prefix);
+  }
+
+  #[test]
+  fn time_based_filter_throttles_delivery() {
+    use crate::{
+      dds::qos::policy::{History, Reliability, TimeBasedFilter},
+      test::random_data::RandomData,
+      QosPolicyBuilder, ReadCondition,
+    };
+
+    let domain_id = 35;
+    let dp1 = DomainParticipant::new(domain_id).expect("Failed to create participant 1");
+    let dp2 = DomainParticipant::new(domain_id).expect("Failed to create participant 2");
+
+    let qos = QosPolicyBuilder::new()
+      .reliability(Reliability::Reliable {
+        max_blocking_time: crate::Duration::from_secs(1),
+      })
+      .history(History::KeepAll)
+      .build();
+    let filtered_qos = qos.modify_by(
+      &QosPolicyBuilder::new()
+        .time_based_filter(TimeBasedFilter {
+          minimum_separation: crate::Duration::from_millis(100),
+        })
+        .build(),
+    );
+    let topic1 = dp1
+      .create_topic(
+        "TimeBasedFilterTopic".to_string(),
+        "RandomData".to_string(),
+        &qos,
+        TopicKind::WithKey,
+      )
+      .unwrap();
+    let topic2 = dp2
+      .create_topic(
+        "TimeBasedFilterTopic".to_string(),
+        "RandomData".to_string(),
+        &qos,
+        TopicKind::WithKey,
+      )
+      .unwrap();
+    let writer = dp1
+      .create_publisher(&qos)
+      .unwrap()
+      .create_datawriter_cdr::<RandomData>(&topic1, None)
+      .unwrap();
+    let subscriber = dp2.create_subscriber(&qos).unwrap();
+    let mut filtered_reader = subscriber
+      .create_datareader_cdr::<RandomData>(&topic2, Some(filtered_qos))
+      .unwrap();
+    let mut reader = subscriber
+      .create_datareader_cdr::<RandomData>(&topic2, None)
+      .unwrap();
+
+    // Make sure we are matched before measuring. This is another instance.
+    let sample = |a| RandomData {
+      a,
+      b: "filter".to_string(),
+    };
+    writer.write(sample(0), None).unwrap();
+    let matched = wait_until(std::time::Duration::from_secs(15), || {
+      matches!(filtered_reader.take_next_sample(), Ok(Some(_)))
+    });
+    assert!(matched);
+    reader.take(10, ReadCondition::any()).unwrap();
+
+    // 100 Hz for one second
+    for _ in 0..100 {
+      writer.write(sample(1), None).unwrap();
+      std::thread::sleep(std::time::Duration::from_millis(10));
+    }
+    std::thread::sleep(std::time::Duration::from_millis(500));
+
+    let unfiltered_count = reader.take(1000, ReadCondition::any()).unwrap().len();
+    let filtered_count = filtered_reader
+      .take(1000, ReadCondition::any())
+      .unwrap()
+      .len();
+    assert_eq!(unfiltered_count, 100);
+    // About one sample per 100 ms
+    assert!(
+      (5..=20).contains(&filtered_count),
+      "{filtered_count} samples passed the filter"
+    );
+  }
+
+  #[test]
+  fn lifespan_expires_stale_samples() {
+    use crate::{
+      dds::qos::policy::{History, Lifespan, Reliability},
+      test::random_data::RandomData,
+      QosPolicyBuilder,
+    };
+
+    let domain_id = 36;
+    let dp1 = DomainParticipant::new(domain_id).expect("Failed to create participant 1");
+    let dp2 = DomainParticipant::new(domain_id).expect("Failed to create participant 2");
+
+    let qos = QosPolicyBuilder::new()
+      .reliability(Reliability::Reliable {
+        max_blocking_time: crate::Duration::from_secs(1),
+      })
+      .history(History::KeepAll)
+      .lifespan(Lifespan {
+        duration: crate::Duration::from_millis(200),
+      })
+      .build();
+    let topic1 = dp1
+      .create_topic(
+        "LifespanTopic".to_string(),
+        "RandomData".to_string(),
+        &qos,
+        TopicKind::WithKey,
+      )
+      .unwrap();
+    let topic2 = dp2
+      .create_topic(
+        "LifespanTopic".to_string(),
+        "RandomData".to_string(),
+        &qos,
+        TopicKind::WithKey,
+      )
+      .unwrap();
+    let writer = dp1
+      .create_publisher(&qos)
+      .unwrap()
+      .create_datawriter_cdr::<RandomData>(&topic1, None)
+      .unwrap();
+    let mut reader = dp2
+      .create_subscriber(&qos)
+      .unwrap()
+      .create_datareader_cdr::<RandomData>(&topic2, None)
+      .unwrap();
+
+    let sample = |a| RandomData {
+      a,
+      b: "lifespan".to_string(),
+    };
+    // Wait until matched. Samples may expire before that, so keep writing.
+    let matched = wait_until(std::time::Duration::from_secs(10), || {
+      writer.write(sample(0), None).unwrap();
+      std::thread::sleep(std::time::Duration::from_millis(50));
+      matches!(reader.take_next_sample(), Ok(Some(_)))
+    });
+    assert!(matched);
+    while let Ok(Some(_)) = reader.take_next_sample() {}
+
+    // A slow reader does not get the sample anymore
+    writer.write(sample(1), None).unwrap();
+    std::thread::sleep(std::time::Duration::from_millis(300));
+    assert!(reader.take_next_sample().unwrap().is_none());
+
+    // A fresh one is delivered
+    writer.write(sample(2), None).unwrap();
+    let mut received = None;
+    wait_until(std::time::Duration::from_millis(150), || {
+      received = reader
+        .take_next_sample()
+        .unwrap()
+        .and_then(|s| s.into_value().value());
+      received.is_some()
+    });
+    assert_eq!(received, Some(sample(2)));
+  }
+
+  #[test]
+  fn late_joiner_gets_spilled_samples() {
+    use crate::{
+      dds::{
+        qos::policy::{Durability, History, Reliability},
+        sample_store::FileSampleStore,
+      },
+      test::random_data::RandomData,
+      CDRSerializerAdapter, QosPolicyBuilder, ReadCondition,
+    };
+
+    let domain_id = 37;
+    let dp1 = DomainParticipant::new(domain_id).expect("Failed to create participant 1");
+    let dp2 = DomainParticipant::new(domain_id).expect("Failed to create participant 2");
+
+    let qos = QosPolicyBuilder::new()
+      .reliability(Reliability::Reliable {
+        max_blocking_time: crate::Duration::from_secs(1),
+      })
+      .durability(Durability::TransientLocal)
+      .history(History::KeepLast { depth: 20 })
+      .build();
+    let create_topic = |dp: &DomainParticipant| {
+      dp.create_topic(
+        "SampleStoreTopic".to_string(),
+        "RandomData".to_string(),
+        &qos,
+        TopicKind::WithKey,
+      )
+      .unwrap()
+    };
+    let store_path =
+      std::env::temp_dir().join(format!("rustdds_spill_test_{}", std::process::id()));
+    let writer = dp1
+      .create_publisher(&qos)
+      .unwrap()
+      .create_datawriter_with_sample_store::<RandomData, CDRSerializerAdapter<RandomData>>(
+        &create_topic(&dp1),
+        None,
+        Box::new(FileSampleStore::create(&store_path).unwrap()),
+      )
+      .unwrap();
+    let mut reader = dp2
+      .create_subscriber(&qos)
+      .unwrap()
+      .create_datareader_cdr::<RandomData>(&create_topic(&dp2), None)
+      .unwrap();
+
+    let sample = |a| RandomData {
+      a,
+      b: "spilled".to_string(),
+    };
+    for a in 0..20 {
+      writer.write(sample(a), None).unwrap();
+    }
+    let mut received = 0;
+    wait_until(std::time::Duration::from_secs(10), || {
+      received += reader.take(100, ReadCondition::any()).unwrap().len();
+      received == 20
+    });
+    assert_eq!(received, 20);
+
+    // Once acknowledged, the samples are spilled to the file.
+    let spilled = wait_until(std::time::Duration::from_secs(10), || {
+      std::fs::metadata(&store_path).map_or(0, |m| m.len()) > 0
+    });
+    assert!(spilled);
+
+    // A late joiner gets the history, now partly or wholly from the file
+    let dp3 = DomainParticipant::new(domain_id).expect("Failed to create participant 3");
+    let mut late_reader = dp3
+      .create_subscriber(&qos)
+      .unwrap()
+      .create_datareader_cdr::<RandomData>(&create_topic(&dp3), None)
+      .unwrap();
+    let mut late_received = Vec::new();
+    wait_until(std::time::Duration::from_secs(10), || {
+      late_received.extend(
+        late_reader
+          .take(100, ReadCondition::any())
+          .unwrap()
+          .into_iter()
+          .filter_map(|s| s.into_value().value()),
+      );
+      late_received.len() == 20
+    });
+    late_received.sort_by_key(|d| d.a);
+    assert_eq!(late_received, (0..20).map(sample).collect::<Vec<_>>());
+  }
+
+  #[test]
+  fn instance_state_follows_dispose_and_writer_loss() {
+    use crate::{
+      dds::qos::policy::{History, Reliability},
+      test::random_data::RandomData,
+      with_key::Sample,
+      InstanceState, QosPolicyBuilder, ViewState,
+    };
+
+    let domain_id = 38;
+    let dp1 = DomainParticipant::new(domain_id).expect("Failed to create participant 1");
+    let dp2 = DomainParticipant::new(domain_id).expect("Failed to create participant 2");
+
+    let qos = QosPolicyBuilder::new()
+      .reliability(Reliability::Reliable {
+        max_blocking_time: crate::Duration::from_secs(1),
+      })
+      .history(History::KeepAll)
+      .build();
+    let topic1 = dp1
+      .create_topic(
+        "InstanceStateTopic".to_string(),
+        "RandomData".to_string(),
+        &qos,
+        TopicKind::WithKey,
+      )
+      .unwrap();
+    let topic2 = dp2
+      .create_topic(
+        "InstanceStateTopic".to_string(),
+        "RandomData".to_string(),
+        &qos,
+        TopicKind::WithKey,
+      )
+      .unwrap();
+    let writer = dp1
+      .create_publisher(&qos)
+      .unwrap()
+      .create_datawriter_cdr::<RandomData>(&topic1, None)
+      .unwrap();
+    let mut reader = dp2
+      .create_subscriber(&qos)
+      .unwrap()
+      .create_datareader_cdr::<RandomData>(&topic2, None)
+      .unwrap();
+
+    let sample = |a| RandomData {
+      a,
+      b: "instance".to_string(),
+    };
+    // Instance 0 is used for matching only. Skip it.
+    let take_next = |reader: &mut crate::with_key::DataReaderCdr<RandomData>| {
+      let mut waits = 0;
+      while waits < 100 {
+        match reader.take_next_sample().unwrap() {
+          Some(s) if s.key() != 0 => return Some(s),
+          Some(_) => (),
+          None => {
+            waits += 1;
+            std::thread::sleep(std::time::Duration::from_millis(50));
+          }
+        }
+      }
+      None
+    };
+
+    // Wait until matched
+    let matched = wait_until(std::time::Duration::from_secs(10), || {
+      writer.write(sample(0), None).unwrap();
+      std::thread::sleep(std::time::Duration::from_millis(50));
+      matches!(reader.take_next_sample(), Ok(Some(_)))
+    });
+    assert!(matched);
+
+    writer.write(sample(1), None).unwrap();
+    let s = take_next(&mut reader).unwrap();
+    assert_eq!(s.sample_info().instance_state(), InstanceState::Alive);
+    assert_eq!(s.sample_info().view_state(), ViewState::New);
+
+    writer.write(sample(1), None).unwrap();
+    let s = take_next(&mut reader).unwrap();
+    assert_eq!(s.sample_info().instance_state(), InstanceState::Alive);
+    assert_eq!(s.sample_info().view_state(), ViewState::NotNew);
+
+    writer.dispose(&1, None).unwrap();
+    let s = take_next(&mut reader).unwrap();
+    assert_eq!(
+      s.sample_info().instance_state(),
+      InstanceState::NotAliveDisposed
+    );
+    assert_eq!(s.into_value(), Sample::Dispose(1));
+
+    writer.write(sample(2), None).unwrap();
+    let s = take_next(&mut reader).unwrap();
+    assert_eq!(s.sample_info().instance_state(), InstanceState::Alive);
+
+    // Losing the only Writer makes instance 2 NotAliveNoWriters. The disposed
+    // instance 1 stays disposed.
+    drop(writer);
+    let s = take_next(&mut reader).expect("No instance state change");
+    assert_eq!(s.key(), 2);
+    assert_eq!(
+      s.sample_info().instance_state(),
+      InstanceState::NotAliveNoWriters
+    );
+    assert_eq!(s.into_value(), Sample::Dispose(2));
+    while let Some(s) = reader.take_next_sample().unwrap() {
+      assert_eq!(s.key(), 0);
+    }
+  }
+
+  #[test]
+  fn dispose_of_unwritten_instance_reaches_reader() {
+    use crate::{
+      dds::qos::policy::{History, Reliability},
+      test::random_data::RandomData,
+      with_key::Sample,
+      InstanceState, QosPolicyBuilder,
+    };
+
+    let domain_id = 57;
+    let dp1 = DomainParticipant::new(domain_id).expect("Failed to create participant 1");
+    let dp2 = DomainParticipant::new(domain_id).expect("Failed to create participant 2");
+
+    let qos = QosPolicyBuilder::new()
+      .reliability(Reliability::Reliable {
+        max_blocking_time: crate::Duration::from_secs(1),
+      })
+      .history(History::KeepAll)
+      .build();
+    let topic1 = dp1
+      .create_topic(
+        "DisposeUnknownTopic".to_string(),
+        "RandomData".to_string(),
+        &qos,
+        TopicKind::WithKey,
+      )
+      .unwrap();
+    let topic2 = dp2
+      .create_topic(
+        "DisposeUnknownTopic".to_string(),
+        "RandomData".to_string(),
+        &qos,
+        TopicKind::WithKey,
+      )
+      .unwrap();
+    let writer = dp1
+      .create_publisher(&qos)
+      .unwrap()
+      .create_datawriter_cdr::<RandomData>(&topic1, None)
+      .unwrap();
+    let mut reader = dp2
+      .create_subscriber(&qos)
+      .unwrap()
+      .create_datareader_cdr::<RandomData>(&topic2, None)
+      .unwrap();
+
+    // Wait until matched, using instance 0
+    let matched = wait_until(std::time::Duration::from_secs(10), || {
+      writer
+        .write(
+          RandomData {
+            a: 0,
+            b: "match".to_string(),
+          },
+          None,
+        )
+        .unwrap();
+      std::thread::sleep(std::time::Duration::from_millis(50));
+      matches!(reader.take_next_sample(), Ok(Some(_)))
+    });
+    assert!(matched);
+
+    // Instance 42 has never been written, neither reader nor writer knows it.
+    writer.dispose(&42, None).unwrap();
+    // Samples of instance 0 written before matching may still be queued
+    let mut disposed = None;
+    wait_until(std::time::Duration::from_secs(10), || {
+      while let Some(s) = reader.take_next_sample().unwrap() {
+        if s.key() == 42 {
+          disposed = Some(s);
+          return true;
+        }
+      }
+      false
+    });
+    let s = disposed.expect("Dispose was not received");
+    assert_eq!(
+      s.sample_info().instance_state(),
+      InstanceState::NotAliveDisposed
+    );
+    assert_eq!(s.into_value(), Sample::Dispose(42));
+  }
+
+  #[test]
+  fn ignored_participant_writer_never_matches() {
+    use crate::{
+      dds::qos::policy::{History, Reliability},
+      structure::entity::RTPSEntity,
+      test::random_data::RandomData,
+      QosPolicyBuilder,
+    };
+
+    let domain_id = 40;
+    let dp = DomainParticipant::new(domain_id).expect("Failed to create participant");
+    let ignored_dp = DomainParticipant::new(domain_id).expect("Failed to create participant");
+    dp.ignore_participant(ignored_dp.guid().prefix).unwrap();
+    assert!(dp.ignore_participant(dp.guid().prefix).is_err());
+    let other_dp = DomainParticipant::new(domain_id).expect("Failed to create participant");
+
+    let qos = QosPolicyBuilder::new()
+      .reliability(Reliability::Reliable {
+        max_blocking_time: crate::Duration::from_secs(1),
+      })
+      .history(History::KeepAll)
+      .build();
+    let create_topic = |dp: &DomainParticipant| {
+      dp.create_topic(
+        "IgnoreTopic".to_string(),
+        "RandomData".to_string(),
+        &qos,
+        TopicKind::WithKey,
+      )
+      .unwrap()
+    };
+    let create_writer = |dp: &DomainParticipant| {
+      dp.create_publisher(&qos)
+        .unwrap()
+        .create_datawriter_cdr::<RandomData>(&create_topic(dp), None)
+        .unwrap()
+    };
+    let ignored_writer = create_writer(&ignored_dp);
+    let other_writer = create_writer(&other_dp);
+    let mut reader = dp
+      .create_subscriber(&qos)
+      .unwrap()
+      .create_datareader_cdr::<RandomData>(&create_topic(&dp), None)
+      .unwrap();
+
+    // Write from both until the other one has matched, and then a while more.
+    let sample = |a| RandomData {
+      a,
+      b: "ignore".to_string(),
+    };
+    let mut received = Vec::new();
+    let mut rounds_after_match = 0;
+    for _ in 0..200 {
+      ignored_writer.write(sample(1), None).unwrap();
+      other_writer.write(sample(2), None).unwrap();
+      std::thread::sleep(std::time::Duration::from_millis(50));
+      while let Ok(Some(s)) = reader.take_next_sample() {
+        received.push(s.key());
+      }
+      if !received.is_empty() {
+        rounds_after_match += 1;
+        if rounds_after_match == 20 {
+          break;
+        }
+      }
+    }
+    assert!(received.contains(&2));
+    assert!(!received.contains(&1));
+  }
+
+  // Backend participants are ignored with security, see DiscoveryBackend.
+  #[cfg(not(feature = "security"))]
+  #[test]
+  fn discovery_backend_seeds_peers() {
+    use std::{
+      sync::{Arc, Mutex},
+      time::Duration,
+    };
+
+    use crate::{
+      dds::qos::policy::{History, Reliability},
+      discovery::{DiscoveryBackend, SpdpDiscoveredParticipantData},
+      structure::entity::RTPSEntity,
+      test::random_data::RandomData,
+      QosPolicyBuilder,
+    };
+
+    // Hands out whatever has been queued by the test.
+    struct MockBackend(Arc<Mutex<Vec<SpdpDiscoveredParticipantData>>>);
+
+    impl DiscoveryBackend for MockBackend {
+      fn poll_participants(&mut self) -> Vec<SpdpDiscoveredParticipantData> {
+        self.0.lock().unwrap().drain(..).collect()
+      }
+
+      fn poll_period(&self) -> Duration {
+        Duration::from_millis(50)
+      }
+    }
+
+    // Different domains do not see each other in SPDP, so the participants
+    // can only find each other through the backends.
+    let peers_1 = Arc::new(Mutex::new(Vec::new()));
+    let peers_2 = Arc::new(Mutex::new(Vec::new()));
+    let build = |domain_id, peers: &Arc<Mutex<_>>| {
+      let mut builder = super::DomainParticipantBuilder::new(domain_id);
+      builder.discovery_backend(Box::new(MockBackend(peers.clone())));
+      builder.build().unwrap()
+    };
+    let dp_1 = build(44, &peers_1);
+    let dp_2 = build(45, &peers_2);
+    let participant_data = |dp: &DomainParticipant| {
+      SpdpDiscoveredParticipantData::from_local_participant(
+        dp,
+        &dp.self_locators(),
+        &None,
+        crate::Duration::from_secs(60),
+      )
+    };
+    peers_1.lock().unwrap().push(participant_data(&dp_2));
+    peers_2.lock().unwrap().push(participant_data(&dp_1));
+
+    let qos = QosPolicyBuilder::new()
+      .reliability(Reliability::Reliable {
+        max_blocking_time: crate::Duration::from_secs(1),
+      })
+      .history(History::KeepAll)
+      .build();
+    let create_topic = |dp: &DomainParticipant| {
+      dp.create_topic(
+        "BackendTopic".to_string(),
+        "RandomData".to_string(),
+        &qos,
+        TopicKind::WithKey,
+      )
+      .unwrap()
+    };
+    let writer = dp_1
+      .create_publisher(&qos)
+      .unwrap()
+      .create_datawriter_cdr::<RandomData>(&create_topic(&dp_1), None)
+      .unwrap();
+    let mut reader = dp_2
+      .create_subscriber(&qos)
+      .unwrap()
+      .create_datareader_cdr::<RandomData>(&create_topic(&dp_2), None)
+      .unwrap();
+
+    let mut received = None;
+    wait_until(Duration::from_secs(10), || {
+      writer
+        .write(
+          RandomData {
+            a: 1,
+            b: "backend".to_string(),
+          },
+          None,
+        )
+        .unwrap();
+      std::thread::sleep(Duration::from_millis(50));
+      received = reader.take_next_sample().ok().flatten().map(|s| s.key());
+      received.is_some()
+    });
+    assert_eq!(received, Some(1));
+    assert!(dp_2
+      .discovered_participants()
+      .iter()
+      .any(|p| p.guid == dp_1.guid()));
+  }
+
+  #[test]
+  fn wait_for_writer_returns_late_writer() {
+    use std::time::{Duration, Instant};
+
+    use crate::{structure::entity::RTPSEntity, test::random_data::RandomData};
+
+    let domain_id = 46;
+    let dp = DomainParticipant::new(domain_id).expect("Failed to create participant");
+    assert!(dp
+      .wait_for_writer("LateTopic", Duration::ZERO)
+      .unwrap()
+      .is_none());
+
+    let publishing = std::thread::spawn(move || {
+      std::thread::sleep(Duration::from_millis(500));
+      let other_dp = DomainParticipant::new(domain_id).expect("Failed to create participant");
+      let qos = QosPolicies::qos_none();
+      let topic = other_dp
+        .create_topic(
+          "LateTopic".to_string(),
+          "RandomData".to_string(),
+          &qos,
+          TopicKind::WithKey,
+        )
+        .unwrap();
+      let writer = other_dp
+        .create_publisher(&qos)
+        .unwrap()
+        .create_datawriter_cdr::<RandomData>(&topic, None)
+        .unwrap();
+      (other_dp, writer)
+    });
+
+    let found = dp
+      .wait_for_writer("LateTopic", Duration::from_secs(20))
+      .unwrap()
+      .expect("No writer found");
+    let (other_dp, writer) = publishing.join().unwrap();
+    assert_eq!(found.writer_proxy.remote_writer_guid, writer.guid());
+    assert_eq!(
+      found.writer_proxy.remote_writer_guid.prefix,
+      other_dp.guid().prefix
+    );
+
+    // Already known, so there is no waiting.
+    let start = Instant::now();
+    assert!(dp
+      .wait_for_writer("LateTopic", Duration::from_secs(20))
+      .unwrap()
+      .is_some());
+    assert!(start.elapsed() < Duration::from_secs(1));
+  }
+
+  #[test]
+  fn message_tap_sees_spdp_and_user_data() {
+    use std::{
+      sync::{Arc, Mutex},
+      time::Duration,
+    };
+
+    use bytes::Bytes;
+
+    use crate::{
+      dds::qos::{policy::Reliability, QosPolicyBuilder},
+      structure::entity::RTPSEntity,
+      MessageDirection,
+    };
+
+    // Writer entity ids of DATA submessages seen by the tap
+    let tapped = Arc::new(Mutex::new(Vec::new()));
+    let tapped_clone = Arc::clone(&tapped);
+    let tap = move |direction: MessageDirection, _peer: SocketAddr, message: &[u8]| {
+      if let Ok(message) = Message::read_from_buffer(&Bytes::copy_from_slice(message)) {
+        for submessage in message.submessages {
+          if let SubmessageBody::Writer(WriterSubmessage::Data(data, _)) = submessage.body {
+            tapped_clone
+              .lock()
+              .unwrap()
+              .push((direction, data.writer_id));
+          }
+        }
+      }
+    };
+
+    let domain_id = 48;
+    let mut builder = super::DomainParticipantBuilder::new(domain_id);
+    builder.message_tap(Arc::new(tap));
+    let dp1 = builder.build().expect("Failed to create participant 1");
+    let dp2 = DomainParticipant::new(domain_id).expect("Failed to create participant 2");
+
+    let qos = QosPolicyBuilder::new()
+      .reliability(Reliability::Reliable {
+        max_blocking_time: crate::Duration::from_secs(1),
+      })
+      .build();
+    let topic1 = dp1
+      .create_topic(
+        "TapTopic".to_string(),
+        "RandomData".to_string(),
+        &qos,
+        TopicKind::WithKey,
+      )
+      .unwrap();
+    let topic2 = dp2
+      .create_topic(
+        "TapTopic".to_string(),
+        "RandomData".to_string(),
+        &qos,
+        TopicKind::WithKey,
+      )
+      .unwrap();
+    let writer = dp1
+      .create_publisher(&qos)
+      .unwrap()
+      .create_datawriter_cdr::<RandomData>(&topic1, None)
+      .unwrap();
+    let mut reader = dp2
+      .create_subscriber(&qos)
+      .unwrap()
+      .create_datareader_cdr::<RandomData>(&topic2, None)
+      .unwrap();
+
+    let received = wait_until(Duration::from_secs(20), || {
+      writer
+        .write(
+          RandomData {
+            a: 1,
+            b: "tapped".to_string(),
+          },
+          None,
+        )
+        .unwrap();
+      std::thread::sleep(Duration::from_millis(200));
+      reader.take_next_sample().unwrap().is_some()
+    });
+    assert!(received, "No data received");
+
+    let tapped = tapped.lock().unwrap();
+    assert!(tapped.contains(&(
+      MessageDirection::Outbound,
+      EntityId::SPDP_BUILTIN_PARTICIPANT_WRITER
+    )));
+    assert!(tapped.contains(&(
+      MessageDirection::Inbound,
+      EntityId::SPDP_BUILTIN_PARTICIPANT_WRITER
+    )));
+    assert!(tapped.contains(&(MessageDirection::Outbound, writer.guid().entity_id)));
+  }
+
+  #[test]
+  fn metrics_text_is_prometheus_format() {
+    use std::time::Duration;
+
+    use crate::{
+      dds::qos::{policy::Reliability, QosPolicyBuilder},
+      test::random_data::RandomData,
+    };
+
+    let domain_id = 67;
+    let dp1 = DomainParticipant::new(domain_id).expect("Failed to create participant 1");
+    let dp2 = DomainParticipant::new(domain_id).expect("Failed to create participant 2");
+    let qos = QosPolicyBuilder::new()
+      .reliability(Reliability::Reliable {
+        max_blocking_time: crate::Duration::from_secs(1),
+      })
+      .build();
+    let topic1 = dp1
+      .create_topic(
+        "MetricsTopic".to_string(),
+        "RandomData".to_string(),
+        &qos,
+        TopicKind::WithKey,
+      )
+      .unwrap();
+    let topic2 = dp2
+      .create_topic(
+        "MetricsTopic".to_string(),
+        "RandomData".to_string(),
+        &qos,
+        TopicKind::WithKey,
+      )
+      .unwrap();
+    let writer = dp1
+      .create_publisher(&qos)
+      .unwrap()
+      .create_datawriter_cdr::<RandomData>(&topic1, None)
+      .unwrap();
+    let mut reader = dp2
+      .create_subscriber(&qos)
+      .unwrap()
+      .create_datareader_cdr::<RandomData>(&topic2, None)
+      .unwrap();
+
+    let received = wait_until(Duration::from_secs(20), || {
+      writer
+        .write(
+          RandomData {
+            a: 1,
+            b: "measured".to_string(),
+          },
+          None,
+        )
+        .unwrap();
+      std::thread::sleep(Duration::from_millis(200));
+      reader.take_next_sample().unwrap().is_some()
+    });
+    assert!(received, "No data received");
+
+    let metrics = dp1.metrics_text();
+    let is_name = |name: &str| {
+      name.starts_with("rustdds_")
+        && name
+          .chars()
+          .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == ':')
+    };
+    let mut samples = Vec::new();
+    for line in metrics.lines() {
+      if let Some(comment) = line.strip_prefix("# ") {
+        let mut parts = comment.splitn(3, ' ');
+        let keyword = parts.next().unwrap();
+        assert!(keyword == "HELP" || keyword == "TYPE", "Bad line: {line}");
+        assert!(is_name(parts.next().unwrap()), "Bad line: {line}");
+        if keyword == "TYPE" {
+          assert!(
+            matches!(parts.next(), Some("counter" | "gauge")),
+            "Bad line: {line}"
+          );
+        }
+        continue;
+      }
+      let (series, value) = line.rsplit_once(' ').expect("No value");
+      let value: f64 = value.parse().expect("Value is not a number");
+      let name = match series.split_once('{') {
+        Some((name, labels)) => {
+          let labels = labels.strip_suffix('}').expect("Unterminated labels");
+          for label in labels.split("\",") {
+            let (label_name, label_value) = label.split_once("=\"").expect("Bad label");
+            assert!(
+              is_name(&format!("rustdds_{label_name}")),
+              "Bad line: {line}"
+            );
+            assert!(!label_value.contains('\n'), "Bad line: {line}");
+          }
+          name
+        }
+        None => series,
+      };
+      assert!(is_name(name), "Bad line: {line}");
+      samples.push((series.to_string(), value));
+    }
+
+    let value_of = |series: &str| {
+      samples
+        .iter()
+        .find(|(s, _)| s == series)
+        .map(|(_, value)| *value)
+    };
+    assert!(value_of("rustdds_messages_sent_total").unwrap() > 0.0);
+    assert!(value_of("rustdds_bytes_received_total").unwrap() > 0.0);
+    assert_eq!(
+      value_of(r#"rustdds_endpoints{topic="MetricsTopic",kind="local_writer"}"#),
+      Some(1.0)
+    );
+  }
+
+  #[test]
+  fn unregister_instance_autodisposes() {
+    use crate::{
+      dds::qos::policy::{History, Reliability, WriterDataLifecycle},
+      test::random_data::RandomData,
+      with_key::Sample,
+      InstanceState, QosPolicyBuilder,
+    };
+
+    let domain_id = 49;
+    let dp1 = DomainParticipant::new(domain_id).expect("Failed to create participant 1");
+    let dp2 = DomainParticipant::new(domain_id).expect("Failed to create participant 2");
+
+    let qos = QosPolicyBuilder::new()
+      .reliability(Reliability::Reliable {
+        max_blocking_time: crate::Duration::from_secs(1),
+      })
+      .history(History::KeepAll)
+      .build();
+    let writer_qos = qos.modify_by(
+      &QosPolicyBuilder::new()
+        .writer_data_lifecycle(WriterDataLifecycle {
+          autodispose_unregistered_instances: true,
+        })
+        .build(),
+    );
+    let topic1 = dp1
+      .create_topic(
+        "UnregisterTopic".to_string(),
+        "RandomData".to_string(),
+        &qos,
+        TopicKind::WithKey,
+      )
+      .unwrap();
+    let topic2 = dp2
+      .create_topic(
+        "UnregisterTopic".to_string(),
+        "RandomData".to_string(),
+        &qos,
+        TopicKind::WithKey,
+      )
+      .unwrap();
+    let writer = dp1
+      .create_publisher(&qos)
+      .unwrap()
+      .create_datawriter_cdr::<RandomData>(&topic1, Some(writer_qos))
+      .unwrap();
+    let mut reader = dp2
+      .create_subscriber(&qos)
+      .unwrap()
+      .create_datareader_cdr::<RandomData>(&topic2, None)
+      .unwrap();
+
+    let sample = |a| RandomData {
+      a,
+      b: "instance".to_string(),
+    };
+    // Instance 0 is used for matching only. Skip it.
+    let take_next = |reader: &mut crate::with_key::DataReaderCdr<RandomData>| {
+      let mut waits = 0;
+      while waits < 100 {
+        match reader.take_next_sample().unwrap() {
+          Some(s) if s.key() != 0 => return Some(s),
+          Some(_) => (),
+          None => {
+            waits += 1;
+            std::thread::sleep(std::time::Duration::from_millis(50));
+          }
+        }
+      }
+      None
+    };
+
+    // Wait until matched
+    let matched = wait_until(std::time::Duration::from_secs(10), || {
+      writer.write(sample(0), None).unwrap();
+      std::thread::sleep(std::time::Duration::from_millis(50));
+      matches!(reader.take_next_sample(), Ok(Some(_)))
+    });
+    assert!(matched);
+
+    writer.write(sample(1), None).unwrap();
+    let s = take_next(&mut reader).unwrap();
+    assert_eq!(s.sample_info().instance_state(), InstanceState::Alive);
+
+    // The writer is still alive, but the instance is disposed, not just
+    // left without writers.
+    writer.unregister_instance(&1, None).unwrap();
+    let s = take_next(&mut reader).expect("No dispose");
+    assert_eq!(
+      s.sample_info().instance_state(),
+      InstanceState::NotAliveDisposed
+    );
+    assert_eq!(s.into_value(), Sample::Dispose(1));
+    // The unregister that follows does not undo the dispose.
+    let s = take_next(&mut reader).expect("No unregister");
+    assert_eq!(
+      s.sample_info().instance_state(),
+      InstanceState::NotAliveDisposed
+    );
+  }
+
+  #[test]
+  fn disposed_instance_is_autopurged() {
+    use crate::{
+      dds::{
+        qos::policy::{History, ReaderDataLifecycle, Reliability},
+        with_key::datareader::SelectByKey,
+      },
+      test::random_data::RandomData,
+      InstanceState, QosPolicyBuilder, ReadCondition,
+    };
+
+    let domain_id = 50;
+    let dp1 = DomainParticipant::new(domain_id).expect("Failed to create participant 1");
+    let dp2 = DomainParticipant::new(domain_id).expect("Failed to create participant 2");
+
+    let qos = QosPolicyBuilder::new()
+      .reliability(Reliability::Reliable {
+        max_blocking_time: crate::Duration::from_secs(1),
+      })
+      .history(History::KeepAll)
+      .build();
+    let reader_qos = qos.modify_by(
+      &QosPolicyBuilder::new()
+        .reader_data_lifecycle(ReaderDataLifecycle {
+          autopurge_nowriter_samples_delay: crate::Duration::DURATION_INFINITE,
+          autopurge_disposed_samples_delay: crate::Duration::from_millis(300),
+        })
+        .build(),
+    );
+    let topic1 = dp1
+      .create_topic(
+        "AutopurgeTopic".to_string(),
+        "RandomData".to_string(),
+        &qos,
+        TopicKind::WithKey,
+      )
+      .unwrap();
+    let topic2 = dp2
+      .create_topic(
+        "AutopurgeTopic".to_string(),
+        "RandomData".to_string(),
+        &qos,
+        TopicKind::WithKey,
+      )
+      .unwrap();
+    let writer = dp1
+      .create_publisher(&qos)
+      .unwrap()
+      .create_datawriter_cdr::<RandomData>(&topic1, None)
+      .unwrap();
+    let mut reader = dp2
+      .create_subscriber(&qos)
+      .unwrap()
+      .create_datareader_cdr::<RandomData>(&topic2, Some(reader_qos))
+      .unwrap();
+
+    let sample = |a| RandomData {
+      a,
+      b: "instance".to_string(),
+    };
+
+    // Wait until matched. Instance 0 stays alive, so it is never purged.
+    let matched = wait_until(std::time::Duration::from_secs(10), || {
+      writer.write(sample(0), None).unwrap();
+      std::thread::sleep(std::time::Duration::from_millis(50));
+      !reader.read(1, ReadCondition::any()).unwrap().is_empty()
+    });
+    assert!(matched);
+
+    writer.write(sample(1), None).unwrap();
+    writer.dispose(&1, None).unwrap();
+    // Read, but do not take, until the dispose has arrived.
+    let disposed = wait_until(std::time::Duration::from_secs(5), || {
+      reader
+        .read_instance(10, ReadCondition::any(), Some(1), SelectByKey::This)
+        .unwrap()
+        .iter()
+        .any(|s| s.sample_info().instance_state() == InstanceState::NotAliveDisposed)
+    });
+    assert!(disposed);
+
+    std::thread::sleep(std::time::Duration::from_millis(500));
+    assert!(reader
+      .read_instance(10, ReadCondition::any(), Some(1), SelectByKey::This)
+      .unwrap()
+      .is_empty());
+    // Alive instances are kept.
+    assert!(!reader
+      .read_instance(10, ReadCondition::any(), Some(0), SelectByKey::This)
+      .unwrap()
+      .is_empty());
+  }
+
+  #[test]
+  fn assert_liveliness_repeatedly() {
+    let dp = DomainParticipant::new(58).expect("Failed to create participant");
+    assert!(dp.assert_liveliness().is_ok());
+    assert!(dp.assert_liveliness().is_ok());
+    // The handle is still usable afterwards.
+    assert!(dp.clone().assert_liveliness().is_ok());
+    assert_eq!(dp.domain_id(), 58);
+  }
+
+  #[test]
+  fn manual_by_topic_liveliness_is_lost_and_regained() {
+    use std::time::{Duration, Instant};
+
+    use crate::{
+      dds::{
+        qos::policy::{Liveliness, Reliability},
+        statusevents::{DataReaderStatus, StatusEvented},
+      },
+      no_key::{DataReader, DataWriter},
+      QosPolicyBuilder,
+    };
+
+    let domain_id = 59;
+    let dp1 = DomainParticipant::new(domain_id).expect("Failed to create participant 1");
+    let dp2 = DomainParticipant::new(domain_id).expect("Failed to create participant 2");
+
+    let qos = QosPolicyBuilder::new()
+      .reliability(Reliability::Reliable {
+        max_blocking_time: crate::Duration::from_millis(100),
+      })
+      .liveliness(Liveliness::ManualByTopic {
+        lease_duration: crate::Duration::from_millis(500),
+      })
+      .build();
+    let topic1 = dp1
+      .create_topic(
+        "ManualByTopicLiveliness".to_string(),
+        "i32".to_string(),
+        &qos,
+        TopicKind::NoKey,
+      )
+      .unwrap();
+    let topic2 = dp2
+      .create_topic(
+        "ManualByTopicLiveliness".to_string(),
+        "i32".to_string(),
+        &qos,
+        TopicKind::NoKey,
+      )
+      .unwrap();
+    let mut reader: DataReader<i32> = dp1
+      .create_subscriber(&qos)
+      .unwrap()
+      .create_datareader_no_key_cdr(&topic1, None)
+      .unwrap();
+    let writer: DataWriter<i32> = dp2
+      .create_publisher(&qos)
+      .unwrap()
+      .create_datawriter_no_key_cdr(&topic2, None)
+      .unwrap();
+    reader.as_status_evented(); // enables status reception
+
+    // Keep writing until the sample gets through, so the Writer is matched.
+    let matched = wait_until(Duration::from_secs(10), || {
+      writer.write(1, None).unwrap();
+      std::thread::sleep(Duration::from_millis(100));
+      reader.take_next_sample().unwrap().is_some()
+    });
+    assert!(matched, "Writer was not matched");
+
+    // Latest (alive, not alive) Writer counts reported by the Reader
+    let mut counts = (1, 0);
+    let mut update_counts = |reader: &DataReader<i32>| {
+      while let Some(status) = reader.try_recv_status() {
+        if let DataReaderStatus::LivelinessChanged {
+          alive_total,
+          not_alive_total,
+        } = status
+        {
+          counts = (alive_total.count(), not_alive_total.count());
+        }
+      }
+      counts
+    };
+
+    // Asserting liveliness well within the lease keeps the Writer alive.
+    let start = Instant::now();
+    while start.elapsed() < Duration::from_millis(1500) {
+      writer.assert_liveliness().unwrap();
+      std::thread::sleep(Duration::from_millis(100));
+      assert_eq!(update_counts(&reader), (1, 0));
+    }
+
+    // Without assertions the lease lapses.
+    let start = Instant::now();
+    while update_counts(&reader) != (0, 1) {
+      assert!(
+        start.elapsed() < Duration::from_secs(5),
+        "Liveliness loss was not detected"
+      );
+      std::thread::sleep(Duration::from_millis(100));
+    }
+
+    // Resuming the assertions brings the Writer back to life.
+    let start = Instant::now();
+    while update_counts(&reader) != (1, 0) {
+      assert!(
+        start.elapsed() < Duration::from_secs(5),
+        "Liveliness recovery was not detected"
+      );
+      writer.assert_liveliness().unwrap();
+      std::thread::sleep(Duration::from_millis(100));
+    }
+  }
+
+  #[test]
+  fn sample_info_reports_publication_metadata() {
+    use crate::{
+      dds::{qos::policy::Reliability, with_key::datawriter::WriteOptionsBuilder},
+      no_key::{DataReader, DataWriter},
+      structure::{entity::RTPSEntity, time::Timestamp},
+      QosPolicyBuilder,
+    };
+
+    let domain_id = 62;
+    let dp1 = DomainParticipant::new(domain_id).expect("Failed to create participant 1");
+    let dp2 = DomainParticipant::new(domain_id).expect("Failed to create participant 2");
+
+    let qos = QosPolicyBuilder::new()
+      .reliability(Reliability::Reliable {
+        max_blocking_time: crate::Duration::from_millis(100),
+      })
+      .build();
+    let topic1 = dp1
+      .create_topic(
+        "SampleInfoTopic".to_string(),
+        "i32".to_string(),
+        &qos,
+        TopicKind::NoKey,
+      )
+      .unwrap();
+    let topic2 = dp2
+      .create_topic(
+        "SampleInfoTopic".to_string(),
+        "i32".to_string(),
+        &qos,
+        TopicKind::NoKey,
+      )
+      .unwrap();
+    let mut reader: DataReader<i32> = dp1
+      .create_subscriber(&qos)
+      .unwrap()
+      .create_datareader_no_key_cdr(&topic1, None)
+      .unwrap();
+    let writer: DataWriter<i32> = dp2
+      .create_publisher(&qos)
+      .unwrap()
+      .create_datawriter_no_key_cdr(&topic2, None)
+      .unwrap();
+
+    // Keep writing until a sample gets through, and check its metadata
+    // against what the writer reported for that sample.
+    let mut written = Vec::new();
+    for i in 0..100 {
+      let source_timestamp = Timestamp::now();
+      let identity = writer
+        .write_with_options(
+          i,
+          WriteOptionsBuilder::new()
+            .source_timestamp(source_timestamp)
+            .build(),
+        )
+        .unwrap();
+      written.push((identity, source_timestamp));
+      std::thread::sleep(std::time::Duration::from_millis(100));
+      if let Some(sample) = reader.take_next_sample().unwrap() {
+        let info = sample.sample_info();
+        let (identity, source_timestamp) = written[*sample.value() as usize];
+        assert_eq!(info.publication_sequence_number(), identity.sequence_number);
+        assert_eq!(info.writer_guid(), writer.guid());
+        assert_eq!(info.source_timestamp(), Some(source_timestamp));
+        assert!(info.reception_timestamp() >= source_timestamp);
+        return;
+      }
+    }
+    panic!("No sample was received");
+  }
+
+  #[test]
+  fn delete_contained_entities_removes_readers_and_writers() {
+    use std::time::Duration;
+
+    use crate::{dds::qos::QosPolicyBuilder, test::random_data::RandomData};
+
+    let domain_id = 69;
+    let dp = DomainParticipant::new(domain_id).expect("Participant creation failed");
+    let qos = QosPolicyBuilder::new().build();
+    let topic = dp
+      .create_topic(
+        "DeleteContainedTopic".to_string(),
+        "RandomData".to_string(),
+        &qos,
+        TopicKind::WithKey,
+      )
+      .unwrap();
+    let publisher = dp.create_publisher(&qos).unwrap();
+    let subscriber = dp.create_subscriber(&qos).unwrap();
+    let writers: Vec<_> = (0..2)
+      .map(|_| {
+        publisher
+          .create_datawriter_cdr::<RandomData>(&topic, None)
+          .unwrap()
+      })
+      .collect();
+    let readers: Vec<_> = (0..2)
+      .map(|_| {
+        subscriber
+          .create_datareader_cdr::<RandomData>(&topic, None)
+          .unwrap()
+      })
+      .collect();
+
+    // Only count our own topic, as security adds a builtin logging DataWriter
+    let user_defined_endpoint_count = |dp: &DomainParticipant| {
+      let db_arc = dp.discovery_db();
+      let db = db_arc.read().unwrap();
+      let readers = db
+        .get_all_local_topic_readers()
+        .filter(|r| r.subscription_topic_data.topic_name() == "DeleteContainedTopic")
+        .count();
+      let writers = db
+        .get_all_local_topic_writers()
+        .filter(|w| w.publication_topic_data.topic_name == "DeleteContainedTopic")
+        .count();
+      (readers, writers)
+    };
+    assert_eq!(user_defined_endpoint_count(&dp), (2, 2));
+
+    dp.delete_contained_entities().unwrap();
+    assert_eq!(publisher.datawriter_count(), 0);
+    assert_eq!(subscriber.datareader_count(), 0);
+
+    // The event loop drops the Writers, so writing fails once they are gone
+    let sample = RandomData {
+      a: 1,
+      b: "deleted".to_string(),
+    };
+    let deleted = wait_until(Duration::from_secs(10), || {
+      user_defined_endpoint_count(&dp) == (0, 0)
+        && writers
+          .iter()
+          .all(|writer| writer.write(sample.clone(), None).is_err())
+    });
+    assert!(deleted, "Entities were not deleted from the event loop");
+
+    // The Publisher and Subscriber are deleted too
+    assert!(matches!(
+      publisher.create_datawriter_cdr::<RandomData>(&topic, None),
+      Err(super::CreateError::ResourceDropped { .. })
+    ));
+    assert!(matches!(
+      subscriber.create_datareader_cdr::<RandomData>(&topic, None),
+      Err(super::CreateError::ResourceDropped { .. })
+    ));
+
+    // New entities can be created and used afterwards
+    let new_publisher = dp.create_publisher(&qos).unwrap();
+    let new_subscriber = dp.create_subscriber(&qos).unwrap();
+    let new_writer = new_publisher
+      .create_datawriter_cdr::<RandomData>(&topic, None)
+      .unwrap();
+    let _new_reader = new_subscriber
+      .create_datareader_cdr::<RandomData>(&topic, None)
+      .unwrap();
+    assert_eq!(user_defined_endpoint_count(&dp), (1, 1));
+    new_writer.write(sample.clone(), None).unwrap();
+
+    // The participant's own security logging DataWriter is not deleted
+    #[cfg(feature = "security")]
+    assert!(dp
+      .discovery_db()
+      .read()
+      .unwrap()
+      .get_all_local_topic_writers()
+      .any(|w| w.publication_topic_data.topic_name == "DDS:Security:LogTopic"));
+
+    // Dropping the deleted handles does not remove anything again
+    drop(writers);
+    drop(readers);
+    assert_eq!(new_publisher.datawriter_count(), 1);
+    assert_eq!(new_subscriber.datareader_count(), 1);
+    new_writer.write(sample, None).unwrap();
+  }
+
+  #[test]
+  fn cache_usage_reports_payload_bytes() {
+    use std::time::Duration;
+
+    use crate::{
+      dds::qos::{policy::History, QosPolicyBuilder},
+      test::random_data::RandomData,
+    };
+
+    let domain_id = 71;
+    let dp = DomainParticipant::new(domain_id).expect("Participant creation failed");
+    let qos = QosPolicyBuilder::new().history(History::KeepAll).build();
+    let topic = dp
+      .create_topic(
+        "CacheUsageTopic".to_string(),
+        "RandomData".to_string(),
+        &qos,
+        TopicKind::WithKey,
+      )
+      .unwrap();
+    let writer = dp
+      .create_publisher(&qos)
+      .unwrap()
+      .create_datawriter_cdr::<RandomData>(&topic, None)
+      .unwrap();
+
+    let sample_count = 10;
+    let string_length = 1000;
+    for a in 0..sample_count {
+      writer
+        .write(
+          RandomData {
+            a,
+            b: "x".repeat(string_length),
+          },
+          None,
+        )
+        .unwrap();
+    }
+
+    let topic_usage = || {
+      dp.cache_usage()
+        .into_iter()
+        .find(|usage| usage.topic_name == "CacheUsageTopic")
+    };
+    // The Writer adds the samples to the cache asynchronously
+    let mut usage = None;
+    wait_until(Duration::from_secs(10), || {
+      usage = topic_usage().filter(|usage| usage.sample_count == sample_count as usize);
+      usage.is_some()
+    });
+    let usage = usage.expect("Samples did not reach the cache");
+    // Each payload is the string and a little CDR overhead
+    let min_bytes = sample_count as usize * string_length;
+    let max_bytes = sample_count as usize * (string_length + 64);
+    assert!(
+      (min_bytes..=max_bytes).contains(&usage.payload_bytes),
+      "Unexpected payload size {}",
+      usage.payload_bytes
+    );
+  }
+
+  #[test]
+  fn prepare_stop_drains_reliable_writers() {
+    use std::time::Duration;
+
+    use crate::{
+      dds::qos::policy::{History, Reliability},
+      test::random_data::RandomData,
+      QosPolicyBuilder, ReadCondition,
+    };
+
+    let domain_id = 72;
+    let dp_writer = DomainParticipant::new(domain_id).expect("Failed to create participant 1");
+    let dp_reader = DomainParticipant::new(domain_id).expect("Failed to create participant 2");
+    let qos = QosPolicyBuilder::new()
+      .reliability(Reliability::Reliable {
+        max_blocking_time: crate::Duration::from_secs(1),
+      })
+      .history(History::KeepAll)
+      .build();
+    let create_topic = |dp: &DomainParticipant| {
+      dp.create_topic(
+        "DrainTopic".to_string(),
+        "RandomData".to_string(),
+        &qos,
+        TopicKind::WithKey,
+      )
+      .unwrap()
+    };
+    let writer = dp_writer
+      .create_publisher(&qos)
+      .unwrap()
+      .create_datawriter_cdr::<RandomData>(&create_topic(&dp_writer), None)
+      .unwrap();
+    let mut reader = dp_reader
+      .create_subscriber(&qos)
+      .unwrap()
+      .create_datareader_cdr::<RandomData>(&create_topic(&dp_reader), None)
+      .unwrap();
+
+    let sample = |a| RandomData {
+      a,
+      b: "drain".repeat(100),
+    };
+    let mut received = Vec::new();
+    let mut take_received = |received: &mut Vec<RandomData>| {
+      received.extend(
+        reader
+          .take(1000, ReadCondition::any())
+          .unwrap()
+          .into_iter()
+          .filter_map(|s| s.into_value().value()),
+      );
+    };
+
+    // The first sample gets through once the Writer and Reader have matched
+    writer.write(sample(0), None).unwrap();
+    let matched = wait_until(Duration::from_secs(10), || {
+      take_received(&mut received);
+      !received.is_empty()
+    });
+    assert!(matched, "Writer and Reader did not match");
+
+    // Shut down right after writing, while the data is still in flight. The
+    // DataWriter outlives the participant.
+    let sample_count = 100;
+    for a in 1..sample_count {
+      writer.write(sample(a), None).unwrap();
+    }
+    drop(dp_writer);
+
+    wait_until(Duration::from_secs(5), || {
+      take_received(&mut received);
+      received.len() == sample_count as usize
+    });
+    received.sort_by_key(|d| d.a);
+    assert_eq!(received, (0..sample_count).map(sample).collect::<Vec<_>>());
+    drop(writer);
   }
 }
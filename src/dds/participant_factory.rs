@@ -0,0 +1,174 @@
+use std::sync::Mutex;
+
+#[allow(unused_imports)]
+use log::{debug, info, warn};
+
+use crate::{
+  dds::{
+    participant::{DomainParticipant, DomainParticipantBuilder},
+    result::CreateResult,
+  },
+  structure::entity::RTPSEntity,
+};
+
+/// Creates DomainParticipants and keeps track of them, so that an application
+/// using many domains, e.g. a gateway bridging several of them, can manage
+/// them in one place.
+///
+/// See DDS Spec v1.4 Section "2.2.2.2.2 DomainParticipantFactory Class".
+///
+/// Each DomainParticipant still has its own sockets, event loop and Discovery
+/// threads. The factory only holds handles to them.
+///
+/// A DomainParticipant shuts down when its last handle is dropped. The
+/// factory drops its own handles in [`shutdown_all`](Self::shutdown_all) and
+/// when the factory itself is dropped, so handles cloned by the application
+/// keep those participants running until they are dropped, too.
+pub struct DomainParticipantFactory {
+  participants: Mutex<Vec<DomainParticipant>>,
+}
+
+impl DomainParticipantFactory {
+  pub fn new() -> Self {
+    DomainParticipantFactory {
+      participants: Mutex::new(Vec::new()),
+    }
+  }
+
+  /// Creates a DomainParticipant like [`DomainParticipant::new`] and starts
+  /// tracking it.
+  pub fn create_participant(&self, domain_id: u16) -> CreateResult<DomainParticipant> {
+    let participant = DomainParticipant::new(domain_id)?;
+    self.track(participant.clone());
+    Ok(participant)
+  }
+
+  /// Builds a DomainParticipant from `builder` and starts tracking it.
+  pub fn create_participant_with(
+    &self,
+    builder: DomainParticipantBuilder,
+  ) -> CreateResult<DomainParticipant> {
+    let participant = builder.build()?;
+    self.track(participant.clone());
+    Ok(participant)
+  }
+
+  /// Returns a tracked DomainParticipant of the given domain, if any.
+  pub fn lookup_participant(&self, domain_id: u16) -> Option<DomainParticipant> {
+    self
+      .participants
+      .lock()
+      .unwrap()
+      .iter()
+      .find(|dp| dp.domain_id() == domain_id)
+      .cloned()
+  }
+
+  /// Returns all tracked DomainParticipants.
+  pub fn participants(&self) -> Vec<DomainParticipant> {
+    self.participants.lock().unwrap().clone()
+  }
+
+  /// Stops tracking `participant`. Returns `false` if it was not created by
+  /// this factory or was already deleted.
+  ///
+  /// The participant shuts down when the last handle to it is dropped.
+  pub fn delete_participant(&self, participant: &DomainParticipant) -> bool {
+    let mut participants = self.participants.lock().unwrap();
+    let count_before = participants.len();
+    participants.retain(|dp| dp != participant);
+    participants.len() < count_before
+  }
+
+  /// Stops tracking all DomainParticipants and drops the factory's handles to
+  /// them.
+  ///
+  /// The participants are dropped concurrently, each in its own thread, so
+  /// that their event loops and Discovery threads are stopped and joined in
+  /// parallel. This returns after all of them have been dropped.
+  pub fn shutdown_all(&self) {
+    let participants = std::mem::take(&mut *self.participants.lock().unwrap());
+    if participants.is_empty() {
+      return;
+    }
+    info!(
+      "DomainParticipantFactory: shutting down {} participants",
+      participants.len()
+    );
+    std::thread::scope(|scope| {
+      for participant in participants {
+        let guid = participant.guid();
+        let spawned = std::thread::Builder::new()
+          .name(format!("RustDDS shutdown {:?}", guid.prefix))
+          .spawn_scoped(scope, move || drop(participant));
+        if let Err(e) = spawned {
+          // The participant was moved into the closure and is dropped with
+          // it, so it is shut down anyway, just not in parallel.
+          warn!("Cannot spawn shutdown thread for {guid:?}: {e:?}");
+        }
+      }
+    });
+    debug!("DomainParticipantFactory: shutdown complete");
+  }
+
+  fn track(&self, participant: DomainParticipant) {
+    self.participants.lock().unwrap().push(participant);
+  }
+}
+
+impl Default for DomainParticipantFactory {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+impl Drop for DomainParticipantFactory {
+  fn drop(&mut self) {
+    self.shutdown_all();
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::net::UdpSocket;
+
+  use super::*;
+  use crate::network::constant::spdp_well_known_unicast_port;
+
+  #[test]
+  fn factory_shuts_down_participants_concurrently() {
+    let factory = DomainParticipantFactory::new();
+    let domain_ids = [52, 53, 54];
+
+    let mut ports = Vec::new();
+    for domain_id in domain_ids {
+      let dp = factory.create_participant(domain_id).unwrap();
+      // Give each participant something to tear down.
+      dp.create_publisher(&crate::QosPolicyBuilder::new().build())
+        .unwrap();
+      ports.push(spdp_well_known_unicast_port(domain_id, dp.participant_id()));
+    }
+    assert_eq!(factory.participants().len(), domain_ids.len());
+    assert_eq!(
+      factory.lookup_participant(53).map(|dp| dp.domain_id()),
+      Some(53)
+    );
+    assert!(factory.lookup_participant(55).is_none());
+
+    // Deleting stops tracking, but the application handle keeps it alive.
+    let deleted = factory.lookup_participant(54).unwrap();
+    assert!(factory.delete_participant(&deleted));
+    assert!(!factory.delete_participant(&deleted));
+    assert_eq!(factory.participants().len(), 2);
+    drop(deleted);
+
+    factory.shutdown_all();
+    assert!(factory.participants().is_empty());
+
+    // All sockets have been released.
+    for port in ports {
+      UdpSocket::bind(("0.0.0.0", port))
+        .unwrap_or_else(|e| panic!("Port {port} still in use: {e:?}"));
+    }
+  }
+}
@@ -1,6 +1,10 @@
 use std::{
+  collections::{BTreeMap, BTreeSet},
   fmt::Debug,
-  sync::{Arc, Mutex, MutexGuard, RwLock},
+  sync::{
+    atomic::{AtomicI64, Ordering},
+    Arc, Mutex, MutexGuard, RwLock, Weak,
+  },
   time::Duration,
 };
 
@@ -11,7 +15,8 @@ use byteorder::LittleEndian;
 use log::{debug, error, info, trace, warn};
 
 use crate::{
-  create_error_dropped, create_error_poisoned,
+  create_error_bad_parameter, create_error_dropped, create_error_out_of_resources,
+  create_error_poisoned,
   dds::{
     adapters,
     key::Keyed,
@@ -21,7 +26,8 @@ use crate::{
     },
     participant::*,
     qos::*,
-    result::{CreateError, CreateResult, WaitResult},
+    result::{CreateError, CreateResult, WaitResult, WriteError, WriteResult},
+    sample_store::SampleStore,
     statusevents::{sync_status_channel, DataReaderStatus},
     topic::*,
     with_key,
@@ -30,7 +36,9 @@ use crate::{
     },
   },
   discovery::{
-    discovery::DiscoveryCommand, discovery_db::DiscoveryDB, sedp_messages::DiscoveredWriterData,
+    discovery::DiscoveryCommand,
+    discovery_db::{discovery_db_read, discovery_db_write, DiscoveryDB},
+    sedp_messages::DiscoveredWriterData,
   },
   mio_source,
   rtps::{
@@ -39,8 +47,10 @@ use crate::{
   },
   serialization::{cdr_deserializer::CDRDeserializerAdapter, cdr_serializer::CDRSerializerAdapter},
   structure::{
+    duration::Duration as DDSDuration,
     entity::RTPSEntity,
     guid::{EntityId, EntityKind, GUID},
+    sequence_number::SequenceNumber,
     topic_kind::TopicKind,
   },
 };
@@ -52,7 +62,7 @@ use super::{
 
 #[cfg(feature = "security")]
 use crate::{
-  security::security_plugins::SecurityPluginsHandle,
+  security::{security_plugins::SecurityPluginsHandle, EndpointSecurityInfo},
 };
 
 #[cfg(not(feature = "security"))]
@@ -170,9 +180,7 @@ impl Publisher {
     D: Keyed,
     SA: adapters::with_key::SerializerAdapter<D>,
   {
-    self
-      .inner_lock()
-      .create_datawriter(self, None, topic, qos, false)
+    self.create_and_enable_datawriter(None, topic, qos, false, None)
   }
 
   /// Shorthand for crate_datawriter with Common Data Representation Little
@@ -189,6 +197,26 @@ impl Publisher {
     self.create_datawriter::<D, CDRSerializerAdapter<D, LittleEndian>>(topic, qos)
   }
 
+  /// Like [`create_datawriter`](Self::create_datawriter), but samples that
+  /// all matched readers have acknowledged are moved from memory to
+  /// `sample_store`. This bounds the memory use of large TransientLocal
+  /// histories. Samples are served to late-joining readers from the store.
+  ///
+  /// DataReaders of the same topic in this participant share the history in
+  /// memory, so they should keep up with the DataWriter.
+  pub fn create_datawriter_with_sample_store<D, SA>(
+    &self,
+    topic: &Topic,
+    qos: Option<QosPolicies>,
+    sample_store: Box<dyn SampleStore>,
+  ) -> CreateResult<WithKeyDataWriter<D, SA>>
+  where
+    D: Keyed,
+    SA: adapters::with_key::SerializerAdapter<D>,
+  {
+    self.create_and_enable_datawriter(None, topic, qos, false, Some(sample_store))
+  }
+
   /// Creates DDS [DataWriter](struct.DataWriter.html) for Nokey Topic
   ///
   /// # Arguments
@@ -223,9 +251,7 @@ impl Publisher {
   where
     SA: adapters::no_key::SerializerAdapter<D>,
   {
-    self
-      .inner_lock()
-      .create_datawriter_no_key(self, None, topic, qos, false)
+    self.create_and_enable_datawriter_no_key(None, topic, qos, false, None)
   }
 
   pub fn create_datawriter_no_key_cdr<D>(
@@ -239,6 +265,84 @@ impl Publisher {
     self.create_datawriter_no_key::<D, CDRSerializerAdapter<D, LittleEndian>>(topic, qos)
   }
 
+  /// NoKey version of
+  /// [`create_datawriter_with_sample_store`](Self::create_datawriter_with_sample_store)
+  pub fn create_datawriter_no_key_with_sample_store<D, SA>(
+    &self,
+    topic: &Topic,
+    qos: Option<QosPolicies>,
+    sample_store: Box<dyn SampleStore>,
+  ) -> CreateResult<NoKeyDataWriter<D, SA>>
+  where
+    SA: adapters::no_key::SerializerAdapter<D>,
+  {
+    self.create_and_enable_datawriter_no_key(None, topic, qos, false, Some(sample_store))
+  }
+
+  /// Like [`create_datawriter`](Self::create_datawriter), but the DataWriter
+  /// gets the given entity key, i.e. the first three bytes of its
+  /// [`EntityId`], instead of a generated one. This makes the GUID of the
+  /// DataWriter the same across runs, which helps with reproducible captures
+  /// and interoperability with systems that expect specific entity ids.
+  ///
+  /// Fails with [`CreateError::BadParameter`] if this participant already
+  /// has a DataWriter with the same entity id.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// # use rustdds::*;
+  /// use rustdds::serialization::CDRSerializerAdapter;
+  /// use serde::Serialize;
+  ///
+  /// let domain_participant = DomainParticipant::new(0).unwrap();
+  /// let qos = QosPolicyBuilder::new().build();
+  ///
+  /// let publisher = domain_participant.create_publisher(&qos).unwrap();
+  ///
+  /// #[derive(Serialize)]
+  /// struct SomeType { a: i32 }
+  /// impl Keyed for SomeType {
+  ///   type K = i32;
+  ///
+  ///   fn key(&self) -> Self::K {
+  ///     self.a
+  ///   }
+  /// }
+  ///
+  /// let topic = domain_participant.create_topic("some_topic".to_string(), "SomeType".to_string(), &qos, TopicKind::WithKey).unwrap();
+  /// let data_writer = publisher
+  ///   .create_datawriter_with_entity_key::<SomeType, CDRSerializerAdapter<_>>([0, 0, 0x42], &topic, None);
+  /// ```
+  pub fn create_datawriter_with_entity_key<D, SA>(
+    &self,
+    entity_key: [u8; 3],
+    topic: &Topic,
+    qos: Option<QosPolicies>,
+  ) -> CreateResult<WithKeyDataWriter<D, SA>>
+  where
+    D: Keyed,
+    SA: adapters::with_key::SerializerAdapter<D>,
+  {
+    let entity_id = EntityId::new(entity_key, EntityKind::WRITER_WITH_KEY_USER_DEFINED);
+    self.create_and_enable_datawriter(Some(entity_id), topic, qos, false, None)
+  }
+
+  /// NoKey version of
+  /// [`create_datawriter_with_entity_key`](Self::create_datawriter_with_entity_key)
+  pub fn create_datawriter_no_key_with_entity_key<D, SA>(
+    &self,
+    entity_key: [u8; 3],
+    topic: &Topic,
+    qos: Option<QosPolicies>,
+  ) -> CreateResult<NoKeyDataWriter<D, SA>>
+  where
+    SA: adapters::no_key::SerializerAdapter<D>,
+  {
+    let entity_id = EntityId::new(entity_key, EntityKind::WRITER_NO_KEY_USER_DEFINED);
+    self.create_and_enable_datawriter_no_key(Some(entity_id), topic, qos, false, None)
+  }
+
   // Versions with callee-specified EntityId. These are for Discovery use only.
 
   pub(crate) fn create_datawriter_with_entity_id_with_key<D, SA>(
@@ -252,9 +356,7 @@ impl Publisher {
     D: Keyed,
     SA: adapters::with_key::SerializerAdapter<D>,
   {
-    self
-      .inner_lock()
-      .create_datawriter(self, Some(entity_id), topic, qos, writer_like_stateless)
+    self.create_and_enable_datawriter(Some(entity_id), topic, qos, writer_like_stateless, None)
   }
 
   #[cfg(feature="security")]  // to avoid "never used" warning
@@ -268,15 +370,74 @@ impl Publisher {
   where
     SA: crate::no_key::SerializerAdapter<D>,
   {
-    self.inner_lock().create_datawriter_no_key(
-      self,
+    self.create_and_enable_datawriter_no_key(
       Some(entity_id),
       topic,
       qos,
       writer_like_stateless,
+      None,
     )
   }
 
+  // The DataWriter is enabled only after the inner lock is released, because
+  // dropping the DataWriter, if enabling fails, takes the lock.
+  fn create_and_enable_datawriter<D, SA>(
+    &self,
+    entity_id_opt: Option<EntityId>,
+    topic: &Topic,
+    qos: Option<QosPolicies>,
+    writer_like_stateless: bool,
+    sample_store: Option<Box<dyn SampleStore>>,
+  ) -> CreateResult<WithKeyDataWriter<D, SA>>
+  where
+    D: Keyed,
+    SA: adapters::with_key::SerializerAdapter<D>,
+  {
+    let (data_writer, autoenable) = {
+      let mut inner = self.inner_lock();
+      let data_writer = inner.create_datawriter(
+        self,
+        entity_id_opt,
+        topic,
+        qos,
+        writer_like_stateless,
+        sample_store,
+      )?;
+      (
+        data_writer,
+        inner.my_qos_policies.autoenable_created_entities(),
+      )
+    };
+    if autoenable {
+      data_writer.enable()?;
+    }
+    Ok(data_writer)
+  }
+
+  fn create_and_enable_datawriter_no_key<D, SA>(
+    &self,
+    entity_id_opt: Option<EntityId>,
+    topic: &Topic,
+    qos: Option<QosPolicies>,
+    writer_like_stateless: bool,
+    sample_store: Option<Box<dyn SampleStore>>,
+  ) -> CreateResult<NoKeyDataWriter<D, SA>>
+  where
+    SA: adapters::no_key::SerializerAdapter<D>,
+  {
+    let entity_id = self
+      .inner_lock()
+      .unwrap_or_new_entity_id(entity_id_opt, EntityKind::WRITER_NO_KEY_USER_DEFINED);
+    let d = self.create_and_enable_datawriter::<NoKeyWrapper<D>, SAWrapper<SA>>(
+      Some(entity_id),
+      topic,
+      qos,
+      writer_like_stateless,
+      sample_store,
+    )?;
+    Ok(NoKeyDataWriter::<D, SA>::from_keyed(d))
+  }
+
   // delete_datawriter should not be needed. The DataWriter object itself should
   // be deleted to accomplish this.
 
@@ -298,18 +459,33 @@ impl Publisher {
     unimplemented!();
   }
 
-  // coherent change set
-  // In case such QoS is not supported, these should be no-ops.
-  // TODO: Implement these when coherent change-sets are supported.
-  // Coherent set not implemented and currently does nothing
-  /// **NOT IMPLEMENTED. DO NOT USE**
-  #[deprecated(note = "unimplemented")]
-  pub fn begin_coherent_changes(&self) {}
+  /// Begins a coherent set of changes. Samples written by the DataWriters of
+  /// this Publisher until the matching
+  /// [`end_coherent_changes`](Publisher::end_coherent_changes) form a coherent
+  /// set: a DataReader, whose Subscriber has
+  /// [`Presentation`](policy::Presentation) QoS with `coherent_access`, gets
+  /// either all of the samples or none of them.
+  ///
+  /// Coherency is provided per DataWriter, i.e. the samples of each DataWriter
+  /// are delivered atomically, but not together with other DataWriters.
+  ///
+  /// Calls can be nested. The set ends at the outermost `end_coherent_changes`.
+  ///
+  /// See DDS Spec 1.4 Section 2.2.2.4.1.10 begin_coherent_changes.
+  pub fn begin_coherent_changes(&self) -> WriteResult<(), ()> {
+    self.inner_lock().begin_coherent_changes()
+  }
 
-  // Coherent set not implemented and currently does nothing
-  /// **NOT IMPLEMENTED. DO NOT USE**
-  #[deprecated(note = "unimplemented")]
-  pub fn end_coherent_changes(&self) {}
+  /// Ends the coherent set started by
+  /// [`begin_coherent_changes`](Publisher::begin_coherent_changes), so that
+  /// DataReaders may receive it. Fails with
+  /// [`WriteError::Internal`](WriteError::Internal) if there is no coherent set
+  /// open.
+  ///
+  /// See DDS Spec 1.4 Section 2.2.2.4.1.11 end_coherent_changes.
+  pub fn end_coherent_changes(&self) -> WriteResult<(), ()> {
+    self.inner_lock().end_coherent_changes()
+  }
 
   // Wait for all matched reliable DataReaders acknowledge data written so far,
   // or timeout.
@@ -339,8 +515,22 @@ impl Publisher {
     self.inner_lock().domain_participant.clone().upgrade()
   }
 
-  // delete_contained_entities: We should not need this. Contained DataWriters
-  // should dispose themselves and notify publisher.
+  /// Number of DataWriters created with this Publisher, and not yet dropped.
+  pub fn datawriter_count(&self) -> usize {
+    self.inner_lock().datawriters.count()
+  }
+
+  /// Limits the number of DataWriters that can exist in this Publisher at
+  /// the same time. Further calls to `create_datawriter*` fail with
+  /// [`CreateError::OutOfResources`]. This is meant to catch bugs, such as
+  /// creating DataWriters in a loop. `None`, the default, is no limit.
+  pub fn set_max_datawriters(&self, max: Option<usize>) {
+    self.inner_lock().datawriters.max = max;
+  }
+
+  // delete_contained_entities: Contained DataWriters dispose themselves and
+  // notify publisher. For explicit teardown, see
+  // DomainParticipant::delete_contained_entities.
 
   /// Returns default DataWriter qos.
   ///
@@ -382,8 +572,29 @@ impl Publisher {
   }
 
   // This is used on DataWriter .drop()
-  pub(crate) fn remove_writer(&self, guid: GUID) {
-    self.inner_lock().remove_writer(guid);
+  pub(crate) fn remove_writer(&self, guid: GUID) -> bool {
+    self.inner_lock().remove_writer(guid)
+  }
+
+  // This is used by DomainParticipant::delete_contained_entities. Deletes the
+  // contained DataWriters, and returns the GUIDs of those that were announced.
+  // No DataWriters can be created afterwards.
+  pub(crate) fn delete(&self) -> CreateResult<Vec<GUID>> {
+    self.inner_lock().delete()
+  }
+
+  pub(crate) fn downgrade(&self) -> PublisherWeak {
+    PublisherWeak {
+      inner: Arc::downgrade(&self.inner),
+    }
+  }
+
+  // This is used on DataWriter .enable()
+  pub(crate) fn enable_writer(
+    &self,
+    disabled_writer: &mut Option<DisabledWriter>,
+  ) -> CreateResult<()> {
+    self.inner_lock().register_writer(disabled_writer)
   }
 } // impl
 
@@ -401,6 +612,67 @@ impl Debug for Publisher {
   }
 }
 
+// The DomainParticipant keeps these to find its Publishers, without keeping
+// them alive.
+#[derive(Clone)]
+pub(crate) struct PublisherWeak {
+  inner: Weak<Mutex<InnerPublisher>>,
+}
+
+impl PublisherWeak {
+  pub fn upgrade(&self) -> Option<Publisher> {
+    self.inner.upgrade().map(|inner| Publisher { inner })
+  }
+}
+
+// What is needed to register a DataWriter with the event loop and Discovery.
+// A DataWriter created disabled keeps this until it is enabled, see the
+// EntityFactory QoS policy.
+pub(crate) struct DisabledWriter {
+  ingredients: WriterIngredients,
+  writer_data: DiscoveredWriterData,
+  topic: Topic,
+}
+
+// Likewise for DataReaders
+pub(crate) struct DisabledReader {
+  ingredients: ReaderIngredients,
+  topic: Topic,
+}
+
+// DataWriters in a Publisher, or DataReaders in a Subscriber, that are neither
+// dropped nor deleted, and an optional limit for their number.
+#[derive(Clone, Debug, Default)]
+struct EntityCount {
+  guids: BTreeSet<GUID>,
+  max: Option<usize>,
+  // The Publisher or Subscriber itself has been deleted, so no more entities
+  // can be created in it.
+  deleted: bool,
+}
+
+impl EntityCount {
+  fn count(&self) -> usize {
+    self.guids.len()
+  }
+
+  // Is there room for one more entity?
+  fn check_limit(&self, entity_type: &str) -> CreateResult<()> {
+    if self.deleted {
+      return create_error_dropped!(
+        "Cannot create a {}, because its parent entity has been deleted",
+        entity_type
+      );
+    }
+    match self.max {
+      Some(max) if self.count() >= max => {
+        create_error_out_of_resources!("Cannot create more than {} {}s", max, entity_type)
+      }
+      _ => Ok(()),
+    }
+  }
+}
+
 // "Inner" struct
 
 #[derive(Clone)]
@@ -414,6 +686,21 @@ struct InnerPublisher {
   remove_writer_sender: mio_channel::SyncSender<GUID>,
   discovery_command: mio_channel::SyncSender<DiscoveryCommand>,
   security_plugins_handle: Option<SecurityPluginsHandle>,
+  coherent_set_writers: BTreeMap<GUID, CoherentSetWriter>,
+  coherent_set_depth: u32, // nesting level of begin_coherent_changes()
+  datawriters: EntityCount,
+}
+
+// What the Publisher needs to know of its DataWriters to start and end coherent
+// sets.
+#[derive(Clone)]
+struct CoherentSetWriter {
+  sequence_number_counter: Arc<AtomicI64>,
+  writer_command_sender: mio_channel::SyncSender<WriterCommand>,
+  max_blocking_time: Option<DDSDuration>,
+  // Counter value when the coherent set began. If the counter has not moved,
+  // nothing was written in the set, and no end marker is needed.
+  counter_at_begin: i64,
 }
 
 // public interface for Publisher
@@ -444,21 +731,27 @@ impl InnerPublisher {
       remove_writer_sender,
       discovery_command,
       security_plugins_handle,
+      coherent_set_writers: BTreeMap::new(),
+      coherent_set_depth: 0,
+      datawriters: EntityCount::default(),
     }
   }
 
   pub fn create_datawriter<D, SA>(
-    &self,
+    &mut self,
     outer: &Publisher,
     entity_id_opt: Option<EntityId>,
     topic: &Topic,
     optional_qos: Option<QosPolicies>,
     writer_like_stateless: bool, // Create a stateless-like RTPS writer? Usually false
+    sample_store: Option<Box<dyn SampleStore>>,
   ) -> CreateResult<WithKeyDataWriter<D, SA>>
   where
     D: Keyed,
     SA: adapters::with_key::SerializerAdapter<D>,
   {
+    self.datawriters.check_limit("DataWriter")?;
+
     // Data samples from DataWriter to HistoryCache
     let (dwcc_upload, hccc_download) = mio_channel::sync_channel::<WriterCommand>(16);
     let writer_waker = Arc::new(Mutex::new(None));
@@ -483,6 +776,9 @@ impl InnerPublisher {
       .participant()
       .ok_or("upgrade fail")
       .or_else(|e| create_error_dropped!("Where is my DomainParticipant? {}", e))?;
+    if entity_id.kind().is_user_defined() {
+      dp.open_user_traffic_listeners()?;
+    }
 
     // Create a new topic to DDScache if it doesn't exist and get a handle to it
     let topic_cache_handle = match dp.dds_cache().write() {
@@ -491,7 +787,12 @@ impl InnerPublisher {
     };
 
     let guid = GUID::new_with_prefix_and_id(dp.guid().prefix, entity_id);
+    if discovery_db_read(&self.discovery_db).is_local_endpoint(guid) {
+      return create_error_bad_parameter!("EntityId {:?} is already in use", entity_id);
+    }
 
+    let reader_states = Arc::new(Mutex::new(BTreeMap::new()));
+    let write_limit = Arc::new(AtomicI64::new(i64::MAX));
     let new_writer = WriterIngredients {
       guid,
       writer_command_receiver: hccc_download,
@@ -501,14 +802,15 @@ impl InnerPublisher {
       like_stateless: writer_like_stateless,
       qos_policies: writer_qos.clone(),
       status_sender,
+      fragment_size: dp.fragment_size(),
+      message_limits: dp.message_limits(),
+      sample_store,
+      reader_states: Arc::clone(&reader_states),
+      write_limit: Arc::clone(&write_limit),
       security_plugins: self.security_plugins_handle.clone(),
     };
 
-    self
-      .add_writer_sender
-      .send(new_writer)
-      .or_else(|e| create_error_poisoned!("Adding a new writer failed: {}", e))?;
-
+    let max_blocking_time = writer_qos.reliable_max_blocking_time();
     let data_writer = WithKeyDataWriter::<D, SA>::new(
       outer.clone(),
       topic.clone(),
@@ -518,7 +820,88 @@ impl InnerPublisher {
       writer_waker,
       self.discovery_command.clone(),
       status_receiver,
+      reader_states,
+      write_limit,
     )?;
+    // From now on, dropping the DataWriter removes it from the count.
+    self.datawriters.guids.insert(guid);
+
+    let coherent_set_writer = CoherentSetWriter {
+      sequence_number_counter: data_writer.sequence_number_counter(),
+      writer_command_sender: data_writer.writer_command_sender(),
+      max_blocking_time,
+      counter_at_begin: data_writer
+        .sequence_number_counter()
+        .load(Ordering::Relaxed),
+    };
+    if self.coherent_set_depth > 0 {
+      // Created in the middle of a coherent set: join it.
+      try_send_timeout(
+        &coherent_set_writer.writer_command_sender,
+        WriterCommand::BeginCoherentSet,
+        max_blocking_time,
+      )
+      .unwrap_or_else(|e| error!("Cannot begin coherent set in {:?} : {:?}", guid, e));
+    }
+    self.coherent_set_writers.insert(guid, coherent_set_writer);
+
+    // With security, advertise the protection our governance applies to the topic.
+    #[cfg(feature = "security")]
+    let security_info = self.security_plugins_handle.as_ref().and_then(|handle| {
+      handle
+        .get_plugins()
+        .get_writer_sec_attributes(guid, topic.name())
+        .map(EndpointSecurityInfo::from)
+        .map_err(|e| error!("Cannot get security attributes of writer {guid:?}: {e}"))
+        .ok()
+    });
+    #[cfg(not(feature = "security"))]
+    let security_info = None;
+    // Publisher::create_and_enable_datawriter enables it, if the EntityFactory
+    // QoS says so.
+    data_writer.set_disabled(DisabledWriter {
+      ingredients: new_writer,
+      writer_data: DiscoveredWriterData::new(&data_writer, topic, &dp, security_info),
+      topic: topic.clone(),
+    });
+
+    Ok(data_writer)
+  }
+
+  // Hands the writer over to the event loop, and to Discovery to be announced.
+  // If the event loop does not get the writer, it is left disabled.
+  fn register_writer(&self, disabled_writer: &mut Option<DisabledWriter>) -> CreateResult<()> {
+    let guid = match disabled_writer {
+      Some(disabled_writer) => disabled_writer.ingredients.guid,
+      None => return Ok(()), // enabled already
+    };
+    if !self.datawriters.guids.contains(&guid) {
+      return create_error_dropped!("DataWriter {:?} has been deleted", guid);
+    }
+    // A disabled DataWriter did not reserve its EntityId in the Discovery DB
+    if discovery_db_read(&self.discovery_db).is_local_endpoint(guid) {
+      return create_error_bad_parameter!("EntityId {:?} is already in use", guid);
+    }
+    let Some(DisabledWriter {
+      ingredients,
+      writer_data,
+      topic,
+    }) = disabled_writer.take()
+    else {
+      return Ok(());
+    };
+    match self.add_writer_sender.send(ingredients) {
+      Ok(()) => {}
+      Err(mio_channel::SendError::Disconnected(ingredients)) => {
+        *disabled_writer = Some(DisabledWriter {
+          ingredients,
+          writer_data,
+          topic,
+        });
+        return create_error_dropped!("Adding a new writer failed: event loop is gone");
+      }
+      Err(e) => return create_error_poisoned!("Adding a new writer failed: {}", e),
+    }
 
     // notify Discovery DB
     let mut db = self
@@ -527,35 +910,9 @@ impl InnerPublisher {
       .map_err(|e| CreateError::Poisoned {
         reason: format!("Discovery DB: {e}"),
       })?;
-    // TODO: "None" below hardwires security_info to None. So we do not publish any.
-    let dwd = DiscoveredWriterData::new(&data_writer, topic, &dp, None);
-    db.update_local_topic_writer(dwd);
-    db.update_topic_data_p(topic);
-
-    Ok(data_writer)
-  }
-
-  pub fn create_datawriter_no_key<D, SA>(
-    &self,
-    outer: &Publisher,
-    entity_id_opt: Option<EntityId>,
-    topic: &Topic,
-    qos: Option<QosPolicies>,
-    writer_like_stateless: bool, // Create a stateless-like RTPS writer? Usually false
-  ) -> CreateResult<NoKeyDataWriter<D, SA>>
-  where
-    SA: adapters::no_key::SerializerAdapter<D>,
-  {
-    let entity_id =
-      self.unwrap_or_new_entity_id(entity_id_opt, EntityKind::WRITER_NO_KEY_USER_DEFINED);
-    let d = self.create_datawriter::<NoKeyWrapper<D>, SAWrapper<SA>>(
-      outer,
-      Some(entity_id),
-      topic,
-      qos,
-      writer_like_stateless,
-    )?;
-    Ok(NoKeyDataWriter::<D, SA>::from_keyed(d))
+    db.update_local_topic_writer(writer_data);
+    db.update_topic_data_p(&topic);
+    Ok(())
   }
 
   pub fn participant(&self) -> Option<DomainParticipant> {
@@ -577,12 +934,118 @@ impl InnerPublisher {
   ) -> EntityId {
     // If the entity_id is given, then just use that. If not, then pull an arbitrary
     // number out of participant's hat.
-    entity_id_opt.unwrap_or_else(|| self.participant().unwrap().new_entity_id(entity_kind))
+    entity_id_opt.unwrap_or_else(|| {
+      new_unused_entity_id(&self.participant().unwrap(), &self.discovery_db, entity_kind)
+    })
   }
 
-  pub(crate) fn remove_writer(&self, guid: GUID) {
+  // Returns false, if the DataWriter was deleted already.
+  pub(crate) fn remove_writer(&mut self, guid: GUID) -> bool {
+    if !self.datawriters.guids.remove(&guid) {
+      return false;
+    }
+    self.coherent_set_writers.remove(&guid);
     try_send_timeout(&self.remove_writer_sender, guid, None)
       .unwrap_or_else(|e| error!("Cannot remove Writer {:?} : {:?}", guid, e));
+    true
+  }
+
+  // Removes the user-defined DataWriters, like dropping them would. Returns the
+  // GUIDs of the enabled ones, which Discovery has announced.
+  fn delete(&mut self) -> CreateResult<Vec<GUID>> {
+    self.datawriters.deleted = true;
+    let deleted: Vec<GUID> = self
+      .datawriters
+      .guids
+      .iter()
+      .copied()
+      .filter(|guid| guid.entity_id.kind().is_user_defined())
+      .collect();
+    let mut announced = Vec::new();
+    for guid in deleted {
+      if discovery_db_read(&self.discovery_db).is_local_endpoint(guid) {
+        announced.push(guid);
+      }
+      self.datawriters.guids.remove(&guid);
+      self.coherent_set_writers.remove(&guid);
+      if let Err(e) = try_send_timeout(&self.remove_writer_sender, guid, None) {
+        return create_error_dropped!("Cannot remove DataWriter {guid:?}: {e:?}");
+      }
+    }
+    Ok(announced)
+  }
+
+  fn begin_coherent_changes(&mut self) -> WriteResult<(), ()> {
+    self.coherent_set_depth += 1;
+    if self.coherent_set_depth > 1 {
+      return Ok(()); // nested: already begun
+    }
+    for (guid, writer) in self.coherent_set_writers.iter_mut() {
+      writer.counter_at_begin = writer.sequence_number_counter.load(Ordering::Relaxed);
+      Self::send_coherent_set_command(*guid, writer, WriterCommand::BeginCoherentSet)?;
+    }
+    Ok(())
+  }
+
+  fn end_coherent_changes(&mut self) -> WriteResult<(), ()> {
+    match self.coherent_set_depth {
+      0 => {
+        return Err(WriteError::Internal {
+          reason: "end_coherent_changes() without begin_coherent_changes()".to_string(),
+        })
+      }
+      1 => self.coherent_set_depth = 0,
+      _ => {
+        self.coherent_set_depth -= 1;
+        return Ok(()); // nested: the outer set continues
+      }
+    }
+    let mut result = Ok(());
+    for (guid, writer) in self.coherent_set_writers.iter() {
+      // The end marker takes the next sequence number of the DataWriter, but only
+      // if something was written. An empty set needs no marker.
+      let sequence_number = if writer.sequence_number_counter.load(Ordering::Relaxed)
+        != writer.counter_at_begin
+      {
+        Some(SequenceNumber::from(
+          writer.sequence_number_counter.fetch_add(1, Ordering::Relaxed),
+        ))
+      } else {
+        None
+      };
+      let send_result = Self::send_coherent_set_command(
+        *guid,
+        writer,
+        WriterCommand::EndCoherentSet { sequence_number },
+      );
+      if send_result.is_err() {
+        if sequence_number.is_some() {
+          writer.sequence_number_counter.fetch_sub(1, Ordering::Relaxed);
+        }
+        // Keep going, so that the other DataWriters end their sets.
+        result = result.and(send_result);
+      }
+    }
+    result
+  }
+
+  fn send_coherent_set_command(
+    guid: GUID,
+    writer: &CoherentSetWriter,
+    command: WriterCommand,
+  ) -> WriteResult<(), ()> {
+    match try_send_timeout(&writer.writer_command_sender, command, writer.max_blocking_time) {
+      Ok(()) => Ok(()),
+      Err(mio_channel::TrySendError::Full(_)) => {
+        warn!("Coherent set command to {:?} timed out", guid);
+        Err(WriteError::WouldBlock { data: () })
+      }
+      Err(mio_channel::TrySendError::Disconnected(_)) => Err(WriteError::Poisoned {
+        reason: format!("Cannot send coherent set command to Writer {guid:?}"),
+        data: (),
+      }),
+      Err(mio_channel::TrySendError::Io(e)) => Err(e.into()),
+    }
   }
 
   pub(crate) fn identity(&self) -> EntityId {
@@ -780,6 +1243,46 @@ impl Subscriber {
     self.create_datareader_no_key::<D, CDRDeserializerAdapter<D>>(topic, qos)
   }
 
+  /// Like [`create_datareader`](Self::create_datareader), but the DataReader
+  /// gets the given entity key, i.e. the first three bytes of its
+  /// [`EntityId`], instead of a generated one. See
+  /// [`Publisher::create_datawriter_with_entity_key`].
+  ///
+  /// Fails with [`CreateError::BadParameter`] if this participant already
+  /// has a DataReader with the same entity id.
+  pub fn create_datareader_with_entity_key<D, SA>(
+    &self,
+    entity_key: [u8; 3],
+    topic: &Topic,
+    qos: Option<QosPolicies>,
+  ) -> CreateResult<WithKeyDataReader<D, SA>>
+  where
+    D: Keyed + 'static,
+    SA: adapters::with_key::DeserializerAdapter<D>,
+  {
+    let entity_id = EntityId::new(entity_key, EntityKind::READER_WITH_KEY_USER_DEFINED);
+    self
+      .inner
+      .create_datareader(self, topic, Some(entity_id), qos, false)
+  }
+
+  /// NoKey version of
+  /// [`create_datareader_with_entity_key`](Self::create_datareader_with_entity_key)
+  pub fn create_datareader_no_key_with_entity_key<D: 'static, SA>(
+    &self,
+    entity_key: [u8; 3],
+    topic: &Topic,
+    qos: Option<QosPolicies>,
+  ) -> CreateResult<NoKeyDataReader<D, SA>>
+  where
+    SA: adapters::no_key::DeserializerAdapter<D>,
+  {
+    let entity_id = EntityId::new(entity_key, EntityKind::READER_NO_KEY_USER_DEFINED);
+    self
+      .inner
+      .create_datareader_no_key(self, topic, Some(entity_id), qos, false)
+  }
+
   // versions with callee-specified EntityId. These are for Discovery use only.
 
   pub(crate) fn create_datareader_with_entity_id_with_key<D: 'static, SA>(
@@ -850,8 +1353,54 @@ impl Subscriber {
     self.inner.participant()
   }
 
-  pub(crate) fn remove_reader(&self, guid: GUID) {
-    self.inner.remove_reader(guid);
+  /// Number of DataReaders created with this Subscriber, and not yet dropped.
+  pub fn datareader_count(&self) -> usize {
+    self.inner.datareaders_lock().count()
+  }
+
+  /// Limits the number of DataReaders that can exist in this Subscriber at
+  /// the same time. Further calls to `create_datareader*` fail with
+  /// [`CreateError::OutOfResources`]. This is meant to catch bugs, such as
+  /// creating DataReaders in a loop. `None`, the default, is no limit.
+  pub fn set_max_datareaders(&self, max: Option<usize>) {
+    self.inner.datareaders_lock().max = max;
+  }
+
+  pub(crate) fn remove_reader(&self, guid: GUID) -> bool {
+    self.inner.remove_reader(guid)
+  }
+
+  // This is used by DomainParticipant::delete_contained_entities. Deletes the
+  // contained DataReaders, and returns the GUIDs of those that were announced.
+  // No DataReaders can be created afterwards.
+  pub(crate) fn delete(&self) -> CreateResult<Vec<GUID>> {
+    self.inner.delete()
+  }
+
+  pub(crate) fn downgrade(&self) -> SubscriberWeak {
+    SubscriberWeak {
+      inner: Arc::downgrade(&self.inner),
+    }
+  }
+
+  // This is used on DataReader .enable()
+  pub(crate) fn enable_reader(
+    &self,
+    disabled_reader: &mut Option<DisabledReader>,
+  ) -> CreateResult<()> {
+    self.inner.register_reader(disabled_reader)
+  }
+}
+
+// Likewise for Subscribers
+#[derive(Clone)]
+pub(crate) struct SubscriberWeak {
+  inner: Weak<InnerSubscriber>,
+}
+
+impl SubscriberWeak {
+  pub fn upgrade(&self) -> Option<Subscriber> {
+    self.inner.upgrade().map(|inner| Subscriber { inner })
   }
 }
 
@@ -864,6 +1413,7 @@ pub struct InnerSubscriber {
   sender_remove_reader: mio_channel::SyncSender<GUID>,
   discovery_command: mio_channel::SyncSender<DiscoveryCommand>,
   security_plugins_handle: Option<SecurityPluginsHandle>,
+  datareaders: Arc<Mutex<EntityCount>>,
 }
 
 impl InnerSubscriber {
@@ -884,9 +1434,17 @@ impl InnerSubscriber {
       sender_remove_reader,
       discovery_command,
       security_plugins_handle,
+      datareaders: Arc::new(Mutex::new(EntityCount::default())),
     }
   }
 
+  fn datareaders_lock(&self) -> MutexGuard<'_, EntityCount> {
+    self
+      .datareaders
+      .lock()
+      .unwrap_or_else(|e| panic!("Subscriber DataReader count lock fail! {e:?}"))
+  }
+
   fn create_datareader_internal<D: 'static, SA>(
     &self,
     outer: &Subscriber,
@@ -923,6 +1481,8 @@ impl InnerSubscriber {
     D: Keyed,
     SA: adapters::with_key::DeserializerAdapter<D>,
   {
+    self.datareaders_lock().check_limit("DataReader")?;
+
     // incoming data notification channel from Reader to DataReader
     let (send, rec) = mio_channel::sync_channel::<()>(4);
     // status change channel from Reader to DataReader
@@ -950,6 +1510,9 @@ impl InnerSubscriber {
       Some(dp) => dp,
       None => return create_error_dropped!("DomainParticipant doesn't exist anymore."),
     };
+    if entity_id.kind().is_user_defined() {
+      dp.open_user_traffic_listeners()?;
+    }
 
     // Create a new topic to DDScache if it doesn't exist and get a handle to it
     let topic_cache_handle = match dp.dds_cache().write() {
@@ -958,6 +1521,9 @@ impl InnerSubscriber {
     };
 
     let reader_guid = GUID::new_with_prefix_and_id(dp.guid_prefix(), entity_id);
+    if discovery_db_read(&self.discovery_db).is_local_endpoint(reader_guid) {
+      return create_error_bad_parameter!("EntityId {:?} is already in use", entity_id);
+    }
 
     let data_reader_waker = Arc::new(Mutex::new(None));
 
@@ -974,18 +1540,10 @@ impl InnerSubscriber {
       data_reader_command_receiver: reader_command_receiver,
       data_reader_waker: data_reader_waker.clone(),
       poll_event_sender,
+      heartbeat_response_delay: dp.heartbeat_response_delay(),
       security_plugins: self.security_plugins_handle.clone(),
     };
 
-    {
-      let mut db = self
-        .discovery_db
-        .write()
-        .or_else(|e| create_error_poisoned!("Cannot lock discovery_db. {}", e))?;
-      db.update_local_topic_reader(&dp, topic, &new_reader);
-      db.update_topic_data_p(topic);
-    }
-
     let datareader = with_key::SimpleDataReader::<D, SA>::new(
       outer.clone(),
       entity_id,
@@ -999,16 +1557,71 @@ impl InnerSubscriber {
       data_reader_waker,
       poll_event_source,
     )?;
-
-    // Return the DataReader Reader pairs to where they are used
-    self
-      .sender_add_reader
-      .try_send(new_reader)
-      .or_else(|e| create_error_poisoned!("Cannot add DataReader. Error: {}", e))?;
+    // From now on, dropping the DataReader removes it from the count.
+    self.datareaders_lock().guids.insert(reader_guid);
+
+    let mut disabled_reader = Some(DisabledReader {
+      ingredients: new_reader,
+      topic: topic.clone(),
+    });
+    if self.qos.autoenable_created_entities() {
+      self.register_reader(&mut disabled_reader)?;
+    }
+    if let Some(disabled_reader) = disabled_reader {
+      datareader.set_disabled(disabled_reader);
+    }
 
     Ok(datareader)
   }
 
+  // Registers the reader with Discovery, to be announced, and hands it over to
+  // the event loop. If the event loop does not get the reader, it is left
+  // disabled.
+  fn register_reader(&self, disabled_reader: &mut Option<DisabledReader>) -> CreateResult<()> {
+    let guid = match disabled_reader {
+      Some(disabled_reader) => disabled_reader.ingredients.guid,
+      None => return Ok(()), // enabled already
+    };
+    if !self.datareaders_lock().guids.contains(&guid) {
+      return create_error_dropped!("DataReader {:?} has been deleted", guid);
+    }
+    let dp = match self.participant() {
+      Some(dp) => dp,
+      None => return create_error_dropped!("DomainParticipant doesn't exist anymore."),
+    };
+    let DisabledReader { ingredients, topic } = {
+      let mut db = self
+        .discovery_db
+        .write()
+        .or_else(|e| create_error_poisoned!("Cannot lock discovery_db. {}", e))?;
+      // A disabled DataReader did not reserve its EntityId in the Discovery DB
+      if db.is_local_endpoint(guid) {
+        return create_error_bad_parameter!("EntityId {:?} is already in use", guid);
+      }
+      let Some(disabled_reader) = disabled_reader.take() else {
+        return Ok(());
+      };
+      db.update_local_topic_reader(&dp, &disabled_reader.topic, &disabled_reader.ingredients);
+      db.update_topic_data_p(&disabled_reader.topic);
+      disabled_reader
+    };
+
+    // Return the DataReader Reader pairs to where they are used
+    match self.sender_add_reader.try_send(ingredients) {
+      Ok(()) => Ok(()),
+      Err(mio_channel::TrySendError::Full(ingredients))
+      | Err(mio_channel::TrySendError::Disconnected(ingredients)) => {
+        discovery_db_write(&self.discovery_db).remove_local_topic_reader(guid);
+        *disabled_reader = Some(DisabledReader { ingredients, topic });
+        create_error_poisoned!(
+          "Cannot add DataReader {:?}. The event loop is busy or gone",
+          guid
+        )
+      }
+      Err(e) => create_error_poisoned!("Cannot add DataReader. Error: {}", e),
+    }
+  }
+
   pub fn create_datareader<D: 'static, SA>(
     &self,
     outer: &Subscriber,
@@ -1088,9 +1701,43 @@ impl InnerSubscriber {
     self.domain_participant.clone().upgrade()
   }
 
-  pub(crate) fn remove_reader(&self, guid: GUID) {
+  // Returns false, if the DataReader was deleted already.
+  pub(crate) fn remove_reader(&self, guid: GUID) -> bool {
+    if !self.datareaders_lock().guids.remove(&guid) {
+      return false;
+    }
     try_send_timeout(&self.sender_remove_reader, guid, None)
       .unwrap_or_else(|e| error!("Cannot remove Reader {:?} : {:?}", guid, e));
+    true
+  }
+
+  // Removes the user-defined DataReaders, like dropping them would. Returns the
+  // GUIDs of the enabled ones, which Discovery has announced.
+  fn delete(&self) -> CreateResult<Vec<GUID>> {
+    let deleted: Vec<GUID> = {
+      let mut datareaders = self.datareaders_lock();
+      datareaders.deleted = true;
+      let deleted = datareaders
+        .guids
+        .iter()
+        .copied()
+        .filter(|guid| guid.entity_id.kind().is_user_defined())
+        .collect::<Vec<_>>();
+      for guid in &deleted {
+        datareaders.guids.remove(guid);
+      }
+      deleted
+    };
+    let mut announced = Vec::new();
+    for guid in deleted {
+      if discovery_db_read(&self.discovery_db).is_local_endpoint(guid) {
+        announced.push(guid);
+      }
+      if let Err(e) = try_send_timeout(&self.sender_remove_reader, guid, None) {
+        return create_error_dropped!("Cannot remove DataReader {guid:?}: {e:?}");
+      }
+    }
+    Ok(announced)
   }
 
   fn unwrap_or_new_entity_id(
@@ -1100,11 +1747,278 @@ impl InnerSubscriber {
   ) -> EntityId {
     // If the entity_id is given, then just use that. If not, then pull an arbitrary
     // number out of participant's hat.
-    entity_id_opt.unwrap_or_else(|| self.participant().unwrap().new_entity_id(entity_kind))
+    entity_id_opt.unwrap_or_else(|| {
+      new_unused_entity_id(&self.participant().unwrap(), &self.discovery_db, entity_kind)
+    })
+  }
+}
+
+// Generates an EntityId, skipping those that have been given explicitly to
+// DataReaders or DataWriters.
+fn new_unused_entity_id(
+  dp: &DomainParticipant,
+  discovery_db: &Arc<RwLock<DiscoveryDB>>,
+  entity_kind: EntityKind,
+) -> EntityId {
+  loop {
+    let entity_id = dp.new_entity_id(entity_kind);
+    let guid = GUID::new_with_prefix_and_id(dp.guid_prefix(), entity_id);
+    if !discovery_db_read(discovery_db).is_local_endpoint(guid) {
+      return entity_id;
+    }
   }
 }
 
 // -------------------------------------------------------------------
 
 #[cfg(test)]
-mod tests {}
+mod tests {
+  use super::*;
+  use crate::{
+    dds::{qos::QosPolicyBuilder, topic::TopicKind},
+    test::{random_data::RandomData, wait_until},
+  };
+
+  #[test]
+  fn entity_count_limits() {
+    let dp = DomainParticipant::new(42).expect("Failed to create participant");
+    let qos = QosPolicyBuilder::new().build();
+    let topic = dp
+      .create_topic(
+        "entity_count_limits".to_string(),
+        "RandomData".to_string(),
+        &qos,
+        TopicKind::WithKey,
+      )
+      .unwrap();
+
+    let publisher = dp.create_publisher(&qos).unwrap();
+    publisher.set_max_datawriters(Some(2));
+    let w1 = publisher
+      .create_datawriter_cdr::<RandomData>(&topic, None)
+      .unwrap();
+    let _w2 = publisher
+      .create_datawriter_cdr::<RandomData>(&topic, None)
+      .unwrap();
+    assert_eq!(publisher.datawriter_count(), 2);
+    assert!(matches!(
+      publisher.create_datawriter_cdr::<RandomData>(&topic, None),
+      Err(CreateError::OutOfResources { .. })
+    ));
+    drop(w1);
+    assert_eq!(publisher.datawriter_count(), 1);
+    assert!(publisher
+      .create_datawriter_cdr::<RandomData>(&topic, None)
+      .is_ok());
+
+    let subscriber = dp.create_subscriber(&qos).unwrap();
+    subscriber.set_max_datareaders(Some(2));
+    let r1 = subscriber
+      .create_datareader_cdr::<RandomData>(&topic, None)
+      .unwrap();
+    let _r2 = subscriber
+      .create_datareader_cdr::<RandomData>(&topic, None)
+      .unwrap();
+    assert_eq!(subscriber.datareader_count(), 2);
+    assert!(matches!(
+      subscriber.create_datareader_cdr::<RandomData>(&topic, None),
+      Err(CreateError::OutOfResources { .. })
+    ));
+    drop(r1);
+    assert_eq!(subscriber.datareader_count(), 1);
+    assert!(subscriber
+      .create_datareader_cdr::<RandomData>(&topic, None)
+      .is_ok());
+  }
+
+  #[test]
+  fn explicit_entity_keys() {
+    let dp = DomainParticipant::new(43).expect("Failed to create participant");
+    let qos = QosPolicyBuilder::new().build();
+    let topic = dp
+      .create_topic(
+        "explicit_entity_keys".to_string(),
+        "RandomData".to_string(),
+        &qos,
+        TopicKind::WithKey,
+      )
+      .unwrap();
+    let publisher = dp.create_publisher(&qos).unwrap();
+    let subscriber = dp.create_subscriber(&qos).unwrap();
+    type Writer = WithKeyDataWriter<RandomData, CDRSerializerAdapter<RandomData, LittleEndian>>;
+    type Reader = WithKeyDataReader<RandomData, CDRDeserializerAdapter<RandomData>>;
+
+    let w1: Writer = publisher
+      .create_datawriter_with_entity_key([0x12, 0x34, 0x56], &topic, None)
+      .unwrap();
+    let w2: Writer = publisher
+      .create_datawriter_with_entity_key([0, 0, 0x42], &topic, None)
+      .unwrap();
+    assert_eq!(
+      w1.guid(),
+      GUID::new(
+        dp.guid_prefix(),
+        EntityId::new([0x12, 0x34, 0x56], EntityKind::WRITER_WITH_KEY_USER_DEFINED)
+      )
+    );
+    assert_eq!(
+      w2.guid(),
+      GUID::new(
+        dp.guid_prefix(),
+        EntityId::new([0, 0, 0x42], EntityKind::WRITER_WITH_KEY_USER_DEFINED)
+      )
+    );
+
+    // The same entity key is rejected for a second writer, but a reader has a
+    // different entity kind, so it may use it.
+    assert!(matches!(
+      publisher.create_datawriter_with_entity_key::<RandomData, CDRSerializerAdapter<_>>(
+        [0, 0, 0x42],
+        &topic,
+        None
+      ),
+      Err(CreateError::BadParameter { .. })
+    ));
+    let r: Reader = subscriber
+      .create_datareader_with_entity_key([0, 0, 0x42], &topic, None)
+      .unwrap();
+    assert_eq!(r.guid().entity_id.entity_key, [0, 0, 0x42]);
+
+    // Generated ids skip explicitly given ones
+    let generated: Writer = publisher.create_datawriter(&topic, None).unwrap();
+    let [a, b, c] = generated.guid().entity_id.entity_key;
+    let [_, a, b, c] = (u32::from_be_bytes([0, a, b, c]) + 1).to_be_bytes();
+    let next_key = [a, b, c];
+    let explicit: Writer = publisher
+      .create_datawriter_with_entity_key(next_key, &topic, None)
+      .unwrap();
+    let generated_after: Writer = publisher.create_datawriter(&topic, None).unwrap();
+    assert_ne!(generated_after.guid(), explicit.guid());
+  }
+
+  #[test]
+  fn disabled_writer_is_announced_only_when_enabled() {
+    use std::time::{Duration, Instant};
+
+    use crate::dds::qos::policy::EntityFactory;
+
+    let domain_id = 70;
+    let dp1 = DomainParticipant::new(domain_id).expect("Failed to create participant 1");
+    let dp2 = DomainParticipant::new(domain_id).expect("Failed to create participant 2");
+    let qos = QosPolicyBuilder::new().build();
+    let topic = dp1
+      .create_topic(
+        "disabled_writer".to_string(),
+        "RandomData".to_string(),
+        &qos,
+        TopicKind::WithKey,
+      )
+      .unwrap();
+    let publisher = dp1
+      .create_publisher(
+        &QosPolicyBuilder::new()
+          .entity_factory(EntityFactory {
+            autoenable_created_entities: false,
+          })
+          .build(),
+      )
+      .unwrap();
+    let writer: WithKeyDataWriter<RandomData> = publisher.create_datawriter(&topic, None).unwrap();
+    assert!(!writer.is_enabled());
+
+    let sample = RandomData {
+      a: 1,
+      b: "disabled".to_string(),
+    };
+    assert!(matches!(
+      writer.write(sample.clone(), None),
+      Err(WriteError::NotEnabled { .. })
+    ));
+
+    let writer_discovered = || {
+      dp2
+        .discovered_writers()
+        .iter()
+        .any(|w| w.writer_proxy.remote_writer_guid == writer.guid())
+    };
+    // Discovery announces local writers every couple of seconds
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while Instant::now() < deadline {
+      assert!(!writer_discovered(), "Disabled writer was announced");
+      std::thread::sleep(Duration::from_millis(200));
+    }
+
+    writer.enable().unwrap();
+    assert!(writer.is_enabled());
+    writer.write(sample, None).unwrap();
+    assert!(
+      wait_until(Duration::from_secs(10), writer_discovered),
+      "Enabled writer was not announced"
+    );
+  }
+
+  #[test]
+  fn failed_enable_leaves_entities_disabled() {
+    use std::time::Duration;
+
+    use crate::dds::qos::policy::EntityFactory;
+
+    let dp = DomainParticipant::new(75).expect("Failed to create participant");
+    let qos = QosPolicyBuilder::new().build();
+    let disabled_qos = QosPolicyBuilder::new()
+      .entity_factory(EntityFactory {
+        autoenable_created_entities: false,
+      })
+      .build();
+    let topic = dp
+      .create_topic(
+        "failed_enable".to_string(),
+        "RandomData".to_string(),
+        &qos,
+        TopicKind::WithKey,
+      )
+      .unwrap();
+    let writer_key = [0, 0, 0x71];
+    let reader_key = [0, 0, 0x72];
+
+    // A disabled entity does not reserve its EntityId, so another one can take
+    // it. Then enabling fails.
+    let disabled_publisher = dp.create_publisher(&disabled_qos).unwrap();
+    let disabled_subscriber = dp.create_subscriber(&disabled_qos).unwrap();
+    let writer: WithKeyDataWriter<RandomData> = disabled_publisher
+      .create_datawriter_with_entity_key(writer_key, &topic, None)
+      .unwrap();
+    let reader: WithKeyDataReader<RandomData> = disabled_subscriber
+      .create_datareader_with_entity_key(reader_key, &topic, None)
+      .unwrap();
+    let publisher = dp.create_publisher(&qos).unwrap();
+    let subscriber = dp.create_subscriber(&qos).unwrap();
+    let other_writer: WithKeyDataWriter<RandomData> = publisher
+      .create_datawriter_with_entity_key(writer_key, &topic, None)
+      .unwrap();
+    let other_reader: WithKeyDataReader<RandomData> = subscriber
+      .create_datareader_with_entity_key(reader_key, &topic, None)
+      .unwrap();
+
+    assert!(matches!(
+      writer.enable(),
+      Err(CreateError::BadParameter { .. })
+    ));
+    assert!(!writer.is_enabled());
+    assert!(matches!(
+      reader.enable(),
+      Err(CreateError::BadParameter { .. })
+    ));
+    assert!(!reader.is_enabled());
+
+    // Enabling can be retried, once the EntityIds are free again
+    drop(other_writer);
+    drop(other_reader);
+    let enabled = wait_until(Duration::from_secs(10), || {
+      writer.enable().is_ok() && reader.enable().is_ok()
+    });
+    assert!(enabled, "Enabling did not succeed");
+    assert!(writer.is_enabled());
+    assert!(reader.is_enabled());
+  }
+}
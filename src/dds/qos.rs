@@ -63,6 +63,48 @@ pub enum QosPolicyId {
   Property, // No Id in the security spec (But this is from older DDS/RTPs spec.)
 }
 
+/// Comparison of the QoS of a local DataWriter or DataReader with the QoS
+/// advertised by a remote endpoint on the same topic.
+///
+/// See e.g.
+/// [`DataWriter::qos_diff`](crate::with_key::DataWriter::qos_diff).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct QosDiff {
+  /// QoS of the local endpoint
+  pub local: QosPolicies,
+  /// QoS announced by the remote endpoint in Discovery
+  pub remote: QosPolicies,
+  /// Policies announced by the remote endpoint that have a different value
+  /// locally. See [`QosPolicies::differing_policies`].
+  pub differing: Vec<QosPolicyId>,
+  /// Policies that prevent the endpoints from matching. These are also in
+  /// `differing`.
+  pub incompatible: Vec<QosPolicyId>,
+}
+
+impl QosDiff {
+  // `local_offers` tells if the local endpoint is the writer, i.e. the
+  // offering side in the compatibility check.
+  pub(crate) fn new(local: QosPolicies, remote: QosPolicies, local_offers: bool) -> Self {
+    let differing = local.differing_policies(&remote);
+    let incompatible = if local_offers {
+      local.compatible_with(&remote)
+    } else {
+      remote.compatible_with(&local)
+    };
+    QosDiff {
+      local,
+      remote,
+      differing,
+      incompatible,
+    }
+  }
+
+  pub fn is_compatible(&self) -> bool {
+    self.incompatible.is_empty()
+  }
+}
+
 /// Utility for building [QosPolicies]
 #[derive(Default)]
 pub struct QosPolicyBuilder {
@@ -78,7 +120,10 @@ pub struct QosPolicyBuilder {
   history: Option<policy::History>,
   resource_limits: Option<policy::ResourceLimits>,
   lifespan: Option<policy::Lifespan>,
-  #[cfg(feature = "security")]
+  transport_priority: Option<policy::TransportPriority>,
+  writer_data_lifecycle: Option<policy::WriterDataLifecycle>,
+  reader_data_lifecycle: Option<policy::ReaderDataLifecycle>,
+  entity_factory: Option<policy::EntityFactory>,
   property: Option<policy::Property>,
 }
 
@@ -159,7 +204,36 @@ impl QosPolicyBuilder {
     self
   }
 
-  #[cfg(feature = "security")]
+  #[must_use]
+  pub const fn transport_priority(mut self, transport_priority: policy::TransportPriority) -> Self {
+    self.transport_priority = Some(transport_priority);
+    self
+  }
+
+  #[must_use]
+  pub const fn writer_data_lifecycle(
+    mut self,
+    writer_data_lifecycle: policy::WriterDataLifecycle,
+  ) -> Self {
+    self.writer_data_lifecycle = Some(writer_data_lifecycle);
+    self
+  }
+
+  #[must_use]
+  pub const fn reader_data_lifecycle(
+    mut self,
+    reader_data_lifecycle: policy::ReaderDataLifecycle,
+  ) -> Self {
+    self.reader_data_lifecycle = Some(reader_data_lifecycle);
+    self
+  }
+
+  #[must_use]
+  pub const fn entity_factory(mut self, entity_factory: policy::EntityFactory) -> Self {
+    self.entity_factory = Some(entity_factory);
+    self
+  }
+
   #[must_use]
   pub fn property(mut self, property: policy::Property) -> Self {
     self.property = Some(property);
@@ -180,7 +254,10 @@ impl QosPolicyBuilder {
       history: self.history,
       resource_limits: self.resource_limits,
       lifespan: self.lifespan,
-      #[cfg(feature = "security")]
+      transport_priority: self.transport_priority,
+      writer_data_lifecycle: self.writer_data_lifecycle,
+      reader_data_lifecycle: self.reader_data_lifecycle,
+      entity_factory: self.entity_factory,
       property: self.property,
     }
   }
@@ -204,7 +281,10 @@ pub struct QosPolicies {
   pub(crate) history: Option<policy::History>,
   pub(crate) resource_limits: Option<policy::ResourceLimits>,
   pub(crate) lifespan: Option<policy::Lifespan>,
-  #[cfg(feature = "security")]
+  pub(crate) transport_priority: Option<policy::TransportPriority>,
+  pub(crate) writer_data_lifecycle: Option<policy::WriterDataLifecycle>,
+  pub(crate) reader_data_lifecycle: Option<policy::ReaderDataLifecycle>,
+  pub(crate) entity_factory: Option<policy::EntityFactory>,
   pub(crate) property: Option<policy::Property>,
 }
 
@@ -279,11 +359,46 @@ impl QosPolicies {
     self.lifespan
   }
 
-  #[cfg(feature = "security")]
+  pub const fn transport_priority(&self) -> Option<policy::TransportPriority> {
+    self.transport_priority
+  }
+
+  pub const fn writer_data_lifecycle(&self) -> Option<policy::WriterDataLifecycle> {
+    self.writer_data_lifecycle
+  }
+
+  pub const fn reader_data_lifecycle(&self) -> Option<policy::ReaderDataLifecycle> {
+    self.reader_data_lifecycle
+  }
+
+  pub const fn entity_factory(&self) -> Option<policy::EntityFactory> {
+    self.entity_factory
+  }
+
+  // Whether DataWriters and DataReaders created with these QoS, as the QoS
+  // of their Publisher or Subscriber, are enabled at creation. The policy of
+  // the DomainParticipant is not consulted. It would apply to Publishers and
+  // Subscribers, which are always enabled.
+  pub(crate) fn autoenable_created_entities(&self) -> bool {
+    self
+      .entity_factory
+      .map_or(true, |ef| ef.autoenable_created_entities)
+  }
+
   pub fn property(&self) -> Option<policy::Property> {
     self.property.clone()
   }
 
+  // Value of the named string property in the Property policy, if any
+  pub(crate) fn get_optional_property(&self, property_name: &str) -> Option<String> {
+    self.property.as_ref().and_then(|p| {
+      p.value
+        .iter()
+        .find(|prop| prop.name == property_name)
+        .map(|prop| prop.value())
+    })
+  }
+
   /// Merge two QosPolicies
   ///
   /// Constructs a QosPolicy, where each policy is taken from `self`,
@@ -303,7 +418,10 @@ impl QosPolicies {
       history: other.history.or(self.history),
       resource_limits: other.resource_limits.or(self.resource_limits),
       lifespan: other.lifespan.or(self.lifespan),
-      #[cfg(feature = "security")]
+      transport_priority: other.transport_priority.or(self.transport_priority),
+      writer_data_lifecycle: other.writer_data_lifecycle.or(self.writer_data_lifecycle),
+      reader_data_lifecycle: other.reader_data_lifecycle.or(self.reader_data_lifecycle),
+      entity_factory: other.entity_factory.or(self.entity_factory),
       property: other.property.clone().or(self.property.clone()),
     }
   }
@@ -322,23 +440,67 @@ impl QosPolicies {
   ///
   /// This is not symmetric.
   pub fn compliance_failure_wrt(&self, other: &Self) -> Option<QosPolicyId> {
+    self.compatible_with(other).first().copied()
+  }
+
+  /// Check offered QoS against requested QoS, and list all the policies that
+  /// are incompatible.
+  ///
+  /// `self` is the "offered" (publisher) QoS
+  /// `other` is the "requested" (subscriber) QoS
+  ///
+  /// An empty result means that the policies are compatible. The rules are
+  /// those of [`compliance_failure_wrt`](Self::compliance_failure_wrt).
+  pub fn compatible_with(&self, other: &Self) -> Vec<QosPolicyId> {
     trace!(
       "QoS compatibility check - offered: {:?} - requested {:?}",
       self,
       other
     );
-    let result = self.compliance_failure_wrt_impl(other);
+    let result = self.incompatible_policies(other);
     trace!("Result: {:?}", result);
     result
   }
 
-  fn compliance_failure_wrt_impl(&self, other: &Self) -> Option<QosPolicyId> {
+  /// Lists the policies that are defined in `other`, but have a different
+  /// value (or no value) in `self`.
+  ///
+  /// Policies that are not defined in `other` are not compared. This way a
+  /// local QoS can be compared with one advertised in Discovery, which
+  /// does not carry all the policies.
+  pub fn differing_policies(&self, other: &Self) -> Vec<QosPolicyId> {
+    let mut differing = Vec::new();
+    macro_rules! compare {
+      ($member:ident, $id:ident) => {
+        if other.$member.is_some() && self.$member != other.$member {
+          differing.push(QosPolicyId::$id);
+        }
+      };
+    }
+    compare!(durability, Durability);
+    compare!(presentation, Presentation);
+    compare!(deadline, Deadline);
+    compare!(latency_budget, LatencyBudget);
+    compare!(ownership, Ownership);
+    compare!(liveliness, Liveliness);
+    compare!(time_based_filter, TimeBasedFilter);
+    compare!(reliability, Reliability);
+    compare!(destination_order, DestinationOrder);
+    compare!(history, History);
+    compare!(resource_limits, ResourceLimits);
+    compare!(lifespan, Lifespan);
+    compare!(property, Property);
+    differing
+  }
+
+  fn incompatible_policies(&self, other: &Self) -> Vec<QosPolicyId> {
     // TODO: Check for cases where policy is requested, but not offered (None)
+    let mut incompatible = Vec::new();
 
     // check Durability: Offered must be better than or equal to Requested.
     if let (Some(off), Some(req)) = (self.durability, other.durability) {
       if off < req {
-        return Some(QosPolicyId::Durability);
+        incompatible.push(QosPolicyId::Durability);
       }
     }
 
@@ -351,14 +513,14 @@ impl QosPolicies {
         || (req.ordered_access && !off.ordered_access)
         || (req.access_scope > off.access_scope)
       {
-        return Some(QosPolicyId::Presentation);
+        incompatible.push(QosPolicyId::Presentation);
       }
     }
 
     // check Deadline: offered period <= requested period
     if let (Some(off), Some(req)) = (self.deadline, other.deadline) {
       if off.0 > req.0 {
-        return Some(QosPolicyId::Deadline);
+        incompatible.push(QosPolicyId::Deadline);
       }
     }
 
@@ -366,15 +528,15 @@ impl QosPolicies {
     // offered duration <= requested duration
     if let (Some(off), Some(req)) = (self.latency_budget, other.latency_budget) {
       if off.duration > req.duration {
-        return Some(QosPolicyId::LatencyBudget);
+        incompatible.push(QosPolicyId::LatencyBudget);
       }
     }
 
     // check Ownership:
-    // offered kind == requested kind
+    // offered kind == requested kind. Strength does not matter.
     if let (Some(off), Some(req)) = (self.ownership, other.ownership) {
-      if off != req {
-        return Some(QosPolicyId::Ownership);
+      if std::mem::discriminant(&off) != std::mem::discriminant(&req) {
+        incompatible.push(QosPolicyId::Ownership);
       }
     }
 
@@ -386,7 +548,7 @@ impl QosPolicies {
     // See Ord implementation on Liveliness.
     if let (Some(off), Some(req)) = (self.liveliness, other.liveliness) {
       if off < req {
-        return Some(QosPolicyId::Liveliness);
+        incompatible.push(QosPolicyId::Liveliness);
       }
     }
 
@@ -395,7 +557,7 @@ impl QosPolicies {
     // kind ranking: BEST_EFFORT < RELIABLE
     if let (Some(off), Some(req)) = (self.reliability, other.reliability) {
       if off < req {
-        return Some(QosPolicyId::Reliability);
+        incompatible.push(QosPolicyId::Reliability);
       }
     }
 
@@ -404,12 +566,11 @@ impl QosPolicies {
     // kind ranking: BY_RECEPTION_TIMESTAMP < BY_SOURCE_TIMESTAMP
     if let (Some(off), Some(req)) = (self.destination_order, other.destination_order) {
       if off < req {
-        return Some(QosPolicyId::DestinationOrder);
+        incompatible.push(QosPolicyId::DestinationOrder);
       }
     }
 
-    // default value. no incompatibility detected.
-    None
+    incompatible
   }
 
   // serialization
@@ -433,8 +594,11 @@ impl QosPolicies {
       history,
       resource_limits,
       lifespan,
-      #[cfg(feature = "security")]
-        property: _, // TODO: properties to parameter list?
+      transport_priority: _, // Not part of the builtin topic data. Only used locally.
+      writer_data_lifecycle: _, // Likewise
+      reader_data_lifecycle: _, // Likewise
+      entity_factory: _,     // Likewise
+      property: _,           // TODO: properties to parameter list?
     } = self;
 
     macro_rules! emit {
@@ -579,7 +743,11 @@ impl QosPolicies {
     let resource_limits: Option<policy::ResourceLimits> = get_option!(PID_RESOURCE_LIMITS);
     let lifespan: Option<policy::Lifespan> = get_option!(PID_LIFESPAN);
 
-    #[cfg(feature = "security")]
+    let transport_priority: Option<policy::TransportPriority> = None; // see to_parameter_list()
+    let writer_data_lifecycle: Option<policy::WriterDataLifecycle> = None; // likewise
+    let reader_data_lifecycle: Option<policy::ReaderDataLifecycle> = None; // likewise
+    let entity_factory: Option<policy::EntityFactory> = None; // likewise
+
     let property: Option<policy::Property> = None; // TODO: Should also properties be read?
 
     // We construct using the struct syntax directly rather than the builder,
@@ -597,7 +765,10 @@ impl QosPolicies {
       history,
       resource_limits,
       lifespan,
-      #[cfg(feature = "security")]
+      transport_priority,
+      writer_data_lifecycle,
+      reader_data_lifecycle,
+      entity_factory,
       property,
     })
   }
@@ -635,6 +806,9 @@ struct ReliabilitySerialization {
 // Use is in resource_limits.
 pub const LENGTH_UNLIMITED: i32 = -1;
 
+/// Elements of the [`Property`](policy::Property) policy
+pub mod property;
+
 // put these into a submodule to avoid repeating the word "policy" or
 // "qospolicy"
 /// Contains all available QoSPolicies
@@ -644,12 +818,10 @@ pub mod policy {
   use speedy::{Readable, Writable};
   #[allow(unused_imports)]
   use log::{debug, error, info, trace, warn};
-  #[cfg(feature = "security")]
   use speedy::{Context, IsEof, Reader, Writer};
 
-  use crate::structure::duration::Duration;
-  #[cfg(feature = "security")]
-  use crate::serialization::speedy_pl_cdr_helpers::*;
+  use super::property;
+  use crate::{serialization::speedy_pl_cdr_helpers::*, structure::duration::Duration};
 
   /*
   pub struct UserData {
@@ -663,11 +835,61 @@ pub mod policy {
   pub struct GroupData {
     pub value: Vec<u8>,
   }
+  */
 
+  /// DDS 2.2.3.15 TRANSPORT_PRIORITY
+  ///
+  /// RustDDS does not pass this on to the network, but uses it to schedule
+  /// DataWriters in the event loop: Writers with a higher value get their
+  /// sending and repair work done first. The default is 0.
+  #[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Readable, Writable)]
   pub struct TransportPriority {
     pub value: i32,
   }
-  */
+
+  /// DDS 2.2.3.21 WRITER_DATA_LIFECYCLE
+  ///
+  /// If `autodispose_unregistered_instances` is true, a DataWriter disposes
+  /// an instance when it unregisters it, so that DataReaders see the
+  /// instance as `NotAliveDisposed` rather than `NotAliveNoWriters`. This
+  /// applies to WithKey topics only. If the policy is not set, the default is
+  /// `true`, as in the DDS spec.
+  #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+  pub struct WriterDataLifecycle {
+    pub autodispose_unregistered_instances: bool,
+  }
+
+  /// DDS 2.2.3.22 READER_DATA_LIFECYCLE
+  ///
+  /// A DataReader purges all samples of an instance, and the instance itself,
+  /// once the instance has been `NotAliveNoWriters` for
+  /// `autopurge_nowriter_samples_delay`, or `NotAliveDisposed` for
+  /// `autopurge_disposed_samples_delay`. This keeps long-running DataReaders
+  /// from accumulating dead instances. Samples are purged also if the
+  /// application has not read them. If the policy is not set, both delays are
+  /// infinite, i.e. nothing is purged, as in the DDS spec.
+  #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+  pub struct ReaderDataLifecycle {
+    pub autopurge_nowriter_samples_delay: Duration,
+    pub autopurge_disposed_samples_delay: Duration,
+  }
+
+  /// DDS 2.2.3.20 ENTITY_FACTORY
+  ///
+  /// If `autoenable_created_entities` is false in the QoS of a Publisher or
+  /// Subscriber, the DataWriters and DataReaders it creates are disabled:
+  /// they are not announced in Discovery and do not match remote endpoints,
+  /// until `enable()` is called on them. This way their setup can be
+  /// completed before they start communicating. If the policy is not set, the
+  /// default is `true`, as in the DDS spec. DomainParticipants, Publishers and
+  /// Subscribers do not communicate by themselves, so they are always enabled.
+  /// For the same reason the policy has no effect in the QoS of a
+  /// DomainParticipant, where it would control enabling its Publishers and
+  /// Subscribers.
+  #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+  pub struct EntityFactory {
+    pub autoenable_created_entities: bool,
+  }
 
   /// DDS 2.2.3.16 LIFESPAN
   #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Readable, Writable)]
@@ -806,6 +1028,10 @@ pub mod policy {
   #[derive(Copy, Clone, Debug, PartialEq, Eq, Ord, PartialOrd, Hash, Readable, Writable)]
   pub enum DestinationOrder {
     ByReceptionTimestamp,
+    /// Samples are delivered in source timestamp order. A Reader holds
+    /// received samples for a short reordering window (see
+    /// `READER_REORDERING_WINDOW_PROPERTY`), and drops samples older than the
+    /// latest one already received for the same instance.
     BySourceTimeStamp,
   }
 
@@ -840,14 +1066,12 @@ pub mod policy {
   // DDS Security spec v1.1
   // Section 7.2.5 PropertyQosPolicy, DomainParticipantQos, DataWriterQos, and
   // DataReaderQos
-  #[cfg(feature = "security")]
   #[derive(Clone, Debug, PartialEq, Eq)]
   pub struct Property {
-    pub value: Vec<security::types::Property>,
-    pub binary_value: Vec<security::types::BinaryProperty>,
+    pub value: Vec<property::Property>,
+    pub binary_value: Vec<property::BinaryProperty>,
   }
 
-  #[cfg(feature = "security")]
   impl<'a, C: Context> Readable<'a, C> for Property {
     fn read_from<R: Reader<'a, C>>(reader: &mut R) -> Result<Self, C::Error> {
       let count = reader.read_u32()?;
@@ -856,7 +1080,7 @@ pub mod policy {
       let mut prev_len = 0;
       for _ in 0..count {
         read_pad(reader, prev_len, 4)?;
-        let s: security::types::Property = reader.read_value()?;
+        let s: property::Property = reader.read_value()?;
         prev_len = s.serialized_len();
         value.push(s);
       }
@@ -871,7 +1095,7 @@ pub mod policy {
           prev_len = 0;
           for _ in 0..count {
             read_pad(reader, prev_len, 4)?;
-            let s: security::types::BinaryProperty = reader.read_value()?;
+            let s: property::BinaryProperty = reader.read_value()?;
             prev_len = s.serialized_len();
             binary_value.push(s);
           }
@@ -897,12 +1121,11 @@ pub mod policy {
   // we have to keep track of alignment.
   // Again, alignment comes BEFORE string length, or vector item count, not after
   // string.
-  #[cfg(feature = "security")]
   impl<C: Context> Writable<C> for Property {
     fn write_to<T: ?Sized + Writer<C>>(&self, writer: &mut T) -> Result<(), C::Error> {
       // First self.value
       // Only those properties with propagate=true are written
-      let propagate_value: Vec<&security::Property> =
+      let propagate_value: Vec<&property::Property> =
         self.value.iter().filter(|p| p.propagate).collect();
 
       // propagate_value vector length
@@ -917,7 +1140,7 @@ pub mod policy {
 
       // Then self.bin_value
       // Only those binary properties with propagate=true are written
-      let propagate_bin_value: Vec<&security::BinaryProperty> =
+      let propagate_bin_value: Vec<&property::BinaryProperty> =
         self.binary_value.iter().filter(|p| p.propagate).collect();
 
       // propagate_bin_value vector length
@@ -0,0 +1,239 @@
+// Property and BinaryProperty are the elements of the PropertyQosPolicy,
+// see qos::policy::Property. They were introduced by the DDS Security
+// specification, but the policy is also useful without security, e.g. for
+// implementation-specific settings.
+
+use bytes::Bytes;
+use speedy::{Context, Readable, Reader, Writable, Writer};
+use serde::{Deserialize, Serialize};
+
+use crate::serialization::speedy_pl_cdr_helpers::*;
+
+/// Name of a DataWriter property that enables redundant delivery.
+///
+/// If the value is `"true"` and the writer is Reliable, each message is sent to
+/// all UDP locators of a matched reader, unicast and multicast, rather than
+/// only one of them. Use [`Property::local`] for this.
+pub const WRITER_REDUNDANCY_PROPERTY: &str = "rustdds.writer.redundancy";
+
+/// Name of a DataWriter property that sets the period of HEARTBEATs sent by
+/// a Reliable writer, in milliseconds.
+///
+/// The default is one second. With LIVELINESS `ManualByTopic`, the period is
+/// at most a third of the lease duration.
+pub const WRITER_HEARTBEAT_PERIOD_PROPERTY: &str = "rustdds.writer.heartbeat_period_ms";
+
+/// Name of a DataWriter property that controls HEARTBEAT suppression.
+///
+/// By default, a Reliable writer skips its periodic HEARTBEAT when all
+/// matched readers have acknowledged everything, to cut idle traffic. Set
+/// this to `"false"` to send HEARTBEATs every period regardless.
+pub const WRITER_HEARTBEAT_SUPPRESSION_PROPERTY: &str = "rustdds.writer.heartbeat_suppression";
+
+/// Name of a DataReader property that enables recovery from sequence number
+/// resets.
+///
+/// Some Writers start numbering their samples again from a low sequence
+/// number without restarting. A Reader would normally ignore these samples as
+/// old duplicates. If this is set to a positive integer N, then after N
+/// consecutive sequence numbers that are more than N below the expected ones,
+/// the Reader resets its tracking of that Writer and accepts data again. The
+/// first N-1 samples after the reset are lost.
+pub const READER_SEQUENCE_NUMBER_RESET_TOLERANCE_PROPERTY: &str =
+  "rustdds.reader.sequence_number_reset_tolerance";
+
+/// Name of a DataReader property that sets the reordering window of
+/// DESTINATION_ORDER `BySourceTimeStamp`, in milliseconds.
+///
+/// Such a Reader holds each received sample for this long before storing it,
+/// so that samples arriving out of order can be stored in source timestamp
+/// order. The default is 50 ms. Zero stores samples as soon as they are
+/// received.
+pub const READER_REORDERING_WINDOW_PROPERTY: &str = "rustdds.reader.reordering_window_ms";
+
+/// Name of a DomainParticipant property that controls the replay check of
+/// authentication handshake messages (ParticipantStatelessMessage).
+///
+/// By default, a handshake message is ignored if its sequence number is lower
+/// than that of a message already received from the same participant, e.g. if
+/// an old message is replayed. Retransmissions have the sequence number of
+/// the original, so they pass the check. Set this to `"false"` to accept all
+/// messages. Only meaningful with security enabled.
+pub const PARTICIPANT_STATELESS_MESSAGE_REPLAY_CHECK_PROPERTY: &str =
+  "rustdds.participant.stateless_message_replay_check";
+
+// Property_t type from section 7.2.1 of the Security specification (v. 1.1)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)] // for CDR in Discovery
+pub struct Property {
+  pub(crate) name: String,
+  pub(crate) value: String,
+  pub(crate) propagate: bool, // NOT SERIALIZED
+}
+
+impl<'a, C: Context> Readable<'a, C> for Property {
+  fn read_from<R: Reader<'a, C>>(reader: &mut R) -> Result<Self, C::Error> {
+    let name: StringWithNul = reader.read_value()?;
+
+    read_pad(reader, name.len(), 4)?; // pad according to previous read
+    let value: StringWithNul = reader.read_value()?;
+
+    Ok(Property {
+      name: name.into(),
+      value: value.into(),
+      propagate: true, // since we read this from thw wire, it was propagated
+    })
+  }
+}
+
+// Writing several strings is a bit complicated, because
+// we have to keep track of alignment.
+// Again, alignment comes BEFORE string length, or vector item count, not after
+// string.
+impl<C: Context> Writable<C> for Property {
+  fn write_to<T: ?Sized + Writer<C>>(&self, writer: &mut T) -> Result<(), C::Error> {
+    if self.propagate {
+      let name = StringWithNul::from(self.name.clone());
+      // nothing yet to pad
+      writer.write_value(&name)?;
+
+      write_pad(writer, name.len(), 4)?;
+      let value = StringWithNul::from(self.value.clone());
+      writer.write_value(&value)?;
+    }
+    Ok(())
+  }
+}
+
+impl Property {
+  /// A string property that is sent to remote participants, if the
+  /// policy is propagated
+  pub fn new(name: &str, value: &str) -> Self {
+    Property {
+      name: name.to_string(),
+      value: value.to_string(),
+      propagate: true,
+    }
+  }
+
+  /// A string property that is only used locally
+  pub fn local(name: &str, value: &str) -> Self {
+    Property {
+      propagate: false,
+      ..Self::new(name, value)
+    }
+  }
+
+  pub fn name(&self) -> &str {
+    &self.name
+  }
+
+  pub fn serialized_len(&self) -> usize {
+    let first = 4 + self.name.len() + 1;
+    let misalign = first % 4;
+    let align = if misalign > 0 { 4 - misalign } else { 0 };
+    let second = 4 + self.value.len() + 1;
+    first + align + second
+  }
+
+  pub fn value(&self) -> String {
+    self.value.clone()
+  }
+}
+
+// BinaryProperty_t type from section 7.2.2 of the Security specification (v.
+// 1.1)
+// // Serialize, Deserialize for CDR in Discovery
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(into = "repr::BinaryProperty", from = "repr::BinaryProperty")]
+pub struct BinaryProperty {
+  pub(crate) name: String,    // public because of serialization
+  pub(crate) value: Bytes,    // Serde cannot derive for Bytes, therefore use repr::
+  pub(crate) propagate: bool, // propagate field is not serialized
+}
+
+impl BinaryProperty {
+  pub fn with_propagate(name: &str, value: Bytes) -> Self {
+    BinaryProperty {
+      name: name.to_string(),
+      value,
+      propagate: true,
+    }
+  }
+
+  pub fn value(&self) -> Bytes {
+    self.value.clone()
+  }
+}
+
+mod repr {
+  use serde::{Deserialize, Serialize};
+
+  #[derive(Serialize, Deserialize)]
+  pub struct BinaryProperty {
+    pub(crate) name: String,
+    pub(crate) value: Vec<u8>,
+    //pub(crate) propagate: bool,
+    // The "propagate" flag is never serialized, because it is always true, if we are serializing.
+  }
+
+  impl From<BinaryProperty> for super::BinaryProperty {
+    fn from(bp: BinaryProperty) -> super::BinaryProperty {
+      super::BinaryProperty {
+        name: bp.name,
+        value: bp.value.into(),
+        propagate: true,
+      }
+    }
+  }
+
+  impl From<super::BinaryProperty> for BinaryProperty {
+    fn from(bp: super::BinaryProperty) -> BinaryProperty {
+      BinaryProperty {
+        name: bp.name,
+        value: bp.value.into(),
+      }
+    }
+  }
+}
+
+impl BinaryProperty {
+  pub fn serialized_len(&self) -> usize {
+    let first = 4 + self.name.len() + 1;
+    let misalign = first % 4;
+    let align = if misalign > 0 { 4 - misalign } else { 0 };
+    let second = 4 + self.value.len(); // no nul terminator byte here
+    first + align + second
+  }
+}
+
+impl<'a, C: Context> Readable<'a, C> for BinaryProperty {
+  fn read_from<R: Reader<'a, C>>(reader: &mut R) -> Result<Self, C::Error> {
+    let name: StringWithNul = reader.read_value()?;
+
+    read_pad(reader, name.len(), 4)?; // pad according to previous read
+    let value: Vec<u8> = reader.read_value()?;
+
+    Ok(BinaryProperty {
+      name: name.into(),
+      value: value.into(),
+      propagate: true, // since we read this from thw wire, it was propagated
+    })
+  }
+}
+
+// Writing several strings is a bit complicated, because
+// we have to keep track of alignment.
+// Again, alignment comes BEFORE string length, or vector item count, not after
+// string.
+impl<C: Context> Writable<C> for BinaryProperty {
+  fn write_to<T: ?Sized + Writer<C>>(&self, writer: &mut T) -> Result<(), C::Error> {
+    if self.propagate {
+      let name = StringWithNul::from(self.name.clone());
+      writer.write_value(&name)?;
+
+      write_pad(writer, name.len(), 4)?;
+      writer.write_value(&<Vec<u8>>::from(self.value.clone()))?;
+    }
+    Ok(())
+  }
+}
@@ -123,6 +123,11 @@ pub enum WriteError<D> {
   #[error("Write operation timed out while blocking")]
   WouldBlock { data: D },
 
+  /// The DataWriter has not been enabled yet. See
+  /// [`EntityFactory`](crate::policy::EntityFactory).
+  #[error("DataWriter is not enabled")]
+  NotEnabled { data: D },
+
   /// Something that should not go wrong went wrong anyway.
   /// This is usually a bug in RustDDS
   #[error("Internal error: {reason}")]
@@ -159,6 +164,7 @@ impl<D> WriteError<D> {
       WriteError::Poisoned { reason, data: _ } => WriteError::Poisoned { reason, data: () },
       WriteError::Io(e) => WriteError::Io(e),
       WriteError::WouldBlock { data: _ } => WriteError::WouldBlock { data: () },
+      WriteError::NotEnabled { data: _ } => WriteError::NotEnabled { data: () },
       WriteError::Internal { reason } => WriteError::Internal { reason },
     }
   }
@@ -180,6 +186,7 @@ pub(crate) fn unwrap_no_key_write_error<D>(
       data: data.d,
     },
     WriteError::WouldBlock { data } => WriteError::WouldBlock { data: data.d },
+    WriteError::NotEnabled { data } => WriteError::NotEnabled { data: data.d },
     WriteError::Internal { reason } => WriteError::Internal { reason },
     WriteError::Io(io) => WriteError::Io(io),
   }
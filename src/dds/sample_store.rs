@@ -0,0 +1,172 @@
+use std::{
+  collections::BTreeMap,
+  fs::{File, OpenOptions},
+  io::{self, Read, Seek, SeekFrom, Write},
+  path::{Path, PathBuf},
+};
+
+use bytes::Bytes;
+#[allow(unused_imports)]
+use log::{debug, error, info, trace, warn};
+
+use crate::structure::sequence_number::SequenceNumber;
+
+/// Storage for the samples that a reliable DataWriter retains in its history
+/// after all matched readers have acknowledged them, e.g. for late-joining
+/// readers of a TransientLocal DataWriter.
+///
+/// Samples that are not yet acknowledged by all readers, i.e. the reliability
+/// window, always stay in memory. Only the serialized payloads are stored.
+/// Disposes and unregisters are not.
+///
+/// Give a `SampleStore` to a DataWriter with
+/// [`Publisher::create_datawriter_with_sample_store`](crate::Publisher::create_datawriter_with_sample_store).
+pub trait SampleStore: Send {
+  /// Store the serialized payload of a sample.
+  fn store(&mut self, sequence_number: SequenceNumber, payload: Bytes) -> io::Result<()>;
+
+  /// Get back a stored payload. `Ok(None)` means that it is not stored.
+  fn load(&mut self, sequence_number: SequenceNumber) -> io::Result<Option<Bytes>>;
+
+  /// The DataWriter no longer needs samples before `sequence_number`.
+  fn remove_before(&mut self, sequence_number: SequenceNumber) -> io::Result<()>;
+}
+
+/// Keeps the samples in memory. The memory use is then the same as without a
+/// `SampleStore`.
+#[derive(Debug, Default)]
+pub struct InMemorySampleStore {
+  samples: BTreeMap<SequenceNumber, Bytes>,
+}
+
+impl InMemorySampleStore {
+  pub fn new() -> Self {
+    Self::default()
+  }
+}
+
+impl SampleStore for InMemorySampleStore {
+  fn store(&mut self, sequence_number: SequenceNumber, payload: Bytes) -> io::Result<()> {
+    self.samples.insert(sequence_number, payload);
+    Ok(())
+  }
+
+  fn load(&mut self, sequence_number: SequenceNumber) -> io::Result<Option<Bytes>> {
+    Ok(self.samples.get(&sequence_number).cloned())
+  }
+
+  fn remove_before(&mut self, sequence_number: SequenceNumber) -> io::Result<()> {
+    self.samples = self.samples.split_off(&sequence_number);
+    Ok(())
+  }
+}
+
+/// Spills the samples to a file. Only an index of the samples stays in
+/// memory.
+///
+/// Samples are appended to the file. The file is emptied when all the samples
+/// in it have been removed, and deleted when the `FileSampleStore` is dropped.
+/// The samples are not meant to survive a restart.
+#[derive(Debug)]
+pub struct FileSampleStore {
+  path: PathBuf,
+  file: File,
+  end: u64,
+  // offset and length of each sample in the file
+  index: BTreeMap<SequenceNumber, (u64, usize)>,
+}
+
+impl FileSampleStore {
+  /// Create a store in the file `path`. An existing file is overwritten.
+  pub fn create<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+    let path = path.as_ref().to_path_buf();
+    let file = OpenOptions::new()
+      .read(true)
+      .write(true)
+      .create(true)
+      .truncate(true)
+      .open(&path)?;
+    Ok(Self {
+      path,
+      file,
+      end: 0,
+      index: BTreeMap::new(),
+    })
+  }
+
+  /// Size of the file in bytes
+  pub fn file_size(&self) -> u64 {
+    self.end
+  }
+}
+
+impl SampleStore for FileSampleStore {
+  fn store(&mut self, sequence_number: SequenceNumber, payload: Bytes) -> io::Result<()> {
+    self.file.seek(SeekFrom::Start(self.end))?;
+    self.file.write_all(&payload)?;
+    self
+      .index
+      .insert(sequence_number, (self.end, payload.len()));
+    self.end += payload.len() as u64;
+    Ok(())
+  }
+
+  fn load(&mut self, sequence_number: SequenceNumber) -> io::Result<Option<Bytes>> {
+    let Some(&(offset, len)) = self.index.get(&sequence_number) else {
+      return Ok(None);
+    };
+    let mut buf = vec![0; len];
+    self.file.seek(SeekFrom::Start(offset))?;
+    self.file.read_exact(&mut buf)?;
+    Ok(Some(Bytes::from(buf)))
+  }
+
+  fn remove_before(&mut self, sequence_number: SequenceNumber) -> io::Result<()> {
+    self.index = self.index.split_off(&sequence_number);
+    // Space is reclaimed only when the file becomes empty. Samples are removed
+    // from the beginning, so this happens whenever the history is fully
+    // acknowledged and then expires.
+    if self.index.is_empty() && self.end > 0 {
+      self.file.set_len(0)?;
+      self.end = 0;
+    }
+    Ok(())
+  }
+}
+
+impl Drop for FileSampleStore {
+  fn drop(&mut self) {
+    std::fs::remove_file(&self.path)
+      .unwrap_or_else(|e| warn!("Cannot remove sample store file {:?}: {}", self.path, e));
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn file_sample_store() {
+    let path = std::env::temp_dir().join(format!("rustdds_sample_store_{}", std::process::id()));
+    let mut store = FileSampleStore::create(&path).unwrap();
+    for i in 1..=3 {
+      store
+        .store(SequenceNumber::new(i), Bytes::from(vec![i as u8; 10]))
+        .unwrap();
+    }
+    assert_eq!(store.file_size(), 30);
+    assert_eq!(
+      store.load(SequenceNumber::new(2)).unwrap(),
+      Some(Bytes::from(vec![2; 10]))
+    );
+
+    store.remove_before(SequenceNumber::new(3)).unwrap();
+    assert_eq!(store.load(SequenceNumber::new(2)).unwrap(), None);
+    assert_eq!(store.file_size(), 30);
+    store.remove_before(SequenceNumber::new(4)).unwrap();
+    assert_eq!(store.file_size(), 0);
+
+    drop(store);
+    assert!(!path.exists());
+  }
+}
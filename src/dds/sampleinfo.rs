@@ -227,6 +227,9 @@ pub struct SampleInfo {
   /// the instance (i.e. wrote this sample)
   pub(crate) publication_handle: GUID,
   pub(crate) sequence_number: SequenceNumber,
+
+  /// When the sample was received by the DataReader
+  pub(crate) reception_timestamp: Timestamp,
 }
 
 #[allow(clippy::new_without_default)]
@@ -237,6 +240,12 @@ impl SampleInfo {
     self.write_options.source_timestamp()
   }
 
+  /// Time when this sample was received. This is local time, so unlike the
+  /// source timestamp, it is always available.
+  pub fn reception_timestamp(&self) -> Timestamp {
+    self.reception_timestamp
+  }
+
   pub fn sample_state(&self) -> SampleState {
     self.sample_state
   }
@@ -295,6 +304,13 @@ impl SampleInfo {
     self.write_options.related_sample_identity()
   }
 
+  /// The RTPS sequence number that the DataWriter assigned to this sample.
+  /// Together with [`writer_guid`](Self::writer_guid), this identifies the
+  /// sample, and orders the samples of one DataWriter.
+  pub fn publication_sequence_number(&self) -> SequenceNumber {
+    self.sequence_number
+  }
+
   pub fn sample_identity(&self) -> SampleIdentity {
     SampleIdentity {
       writer_guid: self.publication_handle,
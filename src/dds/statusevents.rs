@@ -6,6 +6,7 @@
 // Communication statues are detailed in Figure 2.13 and tables in Section
 // 2.2.4.1 in DDS Specification v1.4
 use std::{
+  collections::{BTreeMap, BTreeSet},
   io,
   pin::Pin,
   sync::{Arc, Mutex},
@@ -26,6 +27,8 @@ use crate::{
   },
   mio_source::*,
   read_error_poisoned,
+  structure::{guid::GuidPrefix, sequence_number::SequenceNumber},
+  GUID,
 };
 
 /// This trait corresponds to set_listener() of the Entity class in DDS spec.
@@ -274,15 +277,19 @@ pub enum DataReaderStatus {
   // DataAvailable variant is not implemented, as it seems to bring little additional value,
   // because the normal data waiting mechanism already uses the same mio::poll structure.
   /// A sample has been lost (never received).
-  /// TODO: Implement this.
-  /// * Check that the following interpretation is correct:
   /// * For a BEST_EFFORT reader: Whenever we skip ahead in SequenceNumber,
   ///   possibly because a message is lost, or messages arrive out of order.
-  /// * For a RELIABLE reader: Whenever we skip ahead in SequenceNumbers that
-  ///   are delivered via DataReader. The reason may be that we receive a
-  ///   HEARTBEAT or GAP submessage indicating that some samples we are
-  ///   expecting are not available.
-  SampleLost { count: CountWithChange },
+  /// * For a RELIABLE reader: Whenever a GAP submessage tells that samples we
+  ///   have not received yet are not available.
+  /// * Samples that are evicted from the cache to keep within its memory
+  ///   budget.
+  SampleLost {
+    count: CountWithChange,
+    /// The lost sequence numbers, as an inclusive range `(first, last)` over
+    /// the samples of one DataWriter. Some sequence numbers in between may
+    /// have been received. This is `None` for evicted samples.
+    lost_range: Option<(SequenceNumber, SequenceNumber)>,
+  },
 
   /// The DataReader has found a DataWriter that matches the Topic and has
   /// compatible QoS, or has ceased to be matched with a DataWriter that was
@@ -364,8 +371,68 @@ pub enum SampleRejectedStatusKind {
   BySamplesPerInstanceLimit,
 }
 
+/// How many times a QoS policy has been found incompatible
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct QosPolicyCount {
   policy_id: QosPolicyId,
   count: i32,
 }
+
+impl QosPolicyCount {
+  pub fn policy_id(&self) -> QosPolicyId {
+    self.policy_id
+  }
+
+  pub fn count(&self) -> i32 {
+    self.count
+  }
+}
+
+// Bookkeeping for the OfferedIncompatibleQos and RequestedIncompatibleQos
+// statuses. A remote endpoint is counted once, even if Discovery keeps
+// reporting it, until it is found compatible or forgotten.
+#[derive(Debug, Default)]
+pub(crate) struct IncompatibleQosCounter {
+  total_count: i32,
+  policy_counts: BTreeMap<QosPolicyId, i32>,
+  incompatible_remotes: BTreeSet<GUID>,
+}
+
+impl IncompatibleQosCounter {
+  // Count an incompatible remote endpoint. Returns (count, last_policy_id,
+  // policies) for the status event, or None if the remote was already counted.
+  pub fn add(
+    &mut self,
+    remote_guid: GUID,
+    incompatible_policies: &[QosPolicyId],
+  ) -> Option<(CountWithChange, QosPolicyId, Vec<QosPolicyCount>)> {
+    let last_policy_id = *incompatible_policies.first()?;
+    if !self.incompatible_remotes.insert(remote_guid) {
+      return None;
+    }
+    self.total_count += 1;
+    for policy_id in incompatible_policies {
+      *self.policy_counts.entry(*policy_id).or_insert(0) += 1;
+    }
+    let policies = self
+      .policy_counts
+      .iter()
+      .map(|(&policy_id, &count)| QosPolicyCount { policy_id, count })
+      .collect();
+    Some((
+      CountWithChange::new(self.total_count, 1),
+      last_policy_id,
+      policies,
+    ))
+  }
+
+  pub fn forget(&mut self, remote_guid: GUID) {
+    self.incompatible_remotes.remove(&remote_guid);
+  }
+
+  pub fn forget_participant(&mut self, guid_prefix: GuidPrefix) {
+    self
+      .incompatible_remotes
+      .retain(|guid| guid.prefix != guid_prefix);
+  }
+}
@@ -18,13 +18,14 @@ use crate::{
     key::*,
     qos::*,
     readcondition::*,
-    result::ReadResult,
+    result::{CreateResult, ReadResult},
     statusevents::*,
     with_key::{datasample::*, simpledatareader::*},
   },
   discovery::sedp_messages::PublicationBuiltinTopicData,
   serialization::CDRDeserializerAdapter,
   structure::{duration::Duration, entity::RTPSEntity, guid::GUID, time::Timestamp},
+  TopicDescription,
 };
 
 /// Simplified type for CDR encoding
@@ -40,6 +41,11 @@ pub enum SelectByKey {
 
 /// DDS DataReader for with_key topics.
 ///
+/// When no samples are available, the read and take methods return `Ok` with
+/// an empty `Vec`, or `Ok(None)` for the `_next_sample` methods. An empty
+/// result does not allocate. `Err` means that something actually went wrong,
+/// e.g. a sample could not be deserialized.
+///
 /// # Examples
 ///
 /// ```
@@ -81,7 +87,7 @@ where
   DA: DeserializerAdapter<D>,
 {
   pub(crate) fn from_simple_data_reader(simple_data_reader: SimpleDataReader<D, DA>) -> Self {
-    let dsc = DataSampleCache::new(simple_data_reader.topic().qos());
+    let dsc = DataSampleCache::new(simple_data_reader.qos().clone());
 
     Self {
       simple_data_reader,
@@ -98,6 +104,9 @@ where
         .datasample_cache
         .fill_from_deserialized_cache_change(dcc);
     }
+    let lost_writers = self.simple_data_reader.lost_writers();
+    self.datasample_cache.writers_lost(&lost_writers);
+    self.datasample_cache.autopurge_instances();
     Ok(())
   }
 
@@ -705,6 +714,119 @@ where
     Ok(result)
   }
 
+  /// Reads the samples of the instance that follows `previous`. Instances are
+  /// ordered by their key hashes, which is stable, but unrelated to the key
+  /// order used by [`read_instance`](Self::read_instance). `None` selects the
+  /// first instance.
+  ///
+  /// Instances that have no samples matching `read_condition` are skipped, so
+  /// iteration over all instances ends when the result is empty. See
+  /// [`take_next_instance`](Self::take_next_instance) for an example.
+  pub fn read_next_instance(
+    &mut self,
+    max_samples: usize,
+    read_condition: ReadCondition,
+    previous: Option<&<D as Keyed>::K>,
+  ) -> ReadResult<Vec<DataSample<&D>>> {
+    self.drain_read_notifications();
+    self.fill_and_lock_local_datasample_cache()?;
+
+    let mut selected = self
+      .datasample_cache
+      .select_next_instance_keys_for_access(previous, read_condition);
+    selected.truncate(max_samples);
+
+    Ok(self.datasample_cache.read_by_keys(&selected))
+  }
+
+  /// Takes the samples of the instance that follows `previous`, like
+  /// [`read_next_instance`](Self::read_next_instance).
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// # use serde::{Serialize, Deserialize};
+  /// # use rustdds::*;
+  /// # use rustdds::with_key::DataReader;
+  /// # use rustdds::serialization::CDRDeserializerAdapter;
+  /// #
+  /// # let domain_participant = DomainParticipant::new(0).unwrap();
+  /// # let qos = QosPolicyBuilder::new().build();
+  /// # let subscriber = domain_participant.create_subscriber(&qos).unwrap();
+  /// #
+  /// # #[derive(Serialize, Deserialize)]
+  /// # struct SomeType { a: i32 }
+  /// # impl Keyed for SomeType {
+  /// #   type K = i32;
+  /// #
+  /// #   fn key(&self) -> Self::K {
+  /// #     self.a
+  /// #   }
+  /// # }
+  /// #
+  /// # let topic = domain_participant.create_topic("some_topic".to_string(), "SomeType".to_string(), &qos, TopicKind::WithKey).unwrap();
+  /// let mut data_reader = subscriber.create_datareader::<SomeType, CDRDeserializerAdapter<_>>(&topic, None).unwrap();
+  ///
+  /// // Process the samples one instance at a time
+  /// let mut previous = None;
+  /// while let Ok(datas) = data_reader.take_next_instance(10, ReadCondition::any(), previous.as_ref()) {
+  ///   let Some(first) = datas.first() else { break };
+  ///   previous = Some(first.key());
+  ///   for data in datas.iter() {
+  ///     // do something
+  ///   }
+  /// }
+  /// ```
+  pub fn take_next_instance(
+    &mut self,
+    max_samples: usize,
+    read_condition: ReadCondition,
+    previous: Option<&<D as Keyed>::K>,
+  ) -> ReadResult<Vec<DataSample<D>>> {
+    self.drain_read_notifications();
+    self.fill_and_lock_local_datasample_cache()?;
+
+    let mut selected = self
+      .datasample_cache
+      .select_next_instance_keys_for_access(previous, read_condition);
+    selected.truncate(max_samples);
+
+    Ok(self.take_by_keys(&selected))
+  }
+
+  /// Returns a copy of every sample currently cached for this DataReader.
+  ///
+  /// This is meant for recording and similar tooling. Unlike [`Self::read`]
+  /// and [`Self::take`], this ignores sample, view and instance states and
+  /// always returns the whole retained set, as limited by the History and
+  /// ResourceLimits QoS and by the filters of this DataReader. Nothing is
+  /// marked as read or viewed, and nothing is removed.
+  pub fn read_all_cached(&mut self) -> ReadResult<Vec<DataSample<D>>>
+  where
+    D: Clone,
+  {
+    self.drain_read_notifications();
+    self.fill_and_lock_local_datasample_cache()?;
+    Ok(self.datasample_cache.snapshot_all())
+  }
+
+  /// Reads the next unread sample without deserializing it.
+  ///
+  /// The returned [`RawSample`] borrows the serialized payload directly from
+  /// the topic cache, so nothing is copied. This is meant for applications
+  /// that deserialize into their own types, or just forward the bytes.
+  /// The topic cache stays locked while the `RawSample` exists, so it should
+  /// be dropped promptly.
+  ///
+  /// Samples returned by this method are consumed at the
+  /// RTPS level, i.e. they will not be returned by `read()` or `take()`
+  /// of this DataReader. Use only one of the two ways of reading on a
+  /// DataReader.
+  pub fn read_raw(&mut self) -> Option<RawSample<'_>> {
+    self.drain_read_notifications();
+    self.simple_data_reader.try_take_one_raw()
+  }
+
   /// Return values:
   /// true - got all historical data
   /// false - timeout before all historical data was received
@@ -725,6 +847,78 @@ where
     vec![].into_iter()
   }
 
+  /// Compares the QoS of this DataReader with the QoS advertised by a remote
+  /// DataWriter of the same topic.
+  ///
+  /// The diff lists the policies that differ, and which of them prevent
+  /// matching. This works also for writers that did not match because of
+  /// incompatible QoS.
+  ///
+  /// Returns `None` if no such writer has been discovered.
+  pub fn qos_diff(&self, writer_guid: GUID) -> Option<QosDiff> {
+    let topic_name = self.simple_data_reader.topic().name();
+    let remote = self
+      .simple_data_reader
+      .subscriber()
+      .participant()?
+      .discovered_writers()
+      .into_iter()
+      .find(|w| {
+        w.writer_proxy.remote_writer_guid == writer_guid
+          && w.publication_topic_data.topic_name == topic_name
+      })?;
+    Some(QosDiff::new(
+      self.qos(),
+      remote.publication_topic_data.qos(),
+      false,
+    ))
+  }
+
+  /// The QoS this DataReader actually uses.
+  ///
+  /// This is the Subscriber QoS, modified by the Topic QoS, and then by the
+  /// QoS given when creating the DataReader.
+  pub fn effective_qos(&self) -> QosPolicies {
+    self.qos()
+  }
+
+  /// Enables a DataReader that was created disabled. See
+  /// [`SimpleDataReader::enable`](crate::with_key::SimpleDataReader::enable).
+  pub fn enable(&self) -> CreateResult<()> {
+    self.simple_data_reader.enable()
+  }
+
+  /// Tells if this DataReader is enabled.
+  pub fn is_enabled(&self) -> bool {
+    self.simple_data_reader.is_enabled()
+  }
+
+  /// The QoS offered by a matched remote DataWriter, as advertised in
+  /// Discovery.
+  ///
+  /// Returns `None` if no such writer has been discovered on this topic, or
+  /// its QoS is incompatible with ours. See [`qos_diff`](Self::qos_diff) for
+  /// the latter case.
+  pub fn matched_writer_qos(&self, writer_guid: GUID) -> Option<QosPolicies> {
+    let topic_name = self.simple_data_reader.topic().name();
+    let remote_qos = self
+      .simple_data_reader
+      .subscriber()
+      .participant()?
+      .discovered_writers()
+      .into_iter()
+      .find(|w| {
+        w.writer_proxy.remote_writer_guid == writer_guid
+          && w.publication_topic_data.topic_name == topic_name
+      })?
+      .publication_topic_data
+      .qos();
+    remote_qos
+      .compliance_failure_wrt(&self.qos())
+      .is_none()
+      .then_some(remote_qos)
+  }
+
   /// An async stream for reading the (bare) data samples.
   /// The resulting Stream can be used to get another stream of status events.
   pub fn async_sample_stream(self) -> DataReaderStream<D, DA> {
@@ -989,20 +1183,21 @@ mod tests {
   use std::rc::Rc;
 
   use bytes::Bytes;
-  use mio_extras::channel as mio_channel;
   use log::info;
   use byteorder::LittleEndian;
 
   use super::*;
   use crate::{
     dds::{
+      adapters::no_key,
       participant::DomainParticipant,
+      qos::property::{Property, READER_REORDERING_WINDOW_PROPERTY},
+      sampleinfo::SampleState,
       topic::{TopicDescription, TopicKind},
     },
     messages::submessages::{
       elements::serialized_payload::SerializedPayload, submessage_flag::*, submessages::DecodedData,
     },
-    mio_source,
     network::udp_sender::UDPSender,
     rtps::{
       message_receiver::*,
@@ -1043,32 +1238,14 @@ mod tests {
         .add_new_topic(topic.name(), topic.get_type(), &topic.qos());
 
     // Create a Reader
-    let (notification_sender, _notification_receiver) = mio_channel::sync_channel::<()>(100);
-    let (_notification_event_source, notification_event_sender) =
-      mio_source::make_poll_channel().unwrap();
-    let data_reader_waker = Arc::new(Mutex::new(None));
-
-    let (status_sender, _status_receiver) = sync_status_channel::<DataReaderStatus>(4).unwrap();
-
-    let (_reader_command_sender, reader_command_receiver) =
-      mio_channel::sync_channel::<ReaderCommand>(10);
-
     let default_id = EntityId::default();
     let reader_guid = GUID::new_with_prefix_and_id(dp.guid_prefix(), default_id);
-
-    let reader_ing = ReaderIngredients {
-      guid: reader_guid,
-      notification_sender,
-      status_sender,
-      topic_name: topic.name(),
-      topic_cache_handle: topic_cache,
-      like_stateless: false,
-      qos_policy: QosPolicies::qos_none(),
-      data_reader_command_receiver: reader_command_receiver,
-      data_reader_waker,
-      poll_event_sender: notification_event_sender,
-      security_plugins: None,
-    };
+    let (reader_ing, _channels) = ReaderIngredients::for_test(
+      reader_guid,
+      topic.name(),
+      topic_cache,
+      &QosPolicies::qos_none(),
+    );
 
     let mut reader = Reader::new(
       reader_ing,
@@ -1173,6 +1350,120 @@ mod tests {
     assert_eq!(result_vec2.unwrap().len(), 0);
   }
 
+  #[test]
+  fn read_marks_samples_read_until_taken() {
+    let dp = DomainParticipant::new(0).expect("Participant creation failed!");
+
+    let mut qos = QosPolicies::qos_none();
+    qos.history = Some(policy::History::KeepAll); // Just for testing
+
+    let sub = dp.create_subscriber(&qos).unwrap();
+    let topic = dp
+      .create_topic(
+        "dr read state".to_string(),
+        "read state test?".to_string(),
+        &qos,
+        TopicKind::WithKey,
+      )
+      .unwrap();
+
+    let topic_cache =
+      dp.dds_cache()
+        .write()
+        .unwrap()
+        .add_new_topic(topic.name(), topic.get_type(), &topic.qos());
+
+    // Create a Reader
+    let reader_guid = GUID::new_with_prefix_and_id(dp.guid_prefix(), EntityId::default());
+    let (reader_ing, _channels) = ReaderIngredients::for_test(
+      reader_guid,
+      topic.name(),
+      topic_cache,
+      &QosPolicies::qos_none(),
+    );
+
+    let mut reader = Reader::new(
+      reader_ing,
+      Rc::new(UDPSender::new_with_random_port().unwrap()),
+      mio_extras::timer::Builder::default().build(),
+    );
+
+    let mut datareader = sub
+      .create_datareader::<RandomData, CDRDeserializerAdapter<RandomData>>(&topic, None)
+      .unwrap();
+
+    let writer_guid = GUID {
+      prefix: GuidPrefix::new(&[1; 12]),
+      entity_id: EntityId::create_custom_entity_id(
+        [1; 3],
+        EntityKind::WRITER_WITH_KEY_USER_DEFINED,
+      ),
+    };
+    let mr_state = MessageReceiverState {
+      source_guid_prefix: writer_guid.prefix,
+      ..Default::default()
+    };
+    reader.matched_writer_add(
+      writer_guid,
+      EntityId::UNKNOWN,
+      Vec::new(),
+      Vec::new(),
+      &QosPolicies::qos_none(),
+    );
+
+    let test_data = RandomData {
+      a: 1,
+      b: "read me twice".to_string(),
+    };
+    let data_msg = DecodedData {
+      reader_id: reader.entity_id(),
+      writer_id: writer_guid.entity_id,
+      writer_sn: SequenceNumber::from(1),
+      serialized_payload: Some(SerializedPayload {
+        representation_identifier: RepresentationIdentifier::CDR_LE,
+        representation_options: [0, 0],
+        value: Bytes::from(to_bytes::<RandomData, LittleEndian>(&test_data).unwrap()),
+      }),
+      ..DecodedData::default()
+    };
+    reader.handle_data_msg(
+      data_msg,
+      DATA_Flags::Endianness | DATA_Flags::Data,
+      &mr_state,
+    );
+
+    // The first read gets the sample as not read, and marks it read.
+    {
+      let samples = datareader.read(10, ReadCondition::not_read()).unwrap();
+      assert_eq!(samples.len(), 1);
+      assert_eq!(samples[0].value().clone().unwrap(), &test_data);
+      assert_eq!(
+        samples[0].sample_info().sample_state(),
+        SampleState::NotRead
+      );
+    }
+    // So it is not returned as not read again...
+    assert!(datareader
+      .read(10, ReadCondition::not_read())
+      .unwrap()
+      .is_empty());
+    // ...but it is still there.
+    {
+      let samples = datareader.read(10, ReadCondition::any()).unwrap();
+      assert_eq!(samples.len(), 1);
+      assert_eq!(samples[0].sample_info().sample_state(), SampleState::Read);
+    }
+
+    // Take removes it.
+    let taken = datareader.take(10, ReadCondition::any()).unwrap();
+    assert_eq!(taken.len(), 1);
+    assert_eq!(taken[0].value().clone().unwrap(), test_data);
+    assert!(datareader
+      .read(10, ReadCondition::any())
+      .unwrap()
+      .is_empty());
+  }
+
   #[test]
   fn read_and_take_with_instance() {
     // Test the methods read_instance and take_instance of the DataReader
@@ -1199,32 +1490,14 @@ mod tests {
         .add_new_topic(topic.name(), topic.get_type(), &topic.qos());
 
     // Create a Reader
-    let (notification_sender, _notification_receiver) = mio_channel::sync_channel::<()>(100);
-    let (_notification_event_source, notification_event_sender) =
-      mio_source::make_poll_channel().unwrap();
-    let data_reader_waker = Arc::new(Mutex::new(None));
-
-    let (status_sender, _status_receiver) = sync_status_channel::<DataReaderStatus>(4).unwrap();
-
-    let (_reader_command_sender, reader_command_receiver) =
-      mio_channel::sync_channel::<ReaderCommand>(10);
-
     let default_id = EntityId::default();
     let reader_guid = GUID::new_with_prefix_and_id(dp.guid_prefix(), default_id);
-
-    let reader_ing = ReaderIngredients {
-      guid: reader_guid,
-      notification_sender,
-      status_sender,
-      topic_name: topic.name(),
-      topic_cache_handle: topic_cache,
-      like_stateless: false,
-      qos_policy: QosPolicies::qos_none(),
-      data_reader_command_receiver: reader_command_receiver,
-      data_reader_waker,
-      poll_event_sender: notification_event_sender,
-      security_plugins: None,
-    };
+    let (reader_ing, _channels) = ReaderIngredients::for_test(
+      reader_guid,
+      topic.name(),
+      topic_cache,
+      &QosPolicies::qos_none(),
+    );
 
     let mut reader = Reader::new(
       reader_ing,
@@ -1378,4 +1651,693 @@ mod tests {
     assert!(results.is_ok());
     assert!(results.unwrap().is_empty());
   }
+
+  #[test]
+  fn idle_reader_returns_empty() {
+    // Having nothing to read is not an error. All read and take variants return
+    // an empty result, without allocating.
+    use crate::messages::submessages::elements::{parameter::Parameter, parameter_list::ParameterList};
+    use crate::structure::parameter_id::ParameterId;
+
+    let dp = DomainParticipant::new(0).expect("Participant creation failed!");
+    let mut qos = QosPolicies::qos_none();
+    qos.history = Some(policy::History::KeepAll); // Just for testing
+    let sub = dp.create_subscriber(&qos).unwrap();
+    let topic = dp
+      .create_topic(
+        "dr idle".to_string(),
+        "idle reader test?".to_string(),
+        &qos,
+        TopicKind::WithKey,
+      )
+      .unwrap();
+
+    let topic_cache =
+      dp.dds_cache()
+        .write()
+        .unwrap()
+        .add_new_topic(topic.name(), topic.get_type(), &topic.qos());
+
+    let (reader_ing, _channels) = ReaderIngredients::for_test(
+      GUID::new_with_prefix_and_id(dp.guid_prefix(), EntityId::default()),
+      topic.name(),
+      topic_cache,
+      &qos,
+    );
+    let mut reader = Reader::new(
+      reader_ing,
+      Rc::new(UDPSender::new_with_random_port().unwrap()),
+      mio_extras::timer::Builder::default().build(),
+    );
+
+    let mut datareader = sub
+      .create_datareader::<RandomData, CDRDeserializerAdapter<RandomData>>(&topic, None)
+      .unwrap();
+
+    let check = |datareader: &mut DataReader<RandomData>| {
+      let rc = ReadCondition::any();
+      assert_eq!(datareader.read(10, rc).unwrap().capacity(), 0);
+      assert_eq!(datareader.take(10, rc).unwrap().capacity(), 0);
+      assert!(datareader.read_next_sample().unwrap().is_none());
+      assert!(datareader.take_next_sample().unwrap().is_none());
+      assert_eq!(datareader.iterator().unwrap().count(), 0);
+      assert_eq!(datareader.conditional_iterator(rc).unwrap().count(), 0);
+      assert_eq!(datareader.into_iterator().unwrap().count(), 0);
+      assert_eq!(datareader.into_conditional_iterator(rc).unwrap().count(), 0);
+      for key in [None, Some(1)] {
+        for this_or_next in [SelectByKey::This, SelectByKey::Next] {
+          let read = datareader.read_instance(10, rc, key, this_or_next);
+          assert_eq!(read.unwrap().capacity(), 0);
+          let taken = datareader.take_instance(10, rc, key, this_or_next);
+          assert_eq!(taken.unwrap().capacity(), 0);
+        }
+        let read = datareader.read_next_instance(10, rc, key.as_ref());
+        assert_eq!(read.unwrap().capacity(), 0);
+        let taken = datareader.take_next_instance(10, rc, key.as_ref());
+        assert_eq!(taken.unwrap().capacity(), 0);
+      }
+      assert!(datareader.read_raw().is_none());
+    };
+    check(&mut datareader);
+
+    // A dispose of an instance that the reader has never seen gives nothing to
+    // read either.
+    let writer_guid = GUID {
+      prefix: GuidPrefix::new(&[1; 12]),
+      entity_id: EntityId::create_custom_entity_id(
+        [1; 3],
+        EntityKind::WRITER_WITH_KEY_USER_DEFINED,
+      ),
+    };
+    reader.matched_writer_add(
+      writer_guid,
+      EntityId::UNKNOWN,
+      Vec::new(),
+      Vec::new(),
+      &QosPolicies::qos_none(),
+    );
+    let mut inline_qos = ParameterList::new();
+    inline_qos.push(Parameter {
+      parameter_id: ParameterId::PID_KEY_HASH,
+      value: vec![7; 16],
+    });
+    inline_qos.push(Parameter::create_pid_status_info_parameter(
+      true, true, false,
+    ));
+    let dispose_msg = DecodedData {
+      reader_id: reader.entity_id(),
+      writer_id: writer_guid.entity_id,
+      writer_sn: SequenceNumber::from(1),
+      inline_qos: Some(inline_qos),
+      serialized_payload: None,
+      ..DecodedData::default()
+    };
+    let mr_state = MessageReceiverState {
+      source_guid_prefix: writer_guid.prefix,
+      ..Default::default()
+    };
+    reader.handle_data_msg(
+      dispose_msg,
+      DATA_Flags::Endianness | DATA_Flags::InlineQos,
+      &mr_state,
+    );
+    check(&mut datareader);
+
+    // Same for no_key DataReaders
+    let no_key_topic = dp
+      .create_topic(
+        "dr idle no key".to_string(),
+        "idle reader test?".to_string(),
+        &qos,
+        TopicKind::NoKey,
+      )
+      .unwrap();
+    let mut no_key_datareader = sub
+      .create_datareader_no_key::<RandomData, CDRDeserializerAdapter<RandomData>>(
+        &no_key_topic,
+        None,
+      )
+      .unwrap();
+    let rc = ReadCondition::any();
+    assert_eq!(no_key_datareader.read(10, rc).unwrap().capacity(), 0);
+    assert_eq!(no_key_datareader.take(10, rc).unwrap().capacity(), 0);
+    assert!(no_key_datareader.read_next_sample().unwrap().is_none());
+    assert!(no_key_datareader.take_next_sample().unwrap().is_none());
+    assert_eq!(no_key_datareader.iterator().unwrap().count(), 0);
+    assert_eq!(no_key_datareader.into_iterator().unwrap().count(), 0);
+    assert!(no_key_datareader.read_raw().is_none());
+  }
+
+  #[test]
+  fn read_by_source_timestamp() {
+    // Samples are written in one order, but the source timestamps given by the
+    // application say otherwise. With DestinationOrder::BySourceTimeStamp the
+    // DataReader must present them in source timestamp order.
+    use crate::{QosPolicyBuilder, WriteOptionsBuilder};
+
+    let domain_id = 73;
+    let qos = QosPolicyBuilder::new()
+      .reliability(policy::Reliability::Reliable {
+        max_blocking_time: Duration::from_secs(1),
+      })
+      .durability(policy::Durability::TransientLocal)
+      .history(policy::History::KeepAll)
+      .destination_order(policy::DestinationOrder::BySourceTimeStamp)
+      .build();
+    let create_topic = |dp: &DomainParticipant| {
+      dp.create_topic(
+        "dr source timestamp".to_string(),
+        "read by source timestamp test?".to_string(),
+        &qos,
+        TopicKind::WithKey,
+      )
+      .unwrap()
+    };
+
+    let dp_writer = DomainParticipant::new(domain_id).expect("Participant creation failed!");
+    let writer = dp_writer
+      .create_publisher(&qos)
+      .unwrap()
+      .create_datawriter_cdr::<RandomData>(&create_topic(&dp_writer), None)
+      .unwrap();
+
+    let early_data = RandomData {
+      a: 1,
+      b: "recorded first".to_string(),
+    };
+    let late_data = RandomData {
+      a: 2,
+      b: "recorded second".to_string(),
+    };
+    let recording_start = Timestamp::now() - Duration::from_secs(60);
+    let early_timestamp = recording_start + Duration::from_secs(1);
+    let late_timestamp = recording_start + Duration::from_secs(2);
+
+    // Replay the recording out of order.
+    writer
+      .write_with_options(
+        late_data.clone(),
+        WriteOptionsBuilder::new()
+          .source_timestamp(late_timestamp)
+          .build(),
+      )
+      .unwrap();
+    writer
+      .write_with_options(
+        early_data.clone(),
+        WriteOptionsBuilder::new()
+          .source_timestamp(early_timestamp)
+          .build(),
+      )
+      .unwrap();
+
+    // The Reader joins late, and its reordering window is long enough for the
+    // Writer to send both samples.
+    let mut reader_qos = qos.clone();
+    reader_qos.property = Some(policy::Property {
+      value: vec![Property::local(READER_REORDERING_WINDOW_PROPERTY, "1000")],
+      binary_value: vec![],
+    });
+    let dp_reader = DomainParticipant::new(domain_id).expect("Participant creation failed!");
+    let mut datareader = dp_reader
+      .create_subscriber(&qos)
+      .unwrap()
+      .create_datareader_cdr::<RandomData>(&create_topic(&dp_reader), Some(reader_qos))
+      .unwrap();
+
+    let mut result_vec = Vec::new();
+    crate::test::wait_until(std::time::Duration::from_secs(10), || {
+      result_vec.extend(datareader.take(100, ReadCondition::any()).unwrap());
+      result_vec.len() >= 2
+    });
+    assert_eq!(result_vec.len(), 2);
+    assert_eq!(early_data, result_vec[0].value().clone().unwrap());
+    assert_eq!(late_data, result_vec[1].value().clone().unwrap());
+    assert_eq!(
+      result_vec[0].sample_info().source_timestamp(),
+      Some(early_timestamp)
+    );
+  }
+
+  #[test]
+  fn source_timestamp_reordering_window() {
+    // Samples of the same instance arrive out of order. The Reader holds them
+    // for the reordering window and stores them in source timestamp order, so
+    // that none are dropped for being older than a stored one. A sample that
+    // arrives after the window has passed is dropped.
+
+    let dp = DomainParticipant::new(0).expect("Participant creation failed!");
+
+    let mut qos = QosPolicies::qos_none();
+    qos.history = Some(policy::History::KeepAll); // Just for testing
+    qos.destination_order = Some(policy::DestinationOrder::BySourceTimeStamp);
+    qos.property = Some(policy::Property {
+      value: vec![Property::local(READER_REORDERING_WINDOW_PROPERTY, "100")],
+      binary_value: vec![],
+    });
+
+    let sub = dp.create_subscriber(&qos).unwrap();
+    let topic = dp
+      .create_topic(
+        "dr reordering window".to_string(),
+        "reordering window test?".to_string(),
+        &qos,
+        TopicKind::WithKey,
+      )
+      .unwrap();
+
+    let topic_cache =
+      dp.dds_cache()
+        .write()
+        .unwrap()
+        .add_new_topic(topic.name(), topic.get_type(), &topic.qos());
+
+    // Create a Reader
+    let default_id = EntityId::default();
+    let reader_guid = GUID::new_with_prefix_and_id(dp.guid_prefix(), default_id);
+    let (reader_ing, _channels) =
+      ReaderIngredients::for_test(reader_guid, topic.name(), topic_cache, &qos);
+
+    let mut reader = Reader::new(
+      reader_ing,
+      Rc::new(UDPSender::new_with_random_port().unwrap()),
+      mio_extras::timer::Builder::default()
+        .tick_duration(std::time::Duration::from_millis(1))
+        .build(),
+    );
+
+    let mut datareader = sub
+      .create_datareader::<RandomData, CDRDeserializerAdapter<RandomData>>(&topic, Some(qos))
+      .unwrap();
+
+    let writer_guid = GUID {
+      prefix: GuidPrefix::new(&[2; 12]),
+      entity_id: EntityId::create_custom_entity_id(
+        [2; 3],
+        EntityKind::WRITER_WITH_KEY_USER_DEFINED,
+      ),
+    };
+    reader.matched_writer_add(
+      writer_guid,
+      EntityId::UNKNOWN,
+      Vec::new(),
+      Vec::new(),
+      &QosPolicies::qos_none(),
+    );
+
+    let reader_id = reader.entity_id();
+    let make_data_msg = |sn: i64, b: &str| DecodedData {
+      reader_id,
+      writer_id: writer_guid.entity_id,
+      writer_sn: SequenceNumber::from(sn),
+      serialized_payload: Some(SerializedPayload {
+        representation_identifier: RepresentationIdentifier::CDR_LE,
+        representation_options: [0, 0],
+        value: Bytes::from(
+          to_bytes::<RandomData, LittleEndian>(&RandomData {
+            a: 1,
+            b: b.to_string(),
+          })
+          .unwrap(),
+        ),
+      }),
+      ..DecodedData::default()
+    };
+    let recording_start = Timestamp::now() - Duration::from_secs(60);
+    let mr_state = |secs: i32| MessageReceiverState {
+      source_guid_prefix: writer_guid.prefix,
+      source_timestamp: Some(recording_start + Duration::from_secs(secs)),
+      ..Default::default()
+    };
+    let data_flags = DATA_Flags::Endianness | DATA_Flags::Data;
+    let release_window = |reader: &mut Reader| {
+      std::thread::sleep(std::time::Duration::from_millis(200));
+      reader.handle_timed_event();
+    };
+
+    reader.handle_data_msg(make_data_msg(1, "third"), data_flags, &mr_state(3));
+    reader.handle_data_msg(make_data_msg(2, "first"), data_flags, &mr_state(1));
+    reader.handle_data_msg(make_data_msg(3, "second"), data_flags, &mr_state(2));
+
+    // Nothing is delivered during the reordering window.
+    assert!(datareader
+      .take(100, ReadCondition::any())
+      .unwrap()
+      .is_empty());
+
+    release_window(&mut reader);
+    let result_vec = datareader.take(100, ReadCondition::any()).unwrap();
+    let received: Vec<String> = result_vec
+      .iter()
+      .map(|s| s.value().clone().unwrap().b)
+      .collect();
+    assert_eq!(received, vec!["first", "second", "third"]);
+
+    // Too late: older than what was delivered already.
+    reader.handle_data_msg(make_data_msg(4, "late"), data_flags, &mr_state(0));
+    reader.handle_data_msg(make_data_msg(5, "fourth"), data_flags, &mr_state(4));
+    release_window(&mut reader);
+    let result_vec = datareader.take(100, ReadCondition::any()).unwrap();
+    assert_eq!(result_vec.len(), 1);
+    assert_eq!(result_vec[0].value().clone().unwrap().b, "fourth");
+  }
+
+  #[test]
+  fn read_raw_matches_typed_read() {
+    let dp = DomainParticipant::new(0).expect("Participant creation failed!");
+
+    let mut qos = QosPolicies::qos_none();
+    qos.history = Some(policy::History::KeepAll); // Just for testing
+
+    let sub = dp.create_subscriber(&qos).unwrap();
+    let topic = dp
+      .create_topic(
+        "dr read raw".to_string(),
+        "read raw test?".to_string(),
+        &qos,
+        TopicKind::WithKey,
+      )
+      .unwrap();
+
+    let topic_cache =
+      dp.dds_cache()
+        .write()
+        .unwrap()
+        .add_new_topic(topic.name(), topic.get_type(), &topic.qos());
+
+    // Create a Reader
+    let reader_guid = GUID::new_with_prefix_and_id(dp.guid_prefix(), EntityId::default());
+    let (reader_ing, _channels) = ReaderIngredients::for_test(
+      reader_guid,
+      topic.name(),
+      topic_cache,
+      &QosPolicies::qos_none(),
+    );
+
+    let mut reader = Reader::new(
+      reader_ing,
+      Rc::new(UDPSender::new_with_random_port().unwrap()),
+      mio_extras::timer::Builder::default().build(),
+    );
+
+    // Two DataReaders on the same topic: one reads typed, the other raw.
+    let mut typed_datareader = sub
+      .create_datareader::<RandomData, CDRDeserializerAdapter<RandomData>>(&topic, None)
+      .unwrap();
+    let mut raw_datareader = sub
+      .create_datareader::<RandomData, CDRDeserializerAdapter<RandomData>>(&topic, None)
+      .unwrap();
+
+    let writer_guid = GUID {
+      prefix: GuidPrefix::new(&[1; 12]),
+      entity_id: EntityId::create_custom_entity_id(
+        [1; 3],
+        EntityKind::WRITER_WITH_KEY_USER_DEFINED,
+      ),
+    };
+    let mr_state = MessageReceiverState {
+      source_guid_prefix: writer_guid.prefix,
+      ..Default::default()
+    };
+    reader.matched_writer_add(
+      writer_guid,
+      EntityId::UNKNOWN,
+      Vec::new(),
+      Vec::new(),
+      &QosPolicies::qos_none(),
+    );
+
+    let test_data = RandomData {
+      a: 10,
+      b: "raw bytes".to_string(),
+    };
+    let data_msg = DecodedData {
+      reader_id: reader.entity_id(),
+      writer_id: writer_guid.entity_id,
+      writer_sn: SequenceNumber::from(1),
+      serialized_payload: Some(SerializedPayload {
+        representation_identifier: RepresentationIdentifier::CDR_LE,
+        representation_options: [0, 0],
+        value: Bytes::from(to_bytes::<RandomData, LittleEndian>(&test_data).unwrap()),
+      }),
+      ..DecodedData::default()
+    };
+    reader.handle_data_msg(
+      data_msg,
+      DATA_Flags::Endianness | DATA_Flags::Data,
+      &mr_state,
+    );
+
+    let typed = typed_datareader
+      .take_next_sample()
+      .unwrap()
+      .expect("No typed sample")
+      .into_value()
+      .unwrap();
+
+    {
+      let raw = raw_datareader.read_raw().expect("No raw sample");
+      assert_eq!(raw.writer_guid(), writer_guid);
+      assert_eq!(raw.sequence_number(), SequenceNumber::from(1));
+      assert!(raw.is_data());
+      let rep_id = raw.representation_identifier().unwrap();
+      assert_eq!(rep_id, RepresentationIdentifier::CDR_LE);
+      let decoded: RandomData =
+        <CDRDeserializerAdapter<RandomData> as no_key::DeserializerAdapter<_>>::from_bytes(
+          raw.payload().unwrap(),
+          rep_id,
+        )
+        .unwrap();
+      assert_eq!(decoded, typed);
+      assert_eq!(decoded, test_data);
+    }
+
+    // The raw sample was consumed
+    assert!(raw_datareader.read_raw().is_none());
+  }
+
+  #[test]
+  fn read_all_cached_ignores_read_state() {
+    let dp = DomainParticipant::new(0).expect("Participant creation failed!");
+
+    let mut qos = QosPolicies::qos_none();
+    qos.history = Some(policy::History::KeepAll); // Just for testing
+
+    let sub = dp.create_subscriber(&qos).unwrap();
+    let topic = dp
+      .create_topic(
+        "dr read all cached".to_string(),
+        "read all cached test?".to_string(),
+        &qos,
+        TopicKind::WithKey,
+      )
+      .unwrap();
+
+    let topic_cache =
+      dp.dds_cache()
+        .write()
+        .unwrap()
+        .add_new_topic(topic.name(), topic.get_type(), &topic.qos());
+
+    // Create a Reader
+    let reader_guid = GUID::new_with_prefix_and_id(dp.guid_prefix(), EntityId::default());
+    let (reader_ing, _channels) = ReaderIngredients::for_test(
+      reader_guid,
+      topic.name(),
+      topic_cache,
+      &QosPolicies::qos_none(),
+    );
+
+    let mut reader = Reader::new(
+      reader_ing,
+      Rc::new(UDPSender::new_with_random_port().unwrap()),
+      mio_extras::timer::Builder::default().build(),
+    );
+
+    let mut datareader = sub
+      .create_datareader::<RandomData, CDRDeserializerAdapter<RandomData>>(&topic, None)
+      .unwrap();
+
+    let writer_guid = GUID {
+      prefix: GuidPrefix::new(&[1; 12]),
+      entity_id: EntityId::create_custom_entity_id(
+        [1; 3],
+        EntityKind::WRITER_WITH_KEY_USER_DEFINED,
+      ),
+    };
+    let mr_state = MessageReceiverState {
+      source_guid_prefix: writer_guid.prefix,
+      ..Default::default()
+    };
+    reader.matched_writer_add(
+      writer_guid,
+      EntityId::UNKNOWN,
+      Vec::new(),
+      Vec::new(),
+      &QosPolicies::qos_none(),
+    );
+
+    const N: i64 = 5;
+    for i in 1..=N {
+      let test_data = RandomData {
+        a: i,
+        b: format!("sample {i}"),
+      };
+      let data_msg = DecodedData {
+        reader_id: reader.entity_id(),
+        writer_id: writer_guid.entity_id,
+        writer_sn: SequenceNumber::from(i),
+        serialized_payload: Some(SerializedPayload {
+          representation_identifier: RepresentationIdentifier::CDR_LE,
+          representation_options: [0, 0],
+          value: Bytes::from(to_bytes::<RandomData, LittleEndian>(&test_data).unwrap()),
+        }),
+        ..DecodedData::default()
+      };
+      reader.handle_data_msg(
+        data_msg,
+        DATA_Flags::Endianness | DATA_Flags::Data,
+        &mr_state,
+      );
+    }
+
+    // Nothing read yet
+    assert_eq!(datareader.read_all_cached().unwrap().len(), N as usize);
+
+    // Reading some samples does not change the snapshot
+    assert_eq!(
+      datareader.read(2, ReadCondition::not_read()).unwrap().len(),
+      2
+    );
+    assert_eq!(datareader.read_all_cached().unwrap().len(), N as usize);
+
+    // After everything has been read, the snapshot still has all samples, and
+    // taking the snapshot did not mark anything read.
+    assert_eq!(
+      datareader
+        .read(usize::MAX, ReadCondition::not_read())
+        .unwrap()
+        .len(),
+      N as usize - 2
+    );
+    let all = datareader.read_all_cached().unwrap();
+    assert_eq!(all.len(), N as usize);
+    assert!(all
+      .iter()
+      .all(|s| s.sample_info().sample_state == SampleState::Read));
+    assert!(datareader
+      .read(usize::MAX, ReadCondition::not_read())
+      .unwrap()
+      .is_empty());
+  }
+
+  #[test]
+  fn exclusive_ownership_follows_strength_changes() {
+    use crate::rtps::rtps_writer_proxy::RtpsWriterProxy;
+
+    let dp = DomainParticipant::new(0).expect("Participant creation failed!");
+
+    let qos = QosPolicies::builder()
+      .history(policy::History::KeepAll)
+      .ownership(policy::Ownership::Exclusive { strength: 0 })
+      .build();
+
+    let sub = dp.create_subscriber(&qos).unwrap();
+    let topic = dp
+      .create_topic(
+        "dr exclusive ownership".to_string(),
+        "ownership test?".to_string(),
+        &qos,
+        TopicKind::WithKey,
+      )
+      .unwrap();
+
+    let topic_cache =
+      dp.dds_cache()
+        .write()
+        .unwrap()
+        .add_new_topic(topic.name(), topic.get_type(), &topic.qos());
+
+    // Create a Reader
+    let reader_guid = GUID::new_with_prefix_and_id(dp.guid_prefix(), EntityId::default());
+    let (reader_ing, _channels) =
+      ReaderIngredients::for_test(reader_guid, topic.name(), topic_cache, &qos);
+
+    let mut reader = Reader::new(
+      reader_ing,
+      Rc::new(UDPSender::new_with_random_port().unwrap()),
+      mio_extras::timer::Builder::default().build(),
+    );
+
+    let mut datareader = sub
+      .create_datareader::<RandomData, CDRDeserializerAdapter<RandomData>>(&topic, Some(qos))
+      .unwrap();
+
+    let writer_guid = |n: u8| GUID {
+      prefix: GuidPrefix::new(&[n; 12]),
+      entity_id: EntityId::create_custom_entity_id(
+        [n; 3],
+        EntityKind::WRITER_WITH_KEY_USER_DEFINED,
+      ),
+    };
+    let strong_writer = writer_guid(1);
+    let weak_writer = writer_guid(2);
+
+    let announce = |reader: &mut Reader, writer: GUID, strength: i32| {
+      reader.update_writer_proxy(
+        RtpsWriterProxy::new(writer, Vec::new(), Vec::new(), EntityId::UNKNOWN),
+        &QosPolicies::builder()
+          .ownership(policy::Ownership::Exclusive { strength })
+          .build(),
+      );
+    };
+    announce(&mut reader, strong_writer, 10);
+    announce(&mut reader, weak_writer, 5);
+
+    // Both writers update the same instance
+    let write = |reader: &mut Reader, writer: GUID, sn: i64| {
+      let data = RandomData {
+        a: 1,
+        b: format!("from {writer:?}"),
+      };
+      let data_msg = DecodedData {
+        reader_id: reader.entity_id(),
+        writer_id: writer.entity_id,
+        writer_sn: SequenceNumber::from(sn),
+        serialized_payload: Some(SerializedPayload {
+          representation_identifier: RepresentationIdentifier::CDR_LE,
+          representation_options: [0, 0],
+          value: Bytes::from(to_bytes::<RandomData, LittleEndian>(&data).unwrap()),
+        }),
+        ..DecodedData::default()
+      };
+      let mr_state = MessageReceiverState {
+        source_guid_prefix: writer.prefix,
+        ..Default::default()
+      };
+      reader.handle_data_msg(
+        data_msg,
+        DATA_Flags::Endianness | DATA_Flags::Data,
+        &mr_state,
+      );
+    };
+    let mut take_writers = || -> Vec<GUID> {
+      datareader
+        .take(100, ReadCondition::any())
+        .unwrap()
+        .iter()
+        .map(|s| s.sample_info().writer_guid())
+        .collect()
+    };
+
+    write(&mut reader, strong_writer, 1);
+    write(&mut reader, weak_writer, 1);
+    assert_eq!(take_writers(), vec![strong_writer]);
+
+    // The weak writer raises its strength above the current owner
+    announce(&mut reader, weak_writer, 20);
+    write(&mut reader, weak_writer, 2);
+    write(&mut reader, strong_writer, 2);
+    assert_eq!(take_writers(), vec![weak_writer]);
+  }
 }
@@ -1,6 +1,8 @@
 use std::{
+  cmp::max,
   collections::{BTreeMap, BTreeSet, HashMap, VecDeque},
   ops::Bound,
+  time::Instant,
 };
 
 #[allow(unused_imports)]
@@ -8,13 +10,14 @@ use log::{debug, error, info, warn};
 
 use crate::{
   dds::{
-    key::Keyed,
+    key::{Key, KeyHash, Keyed},
     qos::{policy, QosPolicies},
     readcondition::ReadCondition,
     sampleinfo::*,
     with_key::datasample::{DataSample, DeserializedCacheChange, Sample},
   },
   structure::{guid::GUID, sequence_number::SequenceNumber, time::Timestamp},
+  structure::cache_change::ChangeKind,
   with_key::WriteOptions,
 };
 
@@ -32,13 +35,24 @@ pub struct DataSampleCache<D: Keyed> {
   datasamples: BTreeMap<Timestamp, SampleWithMetaData<D>>, /* ordered storage for deserialized
                                                             * samples */
   pub(crate) instance_map: BTreeMap<D::K, InstanceMetaData>, // ordered storage for instances
+  instances_by_hash: BTreeMap<KeyHash, D::K>, // instance iteration order, stable for any key type
 }
 
 pub(crate) struct InstanceMetaData {
   instance_samples: BTreeSet<Timestamp>, // which samples belong to this instance
   instance_state: InstanceState,         // latest known alive/not_alive state for this instance
+  writers: BTreeSet<GUID>,               // alive Writers that have registered this instance
   latest_generation_available: NotAliveGenerationCounts, // in this instance
   last_generation_accessed: NotAliveGenerationCounts, // in this instance
+  latest_source_timestamp: Option<Timestamp>, // of the samples added to this instance
+  not_alive_since: Option<Instant>,      // when instance_state last became not alive
+}
+
+#[derive(Clone, Copy)]
+struct SampleRanks {
+  sample_rank: i32,
+  generation_rank: i32,
+  absolute_generation_rank: i32,
 }
 
 struct SampleWithMetaData<D: Keyed> {
@@ -75,6 +89,7 @@ where
       qos,
       datasamples: BTreeMap::new(),
       instance_map: BTreeMap::new(),
+      instances_by_hash: BTreeMap::new(),
     }
   }
 
@@ -86,6 +101,7 @@ where
 
     self.add_sample(
       deserialized_cc.sample,
+      deserialized_cc.change_kind,
       deserialized_cc.writer_guid,
       deserialized_cc.sequence_number,
       deserialized_cc.receive_instant,
@@ -96,6 +112,7 @@ where
   fn add_sample(
     &mut self,
     new_sample: Sample<D, D::K>,
+    change_kind: ChangeKind,
     writer_guid: GUID,
     sequence_number: SequenceNumber,
     receive_timestamp: Timestamp,
@@ -106,9 +123,41 @@ where
       Sample::Dispose(k) => k.clone(),
     };
 
-    let new_instance_state = match new_sample {
-      Sample::Value(_) => InstanceState::Alive,
-      Sample::Dispose(_) => InstanceState::NotAliveDisposed,
+    // Unregister from one of several Writers does not change the instance
+    // state, and is not shown to the application.
+    if change_kind == ChangeKind::NotAliveUnregistered {
+      if let Some(imd) = self.instance_map.get_mut(&instance_key) {
+        imd.writers.remove(&writer_guid);
+        if !imd.writers.is_empty() {
+          return;
+        }
+      }
+    }
+
+    // With DestinationOrder BySourceTimeStamp, a sample older than one
+    // already received for the same instance is dropped. See DDS spec v1.4
+    // Section 2.2.3.17 "DESTINATION_ORDER".
+    let source_timestamp = write_options
+      .source_timestamp()
+      .unwrap_or(receive_timestamp);
+    if self.qos.destination_order() == Some(policy::DestinationOrder::BySourceTimeStamp)
+      && self
+        .instance_map
+        .get(&instance_key)
+        .and_then(|imd| imd.latest_source_timestamp)
+        .is_some_and(|latest| source_timestamp < latest)
+    {
+      debug!(
+        "Dropping sample from {:?} seq={:?}: it is older than the latest sample of its instance.",
+        writer_guid, sequence_number
+      );
+      return;
+    }
+
+    let new_instance_state = match (&new_sample, change_kind) {
+      (Sample::Value(_), _) => InstanceState::Alive,
+      (Sample::Dispose(_), ChangeKind::NotAliveUnregistered) => InstanceState::NotAliveNoWriters,
+      (Sample::Dispose(_), _) => InstanceState::NotAliveDisposed,
     };
 
     // find or create metadata record
@@ -119,11 +168,17 @@ where
       let imd = InstanceMetaData {
         instance_samples: BTreeSet::new(),
         instance_state: new_instance_state,
+        writers: BTreeSet::new(),
         latest_generation_available: NotAliveGenerationCounts::zero(), /* this is new instance,
                                                                         * so start from zero */
         last_generation_accessed: NotAliveGenerationCounts::sub_zero(), // never accessed
+        latest_source_timestamp: None,
+        not_alive_since: None,
       };
       self.instance_map.insert(instance_key.clone(), imd);
+      self
+        .instances_by_hash
+        .insert(instance_key.hash_key(false), instance_key.clone());
       self
         .instance_map
         .get_mut(&instance_key)
@@ -133,6 +188,13 @@ where
 
     // update instance metadata
     instance_metadata.instance_samples.insert(receive_timestamp);
+    instance_metadata.latest_source_timestamp = max(
+      instance_metadata.latest_source_timestamp,
+      Some(source_timestamp),
+    );
+    if change_kind != ChangeKind::NotAliveUnregistered {
+      instance_metadata.writers.insert(writer_guid);
+    }
 
     match (instance_metadata.instance_state, new_instance_state) {
       (InstanceState::Alive, _) => (), // was Alive, does not change counts
@@ -157,7 +219,18 @@ where
 
       (InstanceState::NotAliveNoWriters, _) => (), // you can only die once
     }
-    instance_metadata.instance_state = new_instance_state;
+    // Losing the Writers does not undo a dispose.
+    if !(instance_metadata.instance_state == InstanceState::NotAliveDisposed
+      && new_instance_state == InstanceState::NotAliveNoWriters)
+    {
+      if instance_metadata.instance_state != new_instance_state
+        || instance_metadata.not_alive_since.is_none()
+      {
+        instance_metadata.not_alive_since =
+          (new_instance_state != InstanceState::Alive).then(Instant::now);
+      }
+      instance_metadata.instance_state = new_instance_state;
+    }
 
     // insert new_sample to main table
     self
@@ -225,6 +298,97 @@ where
     // sample, i.e.
   }
 
+  // The Writers in `lost_writers` are no longer alive. Instances that have no
+  // other alive Writers become NotAliveNoWriters. If the application has
+  // already accessed all the samples of such an instance, a sample without
+  // data is added to let it know of the state change.
+  pub(crate) fn writers_lost(&mut self, lost_writers: &BTreeSet<GUID>) {
+    if lost_writers.is_empty() {
+      return;
+    }
+    let mut no_writers_instances = Vec::new();
+    for (key, imd) in self.instance_map.iter_mut() {
+      let had_writers = !imd.writers.is_empty();
+      imd.writers.retain(|w| !lost_writers.contains(w));
+      if had_writers && imd.writers.is_empty() && imd.instance_state == InstanceState::Alive {
+        imd.instance_state = InstanceState::NotAliveNoWriters;
+        imd.not_alive_since = Some(Instant::now());
+        let unread_samples = imd.instance_samples.iter().any(|ts| {
+          self
+            .datasamples
+            .get(ts)
+            .is_some_and(|dsm| !dsm.sample_has_been_read)
+        });
+        if !unread_samples {
+          no_writers_instances.push((key.clone(), imd.latest_generation_available));
+        }
+      }
+    }
+
+    for (key, generation_counts) in no_writers_instances {
+      // Receive timestamps must be unique keys
+      let mut timestamp = Timestamp::now();
+      while self.datasamples.contains_key(&timestamp) {
+        timestamp = timestamp + crate::Duration::from_nanos(1);
+      }
+      self.datasamples.insert(
+        timestamp,
+        SampleWithMetaData {
+          generation_counts,
+          writer_guid: GUID::GUID_UNKNOWN,
+          sequence_number: SequenceNumber::zero(),
+          write_options: WriteOptions::default(),
+          sample_has_been_read: false,
+          sample: Sample::Dispose(key.clone()),
+        },
+      );
+      if let Some(imd) = self.instance_map.get_mut(&key) {
+        imd.instance_samples.insert(timestamp);
+      }
+    }
+  }
+
+  // Removes the instances that have been NotAliveNoWriters or NotAliveDisposed
+  // for longer than the autopurge delays of the ReaderDataLifecycle QoS policy,
+  // together with all their samples.
+  pub(crate) fn autopurge_instances(&mut self) {
+    let (nowriter_delay, disposed_delay) = match self.qos.reader_data_lifecycle() {
+      Some(policy::ReaderDataLifecycle {
+        autopurge_nowriter_samples_delay,
+        autopurge_disposed_samples_delay,
+      }) => (
+        autopurge_nowriter_samples_delay.to_std(),
+        autopurge_disposed_samples_delay.to_std(),
+      ),
+      None => return, // Infinite delays by default
+    };
+    let expired_instances: Vec<D::K> = self
+      .instance_map
+      .iter()
+      .filter(|(_key, imd)| {
+        let delay = match imd.instance_state {
+          InstanceState::Alive => return false,
+          InstanceState::NotAliveNoWriters => nowriter_delay,
+          InstanceState::NotAliveDisposed => disposed_delay,
+        };
+        imd
+          .not_alive_since
+          .is_some_and(|since| since.elapsed() >= delay)
+      })
+      .map(|(key, _imd)| key.clone())
+      .collect();
+
+    for key in expired_instances {
+      debug!("Autopurge instance with key hash {:?}", key.hash_key(false));
+      if let Some(imd) = self.instance_map.remove(&key) {
+        for ts in &imd.instance_samples {
+          self.datasamples.remove(ts);
+        }
+      }
+      self.instances_by_hash.remove(&key.hash_key(false));
+    }
+  }
+
   // Calling select_(instance)_keys_for access does not constitute access, i.e.
   // it does not change any state of the cache.
   // Samples are marked read or viewed only when "read" or "take" methods (below)
@@ -269,6 +433,27 @@ where
     }
   }
 
+  // Select samples of the first instance after `previous`, in key hash order,
+  // that has samples matching the ReadCondition. Instances without such
+  // samples are skipped, so that an empty result means that all instances
+  // have been visited.
+  pub fn select_next_instance_keys_for_access(
+    &self,
+    previous: Option<&D::K>,
+    rc: ReadCondition,
+  ) -> Vec<(Timestamp, D::K)> {
+    let lower_bound = match previous {
+      Some(key) => Bound::Excluded(key.hash_key(false)),
+      None => Bound::Unbounded,
+    };
+    self
+      .instances_by_hash
+      .range((lower_bound, Bound::Unbounded))
+      .map(|(_hash, key)| self.select_instance_keys_for_access(key, rc))
+      .find(|selected| !selected.is_empty())
+      .unwrap_or_default()
+  }
+
   // select helper
   fn sample_selector(
     &self,
@@ -299,12 +484,57 @@ where
     )
   }
 
+  // Computes the sample_rank, generation_rank, and absolute_generation_rank of
+  // each sample in a read/take result. All of them are relative to the other
+  // samples of the same instance:
+  // * sample_rank: how many samples of the same instance follow this one in
+  //   the result.
+  // * generation_rank: generation difference to the most recent sample of the
+  //   same instance in the result (MRSIC).
+  // * absolute_generation_rank: generation difference to the most recent
+  //   sample of the same instance that has been received (MRS).
+  //
+  // See DDS spec v1.4 Section "2.2.2.5.5 sample_rank, generation_rank, and
+  // absolute_generation_rank".
+  fn sample_ranks(&self, keys: &[(Timestamp, D::K)]) -> Vec<SampleRanks> {
+    let generation = |ts: &Timestamp| {
+      self
+        .datasamples
+        .get(ts)
+        .map_or(0, |dswm| dswm.generation_counts.total())
+    };
+
+    let mut remaining_samples: HashMap<&D::K, usize> = HashMap::new();
+    let mut mrsic_generations: HashMap<&D::K, i32> = HashMap::new();
+    for (ts, key) in keys {
+      *remaining_samples.entry(key).or_insert(0) += 1;
+      let mrsic = mrsic_generations.entry(key).or_insert(0);
+      *mrsic = (*mrsic).max(generation(ts));
+    }
+
+    keys
+      .iter()
+      .map(|(ts, key)| {
+        let sample_generation = generation(ts);
+        let remaining = remaining_samples.get_mut(key).unwrap(); // all keys were inserted above
+        *remaining -= 1;
+        let mrs_generation = self.instance_map.get(key).map_or(sample_generation, |imd| {
+          imd.latest_generation_available.total()
+        });
+        SampleRanks {
+          sample_rank: *remaining as i32,
+          generation_rank: mrsic_generations[key] - sample_generation,
+          absolute_generation_rank: mrs_generation - sample_generation,
+        }
+      })
+      .collect()
+  }
+
   fn make_sample_info(
+    receive_timestamp: Timestamp,
     dswm: &SampleWithMetaData<D>,
     imd: &InstanceMetaData,
-    sample_rank: usize,
-    mrs_generations: i32,
-    mrsic_generations: i32,
+    ranks: SampleRanks,
   ) -> SampleInfo {
     SampleInfo {
       sample_state: if dswm.sample_has_been_read {
@@ -319,12 +549,13 @@ where
       },
       instance_state: imd.instance_state,
       generation_counts: dswm.generation_counts,
-      sample_rank: sample_rank as i32, // how many samples follow this one
-      generation_rank: mrsic_generations - dswm.generation_counts.total(),
-      absolute_generation_rank: mrs_generations - dswm.generation_counts.total(),
+      sample_rank: ranks.sample_rank,
+      generation_rank: ranks.generation_rank,
+      absolute_generation_rank: ranks.absolute_generation_rank,
       write_options: dswm.write_options.clone(),
       publication_handle: dswm.writer_guid,
       sequence_number: dswm.sequence_number,
+      reception_timestamp: receive_timestamp,
     }
   }
 
@@ -372,27 +603,14 @@ where
     }
 
     let mut instance_generations: HashMap<D::K, NotAliveGenerationCounts> = HashMap::new();
-    let mrsic_total = self
-      .instance_map
-      .get(&keys.last().unwrap().1)
-      .unwrap()
-      .latest_generation_available
-      .total();
-    let mrs_total = self
-      .datasamples
-      .iter()
-      .next_back()
-      .unwrap()
-      .1
-      .generation_counts
-      .total();
+    let ranks = self.sample_ranks(keys);
     let mut sample_infos = VecDeque::with_capacity(len);
     // construct SampleInfos and record read/viewed
-    for (index, (ts, key)) in keys.iter().enumerate() {
+    for ((ts, key), ranks) in keys.iter().zip(ranks) {
       let dswm = self.datasamples.get_mut(ts).unwrap();
       let imd = self.instance_map.get(key).unwrap();
 
-      let sample_info = Self::make_sample_info(dswm, imd, len - index - 1, mrs_total, mrsic_total);
+      let sample_info = Self::make_sample_info(*ts, dswm, imd, ranks);
       dswm.sample_has_been_read = true; // mark as read
       Self::record_instance_generation_viewed(
         &mut instance_generations,
@@ -434,25 +652,12 @@ where
     }
 
     let mut instance_generations: HashMap<D::K, NotAliveGenerationCounts> = HashMap::new();
-    let mrsic_total = self
-      .instance_map
-      .get(&keys.last().unwrap().1)
-      .unwrap()
-      .latest_generation_available
-      .total();
-    let mrs_total = self
-      .datasamples
-      .iter()
-      .next_back()
-      .unwrap()
-      .1
-      .generation_counts
-      .total();
+    let ranks = self.sample_ranks(keys);
     // collect result
-    for (index, (ts, key)) in keys.iter().enumerate() {
+    for ((ts, key), ranks) in keys.iter().zip(ranks) {
       let dswm = self.datasamples.remove(ts).unwrap();
       let imd = self.instance_map.get(key).unwrap();
-      let sample_info = Self::make_sample_info(&dswm, imd, len - index - 1, mrs_total, mrsic_total);
+      let sample_info = Self::make_sample_info(*ts, &dswm, imd, ranks);
       // dwsm.sample_has_been_read = true; // no need to mark read, as the dswm is
       // about to be destroyed
       Self::record_instance_generation_viewed(
@@ -467,6 +672,28 @@ where
     result
   }
 
+  // Clones out every sample in the cache, with SampleInfos as they currently
+  // are. Unlike read_by_keys, this does not mark anything as read or viewed.
+  pub fn snapshot_all(&self) -> Vec<DataSample<D>>
+  where
+    D: Clone,
+  {
+    let keys = self.select_keys_for_access(ReadCondition::any());
+    let ranks = self.sample_ranks(&keys);
+    keys
+      .iter()
+      .zip(ranks)
+      .map(|((ts, key), ranks)| {
+        let dswm = self.datasamples.get(ts).unwrap();
+        let imd = self.instance_map.get(key).unwrap();
+        DataSample::new(
+          Self::make_sample_info(*ts, dswm, imd, ranks),
+          dswm.sample.clone(),
+        )
+      })
+      .collect()
+  }
+
   pub fn read_bare_by_keys(&mut self, keys: &[(Timestamp, D::K)]) -> Vec<Sample<&D, D::K>> {
     let len = keys.len();
     let mut result = Vec::with_capacity(len);
@@ -548,7 +775,144 @@ pub(crate) fn result_ok_as_ref_err_clone<T, E: Clone>(r: &Sample<T, E>) -> Sampl
 
 #[cfg(test)]
 mod tests {
-  // use super::*;
+  use super::*;
+  use crate::test::random_data::RandomData;
+
+  #[test]
+  fn dsc_iterate_instances() {
+    let mut cache = DataSampleCache::<RandomData>::new(
+      crate::QosPolicyBuilder::new()
+        .history(policy::History::KeepAll)
+        .build(),
+    );
+    for (i, a) in [3, 1, 2, 3, 1, 2].into_iter().enumerate() {
+      cache.add_sample(
+        Sample::Value(RandomData {
+          a,
+          b: "instance".to_string(),
+        }),
+        ChangeKind::Alive,
+        GUID::GUID_UNKNOWN,
+        SequenceNumber::new(i as i64 + 1),
+        Timestamp::now(),
+        WriteOptions::default(),
+      );
+      std::thread::sleep(std::time::Duration::from_millis(1)); // unique timestamps
+    }
+
+    let mut visited = Vec::new();
+    let mut previous = None;
+    loop {
+      let selected =
+        cache.select_next_instance_keys_for_access(previous.as_ref(), ReadCondition::any());
+      let samples = cache.take_by_keys(&selected);
+      let Some(first) = samples.first() else { break };
+      assert!(samples.iter().all(|s| s.key() == first.key()));
+      assert_eq!(samples.len(), 2);
+      previous = Some(first.key());
+      visited.push(first.key());
+    }
+    visited.sort();
+    assert_eq!(visited, vec![1, 2, 3]);
+
+    // All taken. Instances are still known, but skipped.
+    assert!(cache
+      .select_next_instance_keys_for_access(None, ReadCondition::any())
+      .is_empty());
+  }
+
+  #[test]
+  fn dsc_sample_ranks() {
+    let mut cache = DataSampleCache::<RandomData>::new(
+      crate::QosPolicyBuilder::new()
+        .history(policy::History::KeepAll)
+        .build(),
+    );
+    // Instance 1 is disposed after its third sample and then written again,
+    // so its last two samples are in the next generation. Instance 2 has a
+    // single sample in between.
+    let changes = [
+      (1, ChangeKind::Alive),
+      (1, ChangeKind::Alive),
+      (2, ChangeKind::Alive),
+      (1, ChangeKind::NotAliveDisposed),
+      (1, ChangeKind::Alive),
+      (1, ChangeKind::Alive),
+    ];
+    for (i, (a, change_kind)) in changes.into_iter().enumerate() {
+      let sample = if change_kind == ChangeKind::Alive {
+        Sample::Value(RandomData {
+          a,
+          b: "rank".to_string(),
+        })
+      } else {
+        Sample::Dispose(a)
+      };
+      cache.add_sample(
+        sample,
+        change_kind,
+        GUID::GUID_UNKNOWN,
+        SequenceNumber::new(i as i64 + 1),
+        Timestamp::now(),
+        WriteOptions::default(),
+      );
+      std::thread::sleep(std::time::Duration::from_millis(1)); // unique timestamps
+    }
+
+    let ranks = |samples: &[DataSample<&RandomData>]| -> Vec<(i64, i32, i32, i32)> {
+      samples
+        .iter()
+        .map(|s| {
+          let info = s.sample_info();
+          (
+            s.key(),
+            info.sample_rank(),
+            info.generation_rank(),
+            info.absolute_generation_rank(),
+          )
+        })
+        .collect()
+    };
+
+    // Ranks are counted only over samples of the same instance
+    let selected = cache.select_keys_for_access(ReadCondition::any());
+    assert_eq!(
+      ranks(&cache.read_by_keys(&selected)),
+      vec![
+        (1, 4, 1, 1),
+        (1, 3, 1, 1),
+        (2, 0, 0, 0),
+        (1, 2, 1, 1),
+        (1, 1, 0, 0),
+        (1, 0, 0, 0),
+      ]
+    );
+
+    // Take only the first generation of instance 1. Now the most recent sample
+    // in the collection (MRSIC) is older than the most recent sample received
+    // (MRS).
+    let selected = cache.select_instance_keys_for_access(&1, ReadCondition::any());
+    let first_generation: Vec<_> = cache
+      .take_by_keys(&selected[..3])
+      .iter()
+      .map(|s| {
+        let info = s.sample_info();
+        (
+          info.sample_rank(),
+          info.generation_rank(),
+          info.absolute_generation_rank(),
+        )
+      })
+      .collect();
+    assert_eq!(first_generation, vec![(2, 0, 1), (1, 0, 1), (0, 0, 1)]);
+
+    let selected = cache.select_instance_keys_for_access(&1, ReadCondition::any());
+    assert_eq!(
+      ranks(&cache.read_by_keys(&selected)),
+      vec![(1, 1, 0, 0), (1, 0, 0, 0)]
+    );
+  }
+
   // use crate::{
   //   structure::{time::Timestamp},
   // };
@@ -1,4 +1,5 @@
 use std::{
+  collections::BTreeMap,
   marker::PhantomData,
   pin::Pin,
   sync::{
@@ -21,10 +22,10 @@ use crate::{
     dds_entity::DDSEntity,
     ddsdata::DDSData,
     helpers::*,
-    pubsub::Publisher,
+    pubsub::{DisabledWriter, Publisher},
     qos::{
-      policy::{Liveliness, Reliability},
-      HasQoSPolicy, QosPolicies,
+      policy::{Liveliness, Reliability, WriterDataLifecycle},
+      HasQoSPolicy, QosDiff, QosPolicies,
     },
     result::{CreateResult, WriteError, WriteResult},
     statusevents::*,
@@ -48,6 +49,8 @@ pub struct WriteOptionsBuilder {
   related_sample_identity: Option<SampleIdentity>,
   source_timestamp: Option<Timestamp>,
   to_single_reader: Option<GUID>,
+  coherent_set: Option<SequenceNumber>,
+  expiration: Option<Timestamp>,
 }
 
 impl WriteOptionsBuilder {
@@ -60,6 +63,8 @@ impl WriteOptionsBuilder {
       related_sample_identity: self.related_sample_identity,
       source_timestamp: self.source_timestamp,
       to_single_reader: self.to_single_reader,
+      coherent_set: self.coherent_set,
+      expiration: self.expiration,
     }
   }
 
@@ -89,6 +94,21 @@ impl WriteOptionsBuilder {
     self.to_single_reader = Some(reader);
     self
   }
+
+  // Coherent sets are started and ended through the Publisher, so this is not
+  // for applications.
+  #[must_use]
+  pub(crate) fn coherent_set(mut self, coherent_set_start: SequenceNumber) -> Self {
+    self.coherent_set = Some(coherent_set_start);
+    self
+  }
+
+  // Expiration comes from the LIFESPAN QoS of the writer.
+  #[must_use]
+  pub(crate) fn expiration(mut self, expiration: Timestamp) -> Self {
+    self.expiration = Some(expiration);
+    self
+  }
 }
 
 /// Type to be used with write_with_options.
@@ -99,6 +119,8 @@ pub struct WriteOptions {
   source_timestamp: Option<Timestamp>,             // from DDS spec
   to_single_reader: Option<GUID>,                  /* try to send to one Reader only
                                                     * future extension room fo other fields. */
+  coherent_set: Option<SequenceNumber>, // first sequence number of the coherent set
+  expiration: Option<Timestamp>,        // source timestamp + LIFESPAN duration
 }
 
 impl WriteOptions {
@@ -113,6 +135,33 @@ impl WriteOptions {
   pub fn to_single_reader(&self) -> Option<GUID> {
     self.to_single_reader
   }
+
+  /// If the sample belongs to a coherent set, this is the sequence number of
+  /// the first sample in the set.
+  pub fn coherent_set(&self) -> Option<SequenceNumber> {
+    self.coherent_set
+  }
+
+  pub(crate) fn with_coherent_set(mut self, coherent_set_start: SequenceNumber) -> Self {
+    self.coherent_set = Some(coherent_set_start);
+    self
+  }
+
+  /// If the writer has a LIFESPAN QoS, the sample is no longer delivered to
+  /// applications after this time.
+  pub fn expiration(&self) -> Option<Timestamp> {
+    self.expiration
+  }
+
+  pub(crate) fn with_source_timestamp(mut self, source_timestamp: Timestamp) -> Self {
+    self.source_timestamp = Some(source_timestamp);
+    self
+  }
+
+  pub(crate) fn with_expiration(mut self, expiration: Timestamp) -> Self {
+    self.expiration = Some(expiration);
+    self
+  }
 }
 
 impl From<Option<Timestamp>> for WriteOptions {
@@ -121,10 +170,29 @@ impl From<Option<Timestamp>> for WriteOptions {
       related_sample_identity: None,
       source_timestamp,
       to_single_reader: None,
+      coherent_set: None,
+      expiration: None,
     }
   }
 }
 
+/// Reliability protocol state of a single matched reader, as seen by the
+/// DataWriter. This is a read-only snapshot intended for debugging delivery
+/// problems. See [`DataWriter::reader_reliability_state`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReaderReliabilityState {
+  /// The reader has acknowledged all samples up to and including this
+  /// sequence number. Zero means nothing has been acknowledged yet.
+  pub highest_acked: SequenceNumber,
+  /// Samples that are still to be sent to the reader. This includes the
+  /// samples the reader has requested (negatively acknowledged) by ACKNACK.
+  pub requested_changes: Vec<SequenceNumber>,
+  /// When the Writer last sent a HEARTBEAT to this reader.
+  pub last_heartbeat_sent: Option<Timestamp>,
+  /// When an ACKNACK was last received from this reader.
+  pub last_acknack_received: Option<Timestamp>,
+}
+
 /// Simplified type for CDR encoding
 pub type DataWriterCdr<D> = DataWriter<D, CDRSerializerAdapter<D>>;
 
@@ -167,7 +235,15 @@ pub struct DataWriter<D: Keyed, SA: SerializerAdapter<D> = CDRSerializerAdapter<
   cc_upload_waker: Arc<Mutex<Option<Waker>>>,
   discovery_command: mio_channel::SyncSender<DiscoveryCommand>,
   status_receiver: StatusReceiver<DataWriterStatus>,
-  available_sequence_number: AtomicI64,
+  // Shared with the Publisher, which allocates coherent set end markers.
+  available_sequence_number: Arc<AtomicI64>,
+  // Updated by the RTPS Writer.
+  reader_states: Arc<Mutex<BTreeMap<GUID, ReaderReliabilityState>>>,
+  // Updated by the RTPS Writer: Highest sequence number we can write without
+  // waiting for acknowledgements.
+  write_limit: Arc<AtomicI64>,
+  // Some, until a DataWriter created disabled is enabled
+  disabled_writer: Mutex<Option<DisabledWriter>>,
 }
 
 impl<D, SA> Drop for DataWriter<D, SA>
@@ -176,10 +252,17 @@ where
   SA: SerializerAdapter<D>,
 {
   fn drop(&mut self) {
-    // Tell Publisher to drop the corresponding RTPS Writer
-    self.my_publisher.remove_writer(self.my_guid);
+    // Tell Publisher to drop the corresponding RTPS Writer, unless
+    // DomainParticipant::delete_contained_entities did that already.
+    if !self.my_publisher.remove_writer(self.my_guid) {
+      return;
+    }
 
-    // Notify Discovery that we are no longer
+    // Notify Discovery that we are no longer. It never heard of a disabled
+    // DataWriter.
+    if !self.is_enabled() {
+      return;
+    }
     match self
       .discovery_command
       .send(DiscoveryCommand::RemoveLocalWriter { guid: self.guid() })
@@ -214,6 +297,8 @@ where
     cc_upload_waker: Arc<Mutex<Option<Waker>>>,
     discovery_command: mio_channel::SyncSender<DiscoveryCommand>,
     status_receiver_rec: StatusChannelReceiver<DataWriterStatus>,
+    reader_states: Arc<Mutex<BTreeMap<GUID, ReaderReliabilityState>>>,
+    write_limit: Arc<AtomicI64>,
   ) -> CreateResult<Self> {
     if let Some(lv) = qos.liveliness {
       match lv {
@@ -236,10 +321,18 @@ where
       cc_upload_waker,
       discovery_command,
       status_receiver: StatusReceiver::new(status_receiver_rec),
-      available_sequence_number: AtomicI64::new(1), // valid numbering starts from 1
+      available_sequence_number: Arc::new(AtomicI64::new(1)), // valid numbering starts from 1
+      reader_states,
+      write_limit,
+      disabled_writer: Mutex::new(None),
     })
   }
 
+  // Defers registration with the event loop and Discovery until enable()
+  pub(crate) fn set_disabled(&self, disabled_writer: DisabledWriter) {
+    *self.disabled_writer.lock().unwrap() = Some(disabled_writer);
+  }
+
   fn next_sequence_number(&self) -> SequenceNumber {
     SequenceNumber::from(
       self
@@ -248,12 +341,30 @@ where
     )
   }
 
+  // LIFESPAN is counted from the source timestamp, so samples are stamped at
+  // write time, not when the Writer gets to them.
+  fn stamp_for_lifespan(&self, write_options: WriteOptions) -> WriteOptions {
+    match (self.qos().lifespan(), write_options.source_timestamp()) {
+      (Some(_), None) => write_options.with_source_timestamp(Timestamp::now()),
+      _ => write_options,
+    }
+  }
+
   fn undo_sequence_number(&self) {
     self
       .available_sequence_number
       .fetch_sub(1, Ordering::Relaxed);
   }
 
+  // For the Publisher to manage coherent sets
+  pub(crate) fn sequence_number_counter(&self) -> Arc<AtomicI64> {
+    Arc::clone(&self.available_sequence_number)
+  }
+
+  pub(crate) fn writer_command_sender(&self) -> mio_channel::SyncSender<WriterCommand> {
+    self.cc_upload.clone()
+  }
+
   // This one function provides both get_matched_subscriptions and
   // get_matched_subscription_data TODO: Maybe we could return references to the
   // subscription data to avoid copying? But then what if the result set changes
@@ -309,6 +420,13 @@ where
 
   /// Writes single data instance to a topic.
   ///
+  /// With Reliable and KeepAll QoS and a ResourceLimits `max_samples` limit,
+  /// the writer does not accept more than `max_samples` samples that are
+  /// not yet acknowledged by all matched reliable readers. Then this call
+  /// blocks for at most the Reliability `max_blocking_time`, waiting for
+  /// acknowledgements, and returns `WriteError::WouldBlock` if there was no
+  /// space.
+  ///
   /// # Examples
   ///
   /// ```
@@ -348,6 +466,9 @@ where
     data: D,
     write_options: WriteOptions,
   ) -> WriteResult<SampleIdentity, D> {
+    if !self.is_enabled() {
+      return Err(WriteError::NotEnabled { data });
+    }
     // serialize
     let send_buffer = match SA::to_bytes(&data) {
       Ok(b) => b,
@@ -366,13 +487,23 @@ where
     let sequence_number = self.next_sequence_number();
     let writer_command = WriterCommand::DDSData {
       ddsdata,
-      write_options,
+      write_options: self.stamp_for_lifespan(write_options),
       sequence_number,
     };
 
     let timeout = self.qos().reliable_max_blocking_time();
 
-    match try_send_timeout(&self.cc_upload, writer_command, timeout) {
+    // Reliable KeepAll history with max_samples must not overflow with
+    // unacknowledged samples. Wait for acknowledgements instead of dropping.
+    let write_limit = &self.write_limit;
+    let acknowledged_enough = || i64::from(sequence_number) <= write_limit.load(Ordering::Acquire);
+
+    match try_send_timeout_when(
+      &self.cc_upload,
+      writer_command,
+      acknowledged_enough,
+      timeout,
+    ) {
       Ok(_) => {
         self.refresh_manual_liveliness();
         Ok(SampleIdentity {
@@ -381,11 +512,19 @@ where
         })
       }
       Err(TrySendError::Full(_writer_command)) => {
-        warn!(
-          "Write timed out: topic={:?}  timeout={:?}",
-          self.my_topic.name(),
-          timeout,
-        );
+        if acknowledged_enough() {
+          warn!(
+            "Write timed out: topic={:?}  timeout={:?}",
+            self.my_topic.name(),
+            timeout,
+          );
+        } else {
+          warn!(
+            "Write timed out waiting for acknowledgements: topic={:?}  timeout={:?}",
+            self.my_topic.name(),
+            timeout,
+          );
+        }
         self.undo_sequence_number();
         Err(WriteError::WouldBlock { data })
       }
@@ -458,28 +597,39 @@ where
           Ready::readable(),
           PollOpt::edge(),
         )?;
-        self
-          .cc_upload
-          .try_send(WriterCommand::WaitForAcknowledgments {
+        let deadline = Instant::now() + max_wait;
+        match try_send_timeout(
+          &self.cc_upload,
+          WriterCommand::WaitForAcknowledgments {
             all_acked: acked_sender,
-          })
-          .unwrap_or_else(|e| {
-            warn!("wait_for_acknowledgments: cannot initiate waiting. This will timeout. {e}");
-          });
+          },
+          Some(crate::Duration::from_std(max_wait)),
+        ) {
+          Ok(()) => (),
+          Err(TrySendError::Full(_)) => {
+            warn!("wait_for_acknowledgments: cannot initiate waiting. Writer is too busy.");
+            return Ok(false);
+          }
+          Err(TrySendError::Disconnected(_)) => {
+            return Err(WriteError::Poisoned {
+              reason: "Cannot send to Writer".to_string(),
+              data: (),
+            })
+          }
+          Err(TrySendError::Io(e)) => return Err(e.into()),
+        }
 
+        // Loop to get over spurious wakeups
         let mut events = Events::with_capacity(1);
-        poll.poll(&mut events, Some(max_wait))?;
-        if let Some(_event) = events.iter().next() {
-          match acked_receiver.try_recv() {
-            Ok(_) => Ok(true), // got token
-            Err(e) => {
-              warn!("wait_for_acknowledgments - Spurious poll event? - {e}");
-              Ok(false) // TODO: We could also loop here
-            }
+        loop {
+          if acked_receiver.try_recv().is_ok() {
+            return Ok(true); // got token
           }
-        } else {
-          // no token, so presumably timed out
-          Ok(false)
+          let time_left = deadline.saturating_duration_since(Instant::now());
+          if time_left.is_zero() {
+            return Ok(false); // timed out
+          }
+          poll.poll(&mut events, Some(time_left))?;
         }
       }
     } // match
@@ -774,8 +924,31 @@ where
     &self.my_publisher
   }
 
+  /// Enables a DataWriter that was created disabled, because
+  /// [`EntityFactory`](crate::policy::EntityFactory) QoS of its Publisher
+  /// has `autoenable_created_entities` set to false. From now on the
+  /// DataWriter is announced in Discovery, matches remote DataReaders, and
+  /// can be written to.
+  ///
+  /// Enabling an enabled DataWriter does nothing.
+  pub fn enable(&self) -> CreateResult<()> {
+    let mut disabled_writer = self.disabled_writer.lock()?;
+    self.my_publisher.enable_writer(&mut disabled_writer)
+  }
+
+  /// Tells if this DataWriter is enabled. See [`enable`](Self::enable).
+  /// Writing to a disabled DataWriter fails with [`WriteError::NotEnabled`].
+  pub fn is_enabled(&self) -> bool {
+    self.disabled_writer.lock().unwrap().is_none()
+  }
+
   /// Manually asserts liveliness (use this instead of refresh) according to QoS
   ///
+  /// With LIVELINESS QoS of MANUAL_BY_TOPIC, this sends a HEARTBEAT with the
+  /// liveliness flag to matched Readers. Such a Writer must assert its
+  /// liveliness (or write) within its lease duration, or the Readers report
+  /// it as not alive with `LivelinessChanged`.
+  ///
   /// # Examples
   ///
   /// ```
@@ -864,8 +1037,55 @@ where
     todo!()
   }
 
+  /// Reports the reliability protocol state of a matched reader: what it has
+  /// acknowledged, what is still to be sent to it, and when HEARTBEAT and
+  /// ACKNACK were last exchanged with it.
+  ///
+  /// This is meant for debugging. The state is updated by the Writer as
+  /// protocol messages are processed, so it may lag behind slightly.
+  ///
+  /// Returns `None` if `reader_guid` is not a matched reader.
+  pub fn reader_reliability_state(&self, reader_guid: GUID) -> Option<ReaderReliabilityState> {
+    self
+      .reader_states
+      .lock()
+      .unwrap()
+      .get(&reader_guid)
+      .cloned()
+  }
+
+  /// Compares the QoS of this DataWriter with the QoS advertised by a remote
+  /// DataReader of the same topic.
+  ///
+  /// The diff lists the policies that differ, and which of them prevent
+  /// matching. This works also for readers that did not match because of
+  /// incompatible QoS.
+  ///
+  /// Returns `None` if no such reader has been discovered.
+  pub fn qos_diff(&self, reader_guid: GUID) -> Option<QosDiff> {
+    let remote = self
+      .my_publisher
+      .participant()?
+      .discovered_readers()
+      .into_iter()
+      .find(|r| {
+        r.reader_proxy.remote_reader_guid == reader_guid
+          && r.subscription_topic_data.topic_name() == &self.my_topic.name()
+      })?;
+    Some(QosDiff::new(
+      self.qos(),
+      remote.subscription_topic_data.qos(),
+      true,
+    ))
+  }
+
   /// Disposes data instance with specified key
   ///
+  /// Only the key is serialized and sent. DataReaders see the dispose as a
+  /// [`Sample::Dispose`](crate::with_key::Sample::Dispose) with instance state
+  /// `NotAliveDisposed`. This works also for instances that this DataWriter
+  /// has never written.
+  ///
   /// # Arguments
   ///
   /// * `key` - Key of the instance
@@ -917,13 +1137,59 @@ where
     key: &<D as Keyed>::K,
     source_timestamp: Option<Timestamp>,
   ) -> WriteResult<(), ()> {
+    self.send_key_change(key, ChangeKind::NotAliveDisposed, source_timestamp)
+  }
+
+  /// Unregisters data instance with specified key, i.e. tells that this
+  /// DataWriter will no longer update it. When an instance has no DataWriters
+  /// left, DataReaders see it as `NotAliveNoWriters`.
+  ///
+  /// If the [`WriterDataLifecycle`] QoS policy has
+  /// `autodispose_unregistered_instances` set, which is the default, the
+  /// instance is also disposed, so DataReaders see it as
+  /// `NotAliveDisposed`.
+  ///
+  /// # Arguments
+  ///
+  /// * `key` - Key of the instance
+  /// * `source_timestamp` - DDS source timestamp (None uses now as time as
+  ///   specified in DDS spec)
+  pub fn unregister_instance(
+    &self,
+    key: &<D as Keyed>::K,
+    source_timestamp: Option<Timestamp>,
+  ) -> WriteResult<(), ()> {
+    let autodispose = match self.qos_policy.writer_data_lifecycle() {
+      Some(WriterDataLifecycle {
+        autodispose_unregistered_instances,
+      }) => autodispose_unregistered_instances,
+      None => true,
+    };
+    // Both changes get the same source timestamp.
+    let source_timestamp = source_timestamp.or_else(|| Some(Timestamp::now()));
+    if autodispose {
+      self.send_key_change(key, ChangeKind::NotAliveDisposed, source_timestamp)?;
+    }
+    self.send_key_change(key, ChangeKind::NotAliveUnregistered, source_timestamp)
+  }
+
+  // Sends a dispose or unregister of the instance with the given key
+  fn send_key_change(
+    &self,
+    key: &<D as Keyed>::K,
+    change_kind: ChangeKind,
+    source_timestamp: Option<Timestamp>,
+  ) -> WriteResult<(), ()> {
+    if !self.is_enabled() {
+      return Err(WriteError::NotEnabled { data: () });
+    }
     let send_buffer = SA::key_to_bytes(key).map_err(|e| WriteError::Serialization {
       reason: format!("{e}"),
       data: (),
     })?; // serialize key
 
     let ddsdata = DDSData::new_disposed_by_key(
-      ChangeKind::NotAliveDisposed,
+      change_kind,
       SerializedPayload::new_from_bytes(SA::output_encoding(), send_buffer),
     );
     self
@@ -1157,12 +1423,16 @@ where
           }) {
           Ok(()) => {
             *self = AsyncWaitForAcknowledgments::Waiting { ack_wait_receiver };
+            // Poll again right away to register for the acknowledgement.
+            cx.waker().wake_by_ref();
             Poll::Pending
           }
 
           Err(TrySendError::Full(WriterCommand::WaitForAcknowledgments {
             all_acked: ack_wait_sender,
           })) => {
+            // Writer wakes us up when it has taken commands from the channel.
+            *writer.cc_upload_waker.lock().unwrap() = Some(cx.waker().clone());
             *self = AsyncWaitForAcknowledgments::WaitingSendCommand {
               writer,
               ack_wait_receiver,
@@ -1210,6 +1480,9 @@ where
     data: D,
     write_options: WriteOptions,
   ) -> WriteResult<SampleIdentity, D> {
+    if !self.is_enabled() {
+      return Err(WriteError::NotEnabled { data });
+    }
     // Construct a future for an async write operation and await for its completion
 
     let send_buffer = match SA::to_bytes(&data) {
@@ -1229,7 +1502,7 @@ where
     let sequence_number = self.next_sequence_number();
     let writer_command = WriterCommand::DDSData {
       ddsdata: dds_data,
-      write_options,
+      write_options: self.stamp_for_lifespan(write_options),
       sequence_number,
     };
 
@@ -1286,8 +1559,8 @@ mod tests {
       participant::DomainParticipant,
     },
     serialization::cdr_serializer::CDRSerializerAdapter,
-    structure::topic_kind::TopicKind,
-    test::random_data::*,
+    structure::{guid::EntityKind, topic_kind::TopicKind},
+    test::{random_data::*, wait_until},
   };
 
   #[test]
@@ -1406,4 +1679,309 @@ mod tests {
     assert!(res); // we should get "true" immediately, because we have
                   // no Reliable QoS
   }
+
+  #[test]
+  fn dw_keep_all_blocks_on_unacked_history() {
+    use crate::{
+      dds::{
+        participant::DomainParticipantBuilder,
+        qos::{policy, QosPolicyBuilder},
+        statusevents::StatusEvented,
+      },
+      Duration as DdsDuration,
+    };
+
+    let domain_id = 22;
+    let writer_participant =
+      DomainParticipant::new(domain_id).expect("Participant creation failed");
+    // A slow reader: it postpones its ACKNACKs far beyond the test duration.
+    let mut builder = DomainParticipantBuilder::new(domain_id);
+    builder.heartbeat_response_delay(std::time::Duration::from_secs(3600));
+    let reader_participant = builder.build().expect("Participant creation failed");
+
+    let max_blocking_time = std::time::Duration::from_millis(200);
+    let qos = QosPolicyBuilder::new()
+      .reliability(policy::Reliability::Reliable {
+        max_blocking_time: DdsDuration::from_std(max_blocking_time),
+      })
+      .history(policy::History::KeepAll)
+      .resource_limits(policy::ResourceLimits {
+        max_samples: 2,
+        max_instances: 1,
+        max_samples_per_instance: 2,
+      })
+      .build();
+
+    let writer_topic = writer_participant
+      .create_topic(
+        "keep_all_blocking".to_string(),
+        "RandomData".to_string(),
+        &qos,
+        TopicKind::WithKey,
+      )
+      .unwrap();
+    let reader_topic = reader_participant
+      .create_topic(
+        "keep_all_blocking".to_string(),
+        "RandomData".to_string(),
+        &qos,
+        TopicKind::WithKey,
+      )
+      .unwrap();
+
+    // The slow reader is slow also in discovery, so it cannot ask for
+    // repairs. Let the participants find each other first, so that endpoint
+    // discovery data is received when it is pushed.
+    let discovered = wait_until(std::time::Duration::from_secs(10), || {
+      reader_participant
+        .discovered_participants()
+        .iter()
+        .any(|p| p.guid.prefix == writer_participant.guid_prefix())
+        && writer_participant
+          .discovered_participants()
+          .iter()
+          .any(|p| p.guid.prefix == reader_participant.guid_prefix())
+    });
+    assert!(discovered, "Participants did not discover each other");
+
+    let _data_reader = reader_participant
+      .create_subscriber(&qos)
+      .unwrap()
+      .create_datareader::<RandomData, crate::CDRDeserializerAdapter<RandomData>>(
+        &reader_topic,
+        None,
+      )
+      .unwrap();
+    let mut data_writer: DataWriter<RandomData, CDRSerializerAdapter<RandomData, LittleEndian>> =
+      writer_participant
+        .create_publisher(&qos)
+        .unwrap()
+        .create_datawriter(&writer_topic, None)
+        .unwrap();
+    data_writer.as_status_evented(); // enables status reception
+
+    let matched = wait_until(std::time::Duration::from_secs(10), || {
+      matches!(
+        data_writer.try_recv_status(),
+        Some(DataWriterStatus::PublicationMatched { .. })
+      )
+    });
+    assert!(matched, "Reader was not matched");
+
+    // The writer history takes max_samples, but then write must time out,
+    // since the reader does not acknowledge anything.
+    let mut result = Ok(());
+    let mut elapsed = std::time::Duration::ZERO;
+    let mut written = 0;
+    for a in 0..100 {
+      let start = std::time::Instant::now();
+      result = data_writer.write(
+        RandomData {
+          a,
+          b: "blocking".to_string(),
+        },
+        None,
+      );
+      elapsed = start.elapsed();
+      if result.is_err() {
+        break;
+      }
+      written += 1;
+    }
+    assert_eq!(
+      written, 2,
+      "History should take exactly max_samples before blocking"
+    );
+    assert!(matches!(result, Err(WriteError::WouldBlock { .. })));
+    assert!(elapsed >= max_blocking_time / 2);
+  }
+
+  #[test]
+  fn dw_wait_for_ack_from_reliable_reader() {
+    use crate::{
+      dds::{
+        qos::{policy, QosPolicyBuilder},
+        statusevents::StatusEvented,
+      },
+      Duration as DdsDuration,
+    };
+
+    let domain_id = 23;
+    let writer_participant =
+      DomainParticipant::new(domain_id).expect("Participant creation failed");
+    let reader_participant =
+      DomainParticipant::new(domain_id).expect("Participant creation failed");
+
+    let qos = QosPolicyBuilder::new()
+      .reliability(policy::Reliability::Reliable {
+        max_blocking_time: DdsDuration::from_millis(100),
+      })
+      .history(policy::History::KeepAll)
+      .build();
+
+    let writer_topic = writer_participant
+      .create_topic(
+        "wait_for_ack".to_string(),
+        "RandomData".to_string(),
+        &qos,
+        TopicKind::WithKey,
+      )
+      .unwrap();
+    let reader_topic = reader_participant
+      .create_topic(
+        "wait_for_ack".to_string(),
+        "RandomData".to_string(),
+        &qos,
+        TopicKind::WithKey,
+      )
+      .unwrap();
+
+    let mut data_writer: DataWriter<RandomData, CDRSerializerAdapter<RandomData, LittleEndian>> =
+      writer_participant
+        .create_publisher(&qos)
+        .unwrap()
+        .create_datawriter(&writer_topic, None)
+        .unwrap();
+    data_writer.as_status_evented(); // enables status reception
+
+    // No matched readers: nobody to wait for.
+    assert!(data_writer
+      .wait_for_acknowledgments(Duration::from_secs(5))
+      .unwrap());
+
+    let mut data_reader = reader_participant
+      .create_subscriber(&qos)
+      .unwrap()
+      .create_datareader::<RandomData, crate::CDRDeserializerAdapter<RandomData>>(
+        &reader_topic,
+        None,
+      )
+      .unwrap();
+
+    let matched = wait_until(Duration::from_secs(10), || {
+      matches!(
+        data_writer.try_recv_status(),
+        Some(DataWriterStatus::PublicationMatched { .. })
+      )
+    });
+    assert!(matched, "Reader was not matched");
+
+    for a in 0..5 {
+      data_writer
+        .write(
+          RandomData {
+            a,
+            b: "ack me".to_string(),
+          },
+          None,
+        )
+        .unwrap();
+    }
+    assert!(data_writer
+      .wait_for_acknowledgments(Duration::from_secs(5))
+      .unwrap());
+
+    // Acknowledged means the reader has the samples.
+    let mut received = 0;
+    while let Ok(Some(_sample)) = data_reader.take_next_sample() {
+      received += 1;
+    }
+    assert_eq!(received, 5);
+  }
+
+  #[test]
+  fn reader_reliability_state_tracks_acks() {
+    use crate::dds::qos::{
+      policy::{History, Reliability},
+      QosPolicyBuilder,
+    };
+
+    let domain_id = 47;
+    let dp1 = DomainParticipant::new(domain_id).expect("Failed to create participant 1");
+    let dp2 = DomainParticipant::new(domain_id).expect("Failed to create participant 2");
+    let qos = QosPolicyBuilder::new()
+      .reliability(Reliability::Reliable {
+        max_blocking_time: crate::Duration::from_secs(1),
+      })
+      .history(History::KeepAll)
+      .build();
+    let topic1 = dp1
+      .create_topic(
+        "ReliabilityStateTopic".to_string(),
+        "RandomData".to_string(),
+        &qos,
+        TopicKind::WithKey,
+      )
+      .unwrap();
+    let topic2 = dp2
+      .create_topic(
+        "ReliabilityStateTopic".to_string(),
+        "RandomData".to_string(),
+        &qos,
+        TopicKind::WithKey,
+      )
+      .unwrap();
+    let data_writer = dp1
+      .create_publisher(&qos)
+      .unwrap()
+      .create_datawriter_cdr::<RandomData>(&topic1, None)
+      .unwrap();
+    let data_reader = dp2
+      .create_subscriber(&qos)
+      .unwrap()
+      .create_datareader_cdr::<RandomData>(&topic2, None)
+      .unwrap();
+
+    // Wait for matching.
+    let mut initial = None;
+    wait_until(Duration::from_secs(20), || {
+      initial = data_writer.reader_reliability_state(data_reader.guid());
+      initial.is_some()
+    });
+    let initial = initial.expect("Reader was not matched");
+    assert_eq!(initial.highest_acked, SequenceNumber::zero());
+    assert!(data_writer
+      .reader_reliability_state(GUID::dummy_test_guid(
+        EntityKind::READER_WITH_KEY_USER_DEFINED
+      ))
+      .is_none());
+
+    for a in 1..=3 {
+      data_writer
+        .write(
+          RandomData {
+            a,
+            b: "state".to_string(),
+          },
+          None,
+        )
+        .unwrap();
+    }
+    assert!(data_writer
+      .wait_for_acknowledgments(Duration::from_secs(20))
+      .unwrap());
+    let state = data_writer
+      .reader_reliability_state(data_reader.guid())
+      .unwrap();
+    assert_eq!(state.highest_acked, SequenceNumber::from(3));
+    assert!(state.requested_changes.is_empty());
+    assert!(state.last_acknack_received.is_some());
+
+    data_writer
+      .write(
+        RandomData {
+          a: 4,
+          b: "state".to_string(),
+        },
+        None,
+      )
+      .unwrap();
+    assert!(data_writer
+      .wait_for_acknowledgments(Duration::from_secs(20))
+      .unwrap());
+    let state = data_writer
+      .reader_reliability_state(data_reader.guid())
+      .unwrap();
+    assert_eq!(state.highest_acked, SequenceNumber::from(4));
+  }
 }
@@ -1,6 +1,6 @@
 use std::{
   cmp::max,
-  collections::BTreeMap,
+  collections::{BTreeMap, BTreeSet},
   io,
   marker::PhantomData,
   pin::Pin,
@@ -21,7 +21,7 @@ use crate::{
     adapters::with_key::*,
     ddsdata::*,
     key::*,
-    pubsub::Subscriber,
+    pubsub::{DisabledReader, Subscriber},
     qos::*,
     result::*,
     statusevents::*,
@@ -30,10 +30,11 @@ use crate::{
   },
   discovery::discovery::DiscoveryCommand,
   mio_source::PollEventSource,
-  serialization::CDRDeserializerAdapter,
+  serialization::{representation_identifier::RepresentationIdentifier, CDRDeserializerAdapter},
   structure::{
-    cache_change::CacheChange,
+    cache_change::{CacheChange, ChangeKind},
     dds_cache::TopicCache,
+    duration::Duration,
     entity::RTPSEntity,
     guid::{EntityId, GUID},
     sequence_number::SequenceNumber,
@@ -53,10 +54,17 @@ pub(crate) struct ReadState<K: Key> {
   latest_instant: Timestamp, /* This is used as a read pointer from dds_cache for BEST_EFFORT
                               * reading */
   last_read_sn: BTreeMap<GUID, SequenceNumber>, // collection of read pointers for RELIABLE reading
+  // Sequence number resets of each Writer, as last seen in the topic cache
+  sequence_number_resets: BTreeMap<GUID, u32>,
   /// hash_to_key_map is used for decoding received key hashes back to original
   /// key values. This is needed when we receive a dispose message via hash
   /// only.
   hash_to_key_map: BTreeMap<KeyHash, K>, // TODO: garbage collect this somehow
+  // Reception time of the latest sample delivered from each instance, for
+  // TIME_BASED_FILTER QoS
+  latest_delivery: BTreeMap<K, Timestamp>,
+  // Current owner Writer of each instance, for EXCLUSIVE ownership QoS
+  instance_owners: BTreeMap<K, GUID>,
 }
 
 impl<K: Key> ReadState<K> {
@@ -64,7 +72,10 @@ impl<K: Key> ReadState<K> {
     ReadState {
       latest_instant: Timestamp::ZERO,
       last_read_sn: BTreeMap::new(),
+      sequence_number_resets: BTreeMap::new(),
       hash_to_key_map: BTreeMap::<KeyHash, K>::new(),
+      latest_delivery: BTreeMap::new(),
+      instance_owners: BTreeMap::new(),
     }
   }
 
@@ -84,6 +95,152 @@ impl<K: Key> ReadState<K> {
     } = self;
     (last_read_sn, hash_to_key_map)
   }
+
+  // If a Writer has reset its sequence numbers, our read pointer for it is
+  // too far ahead, and must be forgotten.
+  fn follow_sequence_number_resets(&mut self, topic_cache: &TopicCache) {
+    for (writer, resets) in topic_cache.sequence_number_resets() {
+      if self.sequence_number_resets.get(writer) != Some(resets) {
+        self.last_read_sn.remove(writer);
+        self.sequence_number_resets.insert(*writer, *resets);
+      }
+    }
+  }
+
+  // EXCLUSIVE ownership QoS: Only samples from the owner of an instance are
+  // delivered. The owner is the Writer with the highest strength, or the
+  // lowest GUID among equally strong ones. A Writer that announces a higher
+  // strength, or a new Writer, takes over with its next sample. So does any
+  // Writer, if the owner is no longer matched. Disposing the instance releases
+  // ownership.
+  fn passes_ownership_arbitration<D: Keyed<K = K>>(
+    &mut self,
+    topic_cache: &TopicCache,
+    writer: GUID,
+    sample: &Sample<D, K>,
+  ) -> bool {
+    let key = match sample {
+      Sample::Value(d) => d.key(),
+      Sample::Dispose(key) => key.clone(),
+    };
+    let is_owner = match self.instance_owners.get(&key) {
+      None => true,
+      Some(owner) if *owner == writer => true,
+      Some(owner) => match (
+        topic_cache.writer_ownership_strength(writer),
+        topic_cache.writer_ownership_strength(*owner),
+      ) {
+        (_, None) => true, // owner is gone
+        (None, Some(_)) => false,
+        (Some(strength), Some(owner_strength)) => {
+          strength > owner_strength || (strength == owner_strength && writer < *owner)
+        }
+      },
+    };
+    if is_owner {
+      match sample {
+        Sample::Value(_) => self.instance_owners.insert(key, writer),
+        Sample::Dispose(_) => self.instance_owners.remove(&key),
+      };
+    }
+    is_owner
+  }
+
+  // TIME_BASED_FILTER QoS: A sample is not delivered if it was received less
+  // than minimum_separation after the latest delivered sample of the same
+  // instance. Disposes are always delivered, as they change the instance
+  // state.
+  fn passes_time_based_filter<D: Keyed<K = K>>(
+    &mut self,
+    minimum_separation: Duration,
+    receive_timestamp: Timestamp,
+    sample: &Sample<D, K>,
+  ) -> bool {
+    match sample {
+      Sample::Value(d) => {
+        let key = d.key();
+        match self.latest_delivery.get(&key) {
+          Some(latest) if receive_timestamp - *latest < minimum_separation => false,
+          _ => {
+            self.latest_delivery.insert(key, receive_timestamp);
+            true
+          }
+        }
+      }
+      Sample::Dispose(key) => {
+        self.latest_delivery.remove(key);
+        true
+      }
+    }
+  }
+}
+
+/// A serialized sample, as received, borrowed from the topic cache.
+///
+/// This is returned by `read_raw()` of DataReaders. It keeps the topic cache
+/// locked, so that the payload does not need to be copied out. Incoming
+/// samples on the same topic cannot be stored while this exists, so drop it
+/// as soon as the payload has been processed.
+pub struct RawSample<'a> {
+  topic_cache: MutexGuard<'a, TopicCache>,
+  timestamp: Timestamp,
+}
+
+impl<'a> RawSample<'a> {
+  fn cache_change(&self) -> &CacheChange {
+    // The topic cache is locked, so the change cannot have been removed.
+    self
+      .topic_cache
+      .get_change(&self.timestamp)
+      .expect("RawSample: cache change disappeared from a locked topic cache")
+  }
+
+  pub fn writer_guid(&self) -> GUID {
+    self.cache_change().writer_guid
+  }
+
+  pub fn sequence_number(&self) -> SequenceNumber {
+    self.cache_change().sequence_number
+  }
+
+  pub fn source_timestamp(&self) -> Option<Timestamp> {
+    self.cache_change().write_options.source_timestamp()
+  }
+
+  /// Time when the sample was received.
+  pub fn reception_timestamp(&self) -> Timestamp {
+    self.timestamp
+  }
+
+  /// `true` for data samples. Otherwise this is a dispose or unregister, and
+  /// the payload, if any, is the serialized key.
+  pub fn is_data(&self) -> bool {
+    self.cache_change().data_value.change_kind() == ChangeKind::Alive
+  }
+
+  /// Encoding of the payload. `None` if there is no payload, i.e. the sample
+  /// is a dispose or unregister that carries only a key hash.
+  pub fn representation_identifier(&self) -> Option<RepresentationIdentifier> {
+    match self.cache_change().data_value {
+      DDSData::Data {
+        ref serialized_payload,
+      } => Some(serialized_payload.representation_identifier),
+      DDSData::DisposeByKey { ref key, .. } => Some(key.representation_identifier),
+      DDSData::DisposeByKeyHash { .. } => None,
+    }
+  }
+
+  /// Serialized payload, without the encapsulation header. Decode it
+  /// according to [`representation_identifier`](Self::representation_identifier).
+  pub fn payload(&self) -> Option<&[u8]> {
+    match self.cache_change().data_value {
+      DDSData::Data {
+        ref serialized_payload,
+      } => Some(&serialized_payload.value),
+      DDSData::DisposeByKey { ref key, .. } => Some(&key.value),
+      DDSData::DisposeByKeyHash { .. } => None,
+    }
+  }
 }
 
 /// SimpleDataReaders can only do "take" semantics and does not have
@@ -103,6 +260,10 @@ pub struct SimpleDataReader<D: Keyed, DA: DeserializerAdapter<D> = CDRDeserializ
   topic_cache: Arc<Mutex<TopicCache>>,
 
   read_state: Mutex<ReadState<<D as Keyed>::K>>,
+  // From TIME_BASED_FILTER QoS, if it is in effect
+  minimum_separation: Option<Duration>,
+  // Ownership QoS is EXCLUSIVE
+  exclusive_ownership: bool,
 
   deserializer_type: PhantomData<DA>, // This is to provide use for DA
 
@@ -115,6 +276,8 @@ pub struct SimpleDataReader<D: Keyed, DA: DeserializerAdapter<D> = CDRDeserializ
   data_reader_waker: Arc<Mutex<Option<Waker>>>,
 
   event_source: PollEventSource,
+  // Some, until a DataReader created disabled is enabled
+  disabled_reader: Mutex<Option<DisabledReader>>,
 }
 
 impl<D, DA> Drop for SimpleDataReader<D, DA>
@@ -123,10 +286,16 @@ where
   DA: DeserializerAdapter<D>,
 {
   fn drop(&mut self) {
-    // Tell dp_event_loop
-    self.my_subscriber.remove_reader(self.my_guid);
+    // Tell dp_event_loop, unless DomainParticipant::delete_contained_entities
+    // did that already.
+    if !self.my_subscriber.remove_reader(self.my_guid) {
+      return;
+    }
 
-    // Tell discovery
+    // Tell discovery, unless it never heard of us
+    if !self.disabled_reader.lock().map_or(true, |d| d.is_none()) {
+      return;
+    }
     match self
       .discovery_command
       .send(DiscoveryCommand::RemoveLocalReader { guid: self.my_guid })
@@ -186,6 +355,15 @@ where
       });
     }
 
+    let minimum_separation = qos_policy
+      .time_based_filter()
+      .map(|tbf| tbf.minimum_separation)
+      .filter(|sep| *sep > Duration::DURATION_ZERO);
+    let exclusive_ownership = matches!(
+      qos_policy.ownership(),
+      Some(policy::Ownership::Exclusive { .. })
+    );
+
     Ok(Self {
       my_subscriber: subscriber,
       qos_policy,
@@ -193,6 +371,8 @@ where
       notification_receiver,
       topic_cache,
       read_state: Mutex::new(ReadState::new()),
+      minimum_separation,
+      exclusive_ownership,
       my_topic: topic,
       deserializer_type: PhantomData,
       discovery_command,
@@ -200,8 +380,31 @@ where
       reader_command,
       data_reader_waker,
       event_source,
+      disabled_reader: Mutex::new(None),
     })
   }
+
+  // Defers registration with Discovery and the event loop until enable()
+  pub(crate) fn set_disabled(&self, disabled_reader: DisabledReader) {
+    *self.disabled_reader.lock().unwrap() = Some(disabled_reader);
+  }
+
+  /// Enables a DataReader that was created disabled, because
+  /// [`EntityFactory`](crate::policy::EntityFactory) QoS of its Subscriber
+  /// has `autoenable_created_entities` set to false. From now on the
+  /// DataReader is announced in Discovery, matches remote DataWriters, and
+  /// receives data. A disabled DataReader has nothing to read.
+  ///
+  /// Enabling an enabled DataReader does nothing.
+  pub fn enable(&self) -> CreateResult<()> {
+    let mut disabled_reader = self.disabled_reader.lock()?;
+    self.my_subscriber.enable_reader(&mut disabled_reader)
+  }
+
+  /// Tells if this DataReader is enabled. See [`enable`](Self::enable).
+  pub fn is_enabled(&self) -> bool {
+    self.disabled_reader.lock().unwrap().is_none()
+  }
   pub fn set_waker(&self, w: Option<Waker>) {
     *self.data_reader_waker.lock().unwrap() = w;
   }
@@ -211,6 +414,11 @@ where
     self.event_source.drain();
   }
 
+  // Matched Writers that have been lost or are not alive
+  pub(crate) fn lost_writers(&self) -> BTreeSet<GUID> {
+    self.acquire_the_topic_cache_guard().lost_writers()
+  }
+
   fn try_take_undecoded<'a>(
     is_reliable: bool,
     topic_cache: &'a TopicCache,
@@ -318,36 +526,122 @@ where
     let topic_cache = self.acquire_the_topic_cache_guard();
 
     let mut read_state_ref = self.read_state.lock().unwrap();
-    let latest_instant = read_state_ref.latest_instant;
-    let (last_read_sn, hash_to_key_map) = read_state_ref.get_sn_map_and_hash_map();
-    let (timestamp, cc) = match Self::try_take_undecoded(
-      is_reliable,
-      &topic_cache,
-      latest_instant,
-      last_read_sn,
-    )
-    .next()
-    {
-      None => return Ok(None),
-      Some((ts, cc)) => (ts, cc),
-    };
-
-    match Self::deserialize(timestamp, cc, hash_to_key_map) {
-      Ok(dcc) => {
+    read_state_ref.follow_sequence_number_resets(&topic_cache);
+    let now = Timestamp::now();
+    loop {
+      let latest_instant = read_state_ref.latest_instant;
+      let (last_read_sn, hash_to_key_map) = read_state_ref.get_sn_map_and_hash_map();
+      let (timestamp, cc) =
+        match Self::try_take_undecoded(is_reliable, &topic_cache, latest_instant, last_read_sn)
+          .next()
+        {
+          None => return Ok(None),
+          Some((ts, cc)) => (ts, cc),
+        };
+
+      // Samples past their LIFESPAN are skipped over without decoding. So is a
+      // dispose by key hash of an instance that this reader has never seen, as
+      // there is nothing to tell the application about it.
+      let expired = cc.write_options.expiration().is_some_and(|e| e < now);
+      let unknown_instance = matches!(cc.data_value,
+        DDSData::DisposeByKeyHash { key_hash, .. } if !hash_to_key_map.contains_key(&key_hash));
+      if unknown_instance {
+        debug!(
+          "Ignoring dispose of unknown instance. Topic = {} writer = {:?}",
+          self.my_topic.name(),
+          cc.writer_guid
+        );
+      }
+      if expired || unknown_instance {
+        let (writer_guid, sequence_number) = (cc.writer_guid, cc.sequence_number);
         read_state_ref.latest_instant = max(read_state_ref.latest_instant, timestamp);
         read_state_ref
           .last_read_sn
-          .insert(dcc.writer_guid, dcc.sequence_number);
-        Ok(Some(dcc))
+          .insert(writer_guid, sequence_number);
+        continue;
+      }
+
+      match Self::deserialize(timestamp, cc, hash_to_key_map) {
+        Ok(dcc) => {
+          read_state_ref.latest_instant = max(read_state_ref.latest_instant, timestamp);
+          read_state_ref
+            .last_read_sn
+            .insert(dcc.writer_guid, dcc.sequence_number);
+          // A filtered-out sample has been received all right, so it is just
+          // skipped over.
+          let filtered_out = (self.exclusive_ownership
+            && !read_state_ref.passes_ownership_arbitration(
+              &topic_cache,
+              dcc.writer_guid,
+              &dcc.sample,
+            ))
+            || self.minimum_separation.is_some_and(|sep| {
+              !read_state_ref.passes_time_based_filter(sep, timestamp, &dcc.sample)
+            });
+          if !filtered_out {
+            return Ok(Some(dcc));
+          }
+        }
+        Err(ser_err) => {
+          return Err(ReadError::Deserialization {
+            reason: format!(
+              "{}, Topic = {}, Type = {:?}",
+              ser_err,
+              self.my_topic.name(),
+              self.my_topic.get_type()
+            ),
+          })
+        }
+      }
+    }
+  }
+
+  /// Like `try_take_one()`, but does not deserialize the sample. The sample
+  /// is borrowed from the topic cache instead. Expired samples are skipped,
+  /// but TIME_BASED_FILTER QoS is not applied, because the instance of the
+  /// sample is not known.
+  ///
+  /// Note: Always remember to call .drain_read_notifications() just before
+  /// calling this one. Otherwise, new notifications may not appear.
+  pub fn try_take_one_raw(&self) -> Option<RawSample<'_>> {
+    let is_reliable = matches!(
+      self.qos_policy.reliability(),
+      Some(policy::Reliability::Reliable { .. })
+    );
+
+    let topic_cache = self.acquire_the_topic_cache_guard();
+
+    let mut read_state_ref = self.read_state.lock().unwrap();
+    read_state_ref.follow_sequence_number_resets(&topic_cache);
+    let now = Timestamp::now();
+    loop {
+      let (timestamp, writer_guid, sequence_number, expired) = Self::try_take_undecoded(
+        is_reliable,
+        &topic_cache,
+        read_state_ref.latest_instant,
+        &read_state_ref.last_read_sn,
+      )
+      .next()
+      .map(|(ts, cc)| {
+        (
+          ts,
+          cc.writer_guid,
+          cc.sequence_number,
+          cc.write_options.expiration().is_some_and(|e| e < now),
+        )
+      })?;
+
+      read_state_ref.latest_instant = max(read_state_ref.latest_instant, timestamp);
+      read_state_ref
+        .last_read_sn
+        .insert(writer_guid, sequence_number);
+
+      if !expired {
+        return Some(RawSample {
+          topic_cache,
+          timestamp,
+        });
       }
-      Err(ser_err) => Err(ReadError::Deserialization {
-        reason: format!(
-          "{}, Topic = {}, Type = {:?}",
-          ser_err,
-          self.my_topic.name(),
-          self.my_topic.get_type()
-        ),
-      }),
     }
   }
 
@@ -363,6 +657,10 @@ where
     &self.my_topic
   }
 
+  pub(crate) fn subscriber(&self) -> &Subscriber {
+    &self.my_subscriber
+  }
+
   pub fn as_async_stream(&self) -> SimpleDataReaderStream<D, DA> {
     SimpleDataReaderStream {
       simple_datareader: self,
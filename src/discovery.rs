@@ -2,7 +2,10 @@ pub(crate) mod builtin_endpoint;
 pub(crate) mod content_filter_property;
 #[allow(clippy::module_inception)]
 pub(crate) mod discovery;
+pub(crate) mod discovery_backend;
 pub(crate) mod discovery_db;
+pub(crate) mod discovery_events;
+pub(crate) mod participant_scan;
 
 #[cfg(feature = "security")]
 pub(crate) mod secure_discovery;
@@ -10,5 +13,10 @@ pub(crate) mod secure_discovery;
 pub(crate) mod sedp_messages;
 pub(crate) mod spdp_participant_data;
 
+pub use discovery_backend::{DiscoveryBackend, NoExtraPeers};
+pub use discovery_events::{DiscoveryEvent, DiscoveryEventReceiver};
+pub use participant_scan::ScannedParticipant;
+// Type of DiscoveredParticipantInfo::vendor_id
+pub use crate::messages::vendor_id::VendorId;
 pub use sedp_messages::*;
 pub use spdp_participant_data::*;
@@ -1,5 +1,5 @@
 use std::{
-  collections::HashMap,
+  collections::{BTreeSet, HashMap},
   sync::{Arc, RwLock},
   time::{Duration as StdDuration, Instant},
 };
@@ -12,7 +12,7 @@ use paste::paste; // token pasting macro
 
 use crate::{
   dds::{
-    participant::DomainParticipantWeak,
+    participant::{DomainParticipant, DomainParticipantWeak},
     qos::{
       policy::{
         Deadline, DestinationOrder, Durability, History, Liveliness, Ownership, Presentation,
@@ -24,6 +24,7 @@ use crate::{
     result::{CreateError, CreateResult},
   },
   discovery::{
+    discovery_backend::DiscoveryBackend,
     discovery_db::{discovery_db_read, discovery_db_write, DiscoveredVia, DiscoveryDB},
     sedp_messages::{
       DiscoveredReaderData, DiscoveredTopicData, DiscoveredWriterData, Endpoint_GUID,
@@ -54,7 +55,7 @@ use crate::{
 #[cfg(not(feature = "security"))]
 use crate::no_security::*;
 
-#[derive(Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Hash)]
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
 pub enum DiscoveryCommand {
   StopDiscovery,
   RemoveLocalWriter {
@@ -64,15 +65,31 @@ pub enum DiscoveryCommand {
     guid: GUID,
   },
   ManualAssertLiveliness,
+  // The DomainParticipant has opened new listeners
+  SelfLocatorsChanged,
   AssertTopicLiveliness {
     writer_guid: GUID,
     manual_assertion: bool,
   },
+  // DomainParticipant::ignore_participant() and ignore_topic()
+  IgnoreParticipant {
+    guid_prefix: GuidPrefix,
+  },
+  IgnoreTopic {
+    topic_name: String,
+  },
+  // DomainParticipant::refresh_endpoints()
+  RefreshEndpoints,
 
   #[cfg(feature = "security")]
   StartKeyExchangeWithRemoteParticipant {
     participant_guid_prefix: GuidPrefix,
   },
+  // DomainParticipant::abort_handshake()
+  #[cfg(feature = "security")]
+  AbortHandshake {
+    guid_prefix: GuidPrefix,
+  },
 }
 
 pub struct LivelinessState {
@@ -168,6 +185,24 @@ pub(crate) struct Discovery {
   // TODO: Why is this a HashMap? Are there ever more than 2?
   self_locators: HashMap<Token, Vec<Locator>>,
 
+  // In passive mode we only listen. Nothing is announced, and the builtin
+  // endpoints of remote participants are not matched, so that we do not send
+  // anything to them.
+  passive: bool,
+
+  // How often our SPDP participant data is announced
+  spdp_announcement_period: StdDuration,
+
+  // Remote participants and topics that the application has asked us to
+  // ignore. Nothing discovered from or about them is matched.
+  ignored_participants: BTreeSet<GuidPrefix>,
+  ignored_topics: BTreeSet<String>,
+
+  // Additional source of remote participants besides SPDP, and the timer
+  // to poll it.
+  discovery_backend: Box<dyn DiscoveryBackend>,
+  discovery_backend_timer: Timer<()>,
+
   // DDS Subscriber and Publisher for Discovery
   // ...but these are not actually used after initialization
   // discovery_subscriber: Subscriber,
@@ -240,7 +275,9 @@ pub(crate) struct Discovery {
 impl Discovery {
   const PARTICIPANT_CLEANUP_PERIOD: StdDuration = StdDuration::from_secs(2);
   const TOPIC_CLEANUP_PERIOD: StdDuration = StdDuration::from_secs(60); // timer for cleaning up inactive topics
-  const SEND_PARTICIPANT_INFO_PERIOD: StdDuration = StdDuration::from_secs(2);
+  // Default SPDP announcement period, and the minimum that can be configured
+  pub(crate) const SEND_PARTICIPANT_INFO_PERIOD: StdDuration = StdDuration::from_secs(2);
+  pub(crate) const MIN_SEND_PARTICIPANT_INFO_PERIOD: StdDuration = StdDuration::from_millis(100);
   const SEND_READERS_INFO_PERIOD: StdDuration = StdDuration::from_secs(2);
   const SEND_WRITERS_INFO_PERIOD: StdDuration = StdDuration::from_secs(2);
   const SEND_TOPIC_INFO_PERIOD: StdDuration = StdDuration::from_secs(10);
@@ -263,7 +300,10 @@ impl Discovery {
     history: Some(History::KeepLast { depth: 1 }),
     resource_limits: None,
     lifespan: None,
-    #[cfg(feature = "security")]
+    transport_priority: None,
+    writer_data_lifecycle: None,
+    reader_data_lifecycle: None,
+    entity_factory: None,
     property: None,
   };
 
@@ -293,6 +333,9 @@ impl Discovery {
     spdp_liveness_receiver: mio_channel::Receiver<GuidPrefix>,
     self_locators: HashMap<Token, Vec<Locator>>,
     security_plugins_opt: Option<SecurityPluginsHandle>,
+    passive: bool,
+    spdp_announcement_period: StdDuration,
+    discovery_backend: Box<dyn DiscoveryBackend>,
   ) -> CreateResult<Self> {
     // helper macro to handle initialization failures.
     macro_rules! try_construct {
@@ -414,7 +457,7 @@ impl Discovery {
       EntityId::SPDP_BUILTIN_PARTICIPANT_READER,
       DISCOVERY_PARTICIPANT_DATA_TOKEN,
       EntityId::SPDP_BUILTIN_PARTICIPANT_WRITER,
-      Self::SEND_PARTICIPANT_INFO_PERIOD,
+      spdp_announcement_period,
       DISCOVERY_SEND_PARTICIPANT_INFO_TOKEN,
     );
 
@@ -431,6 +474,20 @@ impl Discovery {
       "Unable to create participant cleanup timer. {:?}"
     );
 
+    // Poll the discovery backend right away, so that seeded peers are
+    // matched as soon as possible.
+    let mut discovery_backend_timer: Timer<()> = Timer::default();
+    discovery_backend_timer.set_timeout(StdDuration::ZERO, ());
+    try_construct!(
+      poll.register(
+        &discovery_backend_timer,
+        DISCOVERY_BACKEND_POLL_TOKEN,
+        Ready::readable(),
+        PollOpt::edge(),
+      ),
+      "Unable to create discovery backend timer. {:?}"
+    );
+
     // Subscriptions: What are the Readers on the network and what are they
     // subscribing to?
     let dcps_subscription = construct_topic_and_poll!(
@@ -638,6 +695,12 @@ impl Discovery {
       discovery_command_receiver,
       spdp_liveness_receiver,
       self_locators,
+      passive,
+      spdp_announcement_period,
+      ignored_participants: BTreeSet::new(),
+      ignored_topics: BTreeSet::new(),
+      discovery_backend,
+      discovery_backend_timer,
 
       liveliness_state: LivelinessState::new(),
 
@@ -700,21 +763,36 @@ impl Discovery {
               match command {
                 DiscoveryCommand::StopDiscovery => {
                   info!("Stopping Discovery");
-                  // disposing readers
+                  if self.passive {
+                    // We never announced anything, so there is nothing to dispose.
+                    info!("Stopped Discovery");
+                    return;
+                  }
+                  // disposing readers and writers. Only the user-defined ones were
+                  // announced. Disposing a builtin one would make the remotes drop
+                  // their matches with it, and then miss the participant disposal.
                   let db = discovery_db_read(&self.discovery_db);
-                  for reader in db.get_all_local_topic_readers() {
+                  let local_user_readers = db
+                    .get_all_local_topic_readers()
+                    .map(|reader| reader.reader_proxy.remote_reader_guid)
+                    .filter(|guid| guid.entity_id.kind().is_user_defined());
+                  for reader_guid in local_user_readers {
                     self
                       .dcps_subscription
                       .writer
-                      .dispose(&Endpoint_GUID(reader.reader_proxy.remote_reader_guid), None)
+                      .dispose(&Endpoint_GUID(reader_guid), None)
                       .unwrap_or(());
                   }
 
-                  for writer in db.get_all_local_topic_writers() {
+                  let local_user_writers = db
+                    .get_all_local_topic_writers()
+                    .map(|writer| writer.writer_proxy.remote_writer_guid)
+                    .filter(|guid| guid.entity_id.kind().is_user_defined());
+                  for writer_guid in local_user_writers {
                     self
                       .dcps_publication
                       .writer
-                      .dispose(&Endpoint_GUID(writer.writer_proxy.remote_writer_guid), None)
+                      .dispose(&Endpoint_GUID(writer_guid), None)
                       .unwrap_or(());
                   }
                   // finally disposing the participant we have
@@ -723,6 +801,16 @@ impl Discovery {
                     .writer
                     .dispose(&Participant_GUID(self.domain_participant.guid()), None)
                     .unwrap_or(());
+                  // Authenticated participants ignore disposals on the normal
+                  // topic, so also dispose on the secure one.
+                  #[cfg(feature = "security")]
+                  if self.security_opt.is_some() {
+                    self
+                      .dcps_participant_secure
+                      .writer
+                      .dispose(&Participant_GUID(self.domain_participant.guid()), None)
+                      .unwrap_or(());
+                  }
                   info!("Stopped Discovery");
                   return; // terminate event loop
                 }
@@ -730,11 +818,13 @@ impl Discovery {
                   if guid == self.dcps_publication.writer.guid() {
                     continue;
                   }
-                  self
-                    .dcps_publication
-                    .writer
-                    .dispose(&Endpoint_GUID(guid), None)
-                    .unwrap_or_else(|e| error!("Disposing local Writer: {e:?}"));
+                  if !self.passive {
+                    self
+                      .dcps_publication
+                      .writer
+                      .dispose(&Endpoint_GUID(guid), None)
+                      .unwrap_or_else(|e| error!("Disposing local Writer: {e:?}"));
+                  }
 
                   discovery_db_write(&self.discovery_db).remove_local_topic_writer(guid);
                 }
@@ -743,17 +833,22 @@ impl Discovery {
                     continue;
                   }
 
-                  self
-                    .dcps_subscription
-                    .writer
-                    .dispose(&Endpoint_GUID(guid), None)
-                    .unwrap_or_else(|e| error!("Disposing local Reader: {e:?}"));
+                  if !self.passive {
+                    self
+                      .dcps_subscription
+                      .writer
+                      .dispose(&Endpoint_GUID(guid), None)
+                      .unwrap_or_else(|e| error!("Disposing local Reader: {e:?}"));
+                  }
 
                   discovery_db_write(&self.discovery_db).remove_local_topic_reader(guid);
                 }
                 DiscoveryCommand::ManualAssertLiveliness => {
                   self.liveliness_state.last_manual_participant_update = Timestamp::now();
                 }
+                DiscoveryCommand::SelfLocatorsChanged => {
+                  self.self_locators_changed();
+                }
                 DiscoveryCommand::AssertTopicLiveliness {
                   writer_guid,
                   manual_assertion,
@@ -765,6 +860,17 @@ impl Discovery {
                     },
                   );
                 }
+                DiscoveryCommand::IgnoreParticipant { guid_prefix } => {
+                  self.ignore_participant(guid_prefix);
+                }
+                DiscoveryCommand::IgnoreTopic { topic_name } => {
+                  self.ignore_topic(topic_name);
+                }
+                DiscoveryCommand::RefreshEndpoints => {
+                  self.write_topic_info();
+                  self.write_writers_info();
+                  self.write_readers_info();
+                }
                 #[cfg(feature = "security")]
                 DiscoveryCommand::StartKeyExchangeWithRemoteParticipant {
                   participant_guid_prefix,
@@ -777,6 +883,16 @@ impl Discovery {
                     );
                   }
                 }
+                #[cfg(feature = "security")]
+                DiscoveryCommand::AbortHandshake { guid_prefix } => {
+                  if let Some(security) = self.security_opt.as_mut() {
+                    security.abort_handshake(
+                      guid_prefix,
+                      &self.discovery_db,
+                      &self.discovery_updated_sender,
+                    );
+                  }
+                }
               };
             }
           }
@@ -794,6 +910,17 @@ impl Discovery {
               .set_timeout(Self::PARTICIPANT_CLEANUP_PERIOD, ());
           }
 
+          DISCOVERY_BACKEND_POLL_TOKEN => {
+            self.poll_discovery_backend();
+            self
+              .discovery_backend_timer
+              .set_timeout(self.discovery_backend.poll_period(), ());
+          }
+
+          DISCOVERY_SEND_PARTICIPANT_INFO_TOKEN if self.passive => {
+            // Passive participants do not announce themselves. The timer is not
+            // rescheduled.
+          }
           DISCOVERY_SEND_PARTICIPANT_INFO_TOKEN => {
             let strong_dp = if let Some(dp) = self.domain_participant.clone().upgrade() {
               dp
@@ -802,27 +929,12 @@ impl Discovery {
               return;
             };
 
-            // setting 5 times the duration so lease doesn't break if update fails once or
-            // twice
-            let data = SpdpDiscoveredParticipantData::from_local_participant(
-              &strong_dp,
-              &self.self_locators,
-              &self.security_opt,
-              5.0 * Duration::from(Self::SEND_PARTICIPANT_INFO_PERIOD),
-            );
-
-            self
-              .dcps_participant
-              .writer
-              .write(data, None)
-              .unwrap_or_else(|e| {
-                error!("Discovery: Publishing to DCPS participant topic failed: {e:?}");
-              });
+            self.write_participant_info(&strong_dp);
             // reschedule timer
             self
               .dcps_participant
               .timer
-              .set_timeout(Self::SEND_PARTICIPANT_INFO_PERIOD, ());
+              .set_timeout(self.spdp_announcement_period, ());
           }
           DISCOVERY_READER_DATA_TOKEN => {
             self.handle_subscription_reader(None);
@@ -890,11 +1002,18 @@ impl Discovery {
             #[cfg(feature = "security")]
             self.handle_volatile_message_secure_reader();
           }
+          P2P_BUILTIN_PARTICIPANT_VOLATILE_TIMER_TOKEN => {
+            #[cfg(feature = "security")]
+            self.on_volatile_message_resend_triggered();
+          }
+          SECURE_DISCOVERY_PARTICIPANT_DATA_TOKEN => {
+            #[cfg(feature = "security")]
+            self.handle_secure_participant_reader();
+          }
           SECURE_DISCOVERY_SEND_PARTICIPANT_INFO_TOKEN
           | SECURE_DISCOVERY_SEND_READERS_INFO_TOKEN
           | SECURE_DISCOVERY_SEND_WRITERS_INFO_TOKEN
-          | P2P_SECURE_DISCOVERY_PARTICIPANT_MESSAGE_TIMER_TOKEN
-          | P2P_BUILTIN_PARTICIPANT_VOLATILE_TIMER_TOKEN => {
+          | P2P_SECURE_DISCOVERY_PARTICIPANT_MESSAGE_TIMER_TOKEN => {
             debug!("Handler not implemented for {:?}", event.token());
           }
 
@@ -912,6 +1031,50 @@ impl Discovery {
   // built-in and user-defined.
   // If we did not do this, the Readers and Writers in this participant could not
   // find each other.
+  fn write_participant_info(&self, dp: &DomainParticipant) {
+    // setting 5 times the duration so lease doesn't break if update fails once or
+    // twice
+    let data = SpdpDiscoveredParticipantData::from_local_participant(
+      dp,
+      &self.self_locators,
+      &self.security_opt,
+      5.0 * Duration::from(self.spdp_announcement_period),
+    );
+
+    self
+      .dcps_participant
+      .writer
+      .write(data, None)
+      .unwrap_or_else(|e| {
+        error!("Discovery: Publishing to DCPS participant topic failed: {e:?}");
+      });
+  }
+
+  // The DomainParticipant has opened new listeners, e.g. user traffic
+  // listeners on demand. Update our own participant data, and announce it
+  // right away, so that remote participants learn the new locators.
+  fn self_locators_changed(&mut self) {
+    let dp = if let Some(dp) = self.domain_participant.clone().upgrade() {
+      dp
+    } else {
+      error!("DomainParticipant doesn't exist anymore in self_locators_changed.");
+      return;
+    };
+
+    self.self_locators = dp.self_locators();
+    let participant_data = SpdpDiscoveredParticipantData::from_local_participant(
+      &dp,
+      &self.self_locators,
+      &self.security_opt,
+      Duration::DURATION_INFINITE,
+    );
+    discovery_db_write(&self.discovery_db).update_participant(&participant_data);
+
+    if !self.passive {
+      self.write_participant_info(&dp);
+    }
+  }
+
   fn initialize_participant(&self) {
     let dp = if let Some(dp) = self.domain_participant.clone().upgrade() {
       dp
@@ -937,6 +1100,11 @@ impl Discovery {
       guid_prefix: dp.guid().prefix,
     });
 
+    if self.passive {
+      // No announcements, so the SPDP writer needs no destination.
+      return;
+    }
+
     // insert a (fake) reader proxy as multicast address, so discovery notifications
     // are sent somewhere
     let reader_guid = GUID::new(
@@ -1023,6 +1191,13 @@ impl Discovery {
       let s = self.dcps_participant.reader.take_next_sample();
       debug!("handle_participant_reader read {:?}", &s);
       match s {
+        Ok(Some(ds))
+          if self
+            .ignored_participants
+            .contains(&ds.sample_info().writer_guid().prefix) =>
+        {
+          trace!("handle_participant_reader: ignoring {:?}", ds.sample_info().writer_guid());
+        }
         Ok(Some(ds)) => {
           #[cfg(not(feature = "security"))]
           let unsecure_discvery_permission = true;
@@ -1050,22 +1225,7 @@ impl Discovery {
                   "handle_participant_reader discovered {:?}",
                   &participant_data
                 );
-                let was_new =
-                  discovery_db_write(&self.discovery_db).update_participant(&participant_data);
-                let guid_prefix = participant_data.participant_guid.prefix;
-                self.send_discovery_notification(DiscoveryNotificationType::ParticipantUpdated {
-                  guid_prefix,
-                });
-                if was_new {
-                  // This may be a rediscovery of a previously seen participant that
-                  // was temporarily lost due to network outage. Check if we already know
-                  // what it has (readers, writers, topics).
-                  debug!("Participant rediscovery start");
-                  self.handle_topic_reader(Some(guid_prefix));
-                  self.handle_subscription_reader(Some(guid_prefix));
-                  self.handle_publication_reader(Some(guid_prefix));
-                  debug!("Participant rediscovery finished");
-                }
+                self.participant_discovered(&participant_data);
               }
               // Sample::Dispose means that DomainParticipant was disposed
               Sample::Dispose(participant_guid) => {
@@ -1090,6 +1250,77 @@ impl Discovery {
     } // loop
   }
 
+  // Adds or refreshes a remote participant, whether it came from SPDP or from
+  // the discovery backend.
+  fn participant_discovered(&mut self, participant_data: &SpdpDiscoveredParticipantData) {
+    let was_new = discovery_db_write(&self.discovery_db).update_participant(participant_data);
+    let guid_prefix = participant_data.participant_guid.prefix;
+    if !self.passive {
+      // Matches the builtin endpoints of the remote participant.
+      self.send_discovery_notification(DiscoveryNotificationType::ParticipantUpdated {
+        guid_prefix,
+      });
+    }
+    if was_new {
+      // This may be a rediscovery of a previously seen participant that
+      // was temporarily lost due to network outage. Check if we already know
+      // what it has (readers, writers, topics).
+      debug!("Participant rediscovery start");
+      self.handle_topic_reader(Some(guid_prefix));
+      self.handle_subscription_reader(Some(guid_prefix));
+      self.handle_publication_reader(Some(guid_prefix));
+      debug!("Participant rediscovery finished");
+    }
+  }
+
+  fn poll_discovery_backend(&mut self) {
+    let discovered = self.discovery_backend.poll_participants();
+    if self.security_opt.is_some() && !discovered.is_empty() {
+      // Secure participants must be authenticated from their SPDP announcement.
+      warn!("Discovery backend participants are not supported with security. Ignoring.");
+      return;
+    }
+    for participant_data in discovered {
+      let guid_prefix = participant_data.participant_guid.prefix;
+      if self.ignored_participants.contains(&guid_prefix) {
+        trace!("poll_discovery_backend: ignoring {:?}", guid_prefix);
+        continue;
+      }
+      debug!("Discovery backend reported {:?}", &participant_data);
+      self.participant_discovered(&participant_data);
+    }
+  }
+
+  // The secure participant topic is used only for disposals of authenticated
+  // participants. The data is the same as on the normal participant topic.
+  #[cfg(feature = "security")]
+  fn handle_secure_participant_reader(&mut self) {
+    loop {
+      match self.dcps_participant_secure.reader.take_next_sample() {
+        Ok(Some(ds)) => {
+          let writer_guid_prefix = ds.sample_info().writer_guid().prefix;
+          if let Sample::Dispose(participant_guid) = ds.value {
+            let guid_prefix = participant_guid.0.prefix;
+            if guid_prefix != writer_guid_prefix {
+              warn!("Participant {writer_guid_prefix:?} tried to dispose {guid_prefix:?}. Ignoring.");
+              continue;
+            }
+            info!("Authenticated participant {guid_prefix:?} disposed itself");
+            discovery_db_write(&self.discovery_db).remove_participant(guid_prefix, true); // true = actively removed
+            self.send_discovery_notification(DiscoveryNotificationType::ParticipantLost {
+              guid_prefix,
+            });
+          }
+        }
+        Ok(None) => return, // no more data
+        Err(e) => {
+          error!("handle_secure_participant_reader: {e:?}");
+          return;
+        }
+      }
+    }
+  }
+
   // Check if there are messages about new Readers
   pub fn handle_subscription_reader(&mut self, read_history: Option<GuidPrefix>) {
     let drds: Vec<Sample<DiscoveredReaderData, GUID>> =
@@ -1114,8 +1345,23 @@ impl Discovery {
 
     for d in drds {
       match d {
+        Sample::Value(d)
+          if self.is_ignored(
+            d.reader_proxy.remote_reader_guid.prefix,
+            d.subscription_topic_data.topic_name(),
+          ) =>
+        {
+          trace!("handle_subscription_reader: ignoring {:?}", d.reader_proxy.remote_reader_guid);
+        }
         Sample::Value(d) => {
           let drd = discovery_db_write(&self.discovery_db).update_subscription(&d);
+          #[cfg(feature = "security")]
+          if let Some(security) = self.security_opt.as_mut() {
+            security.check_remote_endpoints_protection(
+              d.reader_proxy.remote_reader_guid.prefix,
+              &self.discovery_db,
+            );
+          }
           debug!(
             "handle_subscription_reader - send_discovery_notification ReaderUpdated  {:?}",
             &drd
@@ -1169,10 +1415,25 @@ impl Discovery {
 
     for d in dwds {
       match d {
+        Sample::Value(dwd)
+          if self.is_ignored(
+            dwd.writer_proxy.remote_writer_guid.prefix,
+            &dwd.publication_topic_data.topic_name,
+          ) =>
+        {
+          trace!("handle_publication_reader: ignoring {:?}", dwd.writer_proxy.remote_writer_guid);
+        }
         Sample::Value(dwd) => {
           trace!("handle_publication_reader discovered {:?}", &dwd);
           let discovered_writer_data =
             discovery_db_write(&self.discovery_db).update_publication(&dwd);
+          #[cfg(feature = "security")]
+          if let Some(security) = self.security_opt.as_mut() {
+            security.check_remote_endpoints_protection(
+              dwd.writer_proxy.remote_writer_guid.prefix,
+              &self.discovery_db,
+            );
+          }
           self.send_discovery_notification(DiscoveryNotificationType::WriterUpdated {
             discovered_writer_data,
           });
@@ -1217,6 +1478,11 @@ impl Discovery {
 
     for t in ts {
       match t {
+        Sample::Value((topic_data, writer))
+          if self.is_ignored(writer.prefix, topic_data.topic_name()) =>
+        {
+          trace!("handle_topic_reader: ignoring {:?} from {:?}", topic_data.topic_name(), writer);
+        }
         Sample::Value((topic_data, writer)) => {
           debug!("handle_topic_reader discovered {:?}", &topic_data);
           discovery_db_write(&self.discovery_db).update_topic_data(
@@ -1287,6 +1553,9 @@ impl Discovery {
 
   // TODO: Explain what happens here and by what logic
   pub fn write_participant_message(&mut self) {
+    if self.passive {
+      return;
+    }
     let writer_liveliness: Vec<Liveliness> = discovery_db_read(&self.discovery_db)
       .get_all_local_topic_writers()
       .filter_map(|p| {
@@ -1444,6 +1713,62 @@ impl Discovery {
     }
   }
 
+  #[cfg(feature = "security")]
+  fn on_volatile_message_resend_triggered(&mut self) {
+    if let Some(security) = self.security_opt.as_mut() {
+      // Security is enabled
+      security.resend_unacknowledged_volatile_messages(
+        &self.dcps_participant_volatile_message_secure.writer,
+      );
+
+      self
+        .dcps_participant_volatile_message_secure
+        .timer
+        .set_timeout(Self::CHECK_PARTICIPANT_MESSAGES, ());
+    }
+  }
+
+  fn is_ignored(&self, guid_prefix: GuidPrefix, topic_name: &str) -> bool {
+    self.ignored_participants.contains(&guid_prefix) || self.ignored_topics.contains(topic_name)
+  }
+
+  // Forget what we know of the participant, which also tears down the matches
+  // with its endpoints. Anything it sends later is ignored.
+  fn ignore_participant(&mut self, guid_prefix: GuidPrefix) {
+    info!("Ignoring participant {guid_prefix:?}");
+    self.ignored_participants.insert(guid_prefix);
+    discovery_db_write(&self.discovery_db).remove_participant(guid_prefix, true);
+    self.send_discovery_notification(DiscoveryNotificationType::ParticipantLost { guid_prefix });
+  }
+
+  // Remote endpoints on the topic are unmatched and not matched again.
+  fn ignore_topic(&mut self, topic_name: String) {
+    info!("Ignoring topic {topic_name:?}");
+    let (readers, writers): (Vec<GUID>, Vec<GUID>) = {
+      let db = discovery_db_read(&self.discovery_db);
+      (
+        db.remote_topic_readers()
+          .filter(|drd| *drd.subscription_topic_data.topic_name() == topic_name)
+          .map(|drd| drd.reader_proxy.remote_reader_guid)
+          .collect(),
+        db.remote_topic_writers()
+          .filter(|dwd| dwd.publication_topic_data.topic_name == topic_name)
+          .map(|dwd| dwd.writer_proxy.remote_writer_guid)
+          .collect(),
+      )
+    };
+    self.ignored_topics.insert(topic_name);
+
+    for reader_guid in readers {
+      discovery_db_write(&self.discovery_db).remove_topic_reader(reader_guid);
+      self.send_discovery_notification(DiscoveryNotificationType::ReaderLost { reader_guid });
+    }
+    for writer_guid in writers {
+      discovery_db_write(&self.discovery_db).remove_topic_writer(writer_guid);
+      self.send_discovery_notification(DiscoveryNotificationType::WriterLost { writer_guid });
+    }
+  }
+
   pub fn participant_cleanup(&self) {
     let removed_guid_prefixes = discovery_db_write(&self.discovery_db).participant_cleanup();
     for guid_prefix in removed_guid_prefixes {
@@ -1457,6 +1782,9 @@ impl Discovery {
   }
 
   pub fn write_readers_info(&self) {
+    if self.passive {
+      return;
+    }
     let db = discovery_db_read(&self.discovery_db);
     let local_user_readers = db.get_all_local_topic_readers().filter(|p| {
       p.reader_proxy
@@ -1478,6 +1806,9 @@ impl Discovery {
   }
 
   pub fn write_writers_info(&self) {
+    if self.passive {
+      return;
+    }
     let db = discovery_db_read(&self.discovery_db);
     let local_user_writers = db.get_all_local_topic_writers().filter(|p| {
       p.writer_proxy
@@ -1503,6 +1834,9 @@ impl Discovery {
   }
 
   pub fn write_topic_info(&self) {
+    if self.passive {
+      return;
+    }
     let db = discovery_db_read(&self.discovery_db);
     let datas = db.local_user_topics();
     for data in datas {
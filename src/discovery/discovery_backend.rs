@@ -0,0 +1,49 @@
+use std::time::Duration;
+
+use crate::discovery::spdp_participant_data::SpdpDiscoveredParticipantData;
+
+/// A source of remote participant information for Discovery.
+///
+/// Discovery polls the backend periodically and feeds the returned
+/// participants into its database exactly as if they had been received via
+/// SPDP. Their builtin endpoints are then matched, so SEDP discovery of
+/// readers and writers proceeds over the locators given in the participant
+/// data.
+///
+/// This allows seeding peers from an external source, e.g. a static
+/// configuration file or a registry service, when SPDP multicast is not
+/// available. SPDP itself keeps running alongside any backend.
+///
+/// Participants reported by a backend are ignored when security is enabled,
+/// because secure participants are authenticated starting from their SPDP
+/// announcement.
+///
+/// A participant is dropped when its lease expires, as with SPDP, so a
+/// backend should keep reporting live peers at least once per their
+/// `lease_duration`.
+pub trait DiscoveryBackend: Send {
+  /// Returns the participants that should be (re)discovered now.
+  fn poll_participants(&mut self) -> Vec<SpdpDiscoveredParticipantData>;
+
+  /// How often [`poll_participants`](Self::poll_participants) is called.
+  /// The default is 1 second.
+  fn poll_period(&self) -> Duration {
+    Duration::from_secs(1)
+  }
+}
+
+/// The default backend, which reports no peers. Participants are then
+/// discovered only from SPDP announcements on the builtin participant topic.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoExtraPeers;
+
+impl DiscoveryBackend for NoExtraPeers {
+  fn poll_participants(&mut self) -> Vec<SpdpDiscoveredParticipantData> {
+    Vec::new()
+  }
+
+  fn poll_period(&self) -> Duration {
+    // Nothing to poll, so do not wake up often.
+    Duration::from_secs(60)
+  }
+}
@@ -22,17 +22,23 @@ use crate::{
     duration::Duration,
     entity::RTPSEntity,
     guid::{EntityId, GuidPrefix, GUID},
+    locator::Locator,
   },
 };
 use super::{
+  discovery_events::{DiscoveryEvent, DiscoveryEventSender},
   sedp_messages::{
     DiscoveredReaderData, DiscoveredTopicData, DiscoveredWriterData, ParticipantMessageData,
     ReaderProxy, SubscriptionBuiltinTopicData, TopicBuiltinTopicData, WriterProxy,
   },
-  spdp_participant_data::SpdpDiscoveredParticipantData,
+  spdp_participant_data::{DiscoveredParticipantInfo, SpdpDiscoveredParticipantData},
 };
 #[cfg(feature = "security")]
 use super::secure_discovery::AuthenticationStatus;
+#[cfg(feature = "security")]
+use crate::security::{
+  authentication::authentication_builtin::DiscHandshakeState, EndpointSecurityInfo,
+};
 
 // If remote participant does not specify lease duration, how long silence
 // until we pronounce it dead.
@@ -41,6 +47,10 @@ const DEFAULT_PARTICIPANT_LEASE_DURATION: Duration = Duration::from_secs(60);
 // How much longer to wait than lease duration before pronouncing lost.
 const PARTICIPANT_LEASE_DURATION_TOLERANCE: Duration = Duration::from_secs(0);
 
+/// Default for the maximum number of locators accepted in each locator list
+/// of a discovered participant or endpoint. Excess locators are dropped.
+pub(crate) const DEFAULT_MAX_LOCATORS_PER_LIST: usize = 16;
+
 // TODO: Let DiscoveryDB itself become thread-safe and support smaller-scope
 // lock
 pub(crate) struct DiscoveryDB {
@@ -51,6 +61,10 @@ pub(crate) struct DiscoveryDB {
   // Authentication statuses of participants
   #[cfg(feature = "security")]
   authentication_statuses: BTreeMap<GuidPrefix, AuthenticationStatus>,
+  // Copy of the handshake states kept by SecureDiscovery, so that they can be
+  // read from the DomainParticipant.
+  #[cfg(feature = "security")]
+  handshake_states: BTreeMap<GuidPrefix, DiscHandshakeState>,
 
   // local writer proxies for topics (topic name acts as key)
   local_topic_writers: BTreeMap<GUID, DiscoveredWriterData>,
@@ -71,8 +85,15 @@ pub(crate) struct DiscoveryDB {
   // Inner key is topic data sender.
   topics: BTreeMap<String, BTreeMap<GuidPrefix, (DiscoveredVia, DiscoveredTopicData)>>,
 
-  // sender for notifying (potential) waiters in participant.find_topic() call
+  // sender for notifying (potential) waiters in participant.find_topic() and
+  // participant.wait_for_writer() calls
   topic_updated_sender: mio_extras::channel::SyncSender<()>,
+
+  // Application subscriptions to participant.discovery_events()
+  event_senders: Vec<DiscoveryEventSender>,
+
+  // Maximum length of each locator list accepted from remote participants
+  max_locators_per_list: usize,
 }
 
 // How did we discover this topic
@@ -83,6 +104,21 @@ pub(crate) enum DiscoveredVia {
   Subscription, // we discovered a reader on this topic
 }
 
+// Drop locators beyond the limit, so that a remote participant cannot make us
+// process (and send to) an arbitrary number of addresses.
+fn truncate_locators(locators: &mut Vec<Locator>, max: usize, what: &str, guid: GUID) {
+  if locators.len() > max {
+    warn!(
+      "{:?} announced {} {} locators. Accepting only the first {}.",
+      guid,
+      locators.len(),
+      what,
+      max
+    );
+    locators.truncate(max);
+  }
+}
+
 fn move_by_guid_prefix<D>(
   guid_prefix: GuidPrefix,
   from: &mut BTreeMap<GUID, D>,
@@ -120,6 +156,8 @@ impl DiscoveryDB {
       participant_last_life_signs: BTreeMap::new(),
       #[cfg(feature = "security")]
       authentication_statuses: BTreeMap::new(),
+      #[cfg(feature = "security")]
+      handshake_states: BTreeMap::new(),
       local_topic_writers: BTreeMap::new(),
       local_topic_readers: BTreeMap::new(),
       external_topic_readers: BTreeMap::new(),
@@ -128,9 +166,59 @@ impl DiscoveryDB {
       external_topic_writers_attic: BTreeMap::new(),
       topics: BTreeMap::new(),
       topic_updated_sender,
+      event_senders: Vec::new(),
+      max_locators_per_list: DEFAULT_MAX_LOCATORS_PER_LIST,
+    }
+  }
+
+  pub fn set_max_locators_per_list(&mut self, max: usize) {
+    self.max_locators_per_list = max;
+  }
+
+  pub fn add_event_sender(&mut self, sender: DiscoveryEventSender) {
+    self.event_senders.push(sender);
+  }
+
+  fn send_event(&mut self, make_event: impl FnOnce() -> DiscoveryEvent) {
+    self.event_senders.retain(|s| !s.is_disconnected());
+    if let Some((last, others)) = self.event_senders.split_last() {
+      let event = make_event();
+      for sender in others {
+        sender.send(event.clone());
+      }
+      last.send(event);
     }
   }
 
+  fn limit_participant_locators(&self, data: &mut SpdpDiscoveredParticipantData) {
+    let max = self.max_locators_per_list;
+    let guid = data.participant_guid;
+    truncate_locators(
+      &mut data.metatraffic_unicast_locators,
+      max,
+      "metatraffic unicast",
+      guid,
+    );
+    truncate_locators(
+      &mut data.metatraffic_multicast_locators,
+      max,
+      "metatraffic multicast",
+      guid,
+    );
+    truncate_locators(
+      &mut data.default_unicast_locators,
+      max,
+      "default unicast",
+      guid,
+    );
+    truncate_locators(
+      &mut data.default_multicast_locators,
+      max,
+      "default multicast",
+      guid,
+    );
+  }
+
   // Returns if participant was previously unknown
   pub fn update_participant(&mut self, data: &SpdpDiscoveredParticipantData) -> bool {
     debug!("update_participant: {:?}", &data);
@@ -180,7 +268,14 @@ impl DiscoveryDB {
       );
     }
     // actual work here:
-    self.participant_proxies.insert(guid.prefix, data.clone());
+    let mut data = data.clone();
+    self.limit_participant_locators(&mut data);
+    if new_participant {
+      self.send_event(|| DiscoveryEvent::ParticipantDiscovered {
+        participant: DiscoveredParticipantInfo::from(&data),
+      });
+    }
+    self.participant_proxies.insert(guid.prefix, data);
     self
       .participant_last_life_signs
       .insert(guid.prefix, Instant::now());
@@ -212,10 +307,16 @@ impl DiscoveryDB {
   // participant timed out.
   pub fn remove_participant(&mut self, guid_prefix: GuidPrefix, active_disposal: bool) {
     info!("removing participant {:?}", guid_prefix);
-    self.participant_proxies.remove(&guid_prefix);
+    if self.participant_proxies.remove(&guid_prefix).is_some() && guid_prefix != self.my_guid.prefix
+    {
+      self.send_event(|| DiscoveryEvent::ParticipantLost { guid_prefix });
+    }
     self.participant_last_life_signs.remove(&guid_prefix);
     #[cfg(feature = "security")]
-    self.authentication_statuses.remove(&guid_prefix);
+    {
+      self.authentication_statuses.remove(&guid_prefix);
+      self.handshake_states.remove(&guid_prefix);
+    }
 
     if active_disposal {
       self.remove_topic_reader_with_prefix(guid_prefix);
@@ -242,6 +343,16 @@ impl DiscoveryDB {
     self.participant_proxies.get(&guid_prefix)
   }
 
+  // All known participants, except ourselves
+  pub fn remote_participants(&self) -> impl Iterator<Item = &SpdpDiscoveredParticipantData> {
+    let me = self.my_guid.prefix;
+    self
+      .participant_proxies
+      .iter()
+      .filter(move |(prefix, _)| **prefix != me)
+      .map(|(_, pp)| pp)
+  }
+
   fn remove_topic_reader_with_prefix(&mut self, guid_prefix: GuidPrefix) {
     // TODO: Implement this using .drain_filter() in BTreeMap once it lands in
     // stable.
@@ -257,7 +368,9 @@ impl DiscoveryDB {
 
   pub fn remove_topic_reader(&mut self, guid: GUID) {
     info!("remove_topic_reader {:?}", guid);
-    self.external_topic_readers.remove(&guid);
+    if self.external_topic_readers.remove(&guid).is_some() {
+      self.send_event(|| DiscoveryEvent::ReaderLost { reader_guid: guid });
+    }
   }
 
   fn remove_topic_writer_with_prefix(&mut self, guid_prefix: GuidPrefix) {
@@ -274,7 +387,9 @@ impl DiscoveryDB {
   }
 
   pub fn remove_topic_writer(&mut self, guid: GUID) {
-    self.external_topic_writers.remove(&guid);
+    if self.external_topic_writers.remove(&guid).is_some() {
+      self.send_event(|| DiscoveryEvent::WriterLost { writer_guid: guid });
+    }
   }
 
   // Delete participant proxies, if we have not heard of them within
@@ -392,7 +507,17 @@ impl DiscoveryDB {
   pub fn update_subscription(&mut self, data: &DiscoveredReaderData) -> DiscoveredReaderData {
     let guid = data.reader_proxy.remote_reader_guid;
 
-    self.external_topic_readers.insert(guid, data.clone());
+    let mut data = data.clone();
+    let max = self.max_locators_per_list;
+    let proxy = &mut data.reader_proxy;
+    truncate_locators(&mut proxy.unicast_locator_list, max, "unicast", guid);
+    truncate_locators(&mut proxy.multicast_locator_list, max, "multicast", guid);
+    let data = &data;
+
+    let was_new = self
+      .external_topic_readers
+      .insert(guid, data.clone())
+      .is_none();
 
     // fill in the default locators from participant, in case DRD did not provide
     // any
@@ -427,6 +552,12 @@ impl DiscoveryDB {
       DiscoveredVia::Subscription,
     );
 
+    if was_new && guid.prefix != self.my_guid.prefix {
+      self.send_event(|| DiscoveryEvent::ReaderDiscovered {
+        reader: data.clone(),
+      });
+    }
+
     // TODO: Lookup the topic in DB, data sent by the same participant that sent the
     // reader update. If there is a DiscoveredVia::Topic record, use QosPolicies
     // from that record and modify by QoS given in the DRD.
@@ -446,9 +577,17 @@ impl DiscoveryDB {
   pub fn update_publication(&mut self, data: &DiscoveredWriterData) -> DiscoveredWriterData {
     let guid = data.writer_proxy.remote_writer_guid;
 
-    self
+    let mut data = data.clone();
+    let max = self.max_locators_per_list;
+    let proxy = &mut data.writer_proxy;
+    truncate_locators(&mut proxy.unicast_locator_list, max, "unicast", guid);
+    truncate_locators(&mut proxy.multicast_locator_list, max, "multicast", guid);
+    let data = &data;
+
+    let was_new = self
       .external_topic_writers
-      .insert(data.writer_proxy.remote_writer_guid, data.clone());
+      .insert(data.writer_proxy.remote_writer_guid, data.clone())
+      .is_none();
 
     // fill in the default locators from participant, in case DRD did not provide
     // any
@@ -483,6 +622,14 @@ impl DiscoveryDB {
       guid,
       DiscoveredVia::Publication,
     );
+    if was_new {
+      self.notify_waiters();
+    }
+    if was_new && guid.prefix != self.my_guid.prefix {
+      self.send_event(|| DiscoveryEvent::WriterDiscovered {
+        writer: data.clone(),
+      });
+    }
 
     DiscoveredWriterData {
       writer_proxy: WriterProxy::from(RtpsWriterProxy::from_discovered_writer_data(
@@ -556,18 +703,25 @@ impl DiscoveryDB {
       let mut b = BTreeMap::new();
       b.insert(updater.prefix, (discovered_via, dtd.clone()));
       self.topics.insert(topic_name, b);
+      if updater.prefix != self.my_guid.prefix {
+        self.send_event(|| DiscoveryEvent::TopicDiscovered { topic: dtd.clone() });
+      }
     };
 
     if notify {
-      self
-        .topic_updated_sender
-        .try_send(())
-        // It is quite normal for this to fail due to channel full,
-        // because usually there is no-one at the other end receiving.
-        .unwrap_or_else(|e| trace!("update_topic_data: Notification send failed: {e:?}"));
+      self.notify_waiters();
     }
   }
 
+  fn notify_waiters(&self) {
+    self
+      .topic_updated_sender
+      .try_send(())
+      // It is quite normal for this to fail due to channel full,
+      // because usually there is no-one at the other end receiving.
+      .unwrap_or_else(|e| trace!("notify_waiters: Notification send failed: {e:?}"));
+  }
+
   // local topic readers
   pub fn update_local_topic_reader(
     &mut self,
@@ -579,13 +733,26 @@ impl DiscoveryDB {
 
     let reader_proxy = RtpsReaderProxy::from_reader(reader, domain_participant);
 
+    // With security, advertise the protection our governance applies to the topic.
+    #[cfg(feature = "security")]
+    let security_info = reader.security_plugins.as_ref().and_then(|handle| {
+      handle
+        .get_plugins()
+        .get_reader_sec_attributes(reader_guid, topic.name())
+        .map(EndpointSecurityInfo::from)
+        .map_err(|e| error!("Cannot get security attributes of reader {reader_guid:?}: {e}"))
+        .ok()
+    });
+    #[cfg(not(feature = "security"))]
+    let security_info = None;
+
     let subscription_data = SubscriptionBuiltinTopicData::new(
       reader_guid,
       Some(domain_participant.guid()),
       topic.name(),
       topic.get_type().name().to_string(),
-      &topic.qos(),
-      None, // <<---------------TODO: None here means we have no EndpointSecurityInfo
+      &reader.qos_policy,
+      security_info,
     );
 
     // TODO: possibly change content filter to dynamic value
@@ -606,6 +773,11 @@ impl DiscoveryDB {
     self.local_topic_readers.remove(&guid);
   }
 
+  // Is the GUID taken by a DataReader or DataWriter of this participant?
+  pub fn is_local_endpoint(&self, guid: GUID) -> bool {
+    self.local_topic_writers.contains_key(&guid) || self.local_topic_readers.contains_key(&guid)
+  }
+
   pub fn get_all_local_topic_readers(&self) -> impl Iterator<Item = &DiscoveredReaderData> {
     self.local_topic_readers.values()
   }
@@ -614,6 +786,26 @@ impl DiscoveryDB {
     self.local_topic_writers.values()
   }
 
+  // Readers discovered via SEDP, except our own
+  pub fn remote_topic_readers(&self) -> impl Iterator<Item = &DiscoveredReaderData> {
+    let me = self.my_guid.prefix;
+    self
+      .external_topic_readers
+      .iter()
+      .filter(move |(guid, _)| guid.prefix != me)
+      .map(|(_, drd)| drd)
+  }
+
+  // Writers discovered via SEDP, except our own
+  pub fn remote_topic_writers(&self) -> impl Iterator<Item = &DiscoveredWriterData> {
+    let me = self.my_guid.prefix;
+    self
+      .external_topic_writers
+      .iter()
+      .filter(move |(guid, _)| guid.prefix != me)
+      .map(|(_, dwd)| dwd)
+  }
+
   // Note:
   // If multiple participants announce the same topic, this will
   // return duplicates, one per announcing participant.
@@ -722,11 +914,44 @@ impl DiscoveryDB {
   ) {
     self.authentication_statuses.insert(guid_prefix, status);
   }
+
+  // Authentication statuses of remote participants
+  #[cfg(feature = "security")]
+  pub fn authentication_statuses(&self) -> impl Iterator<Item = AuthenticationStatus> + '_ {
+    let me = self.my_guid.prefix;
+    self
+      .authentication_statuses
+      .iter()
+      .filter(move |(guid_prefix, _)| **guid_prefix != me)
+      .map(|(_, status)| *status)
+  }
+
+  #[cfg(feature = "security")]
+  pub fn update_handshake_state(&mut self, guid_prefix: GuidPrefix, state: DiscHandshakeState) {
+    self.handshake_states.insert(guid_prefix, state);
+  }
+
+  #[cfg(feature = "security")]
+  pub fn remove_handshake_state(&mut self, guid_prefix: GuidPrefix) {
+    self.handshake_states.remove(&guid_prefix);
+  }
+
+  // Handshakes that have not completed yet
+  #[cfg(feature = "security")]
+  pub fn pending_handshakes(&self) -> Vec<(GuidPrefix, DiscHandshakeState)> {
+    self
+      .handshake_states
+      .iter()
+      .filter(|(_, state)| !state.is_completed())
+      .map(|(guid_prefix, state)| (*guid_prefix, *state))
+      .collect()
+  }
 }
 
 #[cfg(test)]
 mod tests {
   use std::{
+    net::SocketAddr,
     sync::{Arc, Mutex},
     time::Duration as StdDuration,
   };
@@ -773,6 +998,45 @@ mod tests {
     // TODO: more operations tests
   }
 
+  #[test]
+  fn discdb_locator_limit() {
+    let (discovery_db_event_sender, _discovery_db_event_receiver) =
+      mio_channel::sync_channel::<()>(4);
+
+    let mut discoverydb = DiscoveryDB::new(GUID::new_participant_guid(), discovery_db_event_sender);
+    discoverydb.set_max_locators_per_list(4);
+
+    let many_locators: Vec<Locator> = (0..100)
+      .map(|port| Locator::from(SocketAddr::new("10.0.0.1".parse().unwrap(), 7400 + port)))
+      .collect();
+
+    let mut data = spdp_participant_data().unwrap();
+    data.default_unicast_locators = many_locators.clone();
+    data.metatraffic_multicast_locators = many_locators.clone();
+    discoverydb.update_participant(&data);
+
+    let pp = discoverydb
+      .find_participant_proxy(data.participant_guid.prefix)
+      .unwrap();
+    assert_eq!(pp.default_unicast_locators, many_locators[..4]);
+    assert_eq!(pp.metatraffic_multicast_locators, many_locators[..4]);
+
+    let mut reader_proxy = reader_proxy_data().unwrap();
+    reader_proxy.unicast_locator_list = many_locators.clone();
+    let dreader = DiscoveredReaderData {
+      reader_proxy,
+      subscription_topic_data: subscription_builtin_topic_data().unwrap(),
+      content_filter: None,
+    };
+    let updated = discoverydb.update_subscription(&dreader);
+    assert_eq!(updated.reader_proxy.unicast_locator_list.len(), 4);
+    let stored = discoverydb
+      .external_topic_readers
+      .get(&dreader.reader_proxy.remote_reader_guid)
+      .unwrap();
+    assert_eq!(stored.reader_proxy.unicast_locator_list, many_locators[..4]);
+  }
+
   #[test]
   fn discdb_writer_proxies() {
     let (discovery_db_event_sender, _discovery_db_event_receiver) =
@@ -913,6 +1177,7 @@ mod tests {
       data_reader_command_receiver: reader_command_receiver1,
       data_reader_waker: data_reader_waker1,
       poll_event_sender: notification_event_sender1,
+      heartbeat_response_delay: StdDuration::ZERO,
       security_plugins: None,
     };
 
@@ -950,6 +1215,7 @@ mod tests {
       data_reader_command_receiver: reader_command_receiver2,
       data_reader_waker: data_reader_waker2,
       poll_event_sender: notification_event_sender2,
+      heartbeat_response_delay: StdDuration::ZERO,
       security_plugins: None,
     };
 
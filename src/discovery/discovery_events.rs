@@ -0,0 +1,153 @@
+// Discovery events: changes in the DiscoveryDB reported to the application,
+// so that it does not need to poll discovered_participants() and friends.
+
+use std::{
+  collections::VecDeque,
+  sync::{Arc, Condvar, Mutex},
+  time::{Duration, Instant},
+};
+
+use crate::structure::guid::{GuidPrefix, GUID};
+use super::{
+  sedp_messages::{DiscoveredReaderData, DiscoveredTopicData, DiscoveredWriterData},
+  spdp_participant_data::DiscoveredParticipantInfo,
+};
+
+// How many events a DiscoveryEventReceiver buffers, if the application is not
+// receiving them. After this, the oldest events are dropped.
+const DISCOVERY_EVENT_QUEUE_CAPACITY: usize = 128;
+
+/// Change in what a DomainParticipant knows about remote entities. See
+/// [`DomainParticipant::discovery_events`](crate::DomainParticipant::discovery_events).
+#[derive(Debug, Clone)]
+#[allow(clippy::large_enum_variant)]
+pub enum DiscoveryEvent {
+  /// A new remote DomainParticipant was discovered.
+  ParticipantDiscovered {
+    participant: DiscoveredParticipantInfo,
+  },
+  /// A remote DomainParticipant has left or timed out. Its DataReaders and
+  /// DataWriters are gone, too, although no separate events are reported.
+  ParticipantLost { guid_prefix: GuidPrefix },
+  /// A new remote DataReader was discovered.
+  ReaderDiscovered { reader: DiscoveredReaderData },
+  /// A remote DataReader was deleted.
+  ReaderLost { reader_guid: GUID },
+  /// A new remote DataWriter was discovered.
+  WriterDiscovered { writer: DiscoveredWriterData },
+  /// A remote DataWriter was deleted.
+  WriterLost { writer_guid: GUID },
+  /// A topic was discovered, i.e. a remote participant has announced it or
+  /// an endpoint on it, and it was not known to us before.
+  TopicDiscovered { topic: DiscoveredTopicData },
+}
+
+struct EventQueue {
+  events: VecDeque<DiscoveryEvent>,
+  lost_count: u64,
+}
+
+type SharedQueue = Arc<(Mutex<EventQueue>, Condvar)>;
+
+pub(crate) fn discovery_event_channel() -> (DiscoveryEventSender, DiscoveryEventReceiver) {
+  let queue = Arc::new((
+    Mutex::new(EventQueue {
+      events: VecDeque::with_capacity(DISCOVERY_EVENT_QUEUE_CAPACITY),
+      lost_count: 0,
+    }),
+    Condvar::new(),
+  ));
+  (
+    DiscoveryEventSender {
+      queue: Arc::clone(&queue),
+    },
+    DiscoveryEventReceiver { queue },
+  )
+}
+
+// Sending never blocks: if the queue is full, the oldest event is dropped and
+// counted, so that a slow application cannot stall Discovery.
+pub(crate) struct DiscoveryEventSender {
+  queue: SharedQueue,
+}
+
+impl DiscoveryEventSender {
+  pub fn send(&self, event: DiscoveryEvent) {
+    let (lock, condvar) = &*self.queue;
+    let mut queue = lock.lock().unwrap();
+    if queue.events.len() >= DISCOVERY_EVENT_QUEUE_CAPACITY {
+      queue.events.pop_front();
+      queue.lost_count += 1;
+    }
+    queue.events.push_back(event);
+    condvar.notify_one();
+  }
+
+  // The receiver has been dropped, so there is no need to send anymore.
+  pub fn is_disconnected(&self) -> bool {
+    Arc::strong_count(&self.queue) == 1
+  }
+}
+
+/// Receiving end of
+/// [`DomainParticipant::discovery_events`](crate::DomainParticipant::discovery_events).
+///
+/// At most 128 events are buffered. If the application does not keep up, the
+/// oldest events are dropped, and counted in
+/// [`lost_count`](DiscoveryEventReceiver::lost_count).
+pub struct DiscoveryEventReceiver {
+  queue: SharedQueue,
+}
+
+impl DiscoveryEventReceiver {
+  /// Returns the oldest buffered event, or `None` if there is none.
+  pub fn try_recv(&self) -> Option<DiscoveryEvent> {
+    self.queue.0.lock().unwrap().events.pop_front()
+  }
+
+  /// Waits for an event at most for `timeout`.
+  pub fn recv_timeout(&self, timeout: Duration) -> Option<DiscoveryEvent> {
+    let (lock, condvar) = &*self.queue;
+    let deadline = Instant::now() + timeout;
+    let mut queue = lock.lock().unwrap();
+    loop {
+      if let Some(event) = queue.events.pop_front() {
+        return Some(event);
+      }
+      let remaining = deadline.checked_duration_since(Instant::now())?;
+      queue = condvar.wait_timeout(queue, remaining).unwrap().0;
+    }
+  }
+
+  /// Number of events dropped so far, because the buffer was full.
+  pub fn lost_count(&self) -> u64 {
+    self.queue.0.lock().unwrap().lost_count
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn full_queue_drops_oldest() {
+    let (sender, receiver) = discovery_event_channel();
+    let total = DISCOVERY_EVENT_QUEUE_CAPACITY + 5;
+    for _ in 0..total {
+      sender.send(DiscoveryEvent::ParticipantLost {
+        guid_prefix: GuidPrefix::random_for_this_participant(),
+      });
+    }
+    assert_eq!(receiver.lost_count(), 5);
+    let mut count = 0;
+    while receiver.try_recv().is_some() {
+      count += 1;
+    }
+    assert_eq!(count, DISCOVERY_EVENT_QUEUE_CAPACITY);
+    assert!(receiver.recv_timeout(Duration::from_millis(10)).is_none());
+
+    assert!(!sender.is_disconnected());
+    drop(receiver);
+    assert!(sender.is_disconnected());
+  }
+}
@@ -0,0 +1,134 @@
+// Discovery scan: listen to SPDP announcements for a while, without creating
+// a DomainParticipant, and report who is out there.
+
+use std::{
+  collections::HashMap,
+  net::{IpAddr, Ipv4Addr},
+  ops::Range,
+  time::{Duration, Instant},
+};
+
+#[allow(unused_imports)]
+use log::{debug, error, info, trace, warn};
+use mio_06::{Events, Poll, PollOpt, Ready, Token};
+use bytes::Bytes;
+
+use crate::{
+  dds::result::CreateResult,
+  messages::submessages::{
+    elements::serialized_payload::SerializedPayload, submessage::WriterSubmessage,
+  },
+  network::{
+    constant::{spdp_well_known_multicast_port, DEFAULT_MULTICAST_GROUP},
+    udp_listener::{SocketReuse, UDPListener},
+    util::get_local_multicast_ip_addrs,
+  },
+  rtps::{message::Message, SubmessageBody},
+  serialization::pl_cdr_adapters::PlCdrDeserialize,
+  structure::guid::{EntityId, GUID},
+};
+use super::spdp_participant_data::{DiscoveredParticipantInfo, SpdpDiscoveredParticipantData};
+
+/// A DomainParticipant found by
+/// [`DomainParticipant::scan`](crate::DomainParticipant::scan)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScannedParticipant {
+  /// The domain in which the participant announced itself
+  pub domain_id: u16,
+  /// Contents of the latest announcement received
+  pub participant: DiscoveredParticipantInfo,
+}
+
+pub(crate) fn scan(
+  domain_ids: Range<u16>,
+  interfaces: &[Ipv4Addr],
+  duration: Duration,
+) -> CreateResult<Vec<ScannedParticipant>> {
+  let interfaces: Vec<IpAddr> = if interfaces.is_empty() {
+    get_local_multicast_ip_addrs()?
+  } else {
+    interfaces.iter().copied().map(IpAddr::V4).collect()
+  };
+
+  let poll = Poll::new()?;
+  let mut listeners = HashMap::new();
+  for domain_id in domain_ids {
+    // Other participants on this host are bound to the same port, so the
+    // listener must share it like theirs do.
+    let mut listener = UDPListener::new_multicast_on_interfaces(
+      "0.0.0.0",
+      spdp_well_known_multicast_port(domain_id),
+      DEFAULT_MULTICAST_GROUP,
+      &interfaces,
+      SocketReuse::ALL,
+    )?;
+    let token = Token(usize::from(domain_id));
+    poll.register(
+      listener.mio_socket(),
+      token,
+      Ready::readable(),
+      PollOpt::edge(),
+    )?;
+    listeners.insert(token, (domain_id, listener));
+  }
+
+  let mut found: HashMap<GUID, ScannedParticipant> = HashMap::new();
+  let mut events = Events::with_capacity(16);
+  let deadline = Instant::now() + duration;
+  while let Some(timeout) = deadline.checked_duration_since(Instant::now()) {
+    poll.poll(&mut events, Some(timeout))?;
+    for event in &events {
+      let Some((domain_id, listener)) = listeners.get_mut(&event.token()) else {
+        continue;
+      };
+      for bytes in listener.messages() {
+        for participant in parse_spdp_message(&bytes) {
+          found.insert(
+            participant.guid,
+            ScannedParticipant {
+              domain_id: *domain_id,
+              participant,
+            },
+          );
+        }
+      }
+    }
+  }
+
+  Ok(found.into_values().collect())
+}
+
+// Extracts the participant announcements from an RTPS message. Anything that
+// is not an SPDP announcement is skipped.
+fn parse_spdp_message(bytes: &Bytes) -> Vec<DiscoveredParticipantInfo> {
+  let message = match Message::read_from_buffer(bytes) {
+    Ok(m) => m,
+    Err(e) => {
+      debug!("Scan: cannot parse RTPS message: {e}");
+      return vec![];
+    }
+  };
+
+  message
+    .submessages
+    .iter()
+    .filter_map(|submessage| match &submessage.body {
+      SubmessageBody::Writer(WriterSubmessage::Data(data, _flags))
+        if data.writer_id == EntityId::SPDP_BUILTIN_PARTICIPANT_WRITER =>
+      {
+        data.encoded_payload.as_ref()
+      }
+      _ => None,
+    })
+    .filter_map(|payload| {
+      let payload = SerializedPayload::from_bytes(payload).ok()?;
+      SpdpDiscoveredParticipantData::from_pl_cdr_bytes(
+        &payload.value,
+        payload.representation_identifier,
+      )
+      .map_err(|e| debug!("Scan: cannot deserialize participant data: {e:?}"))
+      .ok()
+    })
+    .map(|data| DiscoveredParticipantInfo::from(&data))
+    .collect()
+}
@@ -1,6 +1,7 @@
 use std::{
-  collections::HashMap,
+  collections::{HashMap, HashSet},
   sync::{Arc, RwLock},
+  time::{Duration, Instant},
 };
 
 #[allow(unused_imports)]
@@ -11,6 +12,7 @@ use crate::{
   dds::{
     no_key,
     participant::DomainParticipantWeak,
+    qos::property::PARTICIPANT_STATELESS_MESSAGE_REPLAY_CHECK_PROPERTY,
     with_key::{DataSample, Sample, WriteOptionsBuilder},
   },
   qos, rpc,
@@ -27,10 +29,11 @@ use crate::{
       CryptoToken, GMCLASSID_SECURITY_DATAREADER_CRYPTO_TOKENS,
       GMCLASSID_SECURITY_DATAWRITER_CRYPTO_TOKENS, GMCLASSID_SECURITY_PARTICIPANT_CRYPTO_TOKENS,
     },
+    logging::{msgid, LoggingLevel, SecurityLogger},
     security_error,
     security_plugins::SecurityPluginsHandle,
-    DataHolder, ParticipantGenericMessage, ParticipantSecurityInfo, ParticipantStatelessMessage,
-    ParticipantVolatileMessageSecure, SecurityError, SecurityResult,
+    DataHolder, EndpointSecurityInfo, ParticipantGenericMessage, ParticipantSecurityInfo,
+    ParticipantStatelessMessage, ParticipantVolatileMessageSecure, SecurityError, SecurityResult,
   },
   security_error, security_log,
   serialization::pl_cdr_adapters::PlCdrSerialize,
@@ -80,6 +83,131 @@ impl StoredAuthenticationMessage {
   }
 }
 
+// How many times a crypto token message is resent, if the remote does not
+// acknowledge it
+const VOLATILE_MESSAGE_MAX_RESEND_COUNT: u8 = 10;
+// How long to wait for the remote to acknowledge a crypto token message before
+// resending it
+const VOLATILE_MESSAGE_ACK_TIMEOUT: Duration = Duration::from_secs(1);
+
+// Domains in which tests want the first sending of each crypto token message
+// to get lost, with the count of messages lost so far
+#[cfg(test)]
+static LOSE_FIRST_SENT_VOLATILE_MESSAGES: std::sync::Mutex<Vec<(u16, usize)>> =
+  std::sync::Mutex::new(Vec::new());
+
+#[cfg(test)]
+fn lose_volatile_message(domain_id: u16) -> bool {
+  let mut domains = LOSE_FIRST_SENT_VOLATILE_MESSAGES.lock().unwrap();
+  match domains.iter_mut().find(|(d, _)| *d == domain_id) {
+    Some((_, lost_count)) => {
+      *lost_count += 1;
+      true
+    }
+    None => false,
+  }
+}
+
+struct StoredVolatileMessage {
+  message: ParticipantVolatileMessageSecure,
+  // None, if writing the message failed
+  sequence_number: Option<SequenceNumber>,
+  sent_at: Instant,
+  remaining_resend_counter: u8,
+}
+
+// Crypto token messages sent to remote participants, kept until the remote's
+// volatile secure reader acknowledges them. The volatile secure writer is
+// reliable, but it cannot retransmit a message to a reader that was not yet
+// matched when the message was written, and lost messages are not repaired,
+// if the reader does not know to ask for them. Resending the message covers
+// these cases.
+struct UnacknowledgedVolatileMessages {
+  messages: HashMap<GuidPrefix, Vec<StoredVolatileMessage>>,
+}
+
+impl UnacknowledgedVolatileMessages {
+  fn new() -> Self {
+    Self {
+      messages: HashMap::new(),
+    }
+  }
+
+  fn insert(
+    &mut self,
+    remote_guid_prefix: GuidPrefix,
+    message: ParticipantVolatileMessageSecure,
+    sequence_number: Option<SequenceNumber>,
+    now: Instant,
+  ) {
+    self
+      .messages
+      .entry(remote_guid_prefix)
+      .or_default()
+      .push(StoredVolatileMessage {
+        message,
+        sequence_number,
+        sent_at: now,
+        remaining_resend_counter: VOLATILE_MESSAGE_MAX_RESEND_COUNT,
+      });
+  }
+
+  fn remotes(&self) -> Vec<GuidPrefix> {
+    self.messages.keys().copied().collect()
+  }
+
+  // Forgets the messages that the remote has acknowledged
+  fn acknowledge(&mut self, remote_guid_prefix: GuidPrefix, highest_acked: SequenceNumber) {
+    if let Some(messages) = self.messages.get_mut(&remote_guid_prefix) {
+      messages.retain(|m| m.sequence_number.map_or(true, |sn| sn > highest_acked));
+      if messages.is_empty() {
+        self.messages.remove(&remote_guid_prefix);
+      }
+    }
+  }
+
+  // Takes out the messages that have waited for an acknowledgement too long.
+  // Messages that have been resent too many times are dropped.
+  fn take_overdue(&mut self, now: Instant) -> Vec<(GuidPrefix, StoredVolatileMessage)> {
+    let mut overdue = Vec::new();
+    for (remote_guid_prefix, messages) in self.messages.iter_mut() {
+      let (waiting, late): (Vec<_>, Vec<_>) = messages
+        .drain(..)
+        .partition(|m| now.duration_since(m.sent_at) < VOLATILE_MESSAGE_ACK_TIMEOUT);
+      *messages = waiting;
+      for stored in late {
+        if stored.remaining_resend_counter > 0 {
+          overdue.push((*remote_guid_prefix, stored));
+        } else {
+          warn!(
+            "Remote {:?} did not acknowledge crypto tokens. Giving up resending them.",
+            remote_guid_prefix
+          );
+        }
+      }
+    }
+    self.messages.retain(|_, messages| !messages.is_empty());
+    overdue
+  }
+
+  fn resent(
+    &mut self,
+    remote_guid_prefix: GuidPrefix,
+    mut stored: StoredVolatileMessage,
+    sequence_number: Option<SequenceNumber>,
+    now: Instant,
+  ) {
+    stored.sequence_number = sequence_number;
+    stored.sent_at = now;
+    stored.remaining_resend_counter -= 1;
+    self
+      .messages
+      .entry(remote_guid_prefix)
+      .or_default()
+      .push(stored);
+  }
+}
+
 // This struct is an appendix to Discovery that handles Security-related
 // functionality. The intention is that Discovery calls the methods of this
 // struct when Security matters needs to be handled.
@@ -103,6 +231,15 @@ pub(crate) struct SecureDiscovery {
   // Here we store the latest authentication message that we've sent to each remote,
   // in case they need to be sent again
   stored_authentication_messages: HashMap<GuidPrefix, StoredAuthenticationMessage>,
+  // Detects replayed old handshake messages
+  stateless_replay_filter: StatelessMessageReplayFilter,
+  // Security events are logged (also) to the builtin logging topic through this
+  security_logger: SecurityLogger,
+  // Remote endpoints whose protection mismatch has already been reported, so
+  // that repeated Discovery updates do not repeat the report.
+  reported_protection_mismatches: HashSet<GUID>,
+  // Crypto token messages waiting for an acknowledgement from the remote
+  unacknowledged_volatile_messages: UnacknowledgedVolatileMessages,
 }
 
 impl SecureDiscovery {
@@ -172,6 +309,16 @@ impl SecureDiscovery {
 
     drop(plugins); // Drop mutex guard on plugins so that plugins can be moved to self
 
+    let replay_check = domain_participant
+      .qos()
+      .get_optional_property(PARTICIPANT_STATELESS_MESSAGE_REPLAY_CHECK_PROPERTY)
+      .map(|s| !s.trim().eq_ignore_ascii_case("false"))
+      .unwrap_or(true);
+
+    // Creating the logging DataWriter goes through access control, so the
+    // plugins must not be locked here.
+    let security_logger = SecurityLogger::new(domain_participant, &domain_participant.qos());
+
     Ok(Self {
       security_plugins,
       domain_id: domain_participant.domain_id(),
@@ -183,6 +330,10 @@ impl SecureDiscovery {
       generic_message_helper: ParticipantGenericMessageHelper::new(),
       handshake_states: HashMap::new(),
       stored_authentication_messages: HashMap::new(),
+      stateless_replay_filter: StatelessMessageReplayFilter::new(replay_check),
+      security_logger,
+      reported_protection_mismatches: HashSet::new(),
+      unacknowledged_volatile_messages: UnacknowledgedVolatileMessages::new(),
     })
   }
 
@@ -250,6 +401,10 @@ impl SecureDiscovery {
             // But configuration still allows matching with the participant (in a limited
             // way)
             security_log!(
+              self.security_logger,
+              LoggingLevel::Warning,
+              msgid::INCOMPATIBLE_SECURITY,
+              Some(participant_data.participant_guid.prefix),
               "Remote participant has incompatible Security, but matching with it anyways, since \
                configuration allows this. Remote guid: {:?}",
               participant_data.participant_guid
@@ -258,6 +413,10 @@ impl SecureDiscovery {
           } else {
             // Not allowed to match
             security_log!(
+              self.security_logger,
+              LoggingLevel::Error,
+              msgid::INCOMPATIBLE_SECURITY,
+              Some(participant_data.participant_guid.prefix),
               "Remote participant has incompatible Security, not matching with it. Remote guid: \
                {:?}",
               participant_data.participant_guid
@@ -465,6 +624,10 @@ impl SecureDiscovery {
       Err(e) => {
         // Validation failed
         security_log!(
+          self.security_logger,
+          LoggingLevel::Error,
+          msgid::AUTHENTICATION_FAILED,
+          Some(remote_guid.prefix),
           "Failed to validate the identity of a remote participant with guid: {:?}. Info: {}",
           remote_guid,
           e.msg
@@ -475,6 +638,10 @@ impl SecureDiscovery {
           .allow_unauthenticated_participants
         {
           security_log!(
+            self.security_logger,
+            LoggingLevel::Warning,
+            msgid::AUTHENTICATION_FAILED,
+            Some(remote_guid.prefix),
             "Treating the participant with guid {:?} as Unauthenticated, since configuration \
              allows this.",
             remote_guid,
@@ -487,8 +654,12 @@ impl SecureDiscovery {
       }
     };
 
-    info!(
-      "Validated identity of remote participant with guid: {:?}",
+    security_log!(
+      self.security_logger,
+      LoggingLevel::Informational,
+      msgid::HANDSHAKE_STARTED,
+      Some(remote_guid.prefix),
+      "Validated identity of remote participant with guid: {:?}. Starting handshake.",
       remote_guid
     );
 
@@ -509,7 +680,11 @@ impl SecureDiscovery {
     match outcome {
       ValidationOutcome::PendingHandshakeRequest => {
         // We should send the handshake request
-        self.update_handshake_state(remote_guid.prefix, DiscHandshakeState::PendingRequestSend);
+        self.update_handshake_state(
+          remote_guid.prefix,
+          DiscHandshakeState::PendingRequestSend,
+          discovery_db,
+        );
         self.try_sending_new_handshake_request_message(
           remote_guid.prefix,
           discovery_db,
@@ -523,6 +698,7 @@ impl SecureDiscovery {
         self.update_handshake_state(
           remote_guid.prefix,
           DiscHandshakeState::PendingRequestMessage,
+          discovery_db,
         );
 
         debug!(
@@ -640,7 +816,11 @@ impl SecureDiscovery {
     });
 
     // Update handshake state to pending reply message
-    self.update_handshake_state(remote_guid_prefix, DiscHandshakeState::PendingReplyMessage);
+    self.update_handshake_state(
+      remote_guid_prefix,
+      DiscHandshakeState::PendingReplyMessage,
+      discovery_db,
+    );
   }
 
   pub fn resend_unanswered_authentication_messages(
@@ -692,6 +872,50 @@ impl SecureDiscovery {
     }
   }
 
+  // Cancels an in-progress handshake on application request. The remote is
+  // marked Rejected, so that no new handshake is started with it before it
+  // leaves and is discovered again.
+  pub fn abort_handshake(
+    &mut self,
+    remote_guid_prefix: GuidPrefix,
+    discovery_db: &Arc<RwLock<DiscoveryDB>>,
+    discovery_updated_sender: &mio_channel::SyncSender<DiscoveryNotificationType>,
+  ) {
+    match self.get_handshake_state(&remote_guid_prefix) {
+      Some(state) if !state.is_completed() => {}
+      other => {
+        debug!(
+          "No handshake in progress with remote with guid prefix {:?}, not aborting. Handshake \
+           state: {:?}",
+          remote_guid_prefix, other
+        );
+        return;
+      }
+    }
+
+    self
+      .stored_authentication_messages
+      .remove(&remote_guid_prefix);
+    self.handshake_states.remove(&remote_guid_prefix);
+    discovery_db_write(discovery_db).remove_handshake_state(remote_guid_prefix);
+
+    self.update_participant_authentication_status_and_notify_dp(
+      remote_guid_prefix,
+      AuthenticationStatus::Rejected,
+      discovery_db,
+      discovery_updated_sender,
+    );
+
+    security_log!(
+      self.security_logger,
+      LoggingLevel::Warning,
+      msgid::HANDSHAKE_FAILED,
+      Some(remote_guid_prefix),
+      "Handshake aborted by the application. Remote guid prefix: {:?}",
+      remote_guid_prefix
+    );
+  }
+
   pub fn participant_stateless_message_read(
     &mut self,
     message: &ParticipantStatelessMessage,
@@ -714,6 +938,14 @@ impl SecureDiscovery {
     }
 
     let remote_guid_prefix = message.generic.source_guid_prefix();
+    if !self.stateless_replay_filter.accept(message) {
+      debug!(
+        "Ignoring a stale handshake message {:?} from remote with guid prefix {:?}.",
+        message.generic.message_identity.sequence_number, remote_guid_prefix
+      );
+      return;
+    }
+
     // What to do depends on the handshake state with the remote participant
     match self.get_handshake_state(&remote_guid_prefix) {
       None => {
@@ -844,9 +1076,11 @@ impl SecureDiscovery {
         );
 
         // Set handshake state as pending final message
-        self
-          .handshake_states
-          .insert(remote_guid_prefix, DiscHandshakeState::PendingFinalMessage);
+        self.update_handshake_state(
+          remote_guid_prefix,
+          DiscHandshakeState::PendingFinalMessage,
+          discovery_db,
+        );
       }
       Ok((other_outcome, _reply_token)) => {
         // Other outcomes should not be possible
@@ -857,9 +1091,14 @@ impl SecureDiscovery {
         );
       }
       Err(e) => {
-        error!(
+        security_log!(
+          self.security_logger,
+          LoggingLevel::Error,
+          msgid::HANDSHAKE_FAILED,
+          Some(remote_guid_prefix),
           "Replying to a handshake request failed: {}. Remote guid prefix: {:?}",
-          e, remote_guid_prefix
+          e,
+          remote_guid_prefix
         );
       }
     }
@@ -943,9 +1182,10 @@ impl SecureDiscovery {
         );
 
         // Set handshake state as completed with final message
-        self.handshake_states.insert(
+        self.update_handshake_state(
           remote_guid_prefix,
           DiscHandshakeState::CompletedWithFinalMessageSent,
+          discovery_db,
         );
 
         self.on_remote_participant_authenticated(
@@ -963,9 +1203,14 @@ impl SecureDiscovery {
         );
       }
       Err(e) => {
-        error!(
+        security_log!(
+          self.security_logger,
+          LoggingLevel::Error,
+          msgid::HANDSHAKE_FAILED,
+          Some(remote_guid_prefix),
           "Validating handshake reply message failed. Error: {}. Remote guid prefix: {:?}",
-          e, remote_guid_prefix
+          e,
+          remote_guid_prefix
         );
         // Reset stored message resend counter, so our resends can't be depleted by
         // sending us incorrect messages
@@ -1021,9 +1266,10 @@ impl SecureDiscovery {
         // Everything went OK
 
         // Set handshake state as completed with final message
-        self.handshake_states.insert(
+        self.update_handshake_state(
           remote_guid_prefix,
           DiscHandshakeState::CompletedWithFinalMessageReceived,
+          discovery_db,
         );
 
         // Remove the stored reply message so it won't be resent
@@ -1048,9 +1294,14 @@ impl SecureDiscovery {
         );
       }
       Err(e) => {
-        error!(
+        security_log!(
+          self.security_logger,
+          LoggingLevel::Error,
+          msgid::HANDSHAKE_FAILED,
+          Some(remote_guid_prefix),
           "Validating final handshake message failed. Error: {}. Remote guid prefix: {:?}",
-          e, remote_guid_prefix
+          e,
+          remote_guid_prefix
         );
         // Reset stored message resend counter, so our resends can't be depleted by
         // sending us incorrect messages
@@ -1107,7 +1358,11 @@ impl SecureDiscovery {
             remote_participant_guidp
           );
         } else {
-          info!(
+          security_log!(
+            self.security_logger,
+            LoggingLevel::Informational,
+            msgid::CRYPTO_TOKENS_RECEIVED,
+            Some(remote_participant_guidp),
             "Set crypto tokens for remote participant {:?}",
             remote_participant_guidp
           );
@@ -1133,7 +1388,11 @@ impl SecureDiscovery {
             msg.generic.source_endpoint_guid
           );
         } else {
-          info!(
+          security_log!(
+            self.security_logger,
+            LoggingLevel::Informational,
+            msgid::CRYPTO_TOKENS_RECEIVED,
+            Some(msg.generic.source_endpoint_guid.prefix),
             "Set crypto tokens for remote writer {:?}",
             msg.generic.source_endpoint_guid
           );
@@ -1159,7 +1418,11 @@ impl SecureDiscovery {
             msg.generic.source_endpoint_guid
           );
         } else {
-          info!(
+          security_log!(
+            self.security_logger,
+            LoggingLevel::Informational,
+            msgid::CRYPTO_TOKENS_RECEIVED,
+            Some(msg.generic.source_endpoint_guid.prefix),
             "Set crypto tokens for remote reader {:?}",
             msg.generic.source_endpoint_guid
           );
@@ -1178,6 +1441,10 @@ impl SecureDiscovery {
     discovery_updated_sender: &mio_channel::SyncSender<DiscoveryNotificationType>,
   ) {
     security_log!(
+      self.security_logger,
+      LoggingLevel::Notice,
+      msgid::HANDSHAKE_COMPLETED,
+      Some(remote_guid_prefix),
       "Authenticated participant with GUID prefix {:?}",
       remote_guid_prefix
     );
@@ -1194,6 +1461,10 @@ impl SecureDiscovery {
       }
       Err(e) => {
         security_log!(
+          self.security_logger,
+          LoggingLevel::Error,
+          msgid::PERMISSION_DENIED,
+          Some(remote_guid_prefix),
           "Validating permissions for remote failed: {}. Rejecting the remote. Guid prefix: {:?}",
           e,
           remote_guid_prefix
@@ -1219,6 +1490,10 @@ impl SecureDiscovery {
         }
         Err(e) => {
           security_log!(
+            self.security_logger,
+            LoggingLevel::Error,
+            msgid::PERMISSION_DENIED,
+            Some(remote_guid_prefix),
             "Remote participant is not allowed to join the domain: {}. Rejecting the remote. Guid \
              prefix: {:?}",
             e,
@@ -1366,17 +1641,7 @@ impl SecureDiscovery {
       })
       // Send with writer
       .and_then(|vol_msg| {
-        let opts = WriteOptionsBuilder::new()
-          .to_single_reader(GUID::new(
-            remote_guid_prefix,
-            EntityId::P2P_BUILTIN_PARTICIPANT_VOLATILE_SECURE_READER,
-          ))
-          .build();
-        key_exchange_writer
-          .write_with_options(vol_msg, opts)
-          .map_err(|write_err| {
-            security_error(&format!("DataWriter write operation failed: {}", write_err))
-          })
+        self.send_volatile_message(remote_guid_prefix, vol_msg, key_exchange_writer)
       });
 
     if let Err(e) = res {
@@ -1386,7 +1651,14 @@ impl SecureDiscovery {
         remote_guid_prefix
       );
     } else {
-      info!("Sent participant crypto tokens to {:?}", remote_guid_prefix);
+      security_log!(
+        self.security_logger,
+        LoggingLevel::Informational,
+        msgid::CRYPTO_TOKENS_SENT,
+        Some(remote_guid_prefix),
+        "Sent participant crypto tokens to {:?}",
+        remote_guid_prefix
+      );
     }
 
     // Register the rest of the remote's secure built-in readers
@@ -1433,17 +1705,7 @@ impl SecureDiscovery {
           })
           // Send with writer
           .and_then(|vol_msg| {
-            let opts = WriteOptionsBuilder::new()
-              .to_single_reader(GUID::new(
-                remote_guid_prefix,
-                EntityId::P2P_BUILTIN_PARTICIPANT_VOLATILE_SECURE_READER,
-              ))
-              .build();
-            key_exchange_writer
-              .write_with_options(vol_msg, opts)
-              .map_err(|write_err| {
-                security_error(&format!("DataWriter write operation failed: {}", write_err))
-              })
+            self.send_volatile_message(remote_guid_prefix, vol_msg, key_exchange_writer)
           });
 
         if let Err(e) = res {
@@ -1453,7 +1715,11 @@ impl SecureDiscovery {
             remote_reader_guid
           );
         } else {
-          info!(
+          security_log!(
+            self.security_logger,
+            LoggingLevel::Informational,
+            msgid::CRYPTO_TOKENS_SENT,
+            Some(remote_guid_prefix),
             "Sent local writer crypto tokens to {:?}",
             remote_reader_guid
           );
@@ -1506,17 +1772,7 @@ impl SecureDiscovery {
           })
           // Send with writer
           .and_then(|vol_msg| {
-            let opts = WriteOptionsBuilder::new()
-              .to_single_reader(GUID::new(
-                remote_guid_prefix,
-                EntityId::P2P_BUILTIN_PARTICIPANT_VOLATILE_SECURE_READER,
-              ))
-              .build();
-            key_exchange_writer
-              .write_with_options(vol_msg, opts)
-              .map_err(|write_err| {
-                security_error(&format!("DataWriter write operation failed: {}", write_err))
-              })
+            self.send_volatile_message(remote_guid_prefix, vol_msg, key_exchange_writer)
           });
 
         if let Err(e) = res {
@@ -1526,13 +1782,139 @@ impl SecureDiscovery {
             remote_writer_guid
           );
         } else {
-          info!(
+          security_log!(
+            self.security_logger,
+            LoggingLevel::Informational,
+            msgid::CRYPTO_TOKENS_SENT,
+            Some(remote_guid_prefix),
             "Sent local reader crypto tokens to {:?}",
             remote_writer_guid
           );
         }
       }
     }
+
+    // Now that the remote is authenticated, check that it agrees with us on
+    // topic protection
+    self.check_remote_endpoints_protection(remote_guid_prefix, discovery_db);
+  }
+
+  // Compares the protection that the endpoints of a remote participant
+  // advertise with what our governance expects on the same topics. If they
+  // disagree, e.g. one side encrypts and the other expects plaintext, the
+  // endpoints cannot communicate, so this is reported as a security event.
+  // Only authenticated remotes and topics with local endpoints are checked.
+  // Remote endpoints are announced periodically, so endpoints created later on
+  // either side get checked, too.
+  pub fn check_remote_endpoints_protection(
+    &mut self,
+    remote_guid_prefix: GuidPrefix,
+    discovery_db: &Arc<RwLock<DiscoveryDB>>,
+  ) {
+    // (remote endpoint GUID, topic name, advertised info, is the remote a writer)
+    let remote_endpoints: Vec<(GUID, String, Option<EndpointSecurityInfo>, bool)> = {
+      let db = discovery_db_read(discovery_db);
+      if db.get_authentication_status(remote_guid_prefix)
+        != Some(AuthenticationStatus::Authenticated)
+      {
+        return;
+      }
+      let local_reader_topics: HashSet<&String> = db
+        .get_all_local_topic_readers()
+        .filter(|d| {
+          d.reader_proxy
+            .remote_reader_guid
+            .entity_id
+            .kind()
+            .is_user_defined()
+        })
+        .map(|d| d.subscription_topic_data.topic_name())
+        .collect();
+      let local_writer_topics: HashSet<&String> = db
+        .get_all_local_topic_writers()
+        .filter(|d| {
+          d.writer_proxy
+            .remote_writer_guid
+            .entity_id
+            .kind()
+            .is_user_defined()
+        })
+        .map(|d| &d.publication_topic_data.topic_name)
+        .collect();
+
+      let remote_writers = db
+        .remote_topic_writers()
+        .filter(|d| {
+          d.writer_proxy.remote_writer_guid.prefix == remote_guid_prefix
+            && local_reader_topics.contains(&d.publication_topic_data.topic_name)
+        })
+        .map(|d| {
+          (
+            d.writer_proxy.remote_writer_guid,
+            d.publication_topic_data.topic_name.clone(),
+            d.publication_topic_data.security_info.clone(),
+            true,
+          )
+        });
+      let remote_readers = db
+        .remote_topic_readers()
+        .filter(|d| {
+          d.reader_proxy.remote_reader_guid.prefix == remote_guid_prefix
+            && local_writer_topics.contains(d.subscription_topic_data.topic_name())
+        })
+        .map(|d| {
+          (
+            d.reader_proxy.remote_reader_guid,
+            d.subscription_topic_data.topic_name().clone(),
+            d.subscription_topic_data.security_info().clone(),
+            false,
+          )
+        });
+      remote_writers.chain(remote_readers).collect()
+    }; // Release lock
+
+    for (remote_guid, topic_name, remote_info, remote_is_writer) in remote_endpoints {
+      let Some(remote_info) = remote_info else {
+        continue; // Remote does not advertise its protection. Nothing to compare.
+      };
+      // Our expectation is that of a local endpoint of the opposite kind
+      let local_attributes = if remote_is_writer {
+        self
+          .security_plugins
+          .get_plugins()
+          .get_reader_sec_attributes(self.local_participant_guid, topic_name.clone())
+      } else {
+        self
+          .security_plugins
+          .get_plugins()
+          .get_writer_sec_attributes(self.local_participant_guid, topic_name.clone())
+      }; // Release lock
+      let local_info = match local_attributes {
+        Ok(attributes) => EndpointSecurityInfo::from(attributes),
+        Err(e) => {
+          debug!("No local security attributes for topic {topic_name:?}: {e}");
+          continue;
+        }
+      };
+
+      if local_info.is_compatible_with(&remote_info) {
+        self.reported_protection_mismatches.remove(&remote_guid);
+      } else if self.reported_protection_mismatches.insert(remote_guid) {
+        security_log!(
+          self.security_logger,
+          LoggingLevel::Error,
+          msgid::PROTECTION_MISMATCH,
+          Some(remote_guid_prefix),
+          "Remote {} {:?} on topic {:?} advertises protection {:?}, but local governance expects \
+           {:?}. They cannot communicate.",
+          if remote_is_writer { "writer" } else { "reader" },
+          remote_guid,
+          topic_name,
+          remote_info,
+          local_info
+        );
+      }
+    }
   }
 
   fn validate_remote_participant_permissions(
@@ -1615,8 +1997,14 @@ impl SecureDiscovery {
     self.handshake_states.get(remote_guid_prefix).copied()
   }
 
-  fn update_handshake_state(&mut self, remote_guid_prefix: GuidPrefix, state: DiscHandshakeState) {
+  fn update_handshake_state(
+    &mut self,
+    remote_guid_prefix: GuidPrefix,
+    state: DiscHandshakeState,
+    discovery_db: &Arc<RwLock<DiscoveryDB>>,
+  ) {
     self.handshake_states.insert(remote_guid_prefix, state);
+    discovery_db_write(discovery_db).update_handshake_state(remote_guid_prefix, state);
   }
 
   fn get_serialized_local_participant_data(
@@ -1653,6 +2041,86 @@ impl SecureDiscovery {
     ParticipantStatelessMessage::from(generic_message)
   }
 
+  // Sends a crypto token message to the volatile secure reader of the remote.
+  // The message is kept for resending until the remote acknowledges it, also
+  // if writing fails.
+  fn send_volatile_message(
+    &mut self,
+    remote_guid_prefix: GuidPrefix,
+    message: ParticipantVolatileMessageSecure,
+    key_exchange_writer: &no_key::DataWriter<ParticipantVolatileMessageSecure>,
+  ) -> SecurityResult<()> {
+    #[cfg(test)]
+    if lose_volatile_message(self.domain_id) {
+      // Not written at all, as if lost on the way. Resending has to recover.
+      self.unacknowledged_volatile_messages.insert(
+        remote_guid_prefix,
+        message,
+        None,
+        Instant::now(),
+      );
+      return Ok(());
+    }
+    let result = write_volatile_message(remote_guid_prefix, message.clone(), key_exchange_writer);
+    self.unacknowledged_volatile_messages.insert(
+      remote_guid_prefix,
+      message,
+      result.as_ref().ok().copied(),
+      Instant::now(),
+    );
+    result.map(|_| ())
+  }
+
+  pub fn resend_unacknowledged_volatile_messages(
+    &mut self,
+    key_exchange_writer: &no_key::DataWriter<ParticipantVolatileMessageSecure>,
+  ) {
+    for remote_guid_prefix in self.unacknowledged_volatile_messages.remotes() {
+      let remote_volatile_reader_guid = GUID::new(
+        remote_guid_prefix,
+        EntityId::P2P_BUILTIN_PARTICIPANT_VOLATILE_SECURE_READER,
+      );
+      if let Some(state) = key_exchange_writer.reader_reliability_state(remote_volatile_reader_guid)
+      {
+        self
+          .unacknowledged_volatile_messages
+          .acknowledge(remote_guid_prefix, state.highest_acked);
+      }
+    }
+
+    let now = Instant::now();
+    for (remote_guid_prefix, stored) in self.unacknowledged_volatile_messages.take_overdue(now) {
+      let sequence_number = match write_volatile_message(
+        remote_guid_prefix,
+        stored.message.clone(),
+        key_exchange_writer,
+      ) {
+        Ok(sequence_number) => {
+          debug!(
+            "Resent unacknowledged crypto tokens to remote with guid prefix {:?}. Resending at \
+             most {} more times.",
+            remote_guid_prefix,
+            stored.remaining_resend_counter - 1,
+          );
+          Some(sequence_number)
+        }
+        Err(e) => {
+          debug!(
+            "Failed to resend crypto tokens to remote with guid prefix {:?}: {}. Retrying later.",
+            remote_guid_prefix, e
+          );
+          None
+        }
+      };
+      self.unacknowledged_volatile_messages.resent(
+        remote_guid_prefix,
+        stored,
+        sequence_number,
+        now,
+      );
+    }
+  }
+
   // Create a message for the DCPSParticipantVolatileMessageSecure builtin Topic
   fn new_volatile_message(
     &mut self,
@@ -1680,6 +2148,27 @@ impl SecureDiscovery {
   }
 }
 
+// Writes a crypto token message to the volatile secure reader of the remote.
+// Returns the sequence number of the written sample.
+fn write_volatile_message(
+  remote_guid_prefix: GuidPrefix,
+  message: ParticipantVolatileMessageSecure,
+  key_exchange_writer: &no_key::DataWriter<ParticipantVolatileMessageSecure>,
+) -> SecurityResult<SequenceNumber> {
+  let opts = WriteOptionsBuilder::new()
+    .to_single_reader(GUID::new(
+      remote_guid_prefix,
+      EntityId::P2P_BUILTIN_PARTICIPANT_VOLATILE_SECURE_READER,
+    ))
+    .build();
+  key_exchange_writer
+    .write_with_options(message, opts)
+    .map(|sample_identity| sample_identity.sequence_number)
+    .map_err(|write_err| {
+      security_error(&format!("DataWriter write operation failed: {}", write_err))
+    })
+}
+
 fn send_discovery_notification(
   discovery_updated_sender: &mio_channel::SyncSender<DiscoveryNotificationType>,
   dntype: DiscoveryNotificationType,
@@ -1709,6 +2198,42 @@ fn get_handshake_token_from_stateless_message(
     .map(|data_holder| HandshakeMessageToken::from(data_holder.clone()))
 }
 
+// Remembers the highest ParticipantStatelessMessage sequence number received
+// from each remote participant, so that replayed old handshake messages can be
+// ignored. A retransmission has the same sequence number as the original, so
+// an equal sequence number is not stale.
+struct StatelessMessageReplayFilter {
+  enabled: bool,
+  highest_seqnums: HashMap<GuidPrefix, SequenceNumber>,
+}
+
+impl StatelessMessageReplayFilter {
+  fn new(enabled: bool) -> Self {
+    Self {
+      enabled,
+      highest_seqnums: HashMap::new(),
+    }
+  }
+
+  // Returns false if the message is older than one already received
+  fn accept(&mut self, message: &ParticipantStatelessMessage) -> bool {
+    if !self.enabled {
+      return true;
+    }
+    let identity = &message.generic.message_identity;
+    let highest = self
+      .highest_seqnums
+      .entry(identity.writer_guid.prefix)
+      .or_insert(identity.sequence_number);
+    if identity.sequence_number < *highest {
+      false
+    } else {
+      *highest = identity.sequence_number;
+      true
+    }
+  }
+}
+
 // A helper to construct ParticipantGenericMessages. Takes care of
 // sequence numbering the messages
 struct ParticipantGenericMessageHelper {
@@ -1771,3 +2296,174 @@ impl ParticipantGenericMessageHelper {
     }
   }
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::test::wait_until;
+
+  fn handshake_message(
+    helper: &mut ParticipantGenericMessageHelper,
+    source: GUID,
+    destination: GuidPrefix,
+  ) -> ParticipantStatelessMessage {
+    ParticipantStatelessMessage::from(helper.new_message(
+      GMCLASSID_SECURITY_AUTH_HANDSHAKE,
+      source,
+      GUID::GUID_UNKNOWN,
+      None,
+      destination,
+      GUID::GUID_UNKNOWN,
+      vec![],
+    ))
+  }
+
+  #[test]
+  fn replayed_stateless_message_is_ignored() {
+    let remote = GUID::new_participant_guid();
+    let local = GUID::new_participant_guid().prefix;
+    let mut helper = ParticipantGenericMessageHelper::new();
+    let old_message = handshake_message(&mut helper, remote, local);
+    let new_message = handshake_message(&mut helper, remote, local);
+
+    let mut filter = StatelessMessageReplayFilter::new(true);
+    assert!(filter.accept(&old_message));
+    assert!(filter.accept(&new_message));
+    // A retransmission is fine, a replay of the older message is not
+    assert!(filter.accept(&new_message));
+    assert!(!filter.accept(&old_message));
+
+    // Sequence numbers are tracked per remote participant
+    let other_remote = GUID::new_participant_guid();
+    let mut other_helper = ParticipantGenericMessageHelper::new();
+    assert!(filter.accept(&handshake_message(&mut other_helper, other_remote, local)));
+
+    let mut disabled_filter = StatelessMessageReplayFilter::new(false);
+    assert!(disabled_filter.accept(&new_message));
+    assert!(disabled_filter.accept(&old_message));
+  }
+
+  #[test]
+  fn unacknowledged_crypto_tokens_are_resent() {
+    let remote = GUID::new_participant_guid().prefix;
+    let mut helper = ParticipantGenericMessageHelper::new();
+    let message = ParticipantVolatileMessageSecure::from(helper.new_message(
+      GMCLASSID_SECURITY_PARTICIPANT_CRYPTO_TOKENS,
+      GUID::new_participant_guid(),
+      GUID::GUID_UNKNOWN,
+      None,
+      remote,
+      GUID::GUID_UNKNOWN,
+      vec![],
+    ));
+
+    let mut unacknowledged = UnacknowledgedVolatileMessages::new();
+    let sent_at = Instant::now();
+    unacknowledged.insert(remote, message, Some(SequenceNumber::new(1)), sent_at);
+    assert!(unacknowledged.take_overdue(sent_at).is_empty());
+
+    // The message was lost, so the remote acknowledges nothing, and the
+    // message is resent after the timeout
+    unacknowledged.acknowledge(remote, SequenceNumber::zero());
+    let resent_at = sent_at + VOLATILE_MESSAGE_ACK_TIMEOUT;
+    let mut overdue = unacknowledged.take_overdue(resent_at);
+    assert_eq!(overdue.len(), 1);
+    let (overdue_remote, stored) = overdue.pop().unwrap();
+    assert_eq!(overdue_remote, remote);
+    unacknowledged.resent(remote, stored, Some(SequenceNumber::new(2)), resent_at);
+
+    // The resent message is kept until the remote acknowledges it
+    unacknowledged.acknowledge(remote, SequenceNumber::new(1));
+    assert_eq!(unacknowledged.remotes(), vec![remote]);
+    unacknowledged.acknowledge(remote, SequenceNumber::new(2));
+    assert!(unacknowledged.remotes().is_empty());
+  }
+
+  #[test]
+  fn crypto_token_resending_gives_up() {
+    let remote = GUID::new_participant_guid().prefix;
+    let mut helper = ParticipantGenericMessageHelper::new();
+    let message = ParticipantVolatileMessageSecure::from(helper.new_message(
+      GMCLASSID_SECURITY_PARTICIPANT_CRYPTO_TOKENS,
+      GUID::new_participant_guid(),
+      GUID::GUID_UNKNOWN,
+      None,
+      remote,
+      GUID::GUID_UNKNOWN,
+      vec![],
+    ));
+
+    let mut unacknowledged = UnacknowledgedVolatileMessages::new();
+    let mut now = Instant::now();
+    // Writing failed, so there is no sequence number to acknowledge
+    unacknowledged.insert(remote, message, None, now);
+    for _ in 0..VOLATILE_MESSAGE_MAX_RESEND_COUNT {
+      now += VOLATILE_MESSAGE_ACK_TIMEOUT;
+      let (_, stored) = unacknowledged.take_overdue(now).pop().unwrap();
+      unacknowledged.resent(remote, stored, None, now);
+    }
+    now += VOLATILE_MESSAGE_ACK_TIMEOUT;
+    assert!(unacknowledged.take_overdue(now).is_empty());
+    assert!(unacknowledged.remotes().is_empty());
+  }
+
+  #[test]
+  fn lost_crypto_tokens_are_recovered_by_resending() {
+    use crate::{DomainParticipant, DomainParticipantBuilder};
+
+    let domain_id = 74;
+    LOSE_FIRST_SENT_VOLATILE_MESSAGES
+      .lock()
+      .unwrap()
+      .push((domain_id, 0));
+
+    // Discovery is encrypted, so the secure disposal of a participant reaches
+    // the remote only if the crypto tokens have got through.
+    let secure_participant = || -> DomainParticipant {
+      let config_dir = std::path::Path::new("example_security_configuration_files");
+      let mut builder = DomainParticipantBuilder::new(domain_id);
+      builder
+        .security_from_files(
+          config_dir.join("identity_ca_certificate.pem"),
+          config_dir.join("participant1_certificate.pem"),
+          config_dir.join("participant1_private_key.pem"),
+          config_dir.join("permissions_ca_certificate.pem"),
+          config_dir.join("protected_discovery_governance.p7s"),
+          config_dir.join("permissive_permissions.p7s"),
+        )
+        .unwrap();
+      builder.build().expect("Secure participant creation failed")
+    };
+    let dp1 = secure_participant();
+    let dp2 = secure_participant();
+    let guid1 = dp1.guid();
+    let dp1_is_known = || {
+      dp2
+        .discovered_participants()
+        .iter()
+        .any(|p| p.guid == guid1)
+    };
+    let token_messages_lost = || {
+      LOSE_FIRST_SENT_VOLATILE_MESSAGES
+        .lock()
+        .unwrap()
+        .iter()
+        .any(|(d, lost_count)| *d == domain_id && *lost_count > 0)
+    };
+
+    wait_until(Duration::from_secs(10), || {
+      dp1_is_known() && token_messages_lost()
+    });
+    assert!(dp1_is_known(), "Participant 1 was not discovered");
+    assert!(token_messages_lost(), "Key exchange did not start");
+    // Give resending time to make up for the lost messages.
+    std::thread::sleep(3 * VOLATILE_MESSAGE_ACK_TIMEOUT);
+
+    drop(dp1);
+    wait_until(Duration::from_secs(5), || !dp1_is_known());
+    assert!(
+      !dp1_is_known(),
+      "Secure disposal of participant 1 was not received"
+    );
+  }
+}
@@ -271,8 +271,11 @@ impl SubscriptionBuiltinTopicData {
       history: None, // SubscriptionBuiltinTopicData does not contain History QoS
       resource_limits: None, // nor Resource Limits, see Figure 8.30 in RTPS spec 2.5
       lifespan: self.lifespan,
+      transport_priority: None, // Not in the builtin topic data
+      writer_data_lifecycle: None,
+      reader_data_lifecycle: None,
+      entity_factory: None,
 
-      #[cfg(feature = "security")]
       property: None, // TODO: no property QoS?
     }
   }
@@ -695,7 +698,10 @@ impl PublicationBuiltinTopicData {
       history: None,         // PublicationBuiltinTopicData does not contain History QoS
       resource_limits: None, // nor Resource Limits, see Figure 8.30 in RTPS spec 2.5
       lifespan: self.lifespan,
-      #[cfg(feature = "security")]
+      transport_priority: None, // Not in the builtin topic data
+      writer_data_lifecycle: None,
+      reader_data_lifecycle: None,
+      entity_factory: None,
       property: None, // TODO: no property Qos?
     }
   }
@@ -1023,7 +1029,10 @@ impl HasQoSPolicy for TopicBuiltinTopicData {
       history: self.history,
       resource_limits: self.resource_limits,
       lifespan: self.lifespan,
-      #[cfg(feature = "security")]
+      transport_priority: None, // Not in the builtin topic data
+      writer_data_lifecycle: None,
+      reader_data_lifecycle: None,
+      entity_factory: None,
       property: None, // TODO: no property Qos?
     }
   }
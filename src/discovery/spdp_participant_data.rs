@@ -61,6 +61,10 @@ pub struct SpdpDiscoveredParticipantData {
   pub manual_liveliness_count: i32,
   pub builtin_endpoint_qos: Option<BuiltinEndpointQos>,
   pub entity_name: Option<String>,
+  /// The largest RTPS message the participant can receive, if it has
+  /// advertised a limit. Writers fragment payloads to fit. This is
+  /// RustDDS-specific, and ignored if other vendors announce it.
+  pub max_message_size: Option<u32>,
 
   // security
   #[cfg(feature = "security")]
@@ -228,6 +232,9 @@ impl SpdpDiscoveredParticipantData {
       manual_liveliness_count: 0,
       builtin_endpoint_qos: None,
       entity_name: None,
+      max_message_size: participant
+        .max_receive_message_size()
+        .map(|size| size.try_into().unwrap_or(u32::MAX)),
 
       // DDS Security
       #[cfg(feature = "security")]
@@ -242,6 +249,55 @@ impl SpdpDiscoveredParticipantData {
   }
 }
 
+/// Summary of a remote DomainParticipant, as known via Discovery.
+///
+/// This is returned by
+/// [`DomainParticipant::discovered_participants`](crate::DomainParticipant::discovered_participants).
+/// It is a snapshot: it does not get updated if the remote participant
+/// announces new data.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiscoveredParticipantInfo {
+  pub guid: GUID,
+  pub vendor_id: VendorId,
+  pub protocol_version: ProtocolVersion,
+  pub entity_name: Option<String>,
+  pub metatraffic_unicast_locators: Vec<Locator>,
+  pub metatraffic_multicast_locators: Vec<Locator>,
+  pub default_unicast_locators: Vec<Locator>,
+  pub default_multicast_locators: Vec<Locator>,
+  pub available_builtin_endpoints: BuiltinEndpointSet,
+  pub lease_duration: Option<Duration>,
+  /// Time of the latest participant announcement received
+  pub updated_time: chrono::DateTime<Utc>,
+  /// Does the remote participant announce DDS Security information. This is
+  /// always `false` if RustDDS is compiled without the "security" feature.
+  pub security_enabled: bool,
+}
+
+impl From<&SpdpDiscoveredParticipantData> for DiscoveredParticipantInfo {
+  fn from(data: &SpdpDiscoveredParticipantData) -> Self {
+    #[cfg(feature = "security")]
+    let security_enabled = data.security_info.is_some();
+    #[cfg(not(feature = "security"))]
+    let security_enabled = false;
+
+    Self {
+      guid: data.participant_guid,
+      vendor_id: data.vendor_id,
+      protocol_version: data.protocol_version,
+      entity_name: data.entity_name.clone(),
+      metatraffic_unicast_locators: data.metatraffic_unicast_locators.clone(),
+      metatraffic_multicast_locators: data.metatraffic_multicast_locators.clone(),
+      default_unicast_locators: data.default_unicast_locators.clone(),
+      default_multicast_locators: data.default_multicast_locators.clone(),
+      available_builtin_endpoints: data.available_builtin_endpoints,
+      lease_duration: data.lease_duration,
+      updated_time: data.updated_time,
+      security_enabled,
+    }
+  }
+}
+
 impl PlCdrDeserialize for SpdpDiscoveredParticipantData {
   fn from_pl_cdr_bytes(
     input_bytes: &[u8],
@@ -319,6 +375,18 @@ impl PlCdrDeserialize for SpdpDiscoveredParticipantData {
       get_option_from_pl_map::< _ , StringWithNul>(&pl_map, ctx, ParameterId::PID_ENTITY_NAME, "entity name")?
       .map( String::from );
 
+    // Vendor-specific parameter ids mean different things to different vendors.
+    let max_message_size: Option<u32> = if vendor_id == VendorId::THIS_IMPLEMENTATION {
+      get_option_from_pl_map(
+        &pl_map,
+        ctx,
+        ParameterId::PID_RUSTDDS_MAX_MESSAGE_SIZE,
+        "max message size",
+      )?
+    } else {
+      None
+    };
+
     // DDS security
     #[cfg(feature = "security")]
     let identity_token: Option<IdentityToken> = get_option_from_pl_map(
@@ -365,6 +433,7 @@ impl PlCdrDeserialize for SpdpDiscoveredParticipantData {
       manual_liveliness_count,
       builtin_endpoint_qos,
       entity_name,
+      max_message_size,
       #[cfg(feature = "security")]
       identity_token,
       #[cfg(feature = "security")]
@@ -399,6 +468,7 @@ impl PlCdrSerialize for SpdpDiscoveredParticipantData {
       manual_liveliness_count,
       builtin_endpoint_qos,
       entity_name,
+      max_message_size,
 
       // DDS security
       #[cfg(feature = "security")]
@@ -483,6 +553,7 @@ impl PlCdrSerialize for SpdpDiscoveredParticipantData {
     // and does not follow CDR encoding.
     let entity_name_n: Option<StringWithNul> = entity_name.clone().map(|e| e.into());
     emit_option!(PID_ENTITY_NAME, &entity_name_n, StringWithNul);
+    emit_option!(PID_RUSTDDS_MAX_MESSAGE_SIZE, max_message_size, u32);
 
     #[cfg(feature = "security")] // DDS security
     {
@@ -635,6 +706,26 @@ mod tests {
     }
   }
 
+  #[test]
+  fn max_message_size_is_rustdds_specific() {
+    let roundtrip = |data: &SpdpDiscoveredParticipantData| {
+      let bytes = data
+        .to_pl_cdr_bytes(RepresentationIdentifier::PL_CDR_LE)
+        .unwrap();
+      SpdpDiscoveredParticipantData::from_pl_cdr_bytes(&bytes, RepresentationIdentifier::PL_CDR_LE)
+        .unwrap()
+    };
+    let mut data = spdp_participant_data().unwrap();
+    data.max_message_size = Some(1400);
+
+    data.vendor_id = VendorId::THIS_IMPLEMENTATION;
+    assert_eq!(roundtrip(&data).max_message_size, Some(1400));
+
+    // The same parameter id may mean something else to another vendor.
+    data.vendor_id = VendorId::EPROSIMA;
+    assert_eq!(roundtrip(&data).max_message_size, None);
+  }
+
   #[test]
   fn deserialize_evil_spdp_fuzz() {
     use hex_literal::hex;
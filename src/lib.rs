@@ -192,12 +192,15 @@ pub mod dds; // this is public, but not advertised
 pub mod ros2;
 /// Helpers for (De)serialization and definitions of (De)serializer adapters
 pub mod serialization;
+#[cfg(feature = "test-util")]
+pub mod test_util;
 
 // Re-exports from crate root to simplify usage
 #[doc(inline)]
 pub use dds::{
   key::{Key, Keyed},
   participant::{DomainParticipant, DomainParticipantBuilder},
+  participant_factory::DomainParticipantFactory,
   pubsub::{Publisher, Subscriber},
   qos,
   qos::{policy, QosPolicies, QosPolicyBuilder},
@@ -206,19 +209,36 @@ pub use dds::{
   statusevents::StatusEvented,
   topic::{Topic, TopicDescription, TopicKind},
   typedesc::TypeDesc,
-  with_key::{datareader::SelectByKey, WriteOptions, WriteOptionsBuilder},
+  with_key::{
+    datareader::SelectByKey, ReaderReliabilityState, WriteOptions, WriteOptionsBuilder,
+  },
 };
 /// Needed to specify serialized data representation in case it is other than
 /// CDR.
 pub use serialization::representation_identifier::RepresentationIdentifier;
 #[doc(inline)]
-pub use serialization::{
-  CDRDeserializerAdapter, CDRSerializerAdapter, CdrDeserializer, CdrSerializer,
+pub use serialization::{CDRDeserializerAdapter, CDRSerializerAdapter, CdrDeserializer, CdrSerializer};
+#[cfg(feature = "cbor")]
+#[doc(inline)]
+pub use serialization::{CborDeserializerAdapter, CborSerializerAdapter};
+#[cfg(feature = "json")]
+#[doc(inline)]
+pub use serialization::{JsonDeserializerAdapter, JsonSerializerAdapter};
+pub use network::{
+  locator_selector::{LocatorSelectionContext, LocatorSelector, OutboundMessageKind},
+  message_tap::{MessageDirection, MessageTap},
+  udp_listener::SocketReuse,
 };
 pub use structure::{
-  duration::Duration, entity::RTPSEntity, guid::GUID, sequence_number::SequenceNumber,
+  dds_cache::TopicCacheUsage,
+  duration::Duration,
+  entity::RTPSEntity,
+  guid::{GuidPrefix, GUID},
+  sequence_number::SequenceNumber,
   time::Timestamp,
 };
+#[cfg(feature = "security")]
+pub use security::authentication::authentication_builtin::DiscHandshakeState;
 // re-export from a helper crate
 /// Helper trait to compute the CDR-serialized size of data
 pub use cdr_encoding_size::CdrEncodingSize;
@@ -236,3 +256,34 @@ pub mod with_key {
 pub mod rpc {
   pub use crate::structure::rpc::*;
 }
+
+/// Types of the DDS Security builtin logging topic `DDS:Security:LogTopic`,
+/// where security events are published.
+#[cfg(feature = "security")]
+pub mod security_logging {
+  pub use crate::security::logging::{
+    builtin_logging_topic_qos, msgid, BuiltinLoggingType, LoggingLevel, NameValuePair,
+    BUILTIN_LOGGING_TOPIC_NAME, BUILTIN_LOGGING_TYPE_NAME,
+  };
+}
+
+/// Parsed DDS Security governance and permissions documents. These can be
+/// loaded and queried without creating a DomainParticipant, e.g. to inspect
+/// or validate a security configuration.
+#[cfg(feature = "security")]
+pub mod security_config {
+  pub use crate::security::{
+    access_control::access_control_builtin::{
+      domain_governance_document::{
+        BasicProtectionKind, DomainGovernanceDocument as GovernanceConfig, DomainRule,
+        ProtectionKind, TopicRule,
+      },
+      domain_participant_permissions_document::{
+        Action, AllowOrDeny, Criterion, DataTag, DomainIds,
+        DomainParticipantPermissions as PermissionsConfig, Grant, Rule,
+      },
+    },
+    certificate::DistinguishedName,
+    config::ConfigError,
+  };
+}
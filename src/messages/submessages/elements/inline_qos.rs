@@ -10,7 +10,10 @@ use crate::{
   dds::key::KeyHash,
   messages::submessages::elements::{parameter_list::ParameterList, RepresentationIdentifier},
   serialization::{pl_cdr_adapters::PlCdrDeserializeError, speedy_pl_cdr_helpers::*},
-  structure::{cache_change::ChangeKind, parameter_id::ParameterId, rpc::SampleIdentity},
+  structure::{
+    cache_change::ChangeKind, duration::Duration, parameter_id::ParameterId, rpc::SampleIdentity,
+    sequence_number::SequenceNumber,
+  },
 };
 #[cfg(test)]
 use crate::{
@@ -33,7 +36,14 @@ impl InlineQos {
       .parameters
       .iter()
       .find(|p| p.parameter_id == ParameterId::PID_STATUS_INFO);
-    let ctx = pl_cdr_rep_id_to_speedy_d(rep_id)?;
+    // The representation identifier comes from the submessage flags, so it is
+    // plain CDR. StatusInfo is an array of octets, so only the byte order
+    // matters, and not even that.
+    let ctx = match rep_id {
+      RepresentationIdentifier::CDR_LE => Endianness::LittleEndian,
+      RepresentationIdentifier::CDR_BE => Endianness::BigEndian,
+      other => pl_cdr_rep_id_to_speedy_d(other)?,
+    };
 
     let status_info = match status_info {
       Some(p) => StatusInfo::read_from_buffer_with_ctx(ctx, &p.value)?,
@@ -81,6 +91,66 @@ impl InlineQos {
       None => None,
     })
   }
+
+  // RTPS spec v2.5 Section "8.7.5 Group Ordered Access and Coherent Sets":
+  // The value is the sequence number of the first sample in the coherent set.
+  // SEQUENCENUMBER_UNKNOWN marks the end of a coherent set.
+  pub fn coherent_set(
+    params: &ParameterList,
+    representation_id: RepresentationIdentifier,
+  ) -> Result<Option<SequenceNumber>, PlCdrDeserializeError> {
+    let coherent_set = params
+      .parameters
+      .iter()
+      .find(|p| p.parameter_id == ParameterId::PID_COHERENT_SET);
+
+    let endianness = match representation_id {
+      RepresentationIdentifier::PL_CDR_LE | RepresentationIdentifier::CDR_LE => {
+        Endianness::LittleEndian
+      }
+      RepresentationIdentifier::PL_CDR_BE | RepresentationIdentifier::CDR_BE => {
+        Endianness::BigEndian
+      }
+      _ => Err(PlCdrDeserializeError::NotSupported(
+        "Unknown encoding, expected PL_CDR".to_string(),
+      ))?,
+    };
+
+    Ok(match coherent_set {
+      Some(p) => Some(SequenceNumber::read_from_buffer_with_ctx(
+        endianness, &p.value,
+      )?),
+      None => None,
+    })
+  }
+
+  // LIFESPAN of the sample, to be counted from its source timestamp.
+  pub fn lifespan(
+    params: &ParameterList,
+    representation_id: RepresentationIdentifier,
+  ) -> Result<Option<Duration>, PlCdrDeserializeError> {
+    let lifespan = params
+      .parameters
+      .iter()
+      .find(|p| p.parameter_id == ParameterId::PID_LIFESPAN);
+
+    let endianness = match representation_id {
+      RepresentationIdentifier::PL_CDR_LE | RepresentationIdentifier::CDR_LE => {
+        Endianness::LittleEndian
+      }
+      RepresentationIdentifier::PL_CDR_BE | RepresentationIdentifier::CDR_BE => {
+        Endianness::BigEndian
+      }
+      _ => Err(PlCdrDeserializeError::NotSupported(
+        "Unknown encoding, expected PL_CDR".to_string(),
+      ))?,
+    };
+
+    Ok(match lifespan {
+      Some(p) => Some(Duration::read_from_buffer_with_ctx(endianness, &p.value)?),
+      None => None,
+    })
+  }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
@@ -191,6 +261,7 @@ mod tests {
   use byteorder::{BigEndian, LittleEndian};
 
   use super::*;
+  use crate::messages::submessages::elements::parameter::Parameter;
 
   #[test]
   fn inline_qos_status_info() {
@@ -234,4 +305,26 @@ mod tests {
       }
     );
   }
+
+  #[test]
+  fn inline_qos_status_info_with_cdr_representation() {
+    // The representation identifier of a DATA submessage is derived from its
+    // flags, so it is CDR_LE or CDR_BE.
+    let params = ParameterList {
+      parameters: vec![Parameter::create_pid_status_info_parameter(
+        false, true, false,
+      )],
+    };
+    for rep_id in [
+      RepresentationIdentifier::CDR_LE,
+      RepresentationIdentifier::CDR_BE,
+    ] {
+      assert_eq!(
+        InlineQos::status_info(&params, rep_id)
+          .unwrap()
+          .change_kind(),
+        ChangeKind::NotAliveUnregistered
+      );
+    }
+  }
 }
@@ -80,16 +80,23 @@ pub enum SecuritySubmessage {
 // 1) we cannot implement Writable for *Flags defined using enumflags2, as they
 // are foreign types (coherence rules) 2) Writer should not use any enum variant
 // tag in this type, as we have SubmessageHeader already.
+// Unlike other submessages, these are written in the endianness of their flags,
+// because the Security spec requires BigEndian regardless of the message.
 #[cfg(feature = "security")]
 impl<C: Context> Writable<C> for SecuritySubmessage {
   fn write_to<T: ?Sized + Writer<C>>(&self, writer: &mut T) -> Result<(), C::Error> {
-    match self {
-      SecuritySubmessage::SecureBody(s, _f) => writer.write_value(s),
-      SecuritySubmessage::SecurePrefix(s, _f) => writer.write_value(s),
-      SecuritySubmessage::SecurePostfix(s, _f) => writer.write_value(s),
-      SecuritySubmessage::SecureRTPSPrefix(s, _f) => writer.write_value(s),
-      SecuritySubmessage::SecureRTPSPostfix(s, _f) => writer.write_value(s),
-    }
+    let bytes = match self {
+      SecuritySubmessage::SecureBody(s, f) => s.write_to_vec_with_ctx(endianness_flag(f.bits())),
+      SecuritySubmessage::SecurePrefix(s, f) => s.write_to_vec_with_ctx(endianness_flag(f.bits())),
+      SecuritySubmessage::SecurePostfix(s, f) => s.write_to_vec_with_ctx(endianness_flag(f.bits())),
+      SecuritySubmessage::SecureRTPSPrefix(s, f) => {
+        s.write_to_vec_with_ctx(endianness_flag(f.bits()))
+      }
+      SecuritySubmessage::SecureRTPSPostfix(s, f) => {
+        s.write_to_vec_with_ctx(endianness_flag(f.bits()))
+      }
+    }?;
+    writer.write_bytes(&bytes)
   }
 }
 
@@ -123,7 +130,6 @@ impl<C: Context> Writable<C> for InterpreterSubmessage {
 #[derive(Debug)]
 pub enum AckSubmessage {
   AckNack(AckNack),
-  #[allow(dead_code)] // Functionality not yet implemented
   NackFrag(NackFrag),
 }
 
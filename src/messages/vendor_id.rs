@@ -10,6 +10,31 @@ impl VendorId {
     vendor_id: [0x00; 2],
   };
 
+  /// RTI Connext DDS
+  pub const RTI_CONNEXT: Self = Self {
+    vendor_id: [0x01, 0x01],
+  };
+
+  /// ADLINK OpenSplice DDS
+  pub const OPENSPLICE: Self = Self {
+    vendor_id: [0x01, 0x02],
+  };
+
+  /// OCI OpenDDS
+  pub const OPENDDS: Self = Self {
+    vendor_id: [0x01, 0x03],
+  };
+
+  /// eProsima Fast DDS
+  pub const EPROSIMA: Self = Self {
+    vendor_id: [0x01, 0x0F],
+  };
+
+  /// Eclipse Cyclone DDS
+  pub const CYCLONE: Self = Self {
+    vendor_id: [0x01, 0x10],
+  };
+
   /// assigned by OMG DDS SIG on 2020-11-21
   pub const ATOSTEK: Self = Self {
     vendor_id: [0x01, 0x12],
@@ -20,6 +45,19 @@ impl VendorId {
   pub fn as_bytes(&self) -> [u8; 2] {
     self.vendor_id
   }
+
+  /// Name of the DDS implementation, if the vendor id is one of the above
+  pub fn vendor_name(&self) -> Option<&'static str> {
+    match *self {
+      Self::RTI_CONNEXT => Some("RTI Connext DDS"),
+      Self::OPENSPLICE => Some("OpenSplice DDS"),
+      Self::OPENDDS => Some("OpenDDS"),
+      Self::EPROSIMA => Some("Fast DDS"),
+      Self::CYCLONE => Some("Cyclone DDS"),
+      Self::ATOSTEK => Some("RustDDS"),
+      _ => None,
+    }
+  }
 }
 
 impl Default for VendorId {
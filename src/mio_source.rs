@@ -4,7 +4,7 @@ use std::{
   sync::Mutex,
 };
 #[cfg(not(target_os = "windows"))]
-use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+use std::os::fd::OwnedFd;
 #[cfg(target_os = "windows")]
 use std::{thread::sleep, time::Duration};
 
@@ -22,57 +22,35 @@ use mio_08::{self, *};
 // These Events carry no data.
 
 // This is the event receiver end. It is a "Source" in the terminology of mio.
-#[cfg(target_os = "windows")]
 pub struct PollEventSource {
   rec_mio_socket: Mutex<mio_08::net::TcpStream>,
 }
 
-#[cfg(not(target_os = "windows"))]
-pub struct PollEventSource {
-  #[allow(dead_code)]
-  rec_sps: SocketpairStream, // we are storing this just to keep the socket alive
-  rec_mio_socket: Mutex<mio_08::net::TcpStream>,
-}
-
-// TODO: How to store the socket so that it is correctly tracked and dropped
-// when these PolEventSource /-Sender are dropped. Can we do that without
-// unsafe?
-
-#[cfg(target_os = "windows")]
-pub struct PollEventSender {
-  send_mio_socket: Mutex<mio_08::net::TcpStream>,
-}
-
-#[cfg(not(target_os = "windows"))]
+// This is the event sender end.
 pub struct PollEventSender {
-  #[allow(dead_code)]
-  send_sps: SocketpairStream,
   send_mio_socket: Mutex<mio_08::net::TcpStream>,
 }
 
+// Converts one end of the socketpair into a non-blocking mio socket.
+// The mio socket takes ownership of the file descriptor, so it is closed
+// exactly once, when the PollEventSource / -Sender is dropped.
 #[cfg(not(target_os = "windows"))]
-fn set_non_blocking(s: SocketpairStream) -> io::Result<SocketpairStream> {
-  let owned_fd = OwnedFd::from(s);
-  let std_socket = std::net::TcpStream::from(owned_fd);
+fn to_mio_socket(s: SocketpairStream) -> io::Result<mio_08::net::TcpStream> {
+  let std_socket = std::net::TcpStream::from(OwnedFd::from(s));
   std_socket.set_nonblocking(true)?;
-
-  Ok(SocketpairStream::from(OwnedFd::from(std_socket)))
+  Ok(mio_08::net::TcpStream::from_std(std_socket))
 }
 
 #[cfg(not(target_os = "windows"))]
 pub fn make_poll_channel() -> io::Result<(PollEventSource, PollEventSender)> {
   let (rec_sps, send_sps) = socketpair_stream()?;
-  let rec_sps = set_non_blocking(rec_sps)?;
-  let send_sps = set_non_blocking(send_sps)?;
-  let rec_mio_socket = unsafe { mio_08::net::TcpStream::from_raw_fd(rec_sps.as_raw_fd()) };
-  let send_mio_socket = unsafe { mio_08::net::TcpStream::from_raw_fd(send_sps.as_raw_fd()) };
+  let rec_mio_socket = to_mio_socket(rec_sps)?;
+  let send_mio_socket = to_mio_socket(send_sps)?;
   Ok((
     PollEventSource {
-      rec_sps,
       rec_mio_socket: Mutex::new(rec_mio_socket),
     },
     PollEventSender {
-      send_sps,
       send_mio_socket: Mutex::new(send_mio_socket),
     },
   ))
@@ -1,4 +1,8 @@
 pub mod constant;
+pub mod locator_selector;
+pub(crate) mod message_integrity;
+pub mod message_tap;
+pub(crate) mod traffic_counters;
 pub mod udp_listener;
 pub mod udp_sender;
 pub mod util;
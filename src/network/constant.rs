@@ -1,3 +1,5 @@
+use std::net::Ipv4Addr;
+
 // These constants are from RTPS spec Section 9.6.2.3 Default Port Numbers
 const PB: u16 = 7400;
 const DG: u16 = 250;
@@ -23,3 +25,7 @@ pub fn user_traffic_multicast_port(domain_id: u16) -> u16 {
 pub fn user_traffic_unicast_port(domain_id: u16, participant_id: u16) -> u16 {
   PB + DG * domain_id + D3 + PG * participant_id
 }
+
+// Default multicast group for both discovery and user traffic. This is the
+// group from RTPS spec Section 9.6.1.4.1 Default Settings.
+pub const DEFAULT_MULTICAST_GROUP: Ipv4Addr = Ipv4Addr::new(239, 255, 0, 1);
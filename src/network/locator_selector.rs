@@ -0,0 +1,69 @@
+use crate::structure::{guid::GUID, locator::Locator};
+
+/// Kind of message for which a [`LocatorSelector`] chooses destinations.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutboundMessageKind {
+  /// DATA, DATA_FRAG, GAP or HEARTBEAT from a local writer to a remote reader
+  WriterToReader,
+  /// ACKNACK or NACK_FRAG from a local reader to a remote writer
+  ReaderToWriter,
+}
+
+/// What a message is and where it is going, given to a [`LocatorSelector`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LocatorSelectionContext {
+  pub kind: OutboundMessageKind,
+  /// The local reader or writer sending the message
+  pub local_guid: GUID,
+  /// The remote reader or writer the message is for
+  pub remote_guid: GUID,
+}
+
+impl LocatorSelectionContext {
+  // Only traffic between user-defined endpoints may be rerouted.
+  pub(crate) fn is_user_traffic(&self) -> bool {
+    self.local_guid.entity_id.entity_kind.is_user_defined()
+      && self.remote_guid.entity_id.entity_kind.is_user_defined()
+  }
+}
+
+/// A hook that decides where an outbound RTPS message is actually sent, e.g.
+/// for policy-based routing or failover.
+///
+/// The selector is given the locators that the standard selection picked from
+/// the locators advertised by the remote endpoint, and returns the locators
+/// to send to instead. Returning the given locators unchanged is the default
+/// behaviour. Returning an empty list drops the message for that endpoint, in
+/// which case reliable writers and readers recover as from a lost message.
+///
+/// The selector is consulted only for messages between user-defined readers
+/// and writers. Discovery and all security traffic, such as authentication
+/// handshakes and key exchange, use builtin endpoints and are always sent to
+/// the advertised locators. With security enabled, the messages are protected
+/// for the remote participant before locators are selected, so rerouting
+/// cannot expose them to anyone else.
+///
+/// The selector is called from the event loop thread of the DomainParticipant
+/// for each message, so it should return quickly.
+///
+/// Closures of the right signature implement this trait.
+pub trait LocatorSelector: Send + Sync {
+  fn select_locators(
+    &self,
+    context: &LocatorSelectionContext,
+    locators: &[Locator],
+  ) -> Vec<Locator>;
+}
+
+impl<F> LocatorSelector for F
+where
+  F: Fn(&LocatorSelectionContext, &[Locator]) -> Vec<Locator> + Send + Sync,
+{
+  fn select_locators(
+    &self,
+    context: &LocatorSelectionContext,
+    locators: &[Locator],
+  ) -> Vec<Locator> {
+    self(context, locators)
+  }
+}
@@ -0,0 +1,107 @@
+// Lightweight integrity check of RTPS messages, independent of DDS Security.
+//
+// A vendor-specific submessage carrying a CRC-32C of all the preceding bytes
+// of the message is appended as the last submessage of each outgoing message.
+// This detects corruption on the way, but it is not a cryptographic MAC, so
+// it does not protect against deliberate tampering by anyone who knows the
+// scheme. Use DDS Security for that.
+//
+// Receivers that do not check integrity skip the trailer, since vendor-specific
+// submessages are ignored by default.
+
+use bytes::Bytes;
+
+const RTPS_MESSAGE_HEADER_SIZE: usize = 20;
+// Vendor-specific submessage kinds are 0x80 - 0xFF.
+const INTEGRITY_SUBMESSAGE_KIND: u8 = 0x8c;
+// Only the endianness flag is set: content is little-endian.
+const INTEGRITY_SUBMESSAGE_FLAGS: u8 = 0x01;
+const CHECKSUM_LEN: usize = 4;
+// Submessage header and the checksum
+pub(crate) const INTEGRITY_TRAILER_LEN: usize = 4 + CHECKSUM_LEN;
+
+const fn crc32c_table() -> [u32; 256] {
+  let mut table = [0u32; 256];
+  let mut i = 0;
+  while i < 256 {
+    let mut crc = i as u32;
+    let mut bit = 0;
+    while bit < 8 {
+      crc = if crc & 1 != 0 {
+        (crc >> 1) ^ 0x82f6_3b78 // reversed Castagnoli polynomial
+      } else {
+        crc >> 1
+      };
+      bit += 1;
+    }
+    table[i] = crc;
+    i += 1;
+  }
+  table
+}
+
+const CRC32C_TABLE: [u32; 256] = crc32c_table();
+
+fn crc32c(bytes: &[u8]) -> u32 {
+  !bytes.iter().fold(!0u32, |crc, b| {
+    CRC32C_TABLE[((crc ^ u32::from(*b)) & 0xff) as usize] ^ (crc >> 8)
+  })
+}
+
+// Returns a copy of the message with the integrity submessage appended.
+pub(crate) fn append_integrity_trailer(message: &[u8]) -> Vec<u8> {
+  let mut protected = Vec::with_capacity(message.len() + INTEGRITY_TRAILER_LEN);
+  protected.extend_from_slice(message);
+  protected.push(INTEGRITY_SUBMESSAGE_KIND);
+  protected.push(INTEGRITY_SUBMESSAGE_FLAGS);
+  protected.extend_from_slice(&(CHECKSUM_LEN as u16).to_le_bytes());
+  let checksum = crc32c(&protected);
+  protected.extend_from_slice(&checksum.to_le_bytes());
+  protected
+}
+
+// Checks the integrity submessage at the end of the message. Returns the
+// message without it, or None if the trailer is missing or does not match.
+pub(crate) fn verify_and_strip_integrity_trailer(message: &Bytes) -> Option<Bytes> {
+  if message.len() < RTPS_MESSAGE_HEADER_SIZE + INTEGRITY_TRAILER_LEN {
+    return None;
+  }
+  let (covered, checksum) = message.split_at(message.len() - CHECKSUM_LEN);
+  let trailer_header = &covered[covered.len() - (INTEGRITY_TRAILER_LEN - CHECKSUM_LEN)..];
+  let mut expected_header = [INTEGRITY_SUBMESSAGE_KIND, INTEGRITY_SUBMESSAGE_FLAGS, 0, 0];
+  expected_header[2..].copy_from_slice(&(CHECKSUM_LEN as u16).to_le_bytes());
+  if trailer_header != expected_header || checksum != crc32c(covered).to_le_bytes() {
+    return None;
+  }
+  Some(message.slice(..message.len() - INTEGRITY_TRAILER_LEN))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn crc32c_check_value() {
+    // Standard check value of CRC-32C
+    assert_eq!(crc32c(b"123456789"), 0xe306_9283);
+  }
+
+  #[test]
+  fn trailer_round_trip() {
+    let message = [
+      b'R', b'T', b'P', b'S', 2, 4, 1, 15, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12,
+    ];
+    let protected = Bytes::from(append_integrity_trailer(&message));
+    assert_eq!(protected.len(), message.len() + INTEGRITY_TRAILER_LEN);
+    assert_eq!(
+      verify_and_strip_integrity_trailer(&protected),
+      Some(Bytes::copy_from_slice(&message))
+    );
+
+    // Missing trailer
+    assert_eq!(
+      verify_and_strip_integrity_trailer(&Bytes::copy_from_slice(&message)),
+      None
+    );
+  }
+}
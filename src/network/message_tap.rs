@@ -0,0 +1,36 @@
+use std::net::SocketAddr;
+
+/// Direction of an RTPS message seen by a [`MessageTap`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MessageDirection {
+  /// Received from `peer`
+  Inbound,
+  /// Sent to `peer`
+  Outbound,
+}
+
+/// An observer of all RTPS messages that a DomainParticipant sends and
+/// receives over UDP, e.g. for protocol debugging.
+///
+/// The tap is called with the raw bytes of each message, as received from or
+/// sent to the network. Inbound messages are seen before they are parsed, so
+/// also malformed messages are included. Outbound messages are seen only if
+/// sending succeeded. A multicast message is seen once per interface it is
+/// sent on.
+///
+/// The tap is called from the event loop thread of the DomainParticipant, so
+/// it should return quickly. Copy the bytes if they are needed afterwards.
+///
+/// Closures of the right signature implement this trait.
+pub trait MessageTap: Send + Sync {
+  fn on_message(&self, direction: MessageDirection, peer: SocketAddr, message: &[u8]);
+}
+
+impl<F> MessageTap for F
+where
+  F: Fn(MessageDirection, SocketAddr, &[u8]) + Send + Sync,
+{
+  fn on_message(&self, direction: MessageDirection, peer: SocketAddr, message: &[u8]) {
+    self(direction, peer, message);
+  }
+}
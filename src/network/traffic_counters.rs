@@ -0,0 +1,65 @@
+use std::{
+  collections::BTreeMap,
+  sync::{
+    atomic::{AtomicU64, Ordering},
+    Mutex,
+  },
+};
+
+// Participant-wide counters of RTPS traffic, shared between the event loop,
+// which counts, and the DomainParticipant, which reports them.
+#[derive(Debug, Default)]
+pub(crate) struct TrafficCounters {
+  messages_sent: AtomicU64,
+  bytes_sent: AtomicU64,
+  messages_received: AtomicU64,
+  bytes_received: AtomicU64,
+  // Samples sent again by Writers on request of a Reader, or after a failed
+  // send, by topic name. These are rare compared to messages, so a lock is
+  // fine.
+  retransmissions: Mutex<BTreeMap<String, u64>>,
+}
+
+impl TrafficCounters {
+  pub fn count_sent(&self, bytes: usize) {
+    self.messages_sent.fetch_add(1, Ordering::Relaxed);
+    self.bytes_sent.fetch_add(bytes as u64, Ordering::Relaxed);
+  }
+
+  pub fn count_received(&self, bytes: usize) {
+    self.messages_received.fetch_add(1, Ordering::Relaxed);
+    self
+      .bytes_received
+      .fetch_add(bytes as u64, Ordering::Relaxed);
+  }
+
+  pub fn count_retransmission(&self, topic_name: &str) {
+    let mut retransmissions = self.retransmissions.lock().unwrap();
+    match retransmissions.get_mut(topic_name) {
+      Some(count) => *count += 1,
+      None => {
+        retransmissions.insert(topic_name.to_string(), 1);
+      }
+    }
+  }
+
+  pub fn messages_sent(&self) -> u64 {
+    self.messages_sent.load(Ordering::Relaxed)
+  }
+
+  pub fn bytes_sent(&self) -> u64 {
+    self.bytes_sent.load(Ordering::Relaxed)
+  }
+
+  pub fn messages_received(&self) -> u64 {
+    self.messages_received.load(Ordering::Relaxed)
+  }
+
+  pub fn bytes_received(&self) -> u64 {
+    self.bytes_received.load(Ordering::Relaxed)
+  }
+
+  pub fn retransmissions(&self) -> BTreeMap<String, u64> {
+    self.retransmissions.lock().unwrap().clone()
+  }
+}
@@ -18,6 +18,50 @@ const MAX_MESSAGE_SIZE: usize = 64 * 1024; // This is max we can get from UDP.
 const MESSAGE_BUFFER_ALLOCATION_CHUNK: usize = 256 * 1024; // must be >= MAX_MESSAGE_SIZE
 static_assertions::const_assert!(MESSAGE_BUFFER_ALLOCATION_CHUNK > MAX_MESSAGE_SIZE);
 
+/// Address reuse options for the listening UDP sockets of a
+/// DomainParticipant. These are set before binding the socket.
+///
+/// With `reuse_address` (`SO_REUSEADDR`) or `reuse_port` (`SO_REUSEPORT`),
+/// several sockets on the host may bind the same port. This is required for
+/// the multicast listeners, so that all DomainParticipants on the host receive
+/// the multicast traffic of their domain. On the unicast listeners it is
+/// usually harmful: the ParticipantId is chosen by finding a free unicast
+/// discovery port, so DomainParticipants with reuse enabled may end up sharing
+/// a port and stealing each other's traffic.
+///
+/// Rebinding a UDP port right after the previous socket was closed works
+/// without these options on Linux and macOS, since UDP has no TIME_WAIT
+/// state. Reuse may still be needed e.g. when a restarted container process
+/// races with a lingering one holding the port.
+///
+/// `reuse_port` is not available on Windows, Solaris, and illumos, and is
+/// ignored there. On Windows, `SO_REUSEADDR` allows any process to take over
+/// the port, so enabling it for unicast is not recommended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SocketReuse {
+  pub reuse_address: bool,
+  pub reuse_port: bool,
+}
+
+impl SocketReuse {
+  /// No address reuse. This is the default for unicast listeners.
+  pub const NONE: Self = Self {
+    reuse_address: false,
+    reuse_port: false,
+  };
+
+  /// Reuse the address, and the port where the platform supports it. This is
+  /// the default for multicast listeners.
+  pub const ALL: Self = Self {
+    reuse_address: true,
+    reuse_port: cfg!(not(any(
+      target_os = "solaris",
+      target_os = "illumos",
+      windows
+    ))),
+  };
+}
+
 /// Listens to messages coming to specified host port combination.
 /// Only messages from added listen addressed are read when get_all_messages is
 /// called.
@@ -45,25 +89,31 @@ impl UDPListener {
   fn new_listening_socket(
     host: &str,
     port: u16,
-    reuse_addr: bool,
+    reuse: SocketReuse,
   ) -> io::Result<mio_06::net::UdpSocket> {
     let raw_socket = Socket::new(Domain::IPV4, Type::DGRAM, Some(Protocol::UDP))?;
 
-    // We set ReuseAddr so that other DomainParticipants on this host can
-    // bind to the same multicast address and port.
+    // Multicast listeners set ReuseAddr so that other DomainParticipants on
+    // this host can bind to the same multicast address and port.
     // To have an effect on bind, this must be done before bind call, so must be
     // done below Rust std::net::UdpSocket level.
-    if reuse_addr {
+    if reuse.reuse_address {
       raw_socket.set_reuse_address(true)?;
     }
 
     // MacOS requires this also
     #[cfg(not(any(target_os = "solaris", target_os = "illumos", windows)))]
     {
-      if reuse_addr {
+      if reuse.reuse_port {
         raw_socket.set_reuse_port(true)?;
       }
     }
+    #[cfg(any(target_os = "solaris", target_os = "illumos", windows))]
+    {
+      if reuse.reuse_port {
+        debug!("SO_REUSEPORT is not supported on this platform. Ignoring.");
+      }
+    }
 
     let address = SocketAddr::new(
       host
@@ -96,13 +146,18 @@ impl UDPListener {
     let local_port = self.socket.local_addr()?.port();
 
     match self.multicast_group {
-      Some(_ipv4_addr) => Ok(get_local_multicast_locators(local_port)),
+      Some(multicast_group) => Ok(get_local_multicast_locators(multicast_group, local_port)),
       None => Ok(get_local_unicast_locators(local_port)),
     }
   }
 
+  #[cfg(test)]
   pub fn new_unicast(host: &str, port: u16) -> io::Result<Self> {
-    let mio_socket = Self::new_listening_socket(host, port, false)?;
+    Self::new_unicast_with_reuse(host, port, SocketReuse::NONE)
+  }
+
+  pub fn new_unicast_with_reuse(host: &str, port: u16, reuse: SocketReuse) -> io::Result<Self> {
+    let mio_socket = Self::new_listening_socket(host, port, reuse)?;
 
     Ok(Self {
       socket: mio_socket,
@@ -111,7 +166,35 @@ impl UDPListener {
     })
   }
 
+  #[cfg(test)]
   pub fn new_multicast(host: &str, port: u16, multicast_group: Ipv4Addr) -> io::Result<Self> {
+    Self::new_multicast_with_reuse(host, port, multicast_group, SocketReuse::ALL)
+  }
+
+  pub fn new_multicast_with_reuse(
+    host: &str,
+    port: u16,
+    multicast_group: Ipv4Addr,
+    reuse: SocketReuse,
+  ) -> io::Result<Self> {
+    Self::new_multicast_on_interfaces(
+      host,
+      port,
+      multicast_group,
+      &get_local_multicast_ip_addrs()?,
+      reuse,
+    )
+  }
+
+  // Like new_multicast, but joins the multicast group only on the given
+  // interfaces.
+  pub fn new_multicast_on_interfaces(
+    host: &str,
+    port: u16,
+    multicast_group: Ipv4Addr,
+    interfaces: &[IpAddr],
+    reuse: SocketReuse,
+  ) -> io::Result<Self> {
     if !multicast_group.is_multicast() {
       return io::Result::Err(io::Error::new(
         io::ErrorKind::Other,
@@ -119,12 +202,12 @@ impl UDPListener {
       ));
     }
 
-    let mio_socket = Self::new_listening_socket(host, port, true)?;
+    let mio_socket = Self::new_listening_socket(host, port, reuse)?;
 
-    for multicast_if_ipaddr in get_local_multicast_ip_addrs()? {
+    for multicast_if_ipaddr in interfaces {
       match multicast_if_ipaddr {
         IpAddr::V4(a) => mio_socket
-          .join_multicast_v4(&multicast_group, &a)
+          .join_multicast_v4(&multicast_group, a)
           .unwrap_or_else(|e| {
             warn!(
               "join_multicast_v4 failed: {:?}. multicast_group [{:?}] interface [{:?}]",
@@ -174,6 +257,16 @@ impl UDPListener {
 
   /// Get all messages waiting in the socket.
   pub fn messages(&mut self) -> Vec<Bytes> {
+    self
+      .messages_with_source()
+      .into_iter()
+      .map(|(message, _source)| message)
+      .collect()
+  }
+
+  /// Get all messages waiting in the socket, and the addresses they came
+  /// from.
+  pub fn messages_with_source(&mut self) -> Vec<(Bytes, SocketAddr)> {
     let mut messages = Vec::with_capacity(4);
 
     loop {
@@ -196,8 +289,8 @@ impl UDPListener {
         "ensure_receive_buffer_capacity - {} bytes left",
         self.receive_buffer.capacity()
       );
-      let nbytes = match self.socket.recv(&mut self.receive_buffer) {
-        Ok(n) => n,
+      let (nbytes, source) = match self.socket.recv_from(&mut self.receive_buffer) {
+        Ok(received) => received,
         Err(e) => {
           self.receive_buffer.clear(); // since nothing was received
           if e.kind() == io::ErrorKind::WouldBlock {
@@ -226,7 +319,7 @@ impl UDPListener {
       // Now split away the used portion.
       let mut message = self.receive_buffer.split_to(self.receive_buffer.len());
       message.truncate(nbytes); // discard (hide) padding
-      messages.push(Bytes::from(message)); // freeze bytes and push
+      messages.push((Bytes::from(message), source)); // freeze bytes and push
     } // loop
 
     // unreachable!(); // But why does this cause a warning? (rustc 1.66.0)
@@ -299,4 +392,39 @@ mod tests {
     assert_eq!(rec_data.len(), 3);
     assert_eq!(rec_data, data);
   }
+
+  #[cfg(target_os = "linux")]
+  #[test]
+  fn udpl_socket_reuse() {
+    // Without reuse, the port can be bound only once.
+    let listener = UDPListener::new_unicast("127.0.0.1", 0).unwrap();
+    let port = listener.port();
+    let second = UDPListener::new_unicast("127.0.0.1", port);
+    assert_eq!(second.unwrap_err().kind(), io::ErrorKind::AddrInUse);
+
+    // A restart can bind the port again right away.
+    drop(listener);
+    let listener = UDPListener::new_unicast("127.0.0.1", port).unwrap();
+    drop(listener);
+
+    // With reuse set on both, they can share the port.
+    let reuse = SocketReuse {
+      reuse_address: true,
+      reuse_port: false,
+    };
+    let listener = UDPListener::new_unicast_with_reuse("127.0.0.1", port, reuse).unwrap();
+    let second = UDPListener::new_unicast_with_reuse("127.0.0.1", port, reuse).unwrap();
+    assert_eq!(listener.port(), second.port());
+
+    let reuse_port_only = SocketReuse {
+      reuse_address: false,
+      reuse_port: true,
+    };
+    let listener = UDPListener::new_unicast_with_reuse("127.0.0.1", 0, reuse_port_only).unwrap();
+    let port = listener.port();
+    let _second = UDPListener::new_unicast_with_reuse("127.0.0.1", port, reuse_port_only).unwrap();
+    // All sockets sharing the port must agree on reuse.
+    let third = UDPListener::new_unicast("127.0.0.1", port);
+    assert_eq!(third.unwrap_err().kind(), io::ErrorKind::AddrInUse);
+  }
 }
@@ -1,9 +1,14 @@
 use std::{
-  io,
+  borrow::Cow,
+  fmt, io,
   net::{IpAddr, SocketAddr},
+  sync::{
+    atomic::{AtomicU64, Ordering},
+    Arc,
+  },
 };
 #[cfg(test)]
-use std::net::Ipv4Addr;
+use std::{cell::Cell, net::Ipv4Addr};
 
 #[allow(unused_imports)]
 use log::{debug, error, info, trace, warn};
@@ -11,14 +16,70 @@ use socket2::{Domain, Protocol, SockAddr, Socket, Type};
 #[cfg(windows)]
 use local_ip_address::list_afinet_netifas;
 
-use crate::{network::util::get_local_multicast_ip_addrs, structure::locator::Locator};
+use crate::{
+  network::{
+    locator_selector::{LocatorSelectionContext, LocatorSelector},
+    message_integrity::{append_integrity_trailer, INTEGRITY_TRAILER_LEN},
+    message_tap::{MessageDirection, MessageTap},
+    traffic_counters::TrafficCounters,
+    util::get_local_multicast_ip_addrs,
+  },
+  structure::locator::Locator,
+};
+
+// ENOBUFS, i.e. no buffer space available. It has no io::ErrorKind.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+const ENOBUFS: i32 = 105;
+#[cfg(windows)]
+const ENOBUFS: i32 = 10055; // WSAENOBUFS
+#[cfg(not(any(target_os = "linux", target_os = "android", windows)))]
+const ENOBUFS: i32 = 55; // BSD family, including macOS
+
+// Tells if a send error is likely to go away by itself, so that sending again
+// shortly is worthwhile. E.g. a full socket buffer on a congested host.
+pub fn is_transient_send_error(e: &io::Error) -> bool {
+  matches!(
+    e.kind(),
+    io::ErrorKind::WouldBlock | io::ErrorKind::Interrupted
+  ) || e.raw_os_error() == Some(ENOBUFS)
+}
 
 // We need one multicast sender socket per interface
 
-#[derive(Debug)]
 pub struct UDPSender {
   unicast_socket: mio_08::net::UdpSocket,
   multicast_sockets: Vec<mio_08::net::UdpSocket>,
+  // Number of failed send calls, shared with the DomainParticipant
+  send_failures: Arc<AtomicU64>,
+  // Number of messages that Writers gave up sending after retrying, shared
+  // with the DomainParticipant
+  send_drops: Arc<AtomicU64>,
+  // Sees every message sent
+  message_tap: Option<Arc<dyn MessageTap>>,
+  // Overrides the destinations of user traffic
+  locator_selector: Option<Arc<dyn LocatorSelector>>,
+  // Append an integrity checksum to every message
+  message_integrity: bool,
+  // Counts sent messages and bytes, shared with the DomainParticipant
+  traffic_counters: Option<Arc<TrafficCounters>>,
+  // Number of upcoming sends that should fail, to test error handling
+  #[cfg(test)]
+  injected_failures: Cell<usize>,
+}
+
+impl fmt::Debug for UDPSender {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.debug_struct("UDPSender")
+      .field("unicast_socket", &self.unicast_socket)
+      .field("multicast_sockets", &self.multicast_sockets)
+      .field("send_failures", &self.send_failures)
+      .field("send_drops", &self.send_drops)
+      .field("message_tap", &self.message_tap.is_some())
+      .field("locator_selector", &self.locator_selector.is_some())
+      .field("message_integrity", &self.message_integrity)
+      .field("traffic_counters", &self.traffic_counters.is_some())
+      .finish_non_exhaustive()
+  }
 }
 
 impl UDPSender {
@@ -87,6 +148,14 @@ impl UDPSender {
     let sender = Self {
       unicast_socket,
       multicast_sockets,
+      send_failures: Arc::new(AtomicU64::new(0)),
+      send_drops: Arc::new(AtomicU64::new(0)),
+      message_tap: None,
+      locator_selector: None,
+      message_integrity: false,
+      traffic_counters: None,
+      #[cfg(test)]
+      injected_failures: Cell::new(0),
     };
     info!("UDPSender::new() --> {:?}", sender);
     Ok(sender)
@@ -97,14 +166,123 @@ impl UDPSender {
     Self::new(0)
   }
 
-  pub fn send_to_locator_list(&self, buffer: &[u8], ll: &[Locator]) {
+  // Counts failures into the given counter instead of a private one.
+  pub fn with_send_failure_counter(mut self, counter: Arc<AtomicU64>) -> Self {
+    self.send_failures = counter;
+    self
+  }
+
+  // Likewise for messages given up
+  pub fn with_send_drop_counter(mut self, counter: Arc<AtomicU64>) -> Self {
+    self.send_drops = counter;
+    self
+  }
+
+  pub fn with_message_tap(mut self, message_tap: Option<Arc<dyn MessageTap>>) -> Self {
+    self.message_tap = message_tap;
+    self
+  }
+
+  pub fn with_locator_selector(
+    mut self,
+    locator_selector: Option<Arc<dyn LocatorSelector>>,
+  ) -> Self {
+    self.locator_selector = locator_selector;
+    self
+  }
+
+  pub fn with_message_integrity(mut self, message_integrity: bool) -> Self {
+    self.message_integrity = message_integrity;
+    self
+  }
+
+  // Bytes that send_to_locator appends to each message.
+  pub fn trailer_len(&self) -> usize {
+    if self.message_integrity {
+      INTEGRITY_TRAILER_LEN
+    } else {
+      0
+    }
+  }
+
+  pub fn with_traffic_counters(mut self, traffic_counters: Option<Arc<TrafficCounters>>) -> Self {
+    self.traffic_counters = traffic_counters;
+    self
+  }
+
+  // Writers share the UDPSender, so they report their retransmissions here.
+  pub fn count_retransmission(&self, topic_name: &str) {
+    if let Some(counters) = &self.traffic_counters {
+      counters.count_retransmission(topic_name);
+    }
+  }
+
+  // Gives the locator selector, if any, a chance to replace the standard
+  // destinations of a message. Traffic of builtin endpoints is never rerouted.
+  pub fn select_locators<'a>(
+    &self,
+    context: &LocatorSelectionContext,
+    locators: &'a [Locator],
+  ) -> Cow<'a, [Locator]> {
+    match &self.locator_selector {
+      Some(selector) if context.is_user_traffic() => {
+        Cow::Owned(selector.select_locators(context, locators))
+      }
+      _ => Cow::Borrowed(locators),
+    }
+  }
+
+  // Writers call this when they stop retrying a failed send, see
+  // is_transient_send_error.
+  pub fn count_send_drop(&self) {
+    self.send_drops.fetch_add(1, Ordering::Relaxed);
+  }
+
+  #[cfg(test)]
+  pub fn send_failure_count(&self) -> u64 {
+    self.send_failures.load(Ordering::Relaxed)
+  }
+
+  #[cfg(test)]
+  pub fn send_drop_count(&self) -> u64 {
+    self.send_drops.load(Ordering::Relaxed)
+  }
+
+  // Makes the next `count` sends fail as if the socket buffer was full.
+  #[cfg(test)]
+  pub fn inject_send_failures(&self, count: usize) {
+    self.injected_failures.set(count);
+  }
+
+  // Sends to all locators, even if some fail. Returns the last error, if any.
+  pub fn send_to_locator_list(&self, buffer: &[u8], ll: &[Locator]) -> io::Result<()> {
+    let mut result = Ok(());
     for loc in ll {
-      self.send_to_locator(buffer, loc);
+      if let Err(e) = self.send_to_locator(buffer, loc) {
+        result = Err(e);
+      }
     }
+    result
   }
 
-  fn send_to_udp_socket(&self, buffer: &[u8], socket: &mio_08::net::UdpSocket, addr: &SocketAddr) {
-    match socket.send_to(buffer, *addr) {
+  fn send_to_udp_socket(
+    &self,
+    buffer: &[u8],
+    socket: &mio_08::net::UdpSocket,
+    addr: &SocketAddr,
+  ) -> io::Result<()> {
+    #[cfg(test)]
+    let result = match self.injected_failures.get() {
+      0 => socket.send_to(buffer, *addr),
+      n => {
+        self.injected_failures.set(n - 1);
+        Err(io::ErrorKind::WouldBlock.into())
+      }
+    };
+    #[cfg(not(test))]
+    let result = socket.send_to(buffer, *addr);
+
+    match result {
       Ok(bytes_sent) => {
         if bytes_sent == buffer.len() { // ok
         } else {
@@ -114,29 +292,52 @@ impl UDPSender {
             bytes_sent
           );
         }
+        if let Some(tap) = &self.message_tap {
+          tap.on_message(MessageDirection::Outbound, *addr, buffer);
+        }
+        if let Some(counters) = &self.traffic_counters {
+          counters.count_sent(bytes_sent);
+        }
+        Ok(())
       }
       Err(e) => {
+        // The caller decides whether to try again, see
+        // is_transient_send_error.
         warn!(
           "send_to_udp_socket - send_to {} : {:?} len={}",
           addr,
           e,
           buffer.len()
         );
+        self.send_failures.fetch_add(1, Ordering::Relaxed);
+        Err(e)
       }
     }
   }
 
-  pub fn send_to_locator(&self, buffer: &[u8], locator: &Locator) {
+  // Multicast is sent on every interface. Returns the last error, if any.
+  pub fn send_to_locator(&self, buffer: &[u8], locator: &Locator) -> io::Result<()> {
+    let protected;
+    let buffer = if self.message_integrity {
+      protected = append_integrity_trailer(buffer);
+      &protected
+    } else {
+      buffer
+    };
     if buffer.len() > 1500 {
       warn!("send_to_locator: Message size = {}", buffer.len());
     }
     let send = |socket_address: SocketAddr| {
       if socket_address.ip().is_multicast() {
+        let mut result = Ok(());
         for socket in &self.multicast_sockets {
-          self.send_to_udp_socket(buffer, socket, &socket_address);
+          if let Err(e) = self.send_to_udp_socket(buffer, socket, &socket_address) {
+            result = Err(e);
+          }
         }
+        result
       } else {
-        self.send_to_udp_socket(buffer, &self.unicast_socket, &socket_address);
+        self.send_to_udp_socket(buffer, &self.unicast_socket, &socket_address)
       }
     };
 
@@ -145,12 +346,14 @@ impl UDPSender {
       Locator::UdpV6(socket_address) => send(SocketAddr::from(*socket_address)),
       Locator::Invalid | Locator::Reserved => {
         error!("send_to_locator: Cannot send to {:?}", locator);
+        Ok(()) // Retrying would not help
       }
       Locator::Other { kind, .. } =>
       // This is normal, as other implementations can define their own kinds.
       // We get those from Discovery.
       {
         trace!("send_to_locator: Unknown LocatorKind: {:?}", kind);
+        Ok(())
       }
     }
   }
@@ -226,4 +429,24 @@ mod tests {
     assert_eq!(rec_data_2.len(), 6);
     assert_eq!(rec_data_2, data);
   }
+
+  #[test]
+  fn udps_reports_transient_send_failures() {
+    let listener = UDPListener::new_unicast("127.0.0.1", 10401).unwrap();
+    let sender = UDPSender::new_with_random_port().expect("failed to create UDPSender");
+    let data: Vec<u8> = vec![1, 2, 3];
+    let locator = Locator::from(SocketAddr::new("127.0.0.1".parse().unwrap(), 10401));
+
+    // Not retried here, but reported, so that the Writer can retry later.
+    sender.inject_send_failures(1);
+    let result = sender.send_to_locator(&data, &locator);
+    assert!(result.is_err_and(|e| is_transient_send_error(&e)));
+    assert_eq!(sender.send_failure_count(), 1);
+    assert_eq!(sender.send_drop_count(), 0);
+    assert!(listener.get_message().is_empty());
+
+    // Later sends are not affected
+    sender.send_to_locator(&data, &locator).unwrap();
+    assert_eq!(listener.get_message(), data);
+  }
 }
@@ -1,6 +1,6 @@
 use std::{
   io,
-  net::{IpAddr, SocketAddr},
+  net::{IpAddr, Ipv4Addr, SocketAddr},
 };
 
 use if_addrs::Interface;
@@ -9,8 +9,8 @@ use log::{debug, error, info, trace};
 
 use crate::structure::locator::Locator;
 
-pub fn get_local_multicast_locators(port: u16) -> Vec<Locator> {
-  let saddr = SocketAddr::new("239.255.0.1".parse().unwrap(), port);
+pub fn get_local_multicast_locators(multicast_group: Ipv4Addr, port: u16) -> Vec<Locator> {
+  let saddr = SocketAddr::new(IpAddr::V4(multicast_group), port);
   vec![Locator::from(saddr)]
 }
 
@@ -46,8 +46,11 @@ pub mod builtin_datatypes;
 /// Some convenience topic infos for ROS2 communication
 pub mod builtin_topics;
 
+/// ROS 2 topic naming, type naming, and QoS profiles mapped to DDS
+pub(crate) mod mapping;
 pub(crate) mod ros_node;
 
+pub use mapping::*;
 pub use ros_node::*;
 
 pub type RosSubscriber<D, DA> = crate::dds::no_key::datareader::DataReader<D, DA>;
@@ -22,6 +22,20 @@ impl Gid {
     data[15..16].clone_from_slice(&[u8::from(guid.entity_id.entity_kind)]);
     Self { data }
   }
+
+  /// The DDS GUID that this Gid was made from. The rest of the Gid is
+  /// zero padding.
+  pub fn to_guid(&self) -> GUID {
+    let mut bytes: [u8; 16] = [0; 16];
+    bytes.clone_from_slice(&self.data[..16]);
+    GUID::from_bytes(bytes)
+  }
+}
+
+impl From<GUID> for Gid {
+  fn from(guid: GUID) -> Self {
+    Self::from_guid(guid)
+  }
 }
 
 impl Key for Gid {}
@@ -33,7 +33,10 @@ impl ROSDiscoveryTopic {
     lifespan: Some(Lifespan {
       duration: Duration::DURATION_INFINITE,
     }),
-    #[cfg(feature = "security")]
+    transport_priority: None,
+    writer_data_lifecycle: None,
+    reader_data_lifecycle: None,
+    entity_factory: None,
     property: None,
   };
 
@@ -71,7 +74,10 @@ impl ParameterEventsTopic {
     history: Some(History::KeepLast { depth: 1 }),
     resource_limits: None,
     lifespan: None,
-    #[cfg(feature = "security")]
+    transport_priority: None,
+    writer_data_lifecycle: None,
+    reader_data_lifecycle: None,
+    entity_factory: None,
     property: None,
   };
 
@@ -115,7 +121,10 @@ impl RosOutTopic {
     lifespan: Some(Lifespan {
       duration: Duration::from_secs(10),
     }),
-    #[cfg(feature = "security")]
+    transport_priority: None,
+    writer_data_lifecycle: None,
+    reader_data_lifecycle: None,
+    entity_factory: None,
     property: None,
   };
 
@@ -0,0 +1,102 @@
+// Mapping of ROS 2 naming conventions and QoS profiles to DDS.
+//
+// ROS 2 nodes on other DDS implementations name their DDS Topics and types
+// according to the ROS 2 design document "Topic and Service name mapping to
+// DDS", and their QoS profiles come from rmw/qos_profiles.h. These helpers let
+// plain RustDDS applications interoperate without translating by hand.
+//
+// The ros2 module as a whole is deprecated in favour of ros2-client, but that
+// crate is a full ROS 2 client. These helpers are for applications that stay
+// on plain DDS Topics and only need to match the names and QoS of ROS 2 nodes,
+// so they are kept here next to Gid, which they also map.
+
+use crate::{
+  dds::qos::{
+    policy::{Durability, History, Reliability},
+    QosPolicies, QosPolicyBuilder,
+  },
+  structure::duration::Duration,
+};
+
+const TOPIC_PREFIX: &str = "rt/";
+const SERVICE_REQUEST_PREFIX: &str = "rq/";
+const SERVICE_REPLY_PREFIX: &str = "rr/";
+
+/// DDS Topic name for a ROS 2 topic, e.g. `"/chatter"` becomes `"rt/chatter"`.
+pub fn topic_name(node_topic: &str) -> String {
+  format!("{}{}", TOPIC_PREFIX, strip_leading_slash(node_topic))
+}
+
+/// DDS Topic name for the requests of a ROS 2 service, e.g.
+/// `"/add_two_ints"` becomes `"rq/add_two_intsRequest"`.
+pub fn service_request_topic_name(service: &str) -> String {
+  format!(
+    "{}{}Request",
+    SERVICE_REQUEST_PREFIX,
+    strip_leading_slash(service)
+  )
+}
+
+/// DDS Topic name for the replies of a ROS 2 service, e.g.
+/// `"/add_two_ints"` becomes `"rr/add_two_intsReply"`.
+pub fn service_reply_topic_name(service: &str) -> String {
+  format!(
+    "{}{}Reply",
+    SERVICE_REPLY_PREFIX,
+    strip_leading_slash(service)
+  )
+}
+
+/// DDS type name for a ROS 2 interface type, e.g. `"std_msgs/msg/String"`
+/// becomes `"std_msgs::msg::dds_::String_"`.
+///
+/// If the interface kind is left out, as in `"std_msgs/String"`, a message
+/// (`msg`) is assumed.
+pub fn type_name(ros_type: &str) -> String {
+  let parts: Vec<&str> = ros_type.split('/').filter(|p| !p.is_empty()).collect();
+  match parts.as_slice() {
+    [package, kind, name] => format!("{package}::{kind}::dds_::{name}_"),
+    [package, name] => format!("{package}::msg::dds_::{name}_"),
+    _ => ros_type.to_string(), // Not a ROS 2 type name. Leave it be.
+  }
+}
+
+fn strip_leading_slash(name: &str) -> &str {
+  name.strip_prefix('/').unwrap_or(name) // avoid double slash in name
+}
+
+/// The standard ROS 2 QoS profiles
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Profile {
+  /// Default for topics: Reliable, KeepLast(10), Volatile
+  Default,
+  /// For sensor data, where timeliness matters more than delivery: BestEffort,
+  /// KeepLast(5), Volatile
+  SensorData,
+  /// For service requests and replies: Reliable, KeepLast(10), Volatile
+  Services,
+  /// For parameter services: Reliable, KeepLast(1000), Volatile
+  Parameters,
+  /// For the parameter events topic: Reliable, KeepLast(1000), Volatile
+  ParameterEvents,
+}
+
+/// QoS policies corresponding to a ROS 2 QoS profile
+pub fn qos_profile(profile: Profile) -> QosPolicies {
+  let (reliability, depth) = match profile {
+    Profile::Default | Profile::Services => (reliable(), 10),
+    Profile::SensorData => (Reliability::BestEffort, 5),
+    Profile::Parameters | Profile::ParameterEvents => (reliable(), 1000),
+  };
+  QosPolicyBuilder::new()
+    .reliability(reliability)
+    .history(History::KeepLast { depth })
+    .durability(Durability::Volatile)
+    .build()
+}
+
+fn reliable() -> Reliability {
+  Reliability::Reliable {
+    max_blocking_time: Duration::from_millis(100),
+  }
+}
@@ -7,6 +7,7 @@ pub(crate) mod message_receiver;
 pub(crate) mod reader;
 pub(crate) mod rtps_reader_proxy;
 pub(crate) mod rtps_writer_proxy;
+pub(crate) mod vendor_compat;
 pub(crate) mod writer;
 
 pub(crate) mod message;
@@ -17,6 +17,50 @@ pub const PREEMPTIVE_ACKNACK_PERIOD: Duration = Duration::from_secs(5);
 pub const NACK_RESPONSE_DELAY: Duration = Duration::from_millis(200);
 pub const NACK_SUPPRESSION_DURATION: Duration = Duration::from_millis(0);
 
+// A Writer sends a change again after this delay, if sending it failed
+// locally, e.g. because the socket buffer was full. The delay doubles on each
+// retry, and the change is given up after SEND_RETRY_LIMIT retries.
+pub const DEFAULT_SEND_RETRY_DELAY: Duration = Duration::from_millis(10);
+pub const SEND_RETRY_LIMIT: u32 = 3;
+
+// Serialized payloads larger than this are sent as DATA_FRAGs, in fragments
+// of this size.
+pub const DEFAULT_FRAGMENT_SIZE: usize = 1024;
+// Upper limit for a configured fragment size. A DATA_FRAG with one fragment
+// and the RTPS headers must still fit into a single UDP datagram.
+pub const MAX_FRAGMENT_SIZE: usize = 60_000;
+// Room left in a message for everything but the fragment itself, when
+// fragmenting to fit a maximum message size advertised by a remote
+// participant: the RTPS header, INFO_TS and the DATA_FRAG submessage header
+// with inline QoS, and the submessages added by security encoding.
+pub const DATA_FRAG_MESSAGE_OVERHEAD: usize = 256;
+// Fragments are not made smaller than this, however small message size a
+// remote participant advertises.
+pub const MIN_FRAGMENT_SIZE: usize = 64;
+// Writers split messages larger than this at submessage boundaries, even if no
+// maximum message size is configured, so that each message fits into a single
+// UDP datagram (at most 65507 bytes over IPv4). This leaves room for the
+// submessages added by security encoding.
+pub const MAX_MESSAGE_SIZE: usize = 64_000;
+
+// Incomplete fragmented samples are discarded, if no new fragments are
+// received within this time.
+pub const FRAGMENT_ASSEMBLY_TIMEOUT: Duration = Duration::from_secs(10);
+
+// Received changes that could not be stored, because the topic cache was busy,
+// are retried after this delay.
+pub const DEFERRED_CHANGES_RETRY_DELAY: Duration = Duration::from_millis(10);
+
+// Readers with DESTINATION_ORDER BySourceTimeStamp hold received changes this
+// long, so that they can be stored in source timestamp order.
+pub const DEFAULT_REORDERING_WINDOW: Duration = Duration::from_millis(50);
+
+// When a DomainParticipant shuts down, the event loop waits at most this long
+// for remote Readers to acknowledge the data of reliable user Writers.
+pub const PREPARE_STOP_DRAIN_TIMEOUT: Duration = Duration::from_secs(1);
+// Poll interval of the event loop while it is waiting for acknowledgements
+pub const PREPARE_STOP_DRAIN_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
 // Helper list for initializing remote standard (non-secure) built-in readers
 pub const STANDARD_BUILTIN_READERS_INIT_LIST: &[(EntityId, EntityId, u32)] = &[
   (
@@ -222,6 +266,9 @@ pub const ACKNACK_MESSAGE_TO_LOCAL_WRITER_TOKEN: Token = Token(20 + PTB);
 pub const DISCOVERY_UPDATE_NOTIFICATION_TOKEN: Token = Token(21 + PTB);
 pub const DISCOVERY_COMMAND_TOKEN: Token = Token(22 + PTB);
 pub const SPDP_LIVENESS_TOKEN: Token = Token(23 + PTB);
+pub const ADD_LISTENER_TOKEN: Token = Token(24 + PTB);
+#[cfg(feature = "test-util")]
+pub const INJECT_PACKET_TOKEN: Token = Token(25 + PTB);
 
 pub const DISCOVERY_PARTICIPANT_DATA_TOKEN: Token = Token(30 + PTB);
 pub const DISCOVERY_PARTICIPANT_CLEANUP_TOKEN: Token = Token(31 + PTB);
@@ -235,6 +282,7 @@ pub const DISCOVERY_TOPIC_CLEANUP_TOKEN: Token = Token(38 + PTB);
 pub const DISCOVERY_SEND_TOPIC_INFO_TOKEN: Token = Token(39 + PTB);
 pub const DISCOVERY_PARTICIPANT_MESSAGE_TOKEN: Token = Token(40 + PTB);
 pub const DISCOVERY_PARTICIPANT_MESSAGE_TIMER_TOKEN: Token = Token(41 + PTB);
+pub const DISCOVERY_BACKEND_POLL_TOKEN: Token = Token(42 + PTB);
 
 pub const DPEV_ACKNACK_TIMER_TOKEN: Token = Token(45 + PTB);
 
@@ -1,7 +1,8 @@
 use std::{
+  cmp::Reverse,
   collections::HashMap,
   rc::Rc,
-  sync::{Arc, RwLock},
+  sync::{atomic::AtomicU64, Arc, RwLock},
   time::{Duration, Instant},
 };
 
@@ -16,14 +17,18 @@ use crate::{
     discovery_db::{discovery_db_read, DiscoveryDB},
     sedp_messages::{DiscoveredReaderData, DiscoveredWriterData},
   },
-  messages::submessages::submessages::AckSubmessage,
-  network::{udp_listener::UDPListener, udp_sender::UDPSender},
+  messages::{submessages::submessages::AckSubmessage, vendor_id::VendorId},
+  network::{
+    locator_selector::LocatorSelector, message_tap::MessageTap, traffic_counters::TrafficCounters,
+    udp_listener::UDPListener, udp_sender::UDPSender,
+  },
   rtps::{
     constant::*,
     message_receiver::MessageReceiver,
     reader::{Reader, ReaderIngredients},
     rtps_reader_proxy::RtpsReaderProxy,
     rtps_writer_proxy::RtpsWriterProxy,
+    vendor_compat,
     writer::{Writer, WriterIngredients},
   },
   structure::{
@@ -39,17 +44,50 @@ use crate::security::security_plugins::SecurityPluginsHandle;
 
 #[cfg(not(feature = "security"))]
 use crate::no_security::security_plugins::SecurityPluginsHandle;
-
+#[cfg(feature = "test-util")]
+use crate::test_util::InjectedPacket;
 
 pub struct DomainInfo {
   pub domain_participant_guid: GUID,
   pub domain_id: u16,
   pub participant_id: u16,
+  // Drop user DATA that has no source timestamp.
+  pub discard_untimestamped_data: bool,
+  // Do not wait for locks held by other threads. Defer the work instead.
+  pub defer_on_lock_contention: bool,
+  // Writers retry failed sends after this delay, doubled on each retry
+  pub send_retry_delay: std::time::Duration,
+  // Number of failed UDP sends, shared with the DomainParticipant
+  pub send_failure_count: Arc<AtomicU64>,
+  // Number of messages Writers gave up sending, shared with the
+  // DomainParticipant
+  pub send_drop_count: Arc<AtomicU64>,
+  // Observer of all sent and received RTPS messages
+  pub message_tap: Option<Arc<dyn MessageTap>>,
+  // Overrides the destinations of user traffic
+  pub locator_selector: Option<Arc<dyn LocatorSelector>>,
+  // Add and check integrity checksums of RTPS messages
+  pub message_integrity: bool,
+  // Number of received messages dropped for failing the integrity check,
+  // shared with the DomainParticipant
+  pub integrity_failure_count: Arc<AtomicU64>,
+  // Sent and received messages and bytes, shared with the DomainParticipant
+  pub traffic_counters: Arc<TrafficCounters>,
 }
 
 pub(crate) enum EventLoopCommand {
   Stop,
-  PrepareStop,
+  // Send out pending data of reliable Writers and wait for it to be
+  // acknowledged, or for PREPARE_STOP_DRAIN_TIMEOUT. Then signal `drained`.
+  PrepareStop {
+    drained: std::sync::mpsc::Sender<()>,
+  },
+}
+
+// Completion of a PrepareStop
+struct PendingDrain {
+  deadline: Instant,
+  drained: std::sync::mpsc::Sender<()>,
 }
 
 pub struct DPEventLoop {
@@ -72,9 +110,14 @@ pub struct DPEventLoop {
   add_writer_receiver: TokenReceiverPair<WriterIngredients>,
   remove_writer_receiver: TokenReceiverPair<GUID>,
   stop_poll_receiver: mio_channel::Receiver<EventLoopCommand>,
+  // Listeners opened after startup, e.g. on-demand user traffic listeners
+  add_listener_receiver: mio_channel::Receiver<(Token, UDPListener)>,
+  // Packets injected by the application, see test_util::PacketInjector
+  #[cfg(feature = "test-util")]
+  inject_packet_receiver: Option<mio_channel::Receiver<InjectedPacket>>,
   // GuidPrefix sent in this channel needs to be RTPSMessage source_guid_prefix. Writer needs this
   // to locate RTPSReaderProxy if negative acknack.
-  ack_nack_receiver: mio_channel::Receiver<(GuidPrefix, AckSubmessage)>,
+  ack_nack_receiver: mio_channel::Receiver<(GuidPrefix, VendorId, AckSubmessage)>,
 
   writers: HashMap<EntityId, Writer>,
   udp_sender: Rc<UDPSender>,
@@ -98,6 +141,7 @@ impl DPEventLoop {
     add_writer_receiver: TokenReceiverPair<WriterIngredients>,
     remove_writer_receiver: TokenReceiverPair<GUID>,
     stop_poll_receiver: mio_channel::Receiver<EventLoopCommand>,
+    add_listener_receiver: mio_channel::Receiver<(Token, UDPListener)>,
     discovery_update_notification_receiver: mio_channel::Receiver<DiscoveryNotificationType>,
     _discovery_command_sender: mio_channel::SyncSender<DiscoveryCommand>,
     spdp_liveness_sender: mio_channel::SyncSender<GuidPrefix>,
@@ -108,7 +152,7 @@ impl DPEventLoop {
 
     let poll = Poll::new().expect("Unable to create new poll.");
     let (acknack_sender, acknack_receiver) =
-      mio_channel::sync_channel::<(GuidPrefix, AckSubmessage)>(100);
+      mio_channel::sync_channel::<(GuidPrefix, VendorId, AckSubmessage)>(100);
     let mut udp_listeners = udp_listeners;
     for (token, listener) in &mut udp_listeners {
       poll
@@ -165,6 +209,15 @@ impl DPEventLoop {
       )
       .expect("Failed to register stop poll channel");
 
+    poll
+      .register(
+        &add_listener_receiver,
+        ADD_LISTENER_TOKEN,
+        Ready::readable(),
+        PollOpt::edge(),
+      )
+      .expect("Failed to register add listener channel");
+
     poll
       .register(
         &acknack_receiver,
@@ -184,11 +237,25 @@ impl DPEventLoop {
       .expect("Failed to register reader update notification.");
 
     // port number 0 means OS chooses an available port number.
-    let udp_sender = UDPSender::new(0).expect("UDPSender construction fail"); // TODO
+    let udp_sender = UDPSender::new(0)
+      .expect("UDPSender construction fail") // TODO
+      .with_send_failure_counter(domain_info.send_failure_count.clone())
+      .with_send_drop_counter(domain_info.send_drop_count.clone())
+      .with_message_tap(domain_info.message_tap.clone())
+      .with_locator_selector(domain_info.locator_selector.clone())
+      .with_message_integrity(domain_info.message_integrity)
+      .with_traffic_counters(Some(domain_info.traffic_counters.clone()));
 
     #[cfg(not(feature = "security"))]
     let security_plugins_opt = security_plugins_opt.and(None); // make sure it is None an consume value
 
+    let discard_untimestamped_data = domain_info.discard_untimestamped_data;
+    let message_tap = domain_info.message_tap.clone();
+    let traffic_counters = domain_info.traffic_counters.clone();
+    let integrity_failure_count = domain_info
+      .message_integrity
+      .then(|| domain_info.integrity_failure_count.clone());
+
     Self {
       domain_info,
       poll,
@@ -201,7 +268,11 @@ impl DPEventLoop {
         acknack_sender,
         spdp_liveness_sender,
         security_plugins_opt.clone(),
-      ),
+      )
+      .discard_untimestamped_data(discard_untimestamped_data)
+      .message_tap(message_tap)
+      .message_integrity(integrity_failure_count)
+      .traffic_counters(Some(traffic_counters)),
       #[cfg(feature = "security")]
       security_plugins_opt,
       add_reader_receiver,
@@ -209,6 +280,9 @@ impl DPEventLoop {
       add_writer_receiver,
       remove_writer_receiver,
       stop_poll_receiver,
+      add_listener_receiver,
+      #[cfg(feature = "test-util")]
+      inject_packet_receiver: None,
       writers: HashMap::new(),
       ack_nack_receiver: acknack_receiver,
       discovery_update_notification_receiver,
@@ -217,6 +291,26 @@ impl DPEventLoop {
     }
   }
 
+  // Packets received from this channel are handled as if they came from the
+  // UDP listeners.
+  #[cfg(feature = "test-util")]
+  pub(crate) fn with_packet_injection(
+    mut self,
+    inject_packet_receiver: mio_channel::Receiver<InjectedPacket>,
+  ) -> Self {
+    self
+      .poll
+      .register(
+        &inject_packet_receiver,
+        INJECT_PACKET_TOKEN,
+        Ready::readable(),
+        PollOpt::edge(),
+      )
+      .expect("Failed to register inject packet channel");
+    self.inject_packet_receiver = Some(inject_packet_receiver);
+    self
+  }
+
   pub fn event_loop(self) {
     let mut events = Events::with_capacity(16); // too small capacity just delays events to next poll
     let mut acknack_timer = mio_extras::timer::Timer::default();
@@ -234,12 +328,26 @@ impl DPEventLoop {
     let mut poll_alive = Instant::now();
     let mut ev_wrapper = self;
     let mut preparing_to_stop = false;
+    let mut pending_drain: Option<PendingDrain> = None;
 
     // loop starts here
     loop {
+      // Do not wait if some Writer has samples left over. While draining, wake
+      // up often enough to notice the deadline.
+      let commands_pending = ev_wrapper
+        .writers
+        .values()
+        .any(Writer::has_pending_commands);
+      let poll_timeout = if commands_pending {
+        Duration::ZERO
+      } else if pending_drain.is_some() {
+        PREPARE_STOP_DRAIN_POLL_INTERVAL
+      } else {
+        Duration::from_millis(2000)
+      };
       ev_wrapper
         .poll
-        .poll(&mut events, Some(Duration::from_millis(2000)))
+        .poll(&mut events, Some(poll_timeout))
         .expect("Failed in waiting of poll.");
 
       // liveness watchdog
@@ -249,30 +357,64 @@ impl DPEventLoop {
         poll_alive = now;
       }
 
-      if events.is_empty() {
+      // Writers that left samples in their command channel continue as if the
+      // channel had woken us up again.
+      let mut events_by_priority: Vec<Event> = events.iter().collect();
+      if commands_pending {
+        events_by_priority.extend(
+          ev_wrapper
+            .writers
+            .values()
+            .filter(|writer| writer.has_pending_commands())
+            .map(|writer| Event::new(Ready::readable(), writer.entity_token())),
+        );
+      }
+
+      if events_by_priority.is_empty() {
         debug!("dp_event_loop idling.");
       } else {
-        for event in events.iter() {
+        let writers = &ev_wrapper.writers;
+        sort_events_by_priority(&mut events_by_priority, |eid| {
+          writers.get(&eid).map_or(0, Writer::priority)
+        });
+
+        for event in events_by_priority {
           match EntityId::from_token(event.token()) {
             TokenDecode::FixedToken(fixed_token) => match fixed_token {
               STOP_POLL_TOKEN => {
                 use std::sync::mpsc::TryRecvError;
-                match ev_wrapper.stop_poll_receiver.try_recv() {
-                  Ok(EventLoopCommand::Stop) => {
-                    info!("Stopping dp_event_loop");
-                    return;
-                  }
-                  Ok(EventLoopCommand::PrepareStop) => {
-                    info!("dp_event_loop preparing to stop.");
-                    preparing_to_stop = true;
-                  }
-                  Err(TryRecvError::Empty) => {
-                    warn!("Spurious wakeup from dp_event_loop command channel. Very fishy.");
-                  }
-                  Err(TryRecvError::Disconnected) => {
-                    error!(
-                      "Application thread has exited abnormally. Stopping RustDDS event loop."
-                    );
+                // The channel is edge-triggered, and PrepareStop and Stop may
+                // arrive in the same wakeup, so read until empty.
+                let mut received_command = false;
+                loop {
+                  match ev_wrapper.stop_poll_receiver.try_recv() {
+                    Ok(EventLoopCommand::Stop) => {
+                      info!("Stopping dp_event_loop");
+                      ev_wrapper.shutdown();
+                      return;
+                    }
+                    Ok(EventLoopCommand::PrepareStop { drained }) => {
+                      info!("dp_event_loop preparing to stop.");
+                      preparing_to_stop = true;
+                      received_command = true;
+                      ev_wrapper.start_drain();
+                      pending_drain = Some(PendingDrain {
+                        deadline: Instant::now() + PREPARE_STOP_DRAIN_TIMEOUT,
+                        drained,
+                      });
+                    }
+                    Err(TryRecvError::Empty) => {
+                      if !received_command {
+                        warn!("Spurious wakeup from dp_event_loop command channel. Very fishy.");
+                      }
+                      break;
+                    }
+                    Err(TryRecvError::Disconnected) => {
+                      error!(
+                        "Application thread has exited abnormally. Stopping RustDDS event loop."
+                      );
+                      break;
+                    }
                   }
                 }
               }
@@ -288,10 +430,25 @@ impl DPEventLoop {
                       error!("No listener with token {:?}", &event.token());
                       vec![]
                     },
-                    UDPListener::messages,
+                    UDPListener::messages_with_source,
                   );
-                for packet in udp_messages {
-                  ev_wrapper.message_receiver.handle_received_packet(&packet);
+                for (packet, source) in udp_messages {
+                  ev_wrapper
+                    .message_receiver
+                    .handle_received_packet(&packet, source);
+                }
+              }
+              ADD_LISTENER_TOKEN => {
+                ev_wrapper.handle_add_listener();
+              }
+              #[cfg(feature = "test-util")]
+              INJECT_PACKET_TOKEN => {
+                if let Some(receiver) = &ev_wrapper.inject_packet_receiver {
+                  while let Ok((packet, source)) = receiver.try_recv() {
+                    ev_wrapper
+                      .message_receiver
+                      .handle_received_packet(&packet, source);
+                  }
                 }
               }
               ADD_READER_TOKEN | REMOVE_READER_TOKEN => {
@@ -402,9 +559,105 @@ impl DPEventLoop {
           }
         } // for
       } // if
+
+      if let Some(drain) = pending_drain.take() {
+        if ev_wrapper.reliable_writers_drained() {
+          debug!("Reliable writers drained.");
+          let _ = drain.drained.send(());
+        } else if Instant::now() >= drain.deadline {
+          warn!("Stopping before all reliable data was acknowledged. It may be lost.");
+          let _ = drain.drained.send(());
+        } else {
+          pending_drain = Some(drain);
+        }
+      }
     } // loop
   } // fn
 
+  fn flush_writers(&mut self) {
+    for writer in self.writers.values_mut() {
+      writer.flush_writer_commands();
+    }
+  }
+
+  // First step of a clean shutdown: send out everything the DataWriters have
+  // written, and ask the Readers to acknowledge it.
+  fn start_drain(&mut self) {
+    self.flush_writers();
+    for writer in self.writers.values_mut() {
+      if writer.guid().entity_id.entity_kind.is_user_defined()
+        && writer.is_reliable()
+        && !writer.all_changes_acked()
+      {
+        writer.handle_heartbeat_tick(false);
+      }
+    }
+    let local_readers = self
+      .writers
+      .values()
+      .flat_map(Writer::local_readers)
+      .collect();
+    self.message_receiver.notify_data_to_readers(local_readers);
+  }
+
+  // Have the Readers acknowledged all data of reliable user Writers? Built-in
+  // Writers are not waited for, because remote participants are not required
+  // to acknowledge them, e.g. when they are shutting down, too.
+  fn reliable_writers_drained(&self) -> bool {
+    self
+      .writers
+      .values()
+      .filter(|w| w.guid().entity_id.entity_kind.is_user_defined() && w.is_reliable())
+      .all(Writer::all_changes_acked)
+  }
+
+  // Tears down in a defined order, so that nothing uses crypto state after it
+  // is released, and no message is left half-processed when sockets close:
+  // 1. Stop receiving. Messages not yet read from the sockets are dropped
+  //    undecoded. Messages already read were fully handled, because the
+  //    event loop handles one event at a time.
+  // 2. Send what is still queued, most importantly the disposals that
+  //    Discovery wrote when it stopped, so that remote participants need not
+  //    wait for our lease to expire. This still needs the crypto state.
+  // 3. Unregister all participants and endpoints from the crypto plugin, so
+  //    that key material does not linger after shutdown.
+  // 4. Close the sockets, by dropping them. The caller then joins the thread.
+  fn shutdown(mut self) {
+    for (token, listener) in self.udp_listeners.iter_mut() {
+      self
+        .poll
+        .deregister(listener.mio_socket())
+        .unwrap_or_else(|e| warn!("Failed to deregister listener {token:?}: {e:?}"));
+    }
+
+    self.flush_writers();
+
+    #[cfg(feature = "security")]
+    if let Some(security_plugins_handle) = self.security_plugins_opt.as_ref() {
+      security_plugins_handle.unregister_all_participants();
+    }
+
+    drop(self);
+    debug!("dp_event_loop sockets closed");
+  }
+
+  fn handle_add_listener(&mut self) {
+    while let Ok((token, mut listener)) = self.add_listener_receiver.try_recv() {
+      match self.poll.register(
+        listener.mio_socket(),
+        token,
+        Ready::readable(),
+        PollOpt::edge(),
+      ) {
+        Ok(()) => {
+          info!("Added listener {:?}", token);
+          self.udp_listeners.insert(token, listener);
+        }
+        Err(e) => error!("Failed to register listener {:?}: {:?}", token, e),
+      }
+    }
+  }
+
   fn handle_reader_action(&mut self, event: &Event) {
     match event.token() {
       ADD_READER_TOKEN => {
@@ -458,7 +711,9 @@ impl DPEventLoop {
   }
 
   fn handle_writer_acknack_action(&mut self, _event: &Event) {
-    while let Ok((acknack_sender_prefix, acknack_submessage)) = self.ack_nack_receiver.try_recv() {
+    while let Ok((acknack_sender_prefix, acknack_sender_vendor_id, acknack_submessage)) =
+      self.ack_nack_receiver.try_recv()
+    {
       let writer_guid = GUID::new_with_prefix_and_id(
         self.domain_info.domain_participant_guid.prefix,
         acknack_submessage.writer_id(),
@@ -466,20 +721,29 @@ impl DPEventLoop {
       if let Some(found_writer) = self.writers.get_mut(&writer_guid.entity_id) {
         if found_writer.is_reliable() {
           found_writer.handle_ack_nack(acknack_sender_prefix, &acknack_submessage);
+          // Acknowledgements may have made room for blocked writes.
+          if found_writer.has_blocked_writes() {
+            found_writer.process_writer_command();
+            let local_readers = found_writer.local_readers();
+            self.message_receiver.notify_data_to_readers(local_readers);
+          }
         }
+      } else if vendor_compat::expects_unknown_writer(
+        acknack_sender_vendor_id,
+        writer_guid.entity_id,
+      ) {
+        // E.g. FastDDS sends pre-emptive acknacks to the builtin Writers of DDS
+        // XTypes, which RustDDS does not implement. This is not a problem.
+        trace!(
+          "Ignoring acknack/nackfrag from {:?} to unimplemented builtin writer {:?}",
+          acknack_sender_vendor_id,
+          writer_guid
+        );
       } else {
-        // Note: when testing against FastDDS Shapes demo, this else branch is
-        // repeatedly triggered. The resulting log entry contains the following
-        // EntityId: {[0, 3, 0] EntityKind::WRITER_NO_KEY_BUILT_IN}.
-        // In this case a writer cannot be found, because FastDDS sends
-        // pre-emptive acknacks about a built-in topic defined in DDS Xtypes
-        // specification, which RustDDS does not implement. So even though the acknack
-        // cannot be handled, it is not a problem in this case.
         debug!(
           "Couldn't handle acknack/nackfrag! Did not find local RTPS writer with GUID: {:x?}",
           writer_guid
         );
-        continue;
       }
     }
   }
@@ -591,6 +855,12 @@ impl DPEventLoop {
             // Also ParticipantStatelessMessage reader has special Qos
             qos = Discovery::create_participant_stateless_message_qos();
           }
+          // The volatile message reader is Volatile, so it would not match
+          // with the TransientLocal discovery QoS.
+          #[cfg(feature = "security")]
+          if *writer_eid == EntityId::P2P_BUILTIN_PARTICIPANT_VOLATILE_SECURE_WRITER {
+            qos = Discovery::create_participant_volatile_message_secure_qos();
+          }
 
           writer.update_reader_proxy(&reader_proxy, &qos);
           debug!(
@@ -674,6 +944,13 @@ impl DPEventLoop {
   }
 
   fn remote_reader_discovered(&mut self, drd: &DiscoveredReaderData) {
+    let mut reader_proxy = RtpsReaderProxy::from_discovered_reader_data(drd, &[], &[]);
+    // Honor the receive limit that the participant of the Reader advertises
+    reader_proxy.max_message_size = discovery_db_read(&self.discovery_db)
+      .find_participant_proxy(drd.reader_proxy.remote_reader_guid.prefix)
+      .and_then(|participant| participant.max_message_size)
+      .map(|size| size as usize);
+
     for writer in self.writers.values_mut() {
       if drd.subscription_topic_data.topic_name() == writer.topic_name() {
         // // see if the participant has published a QoS for the topic
@@ -686,10 +963,7 @@ impl DPEventLoop {
         //   .unwrap_or_else( QosPolicies::default )
         //   .modify_by( &drd.subscription_topic_data.qos() );
         let requested_qos = drd.subscription_topic_data.qos();
-        writer.update_reader_proxy(
-          &RtpsReaderProxy::from_discovered_reader_data(drd, &[], &[]),
-          &requested_qos,
-        );
+        writer.update_reader_proxy(&reader_proxy, &requested_qos);
       }
     }
   }
@@ -697,10 +971,36 @@ impl DPEventLoop {
   fn remote_reader_lost(&mut self, reader_guid: GUID) {
     for writer in self.writers.values_mut() {
       writer.reader_lost(reader_guid);
+      // The lost reader may have been the one that blocked writes.
+      if writer.has_blocked_writes() {
+        writer.process_writer_command();
+        let local_readers = writer.local_readers();
+        self.message_receiver.notify_data_to_readers(local_readers);
+      }
     }
   }
 
   fn remote_writer_discovered(&mut self, dwd: &DiscoveredWriterData) {
+    // notify DDSCache to create topic if it does not exist yet
+    let topic_accepted = match self.ddscache.write() {
+      Ok(mut ddsc) => {
+        let ptd = &dwd.publication_topic_data;
+        ddsc
+          .add_new_remote_topic(
+            ptd.topic_name.clone(),
+            TypeDesc::new(ptd.type_name.clone()),
+            &ptd.qos(),
+          )
+          .is_some()
+      }
+
+      _ => panic!("DDSCache is poisoned"),
+    };
+    if !topic_accepted {
+      // Topic limit reached. Do not match the writer either.
+      return;
+    }
+
     // update writer proxies in local readers
     for reader in self.message_receiver.available_readers.values_mut() {
       if &dwd.publication_topic_data.topic_name == reader.topic_name() {
@@ -721,19 +1021,6 @@ impl DPEventLoop {
         );
       }
     }
-    // notify DDSCache to create topic if it does not exist yet
-    match self.ddscache.write() {
-      Ok(mut ddsc) => {
-        let ptd = &dwd.publication_topic_data;
-        ddsc.add_new_topic(
-          ptd.topic_name.clone(),
-          TypeDesc::new(ptd.type_name.clone()),
-          &ptd.qos(),
-        );
-      }
-
-      _ => panic!("DDSCache is poisoned"),
-    }
   }
 
   fn remote_writer_lost(&mut self, writer_guid: GUID) {
@@ -743,7 +1030,12 @@ impl DPEventLoop {
   }
 
   fn add_local_reader(&mut self, reader_ing: ReaderIngredients) {
-    let timer = mio_extras::timer::Builder::default().num_slots(8).build();
+    // The default tick of 100 ms would be too coarse for randomized
+    // HEARTBEAT response delays.
+    let timer = mio_extras::timer::Builder::default()
+      .tick_duration(Duration::from_millis(10))
+      .num_slots(8)
+      .build();
     self
       .poll
       .register(
@@ -763,6 +1055,7 @@ impl DPEventLoop {
     let topic_name = reader_ing.topic_name.clone();
 
     let mut new_reader = Reader::new(reader_ing, self.udp_sender.clone(), timer);
+    new_reader.set_defer_on_lock_contention(self.domain_info.defer_on_lock_contention);
 
     // Non-timed action polling
     self
@@ -855,7 +1148,8 @@ impl DPEventLoop {
     #[cfg(feature = "security")]
     let topic_name = writer_ing.topic_name.clone();
 
-    let new_writer = Writer::new(writer_ing, self.udp_sender.clone(), timer);
+    let mut new_writer = Writer::new(writer_ing, self.udp_sender.clone(), timer);
+    new_writer.send_retry_delay = self.domain_info.send_retry_delay;
 
     self
       .poll
@@ -927,6 +1221,21 @@ impl DPEventLoop {
 // -----------------------------------------------------------
 // -----------------------------------------------------------
 
+// Service the events of high-priority writers first, and those of low-priority
+// writers last. Writer actions and timed events get the priority of the
+// writer, everything else is neutral. The sort is stable, so otherwise events
+// are handled in the order poll gave them.
+fn sort_events_by_priority(events: &mut [Event], writer_priority: impl Fn(EntityId) -> i32) {
+  events.sort_by_key(|event| {
+    Reverse(match EntityId::from_token(event.token()) {
+      TokenDecode::Entity(eid) | TokenDecode::AltEntity(eid) if eid.kind().is_writer() => {
+        writer_priority(eid)
+      }
+      _ => 0,
+    })
+  });
+}
+
 #[cfg(test)]
 mod tests {
   use std::{
@@ -947,7 +1256,7 @@ mod tests {
       with_key::simpledatareader::ReaderCommand,
     },
     mio_source,
-    structure::dds_cache::DDSCache,
+    structure::{dds_cache::DDSCache, guid::EntityKind},
   };
 
   //#[test]
@@ -967,6 +1276,7 @@ mod tests {
     let (_remove_writer_sender, remove_writer_receiver) = mio_channel::channel();
 
     let (_stop_poll_sender, stop_poll_receiver) = mio_channel::channel();
+    let (_add_listener_sender, add_listener_receiver) = mio_channel::channel();
 
     let (_discovery_update_notification_sender, discovery_update_notification_receiver) =
       mio_channel::channel();
@@ -987,6 +1297,16 @@ mod tests {
       domain_participant_guid: GUID::default(),
       domain_id: 0,
       participant_id: 0,
+      discard_untimestamped_data: false,
+      defer_on_lock_contention: false,
+      send_retry_delay: DEFAULT_SEND_RETRY_DELAY,
+      send_failure_count: Arc::new(AtomicU64::new(0)),
+      send_drop_count: Arc::new(AtomicU64::new(0)),
+      message_tap: None,
+      locator_selector: None,
+      message_integrity: false,
+      integrity_failure_count: Arc::new(AtomicU64::new(0)),
+      traffic_counters: Arc::new(TrafficCounters::default()),
     };
 
     let (sender_stop, receiver_stop) = mio_channel::channel::<i32>();
@@ -1017,6 +1337,7 @@ mod tests {
           receiver: remove_writer_receiver,
         },
         stop_poll_receiver,
+        add_listener_receiver,
         discovery_update_notification_receiver,
         discovery_command_sender,
         spdp_liveness_sender,
@@ -1070,6 +1391,7 @@ mod tests {
         data_reader_command_receiver: reader_command_receiver,
         data_reader_waker: data_reader_waker.clone(),
         poll_event_sender: notification_event_sender,
+        heartbeat_response_delay: Duration::ZERO,
         security_plugins: None,
       };
 
@@ -1302,4 +1624,44 @@ mod tests {
   //   sender_stop.send(0).unwrap();
   //   child.join().unwrap();
   // }
+
+  #[test]
+  fn events_are_sorted_by_writer_priority() {
+    let writer = |key| EntityId::new([0, 0, key], EntityKind::WRITER_WITH_KEY_USER_DEFINED);
+    let (bulk, control, background) = (writer(1), writer(2), writer(3));
+    let reader = EntityId::new([0, 0, 4], EntityKind::READER_WITH_KEY_USER_DEFINED);
+    let priority = |eid| match eid {
+      eid if eid == control => 10,
+      eid if eid == background => -1,
+      _ => 0,
+    };
+
+    let mut events: Vec<Event> = [
+      background.as_token(),
+      bulk.as_token(),
+      reader.as_token(),
+      STOP_POLL_TOKEN,
+      control.as_alt_token(),
+      bulk.as_alt_token(),
+      control.as_token(),
+    ]
+    .into_iter()
+    .map(|token| Event::new(Ready::readable(), token))
+    .collect();
+    sort_events_by_priority(&mut events, priority);
+
+    // Higher priority first, otherwise in the original order
+    assert_eq!(
+      events.iter().map(Event::token).collect::<Vec<_>>(),
+      vec![
+        control.as_alt_token(),
+        control.as_token(),
+        bulk.as_token(),
+        reader.as_token(),
+        STOP_POLL_TOKEN,
+        bulk.as_alt_token(),
+        background.as_token(),
+      ]
+    );
+  }
 }
@@ -25,9 +25,10 @@ struct AssemblyBuffer {
   fragment_count: usize,
   received_bitmap: BitVec,
 
-  #[allow(dead_code)] // TODO: Purpose is to use this later for e.g.
-  // garbage collection, in case some buffer is not completed within reasonable time.
+  #[allow(dead_code)] // TODO: Use or remove
   created_time: Timestamp,
+  // Used for garbage collection, in case some buffer is not completed within
+  // reasonable time.
   modified_time: Timestamp,
 }
 
@@ -212,6 +213,25 @@ impl FragmentAssembler {
   // AssemblyBuffers   self.assembly_buffers.keys()
   // }
 
+  // Discard assembly buffers that have not received any fragments since
+  // expire_before. Returns the number of discarded buffers.
+  pub fn garbage_collect_before(&mut self, expire_before: Timestamp) -> usize {
+    let before = self.assembly_buffers.len();
+    self.assembly_buffers.retain(|sn, abuf| {
+      let keep = abuf.modified_time >= expire_before;
+      if !keep {
+        debug!(
+          "Discarding incomplete fragmented sample {:?}: {} of {} fragments received",
+          sn,
+          abuf.received_bitmap.iter().filter(|b| *b).count(),
+          abuf.fragment_count
+        );
+      }
+      keep
+    });
+    before - self.assembly_buffers.len()
+  }
+
   pub fn is_partially_received(&self, sn: SequenceNumber) -> bool {
     self.assembly_buffers.contains_key(&sn)
     // assembly buffers map contains a key (SN) if and only if we have some
@@ -233,3 +253,51 @@ impl FragmentAssembler {
     }
   }
 }
+
+#[cfg(test)]
+mod tests {
+  use bytes::Bytes;
+
+  use super::*;
+  use crate::structure::duration::Duration;
+
+  fn datafrag(sn: i64, frag_num: u32, payload: &[u8]) -> DecodedDataFrag {
+    DecodedDataFrag {
+      writer_sn: SequenceNumber::new(sn),
+      fragment_starting_num: FragmentNumber::new(frag_num),
+      fragments_in_submessage: 1,
+      data_size: 10,
+      fragment_size: 4,
+      serialized_payload: Bytes::copy_from_slice(payload),
+      ..Default::default()
+    }
+  }
+
+  #[test]
+  fn incomplete_sample_is_garbage_collected() {
+    let mut fa = FragmentAssembler::new(4);
+    let flags = BitFlags::<DATAFRAG_Flags>::from_flag(DATAFRAG_Flags::Endianness);
+
+    assert!(fa.new_datafrag(&datafrag(1, 3, &[8, 9]), flags).is_none());
+    assert!(fa.is_partially_received(SequenceNumber::new(1)));
+    assert_eq!(
+      fa.missing_frags_for(SequenceNumber::new(1))
+        .collect::<Vec<_>>(),
+      vec![FragmentNumber::new(1), FragmentNumber::new(2)]
+    );
+
+    // Nothing is discarded while fragments are still arriving
+    assert_eq!(
+      fa.garbage_collect_before(Timestamp::now() - Duration::from_secs(10)),
+      0
+    );
+    assert!(fa.is_partially_received(SequenceNumber::new(1)));
+
+    assert_eq!(
+      fa.garbage_collect_before(Timestamp::now() + Duration::from_secs(1)),
+      1
+    );
+    assert!(!fa.is_partially_received(SequenceNumber::new(1)));
+    assert_eq!(fa.missing_frags_for(SequenceNumber::new(1)).count(), 0);
+  }
+}
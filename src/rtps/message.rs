@@ -23,7 +23,8 @@ use crate::{
   },
   rtps::{writer::Writer as RtpsWriter, Submessage, SubmessageBody},
   structure::{
-    cache_change::CacheChange,
+    cache_change::{CacheChange, ChangeKind},
+    duration::Duration,
     entity::RTPSEntity,
     guid::{EntityId, EntityKind, GuidPrefix, GUID},
     parameter_id::ParameterId,
@@ -89,6 +90,76 @@ impl Message {
       submessages: vec![],
     }
   }
+
+  // Splits the message into several, so that each stays within the limits.
+  // Interpreter submessages, e.g. INFO_DST and INFO_TS, apply to the
+  // submessages that follow them, so the latest ones are repeated at the start
+  // of each new message. Every message gets at least one submessage besides
+  // those, even if that exceeds the limits.
+  pub(crate) fn split(self, limits: MessageLimits) -> Vec<Message> {
+    if limits.max_submessages.is_none() && limits.max_size.is_none() {
+      return vec![self];
+    }
+    let submessage_size =
+      |s: &Submessage| SUBMESSAGE_HEADER_SIZE + s.header.content_length as usize;
+
+    let mut messages = Vec::new();
+    let mut context: Vec<Submessage> = Vec::new(); // latest interpreter submessages
+    let mut current: Vec<Submessage> = Vec::new();
+    let mut current_size = RTPS_MESSAGE_HEADER_SIZE;
+    let mut has_entity_submessage = false;
+
+    for submessage in self.submessages {
+      let size = submessage_size(&submessage);
+      let is_interpreter = matches!(submessage.body, SubmessageBody::Interpreter(_));
+      if is_interpreter {
+        context.retain(|c| c.header.kind != submessage.header.kind);
+        context.push(submessage.clone());
+      }
+
+      let over_limits = limits
+        .max_submessages
+        .is_some_and(|max| current.len() + 1 > max)
+        || limits.max_size.is_some_and(|max| current_size + size > max);
+      if over_limits && has_entity_submessage {
+        messages.push(Message {
+          header: self.header,
+          submessages: std::mem::replace(&mut current, context.clone()),
+        });
+        current_size =
+          RTPS_MESSAGE_HEADER_SIZE + context.iter().map(submessage_size).sum::<usize>();
+        has_entity_submessage = false;
+        if is_interpreter {
+          continue; // already in the new message from context
+        }
+      }
+
+      has_entity_submessage |= !is_interpreter;
+      current_size += size;
+      current.push(submessage);
+    }
+
+    // Trailing interpreter submessages alone would be of no use.
+    if has_entity_submessage || messages.is_empty() {
+      messages.push(Message {
+        header: self.header,
+        submessages: current,
+      });
+    }
+    messages
+  }
+}
+
+const RTPS_MESSAGE_HEADER_SIZE: usize = 20;
+const SUBMESSAGE_HEADER_SIZE: usize = 4;
+
+// Limits on the size of RTPS messages that Writers send.
+// See DomainParticipantBuilder::max_submessages_per_message and
+// DomainParticipantBuilder::max_message_size.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub(crate) struct MessageLimits {
+  pub max_submessages: Option<usize>,
+  pub max_size: Option<usize>,
 }
 
 impl Default for Message {
@@ -110,6 +181,16 @@ impl<C: Context> Writable<C> for Message {
   }
 }
 
+// The LIFESPAN duration is sent as inline QoS, so that the reader can compute
+// the expiration from the source timestamp.
+fn lifespan_of(cache_change: &CacheChange) -> Option<Duration> {
+  let write_options = &cache_change.write_options;
+  match (write_options.source_timestamp(), write_options.expiration()) {
+    (Some(source_timestamp), Some(expiration)) => Some(expiration - source_timestamp),
+    _ => None,
+  }
+}
+
 #[derive(Default, Clone)]
 pub(crate) struct MessageBuilder {
   submessages: Vec<Submessage>,
@@ -188,7 +269,16 @@ impl MessageBuilder {
 
     // Check if we are disposing by key hash
     match cache_change.data_value {
-      DDSData::Data { .. } | DDSData::DisposeByKey { .. } => (), // no
+      DDSData::Data { .. } => (), // no
+      DDSData::DisposeByKey { change_kind, .. } => {
+        // no, but tell if this is a dispose or an unregister
+        let status_info = Parameter::create_pid_status_info_parameter(
+          /* disposed */ change_kind == ChangeKind::NotAliveDisposed,
+          /* unregistered */ change_kind == ChangeKind::NotAliveUnregistered,
+          /* filtered */ false,
+        );
+        param_list.push(status_info);
+      }
       DDSData::DisposeByKeyHash { key_hash, .. } => {
         // yes, insert to inline QoS
         // insert key hash
@@ -214,6 +304,24 @@ impl MessageBuilder {
       });
     }
 
+    // Members of a coherent set tell where the set started
+    if let Some(coherent_set_start) = cache_change.write_options.coherent_set() {
+      param_list.push(Parameter {
+        parameter_id: ParameterId::PID_COHERENT_SET,
+        value: coherent_set_start
+          .write_to_vec_with_ctx(endianness)
+          .unwrap(),
+      });
+    }
+
+    // LIFESPAN of the sample, counted from the source timestamp
+    if let Some(lifespan) = lifespan_of(cache_change) {
+      param_list.push(Parameter {
+        parameter_id: ParameterId::PID_LIFESPAN,
+        value: lifespan.write_to_vec_with_ctx(endianness).unwrap(),
+      });
+    }
+
     let serialized_payload = match cache_change.data_value {
       DDSData::Data {
         ref serialized_payload,
@@ -318,6 +426,48 @@ impl MessageBuilder {
     self
   }
 
+  // End marker of a coherent set: DATA with no payload and only the
+  // coherent set inline QoS, whose value SEQUENCENUMBER_UNKNOWN closes the set.
+  // It consumes a sequence number of its own.
+  // RTPS spec v2.5 Section "8.7.5 Group Ordered Access and Coherent Sets"
+  pub fn coherent_set_end_msg(
+    mut self,
+    reader_entity_id: EntityId,
+    writer_guid: GUID,
+    sequence_number: SequenceNumber,
+    endianness: Endianness,
+  ) -> Self {
+    let mut param_list = ParameterList::new();
+    param_list.push(Parameter {
+      parameter_id: ParameterId::PID_COHERENT_SET,
+      value: SequenceNumber::SEQUENCENUMBER_UNKNOWN
+        .write_to_vec_with_ctx(endianness)
+        .unwrap(),
+    });
+
+    let data_message = Data {
+      reader_id: reader_entity_id,
+      writer_id: writer_guid.entity_id,
+      writer_sn: sequence_number,
+      inline_qos: Some(param_list),
+      encoded_payload: None,
+    };
+
+    let flags: BitFlags<DATA_Flags> = BitFlags::<DATA_Flags>::from_endianness(endianness)
+      | BitFlags::<DATA_Flags>::from_flag(DATA_Flags::InlineQos);
+
+    self.submessages.push(Submessage {
+      header: SubmessageHeader {
+        kind: SubmessageKind::DATA,
+        flags: flags.bits(),
+        content_length: data_message.len_serialized() as u16,
+      },
+      body: SubmessageBody::Writer(WriterSubmessage::Data(data_message, flags)),
+      original_bytes: None,
+    });
+    self
+  }
+
   // This whole MessageBuilder structure should be refactored into something more
   // coherent. Now it just looks messy.
   #[allow(clippy::too_many_arguments)]
@@ -360,6 +510,22 @@ impl MessageBuilder {
       });
     }
 
+    if let Some(coherent_set_start) = cache_change.write_options.coherent_set() {
+      param_list.parameters.push(Parameter {
+        parameter_id: ParameterId::PID_COHERENT_SET,
+        value: coherent_set_start
+          .write_to_vec_with_ctx(endianness)
+          .unwrap(),
+      });
+    }
+
+    if let Some(lifespan) = lifespan_of(cache_change) {
+      param_list.parameters.push(Parameter {
+        parameter_id: ParameterId::PID_LIFESPAN,
+        value: lifespan.write_to_vec_with_ctx(endianness).unwrap(),
+      });
+    }
+
     let have_inline_qos = !param_list.is_empty(); // we need this later also
 
     // fragments are numbered starting from 1, not 0.
@@ -461,8 +627,9 @@ impl MessageBuilder {
     self
   }
 
-  // TODO: We should optimize this entire thing to allow long contiguous
-  // irrelevant set to be represented as start_sn +
+  // The contiguous run of SNs at the start of the set is sent as the range
+  // from gap_start to the base of gap_list, so that e.g. a long run of
+  // overwritten changes fits in one GAP. The rest go to the gap_list bitmap.
   pub fn gap_msg(
     mut self,
     irrelevant_sns: &BTreeSet<SequenceNumber>,
@@ -475,7 +642,14 @@ impl MessageBuilder {
       irrelevant_sns.iter().next_back(),
     ) {
       (Some(&base), Some(&_top)) => {
-        let gap_list = SequenceNumberSet::from_base_and_set(base, irrelevant_sns);
+        let mut list_base = base;
+        while irrelevant_sns.contains(&list_base) {
+          list_base = list_base + SequenceNumber::new(1);
+        }
+        let gap_list = SequenceNumberSet::from_base_and_set(
+          list_base,
+          &irrelevant_sns.range(list_base..).copied().collect(),
+        );
         let gap = Gap {
           reader_id: reader_guid.entity_id,
           writer_id: writer_entity_id,
@@ -784,4 +958,68 @@ mod tests {
     info!("read_from_buffer() --> {rtps:?}");
     // if we get here without panic, the test passes
   }
+
+  #[test]
+  fn split_message_within_limits() {
+    let writer_guid =
+      GUID::dummy_test_guid(crate::structure::guid::EntityKind::WRITER_WITH_KEY_USER_DEFINED);
+    let mut builder = MessageBuilder::new()
+      .dst_submessage(Endianness::LittleEndian, GuidPrefix::UNKNOWN)
+      .ts_msg(Endianness::LittleEndian, Some(Timestamp::now()));
+    for sn in 1..=5 {
+      builder = builder.coherent_set_end_msg(
+        EntityId::UNKNOWN,
+        writer_guid,
+        SequenceNumber::new(sn),
+        Endianness::LittleEndian,
+      );
+    }
+    let message = builder.add_header_and_build(writer_guid.prefix);
+    let entity_submessages = |m: &Message| {
+      m.submessages
+        .iter()
+        .filter(|s| !matches!(s.body, SubmessageBody::Interpreter(_)))
+        .cloned()
+        .collect::<Vec<_>>()
+    };
+    let all_entity_submessages = entity_submessages(&message);
+
+    assert_eq!(message.clone().split(MessageLimits::default()).len(), 1);
+
+    let parts = message.clone().split(MessageLimits {
+      max_submessages: Some(3),
+      max_size: None,
+    });
+    assert_eq!(parts.len(), 5);
+    for part in &parts {
+      assert!(part.submessages.len() <= 3);
+      // Every part starts with the INFO_DST and INFO_TS
+      assert_eq!(part.submessages[0].header.kind, SubmessageKind::INFO_DST);
+      assert_eq!(part.submessages[1].header.kind, SubmessageKind::INFO_TS);
+    }
+    assert_eq!(
+      parts
+        .iter()
+        .flat_map(entity_submessages)
+        .collect::<Vec<_>>(),
+      all_entity_submessages
+    );
+
+    let max_size = message.write_to_vec().unwrap().len() / 2;
+    let parts = message.split(MessageLimits {
+      max_submessages: None,
+      max_size: Some(max_size),
+    });
+    assert!(parts.len() > 1);
+    for part in &parts {
+      assert!(part.write_to_vec().unwrap().len() <= max_size);
+    }
+    assert_eq!(
+      parts
+        .iter()
+        .flat_map(entity_submessages)
+        .collect::<Vec<_>>(),
+      all_entity_submessages
+    );
+  }
 }
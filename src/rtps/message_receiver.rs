@@ -1,4 +1,11 @@
-use std::collections::{btree_map::Entry, BTreeMap};
+use std::{
+  collections::{btree_map::Entry, BTreeMap},
+  net::SocketAddr,
+  sync::{
+    atomic::{AtomicU64, Ordering},
+    Arc,
+  },
+};
 
 use enumflags2::BitFlags;
 use mio_extras::{channel as mio_channel, channel::TrySendError};
@@ -15,6 +22,11 @@ use crate::{
     },
     vendor_id::VendorId,
   },
+  network::{
+    message_integrity::verify_and_strip_integrity_trailer,
+    message_tap::{MessageDirection, MessageTap},
+    traffic_counters::TrafficCounters,
+  },
   rtps::{reader::Reader, Message, Submessage, SubmessageBody},
   structure::{
     entity::RTPSEntity,
@@ -25,7 +37,10 @@ use crate::{
 };
 #[cfg(feature = "security")]
 use crate::security::{
-  cryptographic::types::SecureSubmessageCategory, security_plugins::SecurityPluginsHandle,
+  cryptographic::{
+    types::SecureSubmessageCategory, DatareaderCryptoHandle, DatawriterCryptoHandle,
+  },
+  security_plugins::SecurityPluginsHandle,
 };
 #[cfg(feature = "security")]
 use crate::messages::submessages::{secure_postfix::SecurePostfix, secure_prefix::SecurePrefix};
@@ -105,7 +120,7 @@ pub(crate) struct MessageReceiver {
   pub available_readers: BTreeMap<EntityId, Reader>,
   // GuidPrefix sent in this channel needs to be RTPSMessage source_guid_prefix. Writer needs this
   // to locate RTPSReaderProxy if negative acknack.
-  acknack_sender: mio_channel::SyncSender<(GuidPrefix, AckSubmessage)>,
+  acknack_sender: mio_channel::SyncSender<(GuidPrefix, VendorId, AckSubmessage)>,
   // We send notification of remote DomainParticipant liveness to Discovery to
   // bypass Reader, DDSCache, DatasampleCache, and DataReader, because these will drop
   // repeated messages with duplicate SequenceNumbers, but Discovery needs to see them.
@@ -125,12 +140,26 @@ pub(crate) struct MessageReceiver {
   secure_receiver_state: Option<SecureReceiverState>,
   #[cfg(feature = "security")]
   secure_rtps_wrapped: Option<SecureWrapping>,
+
+  // Drop user DATA that is not preceded by a valid INFO_TS, instead of
+  // accepting it with reception time only.
+  discard_untimestamped_data: bool,
+
+  // Sees every received message
+  message_tap: Option<Arc<dyn MessageTap>>,
+
+  // If set, messages must end with a valid integrity checksum. Messages that
+  // do not are dropped and counted here.
+  message_integrity_failures: Option<Arc<AtomicU64>>,
+
+  // Counts received messages and bytes, shared with the DomainParticipant
+  traffic_counters: Option<Arc<TrafficCounters>>,
 }
 
 impl MessageReceiver {
   pub fn new(
     participant_guid_prefix: GuidPrefix,
-    acknack_sender: mio_channel::SyncSender<(GuidPrefix, AckSubmessage)>,
+    acknack_sender: mio_channel::SyncSender<(GuidPrefix, VendorId, AckSubmessage)>,
     spdp_liveness_sender: mio_channel::SyncSender<GuidPrefix>,
     security_plugins: Option<SecurityPluginsHandle>,
   ) -> Self {
@@ -153,9 +182,34 @@ impl MessageReceiver {
       secure_receiver_state: None,
       #[cfg(feature = "security")]
       secure_rtps_wrapped: None,
+
+      discard_untimestamped_data: false,
+      message_tap: None,
+      message_integrity_failures: None,
+      traffic_counters: None,
     }
   }
 
+  pub fn discard_untimestamped_data(mut self, discard: bool) -> Self {
+    self.discard_untimestamped_data = discard;
+    self
+  }
+
+  pub fn message_tap(mut self, message_tap: Option<Arc<dyn MessageTap>>) -> Self {
+    self.message_tap = message_tap;
+    self
+  }
+
+  pub fn message_integrity(mut self, failure_counter: Option<Arc<AtomicU64>>) -> Self {
+    self.message_integrity_failures = failure_counter;
+    self
+  }
+
+  pub fn traffic_counters(mut self, traffic_counters: Option<Arc<TrafficCounters>>) -> Self {
+    self.traffic_counters = traffic_counters;
+    self
+  }
+
   pub fn reset(&mut self) {
     self.source_version = ProtocolVersion::THIS_IMPLEMENTATION;
     self.source_vendor_id = VendorId::VENDOR_UNKNOWN;
@@ -204,7 +258,31 @@ impl MessageReceiver {
     self.available_readers.get_mut(&reader_id)
   }
 
-  pub fn handle_received_packet(&mut self, msg_bytes: &Bytes) {
+  pub fn handle_received_packet(&mut self, msg_bytes: &Bytes, source: SocketAddr) {
+    if let Some(tap) = &self.message_tap {
+      tap.on_message(MessageDirection::Inbound, source, msg_bytes);
+    }
+    if let Some(counters) = &self.traffic_counters {
+      counters.count_received(msg_bytes.len());
+    }
+
+    let verified;
+    let msg_bytes = match &self.message_integrity_failures {
+      None => msg_bytes,
+      Some(failures) => match verify_and_strip_integrity_trailer(msg_bytes) {
+        Some(m) => {
+          verified = m;
+          &verified
+        }
+        None => {
+          warn!("Message from {source} failed integrity check. Dropping it.");
+          debug!("Data was {:?}", &msg_bytes);
+          failures.fetch_add(1, Ordering::Relaxed);
+          return;
+        }
+      },
+    };
+
     // Check for RTPS ping message. At least RTI implementation sends these.
     // What should we do with them? The spec does not say.
     if msg_bytes.len() < RTPS_MESSAGE_HEADER_SIZE {
@@ -505,6 +583,25 @@ impl MessageReceiver {
     let mr_state = self.clone_partial_message_receiver_state();
     let writer_entity_id = submessage.sender_entity_id();
     let source_guid_prefix = mr_state.source_guid_prefix;
+
+    // A DATA that is not preceded by INFO_TS in this message, e.g. from a peer
+    // that sends INFO_TS after DATA, has no source timestamp. It is not
+    // associated with any later INFO_TS. Normally, it is accepted with
+    // reception time only.
+    if self.discard_untimestamped_data
+      && mr_state.source_timestamp.is_none()
+      && writer_entity_id.entity_kind.is_user_defined()
+      && matches!(
+        submessage,
+        WriterSubmessage::Data(..) | WriterSubmessage::DataFrag(..)
+      )
+    {
+      debug!(
+        "Discarding DATA without source timestamp from {:?}",
+        GUID::new(source_guid_prefix, writer_entity_id)
+      );
+      return;
+    }
     let source_guid = &GUID {
       prefix: source_guid_prefix,
       entity_id: writer_entity_id,
@@ -549,6 +646,7 @@ impl MessageReceiver {
         target_reader.handle_heartbeat_msg(
           &heartbeat,
           flags.contains(HEARTBEAT_Flags::Final),
+          flags.contains(HEARTBEAT_Flags::Liveliness),
           &mr_state,
         );
       }
@@ -765,10 +863,11 @@ impl MessageReceiver {
       ReaderSubmessage::AckNack(acknack, _) => {
         // Note: This must not block, because the receiving end is the same thread,
         // i.e. blocking here is an instant deadlock.
-        match self
-          .acknack_sender
-          .try_send((self.source_guid_prefix, AckSubmessage::AckNack(acknack)))
-        {
+        match self.acknack_sender.try_send((
+          self.source_guid_prefix,
+          self.source_vendor_id,
+          AckSubmessage::AckNack(acknack),
+        )) {
           Ok(_) => (),
           Err(TrySendError::Full(_)) => {
             info!("AckNack pipe full. Looks like I am very busy. Discarding submessage.");
@@ -777,8 +876,19 @@ impl MessageReceiver {
         }
       }
 
-      ReaderSubmessage::NackFrag(_, _) => {
-        // TODO: Implement NackFrag handling
+      ReaderSubmessage::NackFrag(nackfrag, _) => {
+        // Same pipe as AckNack, and same reason not to block.
+        match self.acknack_sender.try_send((
+          self.source_guid_prefix,
+          self.source_vendor_id,
+          AckSubmessage::NackFrag(nackfrag),
+        )) {
+          Ok(_) => (),
+          Err(TrySendError::Full(_)) => {
+            info!("AckNack pipe full. Looks like I am very busy. Discarding NackFrag.");
+          }
+          Err(e) => warn!("AckNack pipe fail: {:?}", e),
+        }
       }
     }
   }
@@ -790,23 +900,25 @@ impl MessageReceiver {
     encoded_submessage: Submessage,
     sec_postfix: &SecurePostfix,
   ) {
-    let security_plugins = self.security_plugins.clone();
-    let security_plugins = match security_plugins {
+    // The plugins are locked only for each call, because handling the decoded
+    // submessage may need them again.
+    let security_plugins = match self.security_plugins.clone() {
       None => {
         warn!("Cannot handle secure submessage: No security plugins configured.");
         return;
       }
-      Some(ref security_plugins_handle) => security_plugins_handle.get_plugins(),
+      Some(security_plugins_handle) => security_plugins_handle,
     };
 
     // Call 8.5.1.9.6 Operation: preprocess_secure_submsg to determine what
     // the submessage contains and then proceed to decode and process accordingly.
 
-    match security_plugins.preprocess_secure_submessage(
+    let category = security_plugins.get_plugins().preprocess_secure_submessage(
       sec_prefix,
       &self.source_guid_prefix,
       &self.dest_guid_prefix,
-    ) {
+    );
+    match category {
       Err(e) => {
         error!("{e:?}");
       }
@@ -815,14 +927,13 @@ impl MessageReceiver {
         // preprocess_secure_submsg": decoding does not apply to info
         // submessages. (But what if someone fakes them? Or must we secure whole
         // RTPS message then?)
-        drop(security_plugins);
         self.handle_submessage(encoded_submessage);
       }
       Ok(SecureSubmessageCategory::DatawriterSubmessage(sender_receiver_pairs)) => {
         for (sending_datawriter_crypto_handle, receiving_datareader_crypto_handle) in
           sender_receiver_pairs
         {
-          match security_plugins.decode_datawriter_submessage(
+          match security_plugins.get_plugins().decode_datawriter_submessage(
             (
               sec_prefix.clone(),
               encoded_submessage.clone(),
@@ -831,51 +942,11 @@ impl MessageReceiver {
             receiving_datareader_crypto_handle,
             sending_datawriter_crypto_handle,
           ) {
-            Ok(submessage) => {
-              let receiver_entity_id = submessage.receiver_entity_id();
-
-              // If the receiver entity ID is unknown, we try to find the correct id based on
-              // whether it matches the crypto handle
-              if receiver_entity_id == EntityId::UNKNOWN {
-                let sending_writer_entity_id = submessage.sender_entity_id();
-
-                if let Some(target_reader)=self.available_readers.values().find(|target_reader| {
-                  (
-                  // Reader must contain the writer
-                  target_reader.contains_writer(sending_writer_entity_id)
-                      // But there are two exceptions:
-                      // 1. SPDP reader must read from unknown SPDP writers
-                      //  TODO: This logic here is uglyish. Can we just inject a
-                      //  presupposed writer (proxy) to the built-in reader as it is created?
-                      || (sending_writer_entity_id == EntityId::SPDP_BUILTIN_PARTICIPANT_WRITER
-                        && target_reader.entity_id() == EntityId::SPDP_BUILTIN_PARTICIPANT_READER)
-                      // 2. ParticipantStatelessReader does not contain any writers, since it is stateless
-                      || (sending_writer_entity_id == EntityId::P2P_BUILTIN_PARTICIPANT_STATELESS_WRITER
-                        && target_reader.entity_id() == EntityId::P2P_BUILTIN_PARTICIPANT_STATELESS_READER)
-                      )
-                      &&
-                      security_plugins
-                      .confirm_local_endpoint_guid(receiving_datareader_crypto_handle,
-                        &GUID { prefix: self.dest_guid_prefix,entity_id: target_reader.entity_id() })
-                }){
-                  self.handle_writer_submessage(target_reader.entity_id(), submessage);
-                }else{
-                  error!("No reader matching the CryptoHandle found");
-                }
-              } else {
-                let receiver_guid = GUID {
-                  prefix: self.dest_guid_prefix,
-                  entity_id: receiver_entity_id,
-                };
-                if security_plugins
-                  .confirm_local_endpoint_guid(receiving_datareader_crypto_handle, &receiver_guid)
-                {
-                  self.handle_writer_submessage(receiver_entity_id, submessage);
-                } else {
-                  error!("Destination GUID did not match the handle used for decoding.");
-                }
-              }
-            }
+            Ok(decoded) => self.handle_decoded_writer_submessage(
+              &security_plugins,
+              decoded,
+              receiving_datareader_crypto_handle,
+            ),
             Err(sec_err) => {
               //TODO: Write to security log?
               warn!("Secured DatawriterSubmessage decode failed: {sec_err:?}");
@@ -887,7 +958,7 @@ impl MessageReceiver {
         for (sending_datareader_crypto_handle, receiving_datawriter_crypto_handle) in
           sender_receiver_pairs
         {
-          match security_plugins.decode_datareader_submessage(
+          match security_plugins.get_plugins().decode_datareader_submessage(
             (
               sec_prefix.clone(),
               encoded_submessage.clone(),
@@ -896,20 +967,11 @@ impl MessageReceiver {
             receiving_datawriter_crypto_handle,
             sending_datareader_crypto_handle,
           ) {
-            Ok(submessage) => {
-              let receiver_entity_id = submessage.receiver_entity_id();
-              let receiver_guid = GUID {
-                prefix: self.dest_guid_prefix,
-                entity_id: receiver_entity_id,
-              };
-              if security_plugins
-                .confirm_local_endpoint_guid(receiving_datawriter_crypto_handle, &receiver_guid)
-              {
-                self.handle_reader_submessage(submessage);
-              } else {
-                error!("Destination GUID did not match the handle used for decoding.");
-              }
-            }
+            Ok(decoded) => self.handle_decoded_reader_submessage(
+              &security_plugins,
+              decoded,
+              receiving_datawriter_crypto_handle,
+            ),
             Err(sec_err) => {
               //TODO: Write to security log?
               warn!("Secured DatareaderSubmessage decode failed: {sec_err:?}");
@@ -917,6 +979,154 @@ impl MessageReceiver {
           }
         }
       }
+      Ok(SecureSubmessageCategory::DatawriterOrDatareaderSubmessage(
+        writer_receiver_pairs,
+        reader_receiver_pairs,
+      )) => {
+        // The same key decodes both, so decode as a writer submessage and see
+        // which one was sent.
+        let mut decoded = writer_receiver_pairs.into_iter().filter_map(
+          |(sending_datawriter_crypto_handle, receiving_datareader_crypto_handle)| {
+            security_plugins
+              .get_plugins()
+              .decode_datawriter_submessage(
+                (
+                  sec_prefix.clone(),
+                  encoded_submessage.clone(),
+                  sec_postfix.clone(),
+                ),
+                receiving_datareader_crypto_handle,
+                sending_datawriter_crypto_handle,
+              )
+              .map(|decoded| (decoded, receiving_datareader_crypto_handle))
+              .ok()
+          },
+        );
+        match decoded.next() {
+          None => warn!("Secured submessage decode failed"),
+          Some((decoded @ SubmessageBody::Reader(_), _)) => {
+            for (_, receiving_datawriter_crypto_handle) in reader_receiver_pairs {
+              self.handle_decoded_reader_submessage(
+                &security_plugins,
+                decoded.clone(),
+                receiving_datawriter_crypto_handle,
+              );
+            }
+          }
+          Some(first) => {
+            for (decoded, receiving_datareader_crypto_handle) in
+              std::iter::once(first).chain(decoded)
+            {
+              self.handle_decoded_writer_submessage(
+                &security_plugins,
+                decoded,
+                receiving_datareader_crypto_handle,
+              );
+            }
+          }
+        }
+      }
+    }
+  }
+
+  // Handles a submessage decoded with the key of a remote DataWriter
+  #[cfg(feature = "security")]
+  fn handle_decoded_writer_submessage(
+    &mut self,
+    security_plugins: &SecurityPluginsHandle,
+    decoded: SubmessageBody,
+    receiving_datareader_crypto_handle: DatareaderCryptoHandle,
+  ) {
+    let submessage = match decoded {
+      SubmessageBody::Writer(submessage) => submessage,
+      SubmessageBody::Interpreter(submessage) => {
+        self.handle_interpreter_submessage(submessage);
+        return;
+      }
+      other => {
+        warn!("Expected a Writer submessage, but decoded {other:?}");
+        return;
+      }
+    };
+    let receiver_entity_id = submessage.receiver_entity_id();
+
+    // If the receiver entity ID is unknown, we try to find the correct id based on
+    // whether it matches the crypto handle
+    if receiver_entity_id == EntityId::UNKNOWN {
+      let sending_writer_entity_id = submessage.sender_entity_id();
+
+      if let Some(target_reader) = self.available_readers.values().find(|target_reader| {
+        (
+          // Reader must contain the writer
+          target_reader.contains_writer(sending_writer_entity_id)
+              // But there are two exceptions:
+              // 1. SPDP reader must read from unknown SPDP writers
+              //  TODO: This logic here is uglyish. Can we just inject a
+              //  presupposed writer (proxy) to the built-in reader as it is created?
+              || (sending_writer_entity_id == EntityId::SPDP_BUILTIN_PARTICIPANT_WRITER
+                && target_reader.entity_id() == EntityId::SPDP_BUILTIN_PARTICIPANT_READER)
+              // 2. ParticipantStatelessReader does not contain any writers, since it is stateless
+              || (sending_writer_entity_id == EntityId::P2P_BUILTIN_PARTICIPANT_STATELESS_WRITER
+                && target_reader.entity_id() == EntityId::P2P_BUILTIN_PARTICIPANT_STATELESS_READER)
+        ) && security_plugins.get_plugins().confirm_local_endpoint_guid(
+          receiving_datareader_crypto_handle,
+          &GUID {
+            prefix: self.dest_guid_prefix,
+            entity_id: target_reader.entity_id(),
+          },
+        )
+      }) {
+        self.handle_writer_submessage(target_reader.entity_id(), submessage);
+      } else {
+        error!("No reader matching the CryptoHandle found");
+      }
+    } else {
+      let receiver_guid = GUID {
+        prefix: self.dest_guid_prefix,
+        entity_id: receiver_entity_id,
+      };
+      if security_plugins
+        .get_plugins()
+        .confirm_local_endpoint_guid(receiving_datareader_crypto_handle, &receiver_guid)
+      {
+        self.handle_writer_submessage(receiver_entity_id, submessage);
+      } else {
+        error!("Destination GUID did not match the handle used for decoding.");
+      }
+    }
+  }
+
+  // Handles a submessage decoded with the key of a remote DataReader
+  #[cfg(feature = "security")]
+  fn handle_decoded_reader_submessage(
+    &mut self,
+    security_plugins: &SecurityPluginsHandle,
+    decoded: SubmessageBody,
+    receiving_datawriter_crypto_handle: DatawriterCryptoHandle,
+  ) {
+    let submessage = match decoded {
+      SubmessageBody::Reader(submessage) => submessage,
+      SubmessageBody::Interpreter(submessage) => {
+        self.handle_interpreter_submessage(submessage);
+        return;
+      }
+      other => {
+        warn!("Expected a Reader submessage, but decoded {other:?}");
+        return;
+      }
+    };
+    let receiver_entity_id = submessage.receiver_entity_id();
+    let receiver_guid = GUID {
+      prefix: self.dest_guid_prefix,
+      entity_id: receiver_entity_id,
+    };
+    if security_plugins
+      .get_plugins()
+      .confirm_local_endpoint_guid(receiving_datawriter_crypto_handle, &receiver_guid)
+    {
+      self.handle_reader_submessage(submessage);
+    } else {
+      error!("Destination GUID did not match the handle used for decoding.");
     }
   }
 
@@ -925,8 +1135,9 @@ impl MessageReceiver {
   {
     match interp_subm {
       InterpreterSubmessage::InfoTimestamp(ts_struct, _flags) => {
-        // flags value was used already when parsing timestamp into an Option
-        self.source_timestamp = ts_struct.timestamp;
+        // flags value was used already when parsing timestamp into an Option.
+        // TIME_INVALID is no timestamp either.
+        self.source_timestamp = ts_struct.timestamp.filter(|ts| *ts != Timestamp::INVALID);
       }
       InterpreterSubmessage::InfoSource(info_src, _flags) => {
         self.source_guid_prefix = info_src.guid_prefix;
@@ -1045,10 +1256,19 @@ mod tests {
     network::udp_sender::UDPSender,
     rtps::reader::ReaderIngredients,
     serialization::cdr_deserializer::deserialize_from_little_endian,
-    structure::{dds_cache::DDSCache, guid::EntityKind},
+    structure::{
+      dds_cache::{DDSCache, TopicCache},
+      guid::EntityKind,
+    },
   };
   use super::*;
 
+  // Where the test packets supposedly come from
+  const PEER: SocketAddr = SocketAddr::V4(std::net::SocketAddrV4::new(
+    std::net::Ipv4Addr::LOCALHOST,
+    7410,
+  ));
+
   #[test]
 
   fn test_shapes_demo_message_deserialization() {
@@ -1082,7 +1302,7 @@ mod tests {
 
     // Create a message receiver
     let (acknack_sender, _acknack_receiver) =
-      mio_channel::sync_channel::<(GuidPrefix, AckSubmessage)>(10);
+      mio_channel::sync_channel::<(GuidPrefix, VendorId, AckSubmessage)>(10);
     let (spdp_liveness_sender, _spdp_liveness_receiver) = mio_channel::sync_channel(8);
     let mut message_receiver = MessageReceiver::new(
       target_gui_prefix,
@@ -1126,6 +1346,7 @@ mod tests {
       data_reader_command_receiver: reader_command_receiver,
       data_reader_waker: data_reader_waker.clone(),
       poll_event_sender: notification_event_sender,
+      heartbeat_response_delay: std::time::Duration::ZERO,
       security_plugins: None,
     };
 
@@ -1147,7 +1368,7 @@ mod tests {
     // Add reader to message reader and process the bytes message
     message_receiver.add_reader(new_reader);
 
-    message_receiver.handle_received_packet(&udp_bits1);
+    message_receiver.handle_received_packet(&udp_bits1, PEER);
 
     // Verify the message reader has recorded the right amount of submessages
     assert_eq!(message_receiver.submessage_count, 4);
@@ -1184,6 +1405,158 @@ mod tests {
     assert_eq!(deserialized_shape_type.color, "RED");
   }
 
+  // INFO_DST, INFO_TS, DATA & HEARTBEAT from the ShapesDemo capture above
+  const SHAPES_DEMO_PACKET: [u8; 128] = [
+    0x52, 0x54, 0x50, 0x53, 0x02, 0x03, 0x01, 0x0f, 0x01, 0x0f, 0x99, 0x06, 0x78, 0x34, 0x00, 0x00,
+    0x01, 0x00, 0x00, 0x00, 0x0e, 0x01, 0x0c, 0x00, 0x01, 0x03, 0x00, 0x0c, 0x29, 0x2d, 0x31, 0xa2,
+    0x28, 0x20, 0x02, 0x08, 0x09, 0x01, 0x08, 0x00, 0x1a, 0x15, 0xf3, 0x5e, 0x00, 0xcc, 0xfb, 0x13,
+    0x15, 0x05, 0x2c, 0x00, 0x00, 0x00, 0x10, 0x00, 0x00, 0x00, 0x00, 0x07, 0x00, 0x00, 0x01, 0x02,
+    0x00, 0x00, 0x00, 0x00, 0x5b, 0x00, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x04, 0x00, 0x00, 0x00,
+    0x52, 0x45, 0x44, 0x00, 0x69, 0x00, 0x00, 0x00, 0x17, 0x00, 0x00, 0x00, 0x1e, 0x00, 0x00, 0x00,
+    0x07, 0x01, 0x1c, 0x00, 0x00, 0x00, 0x00, 0x07, 0x00, 0x00, 0x01, 0x02, 0x00, 0x00, 0x00, 0x00,
+    0x5b, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x5b, 0x00, 0x00, 0x00, 0x1f, 0x00, 0x00, 0x00,
+  ];
+
+  // A MessageReceiver with one reader that is matched to the ShapesDemo writer
+  fn shapes_demo_receiver(
+    discard_untimestamped_data: bool,
+  ) -> (MessageReceiver, Arc<Mutex<TopicCache>>) {
+    let target_gui_prefix = GuidPrefix::new(&[
+      0x01, 0x03, 0x00, 0x0c, 0x29, 0x2d, 0x31, 0xa2, 0x28, 0x20, 0x02, 0x08,
+    ]);
+    let remote_writer_guid = GUID::new(
+      GuidPrefix::new(&[
+        0x01, 0x0f, 0x99, 0x06, 0x78, 0x34, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00,
+      ]),
+      EntityId::create_custom_entity_id([0, 0, 1], EntityKind::WRITER_WITH_KEY_USER_DEFINED),
+    );
+
+    let (acknack_sender, _acknack_receiver) =
+      mio_channel::sync_channel::<(GuidPrefix, VendorId, AckSubmessage)>(10);
+    let (spdp_liveness_sender, _spdp_liveness_receiver) = mio_channel::sync_channel(8);
+    let mut message_receiver = MessageReceiver::new(
+      target_gui_prefix,
+      acknack_sender,
+      spdp_liveness_sender,
+      None,
+    )
+    .discard_untimestamped_data(discard_untimestamped_data);
+
+    let entity =
+      EntityId::create_custom_entity_id([0, 0, 0], EntityKind::READER_WITH_KEY_USER_DEFINED);
+    let (notification_sender, _notification_receiver) = mio_channel::sync_channel::<()>(100);
+    let (_notification_event_source, notification_event_sender) =
+      mio_source::make_poll_channel().unwrap();
+    let (status_sender, _status_receiver) = sync_status_channel::<DataReaderStatus>(4).unwrap();
+    let (_reader_command_sender, reader_command_receiver) =
+      mio_channel::sync_channel::<ReaderCommand>(10);
+    let qos_policy = QosPolicies::qos_none();
+    let dds_cache = Arc::new(RwLock::new(DDSCache::new()));
+    let topic_cache_handle = dds_cache.write().unwrap().add_new_topic(
+      "test".to_string(),
+      TypeDesc::new("test".to_string()),
+      &qos_policy,
+    );
+    let reader_ing = ReaderIngredients {
+      guid: GUID::new_with_prefix_and_id(target_gui_prefix, entity),
+      notification_sender,
+      status_sender,
+      topic_name: "test".to_string(),
+      topic_cache_handle: topic_cache_handle.clone(),
+      like_stateless: false,
+      qos_policy,
+      data_reader_command_receiver: reader_command_receiver,
+      data_reader_waker: Arc::new(Mutex::new(None)),
+      poll_event_sender: notification_event_sender,
+      heartbeat_response_delay: std::time::Duration::ZERO,
+      security_plugins: None,
+    };
+    let mut reader = Reader::new(
+      reader_ing,
+      Rc::new(UDPSender::new_with_random_port().unwrap()),
+      mio_extras::timer::Builder::default().build(),
+    );
+    reader.matched_writer_add(
+      remote_writer_guid,
+      EntityId::UNKNOWN,
+      vec![],
+      vec![],
+      &QosPolicies::qos_none(),
+    );
+    message_receiver.add_reader(reader);
+
+    (message_receiver, topic_cache_handle)
+  }
+
+  fn received_source_timestamps(topic_cache: &Arc<Mutex<TopicCache>>) -> Vec<Option<Timestamp>> {
+    topic_cache
+      .lock()
+      .unwrap()
+      .get_changes_in_range_best_effort(Timestamp::ZERO, Timestamp::now())
+      .map(|(_, cc)| cc.write_options.source_timestamp())
+      .collect()
+  }
+
+  #[test]
+  fn data_before_info_ts() {
+    // Same packet, but INFO_TS comes after DATA
+    let packet = &SHAPES_DEMO_PACKET;
+    let reordered = Bytes::from(
+      [
+        &packet[..36],
+        &packet[48..96],
+        &packet[36..48],
+        &packet[96..],
+      ]
+      .concat(),
+    );
+
+    // DATA is accepted without a source timestamp
+    let (mut message_receiver, topic_cache) = shapes_demo_receiver(false);
+    message_receiver.handle_received_packet(&reordered, PEER);
+    assert_eq!(message_receiver.submessage_count, 4);
+    assert_eq!(received_source_timestamps(&topic_cache), vec![None]);
+
+    let (mut message_receiver, topic_cache) = shapes_demo_receiver(false);
+    message_receiver.handle_received_packet(&Bytes::from_static(packet), PEER);
+    let timestamps = received_source_timestamps(&topic_cache);
+    assert_eq!(timestamps.len(), 1);
+    assert!(timestamps[0].is_some());
+
+    // DATA is discarded, but the same sample preceded by INFO_TS is accepted
+    let (mut message_receiver, topic_cache) = shapes_demo_receiver(true);
+    message_receiver.handle_received_packet(&reordered, PEER);
+    assert!(received_source_timestamps(&topic_cache).is_empty());
+    message_receiver.handle_received_packet(&Bytes::from_static(packet), PEER);
+    assert_eq!(received_source_timestamps(&topic_cache), timestamps);
+  }
+
+  #[test]
+  fn integrity_check_rejects_corrupted_message() {
+    let protected =
+      crate::network::message_integrity::append_integrity_trailer(&SHAPES_DEMO_PACKET);
+    let mut corrupted = protected.clone();
+    corrupted[100] ^= 0x01; // in DATA payload
+
+    let failures = Arc::new(AtomicU64::new(0));
+    let (message_receiver, topic_cache) = shapes_demo_receiver(false);
+    let mut message_receiver = message_receiver.message_integrity(Some(failures.clone()));
+
+    message_receiver.handle_received_packet(&Bytes::from(corrupted), PEER);
+    assert_eq!(failures.load(Ordering::Relaxed), 1);
+    assert_eq!(message_receiver.submessage_count, 0);
+
+    // A message without a checksum is rejected, too
+    message_receiver.handle_received_packet(&Bytes::from_static(&SHAPES_DEMO_PACKET), PEER);
+    assert_eq!(failures.load(Ordering::Relaxed), 2);
+    assert!(received_source_timestamps(&topic_cache).is_empty());
+
+    message_receiver.handle_received_packet(&Bytes::from(protected), PEER);
+    assert_eq!(failures.load(Ordering::Relaxed), 2);
+    assert_eq!(message_receiver.submessage_count, 4);
+    assert_eq!(received_source_timestamps(&topic_cache).len(), 1);
+  }
+
   #[test]
   fn mr_test_submsg_count() {
     // Udp packet with INFO_DST, INFO_TS, DATA, HEARTBEAT
@@ -1209,15 +1582,15 @@ mod tests {
 
     let guid_new = GUID::default();
     let (acknack_sender, _acknack_receiver) =
-      mio_channel::sync_channel::<(GuidPrefix, AckSubmessage)>(10);
+      mio_channel::sync_channel::<(GuidPrefix, VendorId, AckSubmessage)>(10);
     let (spdp_liveness_sender, _spdp_liveness_receiver) = mio_channel::sync_channel(8);
     let mut message_receiver =
       MessageReceiver::new(guid_new.prefix, acknack_sender, spdp_liveness_sender, None);
 
-    message_receiver.handle_received_packet(&udp_bits1);
+    message_receiver.handle_received_packet(&udp_bits1, PEER);
     assert_eq!(message_receiver.submessage_count, 4);
 
-    message_receiver.handle_received_packet(&udp_bits2);
+    message_receiver.handle_received_packet(&udp_bits2, PEER);
     assert_eq!(message_receiver.submessage_count, 2);
   }
 
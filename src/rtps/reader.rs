@@ -1,23 +1,36 @@
 use std::{
-  collections::BTreeMap,
+  cmp::{max, min},
+  collections::{btree_map, BTreeMap, BTreeSet},
   fmt, iter,
   rc::Rc,
-  sync::{Arc, Mutex, MutexGuard},
+  sync::{Arc, Mutex, MutexGuard, TryLockError},
   task::Waker,
-  time::Duration as StdDuration,
+  time::{Duration as StdDuration, Instant},
 };
 
 use mio_06::Token;
-use mio_extras::{channel as mio_channel, timer::Timer};
+use mio_extras::{
+  channel as mio_channel,
+  timer::{Timeout, Timer},
+};
 use log::{debug, error, info, trace, warn};
 use enumflags2::BitFlags;
 use speedy::{Endianness, Writable};
+use rand::Rng;
 
 use crate::{
   dds::{
     ddsdata::DDSData,
-    qos::{policy, HasQoSPolicy, QosPolicies},
-    statusevents::{CountWithChange, DataReaderStatus, StatusChannelSender},
+    qos::{
+      policy,
+      property::{
+        READER_REORDERING_WINDOW_PROPERTY, READER_SEQUENCE_NUMBER_RESET_TOLERANCE_PROPERTY,
+      },
+      HasQoSPolicy, QosPolicies,
+    },
+    statusevents::{
+      CountWithChange, DataReaderStatus, IncompatibleQosCounter, StatusChannelSender,
+    },
     with_key::{
       datawriter::{WriteOptions, WriteOptionsBuilder},
       simpledatareader::ReaderCommand,
@@ -34,14 +47,23 @@ use crate::{
     vendor_id::VendorId,
   },
   mio_source,
-  network::udp_sender::UDPSender,
+  network::{
+    locator_selector::{LocatorSelectionContext, OutboundMessageKind},
+    udp_sender::UDPSender,
+  },
   rtps::{
-    fragment_assembler::FragmentAssembler, message_receiver::MessageReceiverState,
-    rtps_writer_proxy::RtpsWriterProxy, Message,
+    constant::{
+      DEFAULT_REORDERING_WINDOW, DEFERRED_CHANGES_RETRY_DELAY, FRAGMENT_ASSEMBLY_TIMEOUT,
+    },
+    fragment_assembler::FragmentAssembler,
+    message_receiver::MessageReceiverState,
+    rtps_writer_proxy::RtpsWriterProxy,
+    Message,
   },
   structure::{
     cache_change::{CacheChange, ChangeKind},
     dds_cache::TopicCache,
+    duration::Duration,
     entity::RTPSEntity,
     guid::{EntityId, GuidPrefix, GUID},
     locator::Locator,
@@ -49,6 +71,8 @@ use crate::{
     time::Timestamp,
   },
 };
+#[cfg(test)]
+use crate::dds::statusevents::{sync_status_channel, StatusChannelReceiver};
 #[cfg(feature = "security")]
 use super::Submessage;
 #[cfg(feature = "security")]
@@ -56,9 +80,52 @@ use crate::security::{security_plugins::SecurityPluginsHandle, SecurityResult};
 #[cfg(not(feature = "security"))]
 use crate::no_security::SecurityPluginsHandle;
 
+// A FragmentNumberSet can span at most 256 fragment numbers.
+// RTPS spec v2.5 Section "8.3.5.6 FragmentNumberSet"
+const MAX_NACKFRAG_SET_SIZE: u32 = 256;
+
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub(crate) enum TimedEvent {
   DeadlineMissedCheck,
+  LivelinessCheck,
+  SendAckNack(GUID), // delayed response to a HEARTBEAT from this Writer
+  StoreDeferredChanges,
+  ReleaseReorderedChanges,
+}
+
+// A response to a HEARTBEAT, waiting for heartbeat_response_delay to pass.
+// The contents of the ACKNACK are decided only when it is sent, so that
+// samples received in the meantime are not requested.
+struct PendingAckNack {
+  first_sn: SequenceNumber,
+  last_sn: SequenceNumber,
+  final_flag_set: bool,
+  reply_locators: Vec<Locator>,
+}
+
+// A change from a Writer, held back because it is in, or behind, a coherent
+// set that has not been completely received yet.
+enum WithheldChange {
+  Sample {
+    ddsdata: DDSData,
+    write_options: WriteOptions,
+  },
+  CoherentSetEnd,
+}
+
+// A change held in the reordering window of DESTINATION_ORDER
+// BySourceTimeStamp.
+struct ReorderedChange {
+  ddsdata: DDSData,
+  write_options: WriteOptions,
+  release_at: Instant,
+}
+
+// What we know of the coherent set at the head of the withheld changes
+enum CoherentSetStatus {
+  Complete { end: SequenceNumber }, // end is exclusive
+  Incomplete,
+  Lost,
 }
 
 // Some pieces necessary to construct a reader.
@@ -75,6 +142,8 @@ pub(crate) struct ReaderIngredients {
   pub data_reader_command_receiver: mio_channel::Receiver<ReaderCommand>,
   pub(crate) data_reader_waker: Arc<Mutex<Option<Waker>>>,
   pub(crate) poll_event_sender: mio_source::PollEventSender,
+  // Maximum random delay of ACKNACK responses to HEARTBEATs
+  pub(crate) heartbeat_response_delay: StdDuration,
 
   pub(crate) security_plugins: Option<SecurityPluginsHandle>,
 }
@@ -83,6 +152,54 @@ impl ReaderIngredients {
   pub fn alt_entity_token(&self) -> Token {
     self.guid.entity_id.as_alt_token()
   }
+
+  // Ingredients for a Reader in tests. The other ends of the channels are
+  // returned, so that they stay open as long as they are kept.
+  #[cfg(test)]
+  pub(crate) fn for_test(
+    guid: GUID,
+    topic_name: String,
+    topic_cache_handle: Arc<Mutex<TopicCache>>,
+    qos_policy: &QosPolicies,
+  ) -> (Self, ReaderChannels) {
+    let (notification_sender, notification_receiver) = mio_channel::sync_channel::<()>(100);
+    let (notification_event_source, notification_event_sender) =
+      mio_source::make_poll_channel().unwrap();
+    let (status_sender, status_receiver) = sync_status_channel::<DataReaderStatus>(16).unwrap();
+    let (reader_command_sender, reader_command_receiver) =
+      mio_channel::sync_channel::<ReaderCommand>(10);
+
+    let reader_ing = Self {
+      guid,
+      notification_sender,
+      status_sender,
+      topic_name,
+      topic_cache_handle,
+      like_stateless: false,
+      qos_policy: qos_policy.clone(),
+      data_reader_command_receiver: reader_command_receiver,
+      data_reader_waker: Arc::new(Mutex::new(None)),
+      poll_event_sender: notification_event_sender,
+      heartbeat_response_delay: StdDuration::ZERO,
+      security_plugins: None,
+    };
+    let channels = ReaderChannels {
+      notification_receiver,
+      status_receiver,
+      _notification_event_source: notification_event_source,
+      _reader_command_sender: reader_command_sender,
+    };
+    (reader_ing, channels)
+  }
+}
+
+// The other ends of the channels in ReaderIngredients::for_test
+#[cfg(test)]
+pub(crate) struct ReaderChannels {
+  pub notification_receiver: mio_channel::Receiver<()>,
+  pub status_receiver: StatusChannelReceiver<DataReaderStatus>,
+  _notification_event_source: mio_source::PollEventSource,
+  _reader_command_sender: mio_channel::SyncSender<ReaderCommand>,
 }
 
 impl fmt::Debug for ReaderIngredients {
@@ -112,6 +229,13 @@ pub(crate) struct Reader {
   reliability: policy::Reliability,
   // Reader stores a pointer to a mutex on the topic cache
   topic_cache: Arc<Mutex<TopicCache>>,
+  // If the topic cache is locked by someone else when a change is received, do
+  // not wait for it, but store the change later from a timed event.
+  defer_on_lock_contention: bool,
+  deferred_changes: Vec<CacheChange>,
+  // Writers whose reliably received mark must be updated with the deferred
+  // changes
+  deferred_writers: BTreeSet<GUID>,
 
   #[cfg(test)]
   seqnum_instant_map: BTreeMap<SequenceNumber, Timestamp>,
@@ -121,7 +245,18 @@ pub(crate) struct Reader {
 
   my_guid: GUID,
 
+  // Responses to HEARTBEATs are delayed randomly up to this
   heartbeat_response_delay: StdDuration,
+  // Tolerance for detecting Writers that reset their sequence numbers
+  sn_reset_tolerance: Option<u32>,
+  // Presentation QoS coherent_access: Withhold coherent sets until complete.
+  coherent_access: bool,
+  coherent_sets: BTreeMap<GUID, BTreeMap<SequenceNumber, WithheldChange>>,
+  // DestinationOrder BySourceTimeStamp: Received changes are held this long,
+  // and then stored in source timestamp order.
+  reordering_window: Option<StdDuration>,
+  reordering_buffer: BTreeMap<(Timestamp, GUID, SequenceNumber), ReorderedChange>,
+  pending_acknacks: BTreeMap<GUID, PendingAckNack>,
 
   // TODO: Implement (use) this
   #[allow(dead_code)]
@@ -134,9 +269,13 @@ pub(crate) struct Reader {
   writer_match_count_total: i32, // total count, never decreases
 
   requested_deadline_missed_count: i32,
-  offered_incompatible_qos_count: i32,
+  incompatible_qos_counter: IncompatibleQosCounter,
+  sample_lost_count: i32,
+  // Samples evicted from the topic cache, as last seen by us
+  topic_cache_evicted_count: u64,
 
   pub(crate) timed_event_timer: Timer<TimedEvent>,
+  liveliness_check_timeout: Option<Timeout>,
   pub(crate) data_reader_command_receiver: mio_channel::Receiver<ReaderCommand>,
   data_reader_waker: Arc<Mutex<Option<Waker>>>,
   poll_event_sender: mio_source::PollEventSender,
@@ -152,7 +291,10 @@ impl Reader {
     timed_event_timer: Timer<TimedEvent>,
   ) -> Self {
     // Verify that the topic cache corresponds to the topic of the Reader
-    let topic_cache_name = i.topic_cache_handle.lock().unwrap().topic_name();
+    let (topic_cache_name, topic_cache_evicted_count) = {
+      let tc = i.topic_cache_handle.lock().unwrap();
+      (tc.topic_name(), tc.evicted_count())
+    };
     if i.topic_name != topic_cache_name {
       panic!(
         "Topic name = {} and topic cache name = {} not equal when creating a Reader",
@@ -165,6 +307,47 @@ impl Reader {
       panic!("Attempted to create a stateless Reader with other than BestEffort reliability");
     }
 
+    let sn_reset_tolerance = i
+      .qos_policy
+      .get_optional_property(READER_SEQUENCE_NUMBER_RESET_TOLERANCE_PROPERTY)
+      .and_then(|value| match value.trim().parse::<u32>() {
+        Ok(0) => None,
+        Ok(tolerance) => Some(tolerance),
+        Err(e) => {
+          warn!(
+            "Bad {} value {:?}: {}. Ignoring. topic={:?}",
+            READER_SEQUENCE_NUMBER_RESET_TOLERANCE_PROPERTY, value, e, i.topic_name
+          );
+          None
+        }
+      });
+
+    let coherent_access = i
+      .qos_policy
+      .presentation()
+      .is_some_and(|presentation| presentation.coherent_access);
+
+    let reordering_window =
+      if i.qos_policy.destination_order() == Some(policy::DestinationOrder::BySourceTimeStamp) {
+        let window = i
+          .qos_policy
+          .get_optional_property(READER_REORDERING_WINDOW_PROPERTY)
+          .and_then(|value| match value.trim().parse::<u64>() {
+            Ok(millis) => Some(StdDuration::from_millis(millis)),
+            Err(e) => {
+              warn!(
+                "Bad {} value {:?}: {}. Ignoring. topic={:?}",
+                READER_REORDERING_WINDOW_PROPERTY, value, e, i.topic_name
+              );
+              None
+            }
+          })
+          .unwrap_or(DEFAULT_REORDERING_WINDOW);
+        Some(window).filter(|w| !w.is_zero())
+      } else {
+        None
+      };
+
     Self {
       notification_sender: i.notification_sender,
       status_sender: i.status_sender,
@@ -175,6 +358,9 @@ impl Reader {
         .reliability() // use qos specification
         .unwrap_or(policy::Reliability::BestEffort), // or default to BestEffort
       topic_cache: i.topic_cache_handle,
+      defer_on_lock_contention: false,
+      deferred_changes: Vec::new(),
+      deferred_writers: BTreeSet::new(),
       topic_name: i.topic_name,
       qos_policy: i.qos_policy,
 
@@ -182,15 +368,24 @@ impl Reader {
       seqnum_instant_map: BTreeMap::new(),
       my_guid: i.guid,
 
-      heartbeat_response_delay: StdDuration::new(0, 500_000_000), // 0,5sec
+      heartbeat_response_delay: i.heartbeat_response_delay,
+      sn_reset_tolerance,
+      coherent_access,
+      coherent_sets: BTreeMap::new(),
+      reordering_window,
+      reordering_buffer: BTreeMap::new(),
+      pending_acknacks: BTreeMap::new(),
       heartbeat_suppression_duration: StdDuration::new(0, 0),
       received_heartbeat_count: 0,
       fragment_assemblers: BTreeMap::new(),
       matched_writers: BTreeMap::new(),
       writer_match_count_total: 0,
       requested_deadline_missed_count: 0,
-      offered_incompatible_qos_count: 0,
+      sample_lost_count: 0,
+      topic_cache_evicted_count,
+      incompatible_qos_counter: IncompatibleQosCounter::default(),
       timed_event_timer,
+      liveliness_check_timeout: None,
       data_reader_command_receiver: i.data_reader_command_receiver,
       data_reader_waker: i.data_reader_waker,
       poll_event_sender: i.poll_event_sender,
@@ -198,6 +393,11 @@ impl Reader {
       security_plugins: i.security_plugins,
     }
   }
+
+  pub fn set_defer_on_lock_contention(&mut self, defer: bool) {
+    self.defer_on_lock_contention = defer;
+  }
+
   // TODO: check if it's necessary to implement different handlers for discovery
   // and user messages
 
@@ -225,6 +425,31 @@ impl Reader {
     }
   }
 
+  // Schedule the next check for the Writers whose liveliness is tracked per
+  // Writer. Check interval is the shortest lease duration.
+  fn set_liveliness_check_timer(&mut self) {
+    if let Some(timeout) = self.liveliness_check_timeout.take() {
+      self.timed_event_timer.cancel_timeout(&timeout);
+    }
+    let shortest_lease = self
+      .matched_writers
+      .values()
+      .filter_map(RtpsWriterProxy::liveliness_lease_duration)
+      .min();
+    if let Some(lease_duration) = shortest_lease {
+      trace!(
+        "GUID={:?} set_liveliness_check_timer: {:?}",
+        self.my_guid,
+        lease_duration
+      );
+      self.liveliness_check_timeout = Some(
+        self
+          .timed_event_timer
+          .set_timeout(lease_duration.to_std(), TimedEvent::LivelinessCheck),
+      );
+    }
+  }
+
   pub fn send_status_change(&self, change: DataReaderStatus) {
     match self.status_sender.try_send(change) {
       Ok(()) => (), // expected result
@@ -299,6 +524,26 @@ impl Reader {
           self.handle_requested_deadline_event();
           self.set_requested_deadline_check_timer(); // re-prime timer
         }
+        TimedEvent::LivelinessCheck => {
+          self.liveliness_check_timeout = None;
+          self.handle_liveliness_check_event();
+          self.set_liveliness_check_timer(); // re-prime timer
+        }
+        TimedEvent::SendAckNack(writer_guid) => {
+          self.send_pending_acknack(writer_guid);
+        }
+        TimedEvent::StoreDeferredChanges => {
+          self.store_deferred_changes(false);
+          if self.has_deferred_changes() {
+            self.timed_event_timer.set_timeout(
+              DEFERRED_CHANGES_RETRY_DELAY,
+              TimedEvent::StoreDeferredChanges,
+            );
+          }
+        }
+        TimedEvent::ReleaseReorderedChanges => {
+          self.release_reordered_changes();
+        }
       }
     }
   }
@@ -325,6 +570,92 @@ impl Reader {
     }
   }
 
+  fn handle_liveliness_check_event(&mut self) {
+    let now = Timestamp::now();
+    let mut lost_writers = Vec::new();
+    for (writer_guid, writer_proxy) in self.matched_writers.iter_mut() {
+      if writer_proxy.check_liveliness_lost(now) {
+        lost_writers.push(*writer_guid);
+      }
+    }
+    for writer_guid in lost_writers {
+      info!(
+        "Writer {:?} lost liveliness. topic={:?}",
+        writer_guid, self.topic_name
+      );
+      self
+        .topic_cache
+        .lock()
+        .unwrap()
+        .set_writer_alive(writer_guid, false);
+      self.send_liveliness_changed(-1, 1);
+    }
+  }
+
+  // Report current alive / not alive counts of matched Writers
+  fn send_liveliness_changed(&self, alive_change: i32, not_alive_change: i32) {
+    let alive_count = self
+      .matched_writers
+      .values()
+      .filter(|wp| wp.is_alive())
+      .count() as i32;
+    let not_alive_count = self.matched_writers.len() as i32 - alive_count;
+    self.send_status_change(DataReaderStatus::LivelinessChanged {
+      alive_total: CountWithChange::new(alive_count, alive_change),
+      not_alive_total: CountWithChange::new(not_alive_count, not_alive_change),
+    });
+  }
+
+  // Some message from the Writer was received, so it is alive.
+  fn writer_liveliness_asserted(&mut self, writer_guid: GUID, now: Timestamp) {
+    let became_alive = self
+      .matched_writer_mut(writer_guid)
+      .is_some_and(|wp| wp.assert_liveliness(now));
+    if became_alive {
+      info!(
+        "Writer {:?} regained liveliness. topic={:?}",
+        writer_guid, self.topic_name
+      );
+      self
+        .topic_cache
+        .lock()
+        .unwrap()
+        .set_writer_alive(writer_guid, true);
+      self.send_liveliness_changed(1, -1);
+    }
+  }
+
+  // Start tracking liveliness of a newly matched Writer, or restart it, if
+  // the Writer has changed its liveliness policy. Keeping the old state would
+  // apply the wrong lease to the Writer.
+  fn update_writer_liveliness(
+    &mut self,
+    writer_guid: GUID,
+    liveliness: Option<policy::Liveliness>,
+    new_proxy: bool,
+  ) {
+    let topic_name = &self.topic_name; // borrow fields separately
+    let became_alive = match self.matched_writers.get_mut(&writer_guid) {
+      Some(wp) if new_proxy || wp.liveliness() != liveliness => {
+        if !new_proxy {
+          info!(
+            "Writer {:?} changed liveliness from {:?} to {:?}. topic={:?}",
+            writer_guid,
+            wp.liveliness(),
+            liveliness,
+            topic_name
+          );
+        }
+        wp.reset_liveliness(liveliness, Timestamp::now())
+      }
+      _ => return, // no change
+    };
+    if became_alive {
+      self.send_liveliness_changed(1, -1);
+    }
+    self.set_liveliness_check_timer();
+  }
+
   fn handle_requested_deadline_event(&mut self) {
     debug!("handle_requested_deadline_event");
     for missed_deadline in self.calculate_if_requested_deadline_is_missed() {
@@ -369,12 +700,32 @@ impl Reader {
     }
 
     debug!("update_writer_proxy topic={:?}", self.topic_name);
-    match offered_qos.compliance_failure_wrt(&self.qos_policy) {
+    let writer_id = proxy.remote_writer_guid;
+    let incompatible_policies = offered_qos.compatible_with(&self.qos_policy);
+    match incompatible_policies.first() {
       None => {
         // success, update or insert
-        let writer_id = proxy.remote_writer_guid;
+        self.incompatible_qos_counter.forget(writer_id);
+        // The strength may have changed since the previous announcement.
+        // DataReaders arbitrate instance ownership with the new strength from
+        // now on.
+        let ownership_strength = match offered_qos.ownership() {
+          Some(policy::Ownership::Exclusive { strength }) => Some(strength),
+          _ => None,
+        };
+        self
+          .topic_cache
+          .lock()
+          .unwrap()
+          .set_writer_ownership_strength(writer_id, ownership_strength);
         let count_change = self.matched_writer_update(proxy);
+        self.update_writer_liveliness(writer_id, offered_qos.liveliness, count_change > 0);
         if count_change > 0 {
+          self
+            .topic_cache
+            .lock()
+            .unwrap()
+            .set_writer_alive(writer_id, true);
           self.writer_match_count_total += count_change;
           self.send_status_change(DataReaderStatus::SubscriptionMatched {
             total: CountWithChange::new(self.writer_match_count_total, count_change),
@@ -388,12 +739,16 @@ impl Reader {
       }
       Some(bad_policy_id) => {
         // no QoS match
-        self.offered_incompatible_qos_count += 1;
-        self.send_status_change(DataReaderStatus::RequestedIncompatibleQos {
-          count: CountWithChange::new(self.offered_incompatible_qos_count, 1),
-          last_policy_id: bad_policy_id,
-          policies: Vec::new(), // TODO. implementation missing
-        });
+        if let Some((count, last_policy_id, policies)) = self
+          .incompatible_qos_counter
+          .add(writer_id, &incompatible_policies)
+        {
+          self.send_status_change(DataReaderStatus::RequestedIncompatibleQos {
+            count,
+            last_policy_id,
+            policies,
+          });
+        }
         warn!("update_writer_proxy - QoS mismatch {:?}", bad_policy_id);
         info!(
           "update_writer_proxy - QoS mismatch: topic={:?} requested={:?}  offered={:?}",
@@ -415,8 +770,16 @@ impl Reader {
   }
 
   pub fn remove_writer_proxy(&mut self, writer_guid: GUID) {
+    self.incompatible_qos_counter.forget(writer_guid);
     if self.matched_writers.contains_key(&writer_guid) {
       self.matched_writers.remove(&writer_guid);
+      self.coherent_sets.remove(&writer_guid);
+      self.store_deferred_changes(true);
+      {
+        let mut topic_cache = self.topic_cache.lock().unwrap();
+        topic_cache.set_writer_ownership_strength(writer_guid, None);
+        topic_cache.set_writer_alive(writer_guid, false);
+      }
       self.send_status_change(DataReaderStatus::SubscriptionMatched {
         total: CountWithChange::new(self.writer_match_count_total, 0),
         current: CountWithChange::new(self.matched_writers.len() as i32, -1),
@@ -427,6 +790,9 @@ impl Reader {
   // Entire remote participant was lost.
   // Remove all remote writers belonging to it.
   pub fn participant_lost(&mut self, guid_prefix: GuidPrefix) {
+    self
+      .incompatible_qos_counter
+      .forget_participant(guid_prefix);
     let lost_writers: Vec<GUID> = self
       .matched_writers
       .range(guid_prefix.range())
@@ -501,10 +867,38 @@ impl Reader {
     }) {
       write_options_b = write_options_b.related_sample_identity(related_sample_identity);
     }
+    let lifespan = data.inline_qos.as_ref().and_then(|iqos| {
+      InlineQos::lifespan(iqos, ri).unwrap_or_else(|e| {
+        error!("Deserializing lifespan: {:?}", &e);
+        None
+      })
+    });
+    if let Some(expiration) =
+      self.sample_expiration(lifespan, mr_state.source_timestamp, receive_timestamp)
+    {
+      write_options_b = write_options_b.expiration(expiration);
+    }
 
     let writer_guid = GUID::new_with_prefix_and_id(mr_state.source_guid_prefix, data.writer_id);
     let writer_seq_num = data.writer_sn; // for borrow checker
 
+    // Check if the sample is in a coherent set, or ends one
+    match data.inline_qos.as_ref().and_then(|iqos| {
+      InlineQos::coherent_set(iqos, ri).unwrap_or_else(|e| {
+        error!("Deserializing coherent_set: {:?}", &e);
+        None
+      })
+    }) {
+      Some(SequenceNumber::SEQUENCENUMBER_UNKNOWN) if data.serialized_payload.is_none() => {
+        self.process_coherent_set_end_marker(writer_guid, writer_seq_num, receive_timestamp);
+        return;
+      }
+      Some(SequenceNumber::SEQUENCENUMBER_UNKNOWN) | None => (),
+      Some(coherent_set_start) => {
+        write_options_b = write_options_b.coherent_set(coherent_set_start);
+      }
+    }
+
     match self.data_to_ddsdata(data, data_flags) {
       Ok(ddsdata) => self.process_received_data(
         ddsdata,
@@ -528,22 +922,6 @@ impl Reader {
     let receive_timestamp = Timestamp::now();
     //trace!("DATAFRAG received topic={:?}", self.topic_name);
 
-    // check if this submessage is expired already
-    // TODO: Maybe this check is in the wrong place altogether? It should be
-    // done when Datareader fetches data for the application.
-    if let (Some(source_timestamp), Some(lifespan)) =
-      (mr_state.source_timestamp, self.qos().lifespan)
-    {
-      let elapsed = receive_timestamp.duration_since(source_timestamp);
-      if lifespan.duration < elapsed {
-        info!(
-          "DataFrag {:?} from {:?} lifespan exceeded. duration={:?} elapsed={:?}",
-          seq_num, writer_guid, lifespan.duration, elapsed
-        );
-        return;
-      }
-    }
-
     // parse write_options out of the message
     // TODO: This is almost duplicate code from DATA processing
     let mut write_options_b = WriteOptionsBuilder::new();
@@ -561,6 +939,40 @@ impl Reader {
     }) {
       write_options_b = write_options_b.related_sample_identity(related_sample_identity);
     }
+    if let Some(coherent_set_start) = datafrag.inline_qos.as_ref().and_then(|iqos| {
+      InlineQos::coherent_set(iqos, ri).unwrap_or_else(|e| {
+        error!("Deserializing coherent_set: {:?}", &e);
+        None
+      })
+    }) {
+      if coherent_set_start != SequenceNumber::SEQUENCENUMBER_UNKNOWN {
+        write_options_b = write_options_b.coherent_set(coherent_set_start);
+      }
+    }
+    let lifespan = datafrag.inline_qos.as_ref().and_then(|iqos| {
+      InlineQos::lifespan(iqos, ri).unwrap_or_else(|e| {
+        error!("Deserializing lifespan: {:?}", &e);
+        None
+      })
+    });
+    if let Some(expiration) =
+      self.sample_expiration(lifespan, mr_state.source_timestamp, receive_timestamp)
+    {
+      write_options_b = write_options_b.expiration(expiration);
+    }
+
+    // Fragments of a sample we already have, e.g. repair fragments arriving
+    // late, must not start a new assembly.
+    if self
+      .matched_writer(writer_guid)
+      .is_some_and(|wp| wp.should_ignore_change(seq_num))
+    {
+      debug!(
+        "DataFrag {:?} from {:?} is for an already received sample. Ignoring. topic={:?}",
+        seq_num, writer_guid, self.topic_name
+      );
+      return;
+    }
 
     // Feed to fragment assembler ...
     let writer_seq_num = datafrag.writer_sn; // for borrow checker
@@ -596,12 +1008,19 @@ impl Reader {
   }
 
   fn garbage_collect_fragments(&mut self) {
-    // TODO: On most calls, do nothing.
-    //
-    // If GC time/packet limit has been exceeded, iterate through
-    // fragment assemblers and discard those assembly buffers whose
-    // creation / modification timestamps look like it is no longer receiving
-    // data and can therefore be discarded.
+    // Discard those assembly buffers whose modification timestamps look like
+    // they are no longer receiving data. The writer has probably
+    // given up on the sample, or it was lost for good.
+    let expire_before = Timestamp::now() - Duration::from_std(FRAGMENT_ASSEMBLY_TIMEOUT);
+    for (writer_guid, fa) in self.fragment_assemblers.iter_mut() {
+      let discarded = fa.garbage_collect_before(expire_before);
+      if discarded > 0 {
+        info!(
+          "Discarded {} incomplete fragmented samples from {:?} on topic {}",
+          discarded, writer_guid, self.topic_name
+        );
+      }
+    }
   }
 
   fn missing_frags_for(
@@ -615,6 +1034,35 @@ impl Reader {
     )
   }
 
+  // The set of fragments to request in a NACK_FRAG for a partially received
+  // sample. Fragments above `last_available` are not requested, because the
+  // writer does not have them yet. The set is bounded to what a single
+  // FragmentNumberSet can carry. Anything beyond that is requested in a later
+  // round. Returns None if there is nothing to request.
+  fn nackfrag_set_for(
+    &self,
+    writer_guid: GUID,
+    seq: SequenceNumber,
+    last_available: Option<FragmentNumber>,
+  ) -> Option<FragmentNumberSet> {
+    let mut missing_frags = self.missing_frags_for(writer_guid, seq);
+    let first = missing_frags.next()?;
+    let window_end = first + FragmentNumber::new(MAX_NACKFRAG_SET_SIZE);
+    let within_limits =
+      |f: &FragmentNumber| *f < window_end && last_available.map_or(true, |last| *f <= last);
+    if !within_limits(&first) {
+      return None;
+    }
+    let missing_frags_set = iter::once(first) // "undo" the .next() above
+      .chain(missing_frags)
+      .take_while(within_limits)
+      .collect();
+    Some(FragmentNumberSet::from_base_and_set(
+      first,
+      &missing_frags_set,
+    ))
+  }
+
   fn is_frag_partially_received(&self, writer_guid: GUID, seq: SequenceNumber) -> bool {
     self
       .fragment_assemblers
@@ -622,6 +1070,22 @@ impl Reader {
       .map_or(false, |fa| fa.is_partially_received(seq))
   }
 
+  // Expiration time of a received sample: source timestamp, or reception time
+  // if there is none, plus LIFESPAN. The writer sends its LIFESPAN as inline
+  // QoS. Without that, our own LIFESPAN QoS applies.
+  fn sample_expiration(
+    &self,
+    inline_lifespan: Option<Duration>,
+    source_timestamp: Option<Timestamp>,
+    receive_timestamp: Timestamp,
+  ) -> Option<Timestamp> {
+    let lifespan = inline_lifespan.or_else(|| self.qos().lifespan.map(|l| l.duration))?;
+    if lifespan == Duration::DURATION_INFINITE {
+      return None;
+    }
+    Some(source_timestamp.unwrap_or(receive_timestamp) + lifespan)
+  }
+
   // common parts of processing DATA or a completed DATAFRAG (when all frags are
   // received)
   fn process_received_data(
@@ -641,7 +1105,32 @@ impl Reader {
       self.like_stateless,
     );
     if !self.like_stateless {
+      let sn_reset = match (
+        self.sn_reset_tolerance,
+        self.matched_writer_mut(writer_guid),
+      ) {
+        (Some(tolerance), Some(writer_proxy)) => {
+          writer_proxy.detect_sequence_number_reset(writer_sn, tolerance)
+        }
+        _ => false,
+      };
+      if sn_reset {
+        warn!(
+          "Writer {:?} seems to have reset its sequence numbers. Tracking it again from {:?}. \
+           topic={:?}",
+          writer_guid, writer_sn, self.topic_name
+        );
+        self.store_deferred_changes(true);
+        self
+          .topic_cache
+          .lock()
+          .unwrap()
+          .reset_sequence_numbers(writer_guid);
+      }
+
       let my_entity_id = self.my_guid.entity_id; // to please borrow checker
+      let best_effort = self.reliability == policy::Reliability::BestEffort;
+      let mut skipped = None;
       if let Some(writer_proxy) = self.matched_writer_mut(writer_guid) {
         if writer_proxy.should_ignore_change(writer_sn) {
           // change already present
@@ -655,8 +1144,13 @@ impl Reader {
             return;
           }
         }
+        // A BestEffort reader will not get back what it skips over.
+        if best_effort {
+          skipped = writer_proxy.skipped_changes(writer_sn);
+        }
         // Add the change and get the instant
         writer_proxy.received_changes_add(writer_sn, receive_timestamp);
+        self.writer_liveliness_asserted(writer_guid, receive_timestamp);
       } else {
         // no writer proxy found
         debug!(
@@ -670,10 +1164,53 @@ impl Reader {
           return;
         }
       }
+      if let Some((count, first, last)) = skipped {
+        self.report_lost_samples(count, Some((first, last)));
+      }
     } else {
       // stateless reader: nothing to do before making cache change
     }
 
+    // Coherent set members, and anything after them, wait until the set is
+    // complete.
+    if self.coherent_access
+      && (write_options.coherent_set().is_some() || self.coherent_sets.contains_key(&writer_guid))
+    {
+      self.coherent_sets.entry(writer_guid).or_default().insert(
+        writer_sn,
+        WithheldChange::Sample {
+          ddsdata,
+          write_options,
+        },
+      );
+      self.release_coherent_sets(writer_guid);
+      return;
+    }
+
+    // Add to own track-keeping data structure
+    #[cfg(test)]
+    self.seqnum_instant_map.insert(writer_sn, receive_timestamp);
+
+    if let Some(window) = self.reordering_window {
+      let source_timestamp = write_options
+        .source_timestamp()
+        .unwrap_or(receive_timestamp);
+      if self.reordering_buffer.is_empty() {
+        self
+          .timed_event_timer
+          .set_timeout(window, TimedEvent::ReleaseReorderedChanges);
+      }
+      self.reordering_buffer.insert(
+        (source_timestamp, writer_guid, writer_sn),
+        ReorderedChange {
+          ddsdata,
+          write_options,
+          release_at: Instant::now() + window,
+        },
+      );
+      return;
+    }
+
     self.make_cache_change(
       ddsdata,
       receive_timestamp,
@@ -682,39 +1219,290 @@ impl Reader {
       writer_sn,
     );
 
-    // Add to own track-keeping data structure
-    #[cfg(test)]
-    self.seqnum_instant_map.insert(writer_sn, receive_timestamp);
-
     self.notify_cache_change();
   }
 
-  fn data_to_ddsdata(
-    &self,
-    data: DecodedData,
-    data_flags: BitFlags<DATA_Flags>,
-  ) -> Result<DDSData, String> {
-    let representation_identifier = DATA_Flags::cdr_representation_identifier(data_flags);
-
-    match (
-      data.serialized_payload,
-      data_flags.contains(DATA_Flags::Data),
-      data_flags.contains(DATA_Flags::Key),
-    ) {
-      (Some(sp), true, false) => {
-        // data
-        Ok(DDSData::new(sp))
+  // Store the changes whose reordering window has passed, in source timestamp
+  // order. A change that was received later, but has an earlier source
+  // timestamp than the first held change, goes before it.
+  fn release_reordered_changes(&mut self) {
+    let now = Instant::now();
+    // Distinct, increasing receive timestamps keep the source timestamp order
+    // in the topic cache.
+    let mut receive_timestamp = Timestamp::now();
+    let mut released = false;
+    while let Some(entry) = self.reordering_buffer.first_entry() {
+      if entry.get().release_at > now {
+        break;
       }
+      let ((_source_timestamp, writer_guid, writer_sn), change) = entry.remove_entry();
+      self.make_cache_change(
+        change.ddsdata,
+        receive_timestamp,
+        change.write_options,
+        writer_guid,
+        writer_sn,
+      );
+      released = true;
+      receive_timestamp = max(
+        Timestamp::now(),
+        receive_timestamp + Duration::from_nanos(1),
+      );
+    }
+    if let Some(first) = self.reordering_buffer.values().next() {
+      self.timed_event_timer.set_timeout(
+        first.release_at.saturating_duration_since(now),
+        TimedEvent::ReleaseReorderedChanges,
+      );
+    }
+    if released {
+      self.notify_cache_change();
+    }
+  }
 
-      (Some(sp), false, true) => {
-        // key
-        Ok(DDSData::new_disposed_by_key(
-          Self::deduce_change_kind(&data.inline_qos, false, representation_identifier),
-          sp,
-        ))
+  // The end marker of a coherent set has a sequence number, but no sample.
+  fn process_coherent_set_end_marker(
+    &mut self,
+    writer_guid: GUID,
+    writer_sn: SequenceNumber,
+    receive_timestamp: Timestamp,
+  ) {
+    if self.like_stateless {
+      return;
+    }
+    let Some(writer_proxy) = self.matched_writer_mut(writer_guid) else {
+      return;
+    };
+    if writer_proxy.should_ignore_change(writer_sn) {
+      return;
+    }
+    writer_proxy.received_changes_add(writer_sn, receive_timestamp);
+    self.writer_liveliness_asserted(writer_guid, receive_timestamp);
+
+    if let Some(withheld) = self.coherent_sets.get_mut(&writer_guid) {
+      withheld.insert(writer_sn, WithheldChange::CoherentSetEnd);
+    }
+    self.release_coherent_sets(writer_guid);
+    // The marker may have moved the reliably received mark
+    let reliably_received_before = self.reliably_received_before(writer_guid);
+    if let Some(sn) = reliably_received_before {
+      self.store_deferred_changes(true);
+      self
+        .acquire_the_topic_cache_guard()
+        .mark_reliably_received_before(writer_guid, sn);
+    }
+  }
+
+  // Deliver withheld changes from a Writer to the topic cache, as far as
+  // coherent sets are complete. Incomplete sets that can no longer be
+  // completed are dropped.
+  fn release_coherent_sets(&mut self, writer_guid: GUID) {
+    let Some(mut withheld) = self.coherent_sets.remove(&writer_guid) else {
+      return;
+    };
+    let mut released = Vec::new();
+
+    while let Some((&first_sn, first)) = withheld.iter().next() {
+      let coherent_set_start = match first {
+        WithheldChange::Sample { write_options, .. } => write_options.coherent_set(),
+        WithheldChange::CoherentSetEnd => None,
+      };
+      let Some(start) = coherent_set_start else {
+        // Not in a coherent set
+        if let Some(WithheldChange::Sample {
+          ddsdata,
+          write_options,
+        }) = withheld.remove(&first_sn)
+        {
+          released.push((first_sn, ddsdata, write_options));
+        }
+        continue;
+      };
+
+      match self.coherent_set_status(writer_guid, &withheld, first_sn, start) {
+        CoherentSetStatus::Complete { end } => {
+          let rest = withheld.split_off(&end);
+          for (sn, change) in std::mem::replace(&mut withheld, rest) {
+            if let WithheldChange::Sample {
+              ddsdata,
+              write_options,
+            } = change
+            {
+              released.push((sn, ddsdata, write_options));
+            }
+          }
+        }
+        CoherentSetStatus::Incomplete => break,
+        CoherentSetStatus::Lost => {
+          let members: Vec<SequenceNumber> = withheld
+            .iter()
+            .take_while(|(_, change)| {
+              matches!(change, WithheldChange::Sample { write_options, .. }
+                if write_options.coherent_set() == Some(start))
+            })
+            .map(|(sn, _)| *sn)
+            .collect();
+          info!(
+            "Dropping incomplete coherent set {:?} ({} samples) from {:?} topic={:?}",
+            start,
+            members.len(),
+            writer_guid,
+            self.topic_name
+          );
+          for sn in members {
+            withheld.remove(&sn);
+          }
+        }
       }
+    }
 
-      (None, false, false) => {
+    if !withheld.is_empty() {
+      self.coherent_sets.insert(writer_guid, withheld);
+    }
+
+    if released.is_empty() {
+      // Dropping a set may still have moved the reliably received mark.
+      if let Some(sn) = self.reliably_received_before(writer_guid) {
+        self.store_deferred_changes(true);
+        self
+          .acquire_the_topic_cache_guard()
+          .mark_reliably_received_before(writer_guid, sn);
+      }
+      return;
+    }
+    // Released samples get fresh, distinct receive timestamps, so that they
+    // are not behind anything that the DataReader has already seen.
+    let mut receive_timestamp = Timestamp::now();
+    for (writer_sn, ddsdata, write_options) in released {
+      self.make_cache_change(
+        ddsdata,
+        receive_timestamp,
+        write_options,
+        writer_guid,
+        writer_sn,
+      );
+      #[cfg(test)]
+      self.seqnum_instant_map.insert(writer_sn, receive_timestamp);
+      receive_timestamp = max(
+        Timestamp::now(),
+        receive_timestamp + Duration::from_nanos(1),
+      );
+    }
+    self.notify_cache_change();
+  }
+
+  // Decide if the coherent set, whose first withheld member is first_sn, can be
+  // delivered.
+  fn coherent_set_status(
+    &self,
+    writer_guid: GUID,
+    withheld: &BTreeMap<SequenceNumber, WithheldChange>,
+    first_sn: SequenceNumber,
+    start: SequenceNumber,
+  ) -> CoherentSetStatus {
+    // Members are contiguous from the start. The set ends at the first change
+    // that is not a member.
+    let mut missing = None;
+    let mut end = None;
+    if first_sn != start {
+      missing = Some(start);
+    } else {
+      let mut expected = first_sn;
+      for (sn, change) in withheld.range(first_sn..) {
+        if *sn != expected {
+          break;
+        }
+        match change {
+          WithheldChange::Sample { write_options, .. }
+            if write_options.coherent_set() == Some(start) =>
+          {
+            expected = expected + SequenceNumber::new(1);
+          }
+          _ => {
+            end = Some(*sn);
+            break;
+          }
+        }
+      }
+      if end.is_none() {
+        missing = Some(expected);
+      }
+    }
+
+    match (end, missing) {
+      (Some(end), _) => CoherentSetStatus::Complete { end },
+      (None, Some(missing)) => {
+        let lost = match self.matched_writer(writer_guid) {
+          None => true,
+          // Anything the writer proxy has seen, but we do not have, is gone.
+          Some(wp) if self.reliability != policy::Reliability::BestEffort => {
+            wp.should_ignore_change(missing)
+          }
+          // BestEffort: if something newer has arrived, the missing change is
+          // not coming.
+          Some(_) => withheld
+            .keys()
+            .next_back()
+            .is_some_and(|last| *last > missing),
+        };
+        if lost {
+          CoherentSetStatus::Lost
+        } else {
+          CoherentSetStatus::Incomplete
+        }
+      }
+      (None, None) => CoherentSetStatus::Incomplete,
+    }
+  }
+
+  // Withheld samples must not be skipped by the DataReader, so reliable
+  // reception is reported only up to the first withheld sample.
+  fn reliably_received_before(&self, writer_guid: GUID) -> Option<SequenceNumber> {
+    let all_ackable_before = self.matched_writer(writer_guid)?.all_ackable_before();
+    // Changes held in the reordering window are not in the topic cache yet.
+    let first_reordered = self
+      .reordering_buffer
+      .keys()
+      .filter(|(_, guid, _)| *guid == writer_guid)
+      .map(|(_, _, sn)| *sn)
+      .min();
+    Some(
+      self
+        .coherent_sets
+        .get(&writer_guid)
+        .and_then(|withheld| withheld.keys().next().copied())
+        .into_iter()
+        .chain(first_reordered)
+        .fold(all_ackable_before, min),
+    )
+  }
+
+  fn data_to_ddsdata(
+    &self,
+    data: DecodedData,
+    data_flags: BitFlags<DATA_Flags>,
+  ) -> Result<DDSData, String> {
+    let representation_identifier = DATA_Flags::cdr_representation_identifier(data_flags);
+
+    match (
+      data.serialized_payload,
+      data_flags.contains(DATA_Flags::Data),
+      data_flags.contains(DATA_Flags::Key),
+    ) {
+      (Some(sp), true, false) => {
+        // data
+        Ok(DDSData::new(sp))
+      }
+
+      (Some(sp), false, true) => {
+        // key
+        Ok(DDSData::new_disposed_by_key(
+          Self::deduce_change_kind(&data.inline_qos, false, representation_identifier),
+          sp,
+        ))
+      }
+
+      (None, false, false) => {
         // no data, no key. Maybe there is inline QoS?
         // At least we should find key hash, or we do not know WTF the writer is talking
         // about
@@ -790,6 +1578,7 @@ impl Reader {
     &mut self,
     heartbeat: &Heartbeat,
     final_flag_set: bool,
+    liveliness_flag_set: bool,
     mr_state: &MessageReceiverState,
   ) -> bool {
     let writer_guid =
@@ -816,6 +1605,15 @@ impl Reader {
       );
       return false;
     }
+    // A Writer with ManualByTopic liveliness asserts it with the liveliness
+    // flag. Its periodic HEARTBEATs do not count, or the lease would never
+    // expire.
+    let manual_by_topic = self
+      .matched_writer(writer_guid)
+      .is_some_and(|wp| wp.liveliness_lease_duration().is_some());
+    if liveliness_flag_set || !manual_by_topic {
+      self.writer_liveliness_asserted(writer_guid, Timestamp::now());
+    }
     // sanity check
     if heartbeat.first_sn < SequenceNumber::default() {
       warn!(
@@ -824,7 +1622,7 @@ impl Reader {
       );
     }
 
-    self
+    let acknack_sent = self
       .with_mutable_writer_proxy(writer_guid, |this, writer_proxy| {
         // Note: This is worker closure. Use `this` instead of `self`.
 
@@ -844,10 +1642,7 @@ impl Reader {
         // remove fragmented changes until first_sn.
         writer_proxy.irrelevant_changes_up_to(heartbeat.first_sn);
 
-        // let received_before = writer_proxy.all_ackable_before();
-        let reader_id = this.entity_id();
-
-        // See if ACKNACK is needed, and generate one.
+        // See if ACKNACK is needed.
         let missing_seqnums = writer_proxy.missing_seqnums(heartbeat.first_sn, heartbeat.last_sn);
 
         // Interpretation of final flag in RTPS spec
@@ -858,125 +1653,195 @@ impl Reader {
         // respond with an ACKNACK Message. The ACKNACK Message may acknowledge
         // having received all the data samples or may indicate that some data
         // samples are missing. The response may be delayed to avoid message storms.
+        if missing_seqnums.is_empty() && final_flag_set {
+          return false;
+        }
 
-        if !missing_seqnums.is_empty() || !final_flag_set {
-          let mut partially_received = Vec::new();
-          // report of what we have.
-          // We claim to have received all SNs before "base" and produce a set of missing
-          // sequence numbers that are >= base.
-          let reader_sn_state = match missing_seqnums.get(0) {
-            Some(&first_missing) => {
-              // Here we assume missing_seqnums are returned in order.
-              // Limit the set to maximum that can be sent in acknack submessage.
-
-              SequenceNumberSet::from_base_and_set(
-                first_missing,
-                &missing_seqnums
-                  .iter()
-                  .copied()
-                  .take_while(|sn| sn < &(first_missing + SequenceNumber::new(256)))
-                  .filter(|sn| {
-                    if this.is_frag_partially_received(writer_guid, *sn) {
-                      partially_received.push(*sn);
-                      false
-                    } else {
-                      true
-                    }
-                  })
-                  .collect(),
-              )
-            }
+        if this.heartbeat_response_delay.is_zero() {
+          this.send_heartbeat_response(writer_proxy, &missing_seqnums, &reply_locators);
+        } else {
+          this.schedule_heartbeat_response(writer_guid, heartbeat, final_flag_set, reply_locators);
+        }
+        true
+      }) // worker fn
+      .unwrap_or(false); // default false: no writer_proxy -> no acknack
 
-            // Nothing missing. Report that we have all we have.
-            None => SequenceNumberSet::new_empty(writer_proxy.all_ackable_before()),
-          };
-
-          let response_ack_nack = AckNack {
-            reader_id,
-            writer_id: heartbeat.writer_id,
-            reader_sn_state,
-            count: writer_proxy.next_ack_nack_sequence_number(),
-          };
-
-          // Sanity check
-          //
-          // Wrong. This sanity check is invalid. The condition
-          // ack_base > heartbeat.last_sn + 1
-          // May be legitimately true, if there are some changes available, and a GAP
-          // after that. E.g. HEARTBEAT 1..8 and GAP 9..10. Then acknack_base == 11
-          // and 11 > 8 + 1.
-          //
-          //
-          // if response_ack_nack.reader_sn_state.base() > heartbeat.last_sn +
-          // SequenceNumber::new(1) {   error!(
-          //     "OOPS! AckNack sanity check tripped: HEARTBEAT = {:?} ACKNACK = {:?}
-          // missing_seqnums = {:?} all_ackable_before = {:?} writer={:?}",
-          //     &heartbeat, &response_ack_nack, missing_seqnums,
-          // writer_proxy.all_ackable_before(), writer_guid,   );
-          // }
-
-          // The acknack can be sent now or later. The rest of the RTPS message
-          // needs to be constructed. p. 48
-          let flags = BitFlags::<ACKNACK_Flags>::from_flag(ACKNACK_Flags::Endianness)
-            | BitFlags::<ACKNACK_Flags>::from_flag(ACKNACK_Flags::Final);
-
-          let fflags = BitFlags::<NACKFRAG_Flags>::from_flag(NACKFRAG_Flags::Endianness);
-
-          // send NackFrags, if any
-          let mut nackfrags = Vec::new();
-          for sn in partially_received {
-            let count = writer_proxy.next_ack_nack_sequence_number();
-            let mut missing_frags = this.missing_frags_for(writer_guid, sn);
-            let first_missing = missing_frags.next();
-            if let Some(first) = first_missing {
-              let missing_frags_set = iter::once(first).chain(missing_frags).collect(); // "undo" the .next() above
-              let nf = NackFrag {
-                reader_id,
-                writer_id: writer_proxy.remote_writer_guid.entity_id,
-                writer_sn: sn,
-                fragment_number_state: FragmentNumberSet::from_base_and_set(
-                  first,
-                  &missing_frags_set,
-                ),
-                count,
-              };
-              nackfrags.push(nf);
-            } else {
-              error!("The dog ate my missing fragments.");
-              // Really, this should not happen, as we are above checking
-              // that this SN is really partially (and not fully) received.
-            }
-          }
+    // Changes no longer available from the writer may make a withheld coherent
+    // set impossible to complete.
+    self.release_coherent_sets(writer_guid);
+    acknack_sent
+  } // fn
 
-          if !nackfrags.is_empty() {
-            this.send_nackfrags_to(
-              fflags,
-              nackfrags,
-              InfoDestination {
-                guid_prefix: mr_state.source_guid_prefix,
-              },
-              &reply_locators,
-              writer_guid,
-            );
-          }
+  // Delay the response to a HEARTBEAT by a random time, so that Readers
+  // receiving the same (multicast) HEARTBEAT do not all respond at once.
+  fn schedule_heartbeat_response(
+    &mut self,
+    writer_guid: GUID,
+    heartbeat: &Heartbeat,
+    final_flag_set: bool,
+    reply_locators: Vec<Locator>,
+  ) {
+    match self.pending_acknacks.entry(writer_guid) {
+      btree_map::Entry::Occupied(mut entry) => {
+        // Already waiting. Respond to the newest HEARTBEAT, but keep the timer.
+        let pending = entry.get_mut();
+        pending.first_sn = heartbeat.first_sn;
+        pending.last_sn = heartbeat.last_sn;
+        pending.final_flag_set &= final_flag_set;
+        pending.reply_locators = reply_locators;
+      }
+      btree_map::Entry::Vacant(entry) => {
+        entry.insert(PendingAckNack {
+          first_sn: heartbeat.first_sn,
+          last_sn: heartbeat.last_sn,
+          final_flag_set,
+          reply_locators,
+        });
+        let delay = rand::thread_rng().gen_range(StdDuration::ZERO..=self.heartbeat_response_delay);
+        self
+          .timed_event_timer
+          .set_timeout(delay, TimedEvent::SendAckNack(writer_guid));
+      }
+    }
+  }
 
-          this.send_acknack_to(
-            flags,
-            response_ack_nack,
-            InfoDestination {
-              guid_prefix: mr_state.source_guid_prefix,
-            },
-            &reply_locators,
-            writer_guid,
-          );
+  fn send_pending_acknack(&mut self, writer_guid: GUID) {
+    let pending = match self.pending_acknacks.remove(&writer_guid) {
+      Some(p) => p,
+      None => return,
+    };
+    if !self.matched_writers.contains_key(&writer_guid) {
+      return; // Writer was unmatched while we were waiting.
+    }
+    self.with_mutable_writer_proxy(writer_guid, |this, writer_proxy| {
+      let missing_seqnums = writer_proxy.missing_seqnums(pending.first_sn, pending.last_sn);
+      if missing_seqnums.is_empty() && pending.final_flag_set {
+        // The missing samples arrived while we were waiting, e.g. as repairs
+        // requested by another Reader.
+        debug!(
+          "Suppressing redundant ACKNACK to {:?}. topic={:?} reader={:?}",
+          writer_guid, this.topic_name, this.my_guid
+        );
+        return;
+      }
+      this.send_heartbeat_response(writer_proxy, &missing_seqnums, &pending.reply_locators);
+    });
+  }
 
-          return true;
-        }
+  // Send ACKNACK and possibly NACK_FRAGs to a Writer, reporting
+  // what we have received and which of the samples announced in a HEARTBEAT
+  // are missing.
+  fn send_heartbeat_response(
+    &self,
+    writer_proxy: &mut RtpsWriterProxy,
+    missing_seqnums: &[SequenceNumber],
+    reply_locators: &[Locator],
+  ) {
+    let writer_guid = writer_proxy.remote_writer_guid;
+    let reader_id = self.entity_id();
+    let mut partially_received = Vec::new();
+    // report of what we have.
+    // We claim to have received all SNs before "base" and produce a set of missing
+    // sequence numbers that are >= base.
+    let reader_sn_state = match missing_seqnums.get(0) {
+      Some(&first_missing) => {
+        // Here we assume missing_seqnums are returned in order.
+        // Limit the set to maximum that can be sent in acknack submessage.
+
+        SequenceNumberSet::from_base_and_set(
+          first_missing,
+          &missing_seqnums
+            .iter()
+            .copied()
+            .take_while(|sn| sn < &(first_missing + SequenceNumber::new(256)))
+            .filter(|sn| {
+              if self.is_frag_partially_received(writer_guid, *sn) {
+                partially_received.push(*sn);
+                false
+              } else {
+                true
+              }
+            })
+            .collect(),
+        )
+      }
 
-        false
-      }) // worker fn
-      .unwrap_or(false) // default false: no writer_proxy -> no acknack
-  } // fn
+      // Nothing missing. Report that we have all we have.
+      None => SequenceNumberSet::new_empty(writer_proxy.all_ackable_before()),
+    };
+
+    let response_ack_nack = AckNack {
+      reader_id,
+      writer_id: writer_guid.entity_id,
+      reader_sn_state,
+      count: writer_proxy.next_ack_nack_sequence_number(),
+    };
+
+    // Sanity check
+    //
+    // Wrong. This sanity check is invalid. The condition
+    // ack_base > heartbeat.last_sn + 1
+    // May be legitimately true, if there are some changes available, and a GAP
+    // after that. E.g. HEARTBEAT 1..8 and GAP 9..10. Then acknack_base == 11
+    // and 11 > 8 + 1.
+    //
+    //
+    // if response_ack_nack.reader_sn_state.base() > heartbeat.last_sn +
+    // SequenceNumber::new(1) {   error!(
+    //     "OOPS! AckNack sanity check tripped: HEARTBEAT = {:?} ACKNACK = {:?}
+    // missing_seqnums = {:?} all_ackable_before = {:?} writer={:?}",
+    //     &heartbeat, &response_ack_nack, missing_seqnums,
+    // writer_proxy.all_ackable_before(), writer_guid,   );
+    // }
+
+    // The acknack can be sent now or later. The rest of the RTPS message
+    // needs to be constructed. p. 48
+    let flags = BitFlags::<ACKNACK_Flags>::from_flag(ACKNACK_Flags::Endianness)
+      | BitFlags::<ACKNACK_Flags>::from_flag(ACKNACK_Flags::Final);
+
+    let fflags = BitFlags::<NACKFRAG_Flags>::from_flag(NACKFRAG_Flags::Endianness);
+
+    // send NackFrags, if any
+    let mut nackfrags = Vec::new();
+    for sn in partially_received {
+      if let Some(fragment_number_state) = self.nackfrag_set_for(writer_guid, sn, None) {
+        let nf = NackFrag {
+          reader_id,
+          writer_id: writer_proxy.remote_writer_guid.entity_id,
+          writer_sn: sn,
+          fragment_number_state,
+          count: writer_proxy.next_ack_nack_sequence_number(),
+        };
+        nackfrags.push(nf);
+      } else {
+        error!("The dog ate my missing fragments.");
+        // Really, this should not happen, as we are above checking
+        // that this SN is really partially (and not fully) received.
+      }
+    }
+
+    if !nackfrags.is_empty() {
+      self.send_nackfrags_to(
+        fflags,
+        nackfrags,
+        InfoDestination {
+          guid_prefix: writer_guid.prefix,
+        },
+        reply_locators,
+        writer_guid,
+      );
+    }
+
+    self.send_acknack_to(
+      flags,
+      response_ack_nack,
+      InfoDestination {
+        guid_prefix: writer_guid.prefix,
+      },
+      reply_locators,
+      writer_guid,
+    );
+  }
 
   pub fn handle_gap_msg(&mut self, gap: &Gap, mr_state: &MessageReceiverState) {
     // ATM all things related to groups is ignored. TODO?
@@ -990,7 +1855,9 @@ impl Reader {
       );
       return;
     }
+    let reliable = self.reliability != policy::Reliability::BestEffort;
     let all_ackable_before;
+    let lost;
     {
       let writer_proxy = if let Some(wp) = self.matched_writer_mut(writer_guid) {
         wp
@@ -1023,6 +1890,19 @@ impl Reader {
       }
       // TODO: check that maximum(gap_list) - minimum(gap_list) < 256 ?
 
+      // A RELIABLE reader has lost what it has not received yet.
+      // TODO: If the the GAP message contained filteredCount (RTPS spec v2.5
+      // Table 8.43), then some of the not-available messages should not be
+      // treated as "lost" but "filtered".
+      lost = if reliable {
+        writer_proxy.not_received_in(
+          std::iter::once((gap.gap_start, gap.gap_list.base() - SequenceNumber::new(1)))
+            .chain(gap.gap_list.iter().map(|sn| (sn, sn))),
+        )
+      } else {
+        None
+      };
+
       // Irrelevant sequence numbers communicated in the Gap message are
       // composed of two groups:
       //   1. All sequence numbers in the range gapStart <= sequence_number <
@@ -1036,28 +1916,86 @@ impl Reader {
       all_ackable_before = writer_proxy.all_ackable_before();
     }
 
-    // Get the topic cache
-    let mut tc = self.acquire_the_topic_cache_guard();
-    tc.mark_reliably_received_before(writer_guid, all_ackable_before);
+    // The GAP may complete, or make impossible, a withheld coherent set.
+    self.release_coherent_sets(writer_guid);
+    let all_ackable_before = self
+      .reliably_received_before(writer_guid)
+      .unwrap_or(all_ackable_before);
 
-    // TODO: If receiving GAP actually moved the reliably received mark forward
-    // in the Topic Cache, then we should generate a SAMPLE_LOST status event
-    // from our Datareader (DDS Spec Section 2.2.4.1)
-    //
-    // If the the GAP message contained filteredCount (RTPS spec v2.5 Table
-    // 8.43), then some of the not-available messages should not be treated
-    // as "lost" but "filtered".
+    // Get the topic cache. Deferred changes go first, so that the DataReader
+    // does not skip them.
+    self.store_deferred_changes(true);
+    self
+      .acquire_the_topic_cache_guard()
+      .mark_reliably_received_before(writer_guid, all_ackable_before);
+
+    if let Some((count, first, last)) = lost {
+      self.report_lost_samples(count, Some((first, last)));
+    }
   }
 
+  // Returns if responding with NACK_FRAG
   pub fn handle_heartbeatfrag_msg(
     &mut self,
     heartbeatfrag: &HeartbeatFrag,
-    _mr_state: &MessageReceiverState,
-  ) {
-    info!(
-      "HeartbeatFrag handling not implemented. topic={:?}   {:?}",
-      self.topic_name, heartbeatfrag
-    );
+    mr_state: &MessageReceiverState,
+  ) -> bool {
+    let writer_guid =
+      GUID::new_with_prefix_and_id(mr_state.source_guid_prefix, heartbeatfrag.writer_id);
+
+    if self.reliability == policy::Reliability::BestEffort || self.like_stateless {
+      debug!(
+        "HEARTBEAT_FRAG from {:?}, but this Reader is BestEffort or stateless. Ignoring. \
+         topic={:?}",
+        writer_guid, self.topic_name
+      );
+      return false;
+    }
+
+    self
+      .with_mutable_writer_proxy(writer_guid, |this, writer_proxy| {
+        if heartbeatfrag.count <= writer_proxy.received_heartbeatfrag_count {
+          // Already seen
+          return false;
+        }
+        writer_proxy.received_heartbeatfrag_count = heartbeatfrag.count;
+
+        // Only partially received samples are repaired with NACK_FRAG. If we have
+        // no fragments at all, or the partial sample was discarded, ACKNACK
+        // will request the whole sample.
+        let fragment_number_state = match this.nackfrag_set_for(
+          writer_guid,
+          heartbeatfrag.writer_sn,
+          Some(heartbeatfrag.last_fragment_num),
+        ) {
+          Some(set) => set,
+          None => return false,
+        };
+
+        let reply_locators = match mr_state.unicast_reply_locator_list.as_slice() {
+          [] | [Locator::Invalid] => writer_proxy.unicast_locator_list.clone(),
+          others => others.to_vec(),
+        };
+
+        let nackfrag = NackFrag {
+          reader_id: this.entity_id(),
+          writer_id: heartbeatfrag.writer_id,
+          writer_sn: heartbeatfrag.writer_sn,
+          fragment_number_state,
+          count: writer_proxy.next_ack_nack_sequence_number(),
+        };
+        this.send_nackfrags_to(
+          BitFlags::<NACKFRAG_Flags>::from_flag(NACKFRAG_Flags::Endianness),
+          vec![nackfrag],
+          InfoDestination {
+            guid_prefix: mr_state.source_guid_prefix,
+          },
+          &reply_locators,
+          writer_guid,
+        );
+        true
+      })
+      .unwrap_or(false)
   }
 
   // This is used to determine exact change kind in case we do not get a data
@@ -1097,17 +2035,149 @@ impl Reader {
     writer_guid: GUID,
     writer_sn: SequenceNumber,
   ) {
-    let cache_change = CacheChange::new(writer_guid, writer_sn, write_options, data);
+    // Samples past their LIFESPAN are not stored, but they still count as
+    // received.
+    let cache_change = if write_options
+      .expiration()
+      .is_some_and(|expiration| expiration < Timestamp::now())
+    {
+      debug!(
+        "Sample {:?} from {:?} lifespan exceeded. Dropping it. topic={:?}",
+        writer_sn, writer_guid, self.topic_name
+      );
+      None
+    } else {
+      Some(CacheChange::new(
+        writer_guid,
+        writer_sn,
+        write_options,
+        data,
+      ))
+    };
+
+    // Changes must reach the topic cache in order, so if earlier changes are
+    // still deferred, this one is, too.
+    if self.has_deferred_changes() {
+      self.defer_change(writer_guid, cache_change);
+      self.store_deferred_changes(false);
+      return;
+    }
 
     // Get the topic cache
-    let mut tc = self.acquire_the_topic_cache_guard();
+    let topic_cache = self.topic_cache.clone();
+    let Some(mut tc) = self.lock_topic_cache(&topic_cache, false) else {
+      self.defer_change(writer_guid, cache_change);
+      return;
+    };
 
-    tc.add_change(&receive_timestamp, cache_change);
+    if let Some(cache_change) = cache_change {
+      tc.add_change(&receive_timestamp, cache_change);
+    }
     // Mark seqnums as received if not behaving statelessly
     if !self.like_stateless {
-      self.matched_writer(writer_guid).map(|wp| {
-        tc.mark_reliably_received_before(writer_guid, wp.all_ackable_before());
-      });
+      self
+        .reliably_received_before(writer_guid)
+        .map(|sn| tc.mark_reliably_received_before(writer_guid, sn));
+    }
+    let evicted_count = tc.evicted_count();
+    drop(tc);
+    self.report_evicted_samples(evicted_count);
+  }
+
+  // Samples evicted from the cache to keep within the memory budget may not
+  // have been read by the DataReader.
+  fn report_evicted_samples(&mut self, evicted_count: u64) {
+    if evicted_count > self.topic_cache_evicted_count {
+      let change = (evicted_count - self.topic_cache_evicted_count) as i32;
+      self.topic_cache_evicted_count = evicted_count;
+      self.report_lost_samples(change.into(), None);
+    }
+  }
+
+  fn report_lost_samples(
+    &mut self,
+    count: i64,
+    lost_range: Option<(SequenceNumber, SequenceNumber)>,
+  ) {
+    let change = i32::try_from(count).unwrap_or(i32::MAX);
+    self.sample_lost_count = self.sample_lost_count.saturating_add(change);
+    self.send_status_change(DataReaderStatus::SampleLost {
+      count: CountWithChange::new(self.sample_lost_count, change),
+      lost_range,
+    });
+  }
+
+  fn has_deferred_changes(&self) -> bool {
+    !self.deferred_changes.is_empty() || !self.deferred_writers.is_empty()
+  }
+
+  fn defer_change(&mut self, writer_guid: GUID, cache_change: Option<CacheChange>) {
+    if !self.has_deferred_changes() {
+      debug!(
+        "Topic cache busy. Deferring received changes. topic={:?}",
+        self.topic_name
+      );
+      self.timed_event_timer.set_timeout(
+        DEFERRED_CHANGES_RETRY_DELAY,
+        TimedEvent::StoreDeferredChanges,
+      );
+    }
+    self.deferred_changes.extend(cache_change);
+    self.deferred_writers.insert(writer_guid);
+  }
+
+  // Move deferred changes to the topic cache. Unless `block` is set, nothing
+  // is done if the topic cache is still busy.
+  fn store_deferred_changes(&mut self, block: bool) {
+    if !self.has_deferred_changes() {
+      return;
+    }
+    let topic_cache = self.topic_cache.clone();
+    let Some(mut tc) = self.lock_topic_cache(&topic_cache, block) else {
+      return;
+    };
+    // The changes get fresh, distinct receive timestamps, so that they are not
+    // behind anything that the DataReader has already seen.
+    let mut receive_timestamp = Timestamp::now();
+    for cache_change in std::mem::take(&mut self.deferred_changes) {
+      tc.add_change(&receive_timestamp, cache_change);
+      receive_timestamp = max(
+        Timestamp::now(),
+        receive_timestamp + Duration::from_nanos(1),
+      );
+    }
+    for writer_guid in std::mem::take(&mut self.deferred_writers) {
+      if !self.like_stateless {
+        if let Some(sn) = self.reliably_received_before(writer_guid) {
+          tc.mark_reliably_received_before(writer_guid, sn);
+        }
+      }
+    }
+    let evicted_count = tc.evicted_count();
+    drop(tc);
+    self.report_evicted_samples(evicted_count);
+    self.notify_cache_change();
+  }
+
+  // Returns None, if the topic cache is locked by someone else, and we should
+  // not wait for it.
+  fn lock_topic_cache<'a>(
+    &self,
+    topic_cache: &'a Mutex<TopicCache>,
+    block: bool,
+  ) -> Option<MutexGuard<'a, TopicCache>> {
+    let result = if block || !self.defer_on_lock_contention {
+      topic_cache.lock().map_err(TryLockError::from)
+    } else {
+      topic_cache.try_lock()
+    };
+    match result {
+      Ok(tc) => Some(tc),
+      Err(TryLockError::WouldBlock) => None,
+      Err(TryLockError::Poisoned(e)) => panic!(
+        "The topic cache of topic {} is poisoned. Error: {}",
+        &self.topic_name, e
+      ),
     }
   }
 
@@ -1144,16 +2214,27 @@ impl Reader {
   fn encode_and_send(
     &self,
     message: Message,
-    _destination_guid: GUID,
+    destination_guid: GUID,
     dst_locator_list: &[Locator],
   ) {
     let bytes = message
       .write_to_vec_with_ctx(Endianness::LittleEndian)
       .unwrap(); //TODO!
     let _dummy = message; // consume it to avoid clippy warning
+                          // Send failures are logged by UDPSender. A lost ACKNACK is replaced by the
+                          // response to the next HEARTBEAT.
+    let context = LocatorSelectionContext {
+      kind: OutboundMessageKind::ReaderToWriter,
+      local_guid: self.my_guid,
+      remote_guid: destination_guid,
+    };
     self
       .udp_sender
-      .send_to_locator_list(&bytes, dst_locator_list);
+      .send_to_locator_list(
+        &bytes,
+        &self.udp_sender.select_locators(&context, dst_locator_list),
+      )
+      .unwrap_or(());
   }
 
   #[cfg(feature = "security")]
@@ -1168,9 +2249,18 @@ impl Reader {
         let bytes = message
           .write_to_vec_with_ctx(Endianness::LittleEndian)
           .unwrap(); //TODO!!
+        let context = LocatorSelectionContext {
+          kind: OutboundMessageKind::ReaderToWriter,
+          local_guid: self.my_guid,
+          remote_guid: destination_guid,
+        };
         self
           .udp_sender
-          .send_to_locator_list(&bytes, dst_locator_list);
+          .send_to_locator_list(
+            &bytes,
+            &self.udp_sender.select_locators(&context, dst_locator_list),
+          )
+          .unwrap_or(()); // Failures are logged. See above.
       }
       Err(e) => error!("Failed to send message to writers. Encoding failed: {e:?}"),
     }
@@ -1358,10 +2448,12 @@ impl fmt::Debug for Reader {
 mod tests {
   use std::sync::RwLock;
 
+  use bytes::Bytes;
+
   use crate::{
     dds::{
       qos::policy::Reliability,
-      statusevents::{sync_status_channel, DataReaderStatus},
+      statusevents::DataReaderStatus,
       typedesc::TypeDesc,
       with_key::datawriter::WriteOptions,
     },
@@ -1369,63 +2461,42 @@ mod tests {
       dds_cache::DDSCache,
       guid::{EntityId, EntityKind, GUID},
     },
+    test::wait_until,
     Duration, QosPolicyBuilder,
   };
   use super::*;
 
-  #[test]
-  fn reader_sends_notification_when_receiving_data() {
-    // 1. Create a reader
-    // Create the DDS cache and a topic
+  // Ingredients of a Reader with the given QoS on a topic of its own
+  fn test_reader_ingredients(qos_policy: &QosPolicies) -> (ReaderIngredients, ReaderChannels) {
     let dds_cache = Arc::new(RwLock::new(DDSCache::new()));
     let topic_name = "test_name";
-    let qos_policy = QosPolicies::qos_none();
-
     let topic_cache_handle = dds_cache.write().unwrap().add_new_topic(
       topic_name.to_string(),
       TypeDesc::new("test_type".to_string()),
-      &qos_policy,
+      qos_policy,
     );
+    ReaderIngredients::for_test(
+      GUID::dummy_test_guid(EntityKind::READER_NO_KEY_USER_DEFINED),
+      topic_name.to_string(),
+      topic_cache_handle,
+      qos_policy,
+    )
+  }
 
-    // Create notification mechanisms
-    // mio-0.6 channel:
-    let (notification_sender, notification_receiver) = mio_channel::sync_channel::<()>(100);
-    // mio-0.8 event source and sender:
-    let (_notification_event_source, notification_event_sender) =
-      mio_source::make_poll_channel().unwrap();
-    // async notification waker
-    let data_reader_waker = Arc::new(Mutex::new(None));
+  #[test]
+  fn reader_sends_notification_when_receiving_data() {
+    // 1. Create a reader
+    let qos_policy = QosPolicies::qos_none();
+    let reader_guid = GUID::dummy_test_guid(EntityKind::READER_NO_KEY_USER_DEFINED);
+    let (reader_ing, channels) = test_reader_ingredients(&qos_policy);
+    let mut reader = Reader::new(
+      reader_ing,
+      Rc::new(UDPSender::new(0).unwrap()),
+      mio_extras::timer::Builder::default().build(),
+    );
 
-    // Create status channel
-    let (status_sender, _status_receiver) = sync_status_channel::<DataReaderStatus>(4).unwrap();
-
-    // Create reader command channel
-    let (_reader_command_sender, reader_command_receiver) =
-      mio_channel::sync_channel::<ReaderCommand>(10);
-
-    // Then finally create the reader
-    let reader_guid = GUID::dummy_test_guid(EntityKind::READER_NO_KEY_USER_DEFINED);
-    let reader_ing = ReaderIngredients {
-      guid: reader_guid,
-      notification_sender,
-      status_sender,
-      topic_name: topic_name.to_string(),
-      topic_cache_handle,
-      like_stateless: false,
-      qos_policy,
-      data_reader_command_receiver: reader_command_receiver,
-      data_reader_waker,
-      poll_event_sender: notification_event_sender,
-      security_plugins: None,
-    };
-    let mut reader = Reader::new(
-      reader_ing,
-      Rc::new(UDPSender::new(0).unwrap()),
-      mio_extras::timer::Builder::default().build(),
-    );
-
-    // 2. Add info of a matched writer to the reader
-    let writer_guid = GUID::dummy_test_guid(EntityKind::WRITER_NO_KEY_USER_DEFINED);
+    // 2. Add info of a matched writer to the reader
+    let writer_guid = GUID::dummy_test_guid(EntityKind::WRITER_NO_KEY_USER_DEFINED);
 
     let mr_state = MessageReceiverState {
       source_guid_prefix: writer_guid.prefix,
@@ -1453,7 +2524,7 @@ mod tests {
 
     // 5. Verify that the reader sends a notification about the new data
     assert!(
-      notification_receiver.try_recv().is_ok(),
+      channels.notification_receiver.try_recv().is_ok(),
       "Reader did not send a notification through the mio-0.6 channel"
     );
     // TODO: Should the other notification mechanisms (mio-0.8 & async) be also
@@ -1463,43 +2534,10 @@ mod tests {
   #[test]
   fn reader_sends_data_to_topic_cache() {
     // 1. Create a reader
-    // Create the DDS cache and a topic
-    let dds_cache = Arc::new(RwLock::new(DDSCache::new()));
-    let topic_name = "test_name";
     let qos_policy = QosPolicies::qos_none();
-
-    let topic_cache_handle = dds_cache.write().unwrap().add_new_topic(
-      topic_name.to_string(),
-      TypeDesc::new("test_type".to_string()),
-      &qos_policy,
-    );
-
-    // Create mechanisms for notifications, statuses & commands
-    let (notification_sender, _notification_receiver) = mio_channel::sync_channel::<()>(100);
-    let (_notification_event_source, notification_event_sender) =
-      mio_source::make_poll_channel().unwrap();
-    let data_reader_waker = Arc::new(Mutex::new(None));
-
-    let (status_sender, _status_receiver) = sync_status_channel::<DataReaderStatus>(4).unwrap();
-
-    let (_reader_command_sender, reader_command_receiver) =
-      mio_channel::sync_channel::<ReaderCommand>(10);
-
-    // Then create the reader
     let reader_guid = GUID::dummy_test_guid(EntityKind::READER_NO_KEY_USER_DEFINED);
-    let reader_ing = ReaderIngredients {
-      guid: reader_guid,
-      notification_sender,
-      status_sender,
-      topic_name: topic_name.to_string(),
-      topic_cache_handle: topic_cache_handle.clone(),
-      like_stateless: false,
-      qos_policy,
-      data_reader_command_receiver: reader_command_receiver,
-      data_reader_waker,
-      poll_event_sender: notification_event_sender,
-      security_plugins: None,
-    };
+    let (reader_ing, _channels) = test_reader_ingredients(&qos_policy);
+    let topic_cache_handle = reader_ing.topic_cache_handle.clone();
     let mut reader = Reader::new(
       reader_ing,
       Rc::new(UDPSender::new(0).unwrap()),
@@ -1559,50 +2597,95 @@ mod tests {
     );
   }
 
+  #[test]
+  fn reader_defers_changes_when_topic_cache_is_busy() {
+    let hold_time = StdDuration::from_millis(300);
+    let qos_policy = QosPolicies::qos_none();
+    let reader_guid = GUID::dummy_test_guid(EntityKind::READER_NO_KEY_USER_DEFINED);
+    let (reader_ing, _channels) = test_reader_ingredients(&qos_policy);
+    let topic_cache_handle = reader_ing.topic_cache_handle.clone();
+    let mut reader = Reader::new(
+      reader_ing,
+      Rc::new(UDPSender::new(0).unwrap()),
+      mio_extras::timer::Builder::default().build(),
+    );
+
+    let writer_guid = GUID::dummy_test_guid(EntityKind::WRITER_NO_KEY_USER_DEFINED);
+    let mr_state = MessageReceiverState {
+      source_guid_prefix: writer_guid.prefix,
+      source_timestamp: Some(Timestamp::now()),
+      ..Default::default()
+    };
+    reader.matched_writer_add(
+      writer_guid,
+      EntityId::UNKNOWN,
+      mr_state.unicast_reply_locator_list.clone(),
+      mr_state.multicast_reply_locator_list.clone(),
+      &QosPolicies::qos_none(),
+    );
+    let data_flags = BitFlags::<DATA_Flags>::from_flag(DATA_Flags::Data);
+    let data = |sn: i64| Data {
+      reader_id: reader_guid.entity_id,
+      writer_id: writer_guid.entity_id,
+      writer_sn: SequenceNumber::new(sn),
+      ..Data::default()
+    };
+    let cached_sequence_numbers = || -> Vec<SequenceNumber> {
+      topic_cache_handle
+        .lock()
+        .unwrap()
+        .get_changes_in_range_best_effort(Timestamp::ZERO, Timestamp::now())
+        .map(|(_, cc)| cc.sequence_number)
+        .collect()
+    };
+    // Another thread, e.g. a DataReader, holds the topic cache lock for a while.
+    // Returns how long the reader stalled when handling a DATA meanwhile.
+    let receive_while_busy = |reader: &mut Reader, sn: i64| {
+      let (locked_sender, locked_receiver) = std::sync::mpsc::channel();
+      let topic_cache = topic_cache_handle.clone();
+      let holder = std::thread::spawn(move || {
+        let _tc = topic_cache.lock().unwrap();
+        locked_sender.send(()).unwrap();
+        std::thread::sleep(hold_time);
+      });
+      locked_receiver.recv().unwrap();
+      let start = std::time::Instant::now();
+      reader.handle_data_msg(data(sn).no_crypto_decoded(), data_flags, &mr_state);
+      let stall = start.elapsed();
+      holder.join().unwrap();
+      stall
+    };
+
+    // By default, the reader waits for the lock.
+    let stall = receive_while_busy(&mut reader, 1);
+    assert!(stall >= hold_time * 9 / 10, "stall {stall:?}");
+    assert_eq!(cached_sequence_numbers(), vec![SequenceNumber::new(1)]);
+
+    // Deferring, the reader does not wait. The change is stored later.
+    reader.set_defer_on_lock_contention(true);
+    let stall = receive_while_busy(&mut reader, 2);
+    assert!(stall < hold_time / 3, "stall {stall:?}");
+    assert_eq!(cached_sequence_numbers(), vec![SequenceNumber::new(1)]);
+
+    wait_until(StdDuration::from_secs(2), || {
+      reader.handle_timed_event();
+      !reader.has_deferred_changes()
+    });
+    assert_eq!(
+      cached_sequence_numbers(),
+      vec![SequenceNumber::new(1), SequenceNumber::new(2)]
+    );
+  }
+
   #[test]
   fn reader_handles_heartbeats() {
     // 1. Create a reader for a topic with Reliable QoS
-    // Create the DDS cache and the topic
-    let dds_cache = Arc::new(RwLock::new(DDSCache::new()));
-    let topic_name = "test_name";
     let reliable_qos = QosPolicyBuilder::new()
       .reliability(Reliability::Reliable {
         max_blocking_time: Duration::from_millis(100),
       })
       .build();
-
-    let topic_cache_handle = dds_cache.write().unwrap().add_new_topic(
-      topic_name.to_string(),
-      TypeDesc::new("test_type".to_string()),
-      &reliable_qos,
-    );
-
-    // Create mechanisms for notifications, statuses & commands
-    let (notification_sender, _notification_receiver) = mio_channel::sync_channel::<()>(100);
-    let (_notification_event_source, notification_event_sender) =
-      mio_source::make_poll_channel().unwrap();
-    let data_reader_waker = Arc::new(Mutex::new(None));
-
-    let (status_sender, _status_receiver) = sync_status_channel::<DataReaderStatus>(4).unwrap();
-
-    let (_reader_command_sender, reader_command_receiver) =
-      mio_channel::sync_channel::<ReaderCommand>(10);
-
-    // Then create the reader
-    let reader_guid = GUID::dummy_test_guid(EntityKind::READER_NO_KEY_USER_DEFINED);
-    let reader_ing = ReaderIngredients {
-      guid: reader_guid,
-      notification_sender,
-      status_sender,
-      topic_name: topic_name.to_string(),
-      topic_cache_handle,
-      like_stateless: false,
-      qos_policy: reliable_qos.clone(),
-      data_reader_command_receiver: reader_command_receiver,
-      data_reader_waker,
-      poll_event_sender: notification_event_sender,
-      security_plugins: None,
-    };
+    let (reader_ing, _channels) = test_reader_ingredients(&reliable_qos);
     let mut reader = Reader::new(
       reader_ing,
       Rc::new(UDPSender::new(0).unwrap()),
@@ -1634,7 +2717,7 @@ mod tests {
       last_sn: SequenceNumber::new(0),
       count: 1,
     };
-    assert!(!reader.handle_heartbeat_msg(&hb_new, true, &mr_state)); // should be false, no ack
+    assert!(!reader.handle_heartbeat_msg(&hb_new, true, false, &mr_state)); // should be false, no ack
 
     // 4. Send the first proper heartbeat, reader should respond with acknack
     let hb_one = Heartbeat {
@@ -1644,12 +2727,12 @@ mod tests {
       last_sn: SequenceNumber::new(1),
       count: 2,
     };
-    assert!(reader.handle_heartbeat_msg(&hb_one, false, &mr_state)); // Should send an ack_nack
+    assert!(reader.handle_heartbeat_msg(&hb_one, false, false, &mr_state)); // Should send an ack_nack
 
     // 5. Send a duplicate of the first heartbeat, reader should not respond with
     // acknack
     let hb_one2 = hb_one.clone();
-    assert!(!reader.handle_heartbeat_msg(&hb_one2, false, &mr_state)); // No acknack
+    assert!(!reader.handle_heartbeat_msg(&hb_one2, false, false, &mr_state)); // No acknack
 
     // 6. Send a second proper heartbeat, reader should respond with acknack
     let hb_2 = Heartbeat {
@@ -1659,7 +2742,7 @@ mod tests {
       last_sn: SequenceNumber::new(3),  // writer has written 3 samples
       count: 3,
     };
-    assert!(reader.handle_heartbeat_msg(&hb_2, false, &mr_state)); // Should send an ack_nack
+    assert!(reader.handle_heartbeat_msg(&hb_2, false, false, &mr_state)); // Should send an ack_nack
 
     // 7. Count of acknack sent should be 2
     // The count is verified from the writer proxy
@@ -1670,45 +2753,119 @@ mod tests {
   }
 
   #[test]
-  fn reader_handles_gaps() {
-    // 1. Create a reader
-    // Create the DDS cache and a topic
-    let dds_cache = Arc::new(RwLock::new(DDSCache::new()));
-    let topic_name = "test_name";
-    let qos_policy = QosPolicies::qos_none();
-
-    let topic_cache_handle = dds_cache.write().unwrap().add_new_topic(
-      topic_name.to_string(),
-      TypeDesc::new("test_type".to_string()),
-      &qos_policy,
+  fn reader_requests_only_missing_fragments() {
+    // 1. Create a reliable reader and match a writer
+    let reliable_qos = QosPolicyBuilder::new()
+      .reliability(Reliability::Reliable {
+        max_blocking_time: Duration::from_millis(100),
+      })
+      .build();
+    let (reader_ing, _channels) = test_reader_ingredients(&reliable_qos);
+    let topic_cache_handle = reader_ing.topic_cache_handle.clone();
+    let mut reader = Reader::new(
+      reader_ing,
+      Rc::new(UDPSender::new(0).unwrap()),
+      mio_extras::timer::Builder::default().build(),
     );
 
-    // Create mechanisms for notifications, statuses & commands
-    let (notification_sender, _notification_receiver) = mio_channel::sync_channel::<()>(100);
-    let (_notification_event_source, notification_event_sender) =
-      mio_source::make_poll_channel().unwrap();
-    let data_reader_waker = Arc::new(Mutex::new(None));
+    let writer_guid = GUID::dummy_test_guid(EntityKind::WRITER_NO_KEY_USER_DEFINED);
+    let mr_state = MessageReceiverState {
+      source_guid_prefix: writer_guid.prefix,
+      ..Default::default()
+    };
+    reader.matched_writer_add(
+      writer_guid,
+      EntityId::UNKNOWN,
+      mr_state.unicast_reply_locator_list.clone(),
+      mr_state.multicast_reply_locator_list.clone(),
+      &reliable_qos,
+    );
 
-    let (status_sender, _status_receiver) = sync_status_channel::<DataReaderStatus>(4).unwrap();
+    // 2. Receive fragments 1, 2 and 4 of a sample of 5 fragments.
+    // The payload starts with a CDR_LE representation identifier.
+    let payload: Vec<u8> = [0x00, 0x01, 0x00, 0x00].into_iter().chain(1..=16).collect();
+    let sn = SequenceNumber::new(1);
+    let reader_id = reader.entity_id();
+    let datafrag = |frag_num: u32| DecodedDataFrag {
+      reader_id,
+      writer_id: writer_guid.entity_id,
+      writer_sn: sn,
+      fragment_starting_num: FragmentNumber::new(frag_num),
+      fragments_in_submessage: 1,
+      data_size: payload.len() as u32,
+      fragment_size: 4,
+      serialized_payload: Bytes::copy_from_slice(
+        &payload[(frag_num as usize - 1) * 4..frag_num as usize * 4],
+      ),
+      ..Default::default()
+    };
+    let flags = BitFlags::<DATAFRAG_Flags>::from_flag(DATAFRAG_Flags::Endianness);
+    for f in [1, 2, 4] {
+      reader.handle_datafrag_msg(&datafrag(f), flags, &mr_state);
+    }
 
-    let (_reader_command_sender, reader_command_receiver) =
-      mio_channel::sync_channel::<ReaderCommand>(10);
+    // 3. Only the dropped fragments are requested
+    let requested = |reader: &Reader, last_available| {
+      reader
+        .nackfrag_set_for(writer_guid, sn, last_available)
+        .map(|set| set.iter().map(u32::from).collect::<Vec<_>>())
+    };
+    assert_eq!(requested(&reader, None), Some(vec![3, 5]));
+    // Fragments the writer does not have yet are not requested
+    assert_eq!(
+      requested(&reader, Some(FragmentNumber::new(4))),
+      Some(vec![3])
+    );
 
-    // Then create the reader
-    let reader_guid = GUID::dummy_test_guid(EntityKind::READER_NO_KEY_USER_DEFINED);
-    let reader_ing = ReaderIngredients {
-      guid: reader_guid,
-      notification_sender,
-      status_sender,
-      topic_name: topic_name.to_string(),
-      topic_cache_handle,
-      like_stateless: false,
-      qos_policy,
-      data_reader_command_receiver: reader_command_receiver,
-      data_reader_waker,
-      poll_event_sender: notification_event_sender,
-      security_plugins: None,
+    let hb_frag = HeartbeatFrag {
+      reader_id: reader.entity_id(),
+      writer_id: writer_guid.entity_id,
+      writer_sn: sn,
+      last_fragment_num: FragmentNumber::new(5),
+      count: 1,
     };
+    assert!(reader.handle_heartbeatfrag_msg(&hb_frag, &mr_state)); // sends NACK_FRAG
+    assert!(!reader.handle_heartbeatfrag_msg(&hb_frag, &mr_state)); // duplicate
+
+    // 4. The partial sample expires. Late fragments must be handled gracefully.
+    reader
+      .fragment_assemblers
+      .get_mut(&writer_guid)
+      .unwrap()
+      .garbage_collect_before(Timestamp::now() + Duration::from_secs(1));
+    assert_eq!(requested(&reader, None), None);
+    for f in [3, 5] {
+      reader.handle_datafrag_msg(&datafrag(f), flags, &mr_state);
+    }
+    assert_eq!(requested(&reader, None), Some(vec![1, 2, 4]));
+
+    // 5. Completing the sample delivers it, and fragments arriving after that
+    // are ignored.
+    for f in [1, 2, 4] {
+      reader.handle_datafrag_msg(&datafrag(f), flags, &mr_state);
+    }
+    assert_eq!(requested(&reader, None), None);
+    reader.handle_datafrag_msg(&datafrag(3), flags, &mr_state);
+    assert!(!reader
+      .fragment_assemblers
+      .get(&writer_guid)
+      .unwrap()
+      .is_partially_received(sn));
+    assert_eq!(
+      topic_cache_handle
+        .lock()
+        .unwrap()
+        .get_changes_in_range_best_effort(Timestamp::ZERO, Timestamp::now())
+        .count(),
+      1
+    );
+  }
+
+  #[test]
+  fn reader_handles_gaps() {
+    // 1. Create a reader
+    let qos_policy = QosPolicies::qos_none();
+    let (reader_ing, _channels) = test_reader_ingredients(&qos_policy);
     let mut reader = Reader::new(
       reader_ing,
       Rc::new(UDPSender::new(0).unwrap()),
@@ -1805,44 +2962,13 @@ mod tests {
   #[test]
   fn stateless_reader_does_not_contain_writer_proxies() {
     // 1. Create a stateless-like reader
-    // Create the DDS cache and a topic
-    let dds_cache = Arc::new(RwLock::new(DDSCache::new()));
-    let topic_name = "test_name";
     let qos_policy = QosPolicies::builder()
       .reliability(Reliability::BestEffort) // Stateless needs to be BestEffort
       .build();
-
-    let topic_cache_handle = dds_cache.write().unwrap().add_new_topic(
-      topic_name.to_string(),
-      TypeDesc::new("test_type".to_string()),
-      &qos_policy,
-    );
-
-    // Create mechanisms for notifications, statuses & commands
-    let (notification_sender, _notification_receiver) = mio_channel::sync_channel::<()>(100);
-    let (_notification_event_source, notification_event_sender) =
-      mio_source::make_poll_channel().unwrap();
-    let data_reader_waker = Arc::new(Mutex::new(None));
-
-    let (status_sender, _status_receiver) = sync_status_channel::<DataReaderStatus>(4).unwrap();
-
-    let (_reader_command_sender, reader_command_receiver) =
-      mio_channel::sync_channel::<ReaderCommand>(10);
-
-    let like_stateless = true;
-    let reader_guid = GUID::dummy_test_guid(EntityKind::READER_NO_KEY_USER_DEFINED);
+    let (reader_ing, _channels) = test_reader_ingredients(&qos_policy);
     let reader_ing = ReaderIngredients {
-      guid: reader_guid,
-      notification_sender,
-      status_sender,
-      topic_name: topic_name.to_string(),
-      topic_cache_handle,
-      like_stateless,
-      qos_policy,
-      data_reader_command_receiver: reader_command_receiver,
-      data_reader_waker,
-      poll_event_sender: notification_event_sender,
-      security_plugins: None,
+      like_stateless: true,
+      ..reader_ing
     };
     let mut reader = Reader::new(
       reader_ing,
@@ -1870,4 +2996,337 @@ mod tests {
     // we attempted to add
     assert!(reader.matched_writer(writer_guid).is_none());
   }
+
+  #[test]
+  fn reader_tracks_writer_liveliness_kind_change() {
+    // 1. Create a reader
+    let qos_policy = QosPolicies::qos_none();
+    let (reader_ing, channels) = test_reader_ingredients(&qos_policy);
+    let mut reader = Reader::new(
+      reader_ing,
+      Rc::new(UDPSender::new(0).unwrap()),
+      mio_extras::timer::Builder::default().build(),
+    );
+
+    // 2. Match a writer with Automatic liveliness
+    let writer_guid = GUID::dummy_test_guid(EntityKind::WRITER_NO_KEY_USER_DEFINED);
+    let automatic = policy::Liveliness::Automatic {
+      lease_duration: Duration::from_millis(50),
+    };
+    let manual_by_topic = policy::Liveliness::ManualByTopic {
+      lease_duration: Duration::from_millis(50),
+    };
+    let writer_qos = |liveliness| QosPolicyBuilder::new().liveliness(liveliness).build();
+
+    reader.matched_writer_add(
+      writer_guid,
+      EntityId::UNKNOWN,
+      vec![],
+      vec![],
+      &writer_qos(automatic),
+    );
+    while channels.status_receiver.try_recv().is_ok() {} // discard the match status
+
+    // Automatic liveliness is not tracked per Writer, so the lease cannot
+    // expire here.
+    std::thread::sleep(StdDuration::from_millis(100));
+    reader.handle_liveliness_check_event();
+    assert!(reader.matched_writer(writer_guid).unwrap().is_alive());
+    assert!(channels.status_receiver.try_recv().is_err());
+
+    // 3. Writer re-announces itself with ManualByTopic liveliness.
+    // Tracking must restart from the change, not from the original match.
+    reader.matched_writer_add(
+      writer_guid,
+      EntityId::UNKNOWN,
+      vec![],
+      vec![],
+      &writer_qos(manual_by_topic),
+    );
+    let writer_proxy = reader.matched_writer(writer_guid).unwrap();
+    assert_eq!(writer_proxy.liveliness(), Some(manual_by_topic));
+    assert!(writer_proxy.is_alive());
+    reader.handle_liveliness_check_event();
+    assert!(channels.status_receiver.try_recv().is_err());
+
+    // 4. Writer does not assert liveliness within its lease
+    std::thread::sleep(StdDuration::from_millis(100));
+    reader.handle_liveliness_check_event();
+    assert!(!reader.matched_writer(writer_guid).unwrap().is_alive());
+    match channels.status_receiver.try_recv() {
+      Ok(DataReaderStatus::LivelinessChanged {
+        alive_total,
+        not_alive_total,
+      }) => {
+        assert_eq!(alive_total, CountWithChange::new(0, -1));
+        assert_eq!(not_alive_total, CountWithChange::new(1, 1));
+      }
+      other => panic!("Expected LivelinessChanged, got {other:?}"),
+    }
+
+    // 5. Writer goes back to Automatic. It must be considered alive again.
+    reader.matched_writer_add(
+      writer_guid,
+      EntityId::UNKNOWN,
+      vec![],
+      vec![],
+      &writer_qos(automatic),
+    );
+    assert!(reader.matched_writer(writer_guid).unwrap().is_alive());
+    match channels.status_receiver.try_recv() {
+      Ok(DataReaderStatus::LivelinessChanged {
+        alive_total,
+        not_alive_total,
+      }) => {
+        assert_eq!(alive_total, CountWithChange::new(1, 1));
+        assert_eq!(not_alive_total, CountWithChange::new(0, -1));
+      }
+      other => panic!("Expected LivelinessChanged, got {other:?}"),
+    }
+    std::thread::sleep(StdDuration::from_millis(100));
+    reader.handle_liveliness_check_event();
+    assert!(channels.status_receiver.try_recv().is_err());
+  }
+
+  #[test]
+  fn delayed_acknacks_are_staggered_and_suppressed() {
+    // Several Readers receive the same HEARTBEAT, as if it was multicast.
+    let max_delay = StdDuration::from_millis(200);
+    let reliable_qos = QosPolicyBuilder::new()
+      .reliability(Reliability::Reliable {
+        max_blocking_time: Duration::from_millis(100),
+      })
+      .build();
+    let writer_guid = GUID::dummy_test_guid(EntityKind::WRITER_NO_KEY_USER_DEFINED);
+    let mr_state = MessageReceiverState {
+      source_guid_prefix: writer_guid.prefix,
+      ..Default::default()
+    };
+
+    // Each Reader sends its ACKNACKs to a socket of its own, so that we can
+    // tell them apart.
+    let mut readers = Vec::new();
+    let mut sockets = Vec::new();
+    let mut keep_alive = Vec::new();
+    for _ in 0..8 {
+      let socket = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+      socket.set_nonblocking(true).unwrap();
+
+      let (reader_ing, channels) = test_reader_ingredients(&reliable_qos);
+      keep_alive.push(channels);
+      let reader_ing = ReaderIngredients {
+        heartbeat_response_delay: max_delay,
+        ..reader_ing
+      };
+      let mut reader = Reader::new(
+        reader_ing,
+        Rc::new(UDPSender::new(0).unwrap()),
+        mio_extras::timer::Builder::default()
+          .tick_duration(StdDuration::from_millis(10))
+          .build(),
+      );
+      reader.matched_writer_add(
+        writer_guid,
+        EntityId::UNKNOWN,
+        vec![Locator::from(socket.local_addr().unwrap())],
+        vec![],
+        &reliable_qos,
+      );
+      readers.push(reader);
+      sockets.push(socket);
+    }
+
+    // The Writer announces a sample that none of the Readers have.
+    let hb = Heartbeat {
+      reader_id: EntityId::UNKNOWN,
+      writer_id: writer_guid.entity_id,
+      first_sn: SequenceNumber::new(1),
+      last_sn: SequenceNumber::new(1),
+      count: 1,
+    };
+    let heartbeat_time = std::time::Instant::now();
+    for reader in readers.iter_mut() {
+      assert!(reader.handle_heartbeat_msg(&hb, true, false, &mr_state));
+    }
+    let mut buf = [0; 1500];
+    for socket in &sockets {
+      assert!(socket.recv(&mut buf).is_err(), "ACKNACK was not delayed");
+    }
+
+    // The first Reader receives the sample, e.g. as a repair requested by
+    // another Reader, before its response is due.
+    let data = Data {
+      reader_id: EntityId::UNKNOWN,
+      writer_id: writer_guid.entity_id,
+      writer_sn: SequenceNumber::new(1),
+      ..Data::default()
+    };
+    let data_flags = BitFlags::<DATA_Flags>::from_flag(DATA_Flags::Data);
+    readers[0].handle_data_msg(data.no_crypto_decoded(), data_flags, &mr_state);
+
+    let mut response_times = vec![None; readers.len()];
+    while heartbeat_time.elapsed() < max_delay + StdDuration::from_millis(300) {
+      for (i, reader) in readers.iter_mut().enumerate() {
+        reader.handle_timed_event();
+        while sockets[i].recv(&mut buf).is_ok() {
+          assert!(response_times[i].is_none(), "Reader {i} responded twice");
+          response_times[i] = Some(heartbeat_time.elapsed());
+        }
+      }
+      std::thread::sleep(StdDuration::from_millis(2));
+    }
+
+    // The redundant request was suppressed
+    assert_eq!(response_times[0], None);
+    // The others requested the sample within the delay, but not all at once.
+    let response_times: Vec<StdDuration> = response_times[1..]
+      .iter()
+      .map(|t| t.expect("Reader did not send ACKNACK"))
+      .collect();
+    assert!(response_times
+      .iter()
+      .all(|t| *t <= max_delay + StdDuration::from_millis(100)));
+    let first = response_times.iter().min().unwrap();
+    let last = response_times.iter().max().unwrap();
+    assert!(
+      *last - *first >= StdDuration::from_millis(10),
+      "ACKNACKs were not staggered: {response_times:?}"
+    );
+  }
+
+  #[test]
+  fn reader_follows_sequence_number_reset() {
+    let tolerance = 3;
+    let qos_policy = QosPolicyBuilder::new()
+      .reliability(Reliability::Reliable {
+        max_blocking_time: Duration::from_millis(100),
+      })
+      .property(policy::Property {
+        value: vec![crate::dds::qos::property::Property::local(
+          READER_SEQUENCE_NUMBER_RESET_TOLERANCE_PROPERTY,
+          &tolerance.to_string(),
+        )],
+        binary_value: vec![],
+      })
+      .build();
+    let reader_guid = GUID::dummy_test_guid(EntityKind::READER_NO_KEY_USER_DEFINED);
+    let (reader_ing, _channels) = test_reader_ingredients(&qos_policy);
+    let topic_cache_handle = reader_ing.topic_cache_handle.clone();
+    let mut reader = Reader::new(
+      reader_ing,
+      Rc::new(UDPSender::new(0).unwrap()),
+      mio_extras::timer::Builder::default().build(),
+    );
+
+    let writer_guid = GUID::dummy_test_guid(EntityKind::WRITER_NO_KEY_USER_DEFINED);
+    let mr_state = MessageReceiverState {
+      source_guid_prefix: writer_guid.prefix,
+      ..Default::default()
+    };
+    reader.matched_writer_add(writer_guid, EntityId::UNKNOWN, vec![], vec![], &qos_policy);
+
+    let data_flags = BitFlags::<DATA_Flags>::from_flag(DATA_Flags::Data);
+    let send_data = |reader: &mut Reader, sn: i64| {
+      let data = Data {
+        reader_id: reader_guid.entity_id,
+        writer_id: writer_guid.entity_id,
+        writer_sn: SequenceNumber::new(sn),
+        ..Data::default()
+      };
+      reader.handle_data_msg(data.no_crypto_decoded(), data_flags, &mr_state);
+    };
+    let cached_count = || {
+      topic_cache_handle
+        .lock()
+        .unwrap()
+        .get_changes_in_range_best_effort(Timestamp::ZERO, Timestamp::now())
+        .count()
+    };
+
+    for sn in 1..=20 {
+      send_data(&mut reader, sn);
+    }
+    assert_eq!(cached_count(), 20);
+
+    // The Writer starts again from 1. Samples before the tolerance is reached
+    // are ignored as duplicates.
+    for sn in 1..tolerance {
+      send_data(&mut reader, sn);
+    }
+    assert_eq!(cached_count(), 20);
+
+    // Consistent progress: tracking is reset, and data flows again.
+    for sn in tolerance..=tolerance + 2 {
+      send_data(&mut reader, sn);
+    }
+    assert_eq!(cached_count(), 23);
+    assert_eq!(
+      reader
+        .matched_writer(writer_guid)
+        .unwrap()
+        .all_ackable_before(),
+      SequenceNumber::new(tolerance + 3)
+    );
+    assert_eq!(
+      topic_cache_handle
+        .lock()
+        .unwrap()
+        .sequence_number_resets()
+        .get(&writer_guid),
+      Some(&1)
+    );
+  }
+
+  #[test]
+  fn best_effort_reader_reports_lost_samples() {
+    let qos_policy = QosPolicyBuilder::new()
+      .reliability(Reliability::BestEffort)
+      .build();
+    let reader_guid = GUID::dummy_test_guid(EntityKind::READER_NO_KEY_USER_DEFINED);
+    let (reader_ing, channels) = test_reader_ingredients(&qos_policy);
+    let mut reader = Reader::new(
+      reader_ing,
+      Rc::new(UDPSender::new(0).unwrap()),
+      mio_extras::timer::Builder::default().build(),
+    );
+
+    let writer_guid = GUID::dummy_test_guid(EntityKind::WRITER_NO_KEY_USER_DEFINED);
+    let mr_state = MessageReceiverState {
+      source_guid_prefix: writer_guid.prefix,
+      ..Default::default()
+    };
+    reader.matched_writer_add(writer_guid, EntityId::UNKNOWN, vec![], vec![], &qos_policy);
+    while channels.status_receiver.try_recv().is_ok() {} // discard the match status
+
+    let data_flags = BitFlags::<DATA_Flags>::from_flag(DATA_Flags::Data);
+    let send_data = |reader: &mut Reader, sn: i64| {
+      let data = Data {
+        reader_id: reader_guid.entity_id,
+        writer_id: writer_guid.entity_id,
+        writer_sn: SequenceNumber::new(sn),
+        ..Data::default()
+      };
+      reader.handle_data_msg(data.no_crypto_decoded(), data_flags, &mr_state);
+    };
+
+    // Joining in the middle of the stream is not a loss.
+    send_data(&mut reader, 5);
+    send_data(&mut reader, 6);
+    assert!(channels.status_receiver.try_recv().is_err());
+
+    // Sample 7 is dropped on the way.
+    send_data(&mut reader, 8);
+    match channels.status_receiver.try_recv() {
+      Ok(DataReaderStatus::SampleLost { count, lost_range }) => {
+        assert_eq!(count, CountWithChange::new(1, 1));
+        assert_eq!(
+          lost_range,
+          Some((SequenceNumber::new(7), SequenceNumber::new(7)))
+        );
+      }
+      other => panic!("Expected SampleLost, got {other:?}"),
+    }
+    send_data(&mut reader, 9);
+    assert!(channels.status_receiver.try_recv().is_err());
+  }
 }
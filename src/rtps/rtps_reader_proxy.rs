@@ -1,11 +1,14 @@
-use std::collections::{BTreeMap, BTreeSet};
+use std::{
+  cmp::max,
+  collections::{BTreeMap, BTreeSet},
+};
 
 use bit_vec::BitVec;
 #[allow(unused_imports)]
 use log::{debug, error, info, trace, warn};
 
 use crate::{
-  dds::{participant::DomainParticipant, qos::QosPolicies},
+  dds::{participant::DomainParticipant, qos::QosPolicies, with_key::ReaderReliabilityState},
   discovery::sedp_messages::DiscoveredReaderData,
   messages::submessages::submessage::AckSubmessage,
   rtps::constant::*,
@@ -13,6 +16,7 @@ use crate::{
     guid::{EntityId, GUID},
     locator::Locator,
     sequence_number::{FragmentNumber, FragmentNumberSet, SequenceNumber},
+    time::Timestamp,
   },
 };
 use super::reader::ReaderIngredients;
@@ -56,8 +60,18 @@ pub(crate) struct RtpsReaderProxy {
   // true = send repair data messages due to NACKs, buffer messages by DataWriter
   // false = send data messages directly from DataWriter
   pub repair_mode: bool,
+  // Consecutive failed sends to this Reader, see Writer::retry_send
+  pub send_retries: u32,
   qos: QosPolicies,
   frags_requested: BTreeMap<SequenceNumber, BitVec>,
+
+  // For ReaderReliabilityState reporting
+  last_heartbeat_sent: Option<Timestamp>,
+  last_acknack_received: Option<Timestamp>,
+
+  // Largest message the participant of the Reader has advertised it can
+  // receive, if any. Payloads are fragmented to fit.
+  pub max_message_size: Option<usize>,
 }
 
 impl RtpsReaderProxy {
@@ -73,8 +87,12 @@ impl RtpsReaderProxy {
       unsent_changes: BTreeSet::new(),
       pending_gap: BTreeSet::new(),
       repair_mode: false,
+      send_retries: 0,
       qos,
       frags_requested: BTreeMap::new(),
+      last_heartbeat_sent: None,
+      last_acknack_received: None,
+      max_message_size: None,
     }
   }
 
@@ -101,6 +119,7 @@ impl RtpsReaderProxy {
     }
 
     self.expects_in_line_qos = update.expects_in_line_qos;
+    self.max_message_size = update.max_message_size;
 
     if self.qos != update.qos {
       warn!("Upddate changes QoS in ReaderProxy.");
@@ -135,6 +154,11 @@ impl RtpsReaderProxy {
     self.unsent_changes.remove(&seq_num);
   }
 
+  // Used when sending failed locally, so the change must be sent again.
+  pub fn mark_change_unsent(&mut self, seq_num: SequenceNumber) {
+    self.unsent_changes.insert(seq_num);
+  }
+
   pub fn from_reader(reader: &ReaderIngredients, domain_participant: &DomainParticipant) -> Self {
     let mut self_locators = domain_participant.self_locators(); // This clones a map of locator lists.
     let unicast_locator_list = self_locators
@@ -155,8 +179,12 @@ impl RtpsReaderProxy {
       unsent_changes: BTreeSet::new(),
       pending_gap: BTreeSet::new(),
       repair_mode: false,
+      send_retries: 0,
       qos: reader.qos_policy.clone(),
       frags_requested: BTreeMap::new(),
+      last_heartbeat_sent: None,
+      last_acknack_received: None,
+      max_message_size: None,
     }
   }
 
@@ -193,8 +221,12 @@ impl RtpsReaderProxy {
       unsent_changes: BTreeSet::new(),
       pending_gap: BTreeSet::new(),
       repair_mode: false,
+      send_retries: 0,
       qos: discovered_reader_data.subscription_topic_data.qos(),
       frags_requested: BTreeMap::new(),
+      last_heartbeat_sent: None,
+      last_acknack_received: None,
+      max_message_size: None,
     }
   }
 
@@ -205,6 +237,7 @@ impl RtpsReaderProxy {
   ) {
     match ack_submessage {
       AckSubmessage::AckNack(acknack) => {
+        self.last_acknack_received = Some(Timestamp::now());
         self.all_acked_before = acknack.reader_sn_state.base();
         // clean up unsent_changes:
         // The handy split_off function "Returns everything after the given key,
@@ -228,9 +261,8 @@ impl RtpsReaderProxy {
         self.pending_gap = self.pending_gap.split_off(&self.all_acked_before);
       }
 
-      AckSubmessage::NackFrag(_nack_frag) => {
-        // TODO
-        error!("NACKFRAG not implemented");
+      AckSubmessage::NackFrag(nack_frag) => {
+        self.mark_frags_requested(nack_frag.writer_sn, &nack_frag.fragment_number_state);
       }
     }
   }
@@ -259,6 +291,22 @@ impl RtpsReaderProxy {
     self.all_acked_before
   }
 
+  pub fn mark_heartbeat_sent(&mut self, timestamp: Timestamp) {
+    self.last_heartbeat_sent = Some(timestamp);
+  }
+
+  pub fn reliability_state(&self) -> ReaderReliabilityState {
+    ReaderReliabilityState {
+      highest_acked: max(
+        self.all_acked_before - SequenceNumber::from(1),
+        SequenceNumber::zero(),
+      ),
+      requested_changes: self.unsent_changes_debug(),
+      last_heartbeat_sent: self.last_heartbeat_sent,
+      last_acknack_received: self.last_acknack_received,
+    }
+  }
+
   // Fragment handling
 
   pub fn mark_all_frags_requested(&mut self, seq_num: SequenceNumber, frag_count: u32) {
@@ -273,27 +321,31 @@ impl RtpsReaderProxy {
   }
 
   pub fn mark_frags_requested(&mut self, seq_num: SequenceNumber, frag_nums: &FragmentNumberSet) {
+    // FragmentNumbers start at 1. Zero would be a malformed request.
+    let max_fn_requested = match frag_nums.iter().rfind(|f| u32::from(*f) > 0) {
+      Some(max_fn) => usize::from(max_fn),
+      None => {
+        warn!(
+          "mark_frags_requested: Empty set in NackFrag??? reader={:?} SN={:?}",
+          self.remote_reader_guid, seq_num
+        );
+        return;
+      }
+    };
+
     let req_set = self
       .frags_requested
       .entry(seq_num)
       .or_insert_with(|| BitVec::with_capacity(64)); // default capacity out of hat
 
-    if let Some(max_fn_requested) = req_set.iter().next_back() {
-      // allocate more space if needed
-      let max_fn_requested = usize::from(max_fn_requested);
-      if max_fn_requested > req_set.len() {
-        let growth_need = max_fn_requested - req_set.len();
-        req_set.grow(growth_need, false);
-      }
-      for f in frag_nums.iter() {
-        // -1 because FragmentNumbers start at 1
-        req_set.set(usize::from(f) - 1, true);
-      }
-    } else {
-      warn!(
-        "mark_frags_requested: Empty set in NackFrag??? reader={:?} SN={:?}",
-        self.remote_reader_guid, seq_num
-      );
+    // allocate more space if needed
+    if max_fn_requested > req_set.len() {
+      let growth_need = max_fn_requested - req_set.len();
+      req_set.grow(growth_need, false);
+    }
+    for f in frag_nums.iter().filter(|f| u32::from(*f) > 0) {
+      // -1 because FragmentNumbers start at 1
+      req_set.set(usize::from(f) - 1, true);
     }
   }
 
@@ -385,3 +437,45 @@ impl Iterator for FragBitVecIterator {
 //     }
 //   }
 // }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{messages::submessages::submessages::NackFrag, structure::guid::EntityKind};
+
+  #[test]
+  fn only_nacked_fragments_are_resent() {
+    let mut proxy = RtpsReaderProxy::new(
+      GUID::dummy_test_guid(EntityKind::READER_NO_KEY_USER_DEFINED),
+      QosPolicies::qos_none(),
+      false,
+    );
+    let sn = SequenceNumber::new(3);
+    let nackfrag = NackFrag {
+      reader_id: proxy.remote_reader_guid.entity_id,
+      writer_id: EntityId::UNKNOWN,
+      writer_sn: sn,
+      fragment_number_state: FragmentNumberSet::from_base_and_set(
+        FragmentNumber::new(2),
+        &[2, 5, 6].into_iter().map(FragmentNumber::new).collect(),
+      ),
+      count: 1,
+    };
+    proxy.handle_ack_nack(&AckSubmessage::NackFrag(nackfrag), sn);
+    assert!(proxy.repair_frags_requested());
+
+    let to_send: Vec<_> = proxy.frags_requested_iterator().collect();
+    assert_eq!(
+      to_send,
+      [2, 5, 6]
+        .into_iter()
+        .map(|f| (sn, FragmentNumber::new(f)))
+        .collect::<Vec<_>>()
+    );
+
+    for (sn, f) in to_send {
+      proxy.mark_frag_sent(sn, &f);
+    }
+    assert!(!proxy.repair_frags_requested());
+  }
+}
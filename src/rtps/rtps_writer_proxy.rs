@@ -5,8 +5,10 @@ use std::{cmp::max, collections::BTreeMap};
 use log::{debug, error, info, trace, warn};
 
 use crate::{
+  dds::qos::policy,
   discovery::sedp_messages::DiscoveredWriterData,
   structure::{
+    duration::Duration,
     guid::{EntityId, GUID},
     locator::Locator,
     sequence_number::SequenceNumber,
@@ -63,6 +65,7 @@ pub(crate) struct RtpsWriterProxy {
 
   // The changes map is cleaned on heartbeat messages. The changes no longer available are dropped.
   pub received_heartbeat_count: i32,
+  pub received_heartbeatfrag_count: i32,
 
   pub sent_ack_nack_count: i32,
 
@@ -75,6 +78,18 @@ pub(crate) struct RtpsWriterProxy {
   last_received_sequence_number: SequenceNumber,
   last_received_timestamp: Timestamp,
   //fragment_assembler: Option<FragmentAssembler>,
+
+  // Consecutive sequence numbers far below ack_base, as (latest, count).
+  // These may indicate that the Writer has reset its sequence numbers.
+  sn_reset_run: Option<(SequenceNumber, u32)>,
+
+  // Liveliness policy offered by the remote Writer, and our tracking of it.
+  // Only ManualByTopic liveliness is tracked per Writer. Automatic and
+  // ManualByParticipant are asserted at participant level, and Discovery
+  // removes the proxies of participants that are lost.
+  liveliness: Option<policy::Liveliness>,
+  last_liveliness_assertion: Timestamp,
+  liveliness_alive: bool,
 }
 
 impl RtpsWriterProxy {
@@ -91,6 +106,7 @@ impl RtpsWriterProxy {
       remote_group_entity_id,
       changes: BTreeMap::new(),
       received_heartbeat_count: 0,
+      received_heartbeatfrag_count: 0,
       sent_ack_nack_count: 0,
       // Sequence numbering must start at 1.
       // Therefore, we can ACK all sequence numbers below 1 even before receiving anything.
@@ -98,6 +114,10 @@ impl RtpsWriterProxy {
       last_received_sequence_number: SequenceNumber::new(0),
       last_received_timestamp: Timestamp::INVALID,
       //fragment_assembler: None,
+      sn_reset_run: None,
+      liveliness: None,
+      last_liveliness_assertion: Timestamp::INVALID,
+      liveliness_alive: true,
     }
   }
 
@@ -119,6 +139,64 @@ impl RtpsWriterProxy {
     self.remote_group_entity_id = other.remote_group_entity_id;
   }
 
+  pub fn liveliness(&self) -> Option<policy::Liveliness> {
+    self.liveliness
+  }
+
+  pub fn is_alive(&self) -> bool {
+    self.liveliness_alive
+  }
+
+  // Restart liveliness tracking, e.g. when the Writer has announced a different
+  // liveliness policy. The Writer is considered alive from now on.
+  // Returns true if the Writer was not alive before.
+  pub fn reset_liveliness(
+    &mut self,
+    liveliness: Option<policy::Liveliness>,
+    now: Timestamp,
+  ) -> bool {
+    self.liveliness = liveliness;
+    self.last_liveliness_assertion = now;
+    let was_alive = self.liveliness_alive;
+    self.liveliness_alive = true;
+    !was_alive
+  }
+
+  // Writer has shown signs of life, e.g. sent DATA or HEARTBEAT.
+  // Returns true if the Writer was not alive before.
+  pub fn assert_liveliness(&mut self, now: Timestamp) -> bool {
+    self.last_liveliness_assertion = now;
+    let was_alive = self.liveliness_alive;
+    self.liveliness_alive = true;
+    !was_alive
+  }
+
+  // Lease duration, if this Writer's liveliness is tracked per Writer
+  pub fn liveliness_lease_duration(&self) -> Option<Duration> {
+    match self.liveliness {
+      Some(policy::Liveliness::ManualByTopic { lease_duration })
+        if lease_duration != Duration::DURATION_INFINITE =>
+      {
+        Some(lease_duration)
+      }
+      _ => None,
+    }
+  }
+
+  // Returns true if the Writer was alive, but its lease has now expired.
+  pub fn check_liveliness_lost(&mut self, now: Timestamp) -> bool {
+    match self.liveliness_lease_duration() {
+      Some(lease_duration)
+        if self.liveliness_alive
+          && now.duration_since(self.last_liveliness_assertion) > lease_duration =>
+      {
+        self.liveliness_alive = false;
+        true
+      }
+      _ => false,
+    }
+  }
+
   // This is used to check for DEADLINE policy
   pub fn last_change_timestamp(&self) -> Option<Timestamp> {
     if self.last_received_sequence_number > SequenceNumber::new(0) {
@@ -205,9 +283,84 @@ impl RtpsWriterProxy {
     seqnum < self.ack_base || self.changes.contains_key(&seqnum)
   }
 
+  // Some Writers reset their sequence numbers without restarting, i.e. without
+  // changing their GUID. Their new samples look like old ones, and would be
+  // ignored. If `tolerance` consecutive sequence numbers arrive, each more than
+  // `tolerance` below ack_base, we conclude that the numbering was reset, and
+  // start tracking the Writer anew from `seqnum`. Returns true in that case.
+  pub fn detect_sequence_number_reset(&mut self, seqnum: SequenceNumber, tolerance: u32) -> bool {
+    if seqnum + SequenceNumber::from(i64::from(tolerance)) >= self.ack_base {
+      return false; // not a large backward jump
+    }
+    let run_length = match self.sn_reset_run {
+      Some((latest, count)) if seqnum == latest.plus_1() => count + 1,
+      Some((latest, _)) if seqnum == latest => return false, // duplicate
+      _ => 1,
+    };
+    if run_length < tolerance {
+      self.sn_reset_run = Some((seqnum, run_length));
+      return false;
+    }
+
+    self.changes.clear();
+    self.ack_base = seqnum;
+    self.last_received_sequence_number = SequenceNumber::zero();
+    self.received_heartbeat_count = 0;
+    self.sn_reset_run = None;
+    true
+  }
+
+  // Sequence numbers skipped over, if seq_num is the next change received.
+  // Nothing is skipped before the first received change, because a Reader
+  // may join at any point. Returns the count and the inclusive range of the
+  // skipped ones, if any.
+  pub fn skipped_changes(
+    &self,
+    seq_num: SequenceNumber,
+  ) -> Option<(i64, SequenceNumber, SequenceNumber)> {
+    let last_received = self.last_received_sequence_number;
+    if last_received <= SequenceNumber::zero() {
+      return None;
+    }
+    self.not_received_in([(last_received.plus_1(), seq_num - SequenceNumber::new(1))])
+  }
+
+  // Of the given inclusive ranges of sequence numbers, find those that have
+  // been neither received nor marked irrelevant. Returns their count, and the
+  // smallest and largest of them, if any.
+  pub fn not_received_in(
+    &self,
+    ranges: impl IntoIterator<Item = (SequenceNumber, SequenceNumber)>,
+  ) -> Option<(i64, SequenceNumber, SequenceNumber)> {
+    let mut result: Option<(i64, SequenceNumber, SequenceNumber)> = None;
+    for (first, last) in ranges {
+      let mut first = max(first, self.ack_base);
+      let mut last = last;
+      // Skip over known changes at both ends of the range.
+      while first <= last && self.changes.contains_key(&first) {
+        first = first.plus_1();
+      }
+      while first <= last && self.changes.contains_key(&last) {
+        last = last - SequenceNumber::new(1);
+      }
+      if first > last {
+        continue;
+      }
+      let known = self.changes.range(first..=last).count() as i64;
+      let count = i64::from(last - first) + 1 - known;
+      result = Some(match result {
+        None => (count, first, last),
+        Some((c, f, l)) => (c + count, f.min(first), l.max(last)),
+      });
+    }
+    result
+  }
+
   // This is used to mark DATA as received.
   pub fn received_changes_add(&mut self, seq_num: SequenceNumber, receive_timestamp: Timestamp) {
     self.changes.insert(seq_num, Some(receive_timestamp));
+    // Normal progress, so no sequence number reset is going on
+    self.sn_reset_run = None;
 
     // Update deadline tracker
     if seq_num > self.last_received_sequence_number {
@@ -318,11 +471,16 @@ impl RtpsWriterProxy {
       multicast_locator_list,
       changes: BTreeMap::new(),
       received_heartbeat_count: 0,
+      received_heartbeatfrag_count: 0,
       sent_ack_nack_count: 0,
       ack_base: SequenceNumber::default(),
       last_received_sequence_number: SequenceNumber::new(0),
       last_received_timestamp: Timestamp::INVALID,
       //fragment_assembler: None,
+      sn_reset_run: None,
+      liveliness: None,
+      last_liveliness_assertion: Timestamp::INVALID,
+      liveliness_alive: true,
     }
   } // fn
 
@@ -0,0 +1,61 @@
+// Workarounds for known quirks of other DDS implementations.
+//
+// The quirks are looked up by the VendorId that the remote participant puts in
+// its RTPS message headers and announces in SPDP.
+
+use crate::{messages::vendor_id::VendorId, structure::guid::EntityId};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub(crate) struct VendorQuirks {
+  // Sends pre-emptive ACKNACKs to builtin Writers that RustDDS does not
+  // implement, e.g. the DDS-XTypes TypeLookup service Writers.
+  pub acknacks_to_unknown_builtin_writers: bool,
+}
+
+const KNOWN_QUIRKS: &[(VendorId, VendorQuirks)] = &[(
+  VendorId::EPROSIMA,
+  VendorQuirks {
+    acknacks_to_unknown_builtin_writers: true,
+  },
+)];
+
+pub(crate) fn quirks(vendor_id: VendorId) -> VendorQuirks {
+  KNOWN_QUIRKS
+    .iter()
+    .find(|(v, _)| *v == vendor_id)
+    .map(|(_, quirks)| *quirks)
+    .unwrap_or_default()
+}
+
+// An ACKNACK or NACK_FRAG to a Writer that we do not have is normally worth a
+// log message, but not if the sender is known to send them anyway.
+pub(crate) fn expects_unknown_writer(vendor_id: VendorId, writer_id: EntityId) -> bool {
+  writer_id.kind().is_built_in() && quirks(vendor_id).acknacks_to_unknown_builtin_writers
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::structure::guid::EntityKind;
+
+  #[test]
+  fn fast_dds_acknacks_to_type_lookup_writer_are_expected() {
+    // Fast DDS Shapes demo sends these to the TypeLookup request Writer
+    let type_lookup_writer = EntityId::new([0, 3, 0], EntityKind::WRITER_NO_KEY_BUILT_IN);
+    let user_writer = EntityId::new([0, 3, 0], EntityKind::WRITER_NO_KEY_USER_DEFINED);
+
+    assert!(expects_unknown_writer(
+      VendorId::EPROSIMA,
+      type_lookup_writer
+    ));
+    assert!(!expects_unknown_writer(VendorId::EPROSIMA, user_writer));
+    assert!(!expects_unknown_writer(
+      VendorId::CYCLONE,
+      type_lookup_writer
+    ));
+    assert!(!expects_unknown_writer(
+      VendorId::VENDOR_UNKNOWN,
+      type_lookup_writer
+    ));
+  }
+}
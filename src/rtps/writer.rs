@@ -4,7 +4,10 @@ use std::{
   iter::FromIterator,
   ops::Bound::Included,
   rc::Rc,
-  sync::{Arc, Mutex, MutexGuard},
+  sync::{
+    atomic::{AtomicI64, Ordering},
+    Arc, Mutex, MutexGuard,
+  },
 };
 use core::task::Waker;
 
@@ -23,15 +26,31 @@ use crate::{
     qos::{
       policy,
       policy::{History, Reliability},
+      property::{
+        WRITER_HEARTBEAT_PERIOD_PROPERTY, WRITER_HEARTBEAT_SUPPRESSION_PROPERTY,
+        WRITER_REDUNDANCY_PROPERTY,
+      },
       HasQoSPolicy, QosPolicies,
     },
-    statusevents::{CountWithChange, DataWriterStatus, StatusChannelSender},
-    with_key::datawriter::WriteOptions,
+    sample_store::SampleStore,
+    statusevents::{
+      CountWithChange, DataWriterStatus, IncompatibleQosCounter, StatusChannelSender,
+    },
+    with_key::datawriter::{ReaderReliabilityState, WriteOptions},
+  },
+  messages::submessages::{
+    elements::serialized_payload::SerializedPayload, submessages::AckSubmessage,
+  },
+  network::{
+    locator_selector::{LocatorSelectionContext, OutboundMessageKind},
+    udp_sender::{is_transient_send_error, UDPSender},
   },
-  messages::submessages::submessages::AckSubmessage,
-  network::udp_sender::UDPSender,
   rtps::{
-    constant::{NACK_RESPONSE_DELAY, NACK_SUPPRESSION_DURATION},
+    constant::{
+      DATA_FRAG_MESSAGE_OVERHEAD, DEFAULT_SEND_RETRY_DELAY, MAX_MESSAGE_SIZE, MIN_FRAGMENT_SIZE,
+      NACK_RESPONSE_DELAY, NACK_SUPPRESSION_DURATION, SEND_RETRY_LIMIT,
+    },
+    message::MessageLimits,
     rtps_reader_proxy::RtpsReaderProxy,
     Message, MessageBuilder,
   },
@@ -80,6 +99,11 @@ pub(crate) struct WriterIngredients {
   pub(crate) like_stateless: bool, // Usually false (see like_stateless attribute of Writer)
   pub qos_policies: QosPolicies,
   pub status_sender: StatusChannelSender<DataWriterStatus>,
+  pub(crate) fragment_size: usize, // Payloads larger than this are fragmented
+  pub(crate) message_limits: MessageLimits, // Messages larger than this are split
+  pub(crate) sample_store: Option<Box<dyn SampleStore>>, // Spill for acked history
+  pub(crate) reader_states: Arc<Mutex<BTreeMap<GUID, ReaderReliabilityState>>>, // For DataWriter
+  pub(crate) write_limit: Arc<AtomicI64>, // For DataWriter, see unacked_history_is_full()
 
   pub(crate) security_plugins: Option<SecurityPluginsHandle>,
 }
@@ -122,6 +146,11 @@ impl AckWaiter {
 
 const EPSILON_DELAY: Duration = Duration::from_nanos(10_000);
 
+// How many samples a Writer takes from its DataWriter in one go. The rest wait
+// for the next round of the event loop, so that a busy Writer does not
+// monopolize it.
+const MAX_COMMANDS_PER_WAKEUP: usize = 32;
+
 pub(crate) struct Writer {
   pub endianness: Endianness,
   pub heartbeat_message_counter: i32,
@@ -140,6 +169,8 @@ pub(crate) struct Writer {
   /// availability of data by sending a
   /// Heartbeat Message.
   pub heartbeat_period: Option<Duration>,
+  /// Skip periodic heartbeats when all readers have acknowledged all data.
+  suppress_acked_heartbeats: bool,
   /// duration to launch cache change remove from DDSCache
   pub cache_cleaning_period: Duration,
   /// Protocol tuning parameter that
@@ -149,6 +180,8 @@ pub(crate) struct Writer {
   pub nack_response_delay: std::time::Duration,
   pub nackfrag_response_delay: std::time::Duration,
   pub repairfrags_continue_delay: std::time::Duration,
+  /// Delay before sending again to readers, to which sending failed locally.
+  pub send_retry_delay: std::time::Duration,
 
   /// Protocol tuning parameter that
   /// allows the RTPS Writer to ignore
@@ -179,6 +212,9 @@ pub(crate) struct Writer {
   // "The fragment size must be fixed for a given Writer and is identical for all remote Readers"
   pub data_max_size_serialized: usize,
 
+  // Messages are split to stay within these.
+  message_limits: MessageLimits,
+
   my_guid: GUID,
   pub(crate) writer_command_receiver: mio_channel::Receiver<WriterCommand>,
   writer_command_receiver_waker: Arc<Mutex<Option<Waker>>>,
@@ -186,7 +222,7 @@ pub(crate) struct Writer {
   /// StatefulWriter maintains on each matched RTPS Reader
   readers: BTreeMap<GUID, RtpsReaderProxy>,
   matched_readers_count_total: i32, // all matches, never decremented
-  requested_incompatible_qos_count: i32, // how many times a Reader requested incompatible QoS
+  incompatible_qos_counter: IncompatibleQosCounter, // Readers that requested incompatible QoS
   // message: Option<Message>,
   udp_sender: Rc<UDPSender>,
 
@@ -236,7 +272,35 @@ pub(crate) struct Writer {
   // Used for sending status info about messages sent
   status_sender: StatusChannelSender<DataWriterStatus>,
   // offered_deadline_status: OfferedDeadlineMissedStatus,
-  ack_waiter: Option<AckWaiter>,
+  ack_waiters: Vec<AckWaiter>,
+
+  // True when we stopped taking samples from the DataWriter, because the
+  // history is full of unacknowledged samples. See unacked_history_is_full().
+  writes_blocked: bool,
+  // True when we stopped taking samples from the DataWriter after
+  // MAX_COMMANDS_PER_WAKEUP, and there may be more.
+  commands_pending: bool,
+
+  // Coherent set state. Samples written while a set is open are tagged with the
+  // sequence number of the first sample in the set. The end markers have
+  // sequence numbers, but no cache changes, so they are remembered here for
+  // repair.
+  coherent_set_open: bool,
+  coherent_set_start: Option<SequenceNumber>,
+  coherent_set_end_markers: BTreeSet<SequenceNumber>,
+
+  // If there is a SampleStore, samples acknowledged by all readers are moved
+  // there from the topic cache. Their metadata stays here.
+  sample_store: Option<Box<dyn SampleStore>>,
+  spilled_changes: BTreeMap<SequenceNumber, WriteOptions>,
+
+  // Snapshot of the reader proxies' reliability state, readable from the
+  // DataWriter. See DataWriter::reader_reliability_state().
+  reader_states: Arc<Mutex<BTreeMap<GUID, ReaderReliabilityState>>>,
+
+  // Highest sequence number that fits in the history without waiting for
+  // acknowledgements. The DataWriter waits on this before sending samples.
+  write_limit: Arc<AtomicI64>,
 
   security_plugins: Option<SecurityPluginsHandle>,
 }
@@ -251,6 +315,13 @@ pub enum WriterCommand {
   WaitForAcknowledgments {
     all_acked: StatusChannelSender<()>,
   },
+  // Sent by the Publisher from begin_coherent_changes()
+  BeginCoherentSet,
+  // Sent by the Publisher from end_coherent_changes(). The sequence number is
+  // for the end marker, and is given only if samples were written in the set.
+  EndCoherentSet {
+    sequence_number: Option<SequenceNumber>,
+  },
   // ResetOfferedDeadlineMissedStatus { writer_guid: GUID },
 }
 
@@ -275,12 +346,33 @@ impl Writer {
       panic!("Attempted to create a stateless-like Writer with other than BestEffort reliability");
     }
 
+    let configured_heartbeat_period = i
+      .qos_policies
+      .get_optional_property(WRITER_HEARTBEAT_PERIOD_PROPERTY)
+      .and_then(|value| match value.trim().parse::<i64>() {
+        Ok(millis) if millis > 0 => Some(Duration::from_millis(millis)),
+        Ok(_) => {
+          warn!(
+            "{} must be positive. Ignoring. topic={:?}",
+            WRITER_HEARTBEAT_PERIOD_PROPERTY, i.topic_name
+          );
+          None
+        }
+        Err(e) => {
+          warn!(
+            "Bad {} value {:?}: {}. Ignoring. topic={:?}",
+            WRITER_HEARTBEAT_PERIOD_PROPERTY, value, e, i.topic_name
+          );
+          None
+        }
+      });
+
     let heartbeat_period = i
       .qos_policies
       .reliability
       .and_then(|reliability| {
         if matches!(reliability, Reliability::Reliable { .. }) {
-          Some(Duration::from_secs(1))
+          Some(configured_heartbeat_period.unwrap_or(Duration::from_secs(1)))
         } else {
           None
         }
@@ -291,12 +383,20 @@ impl Writer {
           i.qos_policies.liveliness
         {
           let std_dur = lease_duration;
-          std_dur / 3
+          match configured_heartbeat_period {
+            Some(_) => std::cmp::min(std_dur / 3, hbp),
+            None => std_dur / 3,
+          }
         } else {
           hbp
         }
       });
 
+    let suppress_acked_heartbeats = !i
+      .qos_policies
+      .get_optional_property(WRITER_HEARTBEAT_SUPPRESSION_PROPERTY)
+      .is_some_and(|v| v.trim().eq_ignore_ascii_case("false"));
+
     // TODO: Configuration value
     let cache_cleaning_period = Duration::from_secs(2 * 60);
 
@@ -317,22 +417,25 @@ impl Writer {
       heartbeat_message_counter: 1,
       push_mode: true,
       heartbeat_period,
+      suppress_acked_heartbeats,
       cache_cleaning_period,
       nack_response_delay: NACK_RESPONSE_DELAY, // default value from dp_event_loop
       nackfrag_response_delay: NACK_RESPONSE_DELAY, // default value from dp_event_loop
       repairfrags_continue_delay: std::time::Duration::from_millis(1),
+      send_retry_delay: DEFAULT_SEND_RETRY_DELAY, // set by dp_event_loop
       nack_suppression_duration: NACK_SUPPRESSION_DURATION,
       first_change_sequence_number: SequenceNumber::from(1), // first = 1, last = 0
       last_change_sequence_number: SequenceNumber::from(0),  // means we have nothing to write
-      data_max_size_serialized: 1024,
+      data_max_size_serialized: i.fragment_size,
       // ^^ TODO: Maybe a smarter selection would be in order.
       // We should get the minimum over all outgoing interfaces.
+      message_limits: i.message_limits,
       my_guid: i.guid,
       writer_command_receiver: i.writer_command_receiver,
       writer_command_receiver_waker: i.writer_command_receiver_waker,
       readers: BTreeMap::new(),
       matched_readers_count_total: 0,
-      requested_incompatible_qos_count: 0,
+      incompatible_qos_counter: IncompatibleQosCounter::default(),
       udp_sender,
       topic_cache: i.topic_cache_handle,
       my_topic_name: i.topic_name,
@@ -343,8 +446,17 @@ impl Writer {
       qos_policies: i.qos_policies,
       status_sender: i.status_sender,
       // offered_deadline_status: OfferedDeadlineMissedStatus::new(),
-      ack_waiter: None,
-
+      ack_waiters: Vec::new(),
+      writes_blocked: false,
+      commands_pending: false,
+      coherent_set_open: false,
+      coherent_set_start: None,
+      coherent_set_end_markers: BTreeSet::new(),
+
+      sample_store: i.sample_store,
+      spilled_changes: BTreeMap::new(),
+      reader_states: i.reader_states,
+      write_limit: i.write_limit,
       security_plugins: i.security_plugins,
     }
   }
@@ -359,6 +471,27 @@ impl Writer {
     self.qos_policies.is_reliable()
   }
 
+  // Scheduling priority in the event loop. Higher is serviced first.
+  pub fn priority(&self) -> i32 {
+    self
+      .qos_policies
+      .transport_priority()
+      .map_or(0, |tp| tp.value)
+  }
+
+  // Active redundancy: a reliable writer may be configured to send every
+  // message to all of the reader's UDP locators, unicast and multicast,
+  // instead of picking one. If one network path loses a sample, another one
+  // may still deliver it, and the reader drops the duplicates by sequence
+  // number. This avoids waiting for a repair round trip.
+  fn redundant_delivery(&self) -> bool {
+    self.is_reliable()
+      && self
+        .qos_policies
+        .get_optional_property(WRITER_REDUNDANCY_PROPERTY)
+        .is_some_and(|v| v.trim().eq_ignore_ascii_case("true"))
+  }
+
   pub fn local_readers(&self) -> Vec<EntityId> {
     let min = GUID::new_with_prefix_and_id(self.my_guid.prefix, EntityId::MIN);
     let max = GUID::new_with_prefix_and_id(self.my_guid.prefix, EntityId::MAX);
@@ -457,6 +590,14 @@ impl Writer {
                              // in case some supplied QoS setting does not specify a larger value.
                              // In any case, there has to be some limit to avoid memory leak.
 
+    // Expired samples are not delivered anyway, so drop them whether acked or
+    // not.
+    if self.qos_policies.lifespan.is_some() {
+      self
+        .acquire_the_topic_cache_guard()
+        .remove_expired_changes(Timestamp::now());
+    }
+
     match self.qos_policies.history {
       None => {
         self.remove_all_acked_changes_but_keep_depth(1);
@@ -473,8 +614,8 @@ impl Writer {
   // --------------------------------------------------------------
   // --------------------------------------------------------------
   // --------------------------------------------------------------
-  fn num_frags_and_frag_size(&self, payload_size: usize) -> (u32, u16) {
-    let fragment_size = self.data_max_size_serialized as u32; // TODO: overflow check
+  fn num_frags_and_frag_size(&self, payload_size: usize, fragment_size: usize) -> (u32, u16) {
+    let fragment_size = fragment_size as u32; // TODO: overflow check
     let data_size = payload_size as u32; // TODO: overflow check
                                          // Formula from RTPS spec v2.5 Section "8.3.8.3.5 Logical Interpretation"
     let num_frags = (data_size / fragment_size) + u32::from(data_size % fragment_size != 0); // rounding up
@@ -483,9 +624,262 @@ impl Writer {
     (num_frags, fragment_size as u16)
   }
 
+  // Reliable KeepAll Writer with ResourceLimits max_samples must not accept
+  // more than that many samples that some reliable reader has not yet
+  // acknowledged. When this is full, we leave the samples in the DataWriter to
+  // Writer channel, so that DataWriter::write() blocks until either ACKNACKs
+  // free up space or max_blocking_time runs out.
+  fn unacked_history_is_full(&self) -> bool {
+    i64::from(self.last_change_sequence_number) >= self.unacked_history_limit()
+  }
+
+  // The highest sequence number that the history can take in before some
+  // reliable reader acknowledges more samples.
+  fn unacked_history_limit(&self) -> i64 {
+    if self.like_stateless || !self.is_reliable() {
+      return i64::MAX;
+    }
+    let max_samples = match (self.qos_policies.history, self.qos_policies.resource_limits) {
+      (Some(History::KeepAll), Some(policy::ResourceLimits { max_samples, .. }))
+        if max_samples > 0 =>
+      {
+        i64::from(max_samples)
+      }
+      _ => return i64::MAX, // KeepLast just overwrites old samples, so never full
+    };
+    let oldest_unacked = self
+      .readers
+      .values()
+      .filter(|rp| rp.qos().is_reliable())
+      .map(|rp| max(rp.all_acked_before, self.first_change_sequence_number))
+      .min();
+    match oldest_unacked {
+      None => i64::MAX, // no reliable readers, nobody to wait for
+      Some(oldest) => i64::from(oldest) + max_samples - 1,
+    }
+  }
+
+  // Let the DataWriter know how far it can write.
+  fn update_write_limit(&self) {
+    self
+      .write_limit
+      .store(self.unacked_history_limit(), Ordering::Release);
+  }
+
+  // Payloads larger than this are sent to the Reader in fragments of this size.
+  // It is the configured fragment size, unless the participant of the Reader
+  // has advertised that it cannot receive messages that large.
+  fn fragment_size_for(&self, reader_proxy: &RtpsReaderProxy) -> usize {
+    match reader_proxy.max_message_size {
+      Some(max_message_size) => self.data_max_size_serialized.min(
+        max_message_size
+          .saturating_sub(DATA_FRAG_MESSAGE_OVERHEAD + self.udp_sender.trailer_len())
+          .max(MIN_FRAGMENT_SIZE),
+      ),
+      None => self.data_max_size_serialized,
+    }
+  }
+
+  // Sends a new change as DATA to the given Readers, with a HEARTBEAT.
+  #[allow(clippy::too_many_arguments)]
+  fn push_data(
+    &mut self,
+    mut message_builder: MessageBuilder,
+    timestamp: Timestamp,
+    sequence_number: SequenceNumber,
+    write_options: &WriteOptions,
+    payload_ready: bool,
+    to_single_reader: bool,
+    readers: &[GUID],
+  ) {
+    // the beef: DATA submessage
+    if self.push_mode && payload_ready {
+      // If we are in push mode, proactively send DATA submessage along with
+      // HEARTBEAT.
+      if let Some(cache_change) = self.acquire_the_topic_cache_guard().get_change(&timestamp) {
+        // If DataWriter sent us a source timestamp, then add that.
+        // Timestamp has to go before Data to have effect on Data.
+        if let Some(src_ts) = cache_change.write_options.source_timestamp() {
+          message_builder = message_builder.ts_msg(self.endianness, Some(src_ts));
+        }
+        // TODO: insert info_destination if sending to single reader
+
+        let reader_entity_id = write_options
+          .to_single_reader()
+          .map_or(EntityId::UNKNOWN, |g| g.entity_id);
+        message_builder = message_builder.data_msg(
+          cache_change,
+          reader_entity_id,
+          self.my_guid, // writer
+          self.endianness,
+          self.security_plugins.as_ref(),
+        );
+      } else {
+        // We just did .insert_to_history_cache but nothing was found?
+        error!(
+          "process_writer_command: The dog ate my CacheChange {:?} topic={:?}",
+          sequence_number,
+          self.topic_name(),
+        );
+      }
+    } else {
+      // Not pushing: Send only HEARTBEAT. Send DATA only after readers
+      // ACKNACK asking for it, or when the payload can be protected.
+    };
+
+    if !self.like_stateless {
+      // Add HEARTBEAT if regular stateful writer (stateless-like supports only
+      // BestEffort currently)
+      let final_flag = false; // false = request that readers acknowledge with ACKNACK.
+      let liveliness_flag = false; // This is not a manual liveliness assertion (DDS API call), but side-effect of
+                                   // writing new data.
+      message_builder =
+        message_builder.heartbeat_msg(self, EntityId::UNKNOWN, final_flag, liveliness_flag);
+    } else {
+      debug!(
+        "Skipped sending HEARTBEAT in a stateless-like Writer. topic={:?}",
+        self.my_topic_name
+      );
+    }
+
+    let data_hb_message = message_builder.add_header_and_build(self.my_guid.prefix);
+    // debug
+    if to_single_reader {
+      debug!("Single reader send: {data_hb_message:?}");
+    }
+    // end debug
+    let mut failed_readers = self.send_message_to_readers(
+      DeliveryMode::Multicast,
+      data_hb_message,
+      &mut readers.iter().filter_map(|guid| self.readers.get(guid)),
+    );
+    if self.push_mode {
+      if !payload_ready {
+        failed_readers.extend(readers.iter().copied());
+      }
+      self.retry_send(sequence_number, failed_readers);
+    }
+  }
+
+  // Sends a new change as DATA_FRAGs of `fragment_size` to the given Readers,
+  // followed by a HEARTBEAT.
+  fn push_data_frags(
+    &mut self,
+    message_builder: &MessageBuilder,
+    timestamp: Timestamp,
+    sequence_number: SequenceNumber,
+    payload_ready: bool,
+    fragment_size: usize,
+    readers: &[GUID],
+  ) {
+    let mut failed_readers = BTreeSet::new();
+    if let Some(cache_change) = self.acquire_the_topic_cache_guard().get_change(&timestamp) {
+      let data_size = cache_change.data_value.payload_size();
+      let (num_frags, fragment_size) = self.num_frags_and_frag_size(data_size, fragment_size);
+
+      if self.push_mode && payload_ready {
+        // loop over fragments
+        for frag_num in
+          FragmentNumber::range_inclusive(FragmentNumber::new(1), FragmentNumber::new(num_frags))
+        {
+          let mut message_builder = message_builder.clone(); // fresh builder
+          if let Some(src_ts) = cache_change.write_options.source_timestamp() {
+            message_builder = message_builder.ts_msg(self.endianness, Some(src_ts));
+          }
+          // TODO: insert info_destination if sending to single reader
+          message_builder = message_builder.data_frag_msg(
+            cache_change,
+            EntityId::UNKNOWN, // reader
+            self.my_guid,      // writer
+            frag_num,
+            fragment_size,
+            data_size.try_into().unwrap(),
+            self.endianness,
+            self.security_plugins.as_ref(),
+          );
+
+          // TODO: some sort of queuing is needed
+          failed_readers.extend(self.send_message_to_readers(
+            DeliveryMode::Multicast,
+            message_builder.add_header_and_build(self.my_guid.prefix),
+            &mut readers.iter().filter_map(|guid| self.readers.get(guid)),
+          ));
+        } // end for
+      }
+      // Regardless of push mode, we send a Heartbeat
+      let final_flag = false; // false = request that readers acknowledge with ACKNACK.
+      let liveliness_flag = false; // This is not a manual liveliness assertion (DDS API call), but side-effect of
+      let hb_message = MessageBuilder::new()
+        .heartbeat_msg(self, EntityId::UNKNOWN, final_flag, liveliness_flag)
+        .add_header_and_build(self.my_guid.prefix);
+      self.send_message_to_readers(
+        DeliveryMode::Multicast,
+        hb_message,
+        &mut readers.iter().filter_map(|guid| self.readers.get(guid)),
+      );
+    } else {
+      // We just did .insert_to_history_cache but nothing was found?
+      error!(
+        "process_writer_command (frag): The dog ate my CacheChange {:?} topic={:?}",
+        sequence_number,
+        self.topic_name(),
+      );
+    }
+    if self.push_mode && !payload_ready {
+      failed_readers.extend(readers.iter().copied());
+    }
+    // Any missing fragments are sent again.
+    self.retry_send(sequence_number, failed_readers);
+  }
+
+  // Is there data from DataWriter waiting for space in history?
+  pub fn has_blocked_writes(&self) -> bool {
+    self.writes_blocked
+  }
+
+  // Did the last process_writer_command() leave samples for the next round?
+  pub fn has_pending_commands(&self) -> bool {
+    self.commands_pending
+  }
+
+  // Have all matched reliable Readers acknowledged everything written so far?
+  pub fn all_changes_acked(&self) -> bool {
+    self
+      .readers
+      .values()
+      .filter(|rp| rp.qos().is_reliable())
+      .all(|rp| self.last_change_sequence_number < rp.all_acked_before)
+  }
+
   // Receive new data samples from the DDS DataWriter
   pub fn process_writer_command(&mut self) {
-    while let Ok(cc) = self.writer_command_receiver.try_recv() {
+    self.process_writer_commands(MAX_COMMANDS_PER_WAKEUP);
+  }
+
+  // Receive everything the DataWriter has written, e.g. before shutting down.
+  pub fn flush_writer_commands(&mut self) {
+    self.process_writer_commands(usize::MAX);
+  }
+
+  fn process_writer_commands(&mut self, max_count: usize) {
+    self.commands_pending = false;
+    for command_count in 0.. {
+      if command_count == max_count {
+        self.commands_pending = true;
+        break;
+      }
+      self.update_write_limit();
+      self.writes_blocked = self.unacked_history_is_full();
+      if self.writes_blocked {
+        debug!(
+          "History full of unacknowledged samples. Blocking writes. topic={:?}",
+          self.my_topic_name
+        );
+        break;
+      }
+      let Ok(cc) = self.writer_command_receiver.try_recv() else {
+        break;
+      };
       match cc {
         WriterCommand::DDSData {
           ddsdata,
@@ -507,13 +901,44 @@ impl Writer {
           // 2. Send out data. If we are pushing data, send the DATA submessage and
           // HEARTBEAT. If we are not pushing, send out HEARTBEAT only. Readers will then
           // ask for the DATA with ACKNACK, if they are interested.
-          let fragmentation_needed = ddsdata.payload_size() > self.data_max_size_serialized;
+          let payload_size = ddsdata.payload_size();
+          let write_options = if self.coherent_set_open {
+            let coherent_set_start = *self.coherent_set_start.get_or_insert(sequence_number);
+            write_options.with_coherent_set(coherent_set_start)
+          } else {
+            write_options
+          };
+          // With LIFESPAN, the sample expires at source timestamp + lifespan. The
+          // source timestamp is then always sent, so that readers can tell the
+          // expiration time.
+          let write_options = match self.qos_policies.lifespan {
+            Some(policy::Lifespan { duration }) if duration != Duration::DURATION_INFINITE => {
+              let source_timestamp = write_options
+                .source_timestamp()
+                .unwrap_or_else(Timestamp::now);
+              write_options
+                .with_source_timestamp(source_timestamp)
+                .with_expiration(source_timestamp + duration)
+            }
+            _ => write_options,
+          };
           let timestamp =
             self.insert_to_history_cache(ddsdata, write_options.clone(), sequence_number);
 
           self.increase_heartbeat_counter();
           let mut message_builder = MessageBuilder::new();
 
+          // A protected payload must never go out as plaintext. If the key
+          // material is not there yet, the change is not pushed, but kept unsent
+          // until it can be protected. Heartbeats still announce it.
+          let payload_ready = self.payload_key_material_ready();
+          if !payload_ready {
+            warn!(
+              "No key material for protecting the payload yet. Holding back {:?} topic={:?}",
+              sequence_number, self.my_topic_name
+            );
+          }
+
           // Check if this is for single Reader only.
           // If so, insert GAP for others.
           // And additionally send any pending gap for the single reader.
@@ -538,129 +963,56 @@ impl Writer {
             }
           }
 
-          // Proceed to send either a DATA or several DATAFRAGs
-          if !fragmentation_needed {
-            // the beef: DATA submessage
-            if self.push_mode {
-              // If we are in push mode, proactively send DATA submessage along with
-              // HEARTBEAT.
-              if let Some(cache_change) =
-                self.acquire_the_topic_cache_guard().get_change(&timestamp)
-              {
-                // If DataWriter sent us a source timestamp, then add that.
-                // Timestamp has to go before Data to have effect on Data.
-                if let Some(src_ts) = cache_change.write_options.source_timestamp() {
-                  message_builder = message_builder.ts_msg(self.endianness, Some(src_ts));
-                }
-                // TODO: insert info_destination if sending to single reader
-
-                let reader_entity_id = write_options
-                  .to_single_reader()
-                  .map_or(EntityId::UNKNOWN, |g| g.entity_id);
-                message_builder = message_builder.data_msg(
-                  cache_change,
-                  reader_entity_id,
-                  self.my_guid, // writer
-                  self.endianness,
-                  self.security_plugins.as_ref(),
-                );
-              } else {
-                // We just did .insert_to_history_cache but nothing was found?
-                error!(
-                  "process_writer_command: The dog ate my CacheChange {:?} topic={:?}",
-                  sequence_number,
-                  self.topic_name(),
-                );
-              }
-            } else {
-              // Not pushing: Send only HEARTBEAT. Send DATA only after readers
-              // ACKNACK asking for it.
-            };
-
-            if !self.like_stateless {
-              // Add HEARTBEAT if regular stateful writer (stateless-like supports only
-              // BestEffort currently)
-              let final_flag = false; // false = request that readers acknowledge with ACKNACK.
-              let liveliness_flag = false; // This is not a manual liveliness assertion (DDS API call), but side-effect of
-                                           // writing new data.
-              message_builder =
-                message_builder.heartbeat_msg(self, EntityId::UNKNOWN, final_flag, liveliness_flag);
+          // Proceed to send either a DATA or several DATAFRAGs. A Reader whose
+          // participant cannot receive the payload in one message gets it in
+          // fragments that fit, so Readers are grouped by fragment size.
+          let mut data_readers = Vec::new();
+          let mut frag_readers: BTreeMap<usize, Vec<GUID>> = BTreeMap::new();
+          for reader_proxy in self.readers.values() {
+            let fragment_size = self.fragment_size_for(reader_proxy);
+            if payload_size <= fragment_size {
+              data_readers.push(reader_proxy.remote_reader_guid);
             } else {
-              debug!(
-                "Skipped sending HEARTBEAT in a stateless-like Writer. topic={:?}",
-                self.my_topic_name
-              );
+              frag_readers
+                .entry(fragment_size)
+                .or_default()
+                .push(reader_proxy.remote_reader_guid);
             }
-
-            let data_hb_message = message_builder.add_header_and_build(self.my_guid.prefix);
-            // debug
-            if to_single_reader {
-              debug!("Single reader send: {data_hb_message:?}");
-            }
-            // end debug
-            self.send_message_to_readers(
-              DeliveryMode::Multicast,
-              data_hb_message,
-              &mut self.readers.values(),
+          }
+          if !data_readers.is_empty() || frag_readers.is_empty() {
+            self.push_data(
+              message_builder.clone(),
+              timestamp,
+              sequence_number,
+              &write_options,
+              payload_ready,
+              to_single_reader,
+              &data_readers,
             );
-          } else {
-            // Large payload, must fragment.
-
-            if let Some(cache_change) = self.acquire_the_topic_cache_guard().get_change(&timestamp)
-            {
-              let data_size = cache_change.data_value.payload_size();
-              let (num_frags, fragment_size) = self.num_frags_and_frag_size(data_size);
-
-              if self.push_mode {
-                // loop over fragments
-                for frag_num in FragmentNumber::range_inclusive(
-                  FragmentNumber::new(1),
-                  FragmentNumber::new(num_frags),
-                ) {
-                  let mut message_builder = message_builder.clone(); // fresh builder
-                  if let Some(src_ts) = cache_change.write_options.source_timestamp() {
-                    message_builder = message_builder.ts_msg(self.endianness, Some(src_ts));
-                  }
-                  // TODO: insert info_destination if sending to single reader
-                  message_builder = message_builder.data_frag_msg(
-                    cache_change,
-                    EntityId::UNKNOWN, // reader
-                    self.my_guid,      // writer
-                    frag_num,
-                    fragment_size,
-                    data_size.try_into().unwrap(),
-                    self.endianness,
-                    self.security_plugins.as_ref(),
-                  );
+          }
+          for (fragment_size, readers) in frag_readers {
+            self.push_data_frags(
+              &message_builder,
+              timestamp,
+              sequence_number,
+              payload_ready,
+              fragment_size,
+              &readers,
+            );
+          }
+        }
 
-                  // TODO: some sort of queuing is needed
-                  self.send_message_to_readers(
-                    DeliveryMode::Multicast,
-                    message_builder.add_header_and_build(self.my_guid.prefix),
-                    &mut self.readers.values(),
-                  );
-                } // end for
-              }
-              // Regardless of push mode, we send a Heartbeat
-              let final_flag = false; // false = request that readers acknowledge with ACKNACK.
-              let liveliness_flag = false; // This is not a manual liveliness assertion (DDS API call), but side-effect of
-              let hb_message = MessageBuilder::new()
-                .heartbeat_msg(self, EntityId::UNKNOWN, final_flag, liveliness_flag)
-                .add_header_and_build(self.my_guid.prefix);
-              self.send_message_to_readers(
-                DeliveryMode::Multicast,
-                hb_message,
-                &mut self.readers.values(),
-              );
-            } else {
-              // We just did .insert_to_history_cache but nothing was found?
-              error!(
-                "process_writer_command (frag): The dog ate my CacheChange {:?} topic={:?}",
-                sequence_number,
-                self.topic_name(),
-              );
-            }
-          } // end if large payload
+        WriterCommand::BeginCoherentSet => {
+          self.coherent_set_open = true;
+          self.coherent_set_start = None;
+        }
+
+        WriterCommand::EndCoherentSet { sequence_number } => {
+          self.coherent_set_open = false;
+          self.coherent_set_start = None;
+          if let Some(sequence_number) = sequence_number {
+            self.send_coherent_set_end_marker(sequence_number);
+          }
         }
 
         // WriterCommand::ResetOfferedDeadlineMissedStatus { writer_guid: _, } => {
@@ -674,7 +1026,7 @@ impl Writer {
               self.my_topic_name
             );
             let _ = all_acked.try_send(()); // Let the poor waiter continue.
-            return;
+            continue;
           }
 
           let wait_until = self.last_change_sequence_number;
@@ -689,25 +1041,59 @@ impl Writer {
               }
             })
             .collect();
-          self.ack_waiter = if readers_pending.is_empty() {
+          if readers_pending.is_empty() {
             // all acked already: try to signal app waiting at DataWriter
             let _ = all_acked.try_send(());
             // but we ignore any failure to signal, if no-one is listening
             // since that is normal. They may have timed out and stopped waiting.
-            None
           } else {
-            // Someone still needs to ack. Wait for them.
-            Some(AckWaiter {
+            // Someone still needs to ack. Wait for them. There may be several
+            // waiters, e.g. from different threads.
+            self.ack_waiters.push(AckWaiter {
               wait_until,
               complete_channel: all_acked,
               readers_pending,
-            })
-          };
+            });
+          }
         }
       }
     }
   }
 
+  // Closes the coherent set on the wire. Readers withhold the set until they
+  // have received everything up to this marker.
+  fn send_coherent_set_end_marker(&mut self, sequence_number: SequenceNumber) {
+    self.last_change_sequence_number = sequence_number;
+    let first_change_sequence_number = self.first_change_sequence_number;
+    self
+      .coherent_set_end_markers
+      .retain(|sn| *sn >= first_change_sequence_number);
+    self.coherent_set_end_markers.insert(sequence_number);
+
+    self.increase_heartbeat_counter();
+    let mut message_builder = MessageBuilder::new();
+    if self.push_mode {
+      message_builder = message_builder.coherent_set_end_msg(
+        EntityId::UNKNOWN,
+        self.my_guid,
+        sequence_number,
+        self.endianness,
+      );
+    }
+    if !self.like_stateless {
+      let final_flag = false;
+      let liveliness_flag = false;
+      message_builder =
+        message_builder.heartbeat_msg(self, EntityId::UNKNOWN, final_flag, liveliness_flag);
+    }
+    let marker_message = message_builder.add_header_and_build(self.my_guid.prefix);
+    self.send_message_to_readers(
+      DeliveryMode::Multicast,
+      marker_message,
+      &mut self.readers.values(),
+    );
+  }
+
   fn insert_to_history_cache(
     &mut self,
     data: DDSData,
@@ -794,10 +1180,14 @@ impl Writer {
     // TODO: This produces same heartbeat count for all messages sent, but
     // then again, they represent the same writer status.
 
-    if self
-      .readers
-      .values()
-      .all(|rp| self.last_change_sequence_number < rp.all_acked_before)
+    // A manual liveliness assertion is sent even if there is nothing new, as
+    // Readers rely on it to keep the Writer alive.
+    if !is_manual_assertion
+      && self.suppress_acked_heartbeats
+      && self
+        .readers
+        .values()
+        .all(|rp| self.last_change_sequence_number < rp.all_acked_before)
     {
       trace!("heartbeat tick: all readers have all available data.");
     } else {
@@ -816,6 +1206,11 @@ impl Writer {
         hb_message,
         &mut self.readers.values(),
       );
+      let now = Timestamp::now();
+      for reader_proxy in self.readers.values_mut() {
+        reader_proxy.mark_heartbeat_sent(now);
+      }
+      self.update_all_reader_states();
     }
   }
 
@@ -851,7 +1246,6 @@ impl Writer {
         }
         let my_topic = self.my_topic_name.clone(); // for debugging
         let reader_guid = GUID::new(reader_guid_prefix, an.reader_id);
-        self.update_ack_waiters(reader_guid, Some(an.reader_sn_state.base()));
 
         if let Some(reader_proxy) = self.lookup_reader_proxy_mut(reader_guid) {
           // Mark requested SNs as "unsent changes"
@@ -925,14 +1319,58 @@ impl Writer {
             );
           }
         }
+
+        // Update the reported state before waking up anyone waiting for acks.
+        self.update_reader_state(reader_guid);
+        self.update_ack_waiters(reader_guid, Some(an.reader_sn_state.base()));
+        // Acknowledged samples can go to the sample store.
+        self.spill_acked_changes();
       } // AckNack
       AckSubmessage::NackFrag(ref nackfrag) => {
         // NackFrag is negative acknowledgement only, i.e. requesting missing fragments.
 
         let reader_guid = GUID::new(reader_guid_prefix, nackfrag.reader_id);
+
+        // Sanity check: The requested fragments must exist.
+        let fragment_size = self
+          .readers
+          .get(&reader_guid)
+          .map_or(self.data_max_size_serialized, |rp| {
+            self.fragment_size_for(rp)
+          });
+        let num_frags = self
+          .sequence_number_to_instant(nackfrag.writer_sn)
+          .and_then(|ts| self.history_change(nackfrag.writer_sn, ts))
+          .map(|cc| {
+            self
+              .num_frags_and_frag_size(cc.data_value.payload_size(), fragment_size)
+              .0
+          });
+        match (num_frags, nackfrag.fragment_number_state.iter().next_back()) {
+          (None, _) => {
+            // The sample is gone. An ACKNACK will get the reader a GAP.
+            debug!(
+              "NackFrag from {:?} for {:?}, which is no longer available. topic={:?}",
+              reader_guid, nackfrag.writer_sn, self.my_topic_name
+            );
+            return;
+          }
+          (Some(num_frags), Some(max_requested)) if u32::from(max_requested) > num_frags => {
+            warn!(
+              "NackFrag from {:?} asks for fragment {:?} of {:?}, but there are only {} \
+               fragments. topic={:?}",
+              reader_guid, max_requested, nackfrag.writer_sn, num_frags, self.my_topic_name
+            );
+            return;
+          }
+          _ => (),
+        }
+
+        let last_seq = self.last_change_sequence_number;
         if let Some(reader_proxy) = self.lookup_reader_proxy_mut(reader_guid) {
-          reader_proxy.mark_frags_requested(nackfrag.writer_sn, &nackfrag.fragment_number_state);
+          reader_proxy.handle_ack_nack(ack_submessage, last_seq);
         }
+        self.update_reader_state(reader_guid);
         self.timed_event_timer.set_timeout(
           self.nackfrag_response_delay,
           TimedEvent::SendRepairFrags {
@@ -943,20 +1381,40 @@ impl Writer {
     }
   }
 
-  fn update_ack_waiters(&mut self, guid: GUID, acked_before: Option<SequenceNumber>) {
-    let completed = self
-      .ack_waiter
-      .as_mut()
-      .map_or(false, |aw| aw.reader_acked_or_lost(guid, acked_before));
-    if completed {
-      self
-        .ack_waiter
-        .as_ref()
-        .map(AckWaiter::notify_wait_complete);
-      self.ack_waiter = None;
+  // Publish the reliability state of a reader proxy to the DataWriter.
+  fn update_reader_state(&self, guid: GUID) {
+    self.update_write_limit();
+    let mut reader_states = self.reader_states.lock().unwrap();
+    match self.readers.get(&guid) {
+      Some(reader_proxy) => {
+        reader_states.insert(guid, reader_proxy.reliability_state());
+      }
+      None => {
+        reader_states.remove(&guid);
+      }
     }
   }
 
+  fn update_all_reader_states(&self) {
+    self.update_write_limit();
+    let mut reader_states = self.reader_states.lock().unwrap();
+    *reader_states = self
+      .readers
+      .iter()
+      .map(|(guid, reader_proxy)| (*guid, reader_proxy.reliability_state()))
+      .collect();
+  }
+
+  fn update_ack_waiters(&mut self, guid: GUID, acked_before: Option<SequenceNumber>) {
+    self.ack_waiters.retain_mut(|aw| {
+      let completed = aw.reader_acked_or_lost(guid, acked_before);
+      if completed {
+        aw.notify_wait_complete();
+      }
+      !completed
+    });
+  }
+
   // Send out missing data
 
   fn handle_repair_data_send(&mut self, to_reader: GUID) {
@@ -968,6 +1426,14 @@ impl Writer {
       );
       return;
     }
+    if !self.payload_key_material_ready() {
+      // Keep the changes unsent. The repair timer tries again as long as the
+      // reader is in repair mode.
+      if let Some(reader_proxy) = self.lookup_reader_proxy_mut(to_reader) {
+        reader_proxy.repair_mode = true;
+      }
+      return;
+    }
     // Note: here we remove the reader from our reader map temporarily.
     // Then we can mutate both the reader and other fields in self.
     // Doing a .get_mut() on the reader map would make self immutable.
@@ -975,7 +1441,7 @@ impl Writer {
       // We use a worker function to ensure that afterwards we can insert the
       // reader_proxy back. This technique ensures that all return paths lead to
       // re-insertion.
-      self.handle_repair_data_send_worker(&mut reader_proxy);
+      let failed_sn = self.handle_repair_data_send_worker(&mut reader_proxy);
       // insert reader back
       if let Some(rp) = self
         .readers
@@ -983,6 +1449,10 @@ impl Writer {
       {
         error!("Reader proxy was duplicated somehow??? {:?}", rp);
       }
+      if let Some(sn) = failed_sn {
+        self.retry_send(sn, BTreeSet::from([to_reader]));
+      }
+      self.update_reader_state(to_reader);
     }
   }
 
@@ -995,6 +1465,10 @@ impl Writer {
       );
       return;
     }
+    if !self.payload_key_material_ready() {
+      // The requested fragments stay requested, so the repair timer tries again.
+      return;
+    }
 
     // see similar function above
     if let Some(mut reader_proxy) = self.readers.remove(&to_reader) {
@@ -1009,15 +1483,15 @@ impl Writer {
     }
   }
 
-  fn handle_repair_data_send_worker(&mut self, reader_proxy: &mut RtpsReaderProxy) {
+  // Returns the change that could not be sent, if any.
+  fn handle_repair_data_send_worker(
+    &mut self,
+    reader_proxy: &mut RtpsReaderProxy,
+  ) -> Option<SequenceNumber> {
     // Note: The reader_proxy is now removed from readers map
     let reader_guid = reader_proxy.remote_reader_guid;
-    let mut partial_message = MessageBuilder::new()
-      .dst_submessage(self.endianness, reader_guid.prefix)
-      .ts_msg(self.endianness, Some(Timestamp::now()));
-    // TODO: This timestamp should probably not be
-    // the current (retransmit) time, but the initial sample production timestamp,
-    // i.e. should be read from DDSCache (and be implemented there)
+    let mut partial_message =
+      MessageBuilder::new().dst_submessage(self.endianness, reader_guid.prefix);
     debug!(
       "Repair data send due to ACKNACK. ReaderProxy Unsent changes: {:?}",
       reader_proxy.unsent_changes_debug()
@@ -1028,28 +1502,67 @@ impl Writer {
     let mut sending_data = false;
     let mut sending_gap = false;
     let mut trigger_send_repair_frags = false;
+    let mut data_sn = None;
+    let mut failed_sn = None;
+
+    // Changes before the first one in our history are gone for good, e.g.
+    // overwritten in a KeepLast history, even if the topic cache still has
+    // them. Cover them all with one GAP instead of one repair round each.
+    let gone: Vec<SequenceNumber> = reader_proxy
+      .unsent_changes_iter()
+      .take_while(|sn| *sn < self.first_change_sequence_number)
+      .collect();
+    if !gone.is_empty() {
+      debug!(
+        "Reader {:?} requested {:?}, but history starts from {:?}. Sending GAP. topic={:?}",
+        reader_guid, gone, self.first_change_sequence_number, self.my_topic_name
+      );
+      for sn in &gone {
+        reader_proxy.mark_change_sent(*sn);
+      }
+      no_longer_relevant.extend(gone);
+      found_data = true;
+    }
+
     if let Some(unsent_sn) = reader_proxy.first_unsent_change() {
       // There are unsent changes.
-      if let Some(timestamp) = self.sequence_number_to_instant(unsent_sn) {
-        // Try to find the cache change from topic cache
-        if let Some(cache_change) = self.acquire_the_topic_cache_guard().get_change(&timestamp) {
+      if self.coherent_set_end_markers.contains(&unsent_sn) {
+        // Coherent set end marker has no cache change. Just send it again.
+        partial_message = partial_message.coherent_set_end_msg(
+          reader_guid.entity_id,
+          self.my_guid,
+          unsent_sn,
+          self.endianness,
+        );
+        sending_data = true;
+      } else if let Some(timestamp) = self.sequence_number_to_instant(unsent_sn) {
+        // Try to find the cache change from topic cache, or the sample store
+        if let Some(cache_change) = self.history_change(unsent_sn, timestamp) {
           // CacheChange found, check if we can send it in one piece (i.e. DATA)
-          if cache_change.data_value.payload_size() <= self.data_max_size_serialized {
-            // construct DATA submessage
-            partial_message = partial_message.data_msg(
-              cache_change,
-              reader_guid.entity_id, // reader
-              self.my_guid,          // writer
-              self.endianness,
-              self.security_plugins.as_ref(),
-            );
+          let fragment_size = self.fragment_size_for(reader_proxy);
+          if cache_change.data_value.payload_size() <= fragment_size {
+            // construct DATA submessage. The source timestamp is the one of the
+            // original write, so that e.g. LIFESPAN is counted from there.
+            let source_timestamp = cache_change
+              .write_options
+              .source_timestamp()
+              .unwrap_or_else(Timestamp::now);
+            partial_message = partial_message
+              .ts_msg(self.endianness, Some(source_timestamp))
+              .data_msg(
+                &cache_change,
+                reader_guid.entity_id, // reader
+                self.my_guid,          // writer
+                self.endianness,
+                self.security_plugins.as_ref(),
+              );
             // TODO: Here we are cloning the entire payload. We need to rewrite
             // the transmit path to avoid copying.
             sending_data = true;
           } else {
             // Large data: arrange DATAFRAGs to be sent
             let (num_frags, _frag_size) =
-              self.num_frags_and_frag_size(cache_change.data_value.payload_size());
+              self.num_frags_and_frag_size(cache_change.data_value.payload_size(), fragment_size);
             reader_proxy.mark_all_frags_requested(unsent_sn, num_frags);
             // We cannot set up a repair timer right here, because self is already borrowed
             // So just set a flag.
@@ -1087,6 +1600,9 @@ impl Writer {
       // from unsent list.
       reader_proxy.mark_change_sent(unsent_sn);
       found_data = true;
+      if sending_data {
+        data_sn = Some(unsent_sn);
+      }
     }
     // Add GAP submessage, if some cache changes could not be found.
     if !no_longer_relevant.is_empty() {
@@ -1102,11 +1618,17 @@ impl Writer {
     // if we have DATA or GAP to send, then build message and send
     if sending_data || sending_gap {
       let data_gap_msg = partial_message.add_header_and_build(self.my_guid.prefix);
-      self.send_message_to_readers(
+      let failed_readers = self.send_message_to_readers(
         DeliveryMode::Unicast,
         data_gap_msg,
         &mut std::iter::once(&*reader_proxy),
       );
+      if let (false, Some(sn)) = (failed_readers.is_empty(), data_sn) {
+        failed_sn = Some(sn);
+      } else if data_sn.is_some() {
+        reader_proxy.send_retries = 0;
+        self.udp_sender.count_retransmission(&self.my_topic_name);
+      }
     }
     if trigger_send_repair_frags {
       self.timed_event_timer.set_timeout(
@@ -1123,6 +1645,7 @@ impl Writer {
       // Unsent list is empty. Switch off repair mode.
       reader_proxy.repair_mode = false;
     }
+    failed_sn
   } // fn
 
   fn handle_repair_frags_send_worker(
@@ -1141,19 +1664,19 @@ impl Writer {
       // ^^^ TODO
 
       if let Some(timestamp) = self.sequence_number_to_instant(seq_num) {
-        // Try to find the cache change from topic cache
-        if let Some(cache_change) = self.acquire_the_topic_cache_guard().get_change(&timestamp) {
+        // Try to find the cache change from topic cache, or the sample store
+        if let Some(cache_change) = self.history_change(seq_num, timestamp) {
           // Generate datafrag message
           let mut message_builder = MessageBuilder::new();
           if let Some(src_ts) = cache_change.write_options.source_timestamp() {
             message_builder = message_builder.ts_msg(self.endianness, Some(src_ts));
           }
 
-          let fragment_size: u32 = self.data_max_size_serialized as u32; // TODO: overflow check
+          let fragment_size: u32 = self.fragment_size_for(reader_proxy) as u32; // TODO: overflow check
           let data_size: u32 = cache_change.data_value.payload_size() as u32; // TODO: overflow check
 
           message_builder = message_builder.data_frag_msg(
-            cache_change,
+            &cache_change,
             reader_proxy.remote_reader_guid.entity_id, // reader
             self.my_guid,                              // writer
             frag_num,
@@ -1164,10 +1687,11 @@ impl Writer {
           );
 
           // TODO: some sort of queuing is needed
+          // Note: reader_proxy is not in self.readers while we are here.
           self.send_message_to_readers(
             DeliveryMode::Unicast,
             message_builder.add_header_and_build(self.my_guid.prefix),
-            &mut self.readers.values(),
+            &mut std::iter::once(&*reader_proxy),
           );
         } else {
           error!(
@@ -1196,12 +1720,7 @@ impl Writer {
     let first_keeper = if !self.like_stateless {
       // Regular stateful writer behavior
       // All readers have acked up to this point (SequenceNumber)
-      let acked_by_all_readers = self
-        .readers
-        .values()
-        .map(RtpsReaderProxy::acked_up_to_before)
-        .min()
-        .unwrap_or_else(SequenceNumber::zero);
+      let acked_by_all_readers = self.acked_by_all_readers();
       // If all readers have acked all up to before 5, and depth is 5, we need
       // to keep samples 0..4, i.e. from acked_up_to_before - depth .
       max(
@@ -1238,12 +1757,126 @@ impl Writer {
     }
     self.first_change_sequence_number = first_keeper;
     self.sequence_number_to_instant = self.sequence_number_to_instant.split_off(&first_keeper);
+
+    self.spilled_changes = self.spilled_changes.split_off(&first_keeper);
+    if let Some(sample_store) = self.sample_store.as_mut() {
+      sample_store
+        .remove_before(first_keeper)
+        .unwrap_or_else(|e| warn!("SampleStore cannot remove samples: {e}"));
+    }
+    self.spill_acked_changes();
+  }
+
+  // All readers have acked up to this point
+  fn acked_by_all_readers(&self) -> SequenceNumber {
+    self
+      .readers
+      .values()
+      .map(RtpsReaderProxy::acked_up_to_before)
+      .min()
+      .unwrap_or_else(SequenceNumber::zero)
+  }
+
+  // Move the samples that all readers have acknowledged from the topic cache to
+  // the sample store, if we have one. Disposes are small, so they stay.
+  fn spill_acked_changes(&mut self) {
+    if self.sample_store.is_none() || self.like_stateless {
+      return;
+    }
+    let acked_before = self.acked_by_all_readers();
+    let my_guid = self.my_guid;
+    let Some(sample_store) = self.sample_store.as_mut() else {
+      return;
+    };
+    let mut topic_cache = self.topic_cache.lock().unwrap();
+
+    for (&sn, instant) in self.sequence_number_to_instant.range(..acked_before) {
+      if self.spilled_changes.contains_key(&sn) {
+        continue;
+      }
+      match topic_cache.get_change(instant) {
+        Some(cc) if cc.writer_guid == my_guid && matches!(cc.data_value, DDSData::Data { .. }) => {}
+        _ => continue,
+      }
+      let Some(cc) = topic_cache.remove_change(instant) else {
+        continue;
+      };
+      let DDSData::Data {
+        ref serialized_payload,
+      } = cc.data_value
+      else {
+        continue;
+      };
+      let payload = serialized_payload.bytes_slice(0, serialized_payload.len_serialized());
+      match sample_store.store(sn, payload) {
+        Ok(()) => {
+          self.spilled_changes.insert(sn, cc.write_options);
+        }
+        Err(e) => {
+          warn!(
+            "SampleStore cannot store {:?}, keeping it in memory: {} topic={:?}",
+            sn, e, self.my_topic_name
+          );
+          topic_cache.add_change(instant, cc);
+        }
+      }
+    }
+  }
+
+  // Find a change of our history. It is in the topic cache, unless it has been
+  // spilled to the sample store.
+  fn history_change(
+    &mut self,
+    sequence_number: SequenceNumber,
+    timestamp: Timestamp,
+  ) -> Option<CacheChange> {
+    if let Some(cc) = self.acquire_the_topic_cache_guard().get_change(&timestamp) {
+      return Some(cc.clone());
+    }
+    let write_options = self.spilled_changes.get(&sequence_number)?.clone();
+    let payload = self
+      .sample_store
+      .as_mut()?
+      .load(sequence_number)
+      .unwrap_or_else(|e| {
+        error!("SampleStore cannot load {sequence_number:?}: {e}");
+        None
+      })?;
+    match SerializedPayload::from_bytes(&payload) {
+      Ok(serialized_payload) => Some(CacheChange::new(
+        self.my_guid,
+        sequence_number,
+        write_options,
+        DDSData::new(serialized_payload),
+      )),
+      Err(e) => {
+        error!("Spilled sample {sequence_number:?} is corrupt: {e}");
+        None
+      }
+    }
   }
 
   fn increase_heartbeat_counter(&mut self) {
     self.heartbeat_message_counter += 1;
   }
 
+  // True if the payload of our changes can be sent, i.e. either it needs no
+  // protection or the key material for protecting it is available.
+  #[cfg(feature = "security")]
+  fn payload_key_material_ready(&self) -> bool {
+    match &self.security_plugins {
+      Some(security_plugins_handle) => security_plugins_handle
+        .get_plugins()
+        .payload_key_material_ready(&self.my_guid),
+      None => true,
+    }
+  }
+
+  #[cfg(not(feature = "security"))]
+  fn payload_key_material_ready(&self) -> bool {
+    true
+  }
+
   #[cfg(feature = "security")]
   fn security_encode(
     &self,
@@ -1297,20 +1930,48 @@ impl Writer {
     }
   }
 
+  // Returns the readers to which sending failed transiently, e.g. due to a
+  // full socket buffer.
   fn send_message_to_readers(
     &self,
     preferred_mode: DeliveryMode,
     message: Message,
     readers: &mut dyn Iterator<Item = &RtpsReaderProxy>,
-  ) {
+  ) -> BTreeSet<GUID> {
+    let readers = readers.collect::<Vec<_>>(); // clone itterator
+
+    // Stay within the limits of what peers accept, and what fits into a
+    // datagram. The limits include the trailer that UDPSender appends.
+    let max_size = readers
+      .iter()
+      .filter_map(|rp| rp.max_message_size)
+      .chain(self.message_limits.max_size)
+      .fold(MAX_MESSAGE_SIZE, usize::min)
+      .saturating_sub(self.udp_sender.trailer_len());
+    let limits = MessageLimits {
+      max_size: Some(max_size),
+      ..self.message_limits
+    };
+    let mut failed_readers = BTreeSet::new();
+    for message in message.split(limits) {
+      failed_readers.extend(self.send_single_message_to_readers(preferred_mode, message, &readers));
+    }
+    failed_readers
+  }
+
+  fn send_single_message_to_readers(
+    &self,
+    preferred_mode: DeliveryMode,
+    message: Message,
+    readers: &[&RtpsReaderProxy],
+  ) -> BTreeSet<GUID> {
+    let mut failed_readers = BTreeSet::new();
     // TODO: This is a stupid transmit algorithm. We should compute a preferred
     // unicast and multicast locators for each reader only on every reader update,
     // and not find it dynamically on every message.
 
-    let readers = readers.collect::<Vec<_>>(); // clone itterator
-
     #[cfg(feature = "security")]
-    let encoded = self.security_encode(message, &readers);
+    let encoded = self.security_encode(message, readers);
     #[cfg(not(feature = "security"))]
     let encoded: Result<Message, ()> = Ok(message);
 
@@ -1318,21 +1979,49 @@ impl Writer {
       Ok(message) => {
         let buffer = message.write_to_vec_with_ctx(self.endianness).unwrap();
         let mut already_sent_to = BTreeSet::new();
+        let mut failed_locators = BTreeSet::new();
+        let redundant_delivery = self.redundant_delivery();
 
         macro_rules! send_unless_sent_and_mark {
-          ($locs:expr) => {
-            for loc in $locs.iter() {
+          ($reader:expr, $locs:expr) => {{
+            let context = LocatorSelectionContext {
+              kind: OutboundMessageKind::WriterToReader,
+              local_guid: self.my_guid,
+              remote_guid: $reader.remote_reader_guid,
+            };
+            for loc in self.udp_sender.select_locators(&context, &$locs).iter() {
               if already_sent_to.contains(loc) {
                 trace!("Already sent to {:?}", loc);
               } else {
-                self.udp_sender.send_to_locator(&buffer, loc);
+                match self.udp_sender.send_to_locator(&buffer, loc) {
+                  Err(e) if is_transient_send_error(&e) => {
+                    failed_locators.insert(loc.clone());
+                  }
+                  _ => (), // Other errors would not go away by retrying
+                }
                 already_sent_to.insert(loc.clone());
               }
+              if failed_locators.contains(loc) {
+                failed_readers.insert($reader.remote_reader_guid);
+              }
             }
-          };
+          }};
         }
 
         for reader in readers {
+          if redundant_delivery {
+            send_unless_sent_and_mark!(
+              reader,
+              reader
+                .unicast_locator_list
+                .iter()
+                .chain(reader.multicast_locator_list.iter())
+                .filter(|l| Locator::is_udp(l))
+                .cloned()
+                .collect::<Vec<_>>()
+            );
+            continue;
+          }
           match (
             preferred_mode,
             reader
@@ -1345,16 +2034,16 @@ impl Writer {
               .find(|l| Locator::is_udp(l)),
           ) {
             (DeliveryMode::Multicast, _, Some(_mc_locator)) => {
-              send_unless_sent_and_mark!(reader.multicast_locator_list);
+              send_unless_sent_and_mark!(reader, reader.multicast_locator_list);
             }
             (DeliveryMode::Unicast, Some(_uc_locator), _) => {
-              send_unless_sent_and_mark!(reader.unicast_locator_list)
+              send_unless_sent_and_mark!(reader, reader.unicast_locator_list);
             }
             (_delivery_mode, _, Some(_mc_locator)) => {
-              send_unless_sent_and_mark!(reader.multicast_locator_list);
+              send_unless_sent_and_mark!(reader, reader.multicast_locator_list);
             }
             (_delivery_mode, Some(_uc_locator), _) => {
-              send_unless_sent_and_mark!(reader.unicast_locator_list)
+              send_unless_sent_and_mark!(reader, reader.unicast_locator_list);
             }
             (_delivery_mode, None, None) => {
               warn!("send_message_to_readers: No locators for {:?}", reader);
@@ -1364,6 +2053,47 @@ impl Writer {
       }
       Err(e) => error!("Failed to send message to readers. Encoding failed: {e:?}"),
     }
+    failed_readers
+  }
+
+  // The writer sends the change again to the readers that it could not be sent
+  // to, after a short delay. The delay doubles on each consecutive failure, and
+  // after SEND_RETRY_LIMIT retries the change is given up. A reliable reader
+  // can still request it with an ACKNACK.
+  fn retry_send(&mut self, sequence_number: SequenceNumber, failed_readers: BTreeSet<GUID>) {
+    if self.like_stateless {
+      return; // No reader proxies to repair from
+    }
+    for reader_guid in failed_readers {
+      let send_retry_delay = self.send_retry_delay;
+      if let Some(reader_proxy) = self.lookup_reader_proxy_mut(reader_guid) {
+        if reader_proxy.send_retries >= SEND_RETRY_LIMIT {
+          warn!(
+            "Send of {:?} to {:?} failed {} times. Giving up.",
+            sequence_number,
+            reader_guid,
+            reader_proxy.send_retries + 1
+          );
+          reader_proxy.send_retries = 0;
+          self.udp_sender.count_send_drop();
+          continue;
+        }
+        let delay = send_retry_delay * 2_u32.pow(reader_proxy.send_retries);
+        reader_proxy.send_retries += 1;
+        debug!(
+          "Send of {:?} to {:?} failed. Retrying in {:?}.",
+          sequence_number, reader_guid, delay
+        );
+        reader_proxy.mark_change_unsent(sequence_number);
+        reader_proxy.repair_mode = true;
+        self.timed_event_timer.set_timeout(
+          delay,
+          TimedEvent::SendRepairData {
+            to_reader: reader_guid,
+          },
+        );
+      }
+    }
   }
 
   // Send status to DataWriter or however is listening
@@ -1388,10 +2118,15 @@ impl Writer {
     requested_qos: &QosPolicies,
   ) {
     debug!("update_reader_proxy topic={:?}", self.my_topic_name);
-    match self.qos_policies.compliance_failure_wrt(requested_qos) {
+    let incompatible_policies = self.qos_policies.compatible_with(requested_qos);
+    match incompatible_policies.first() {
       // matched QoS
       None => {
+        self
+          .incompatible_qos_counter
+          .forget(reader_proxy.remote_reader_guid);
         let change = self.matched_reader_update(reader_proxy);
+        self.update_reader_state(reader_proxy.remote_reader_guid);
         if change > 0 {
           self.matched_readers_count_total += change;
           self.send_status(DataWriterStatus::PublicationMatched {
@@ -1417,12 +2152,16 @@ impl Writer {
           bad_policy_id,
           self.topic_name()
         );
-        self.requested_incompatible_qos_count += 1;
-        self.send_status(DataWriterStatus::OfferedIncompatibleQos {
-          count: CountWithChange::new(self.requested_incompatible_qos_count, 1),
-          last_policy_id: bad_policy_id,
-          policies: Vec::new(), // TODO: implement this
-        });
+        if let Some((count, last_policy_id, policies)) = self
+          .incompatible_qos_counter
+          .add(reader_proxy.remote_reader_guid, &incompatible_policies)
+        {
+          self.send_status(DataWriterStatus::OfferedIncompatibleQos {
+            count,
+            last_policy_id,
+            policies,
+          });
+        }
       }
     } // match
   }
@@ -1453,10 +2192,12 @@ impl Writer {
       );
       debug!("Removed reader proxy details: {:?}", removed_reader);
     }
+    self.update_reader_state(guid);
     removed
   }
 
   pub fn reader_lost(&mut self, guid: GUID) {
+    self.incompatible_qos_counter.forget(guid);
     if self.readers.contains_key(&guid) {
       info!(
         "reader_lost topic={:?} reader={:?}",
@@ -1470,13 +2211,16 @@ impl Writer {
         current: CountWithChange::new(self.readers.len() as i32, -1),
       });
     }
-    // also remember to remove reader from ack_waiter
+    // also remember to remove reader from ack_waiters
     self.update_ack_waiters(guid, None);
   }
 
   // Entire remote participant was lost.
   // Remove all remote readers belonging to it.
   pub fn participant_lost(&mut self, guid_prefix: GuidPrefix) {
+    self
+      .incompatible_qos_counter
+      .forget_participant(guid_prefix);
     let lost_readers: Vec<GUID> = self
       .readers
       .range(guid_prefix.range())
@@ -1539,13 +2283,29 @@ mod tests {
   use byteorder::LittleEndian;
   use log::info;
 
+  use std::{net::SocketAddr, sync::RwLock};
+
+  use bytes::Bytes;
+
+  use super::*;
   use crate::{
     dds::{
-      participant::DomainParticipant, qos::QosPolicies, topic::TopicKind,
+      participant::DomainParticipant,
+      qos::{property::Property, QosPolicyBuilder},
+      statusevents::sync_status_channel,
+      topic::TopicKind,
+      typedesc::TypeDesc,
       with_key::datawriter::DataWriter,
     },
+    messages::submessages::{
+      elements::serialized_payload::SerializedPayload,
+      submessages::{AckNack, WriterSubmessage},
+    },
+    rtps::SubmessageBody,
     serialization::cdr_serializer::CDRSerializerAdapter,
+    structure::{dds_cache::DDSCache, guid::EntityKind, sequence_number::SequenceNumberSet},
     test::random_data::*,
+    RepresentationIdentifier,
   };
 
   #[test]
@@ -1598,4 +2358,671 @@ mod tests {
     thread::sleep(std::time::Duration::from_millis(100));
     info!("writerResult:  {:?}", write_result);
   }
+
+  // Creates a Writer on a topic of its own, and matches it with a reader that
+  // receives on the returned socket. Anything sent on matching is discarded.
+  fn test_writer(
+    qos: &QosPolicies,
+    udp_sender: UDPSender,
+    security_plugins: Option<SecurityPluginsHandle>,
+  ) -> (
+    Writer,
+    mio_channel::SyncSender<WriterCommand>,
+    std::net::UdpSocket,
+  ) {
+    let dds_cache = Arc::new(RwLock::new(DDSCache::new()));
+    let topic_name = "test_topic";
+    let topic_cache_handle = dds_cache.write().unwrap().add_new_topic(
+      topic_name.to_string(),
+      TypeDesc::new("test_type".to_string()),
+      qos,
+    );
+    let (writer_command_sender, writer_command_receiver) =
+      mio_channel::sync_channel::<WriterCommand>(8);
+    let (status_sender, _status_receiver) = sync_status_channel::<DataWriterStatus>(4).unwrap();
+    let mut writer = Writer::new(
+      WriterIngredients {
+        guid: GUID::dummy_test_guid(EntityKind::WRITER_NO_KEY_USER_DEFINED),
+        writer_command_receiver,
+        writer_command_receiver_waker: Arc::new(Mutex::new(None)),
+        topic_name: topic_name.to_string(),
+        topic_cache_handle,
+        like_stateless: false,
+        qos_policies: qos.clone(),
+        status_sender,
+        fragment_size: 1024,
+        message_limits: MessageLimits::default(),
+        sample_store: None,
+        reader_states: Arc::new(Mutex::new(BTreeMap::new())),
+        write_limit: Arc::new(AtomicI64::new(i64::MAX)),
+        security_plugins,
+      },
+      Rc::new(udp_sender),
+      mio_extras::timer::Builder::default().build(),
+    );
+
+    let socket = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+    socket
+      .set_read_timeout(Some(std::time::Duration::from_millis(200)))
+      .unwrap();
+    let mut reader_proxy = RtpsReaderProxy::new(
+      GUID::dummy_test_guid(EntityKind::READER_NO_KEY_USER_DEFINED),
+      qos.clone(),
+      false,
+    );
+    reader_proxy.unicast_locator_list = vec![Locator::from(socket.local_addr().unwrap())];
+    writer.update_reader_proxy(&reader_proxy, qos);
+    while socket.recv(&mut [0; 1500]).is_ok() {}
+
+    (writer, writer_command_sender, socket)
+  }
+
+  // Writer whose only matched Reader has two paths: a multicast locator where
+  // nobody listens (packets on this path are lost) and a unicast socket.
+  // Returns the number of DATA submessages that arrived on the unicast path
+  // after writing one sample. No ACKNACKs are ever given to the Writer, so
+  // anything that arrives is from the first transmission.
+  fn data_received_on_unicast_path(qos: QosPolicies) -> usize {
+    let (mut writer, writer_command_sender, socket) =
+      test_writer(&qos, UDPSender::new(0).unwrap(), None);
+    let mut reader_proxy = writer.readers.values().next().unwrap().clone();
+    reader_proxy.multicast_locator_list = vec![Locator::from(SocketAddr::from((
+      [239, 255, 0, 99],
+      socket.local_addr().unwrap().port(),
+    )))];
+    writer.update_reader_proxy(&reader_proxy, &qos);
+    // Discard anything sent on matching
+    while socket.recv(&mut [0; 1500]).is_ok() {}
+
+    writer_command_sender
+      .send(WriterCommand::DDSData {
+        ddsdata: DDSData::new(SerializedPayload::new(
+          RepresentationIdentifier::CDR_LE,
+          vec![1, 2, 3, 4],
+        )),
+        write_options: WriteOptions::default(),
+        sequence_number: SequenceNumber::new(1),
+      })
+      .unwrap();
+    writer.process_writer_command();
+
+    let mut data_count = 0;
+    let mut buf = [0; 1500];
+    while let Ok(len) = socket.recv(&mut buf) {
+      let message = Message::read_from_buffer(&Bytes::copy_from_slice(&buf[..len])).unwrap();
+      data_count += message
+        .submessages()
+        .iter()
+        .filter(|sm| matches!(sm.body, SubmessageBody::Writer(WriterSubmessage::Data(..))))
+        .count();
+    }
+    data_count
+  }
+
+  #[test]
+  fn redundant_writer_delivers_over_remaining_path() {
+    let reliable_qos = QosPolicyBuilder::new()
+      .reliability(Reliability::Reliable {
+        max_blocking_time: crate::Duration::from_millis(100),
+      })
+      .build();
+
+    // Normally only the multicast path is used for new samples, so the sample
+    // is lost and would need a repair.
+    assert_eq!(data_received_on_unicast_path(reliable_qos.clone()), 0);
+
+    // With redundancy the unicast path delivers it right away.
+    let redundant_qos = QosPolicyBuilder::new()
+      .property(policy::Property {
+        value: vec![Property::local(WRITER_REDUNDANCY_PROPERTY, "true")],
+        binary_value: vec![],
+      })
+      .build();
+    assert_eq!(
+      data_received_on_unicast_path(reliable_qos.modify_by(&redundant_qos)),
+      1
+    );
+  }
+
+  #[test]
+  fn failed_send_is_retried() {
+    let qos = QosPolicyBuilder::new()
+      .reliability(Reliability::Reliable {
+        max_blocking_time: crate::Duration::from_millis(100),
+      })
+      .build();
+    let (mut writer, writer_command_sender, socket) =
+      test_writer(&qos, UDPSender::new(0).unwrap(), None);
+    let reader_guid = GUID::dummy_test_guid(EntityKind::READER_NO_KEY_USER_DEFINED);
+    let udp_sender = writer.udp_sender.clone();
+
+    let received_data_count = || {
+      let mut data_count = 0;
+      let mut buf = [0; 1500];
+      while let Ok(len) = socket.recv(&mut buf) {
+        let message = Message::read_from_buffer(&Bytes::copy_from_slice(&buf[..len])).unwrap();
+        data_count += message
+          .submessages()
+          .iter()
+          .filter(|sm| matches!(sm.body, SubmessageBody::Writer(WriterSubmessage::Data(..))))
+          .count();
+      }
+      data_count
+    };
+
+    // The first send fails, as if the socket buffer was full.
+    udp_sender.inject_send_failures(1);
+    writer_command_sender
+      .send(WriterCommand::DDSData {
+        ddsdata: DDSData::new(SerializedPayload::new(
+          RepresentationIdentifier::CDR_LE,
+          vec![1, 2, 3, 4],
+        )),
+        write_options: WriteOptions::default(),
+        sequence_number: SequenceNumber::new(1),
+      })
+      .unwrap();
+    writer.process_writer_command();
+    assert_eq!(received_data_count(), 0);
+    assert_eq!(udp_sender.send_failure_count(), 1);
+    assert_eq!(
+      writer.readers[&reader_guid].first_unsent_change(),
+      Some(SequenceNumber::new(1))
+    );
+
+    // The writer sends it again without waiting for an ACKNACK.
+    thread::sleep(std::time::Duration::from_millis(300));
+    writer.handle_timed_event();
+    assert_eq!(received_data_count(), 1);
+    assert_eq!(writer.readers[&reader_guid].first_unsent_change(), None);
+    assert_eq!(udp_sender.send_drop_count(), 0);
+  }
+
+  #[test]
+  fn failed_send_is_given_up_after_retries() {
+    let qos = QosPolicyBuilder::new()
+      .reliability(Reliability::BestEffort)
+      .build();
+    let (mut writer, writer_command_sender, socket) =
+      test_writer(&qos, UDPSender::new(0).unwrap(), None);
+    let reader_guid = GUID::dummy_test_guid(EntityKind::READER_NO_KEY_USER_DEFINED);
+    let udp_sender = writer.udp_sender.clone();
+
+    let received_data_count = || {
+      let mut data_count = 0;
+      let mut buf = [0; 1500];
+      while let Ok(len) = socket.recv(&mut buf) {
+        let message = Message::read_from_buffer(&Bytes::copy_from_slice(&buf[..len])).unwrap();
+        data_count += message
+          .submessages()
+          .iter()
+          .filter(|sm| matches!(sm.body, SubmessageBody::Writer(WriterSubmessage::Data(..))))
+          .count();
+      }
+      data_count
+    };
+    let mut write = |sn: i64| {
+      writer_command_sender
+        .send(WriterCommand::DDSData {
+          ddsdata: DDSData::new(SerializedPayload::new(
+            RepresentationIdentifier::CDR_LE,
+            vec![1, 2, 3, 4],
+          )),
+          write_options: WriteOptions::default(),
+          sequence_number: SequenceNumber::new(sn),
+        })
+        .unwrap();
+      writer.process_writer_command();
+      // The retry delays are 10, 20 and 40 ms.
+      for _ in 0..4 {
+        thread::sleep(std::time::Duration::from_millis(100));
+        writer.handle_timed_event();
+      }
+      writer.readers[&reader_guid].first_unsent_change()
+    };
+
+    // A best effort writer retries, too. The last retry gets through.
+    udp_sender.inject_send_failures(SEND_RETRY_LIMIT as usize);
+    assert_eq!(write(1), None);
+    assert_eq!(received_data_count(), 1);
+    assert_eq!(udp_sender.send_drop_count(), 0);
+
+    // The first send and all retries fail, so the change is dropped.
+    udp_sender.inject_send_failures(SEND_RETRY_LIMIT as usize + 1);
+    assert_eq!(write(2), None);
+    assert_eq!(received_data_count(), 0);
+    assert_eq!(
+      udp_sender.send_failure_count(),
+      2 * u64::from(SEND_RETRY_LIMIT) + 1
+    );
+    assert_eq!(udp_sender.send_drop_count(), 1);
+  }
+
+  #[test]
+  fn locator_selector_redirects_data() {
+    let qos = QosPolicyBuilder::new().build();
+
+    let redirect_socket = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+    redirect_socket
+      .set_read_timeout(Some(std::time::Duration::from_millis(200)))
+      .unwrap();
+    let redirect_locator = Locator::from(redirect_socket.local_addr().unwrap());
+    let reader_guid = GUID::dummy_test_guid(EntityKind::READER_NO_KEY_USER_DEFINED);
+    let selector = move |context: &LocatorSelectionContext, _locators: &[Locator]| {
+      assert_eq!(context.kind, OutboundMessageKind::WriterToReader);
+      assert_eq!(context.remote_guid, reader_guid);
+      vec![redirect_locator]
+    };
+
+    let (mut writer, writer_command_sender, advertised_socket) = test_writer(
+      &qos,
+      UDPSender::new(0)
+        .unwrap()
+        .with_locator_selector(Some(Arc::new(selector))),
+      None,
+    );
+    while redirect_socket.recv(&mut [0; 1500]).is_ok() {}
+
+    writer_command_sender
+      .send(WriterCommand::DDSData {
+        ddsdata: DDSData::new(SerializedPayload::new(
+          RepresentationIdentifier::CDR_LE,
+          vec![1, 2, 3, 4],
+        )),
+        write_options: WriteOptions::default(),
+        sequence_number: SequenceNumber::new(1),
+      })
+      .unwrap();
+    writer.process_writer_command();
+
+    let received_data_count = |socket: &std::net::UdpSocket| {
+      let mut data_count = 0;
+      let mut buf = [0; 1500];
+      while let Ok(len) = socket.recv(&mut buf) {
+        let message = Message::read_from_buffer(&Bytes::copy_from_slice(&buf[..len])).unwrap();
+        data_count += message
+          .submessages()
+          .iter()
+          .filter(|sm| matches!(sm.body, SubmessageBody::Writer(WriterSubmessage::Data(..))))
+          .count();
+      }
+      data_count
+    };
+    assert_eq!(received_data_count(&redirect_socket), 1);
+    assert_eq!(received_data_count(&advertised_socket), 0);
+  }
+
+  #[test]
+  fn fragments_fit_advertised_max_message_size() {
+    let qos = QosPolicyBuilder::new().build();
+    let (mut writer, writer_command_sender, socket) =
+      test_writer(&qos, UDPSender::new(0).unwrap(), None);
+
+    // One reader advertises a small maximum message size, the other nothing.
+    let max_message_size = 600;
+    let mut reader_proxy = writer.readers.values().next().unwrap().clone();
+    reader_proxy.max_message_size = Some(max_message_size);
+    writer.update_reader_proxy(&reader_proxy, &qos);
+    let other_socket = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+    other_socket
+      .set_read_timeout(Some(std::time::Duration::from_millis(200)))
+      .unwrap();
+    let mut other_reader_proxy = RtpsReaderProxy::new(
+      GUID::new(
+        GuidPrefix::new(&[2; 12]),
+        EntityId::create_custom_entity_id([2; 3], EntityKind::READER_NO_KEY_USER_DEFINED),
+      ),
+      qos.clone(),
+      false,
+    );
+    other_reader_proxy.unicast_locator_list =
+      vec![Locator::from(other_socket.local_addr().unwrap())];
+    writer.update_reader_proxy(&other_reader_proxy, &qos);
+    for socket in [&socket, &other_socket] {
+      while socket.recv(&mut [0; 1500]).is_ok() {}
+    }
+
+    writer_command_sender
+      .send(WriterCommand::DDSData {
+        ddsdata: DDSData::new(SerializedPayload::new(
+          RepresentationIdentifier::CDR_LE,
+          vec![7; 3000],
+        )),
+        write_options: WriteOptions::default(),
+        sequence_number: SequenceNumber::new(1),
+      })
+      .unwrap();
+    writer.process_writer_command();
+
+    // Returns the largest message size and the fragment sizes received
+    let receive = |socket: &std::net::UdpSocket| {
+      let mut largest_message = 0;
+      let mut fragment_sizes = Vec::new();
+      let mut buf = [0; 2000];
+      while let Ok(len) = socket.recv(&mut buf) {
+        largest_message = largest_message.max(len);
+        let message = Message::read_from_buffer(&Bytes::copy_from_slice(&buf[..len])).unwrap();
+        for sm in message.submessages() {
+          if let SubmessageBody::Writer(WriterSubmessage::DataFrag(df, _)) = sm.body {
+            fragment_sizes.push(df.fragment_size);
+          }
+        }
+      }
+      (largest_message, fragment_sizes)
+    };
+
+    // 3004 bytes of payload with the encapsulation header
+    let (largest_message, fragment_sizes) = receive(&socket);
+    assert!(largest_message <= max_message_size);
+    assert_eq!(fragment_sizes.len(), 9);
+    assert!(fragment_sizes.iter().all(|size| *size == 344));
+
+    // The other reader gets the configured fragment size.
+    let (_, fragment_sizes) = receive(&other_socket);
+    assert_eq!(fragment_sizes, vec![1024; 3]);
+  }
+
+  #[test]
+  fn integrity_trailer_fits_advertised_max_message_size() {
+    let qos = QosPolicyBuilder::new().build();
+    let (mut writer, writer_command_sender, socket) = test_writer(
+      &qos,
+      UDPSender::new(0).unwrap().with_message_integrity(true),
+      None,
+    );
+
+    let max_message_size = 600;
+    let mut reader_proxy = writer.readers.values().next().unwrap().clone();
+    reader_proxy.max_message_size = Some(max_message_size);
+    writer.update_reader_proxy(&reader_proxy, &qos);
+
+    writer_command_sender
+      .send(WriterCommand::DDSData {
+        ddsdata: DDSData::new(SerializedPayload::new(
+          RepresentationIdentifier::CDR_LE,
+          vec![7; 3000],
+        )),
+        write_options: WriteOptions::default(),
+        sequence_number: SequenceNumber::new(1),
+      })
+      .unwrap();
+    writer.process_writer_command();
+
+    let mut message_count = 0;
+    let mut buf = [0; 2000];
+    while let Ok(len) = socket.recv(&mut buf) {
+      assert!(len <= max_message_size, "{len} bytes with the trailer");
+      message_count += 1;
+    }
+    assert!(message_count > 1);
+  }
+
+  #[cfg(feature = "security")]
+  #[test]
+  fn protected_payload_is_not_sent_before_key_material() {
+    use crate::security::{
+      access_control::access_control_builtin::AccessControlBuiltin,
+      authentication::authentication_builtin::AuthenticationBuiltin,
+      cryptographic::cryptographic_builtin::CryptographicBuiltin,
+      security_plugins::{SecurityPlugins, SecurityPluginsHandle},
+    };
+
+    let qos = QosPolicyBuilder::new()
+      .reliability(Reliability::Reliable {
+        max_blocking_time: crate::Duration::from_millis(100),
+      })
+      .build();
+    // The writer has not been registered to the plugins, so there is no key
+    // material for it.
+    let security_plugins = SecurityPluginsHandle::new(SecurityPlugins::new(
+      Box::new(AuthenticationBuiltin::new()),
+      Box::new(AccessControlBuiltin::new()),
+      Box::new(CryptographicBuiltin::new()),
+    ));
+    let (mut writer, writer_command_sender, socket) =
+      test_writer(&qos, UDPSender::new(0).unwrap(), Some(security_plugins));
+    let reader_guid = GUID::dummy_test_guid(EntityKind::READER_NO_KEY_USER_DEFINED);
+
+    let secret = b"top secret payload";
+    let plaintext_was_sent = || {
+      let mut buf = [0; 1500];
+      let mut found = false;
+      while let Ok(len) = socket.recv(&mut buf) {
+        found |= buf[..len].windows(secret.len()).any(|w| w == secret);
+      }
+      found
+    };
+
+    writer_command_sender
+      .send(WriterCommand::DDSData {
+        ddsdata: DDSData::new(SerializedPayload::new(
+          RepresentationIdentifier::CDR_LE,
+          secret.to_vec(),
+        )),
+        write_options: WriteOptions::default(),
+        sequence_number: SequenceNumber::new(1),
+      })
+      .unwrap();
+    writer.process_writer_command();
+    assert!(!plaintext_was_sent());
+
+    // Retries do not send it either, but the change stays queued.
+    thread::sleep(std::time::Duration::from_millis(300));
+    writer.handle_timed_event();
+    assert!(!plaintext_was_sent());
+    assert_eq!(
+      writer.readers[&reader_guid].first_unsent_change(),
+      Some(SequenceNumber::new(1))
+    );
+  }
+
+  #[test]
+  fn oversized_message_is_split_into_datagrams() {
+    let qos = QosPolicyBuilder::new().build();
+    let (writer, _writer_command_sender, socket) =
+      test_writer(&qos, UDPSender::new(0).unwrap(), None);
+    let reader_guid = GUID::dummy_test_guid(EntityKind::READER_NO_KEY_USER_DEFINED);
+
+    // Far more submessages than fit into one datagram, although no message
+    // size limit is configured.
+    let count = 3000;
+    let mut builder = MessageBuilder::new().ts_msg(Endianness::LittleEndian, None);
+    for sn in 1..=count {
+      builder = builder.coherent_set_end_msg(
+        reader_guid.entity_id,
+        writer.guid(),
+        SequenceNumber::new(sn),
+        Endianness::LittleEndian,
+      );
+    }
+    let message = builder.add_header_and_build(writer.guid().prefix);
+    assert!(message.write_to_vec().unwrap().len() > MAX_MESSAGE_SIZE);
+    writer.send_message_to_readers(DeliveryMode::Unicast, message, &mut writer.readers.values());
+
+    // Each datagram is a message of its own, and together they have all the
+    // submessages in order.
+    let mut datagram_count = 0;
+    let mut received_sns = Vec::new();
+    let mut buf = vec![0; 65536];
+    while let Ok(len) = socket.recv(&mut buf) {
+      assert!(len <= MAX_MESSAGE_SIZE);
+      datagram_count += 1;
+      let message = Message::read_from_buffer(&Bytes::copy_from_slice(&buf[..len])).unwrap();
+      for sm in message.submessages() {
+        if let SubmessageBody::Writer(WriterSubmessage::Data(data, _)) = sm.body {
+          received_sns.push(data.writer_sn);
+        }
+      }
+    }
+    assert!(datagram_count > 1);
+    assert_eq!(
+      received_sns,
+      (1..=count).map(SequenceNumber::new).collect::<Vec<_>>()
+    );
+  }
+
+  #[test]
+  fn nack_for_overwritten_changes_gets_gap() {
+    let qos = QosPolicyBuilder::new()
+      .reliability(Reliability::Reliable {
+        max_blocking_time: crate::Duration::from_millis(100),
+      })
+      .history(History::KeepLast { depth: 1 })
+      .build();
+    let (mut writer, writer_command_sender, socket) =
+      test_writer(&qos, UDPSender::new(0).unwrap(), None);
+    let reader_guid = GUID::dummy_test_guid(EntityKind::READER_NO_KEY_USER_DEFINED);
+
+    // Each sample overwrites the previous one in the history.
+    for sn in 1..=5 {
+      writer_command_sender
+        .send(WriterCommand::DDSData {
+          ddsdata: DDSData::new(SerializedPayload::new(
+            RepresentationIdentifier::CDR_LE,
+            vec![sn as u8; 4],
+          )),
+          write_options: WriteOptions::default(),
+          sequence_number: SequenceNumber::new(sn),
+        })
+        .unwrap();
+      writer.process_writer_command();
+    }
+    assert_eq!(writer.first_change_sequence_number, SequenceNumber::new(5));
+    // Discard anything sent on matching and writing
+    while socket.recv(&mut [0; 1500]).is_ok() {}
+
+    // The reader missed all but the latest.
+    let missing = BTreeSet::from_iter((1..=4).map(SequenceNumber::new));
+    writer.handle_ack_nack(
+      reader_guid.prefix,
+      &AckSubmessage::AckNack(AckNack {
+        reader_id: reader_guid.entity_id,
+        writer_id: writer.entity_id(),
+        reader_sn_state: SequenceNumberSet::from_base_and_set(SequenceNumber::new(1), &missing),
+        count: 1,
+      }),
+    );
+    thread::sleep(NACK_RESPONSE_DELAY + std::time::Duration::from_millis(100));
+    writer.handle_timed_event();
+
+    let mut old_data_count = 0;
+    let mut irrelevant = BTreeSet::new();
+    let mut buf = [0; 1500];
+    while let Ok(len) = socket.recv(&mut buf) {
+      let message = Message::read_from_buffer(&Bytes::copy_from_slice(&buf[..len])).unwrap();
+      for sm in message.submessages() {
+        match sm.body {
+          // The latest change is not acknowledged yet, so it may be repaired.
+          SubmessageBody::Writer(WriterSubmessage::Data(ref data, _))
+            if data.writer_sn < SequenceNumber::new(5) =>
+          {
+            old_data_count += 1;
+          }
+          SubmessageBody::Writer(WriterSubmessage::Gap(gap, _)) => {
+            let mut sn = gap.gap_start;
+            while sn < gap.gap_list.base() {
+              irrelevant.insert(sn);
+              sn = sn + SequenceNumber::new(1);
+            }
+            irrelevant.extend(gap.gap_list.iter());
+          }
+          _ => (),
+        }
+      }
+    }
+    // One GAP covers everything, and nothing old is sent again.
+    assert_eq!(old_data_count, 0);
+    assert_eq!(irrelevant, missing);
+    assert_eq!(writer.readers[&reader_guid].first_unsent_change(), None);
+  }
+
+  // Writes one sample to a Reliable Writer with a matched reader, and counts
+  // the HEARTBEATs from periodic ticks before and after the reader
+  // acknowledges it.
+  fn heartbeats_before_and_after_ack(qos: QosPolicies) -> (usize, usize) {
+    let (mut writer, writer_command_sender, socket) =
+      test_writer(&qos, UDPSender::new(0).unwrap(), None);
+    assert_eq!(writer.heartbeat_period, Some(Duration::from_millis(100)));
+    let reader_guid = GUID::dummy_test_guid(EntityKind::READER_NO_KEY_USER_DEFINED);
+
+    writer_command_sender
+      .send(WriterCommand::DDSData {
+        ddsdata: DDSData::new(SerializedPayload::new(
+          RepresentationIdentifier::CDR_LE,
+          vec![1, 2, 3, 4],
+        )),
+        write_options: WriteOptions::default(),
+        sequence_number: SequenceNumber::new(1),
+      })
+      .unwrap();
+    writer.process_writer_command();
+
+    // Discard anything sent on matching and writing
+    while socket.recv(&mut [0; 1500]).is_ok() {}
+
+    let received_heartbeat_count = || {
+      let mut heartbeat_count = 0;
+      let mut buf = [0; 1500];
+      while let Ok(len) = socket.recv(&mut buf) {
+        let message = Message::read_from_buffer(&Bytes::copy_from_slice(&buf[..len])).unwrap();
+        heartbeat_count += message
+          .submessages()
+          .iter()
+          .filter(|sm| {
+            matches!(
+              sm.body,
+              SubmessageBody::Writer(WriterSubmessage::Heartbeat(..))
+            )
+          })
+          .count();
+      }
+      heartbeat_count
+    };
+
+    writer.handle_heartbeat_tick(false);
+    let before = received_heartbeat_count();
+
+    writer.handle_ack_nack(
+      reader_guid.prefix,
+      &AckSubmessage::AckNack(AckNack {
+        reader_id: reader_guid.entity_id,
+        writer_id: writer.entity_id(),
+        reader_sn_state: SequenceNumberSet::new_empty(SequenceNumber::new(2)),
+        count: 1,
+      }),
+    );
+    while socket.recv(&mut [0; 1500]).is_ok() {}
+
+    writer.handle_heartbeat_tick(false);
+    let after = received_heartbeat_count();
+    (before, after)
+  }
+
+  #[test]
+  fn heartbeats_stop_when_acked_with_suppression() {
+    let qos = QosPolicyBuilder::new()
+      .reliability(Reliability::Reliable {
+        max_blocking_time: crate::Duration::from_millis(100),
+      })
+      .property(policy::Property {
+        value: vec![Property::local(WRITER_HEARTBEAT_PERIOD_PROPERTY, "100")],
+        binary_value: vec![],
+      })
+      .build();
+    assert_eq!(heartbeats_before_and_after_ack(qos.clone()), (1, 0));
+
+    let unsuppressed_qos = QosPolicyBuilder::new()
+      .property(policy::Property {
+        value: vec![
+          Property::local(WRITER_HEARTBEAT_PERIOD_PROPERTY, "100"),
+          Property::local(WRITER_HEARTBEAT_SUPPRESSION_PROPERTY, "false"),
+        ],
+        binary_value: vec![],
+      })
+      .build();
+    assert_eq!(
+      heartbeats_before_and_after_ack(qos.modify_by(&unsuppressed_qos)),
+      (1, 1)
+    );
+  }
 }
@@ -19,8 +19,8 @@ use self::{
 use super::{AccessControl, PermissionsHandle};
 
 //mod config_error; --> crate::security::config
-mod domain_governance_document;
-mod domain_participant_permissions_document;
+pub(crate) mod domain_governance_document;
+pub(crate) mod domain_participant_permissions_document;
 //mod permissions_ca_certificate; --> crate::security::certificate
 pub mod s_mime_config_parser;
 
@@ -154,11 +154,6 @@ impl AccessControlBuiltin {
     data_tags: &[(&str, &str)],
     entity_kind: &Entity,
   ) -> SecurityResult<()> {
-    // TODO: remove after testing
-    if true {
-      return Ok(());
-    }
-
     let grant = self.get_grant(&permissions_handle)?;
     let domain_rule = self.get_domain_rule(&permissions_handle)?;
 
@@ -3,18 +3,27 @@ use glob::*;
 pub use xml::{BasicProtectionKind, ProtectionKind};
 
 use crate::security::config::ConfigError;
-use super::domain_participant_permissions_document::DomainIds;
+use super::{
+  domain_participant_permissions_document::DomainIds, s_mime_config_parser::verified_document_text,
+};
 
 // This module provides access (parsing and query) to Domain Governance
 // Document as specified in Section "9.4.1.2 Domain Governance Document" of
 // DDS Security Spec v1.1
 
-#[derive(Debug)]
+/// Parsed Domain Governance Document. Exported to applications as
+/// `GovernanceConfig`.
+#[derive(Debug, Clone)]
 pub struct DomainGovernanceDocument {
   domain_access_rules: Vec<DomainRule>,
 }
 
 impl DomainGovernanceDocument {
+  /// Domain rules in the order they are given in the document
+  pub fn domain_rules(&self) -> &[DomainRule] {
+    &self.domain_access_rules
+  }
+
   // Find an applicable rule for domain according to
   // Section "9.4.1.2.7 Application of Domain and Topic Rules"
   //
@@ -46,8 +55,22 @@ impl DomainGovernanceDocument {
       domain_access_rules,
     })
   }
+
+  /// Parse a governance document in the signed S/MIME format given to a
+  /// participant, after verifying the signature with the Permissions CA
+  /// certificate (PEM).
+  pub fn from_signed_document(
+    signed_document: &[u8],
+    permissions_ca_certificate_pem: &[u8],
+  ) -> Result<Self, ConfigError> {
+    Self::from_xml(&verified_document_text(
+      signed_document,
+      permissions_ca_certificate_pem,
+    )?)
+  }
 }
 
+/// Rule that applies to a set of domains
 #[derive(Debug, Clone)]
 pub struct DomainRule {
   pub domains: Vec<DomainIds>,
@@ -91,6 +114,7 @@ impl DomainRule {
   }
 }
 
+/// Protection of the topics whose name matches `topic_expression`
 #[derive(Debug, Clone)]
 pub struct TopicRule {
   pub topic_expression: Pattern,
@@ -378,4 +402,105 @@ xsi:noNamespaceSchemaLocation="http://www.omg.org/spec/DDS-Security/20170801/omg
       DomainGovernanceDocument::from_xml(domain_governance_document).unwrap()
     );
   }
+
+  #[test]
+  pub fn typed_topic_rules() {
+    let domain_governance_document = r#"<?xml version="1.0" encoding="utf-8"?>
+<dds xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance"
+xsi:noNamespaceSchemaLocation="http://www.omg.org/spec/DDS-Security/20170801/omg_shared_ca_domain_governance.xsd">
+  <domain_access_rules>
+    <domain_rule>
+      <domains>
+        <id_range>
+          <min>10</min>
+          <max>20</max>
+        </id_range>
+      </domains>
+      <allow_unauthenticated_participants>false</allow_unauthenticated_participants>
+      <enable_join_access_control>true</enable_join_access_control>
+      <discovery_protection_kind>ENCRYPT</discovery_protection_kind>
+      <liveliness_protection_kind>SIGN</liveliness_protection_kind>
+      <rtps_protection_kind>SIGN_WITH_ORIGIN_AUTHENTICATION</rtps_protection_kind>
+      <topic_access_rules>
+        <topic_rule>
+          <topic_expression>Secret*</topic_expression>
+          <enable_discovery_protection>true</enable_discovery_protection>
+          <enable_liveliness_protection>false</enable_liveliness_protection>
+          <enable_read_access_control>true</enable_read_access_control>
+          <enable_write_access_control>true</enable_write_access_control>
+          <metadata_protection_kind>ENCRYPT_WITH_ORIGIN_AUTHENTICATION</metadata_protection_kind>
+          <data_protection_kind>ENCRYPT</data_protection_kind>
+        </topic_rule>
+        <topic_rule>
+          <topic_expression>*</topic_expression>
+          <enable_discovery_protection>false</enable_discovery_protection>
+          <enable_liveliness_protection>false</enable_liveliness_protection>
+          <enable_read_access_control>false</enable_read_access_control>
+          <enable_write_access_control>true</enable_write_access_control>
+          <metadata_protection_kind>NONE</metadata_protection_kind>
+          <data_protection_kind>SIGN</data_protection_kind>
+        </topic_rule>
+      </topic_access_rules>
+    </domain_rule>
+  </domain_access_rules>
+</dds>
+"#;
+
+    let governance = DomainGovernanceDocument::from_xml(domain_governance_document).unwrap();
+    assert_eq!(governance.domain_rules().len(), 1);
+    assert!(governance.find_rule(9).is_none());
+
+    let domain_rule = governance.find_rule(15).unwrap();
+    assert!(!domain_rule.allow_unauthenticated_participants);
+    assert!(domain_rule.enable_join_access_control);
+    assert_eq!(
+      domain_rule.discovery_protection_kind,
+      ProtectionKind::Encrypt
+    );
+    assert_eq!(domain_rule.liveliness_protection_kind, ProtectionKind::Sign);
+    assert_eq!(
+      domain_rule.rtps_protection_kind,
+      ProtectionKind::SignWithOriginAuthentication
+    );
+    assert_eq!(domain_rule.topic_access_rules.len(), 2);
+
+    // Rules are matched in document order
+    let secret = domain_rule.find_topic_rule("SecretSquare").unwrap();
+    assert_eq!(secret.topic_expression.as_str(), "Secret*");
+    assert!(secret.enable_discovery_protection);
+    assert!(!secret.enable_liveliness_protection);
+    assert!(secret.enable_read_access_control);
+    assert!(secret.enable_write_access_control);
+    assert_eq!(
+      secret.metadata_protection_kind,
+      ProtectionKind::EncryptWithOriginAuthentication
+    );
+    assert_eq!(secret.data_protection_kind, BasicProtectionKind::Encrypt);
+
+    let other = domain_rule.find_topic_rule("Square").unwrap();
+    assert_eq!(other.topic_expression.as_str(), "*");
+    assert!(!other.enable_read_access_control);
+    assert!(other.enable_write_access_control);
+    assert_eq!(other.metadata_protection_kind, ProtectionKind::None);
+    assert_eq!(other.data_protection_kind, BasicProtectionKind::Sign);
+  }
+
+  #[test]
+  pub fn parse_signed_example_file() {
+    let config_dir = std::path::Path::new("example_security_configuration_files");
+    let signed = std::fs::read(config_dir.join("permissive_governance.p7s")).unwrap();
+    let permissions_ca = std::fs::read(config_dir.join("permissions_ca_certificate.pem")).unwrap();
+    let identity_ca = std::fs::read(config_dir.join("identity_ca_certificate.pem")).unwrap();
+
+    let governance =
+      DomainGovernanceDocument::from_signed_document(&signed, &permissions_ca).unwrap();
+    let topic_rule = governance
+      .find_rule(0)
+      .and_then(|dr| dr.find_topic_rule("any_topic"))
+      .unwrap();
+    assert_eq!(topic_rule.data_protection_kind, BasicProtectionKind::None);
+
+    // Not signed by the identity CA
+    assert!(DomainGovernanceDocument::from_signed_document(&signed, &identity_ca).is_err());
+  }
 }
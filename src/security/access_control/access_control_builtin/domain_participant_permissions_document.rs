@@ -7,8 +7,10 @@ use crate::security::{
   certificate::DistinguishedName,
   config::{parse_config_error, to_config_error_parse, ConfigError},
 };
+use super::s_mime_config_parser::verified_document_text;
 
-// A list of Grants
+/// Parsed DomainParticipant Permissions Document, i.e. a list of Grants.
+/// Exported to applications as `PermissionsConfig`.
 #[derive(Debug, Clone)]
 pub struct DomainParticipantPermissions {
   pub grants: Vec<Grant>,
@@ -45,6 +47,19 @@ impl DomainParticipantPermissions {
       original_string: domain_participant_permissions_xml.into(),
     })
   }
+
+  /// Parse a permissions document in the signed S/MIME format given to a
+  /// participant, after verifying the signature with the Permissions CA
+  /// certificate (PEM).
+  pub fn from_signed_document(
+    signed_document: &[u8],
+    permissions_ca_certificate_pem: &[u8],
+  ) -> Result<Self, ConfigError> {
+    Self::from_xml(&verified_document_text(
+      signed_document,
+      permissions_ca_certificate_pem,
+    )?)
+  }
 }
 
 // A Grant is a set of permissions for a particular DomainParticipant, which
@@ -282,7 +297,7 @@ impl Rule {
   }
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum AllowOrDeny {
   Allow,
   Deny,
@@ -326,6 +341,18 @@ pub struct Criterion {
 }
 
 impl Criterion {
+  pub fn topics(&self) -> &[Pattern] {
+    &self.topics
+  }
+
+  pub fn partitions(&self) -> &[Pattern] {
+    &self.partitions
+  }
+
+  pub fn data_tags(&self) -> &[DataTag] {
+    &self.data_tags
+  }
+
   pub fn is_applicable<'a>(
     &self,
     topic_name: &'a str,
@@ -396,6 +423,14 @@ pub struct DataTag {
 }
 
 impl DataTag {
+  pub fn name(&self) -> &str {
+    &self.name
+  }
+
+  pub fn value(&self) -> &str {
+    &self.value
+  }
+
   fn check(&self, name: &str, value: &str) -> bool {
     name == self.name && value == self.value
   }
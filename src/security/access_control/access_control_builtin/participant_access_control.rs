@@ -1,3 +1,4 @@
+use bytes::Bytes;
 use chrono::Utc;
 
 use crate::{
@@ -38,49 +39,26 @@ const QOS_PERMISSIONS_CERTIFICATE_PROPERTY_NAME: &str = "dds.sec.access.permissi
 const QOS_GOVERNANCE_DOCUMENT_PROPERTY_NAME: &str = "dds.sec.access.governance";
 const QOS_PERMISSIONS_DOCUMENT_PROPERTY_NAME: &str = "dds.sec.access.permissions";
 
-impl AccessControlBuiltin {
-  fn check_participant(
-    &self,
-    permissions_handle: PermissionsHandle,
-    domain_id: u16,
-  ) -> SecurityResult<()> {
-    let grant = self.get_grant(&permissions_handle)?;
-    let DomainRule {
-      // corresponds to is_access_protected
-      enable_join_access_control,
-      topic_access_rules,
-      ..
-    } = self.get_domain_rule(&permissions_handle)?;
-
-    let unprotected_topics = !enable_join_access_control
-      || topic_access_rules.iter().any(
-        |TopicRule {
-           enable_read_access_control,
-           enable_write_access_control,
-           ..
-         }| !(*enable_read_access_control && *enable_write_access_control),
-      );
-
-    // The specification seems to have a mistake here for check_create_participant. We should also check for protected topics for which we haver permissions. See https://issues.omg.org/issues/DDSSEC12-79
-    let joinable_topics = unprotected_topics || grant.check_participant_join(domain_id);
-
-    joinable_topics.then_some(()).ok_or_else(|| {
-      security_error!(
-        "The participant is not allowed to join any topic by the domain rule nor the grant."
-      )
-    })
-  }
+// The local permissions configuration, read from the files given in the
+// participant QoS
+struct LocalPermissions {
+  permissions_ca_certificate: Certificate,
+  domain_rule: DomainRule,
+  subject_name: DistinguishedName,
+  domain_participant_permissions: DomainParticipantPermissions,
+  signed_permissions: Bytes,
 }
 
-// 9.4.3
-impl ParticipantAccessControl for AccessControlBuiltin {
-  fn validate_local_permissions(
-    &mut self,
+impl AccessControlBuiltin {
+  // Reads and verifies the documents given in the participant QoS. Used by both
+  // validate_local_permissions and reload_local_permissions.
+  fn read_local_permissions(
+    &self,
     auth_plugin: &dyn Authentication,
     identity_handle: IdentityHandle,
     domain_id: u16,
     participant_qos: &QosPolicies,
-  ) -> SecurityResult<PermissionsHandle> {
+  ) -> SecurityResult<LocalPermissions> {
     let permissions_ca_certificate = participant_qos
       .get_property(QOS_PERMISSIONS_CERTIFICATE_PROPERTY_NAME)
       .and_then(|certificate_uri| {
@@ -163,7 +141,27 @@ impl ParticipantAccessControl for AccessControlBuiltin {
       ))?;
     }
 
-    let permissions_handle = self.generate_permissions_handle();
+    Ok(LocalPermissions {
+      permissions_ca_certificate,
+      domain_rule,
+      subject_name,
+      domain_participant_permissions,
+      signed_permissions,
+    })
+  }
+
+  fn store_local_permissions(
+    &mut self,
+    permissions_handle: PermissionsHandle,
+    local_permissions: LocalPermissions,
+  ) {
+    let LocalPermissions {
+      permissions_ca_certificate,
+      domain_rule,
+      subject_name,
+      domain_participant_permissions,
+      signed_permissions,
+    } = local_permissions;
     self.domain_rules.insert(permissions_handle, domain_rule);
     self.domain_participant_permissions.insert(
       permissions_handle,
@@ -172,15 +170,79 @@ impl ParticipantAccessControl for AccessControlBuiltin {
     self
       .signed_permissions_documents
       .insert(permissions_handle, signed_permissions);
-    self
-      .identity_to_permissions
-      .insert(identity_handle, permissions_handle);
     self
       .permissions_ca_certificates
       .insert(permissions_handle, permissions_ca_certificate);
+  }
+
+  fn check_participant(
+    &self,
+    permissions_handle: PermissionsHandle,
+    domain_id: u16,
+  ) -> SecurityResult<()> {
+    let grant = self.get_grant(&permissions_handle)?;
+    let DomainRule {
+      // corresponds to is_access_protected
+      enable_join_access_control,
+      topic_access_rules,
+      ..
+    } = self.get_domain_rule(&permissions_handle)?;
+
+    let unprotected_topics = !enable_join_access_control
+      || topic_access_rules.iter().any(
+        |TopicRule {
+           enable_read_access_control,
+           enable_write_access_control,
+           ..
+         }| !(*enable_read_access_control && *enable_write_access_control),
+      );
+
+    // The specification seems to have a mistake here for check_create_participant. We should also check for protected topics for which we haver permissions. See https://issues.omg.org/issues/DDSSEC12-79
+    let joinable_topics = unprotected_topics || grant.check_participant_join(domain_id);
+
+    joinable_topics.then_some(()).ok_or_else(|| {
+      security_error!(
+        "The participant is not allowed to join any topic by the domain rule nor the grant."
+      )
+    })
+  }
+}
+
+// 9.4.3
+impl ParticipantAccessControl for AccessControlBuiltin {
+  fn validate_local_permissions(
+    &mut self,
+    auth_plugin: &dyn Authentication,
+    identity_handle: IdentityHandle,
+    domain_id: u16,
+    participant_qos: &QosPolicies,
+  ) -> SecurityResult<PermissionsHandle> {
+    let local_permissions =
+      self.read_local_permissions(auth_plugin, identity_handle, domain_id, participant_qos)?;
+    let permissions_handle = self.generate_permissions_handle();
+    self.store_local_permissions(permissions_handle, local_permissions);
+    self
+      .identity_to_permissions
+      .insert(identity_handle, permissions_handle);
     Ok(permissions_handle)
   }
 
+  fn reload_local_permissions(
+    &mut self,
+    auth_plugin: &dyn Authentication,
+    identity_handle: IdentityHandle,
+    domain_id: u16,
+    participant_qos: &QosPolicies,
+  ) -> SecurityResult<()> {
+    let permissions_handle = *self.get_permissions_handle(&identity_handle)?;
+    // Read everything before storing anything, so that a failure leaves the
+    // previous documents in effect.
+    let local_permissions =
+      self.read_local_permissions(auth_plugin, identity_handle, domain_id, participant_qos)?;
+    self.store_local_permissions(permissions_handle, local_permissions);
+    Ok(())
+  }
+
   fn validate_remote_permissions(
     &mut self,
     _auth_plugin: &dyn Authentication,
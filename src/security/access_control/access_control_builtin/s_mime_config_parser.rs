@@ -39,6 +39,19 @@ use crate::{
   security_error,
 };
 
+// Verify a signed document with the Permissions CA certificate (PEM) and
+// return the verified contents as text.
+pub(crate) fn verified_document_text(
+  signed_document: &[u8],
+  permissions_ca_certificate_pem: &[u8],
+) -> Result<String, ConfigError> {
+  let permissions_ca_certificate = Certificate::from_pem(permissions_ca_certificate_pem)?;
+  let verified = SignedDocument::from_bytes(signed_document)?
+    .verify_signature(&permissions_ca_certificate)
+    .map_err(|e| ConfigError::Security(e.msg))?;
+  Ok(String::from_utf8_lossy(verified.as_ref()).into_owned())
+}
+
 #[derive(Debug)]
 pub struct SignedDocument {
   input_bytes: Bytes,
@@ -37,6 +37,20 @@ pub trait ParticipantAccessControl: Send {
     participant_qos: &QosPolicies,
   ) -> SecurityResult<PermissionsHandle>;
 
+  /// Re-read the governance and permissions documents given in the
+  /// participant PropertyQosPolicy, and replace the ones validated by
+  /// validate_local_permissions. Not in the specification.
+  ///
+  /// The PermissionsHandle stays the same. If the new documents cannot be
+  /// read or verified, the previous ones stay in effect.
+  fn reload_local_permissions(
+    &mut self,
+    auth_plugin: &dyn Authentication,
+    identity: IdentityHandle,
+    domain_id: u16,
+    participant_qos: &QosPolicies,
+  ) -> SecurityResult<()>;
+
   /// validate_remote_permissions: section 8.4.2.9.2 of the Security
   /// specification
   fn validate_remote_permissions(
@@ -1,4 +1,4 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, time::Duration};
 
 use bytes::Bytes;
 use ring::agreement;
@@ -70,16 +70,34 @@ pub(crate) enum BuiltinHandshakeState {
 // This is a mirror of the above states, but with no data carried from
 // one state to another. This is for use in secure Discovery.
 // TODO: Refactor (how?) to not need to separate types for this.
-#[derive(Clone, Copy, PartialEq, Debug)]
-pub(crate) enum DiscHandshakeState {
+/// State of an authentication handshake with a remote DomainParticipant. See
+/// [`DomainParticipant::pending_handshakes`](crate::DomainParticipant::pending_handshakes).
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum DiscHandshakeState {
+  /// We are the initiator, but have not yet sent the handshake request.
   PendingRequestSend,
+  /// We are the replier, waiting for the handshake request.
   PendingRequestMessage,
+  /// We have sent the request, waiting for the reply.
   PendingReplyMessage,
+  /// We have sent the reply, waiting for the final message.
   PendingFinalMessage,
+  /// Handshake completed. We sent the final message.
   CompletedWithFinalMessageSent,
+  /// Handshake completed. We received the final message.
   CompletedWithFinalMessageReceived,
 }
 
+impl DiscHandshakeState {
+  /// Is the handshake finished, successfully.
+  pub fn is_completed(&self) -> bool {
+    matches!(
+      self,
+      Self::CompletedWithFinalMessageSent | Self::CompletedWithFinalMessageReceived
+    )
+  }
+}
+
 struct LocalParticipantInfo {
   identity_handle: IdentityHandle,
   identity_token: BuiltinIdentityToken,
@@ -122,8 +140,20 @@ pub struct AuthenticationBuiltin {
   next_handshake_handle: HandshakeHandle,
 
   mock_handshakes: bool, // Mock handshakes for testing? Temporary field, for development only
+
+  // Allowed clock skew when checking certificate validity periods
+  certificate_validity_tolerance: Duration,
+
+  // Where to (re)load the Certificate Revocation List from, if configured
+  certificate_revocation_list_uri: Option<String>,
+  // Revoked identity certificates, issued by our identity CA
+  certificate_revocation_list: Option<certificate::CertificateRevocationList>,
 }
 
+// Default allowance for clock skew between participants, when checking
+// notBefore / notAfter of identity certificates.
+const DEFAULT_CERTIFICATE_VALIDITY_TOLERANCE: Duration = Duration::from_secs(60);
+
 impl AuthenticationBuiltin {
   pub fn new() -> Self {
     Self {
@@ -133,6 +163,9 @@ impl AuthenticationBuiltin {
       next_identity_handle: 0,
       next_handshake_handle: 0,
       mock_handshakes: false,
+      certificate_validity_tolerance: DEFAULT_CERTIFICATE_VALIDITY_TOLERANCE,
+      certificate_revocation_list_uri: None,
+      certificate_revocation_list: None,
     }
   }
 
@@ -182,6 +215,18 @@ impl AuthenticationBuiltin {
       .ok_or_else(|| security_error!("Remote participant info not found"))
   }
 
+  // Check that the certificate is not on our Certificate Revocation List.
+  // Passes trivially, if there is no CRL configured. Fails, if the CRL has
+  // expired since it was loaded, until a new one is loaded.
+  fn check_not_revoked(&self, certificate: &certificate::Certificate) -> SecurityResult<()> {
+    match self.certificate_revocation_list {
+      Some(ref crl) => crl
+        .check_validity(self.certificate_validity_tolerance)
+        .and_then(|()| crl.check_not_revoked(certificate)),
+      None => Ok(()),
+    }
+  }
+
   fn handshake_handle_to_identity_handle(
     &self,
     hs_handle: &HandshakeHandle,
@@ -1,4 +1,4 @@
-use std::cmp::Ordering;
+use std::{cmp::Ordering, time::Duration};
 
 use speedy::Writable;
 use bytes::{Bytes, BytesMut};
@@ -47,6 +47,40 @@ const QOS_IDENTITY_CA_PROPERTY_NAME: &str = "dds.sec.auth.identity_ca";
 const QOS_IDENTITY_CERTIFICATE_PROPERTY_NAME: &str = "dds.sec.auth.identity_certificate";
 const QOS_PRIVATE_KEY_PROPERTY_NAME: &str = "dds.sec.auth.private_key";
 const QOS_PASSWORD_PROPERTY_NAME: &str = "dds.sec.auth.password";
+// Not in the spec. Tolerance in seconds for clock skew when checking the
+// validity period of identity certificates.
+const QOS_CERTIFICATE_VALIDITY_TOLERANCE_PROPERTY_NAME: &str =
+  "dds.sec.auth.certificate_validity_tolerance";
+// Not in the spec. URI of a Certificate Revocation List (PEM) issued by the
+// identity CA. Identity certificates listed there are rejected.
+const QOS_CRL_PROPERTY_NAME: &str = "dds.sec.auth.crl";
+// Not in the spec. If "true", the GUID prefix is derived from the identity
+// certificate only, so that it stays the same across restarts.
+const QOS_DERIVE_GUID_FROM_CERTIFICATE_PROPERTY_NAME: &str =
+  "dds.sec.auth.derive_guid_from_certificate";
+
+// Read a CRL and make sure it is really issued by the identity CA, and is
+// currently valid.
+fn read_certificate_revocation_list(
+  crl_uri: &str,
+  identity_ca: &Certificate,
+  validity_tolerance: Duration,
+) -> SecurityResult<CertificateRevocationList> {
+  let crl = read_uri(crl_uri)
+    .and_then(CertificateRevocationList::from_pem)
+    .map_err(|conf_err| {
+      security_error!(
+        "Failed to read the certificate revocation list from {}: {:?}",
+        crl_uri,
+        conf_err
+      )
+    })?;
+  crl
+    .verify_signed_by_certificate(identity_ca)
+    .map_err(|e| security_error!("Certificate revocation list does not verify: {e}"))?;
+  crl.check_validity(validity_tolerance)?;
+  Ok(crl)
+}
 
 impl Authentication for AuthenticationBuiltin {
   fn validate_local_identity(
@@ -76,9 +110,9 @@ impl Authentication for AuthenticationBuiltin {
     // If it does not pass, our identity certificate is useless,
     // because others would not accept it either.
     //
-    // (Should also check if the certificate has been revoked.
-    // The CA certificate may have a revocation list and/or check an on-line OCSP
-    // server.)
+    // Check that the certificate has not been revoked. The identity CA may
+    // publish a revocation list (CRL), which is given to us via the
+    // PropertyQosPolicy. On-line OCSP checking is not supported.
     //
     // The returned IdentityHandle must be capable of
     // * reading this participant's public key (from identity_certificate)
@@ -135,6 +169,23 @@ impl Authentication for AuthenticationBuiltin {
         PrivateKey::from_pem(private_key_pem).map_err(|e| security_error!("{e:?}"))
       })?;
 
+    if let Some(tolerance) =
+      participant_qos.get_optional_property(QOS_CERTIFICATE_VALIDITY_TOLERANCE_PROPERTY_NAME)
+    {
+      self.certificate_validity_tolerance = tolerance
+        .trim()
+        .parse::<u64>()
+        .map(Duration::from_secs)
+        .map_err(|e| {
+          security_error!(
+            "Invalid value {:?} for {}: {}",
+            tolerance,
+            QOS_CERTIFICATE_VALIDITY_TOLERANCE_PROPERTY_NAME,
+            e
+          )
+        })?;
+    }
+
     // Verify that CA has signed our identity
     identity_certificate
       .verify_signed_by_certificate(&identity_ca)
@@ -142,7 +193,29 @@ impl Authentication for AuthenticationBuiltin {
         security_error!("My own identity certificate does not verify against identity CA.")
       })?;
 
-    // TODO: Check (somehow) that my identity has not been revoked.
+    // Verify that our identity (and its CA) are currently valid. Others would
+    // reject an expired identity anyway.
+    identity_ca
+      .check_validity(self.certificate_validity_tolerance)
+      .and_then(|()| identity_certificate.check_validity(self.certificate_validity_tolerance))
+      .map_err(|e| security_error!("My own identity is not valid: {e}"))?;
+
+    // Load the optional Certificate Revocation List, and check that my
+    // identity has not been revoked.
+    let crl_uri = participant_qos.get_optional_property(QOS_CRL_PROPERTY_NAME);
+    let crl = crl_uri
+      .as_ref()
+      .map(|uri| {
+        read_certificate_revocation_list(uri, &identity_ca, self.certificate_validity_tolerance)
+      })
+      .transpose()?;
+    if let Some(ref crl) = crl {
+      crl
+        .check_not_revoked(&identity_certificate)
+        .map_err(|e| security_error!("My own identity is not valid: {e}"))?;
+    }
+    self.certificate_revocation_list_uri = crl_uri;
+    self.certificate_revocation_list = crl;
 
     // Compute the new adjusted GUID
     // DDS Security spec v1.1 Section "9.3.3 DDS:Auth:PKI-DH plugin behavior", Table
@@ -160,10 +233,21 @@ impl Authentication for AuthenticationBuiltin {
         | 0x8000_0000_0000_0000u64)
         .to_be_bytes()[0..6];
 
-    let candidate_guid_hash = Sha256::hash(&candidate_participant_guid.to_bytes());
+    // The spec leaves the rest to the implementation. Normally it comes from the
+    // random candidate GUID, but it can also be derived from the whole identity
+    // certificate. Then the GUID is the same every time, but only one
+    // participant at a time may use the identity.
+    let derive_from_certificate = participant_qos
+      .get_optional_property(QOS_DERIVE_GUID_FROM_CERTIFICATE_PROPERTY_NAME)
+      .is_some_and(|s| s.trim().eq_ignore_ascii_case("true"));
+    let rest_hash = if derive_from_certificate {
+      Sha256::hash(identity_certificate.to_der())
+    } else {
+      Sha256::hash(&candidate_participant_guid.to_bytes())
+    };
 
     // slicing will succeed, because digest is longer than 6 bytes
-    let prefix_bytes = [&bytes_from_subject_name, &candidate_guid_hash.as_ref()[..6]].concat();
+    let prefix_bytes = [&bytes_from_subject_name, &rest_hash.as_ref()[..6]].concat();
 
     let adjusted_guid = GUID::new(
       GuidPrefix::new(&prefix_bytes),
@@ -490,6 +574,10 @@ impl Authentication for AuthenticationBuiltin {
 
     // Verify that 1's identity cert checks out against CA.
     cert1.verify_signed_by_certificate(&local_info.identity_ca)?;
+    // and that it is currently within its validity period
+    cert1.check_validity(self.certificate_validity_tolerance)?;
+    // and that it has not been revoked
+    self.check_not_revoked(&cert1)?;
 
     let pdata_bytes = Bytes::from(serialized_local_participant_data);
 
@@ -639,6 +727,10 @@ impl Authentication for AuthenticationBuiltin {
 
         // Verify that 2's identity cert checks out against CA.
         cert2.verify_signed_by_certificate(&local_info.identity_ca)?;
+        // and that it is currently within its validity period
+        cert2.check_validity(self.certificate_validity_tolerance)?;
+        // and that it has not been revoked
+        self.check_not_revoked(&cert2)?;
 
         // TODO: verify ocsp_status / status of IdentityCredential
 
@@ -928,9 +1020,184 @@ impl Authentication for AuthenticationBuiltin {
     Ok(AuthenticatedPeerCredentialToken::from(builtin_token))
   }
 
+  fn reload_certificate_revocation_list(&mut self) -> SecurityResult<()> {
+    let crl_uri = match self.certificate_revocation_list_uri {
+      Some(ref uri) => uri,
+      None => {
+        info!("No certificate revocation list configured. Nothing to reload.");
+        return Ok(());
+      }
+    };
+    let identity_ca = &self.get_local_participant_info()?.identity_ca;
+    let crl =
+      read_certificate_revocation_list(crl_uri, identity_ca, self.certificate_validity_tolerance)?;
+    self.certificate_revocation_list = Some(crl);
+    Ok(())
+  }
+
   fn set_listener(&self) -> SecurityResult<()> {
     Err(security_error!(
       "set_listener not supported. Use status events in DataReader/DataWriter instead."
     ))
   }
 }
+
+#[cfg(test)]
+mod tests {
+  use crate::{dds::qos::policy, security, QosPolicyBuilder};
+  use super::*;
+
+  const CONFIG_DIR: &str = "example_security_configuration_files";
+
+  fn property(name: &str, value: String) -> security::types::Property {
+    security::types::Property {
+      name: name.to_string(),
+      value,
+      propagate: false,
+    }
+  }
+
+  fn participant_qos(participant: &str, crl_path: &str) -> QosPolicies {
+    participant_qos_with(participant, crl_path, vec![])
+  }
+
+  fn participant_qos_with(
+    participant: &str,
+    crl_path: &str,
+    mut extra_properties: Vec<security::types::Property>,
+  ) -> QosPolicies {
+    let mut properties = vec![
+      property(
+        QOS_IDENTITY_CA_PROPERTY_NAME,
+        format!("file:{CONFIG_DIR}/identity_ca_certificate.pem"),
+      ),
+      property(
+        QOS_IDENTITY_CERTIFICATE_PROPERTY_NAME,
+        format!("file:{CONFIG_DIR}/{participant}_certificate.pem"),
+      ),
+      property(
+        QOS_PRIVATE_KEY_PROPERTY_NAME,
+        format!("file:{CONFIG_DIR}/{participant}_private_key.pem"),
+      ),
+      property(QOS_CRL_PROPERTY_NAME, format!("file:{crl_path}")),
+    ];
+    properties.append(&mut extra_properties);
+    QosPolicyBuilder::new()
+      .property(policy::Property {
+        value: properties,
+        binary_value: vec![],
+      })
+      .build()
+  }
+
+  fn local_participant(qos: &QosPolicies) -> (AuthenticationBuiltin, IdentityHandle, GUID) {
+    let mut auth = AuthenticationBuiltin::new();
+    let (_outcome, handle, guid) = auth
+      .validate_local_identity(0, qos, GUID::new_participant_guid())
+      .unwrap();
+    (auth, handle, guid)
+  }
+
+  // Runs a full handshake between two participants, in whichever role the
+  // GUID ordering gives them.
+  fn handshake(
+    a: &mut (AuthenticationBuiltin, IdentityHandle, GUID),
+    b: &mut (AuthenticationBuiltin, IdentityHandle, GUID),
+  ) -> SecurityResult<()> {
+    let a_token = a.0.get_identity_token(a.1)?;
+    let b_token = b.0.get_identity_token(b.1)?;
+    let (a_outcome, b_in_a, _) = a
+      .0
+      .validate_remote_identity(None, a.1, b_token, b.2.prefix)?;
+    let (_b_outcome, a_in_b, _) = b
+      .0
+      .validate_remote_identity(None, b.1, a_token, a.2.prefix)?;
+
+    let (initiator, initiator_remote, replier, replier_remote) =
+      if let ValidationOutcome::PendingHandshakeRequest = a_outcome {
+        (a, b_in_a, b, a_in_b)
+      } else {
+        (b, a_in_b, a, b_in_a)
+      };
+
+    let (_, initiator_hs, request) =
+      initiator
+        .0
+        .begin_handshake_request(initiator.1, initiator_remote, vec![1, 2, 3])?;
+    let (_, replier_hs, reply) =
+      replier
+        .0
+        .begin_handshake_reply(request, replier_remote, replier.1, vec![4, 5, 6])?;
+    let (_, final_message) = initiator.0.process_handshake(reply, initiator_hs)?;
+    replier
+      .0
+      .process_handshake(final_message.unwrap(), replier_hs)?;
+    Ok(())
+  }
+
+  #[test]
+  fn revoked_participant_fails_handshake() {
+    let crl_path =
+      std::env::temp_dir().join(format!("rustdds_test_crl_{}.pem", std::process::id()));
+    let crl_path_str = crl_path.to_str().unwrap().to_string();
+    std::fs::copy(format!("{CONFIG_DIR}/identity_ca_empty_crl.pem"), &crl_path).unwrap();
+
+    let mut participant1 = local_participant(&participant_qos("participant1", &crl_path_str));
+    let mut participant2 = local_participant(&participant_qos("participant2", &crl_path_str));
+
+    // Nothing revoked yet: authentication succeeds
+    handshake(&mut participant1, &mut participant2).unwrap();
+
+    // Revoke participant2 and reload the CRL at participant1
+    std::fs::copy(
+      format!("{CONFIG_DIR}/identity_ca_revoked_participant2_crl.pem"),
+      &crl_path,
+    )
+    .unwrap();
+    participant1.0.reload_certificate_revocation_list().unwrap();
+
+    // A fresh handshake with participant2 must now be rejected
+    let mut participant2_again = local_participant(&participant_qos(
+      "participant2",
+      &format!("{CONFIG_DIR}/identity_ca_empty_crl.pem"),
+    ));
+    assert!(handshake(&mut participant1, &mut participant2_again).is_err());
+
+    // Revoked participant cannot even start up with the new CRL
+    let mut revoked_auth = AuthenticationBuiltin::new();
+    assert!(revoked_auth
+      .validate_local_identity(
+        0,
+        &participant_qos("participant2", &crl_path_str),
+        GUID::new_participant_guid()
+      )
+      .is_err());
+
+    std::fs::remove_file(crl_path).unwrap();
+  }
+
+  #[test]
+  fn guid_derived_from_certificate_is_stable() {
+    let crl = format!("{CONFIG_DIR}/identity_ca_empty_crl.pem");
+    let derive = || {
+      vec![property(
+        QOS_DERIVE_GUID_FROM_CERTIFICATE_PROPERTY_NAME,
+        "true".to_string(),
+      )]
+    };
+
+    // Same identity, different candidate GUIDs: same GUID prefix
+    let (_, _, guid1) = local_participant(&participant_qos_with("participant1", &crl, derive()));
+    let (_, _, guid2) = local_participant(&participant_qos_with("participant1", &crl, derive()));
+    assert_eq!(guid1.prefix, guid2.prefix);
+
+    // Another identity gives another prefix
+    let (_, _, other) = local_participant(&participant_qos_with("participant2", &crl, derive()));
+    assert_ne!(guid1.prefix, other.prefix);
+
+    // By default the candidate GUID is used
+    let (_, _, random1) = local_participant(&participant_qos("participant1", &crl));
+    let (_, _, random2) = local_participant(&participant_qos("participant1", &crl));
+    assert_ne!(random1.prefix, random2.prefix);
+  }
+}
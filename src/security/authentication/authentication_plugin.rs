@@ -119,6 +119,12 @@ pub trait Authentication: Send {
     permissions_token: PermissionsToken,
   ) -> SecurityResult<()>;
 
+  /// Re-read the Certificate Revocation List given in the participant
+  /// PropertyQosPolicy at validate_local_identity. Not in the specification.
+  ///
+  /// Handshakes started after reloading are checked against the new list.
+  fn reload_certificate_revocation_list(&mut self) -> SecurityResult<()>;
+
   /// set_listener: section 8.3.2.11.12 of the Security
   /// specification.
   /// TODO: we do not need this as listeners are not used in RustDDS, but which
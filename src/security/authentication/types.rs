@@ -176,6 +176,27 @@ pub struct SharedSecretHandle {
   pub challenge2: Challenge, // 256-bit nonce
 }
 
+// Prefix of the fingerprint hash input, so that the fingerprint cannot be
+// mistaken for, or collide with, any key derived from the same material.
+const SHARED_SECRET_FINGERPRINT_LABEL: &[u8] = b"RustDDS shared secret fingerprint";
+
+impl SharedSecretHandle {
+  // A non-secret fingerprint of the key material, for audit. Both ends of a
+  // handshake compute the same fingerprint. Being a SHA-256 hash, it does not
+  // reveal the secret.
+  pub fn fingerprint(&self) -> Sha256 {
+    Sha256::hash(
+      &[
+        SHARED_SECRET_FINGERPRINT_LABEL,
+        self.shared_secret.as_ref(),
+        self.challenge1.as_ref(),
+        self.challenge2.as_ref(),
+      ]
+      .concat(),
+    )
+  }
+}
+
 // IdentityToken: section 8.3.2.1 of the Security specification (v. 1.1)
 #[derive(Debug, Clone, PartialEq, Eq, Readable, Writable)]
 pub struct IdentityToken {
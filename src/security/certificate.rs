@@ -17,12 +17,14 @@
 // Permissions documents. The verification of the two can use the same or
 // different Certificate instances.
 
+use std::time::{Duration, SystemTime};
+
 use bytes::Bytes;
 use x509_certificate::{
   certificate::CapturedX509Certificate, signing::InMemorySigningKeyPair, EcdsaCurve, KeyAlgorithm,
   Signer,
 };
-use der::Decode;
+use der::{Decode, Encode};
 use bcder::{encode::Values, Mode};
 
 use crate::security::{
@@ -38,6 +40,10 @@ use crate::security::{
 pub struct Certificate {
   cert: CapturedX509Certificate,
   subject_name: DistinguishedName,
+  issuer_name: DistinguishedName,
+  serial_number: Vec<u8>,
+  not_before: SystemTime,
+  not_after: SystemTime,
 }
 
 impl Certificate {
@@ -48,19 +54,73 @@ impl Certificate {
     let other_cert = x509_cert::certificate::Certificate::from_der(cert.constructed_data())
       .map_err(to_config_error_parse("Cannot read X.509 Certificate(2)"))?;
 
+    let validity = other_cert.tbs_certificate.validity;
+    let not_before = validity.not_before.to_system_time();
+    let not_after = validity.not_after.to_system_time();
+
+    let serial_number = other_cert.tbs_certificate.serial_number.as_bytes().to_vec();
+    let issuer_name = other_cert.tbs_certificate.issuer.into();
     let subject_name = other_cert.tbs_certificate.subject.into();
 
-    Ok(Certificate { cert, subject_name })
+    Ok(Certificate {
+      cert,
+      subject_name,
+      issuer_name,
+      serial_number,
+      not_before,
+      not_after,
+    })
   }
 
   pub fn to_pem(&self) -> String {
     self.cert.encode_pem()
   }
 
+  pub fn to_der(&self) -> &[u8] {
+    self.cert.constructed_data()
+  }
+
   pub fn subject_name(&self) -> &DistinguishedName {
     &self.subject_name
   }
 
+  pub fn issuer_name(&self) -> &DistinguishedName {
+    &self.issuer_name
+  }
+
+  pub fn not_before(&self) -> SystemTime {
+    self.not_before
+  }
+
+  pub fn not_after(&self) -> SystemTime {
+    self.not_after
+  }
+
+  // Check that `now` is within the validity period (notBefore ..= notAfter) of
+  // the certificate. `tolerance` is a grace period applied to both ends to
+  // allow for clock skew between participants.
+  pub fn check_validity_at(&self, now: SystemTime, tolerance: Duration) -> SecurityResult<()> {
+    if now + tolerance < self.not_before {
+      Err(security_error(&format!(
+        "Certificate {} is not yet valid. Valid from {}",
+        self.subject_name,
+        chrono::DateTime::<chrono::Utc>::from(self.not_before)
+      )))
+    } else if self.not_after + tolerance < now {
+      Err(security_error(&format!(
+        "Certificate {} has expired. Valid until {}",
+        self.subject_name,
+        chrono::DateTime::<chrono::Utc>::from(self.not_after)
+      )))
+    } else {
+      Ok(())
+    }
+  }
+
+  pub fn check_validity(&self, tolerance: Duration) -> SecurityResult<()> {
+    self.check_validity_at(SystemTime::now(), tolerance)
+  }
+
   pub fn subject_name_der(&self) -> Result<Vec<u8>, ConfigError> {
     let er = &self.cert.subject_name().encode_ref();
     let mut buf = Vec::with_capacity(er.encoded_len(Mode::Der));
@@ -126,6 +186,164 @@ impl Certificate {
   }
 }
 
+// X.509 Certificate Revocation List (CRL), see
+// https://datatracker.ietf.org/doc/html/rfc5280#section-5
+//
+// The CRL is issued by a Certification Authority and lists serial numbers of
+// certificates that must no longer be accepted, even though they have not
+// expired yet.
+#[derive(Clone, Debug)]
+pub struct CertificateRevocationList {
+  issuer_name: DistinguishedName,
+  revoked_serial_numbers: Vec<Vec<u8>>,
+  // The CRL is valid from this_update until next_update. Without next_update
+  // it does not expire.
+  this_update: SystemTime,
+  next_update: Option<SystemTime>,
+  // These are needed for signature verification
+  tbs_cert_list_der: Vec<u8>,
+  signature_algorithm: const_oid::ObjectIdentifier,
+  signature: Vec<u8>,
+}
+
+const ECDSA_WITH_SHA256_OID: const_oid::ObjectIdentifier =
+  const_oid::ObjectIdentifier::new_unwrap("1.2.840.10045.4.3.2");
+const SHA256_WITH_RSA_ENCRYPTION_OID: const_oid::ObjectIdentifier =
+  const_oid::ObjectIdentifier::new_unwrap("1.2.840.113549.1.1.11");
+
+impl CertificateRevocationList {
+  pub fn from_pem(pem_data: impl AsRef<[u8]>) -> Result<Self, ConfigError> {
+    let (label, der_data) = der::pem::decode_vec(pem_data.as_ref())
+      .map_err(to_config_error_parse("Cannot read CRL PEM"))?;
+    if label != "X509 CRL" {
+      return Err(ConfigError::Parse(format!(
+        "Expected PEM label X509 CRL, got {label}"
+      )));
+    }
+
+    let crl = x509_cert::crl::CertificateList::from_der(&der_data)
+      .map_err(to_config_error_parse("Cannot read X.509 CRL"))?;
+
+    let tbs_cert_list_der = crl
+      .tbs_cert_list
+      .to_der()
+      .map_err(to_config_error_other("Cannot encode X.509 CRL"))?;
+    let signature = crl
+      .signature
+      .as_bytes()
+      .ok_or_else(|| ConfigError::Parse("CRL signature has unused bits".to_string()))?
+      .to_vec();
+
+    let revoked_serial_numbers = crl
+      .tbs_cert_list
+      .revoked_certificates
+      .unwrap_or_default()
+      .iter()
+      .map(|revoked| revoked.serial_number.as_bytes().to_vec())
+      .collect();
+
+    let this_update = crl.tbs_cert_list.this_update.to_system_time();
+    let next_update = crl
+      .tbs_cert_list
+      .next_update
+      .map(|next_update| next_update.to_system_time());
+
+    Ok(CertificateRevocationList {
+      issuer_name: crl.tbs_cert_list.issuer.into(),
+      revoked_serial_numbers,
+      this_update,
+      next_update,
+      tbs_cert_list_der,
+      signature_algorithm: crl.signature_algorithm.oid,
+      signature,
+    })
+  }
+
+  pub fn issuer_name(&self) -> &DistinguishedName {
+    &self.issuer_name
+  }
+
+  // Verify that the CRL was issued and signed by the given CA. An unverified
+  // CRL must not be trusted, because otherwise anyone could revoke
+  // certificates.
+  pub fn verify_signed_by_certificate(&self, ca: &Certificate) -> SecurityResult<()> {
+    if !self.issuer_name.matches(ca.subject_name()) {
+      return Err(security_error(&format!(
+        "CRL issuer {} does not match CA {}",
+        self.issuer_name,
+        ca.subject_name()
+      )));
+    }
+    let verify_algorithm: &'static dyn ring::signature::VerificationAlgorithm =
+      if self.signature_algorithm == ECDSA_WITH_SHA256_OID {
+        &ring::signature::ECDSA_P256_SHA256_ASN1
+      } else if self.signature_algorithm == SHA256_WITH_RSA_ENCRYPTION_OID {
+        &ring::signature::RSA_PKCS1_2048_8192_SHA256
+      } else {
+        return Err(security_error(&format!(
+          "Unsupported CRL signature algorithm {}",
+          self.signature_algorithm
+        )));
+      };
+    ca.verify_signed_data_with_algorithm(&self.tbs_cert_list_der, &self.signature, verify_algorithm)
+  }
+
+  pub fn this_update(&self) -> SystemTime {
+    self.this_update
+  }
+
+  pub fn next_update(&self) -> Option<SystemTime> {
+    self.next_update
+  }
+
+  // Check that `now` is within thisUpdate ..= nextUpdate of the CRL. An expired
+  // CRL may be missing recent revocations, so it must not be trusted.
+  // `tolerance` allows for clock skew, as with certificates.
+  pub fn check_validity_at(&self, now: SystemTime, tolerance: Duration) -> SecurityResult<()> {
+    if now + tolerance < self.this_update {
+      Err(security_error(&format!(
+        "Certificate revocation list of {} is not yet valid. Valid from {}",
+        self.issuer_name,
+        chrono::DateTime::<chrono::Utc>::from(self.this_update)
+      )))
+    } else if self
+      .next_update
+      .is_some_and(|next_update| next_update + tolerance < now)
+    {
+      Err(security_error(&format!(
+        "Certificate revocation list of {} has expired. Next update was due {}",
+        self.issuer_name,
+        chrono::DateTime::<chrono::Utc>::from(self.next_update.unwrap_or(self.this_update))
+      )))
+    } else {
+      Ok(())
+    }
+  }
+
+  pub fn check_validity(&self, tolerance: Duration) -> SecurityResult<()> {
+    self.check_validity_at(SystemTime::now(), tolerance)
+  }
+
+  pub fn is_revoked(&self, certificate: &Certificate) -> bool {
+    self.issuer_name.matches(certificate.issuer_name())
+      && self
+        .revoked_serial_numbers
+        .contains(&certificate.serial_number)
+  }
+
+  pub fn check_not_revoked(&self, certificate: &Certificate) -> SecurityResult<()> {
+    if self.is_revoked(certificate) {
+      Err(security_error(&format!(
+        "Certificate {} has been revoked by {}",
+        certificate.subject_name(),
+        self.issuer_name
+      )))
+    } else {
+      Ok(())
+    }
+  }
+}
+
 // This represents X.501 Distinguished Name
 //
 // See https://datatracker.ietf.org/doc/html/rfc4514
@@ -237,4 +455,102 @@ EEJPTyIYWQldbZS8GH/4SOMViyWhL/BcSU48V0RidVPvyKqqiLyVaVmk
 
     println!("{:?}", key);
   }
+
+  fn read_fixture_certificate(file_name: &str) -> Certificate {
+    let pem = std::fs::read(format!("example_security_configuration_files/{file_name}")).unwrap();
+    Certificate::from_pem(pem).unwrap()
+  }
+
+  #[test]
+  pub fn validity_of_valid_certificate() {
+    let cert = read_fixture_certificate("participant1_certificate.pem");
+    assert!(cert.check_validity(Duration::ZERO).is_ok());
+  }
+
+  #[test]
+  pub fn validity_of_expired_certificate() {
+    let cert = read_fixture_certificate("participant1_expired_certificate.pem");
+    assert!(cert.check_validity(Duration::ZERO).is_err());
+    // Tolerance does not help if expiry was long ago
+    assert!(cert.check_validity(Duration::from_secs(3600)).is_err());
+    // But it was valid during its validity period
+    let during_validity = cert.not_before() + Duration::from_secs(3600);
+    assert!(cert
+      .check_validity_at(during_validity, Duration::ZERO)
+      .is_ok());
+  }
+
+  #[test]
+  pub fn validity_of_not_yet_valid_certificate() {
+    let cert = read_fixture_certificate("participant1_not_yet_valid_certificate.pem");
+    assert!(cert.check_validity(Duration::ZERO).is_err());
+  }
+
+  #[test]
+  pub fn validity_tolerance() {
+    let cert = read_fixture_certificate("participant1_expired_certificate.pem");
+    let tolerance = Duration::from_secs(60);
+
+    let just_expired = cert.not_after() + Duration::from_secs(30);
+    assert!(cert
+      .check_validity_at(just_expired, Duration::ZERO)
+      .is_err());
+    assert!(cert.check_validity_at(just_expired, tolerance).is_ok());
+
+    let almost_valid = cert.not_before() - Duration::from_secs(30);
+    assert!(cert
+      .check_validity_at(almost_valid, Duration::ZERO)
+      .is_err());
+    assert!(cert.check_validity_at(almost_valid, tolerance).is_ok());
+  }
+
+  fn read_fixture_crl(file_name: &str) -> CertificateRevocationList {
+    let pem = std::fs::read(format!("example_security_configuration_files/{file_name}")).unwrap();
+    CertificateRevocationList::from_pem(pem).unwrap()
+  }
+
+  #[test]
+  pub fn crl_revokes_certificate() {
+    let identity_ca = read_fixture_certificate("identity_ca_certificate.pem");
+    let participant1 = read_fixture_certificate("participant1_certificate.pem");
+    let participant2 = read_fixture_certificate("participant2_certificate.pem");
+
+    let empty_crl = read_fixture_crl("identity_ca_empty_crl.pem");
+    assert!(empty_crl.verify_signed_by_certificate(&identity_ca).is_ok());
+    assert!(empty_crl.check_not_revoked(&participant1).is_ok());
+    assert!(empty_crl.check_not_revoked(&participant2).is_ok());
+
+    let crl = read_fixture_crl("identity_ca_revoked_participant2_crl.pem");
+    assert!(crl.verify_signed_by_certificate(&identity_ca).is_ok());
+    assert!(crl.check_not_revoked(&participant1).is_ok());
+    assert!(crl.check_not_revoked(&participant2).is_err());
+  }
+
+  #[test]
+  pub fn validity_of_crl() {
+    let crl = read_fixture_crl("identity_ca_empty_crl.pem");
+    assert!(crl.check_validity(Duration::ZERO).is_ok());
+
+    let next_update = crl.next_update().expect("Fixture CRL has no nextUpdate");
+    let expired = next_update + Duration::from_secs(30);
+    assert!(crl.check_validity_at(expired, Duration::ZERO).is_err());
+    assert!(crl
+      .check_validity_at(expired, Duration::from_secs(60))
+      .is_ok());
+
+    let not_yet_valid = crl.this_update() - Duration::from_secs(30);
+    assert!(crl
+      .check_validity_at(not_yet_valid, Duration::ZERO)
+      .is_err());
+    assert!(crl
+      .check_validity_at(not_yet_valid, Duration::from_secs(60))
+      .is_ok());
+  }
+
+  #[test]
+  pub fn crl_from_wrong_issuer_is_rejected() {
+    let permissions_ca = read_fixture_certificate("permissions_ca_certificate.pem");
+    let crl = read_fixture_crl("identity_ca_revoked_participant2_crl.pem");
+    assert!(crl.verify_signed_by_certificate(&permissions_ca).is_err());
+  }
 }
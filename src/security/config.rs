@@ -1,3 +1,5 @@
+use std::path::Path;
+
 use crate::{qos, security};
 
 // Temporary module for determining security configurations for development
@@ -69,6 +71,56 @@ pub fn test_config() -> SecurityConfig {
   }
 }
 
+// Security configuration from user-supplied files. The files are checked to be
+// readable here, so that a missing file is reported by name before the
+// plugins try to parse them.
+pub(crate) struct SecurityConfigFiles<'a> {
+  pub identity_ca: &'a Path,
+  pub identity_certificate: &'a Path,
+  pub private_key: &'a Path,
+  pub permissions_ca: &'a Path,
+  pub governance: &'a Path,
+  pub permissions: &'a Path,
+}
+
+pub(crate) fn config_from_files(
+  files: &SecurityConfigFiles,
+) -> Result<SecurityConfig, ConfigError> {
+  let file_property = |name: &str, path: &Path| -> Result<security::types::Property, ConfigError> {
+    let path_str = path
+      .to_str()
+      .ok_or_else(|| parse_config_error(format!("{name}: path {path:?} is not valid UTF-8")))?;
+    let uri = format!("file:{path_str}");
+    read_uri(&uri).map_err(|e| other_config_error(format!("{name}: {e:?}")))?;
+    Ok(security::types::Property {
+      name: name.to_string(),
+      value: uri,
+      propagate: false,
+    })
+  };
+
+  let properties = vec![
+    // For the authentication plugin
+    file_property("dds.sec.auth.identity_ca", files.identity_ca)?,
+    file_property(
+      "dds.sec.auth.identity_certificate",
+      files.identity_certificate,
+    )?,
+    file_property("dds.sec.auth.private_key", files.private_key)?,
+    // For the access control plugin
+    file_property("dds.sec.access.permissions_ca", files.permissions_ca)?,
+    file_property("dds.sec.access.governance", files.governance)?,
+    file_property("dds.sec.access.permissions", files.permissions)?,
+  ];
+  Ok(SecurityConfig {
+    security_enabled: true,
+    properties: qos::policy::Property {
+      value: properties,
+      binary_value: vec![],
+    },
+  })
+}
+
 use bytes::Bytes;
 
 pub(crate) fn read_uri(uri: &str) -> Result<Bytes, ConfigError> {
@@ -12,7 +12,12 @@ use std::collections::{HashMap, HashSet};
 
 use crate::{
   security::{
-    access_control::types::*,
+    access_control::{
+      access_control_builtin::types::{
+        BuiltinPluginEndpointSecurityAttributes, BuiltinPluginParticipantSecurityAttributes,
+      },
+      types::*,
+    },
     authentication::types::*,
     cryptographic::{cryptographic_builtin::types::*, cryptographic_plugin::*, types::*},
     types::*,
@@ -78,6 +83,11 @@ pub struct CryptographicBuiltin {
   matched_local_endpoint: HashMap<EndpointCryptoHandle, EndpointCryptoHandle>,
 
   crypto_handle_counter: u32,
+
+  // Set by the "dds.sec.crypto.require_aes256" property of the local participant.
+  // Forbids the AES-128 transformation kinds both locally and from remote
+  // entities.
+  require_aes256: bool,
 }
 
 // Combine the trait implementations from the submodules
@@ -96,6 +106,7 @@ impl CryptographicBuiltin {
       matched_remote_endpoint: HashMap::new(),
       matched_local_endpoint: HashMap::new(),
       crypto_handle_counter: 0,
+      require_aes256: false,
     }
   }
 
@@ -381,6 +392,14 @@ impl CryptographicBuiltin {
             .and_then(|receiver_key_material| {
               receiver_key_material.receiver_key_material_for(common_encode_key_material)
             })
+        })
+        // Receivers without origin authentication have no receiver-specific key
+        .filter(|rec_spec_key_material| match rec_spec_key_material {
+          Ok(ReceiverSpecificKeyMaterial { key_id, .. }) => !key_id.is_zero(),
+          Err(_) => true,
+        })
+        .map(|rec_spec_key_material| {
+          rec_spec_key_material
             // Map to session keys
             .map(|rec_spec_key_material| {
               let session_key = Self::compute_session_key(
@@ -406,6 +425,60 @@ impl CryptographicBuiltin {
     })
   }
 
+  // Rejects transformations that the local configuration does not allow, so
+  // that a remote entity cannot downgrade the protection.
+  fn check_transformation_kind_allowed(
+    &self,
+    transformation_kind: BuiltinCryptoTransformationKind,
+  ) -> SecurityResult<()> {
+    match transformation_kind {
+      BuiltinCryptoTransformationKind::CRYPTO_TRANSFORMATION_KIND_AES128_GMAC
+      | BuiltinCryptoTransformationKind::CRYPTO_TRANSFORMATION_KIND_AES128_GCM
+        if self.require_aes256 =>
+      {
+        Err(security_error!(
+          "Transformation kind {:?} is not allowed, because AES-256 is required",
+          transformation_kind
+        ))
+      }
+      _ => Ok(()),
+    }
+  }
+
+  // Origin authentication is a property of the local receiver. When it is
+  // set, every remote sender must include a receiver-specific MAC for it.
+  // Participant-level (RTPS message) protection and submessage protection
+  // are configured separately.
+  fn requires_rtps_origin_authentication(
+    &self,
+    local_participant_crypto_handle: ParticipantCryptoHandle,
+  ) -> bool {
+    self
+      .participant_encrypt_options
+      .get(&local_participant_crypto_handle)
+      .and_then(|attributes| {
+        BuiltinPluginParticipantSecurityAttributes::try_from(
+          attributes.plugin_participant_attributes,
+        )
+        .ok()
+      })
+      .is_some_and(|attributes| attributes.is_rtps_origin_authenticated)
+  }
+
+  fn requires_submessage_origin_authentication(
+    &self,
+    local_endpoint_crypto_handle: EndpointCryptoHandle,
+  ) -> bool {
+    self
+      .endpoint_encrypt_options
+      .get(&local_endpoint_crypto_handle)
+      .and_then(|attributes| {
+        BuiltinPluginEndpointSecurityAttributes::try_from(attributes.plugin_endpoint_attributes)
+          .ok()
+      })
+      .is_some_and(|attributes| attributes.is_submessage_origin_authenticated)
+  }
+
   // Get materials needed for encrypting
   fn session_decode_crypto_materials(
     &self,
@@ -426,6 +499,7 @@ impl CryptographicBuiltin {
       .select(key_material_scope);
 
     let transformation_kind = *transformation_kind;
+    self.check_transformation_kind_allowed(transformation_kind)?;
     let session_key = Self::compute_session_key(
       ReceiverSpecific::No,
       master_sender_key,
@@ -476,3 +550,461 @@ struct DecryptSessionMaterials {
   receiver_specific_key: Option<ReceiverSpecificKeyMaterial>,
   // Either we have receiver specific key material specific to us or not.
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn shared_secret() -> SharedSecretHandle {
+    SharedSecretHandle {
+      shared_secret: SharedSecret::from([7; 32]),
+      challenge1: Challenge::from([1; 32]),
+      challenge2: Challenge::from([2; 32]),
+    }
+  }
+
+  fn protected_endpoint() -> EndpointSecurityAttributes {
+    EndpointSecurityAttributes {
+      is_submessage_protected: true,
+      is_payload_protected: true,
+      ..EndpointSecurityAttributes::empty()
+    }
+  }
+
+  fn has_crypto_state(crypto: &CryptographicBuiltin) -> bool {
+    !crypto.common_encode_key_materials.is_empty()
+      || !crypto.receiver_specific_encode_key_materials.is_empty()
+      || !crypto.decode_key_materials.is_empty()
+      || !crypto.participant_encrypt_options.is_empty()
+      || !crypto.endpoint_encrypt_options.is_empty()
+      || !crypto.participant_to_endpoint_info.is_empty()
+      || !crypto.endpoint_to_participant.is_empty()
+      || !crypto.matched_remote_endpoint.is_empty()
+      || !crypto.matched_local_endpoint.is_empty()
+  }
+
+  #[test]
+  fn unregistering_participants_clears_key_material() {
+    let mut crypto = CryptographicBuiltin::new();
+
+    let local_participant = crypto
+      .register_local_participant(1, 1, &[], ParticipantSecurityAttributes::empty())
+      .unwrap();
+    let remote_participant = crypto
+      .register_matched_remote_participant(local_participant, 2, 2, shared_secret())
+      .unwrap();
+
+    let local_writer = crypto
+      .register_local_datawriter(local_participant, &[], protected_endpoint())
+      .unwrap();
+    let remote_reader = crypto
+      .register_matched_remote_datareader(local_writer, remote_participant, shared_secret(), false)
+      .unwrap();
+    let local_reader = crypto
+      .register_local_datareader(local_participant, &[], protected_endpoint())
+      .unwrap();
+    crypto
+      .register_matched_remote_datawriter(local_reader, remote_participant, shared_secret())
+      .unwrap();
+
+    assert!(has_crypto_state(&crypto));
+
+    crypto.unregister_participant(remote_participant).unwrap();
+    crypto.unregister_participant(local_participant).unwrap();
+
+    assert!(!has_crypto_state(&crypto));
+  }
+
+  #[test]
+  fn require_aes256_rejects_aes128_peer() {
+    use crate::security::access_control::access_control_builtin::types::BuiltinPluginParticipantSecurityAttributes;
+
+    let encrypted_rtps = || ParticipantSecurityAttributes {
+      is_rtps_protected: true,
+      plugin_participant_attributes: BuiltinPluginParticipantSecurityAttributes {
+        is_rtps_encrypted: true,
+        is_discovery_encrypted: false,
+        is_liveliness_encrypted: false,
+        is_rtps_origin_authenticated: true,
+        is_discovery_origin_authenticated: false,
+        is_liveliness_origin_authenticated: false,
+      }
+      .into(),
+      ..ParticipantSecurityAttributes::empty()
+    };
+    let aes128 = Property::local("dds.sec.crypto.keysize", "128");
+    let require_aes256 = Property::local("dds.sec.crypto.require_aes256", "true");
+
+    // The peer offers AES-128 GCM key material
+    let mut peer = CryptographicBuiltin::new();
+    let peer_local = peer
+      .register_local_participant(1, 1, &[aes128.clone()], encrypted_rtps())
+      .unwrap();
+    let peer_remote = peer
+      .register_matched_remote_participant(peer_local, 2, 2, shared_secret())
+      .unwrap();
+    let peer_tokens = peer
+      .create_local_participant_crypto_tokens(peer_local, peer_remote)
+      .unwrap();
+
+    for required in [false, true] {
+      let mut properties = vec![aes128.clone()];
+      if required {
+        properties.push(require_aes256.clone());
+      }
+      let mut crypto = CryptographicBuiltin::new();
+      let local_participant = crypto
+        .register_local_participant(1, 1, &properties, encrypted_rtps())
+        .unwrap();
+      let remote_participant = crypto
+        .register_matched_remote_participant(local_participant, 2, 2, shared_secret())
+        .unwrap();
+      crypto
+        .set_remote_participant_crypto_tokens(
+          local_participant,
+          remote_participant,
+          peer_tokens.clone(),
+        )
+        .unwrap();
+
+      let decode_result = crypto.session_decode_crypto_materials(
+        remote_participant,
+        CryptoTransformKeyId::ZERO,
+        KeyMaterialScope::PayloadAndMetadata,
+        crypto.random_initialization_vector(),
+      );
+      assert_eq!(decode_result.is_err(), required);
+
+      // Locally, the key size property is overridden
+      let local_transformation_kind = match crypto
+        .get_common_encode_key_materials(&local_participant)
+        .unwrap()
+      {
+        CommonEncodeKeyMaterials::Some(key_materials) => {
+          key_materials.key_material().transformation_kind
+        }
+        CommonEncodeKeyMaterials::Volatile(_) => panic!("Participant key material is volatile"),
+      };
+      assert_eq!(
+        local_transformation_kind,
+        if required {
+          BuiltinCryptoTransformationKind::CRYPTO_TRANSFORMATION_KIND_AES256_GCM
+        } else {
+          BuiltinCryptoTransformationKind::CRYPTO_TRANSFORMATION_KIND_AES128_GCM
+        }
+      );
+    }
+  }
+
+  #[test]
+  fn origin_authentication_requires_receiver_specific_mac() {
+    use enumflags2::BitFlags;
+
+    use crate::{
+      messages::submessages::{
+        secure_postfix::SecurePostfix, submessage::SecuritySubmessage, submessages::Heartbeat,
+      },
+      rtps::{Submessage, SubmessageBody},
+      security::access_control::access_control_builtin::types::BuiltinPluginEndpointSecurityAttributes,
+      structure::{guid::EntityId, sequence_number::SequenceNumber},
+    };
+
+    let endpoint = |origin_authenticated: bool| EndpointSecurityAttributes {
+      is_submessage_protected: true,
+      plugin_endpoint_attributes: BuiltinPluginEndpointSecurityAttributes {
+        is_submessage_encrypted: true,
+        is_submessage_origin_authenticated: origin_authenticated,
+        is_payload_encrypted: false,
+      }
+      .into(),
+      ..EndpointSecurityAttributes::empty()
+    };
+
+    // Returns the encoded heartbeat and the decoding side with the handles
+    // needed for decoding
+    let encode = |writer_origin_auth: bool, reader_origin_auth: bool| {
+      let mut writer_side = CryptographicBuiltin::new();
+      let participant = writer_side
+        .register_local_participant(1, 1, &[], ParticipantSecurityAttributes::empty())
+        .unwrap();
+      let remote_participant = writer_side
+        .register_matched_remote_participant(participant, 2, 2, shared_secret())
+        .unwrap();
+      let writer = writer_side
+        .register_local_datawriter(participant, &[], endpoint(writer_origin_auth))
+        .unwrap();
+      let remote_reader = writer_side
+        .register_matched_remote_datareader(writer, remote_participant, shared_secret(), false)
+        .unwrap();
+      let writer_tokens = writer_side
+        .create_local_datawriter_crypto_tokens(writer, remote_reader)
+        .unwrap();
+
+      let mut reader_side = CryptographicBuiltin::new();
+      let participant = reader_side
+        .register_local_participant(2, 2, &[], ParticipantSecurityAttributes::empty())
+        .unwrap();
+      let remote_participant = reader_side
+        .register_matched_remote_participant(participant, 1, 1, shared_secret())
+        .unwrap();
+      let reader = reader_side
+        .register_local_datareader(participant, &[], endpoint(reader_origin_auth))
+        .unwrap();
+      let remote_writer = reader_side
+        .register_matched_remote_datawriter(reader, remote_participant, shared_secret())
+        .unwrap();
+      reader_side
+        .set_remote_datawriter_crypto_tokens(reader, remote_writer, writer_tokens)
+        .unwrap();
+
+      let heartbeat = Heartbeat {
+        reader_id: EntityId::UNKNOWN,
+        writer_id: EntityId::UNKNOWN,
+        first_sn: SequenceNumber::new(1),
+        last_sn: SequenceNumber::new(1),
+        count: 1,
+      }
+      .create_submessage(BitFlags::empty())
+      .unwrap();
+      let encoded = match writer_side
+        .encode_datawriter_submessage(heartbeat, writer, vec![remote_reader])
+        .unwrap()
+      {
+        EncodedSubmessage::Encoded(
+          Submessage {
+            body: SubmessageBody::Security(SecuritySubmessage::SecurePrefix(prefix, _)),
+            ..
+          },
+          body,
+          Submessage {
+            body: SubmessageBody::Security(SecuritySubmessage::SecurePostfix(postfix, _)),
+            ..
+          },
+        ) => (prefix, body, postfix),
+        _ => panic!("Heartbeat was not encoded"),
+      };
+      (reader_side, reader, remote_writer, encoded)
+    };
+
+    // The writer computes a receiver-specific MAC for the reader
+    let (reader_side, reader, remote_writer, (prefix, body, postfix)) = encode(true, true);
+    reader_side
+      .decode_datawriter_submessage(
+        (prefix.clone(), body.clone(), postfix.clone()),
+        reader,
+        remote_writer,
+      )
+      .unwrap();
+
+    // Strip the receiver-specific MAC
+    let mut footer = BuiltinCryptoFooter::try_from(postfix.crypto_footer).unwrap();
+    assert!(!footer.receiver_specific_macs.is_empty());
+    footer.receiver_specific_macs.clear();
+    let stripped_postfix = SecurePostfix {
+      crypto_footer: footer.try_into().unwrap(),
+    };
+    assert!(reader_side
+      .decode_datawriter_submessage((prefix, body, stripped_postfix), reader, remote_writer)
+      .is_err());
+
+    // A writer without origin authentication does not give the reader a
+    // receiver-specific key, which is accepted only if the reader does not
+    // require origin authentication.
+    for reader_origin_auth in [false, true] {
+      let (reader_side, reader, remote_writer, encoded) = encode(false, reader_origin_auth);
+      assert_eq!(
+        reader_side
+          .decode_datawriter_submessage(encoded, reader, remote_writer)
+          .is_err(),
+        reader_origin_auth
+      );
+    }
+  }
+
+  #[test]
+  fn interpreter_submessages_are_protected() {
+    use crate::{
+      messages::submessages::submessage::SecuritySubmessage,
+      rtps::{message::MessageBuilder, Submessage, SubmessageBody},
+      security::access_control::access_control_builtin::types::BuiltinPluginEndpointSecurityAttributes,
+      structure::{guid::GuidPrefix, time::Timestamp},
+    };
+
+    let endpoint = EndpointSecurityAttributes {
+      is_submessage_protected: true,
+      plugin_endpoint_attributes: BuiltinPluginEndpointSecurityAttributes {
+        is_submessage_encrypted: true,
+        is_submessage_origin_authenticated: false,
+        is_payload_encrypted: false,
+      }
+      .into(),
+      ..EndpointSecurityAttributes::empty()
+    };
+
+    let mut writer_side = CryptographicBuiltin::new();
+    let participant = writer_side
+      .register_local_participant(1, 1, &[], ParticipantSecurityAttributes::empty())
+      .unwrap();
+    let remote_participant = writer_side
+      .register_matched_remote_participant(participant, 2, 2, shared_secret())
+      .unwrap();
+    let writer = writer_side
+      .register_local_datawriter(participant, &[], endpoint.clone())
+      .unwrap();
+    let remote_reader = writer_side
+      .register_matched_remote_datareader(writer, remote_participant, shared_secret(), false)
+      .unwrap();
+    let writer_tokens = writer_side
+      .create_local_datawriter_crypto_tokens(writer, remote_reader)
+      .unwrap();
+
+    let mut reader_side = CryptographicBuiltin::new();
+    let participant = reader_side
+      .register_local_participant(2, 2, &[], ParticipantSecurityAttributes::empty())
+      .unwrap();
+    let remote_participant = reader_side
+      .register_matched_remote_participant(participant, 1, 1, shared_secret())
+      .unwrap();
+    let reader = reader_side
+      .register_local_datareader(participant, &[], endpoint)
+      .unwrap();
+    let remote_writer = reader_side
+      .register_matched_remote_datawriter(reader, remote_participant, shared_secret())
+      .unwrap();
+    reader_side
+      .set_remote_datawriter_crypto_tokens(reader, remote_writer, writer_tokens)
+      .unwrap();
+
+    // The INFO_TS sent before a DATA is protected like the DATA itself
+    let info_ts = MessageBuilder::new()
+      .ts_msg(speedy::Endianness::LittleEndian, Some(Timestamp::now()))
+      .add_header_and_build(GuidPrefix::UNKNOWN)
+      .submessages
+      .remove(0);
+    let encoded = match writer_side
+      .encode_datawriter_submessage(info_ts.clone(), writer, vec![remote_reader])
+      .unwrap()
+    {
+      EncodedSubmessage::Encoded(
+        Submessage {
+          body: SubmessageBody::Security(SecuritySubmessage::SecurePrefix(prefix, _)),
+          ..
+        },
+        body,
+        Submessage {
+          body: SubmessageBody::Security(SecuritySubmessage::SecurePostfix(postfix, _)),
+          ..
+        },
+      ) => (prefix, body, postfix),
+      _ => panic!("INFO_TS was not encoded"),
+    };
+    assert_eq!(
+      reader_side
+        .decode_datawriter_submessage(encoded, reader, remote_writer)
+        .unwrap(),
+      info_ts.body
+    );
+  }
+
+  #[test]
+  fn rtps_message_mac_validates_without_original_bytes() {
+    use bytes::Bytes;
+    use enumflags2::BitFlags;
+    use speedy::Writable;
+
+    use crate::{
+      messages::{
+        header::Header,
+        submessages::{submessage_flag::FromEndianness, submessages::Heartbeat},
+      },
+      rtps::Message,
+      security::access_control::access_control_builtin::types::BuiltinPluginParticipantSecurityAttributes,
+      structure::{
+        guid::{EntityId, GuidPrefix},
+        sequence_number::SequenceNumber,
+      },
+    };
+
+    // RTPS messages are signed (GMAC), but not encrypted
+    let signed_rtps = || ParticipantSecurityAttributes {
+      is_rtps_protected: true,
+      plugin_participant_attributes: BuiltinPluginParticipantSecurityAttributes {
+        is_rtps_encrypted: false,
+        is_discovery_encrypted: false,
+        is_liveliness_encrypted: false,
+        is_rtps_origin_authenticated: false,
+        is_discovery_origin_authenticated: false,
+        is_liveliness_origin_authenticated: false,
+      }
+      .into(),
+      ..ParticipantSecurityAttributes::empty()
+    };
+
+    let mut sender = CryptographicBuiltin::new();
+    let sender_local = sender
+      .register_local_participant(1, 1, &[], signed_rtps())
+      .unwrap();
+    let sender_remote = sender
+      .register_matched_remote_participant(sender_local, 2, 2, shared_secret())
+      .unwrap();
+    let sender_tokens = sender
+      .create_local_participant_crypto_tokens(sender_local, sender_remote)
+      .unwrap();
+
+    let mut receiver = CryptographicBuiltin::new();
+    let receiver_local = receiver
+      .register_local_participant(2, 2, &[], signed_rtps())
+      .unwrap();
+    let receiver_remote = receiver
+      .register_matched_remote_participant(receiver_local, 1, 1, shared_secret())
+      .unwrap();
+    receiver
+      .set_remote_participant_crypto_tokens(receiver_local, receiver_remote, sender_tokens)
+      .unwrap();
+
+    let heartbeat = Heartbeat {
+      reader_id: EntityId::UNKNOWN,
+      writer_id: EntityId::UNKNOWN,
+      first_sn: SequenceNumber::new(1),
+      last_sn: SequenceNumber::new(1),
+      count: 1,
+    }
+    .create_submessage(BitFlags::from_endianness(speedy::Endianness::LittleEndian))
+    .unwrap();
+    let message = Message {
+      header: Header::new(GuidPrefix::new(&[1; 12])),
+      submessages: vec![heartbeat],
+    };
+    let encoded = sender
+      .encode_rtps_message(message, sender_local, vec![sender_remote])
+      .unwrap();
+
+    // The encoded message is constructed locally, so none of its submessages
+    // have original bytes.
+    assert!(encoded
+      .submessages
+      .iter()
+      .all(|s| s.original_bytes.is_none()));
+    let decoded = receiver
+      .decode_rtps_message(encoded.clone(), receiver_local, receiver_remote)
+      .unwrap();
+    assert_eq!(decoded.submessages.len(), 1);
+
+    // As received from the network, all submessages have original bytes
+    let mut received =
+      Message::read_from_buffer(&Bytes::from(encoded.write_to_vec().unwrap())).unwrap();
+    assert!(received
+      .submessages
+      .iter()
+      .all(|s| s.original_bytes.is_some()));
+    receiver
+      .decode_rtps_message(received.clone(), receiver_local, receiver_remote)
+      .unwrap();
+
+    // Mixed: the heartbeat has lost its original bytes
+    received.submessages[2].original_bytes = None;
+    receiver
+      .decode_rtps_message(received, receiver_local, receiver_remote)
+      .unwrap();
+  }
+}
@@ -66,25 +66,21 @@ fn to_builtin_mac(tag: &Tag) -> BuiltinMAC {
 // We DO have to assume the ciphertext may be longer than the plaintext.
 
 // Computes the message authentication code (MAC) for the given data
+//
+// This is AES-GMAC, i.e. AES-GCM where the data is the Additional
+// Authenticated Data and the plaintext is empty. Then the tag authenticates
+// the data as is, without encrypting it.
 pub(super) fn compute_mac(
   key: &BuiltinKey,
   initialization_vector: BuiltinInitializationVector,
   data: &[u8],
 ) -> SecurityResult<BuiltinMAC> {
-  // ring encrypts + tags (signs) in place, so we must create a buffer for that.
-  let mut in_out_data = Vec::from(data);
-
-  // Section "9.5.3.3.4.4 Result from encode_serialized_payload" :
-  //
-  // "The common_mac in the CryptoFooter is the authentication tag generated
-  // by the same AES-GCM where the Additional Authenticated Data is empty."
-
   let mut sealing_key = SealingKey::new(
     to_unbound_AES_GCM_key(key)?,
     TrivialNonceSequence::new(initialization_vector),
   );
 
-  let tag = sealing_key.seal_in_place_separate_tag(Aad::empty(), &mut in_out_data)?;
+  let tag = sealing_key.seal_in_place_separate_tag(Aad::from(data), &mut [])?;
 
   Ok(to_builtin_mac(&tag))
 }
@@ -116,9 +112,8 @@ pub(super) fn validate_mac(
   data: &[u8],
   mac: BuiltinMAC,
 ) -> SecurityResult<()> {
-  let mut in_out = Vec::with_capacity(data.len() + MAC_LENGTH);
-  in_out.extend_from_slice(data);
-  in_out.extend_from_slice(&mac);
+  // The "ciphertext" is empty, so only the tag is opened.
+  let mut in_out = Vec::from(mac.as_ref());
 
   let mut opening_key = OpeningKey::new(
     to_unbound_AES_GCM_key(key)?,
@@ -126,7 +121,7 @@ pub(super) fn validate_mac(
   );
 
   // This will return `Err(..)` if verification fails
-  let plaintext = opening_key.open_in_place(Aad::empty(), &mut in_out)?;
+  opening_key.open_in_place(Aad::from(data), &mut in_out)?;
   // If we get here, the mac ("tag") was valid.
   Ok(())
 }
@@ -1,3 +1,5 @@
+use zeroize::Zeroize;
+
 use crate::security::{security_error, SecurityResult};
 use super::types::BuiltinCryptoTransformationKind;
 
@@ -64,6 +66,18 @@ impl BuiltinKey {
   }
 }
 
+// Overwrite the key bytes when the key is dropped, so that no key material
+// lingers in freed memory.
+impl Drop for BuiltinKey {
+  fn drop(&mut self) {
+    match self {
+      BuiltinKey::None => {}
+      BuiltinKey::AES128(d) => d.zeroize(),
+      BuiltinKey::AES256(d) => d.zeroize(),
+    }
+  }
+}
+
 pub(super) const AES128_KEY_LENGTH: usize = 16;
 pub(super) type AES128Key = [u8; AES128_KEY_LENGTH];
 
@@ -154,11 +154,22 @@ impl CryptographicBuiltin {
     BuiltinKey::from_bytes(KeyLength::AES256, hashed_secret.as_ref()).unwrap()
   }
 
-  fn use_256_bit_key(properties: &[Property]) -> bool {
+  fn use_256_bit_key(&self, properties: &[Property]) -> bool {
+    self.require_aes256
+      || properties
+        .iter()
+        .find(|property| property.name.eq("dds.sec.crypto.keysize"))
+        .map_or(true, |property| !property.value.eq("128"))
+  }
+
+  // With this participant property set to "true", only 256-bit
+  // transformations are used locally, and key material from remote
+  // participants must be 256-bit, too.
+  fn require_aes256(properties: &[Property]) -> bool {
     properties
       .iter()
-      .find(|property| property.name.eq("dds.sec.crypto.keysize"))
-      .map_or(true, |property| !property.value.eq("128"))
+      .find(|property| property.name.eq("dds.sec.crypto.require_aes256"))
+      .is_some_and(|property| property.value.trim().eq_ignore_ascii_case("true"))
   }
 
   fn transformation_kind(
@@ -214,7 +225,9 @@ impl CryptographicBuiltin {
     if origin_authentication {
       let master_receiver_specific_key =
         keygen(key_materials.key_material().transformation_kind.into());
-      let key_id = key_materials.key_material().sender_key_id; // TODO: Is this correct?
+      // The id must be nonzero, since zero means that there is no
+      // receiver-specific key. The handle of the receiver is unique.
+      let key_id = CryptoTransformKeyId::from(crypto_handle.to_be_bytes());
       key_materials.add_master_receiver_specific_key(key_id, master_receiver_specific_key)
     } else {
       key_materials.add_master_receiver_specific_key(CryptoTransformKeyId::ZERO, BuiltinKey::None)
@@ -287,13 +300,14 @@ impl CryptoKeyFactory for CryptographicBuiltin {
         participant_security_attributes.plugin_participant_attributes,
       )?;
     let crypto_handle = self.generate_crypto_handle();
+    self.require_aes256 = Self::require_aes256(participant_properties);
 
     let key_material = Self::generate_key_material(
       crypto_handle,
       Self::transformation_kind(
         participant_security_attributes.is_rtps_protected,
         plugin_participant_security_attributes.is_rtps_encrypted,
-        Self::use_256_bit_key(participant_properties),
+        self.use_256_bit_key(participant_properties),
       ),
     );
     self
@@ -375,7 +389,7 @@ impl CryptoKeyFactory for CryptographicBuiltin {
 
     let local_datawriter_crypto_handle = self.generate_crypto_handle();
 
-    let use_256_bit_key = Self::use_256_bit_key(datawriter_properties);
+    let use_256_bit_key = self.use_256_bit_key(datawriter_properties);
 
     // The key material for volatile datawriter is derived from the shared secret in
     // register_matched_remote_datareader
@@ -537,7 +551,7 @@ impl CryptoKeyFactory for CryptographicBuiltin {
 
     let local_datareader_crypto_handle = self.generate_crypto_handle();
 
-    let use_256_bit_key = Self::use_256_bit_key(datareader_properties);
+    let use_256_bit_key = self.use_256_bit_key(datareader_properties);
     // The key material for volatile datareader is derived from the shared secret in
     // register_matched_remote_datawriter
     if Self::is_volatile(datareader_properties) {
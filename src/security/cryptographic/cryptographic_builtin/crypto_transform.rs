@@ -17,7 +17,7 @@ use crate::{
     secure_rtps_prefix::SecureRTPSPrefix,
     submessage::SecuritySubmessage,
     submessage_flag::FromEndianness,
-    submessages::{InterpreterSubmessage, ReaderSubmessage, WriterSubmessage},
+    submessages::InterpreterSubmessage,
   },
   rtps::{Message, Submessage, SubmessageBody},
   security::cryptographic::cryptographic_builtin::*,
@@ -158,6 +158,7 @@ impl CryptographicBuiltin {
     let receiver_specific_key_and_mac = find_receiver_specific_mac(
       decode_key_material.receiver_specific_key,
       &receiver_specific_macs,
+      self.requires_submessage_origin_authentication(receiving_endpoint_crypto_handle),
     )?;
 
     let decode_key = decode_key_material.session_key;
@@ -369,7 +370,7 @@ impl CryptoTransform for CryptographicBuiltin {
     };
 
     // Build crypto header and security prefix
-    let prefix = SecurePrefix {
+    let prefix = SecureRTPSPrefix {
       crypto_header: CryptoHeader::from(BuiltinCryptoHeader {
         transform_identifier: BuiltinCryptoTransformIdentifier {
           transformation_kind,
@@ -380,7 +381,7 @@ impl CryptoTransform for CryptographicBuiltin {
     };
 
     // Build security postfix
-    let postfix = SecurePostfix {
+    let postfix = SecureRTPSPostfix {
       crypto_footer: CryptoFooter::try_from(crypto_footer)?,
     };
 
@@ -402,7 +403,7 @@ impl CryptoTransform for CryptographicBuiltin {
       header: rtps_header,
       submessages,
     }: Message,
-    _receiving_participant_crypto_handle: ParticipantCryptoHandle,
+    receiving_participant_crypto_handle: ParticipantCryptoHandle,
     sending_participant_crypto_handle: ParticipantCryptoHandle,
   ) -> SecurityResult<Message> {
     // we expect SecureRTPSPRefix + some submessages + SecureRTPSPostfix
@@ -460,6 +461,7 @@ impl CryptoTransform for CryptographicBuiltin {
       let receiver_specific_key_and_mac = find_receiver_specific_mac(
         decode_key_material.receiver_specific_key,
         &receiver_specific_macs,
+        self.requires_rtps_origin_authentication(receiving_participant_crypto_handle),
       )?;
 
       let decode_key = decode_key_material.session_key;
@@ -484,10 +486,17 @@ impl CryptoTransform for CryptographicBuiltin {
             ] = submessages_with_info_source
           {
             // Get original serialized data for submessage sequence: Concatenate original_bytes.
-            let serialized_submessages = submessages_with_info_source.iter()
-              .fold(Vec::<u8>::with_capacity(512), move |mut a,s| {
-                a.extend_from_slice(s.original_bytes.as_ref().unwrap_or(&Bytes::new()).as_ref()); a }
-              );
+            // A submessage that was not parsed from bytes has none, so serialize it again, like
+            // encode_rtps_message does.
+            let serialized_submessages = SecurityResult::<Vec<Vec<u8>>>::from_iter(
+              submessages_with_info_source.iter()
+                .map(|s| match &s.original_bytes {
+                  Some(bytes) => Ok(bytes.to_vec()),
+                  None => s.write_to_vec()
+                    .map_err(|err| security_error!("Error converting Submessage to byte vector: {}", err)),
+                })
+              )?
+              .concat();
             // Validate the common MAC
             aes_gcm_gmac::validate_mac(&decode_key, initialization_vector, &serialized_submessages, common_mac)
               // Validate the receiver-specific MAC if one exists
@@ -589,6 +598,7 @@ impl CryptoTransform for CryptographicBuiltin {
     // Check the validity of transformation_kind
     let submessage_transformation_kind =
       BuiltinCryptoTransformationKind::try_from(transformation_kind)?;
+    self.check_transformation_kind_allowed(submessage_transformation_kind)?;
 
     // Search for matching key materials over endpoints registered to the sender
     let sending_participant_endpoints = self
@@ -654,14 +664,10 @@ impl CryptoTransform for CryptographicBuiltin {
          sending_participant_crypto_handle {}.",
         sending_participant_crypto_handle
       )),
-      (false, false) => {
-        //
-        Err(security_error!(
-          "Matching key materials found for both registered datawriters and datareaders for the \
-           sending_participant_crypto_handle {}.",
-          sending_participant_crypto_handle
-        ))
-      }
+      (false, false) => Ok(SecureSubmessageCategory::DatawriterOrDatareaderSubmessage(
+        datawriter_submessage_handle_pairs,
+        datareader_submessage_handle_pairs,
+      )),
       (false, true) => Ok(SecureSubmessageCategory::DatawriterSubmessage(
         datawriter_submessage_handle_pairs,
       )),
@@ -676,20 +682,12 @@ impl CryptoTransform for CryptographicBuiltin {
     encoded_rtps_submessage: (SecurePrefix, Submessage, SecurePostfix),
     receiving_datareader_crypto_handle: DatawriterCryptoHandle,
     sending_datawriter_crypto_handle: DatareaderCryptoHandle,
-  ) -> SecurityResult<WriterSubmessage> {
-    match self.decode_submessage(
+  ) -> SecurityResult<SubmessageBody> {
+    self.decode_submessage(
       encoded_rtps_submessage,
       receiving_datareader_crypto_handle,
       sending_datawriter_crypto_handle,
-    )? {
-      SubmessageBody::Writer(submessage) => Ok(submessage),
-      other => {
-        warn!("Expected WriterSubmessage, but decoded as {other:?}");
-        Err(security_error(
-          "decode_datawriter_submessage: Decode result was not WriterSubmessage",
-        ))
-      }
-    }
+    )
   }
 
   fn decode_datareader_submessage(
@@ -697,20 +695,12 @@ impl CryptoTransform for CryptographicBuiltin {
     encoded_rtps_submessage: (SecurePrefix, Submessage, SecurePostfix),
     receiving_datawriter_crypto_handle: DatawriterCryptoHandle,
     sending_datareader_crypto_handle: DatareaderCryptoHandle,
-  ) -> SecurityResult<ReaderSubmessage> {
-    match self.decode_submessage(
+  ) -> SecurityResult<SubmessageBody> {
+    self.decode_submessage(
       encoded_rtps_submessage,
       receiving_datawriter_crypto_handle,
       sending_datareader_crypto_handle,
-    )? {
-      SubmessageBody::Reader(submessage) => Ok(submessage),
-      other => {
-        warn!("Expected ReaderSubmessage, but decoded as {other:?}");
-        Err(security_error(
-          "decode_datareader_submessage: Decode result was not ReaderSubmessage",
-        ))
-      }
-    }
+    )
   }
 
   fn decode_serialized_payload(
@@ -19,7 +19,16 @@ use super::{
 pub(super) fn find_receiver_specific_mac(
   receiver_specific_key: Option<ReceiverSpecificKeyMaterial>,
   receiver_specific_macs: &[ReceiverSpecificMAC],
+  origin_authentication_required: bool,
 ) -> SecurityResult<Option<(BuiltinKey, BuiltinMAC)>> {
+  // If origin authentication is required, the sender must have given us a
+  // receiver-specific key. Otherwise any member of the group holding the
+  // common key could spoof messages from the sender.
+  if origin_authentication_required && receiver_specific_key.is_none() {
+    return Err(security_error!(
+      "Origin authentication is required, but the sender has no receiver-specific key for us"
+    ));
+  }
   // If the key is None, we are not expecting a receiver-specific MAC
   receiver_specific_key
     .map(|ReceiverSpecificKeyMaterial{ key_id, key }|
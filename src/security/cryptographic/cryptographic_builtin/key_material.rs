@@ -349,7 +349,12 @@ impl TryFrom<Serializable_KeyMaterial_AES_GCM_GMAC> for KeyMaterial_AES_GCM_GMAC
       sender_key_id,
       master_sender_key: BuiltinKey::from_bytes(key_len, &master_sender_key)?,
       receiver_specific_key_id,
-      master_receiver_specific_key: BuiltinKey::from_bytes(key_len, &master_receiver_specific_key)?,
+      // Without origin authentication there is no receiver-specific key
+      master_receiver_specific_key: if receiver_specific_key_id.is_zero() {
+        BuiltinKey::None
+      } else {
+        BuiltinKey::from_bytes(key_len, &master_receiver_specific_key)?
+      },
     })
   }
 }
@@ -1,18 +1,16 @@
 use crate::{
   messages::submessages::{
-    elements::parameter_list::ParameterList,
-    secure_postfix::SecurePostfix,
+    elements::parameter_list::ParameterList, secure_postfix::SecurePostfix,
     secure_prefix::SecurePrefix,
-    submessages::{ReaderSubmessage, WriterSubmessage},
   },
-  rtps::{Message, Submessage},
+  rtps::{Message, Submessage, SubmessageBody},
   security::{
     access_control::types::*, authentication::types::*, cryptographic::types::*, types::*,
   },
 };
 // Imports for doc references
 #[cfg(doc)]
-use crate::{messages::submessages::submessage::SecuritySubmessage, rtps::SubmessageBody};
+use crate::messages::submessages::submessage::SecuritySubmessage;
 
 /// CryptoKeyFactory: section 8.5.1.7 of the Security specification (v. 1.1)
 pub trait CryptoKeyFactory: Send {
@@ -272,26 +270,28 @@ pub trait CryptoTransform: Send {
   /// decode_datawriter_submessage: section 8.5.1.9.7 of the Security
   /// specification (v. 1.1)
   ///
-  /// Return the writer submessage that would be written in
-  /// `plain_rtps_submessage`.
+  /// Return the submessage that would be written in `plain_rtps_submessage`.
+  /// Besides Writer submessages, a DataWriter protects the Interpreter
+  /// submessages it sends, so the caller must check the kind.
   fn decode_datawriter_submessage(
     &self,
     encoded_rtps_submessage: (SecurePrefix, Submessage, SecurePostfix),
     receiving_datareader_crypto_handle: DatareaderCryptoHandle,
     sending_datawriter_crypto_handle: DatawriterCryptoHandle,
-  ) -> SecurityResult<WriterSubmessage>;
+  ) -> SecurityResult<SubmessageBody>;
 
   /// decode_datareader_submessage: section 8.5.1.9.8 of the Security
   /// specification (v. 1.1)
   ///
-  /// Return the reader submessage that would be written in
-  /// `plain_rtps_submessage`.
+  /// Return the submessage that would be written in `plain_rtps_submessage`.
+  /// Besides Reader submessages, a DataReader protects the Interpreter
+  /// submessages it sends, so the caller must check the kind.
   fn decode_datareader_submessage(
     &self,
     encoded_rtps_submessage: (SecurePrefix, Submessage, SecurePostfix),
     receiving_datawriter_crypto_handle: DatawriterCryptoHandle,
     sending_datareader_crypto_handle: DatareaderCryptoHandle,
-  ) -> SecurityResult<ReaderSubmessage>;
+  ) -> SecurityResult<SubmessageBody>;
 
   /// decode_serialized_payload: section 8.5.1.9.9 of the Security specification
   /// (v. 1.1)
@@ -92,6 +92,14 @@ pub enum SecureSubmessageCategory {
   InfoSubmessage,
   DatawriterSubmessage(Vec<(DatawriterCryptoHandle, DatareaderCryptoHandle)>),
   DatareaderSubmessage(Vec<(DatareaderCryptoHandle, DatawriterCryptoHandle)>),
+  // The matching key materials belong to both a DataWriter and a DataReader,
+  // so only decoding tells which one sent the submessage. This is the case with
+  // the volatile message endpoints, whose keys are derived from the shared
+  // secret.
+  DatawriterOrDatareaderSubmessage(
+    Vec<(DatawriterCryptoHandle, DatareaderCryptoHandle)>,
+    Vec<(DatareaderCryptoHandle, DatawriterCryptoHandle)>,
+  ),
 }
 
 /// [super::cryptographic_plugin::CryptoTransform::encode_datawriter_submessage]
@@ -1,11 +1,335 @@
-// A macro for logging of security events.
-// Currently just creates a normal info-level log entry.
-// However, this dedicated macro is intended as a reminder that security-related
-// logging should be handled with special care (by a security-logging-plugin in
-// the future?) So it acts as a placeholder for more to come.
+// Builtin Logging plugin
+//
+// DDS Security spec v1.1
+// Section "9.6 Builtin Logging Plugin"
+//
+// Security events are always written to the normal (log crate) log. In
+// addition, they are published on the builtin logging topic
+// "DDS:Security:LogTopic", so that a central monitor can subscribe to them
+// over DDS.
+
+use std::collections::BTreeMap;
+
+#[allow(unused_imports)]
+use log::{debug, error, info, trace, warn};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+  dds::{no_key, participant::DomainParticipantWeak, qos, CreateResult},
+  structure::{
+    entity::RTPSEntity,
+    guid::{EntityId, GuidPrefix},
+  },
+  QosPolicies, QosPolicyBuilder, Timestamp, TopicKind, GUID,
+};
+
+pub const BUILTIN_LOGGING_TOPIC_NAME: &str = "DDS:Security:LogTopic";
+pub const BUILTIN_LOGGING_TYPE_NAME: &str = "DDS:Security:BuiltinLoggingType";
+
+// Properties to configure the builtin logging plugin. The spec calls these
+// LogOptions.
+const QOS_LOG_LEVEL_PROPERTY_NAME: &str = "dds.sec.log.builtin.log_level";
+const QOS_LOG_DISTRIBUTE_PROPERTY_NAME: &str = "dds.sec.log.builtin.distribute";
+
+// Messages at this level and more severe are published by default.
+const DEFAULT_LOG_LEVEL: LoggingLevel = LoggingLevel::Notice;
+
+// History depth of the logging DataWriter. The writer is TransientLocal, so
+// a monitor that subscribes a bit late still gets the latest events.
+const LOG_HISTORY_DEPTH: i32 = 64;
+
+// Value of the facility field. 10 is "security/authorization messages" in
+// syslog terms.
+const LOG_FACILITY_SECURITY: u8 = 0x0A;
+
+// Structured data element id for the GUIDs involved in the event
+const STRUCTURED_DATA_DDS: &str = "DDS";
+
+/// Severity of a security log message.
+///
+/// Declared from most to least severe, as in the spec, so that the derived
+/// ordering compares "more severe" as smaller.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum LoggingLevel {
+  Emergency,
+  Alert,
+  Critical,
+  Error,
+  Warning,
+  Notice,
+  Informational,
+  Debug,
+}
+
+impl LoggingLevel {
+  fn parse(s: &str) -> Option<Self> {
+    match s.trim().to_ascii_uppercase().trim_end_matches("_LEVEL") {
+      "EMERGENCY" | "0" => Some(Self::Emergency),
+      "ALERT" | "1" => Some(Self::Alert),
+      "CRITICAL" | "2" => Some(Self::Critical),
+      "ERROR" | "3" => Some(Self::Error),
+      "WARNING" | "4" => Some(Self::Warning),
+      "NOTICE" | "5" => Some(Self::Notice),
+      "INFORMATIONAL" | "INFO" | "6" => Some(Self::Informational),
+      "DEBUG" | "7" => Some(Self::Debug),
+      _ => None,
+    }
+  }
+}
+
+impl From<LoggingLevel> for log::Level {
+  fn from(level: LoggingLevel) -> log::Level {
+    match level {
+      LoggingLevel::Emergency
+      | LoggingLevel::Alert
+      | LoggingLevel::Critical
+      | LoggingLevel::Error => log::Level::Error,
+      LoggingLevel::Warning => log::Level::Warn,
+      LoggingLevel::Notice | LoggingLevel::Informational => log::Level::Info,
+      LoggingLevel::Debug => log::Level::Debug,
+    }
+  }
+}
+
+/// Name-value pair in the structured data of a [`BuiltinLoggingType`]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NameValuePair {
+  pub name: String,
+  pub value: String,
+}
+
+/// Data type of the builtin security logging topic
+/// `DDS:Security:LogTopic`.
+///
+/// See DDS Security spec v1.1 Section "9.6 Builtin Logging Plugin".
+/// The GUIDs involved in the event are in `structured_data` under the key
+/// `"DDS"`, together with the `instance_id` of the local participant, if it
+/// has one.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BuiltinLoggingType {
+  pub facility: u8,
+  pub severity: LoggingLevel,
+  pub timestamp: Timestamp,
+  pub hostname: String,
+  pub hostip: String,
+  pub appname: String,
+  pub procid: String,
+  /// Identifies the type of event, e.g. `"HANDSHAKE_COMPLETED"`.
+  pub msgid: String,
+  pub message: String,
+  pub structured_data: BTreeMap<String, Vec<NameValuePair>>,
+}
+
+impl BuiltinLoggingType {
+  /// Look up a value from the structured data
+  pub fn structured_value(&self, sd_id: &str, name: &str) -> Option<&str> {
+    self
+      .structured_data
+      .get(sd_id)?
+      .iter()
+      .find(|nvp| nvp.name == name)
+      .map(|nvp| nvp.value.as_str())
+  }
+}
+
+/// QoS of the builtin logging topic. Subscribers should use this, or
+/// something compatible with it.
+pub fn builtin_logging_topic_qos() -> QosPolicies {
+  QosPolicyBuilder::new()
+    .reliability(qos::policy::Reliability::Reliable {
+      max_blocking_time: crate::Duration::DURATION_ZERO,
+    })
+    .durability(qos::policy::Durability::TransientLocal)
+    .history(qos::policy::History::KeepLast {
+      depth: LOG_HISTORY_DEPTH,
+    })
+    .build()
+}
+
+/// Values of [`BuiltinLoggingType::msgid`] for the events that RustDDS logs
+pub mod msgid {
+  pub const HANDSHAKE_STARTED: &str = "HANDSHAKE_STARTED";
+  pub const HANDSHAKE_COMPLETED: &str = "HANDSHAKE_COMPLETED";
+  pub const HANDSHAKE_FAILED: &str = "HANDSHAKE_FAILED";
+  pub const AUTHENTICATION_FAILED: &str = "AUTHENTICATION_FAILED";
+  pub const INCOMPATIBLE_SECURITY: &str = "INCOMPATIBLE_SECURITY";
+  /// Local and remote governance disagree on how a topic is protected
+  pub const PROTECTION_MISMATCH: &str = "PROTECTION_MISMATCH";
+  pub const PERMISSION_DENIED: &str = "PERMISSION_DENIED";
+  pub const CRYPTO_TOKENS_SENT: &str = "CRYPTO_TOKENS_SENT";
+  pub const CRYPTO_TOKENS_RECEIVED: &str = "CRYPTO_TOKENS_RECEIVED";
+}
+
+// Publishes security events on the builtin logging topic. Owned by
+// SecureDiscovery, which is where the events happen.
+pub(crate) struct SecurityLogger {
+  local_participant_guid: GUID,
+  instance_id: Option<String>,
+  log_level: LoggingLevel,
+  writer: Option<no_key::DataWriter<BuiltinLoggingType>>,
+}
+
+impl SecurityLogger {
+  pub fn new(domain_participant: &DomainParticipantWeak, participant_qos: &QosPolicies) -> Self {
+    let log_level = match participant_qos.get_optional_property(QOS_LOG_LEVEL_PROPERTY_NAME) {
+      Some(level_str) => LoggingLevel::parse(&level_str).unwrap_or_else(|| {
+        warn!(
+          "Unknown {} value {:?}. Using {:?}.",
+          QOS_LOG_LEVEL_PROPERTY_NAME, level_str, DEFAULT_LOG_LEVEL
+        );
+        DEFAULT_LOG_LEVEL
+      }),
+      None => DEFAULT_LOG_LEVEL,
+    };
+
+    let distribute = participant_qos
+      .get_optional_property(QOS_LOG_DISTRIBUTE_PROPERTY_NAME)
+      .map(|s| s.trim().eq_ignore_ascii_case("true"))
+      .unwrap_or(true);
+
+    let writer = if distribute {
+      Self::create_writer(domain_participant)
+        .map_err(|e| warn!("Cannot create the builtin security logging DataWriter: {e}"))
+        .ok()
+    } else {
+      None
+    };
+
+    SecurityLogger {
+      local_participant_guid: domain_participant.guid(),
+      instance_id: domain_participant.instance_id(),
+      log_level,
+      writer,
+    }
+  }
+
+  fn create_writer(
+    domain_participant: &DomainParticipantWeak,
+  ) -> CreateResult<no_key::DataWriter<BuiltinLoggingType>> {
+    let qos = builtin_logging_topic_qos();
+    let topic = domain_participant.create_topic(
+      BUILTIN_LOGGING_TOPIC_NAME.to_string(),
+      BUILTIN_LOGGING_TYPE_NAME.to_string(),
+      &qos,
+      TopicKind::NoKey,
+    )?;
+    domain_participant
+      .create_builtin_publisher(&qos)?
+      .create_datawriter_no_key_cdr(&topic, None)
+  }
+
+  // Log a security event. It is always written to the normal log, and
+  // published on the logging topic if severe enough.
+  pub fn log(
+    &self,
+    level: LoggingLevel,
+    msg_id: &str,
+    remote_guid_prefix: Option<GuidPrefix>,
+    message: String,
+  ) {
+    log::log!(level.into(), "{}", message);
+
+    if level > self.log_level {
+      return;
+    }
+    let writer = match self.writer {
+      Some(ref w) => w,
+      None => return,
+    };
+
+    writer
+      .write(
+        self.log_message(level, msg_id, remote_guid_prefix, message),
+        None,
+      )
+      .unwrap_or_else(|e| debug!("Cannot publish security log message: {e}"));
+  }
+
+  fn log_message(
+    &self,
+    level: LoggingLevel,
+    msg_id: &str,
+    remote_guid_prefix: Option<GuidPrefix>,
+    message: String,
+  ) -> BuiltinLoggingType {
+    let mut guids = vec![NameValuePair {
+      name: "local_participant_guid".to_string(),
+      value: format!("{:?}", self.local_participant_guid),
+    }];
+    if let Some(prefix) = remote_guid_prefix {
+      guids.push(NameValuePair {
+        name: "remote_participant_guid".to_string(),
+        value: format!("{:?}", GUID::new(prefix, EntityId::PARTICIPANT)),
+      });
+    }
+
+    if let Some(ref instance_id) = self.instance_id {
+      guids.push(NameValuePair {
+        name: "instance_id".to_string(),
+        value: instance_id.clone(),
+      });
+    }
+
+    BuiltinLoggingType {
+      facility: LOG_FACILITY_SECURITY,
+      severity: level,
+      timestamp: Timestamp::now(),
+      hostname: String::new(),
+      hostip: String::new(),
+      appname: "RustDDS".to_string(),
+      procid: std::process::id().to_string(),
+      msgid: msg_id.to_string(),
+      message,
+      structured_data: BTreeMap::from([(STRUCTURED_DATA_DDS.to_string(), guids)]),
+    }
+  }
+}
+
+// A macro for logging of security events through a SecurityLogger:
+// security_log!(logger, level, msgid, remote_guid_prefix_opt, format, args..)
 #[macro_export]
 macro_rules! security_log {
-  ($($arg:tt)*) => (
-      {log::info!($($arg)*);}
+  ($logger:expr, $level:expr, $msg_id:expr, $remote:expr, $($arg:tt)*) => (
+      {$logger.log($level, $msg_id, $remote, format!($($arg)*));}
     )
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn parse_logging_level() {
+    assert_eq!(LoggingLevel::parse("error"), Some(LoggingLevel::Error));
+    assert_eq!(
+      LoggingLevel::parse("WARNING_LEVEL"),
+      Some(LoggingLevel::Warning)
+    );
+    assert_eq!(LoggingLevel::parse(" 7 "), Some(LoggingLevel::Debug));
+    assert_eq!(LoggingLevel::parse("loud"), None);
+    assert!(LoggingLevel::Error < LoggingLevel::Notice);
+  }
+
+  #[test]
+  fn log_message_has_instance_id() {
+    let logger = SecurityLogger {
+      local_participant_guid: GUID::new_participant_guid(),
+      instance_id: Some("robot-7".to_string()),
+      log_level: DEFAULT_LOG_LEVEL,
+      writer: None,
+    };
+    let message = logger.log_message(
+      LoggingLevel::Notice,
+      msgid::HANDSHAKE_COMPLETED,
+      None,
+      "Handshake completed".to_string(),
+    );
+    assert_eq!(
+      message.structured_value(STRUCTURED_DATA_DDS, "instance_id"),
+      Some("robot-7")
+    );
+    assert!(message
+      .structured_value(STRUCTURED_DATA_DDS, "local_participant_guid")
+      .is_some());
+  }
+}
@@ -1,20 +1,21 @@
 use core::fmt;
 use std::{
   collections::{HashMap, HashSet},
-  sync::{Arc, Mutex, MutexGuard},
+  sync::{Arc, Mutex, MutexGuard, PoisonError},
 };
 
+#[allow(unused_imports)]
+use log::{debug, error, info, trace, warn};
+
 use crate::{
   messages::submessages::{
-    elements::parameter_list::ParameterList,
-    secure_postfix::SecurePostfix,
+    elements::parameter_list::ParameterList, secure_postfix::SecurePostfix,
     secure_prefix::SecurePrefix,
-    submessage::{ReaderSubmessage, WriterSubmessage},
   },
   qos,
-  rtps::{Message, Submessage},
+  rtps::{Message, Submessage, SubmessageBody},
   security_error,
-  structure::guid::GuidPrefix,
+  structure::guid::{EntityId, GuidPrefix},
   QosPolicies, GUID,
 };
 use super::{
@@ -29,6 +30,15 @@ use super::{
   AccessControl, Cryptographic,
 };
 
+// The crypto plugin recognizes the volatile message endpoints by this property
+fn builtin_endpoint_name_property(endpoint_name: &str) -> Property {
+  Property {
+    name: "dds.sec.builtin_endpoint_name".to_string(),
+    value: endpoint_name.to_string(),
+    propagate: false,
+  }
+}
+
 pub(crate) struct SecurityPlugins {
   auth: Box<dyn Authentication>,
   access: Box<dyn AccessControl>,
@@ -348,6 +358,19 @@ impl SecurityPlugins {
     let handle = self.get_handshake_handle(&remote_participant_guidp)?;
     self.auth.get_shared_secret(handle)
   }
+
+  pub fn get_shared_secret_fingerprint(
+    &self,
+    remote_participant_guidp: GuidPrefix,
+  ) -> SecurityResult<Sha256> {
+    self
+      .get_shared_secret(remote_participant_guidp)
+      .map(|handle| handle.fingerprint())
+  }
+
+  pub fn reload_certificate_revocation_list(&mut self) -> SecurityResult<()> {
+    self.auth.reload_certificate_revocation_list()
+  }
 }
 
 /// Interface for using the Access control plugin
@@ -371,6 +394,18 @@ impl SecurityPlugins {
     Ok(())
   }
 
+  pub fn reload_local_permissions(
+    &mut self,
+    domain_id: u16,
+    participant_guidp: GuidPrefix,
+    participant_qos: &QosPolicies,
+  ) -> SecurityResult<()> {
+    let identity_handle = self.get_identity_handle(&participant_guidp)?;
+    self
+      .access
+      .reload_local_permissions(&*self.auth, identity_handle, domain_id, participant_qos)
+  }
+
   pub fn validate_remote_permissions(
     &mut self,
     local_participant_guidp: GuidPrefix,
@@ -416,6 +451,32 @@ impl SecurityPlugins {
       .check_remote_participant(handle, domain_id, None)
   }
 
+  pub fn check_create_datawriter(
+    &self,
+    domain_id: u16,
+    participant_guidp: GuidPrefix,
+    topic_name: String,
+    qos: &QosPolicies,
+  ) -> SecurityResult<()> {
+    let handle = self.get_permissions_handle(&participant_guidp)?;
+    self
+      .access
+      .check_create_datawriter(handle, domain_id, topic_name, qos)
+  }
+
+  pub fn check_create_datareader(
+    &self,
+    domain_id: u16,
+    participant_guidp: GuidPrefix,
+    topic_name: String,
+    qos: &QosPolicies,
+  ) -> SecurityResult<()> {
+    let handle = self.get_permissions_handle(&participant_guidp)?;
+    self
+      .access
+      .check_create_datareader(handle, domain_id, topic_name, qos)
+  }
+
   pub fn get_permissions_token(
     &self,
     participant_guidp: GuidPrefix,
@@ -503,7 +564,12 @@ impl SecurityPlugins {
   ) -> SecurityResult<()> {
     let participant_crypto_handle = self.get_participant_crypto_handle(&reader_guid.prefix)?;
 
-    let properties = reader_properties.map(|prop| prop.value).unwrap_or_default();
+    let mut properties = reader_properties.map(|prop| prop.value).unwrap_or_default();
+    if reader_guid.entity_id == EntityId::P2P_BUILTIN_PARTICIPANT_VOLATILE_SECURE_READER {
+      properties.push(builtin_endpoint_name_property(
+        "BuiltinParticipantVolatileMessageSecureReader",
+      ));
+    }
 
     if !reader_security_attributes.is_submessage_protected {
       self.submessage_not_protected.insert(reader_guid);
@@ -532,7 +598,12 @@ impl SecurityPlugins {
   ) -> SecurityResult<()> {
     let participant_crypto_handle = self.get_participant_crypto_handle(&writer_guid.prefix)?;
 
-    let properties = writer_properties.map(|prop| prop.value).unwrap_or_default();
+    let mut properties = writer_properties.map(|prop| prop.value).unwrap_or_default();
+    if writer_guid.entity_id == EntityId::P2P_BUILTIN_PARTICIPANT_VOLATILE_SECURE_WRITER {
+      properties.push(builtin_endpoint_name_property(
+        "BuiltinParticipantVolatileMessageSecureWriter",
+      ));
+    }
 
     if !writer_security_attributes.is_submessage_protected {
       self.submessage_not_protected.insert(writer_guid);
@@ -637,6 +708,22 @@ impl SecurityPlugins {
     let handle = self.get_local_endpoint_crypto_handle(writer_guid)?;
     self.crypto.unregister_datawriter(handle)
   }
+
+  // Called on participant shutdown. Unregisters all participants, local and
+  // remote, from the crypto plugin, so that it can discard all key material.
+  // The endpoints of the participants are unregistered along with them.
+  pub fn unregister_all_participants(&mut self) {
+    for (guid_prefix, handle) in self.participant_crypto_handle_cache.drain() {
+      if let Err(e) = self.crypto.unregister_participant(handle) {
+        warn!(
+          "Failed to unregister participant {:?} from the crypto plugin: {}",
+          guid_prefix, e
+        );
+      }
+    }
+    self.local_endpoint_crypto_handle_cache.clear();
+    self.remote_endpoint_crypto_handle_cache.clear();
+  }
 }
 
 /// Interface for using the CryptoKeyExchange of the Cryptographic plugin
@@ -751,10 +838,22 @@ impl SecurityPlugins {
       return Ok((serialized_payload, ParameterList::new()));
     }
 
-    self.crypto.encode_serialized_payload(
-      serialized_payload,
-      self.get_local_endpoint_crypto_handle(sending_datawriter_guid)?,
-    )
+    // The payload must be protected, or the writer has not been registered (yet),
+    // so we do not know if it is. Either way, never fall back to plaintext.
+    let local_datawriter_crypto_handle = self
+      .local_endpoint_crypto_handle_cache
+      .get(sending_datawriter_guid)
+      .copied()
+      .ok_or_else(|| {
+        security_error!(
+          "No key material for protecting the payload of writer {:?}. Refusing to send it.",
+          sending_datawriter_guid
+        )
+      })?;
+
+    self
+      .crypto
+      .encode_serialized_payload(serialized_payload, local_datawriter_crypto_handle)
   }
 
   pub fn encode_datawriter_submessage(
@@ -881,7 +980,7 @@ impl SecurityPlugins {
     encoded_rtps_submessage: (SecurePrefix, Submessage, SecurePostfix),
     receiving_datareader_crypto: DatareaderCryptoHandle,
     sending_datawriter_crypto: DatawriterCryptoHandle,
-  ) -> SecurityResult<WriterSubmessage> {
+  ) -> SecurityResult<SubmessageBody> {
     self.crypto.decode_datawriter_submessage(
       encoded_rtps_submessage,
       receiving_datareader_crypto,
@@ -894,7 +993,7 @@ impl SecurityPlugins {
     encoded_rtps_submessage: (SecurePrefix, Submessage, SecurePostfix),
     receiving_datawriter_crypto: DatawriterCryptoHandle,
     sending_datareader_crypto: DatareaderCryptoHandle,
-  ) -> SecurityResult<ReaderSubmessage> {
+  ) -> SecurityResult<SubmessageBody> {
     self.crypto.decode_datareader_submessage(
       encoded_rtps_submessage,
       receiving_datawriter_crypto,
@@ -939,6 +1038,42 @@ impl SecurityPlugins {
   pub fn payload_not_protected(&self, local_endpoint_guid: &GUID) -> bool {
     self.payload_not_protected.contains(local_endpoint_guid)
   }
+
+  // True if the payloads of the local writer can be sent: either they need no
+  // protection, or the key material for protecting them is available.
+  pub fn payload_key_material_ready(&self, local_datawriter_guid: &GUID) -> bool {
+    self.test_disable_crypto_transform
+      || self.payload_not_protected(local_datawriter_guid)
+      || self
+        .local_endpoint_crypto_handle_cache
+        .contains_key(local_datawriter_guid)
+  }
+}
+
+#[cfg(test)]
+impl SecurityPlugins {
+  pub(crate) fn participant_crypto_handle(
+    &self,
+    guidp: &GuidPrefix,
+  ) -> Option<ParticipantCryptoHandle> {
+    self.participant_crypto_handle_cache.get(guidp).copied()
+  }
+
+  // Whether any crypto handles are cached, or the crypto plugin still has key
+  // material for protecting messages from the local to the remote participant.
+  pub(crate) fn has_crypto_state(
+    &mut self,
+    local_handle: ParticipantCryptoHandle,
+    remote_handle: ParticipantCryptoHandle,
+  ) -> bool {
+    !self.participant_crypto_handle_cache.is_empty()
+      || !self.local_endpoint_crypto_handle_cache.is_empty()
+      || !self.remote_endpoint_crypto_handle_cache.is_empty()
+      || self
+        .crypto
+        .create_local_participant_crypto_tokens(local_handle, remote_handle)
+        .is_ok()
+  }
 }
 
 #[derive(Clone)]
@@ -953,6 +1088,15 @@ impl SecurityPluginsHandle {
     }
   }
 
+  // Called on shutdown, to release all crypto state. Unlike get_plugins(),
+  // this does not panic if some thread panicked while holding the plugins.
+  pub(crate) fn unregister_all_participants(&self) {
+    self
+      .lock()
+      .unwrap_or_else(PoisonError::into_inner)
+      .unregister_all_participants();
+  }
+
   pub(crate) fn get_plugins(&self) -> MutexGuard<SecurityPlugins> {
     self.lock().unwrap_or_else(|e| {
       security_error!("Security plugins are poisoned! {}", e);
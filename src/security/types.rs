@@ -98,61 +98,9 @@ macro_rules! security_error {
     )
 }
 
-// Property_t type from section 7.2.1 of the Security specification (v. 1.1)
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)] // for CDR in Discovery
-pub struct Property {
-  pub(crate) name: String,
-  pub(crate) value: String,
-  pub(crate) propagate: bool, // NOT SERIALIZED
-}
-
-impl<'a, C: Context> Readable<'a, C> for Property {
-  fn read_from<R: Reader<'a, C>>(reader: &mut R) -> Result<Self, C::Error> {
-    let name: StringWithNul = reader.read_value()?;
-
-    read_pad(reader, name.len(), 4)?; // pad according to previous read
-    let value: StringWithNul = reader.read_value()?;
-
-    Ok(Property {
-      name: name.into(),
-      value: value.into(),
-      propagate: true, // since we read this from thw wire, it was propagated
-    })
-  }
-}
-
-// Writing several strings is a bit complicated, because
-// we have to keep track of alignment.
-// Again, alignment comes BEFORE string length, or vector item count, not after
-// string.
-impl<C: Context> Writable<C> for Property {
-  fn write_to<T: ?Sized + Writer<C>>(&self, writer: &mut T) -> Result<(), C::Error> {
-    if self.propagate {
-      let name = StringWithNul::from(self.name.clone());
-      // nothing yet to pad
-      writer.write_value(&name)?;
-
-      write_pad(writer, name.len(), 4)?;
-      let value = StringWithNul::from(self.value.clone());
-      writer.write_value(&value)?;
-    }
-    Ok(())
-  }
-}
-
-impl Property {
-  pub fn serialized_len(&self) -> usize {
-    let first = 4 + self.name.len() + 1;
-    let misalign = first % 4;
-    let align = if misalign > 0 { 4 - misalign } else { 0 };
-    let second = 4 + self.value.len() + 1;
-    first + align + second
-  }
-
-  pub fn value(&self) -> String {
-    self.value.clone()
-  }
-}
+// Property_t and BinaryProperty_t (sections 7.2.1 and 7.2.2 of the Security
+// specification v. 1.1) are defined with the PropertyQosPolicy.
+pub use crate::dds::qos::property::{BinaryProperty, Property};
 
 fn get_optional_property(properties: &[Property], property_name: &str) -> Option<String> {
   properties
@@ -166,14 +114,6 @@ fn get_property(properties: &[Property], property_name: &str) -> SecurityResult<
 }
 
 impl QosPolicies {
-  pub(super) fn get_optional_property(&self, property_name: &str) -> Option<String> {
-    self
-      .property
-      .as_ref()
-      .and_then(|properties_or_binary_properties| {
-        get_optional_property(&properties_or_binary_properties.value, property_name)
-      })
-  }
   pub(super) fn get_property(&self, property_name: &str) -> SecurityResult<String> {
     self
       .property
@@ -185,31 +125,6 @@ impl QosPolicies {
   }
 }
 
-// BinaryProperty_t type from section 7.2.2 of the Security specification (v.
-// 1.1)
-// // Serialize, Deserialize for CDR in Discovery
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
-#[serde(into = "repr::BinaryProperty", from = "repr::BinaryProperty")]
-pub struct BinaryProperty {
-  pub(crate) name: String,    // public because of serialization
-  pub(crate) value: Bytes,    // Serde cannot derive for Bytes, therefore use repr::
-  pub(crate) propagate: bool, // propagate field is not serialized
-}
-
-impl BinaryProperty {
-  pub fn with_propagate(name: &str, value: Bytes) -> Self {
-    BinaryProperty {
-      name: name.to_string(),
-      value,
-      propagate: true,
-    }
-  }
-
-  pub fn value(&self) -> Bytes {
-    self.value.clone()
-  }
-}
-
 fn get_optional_binary_property(
   binary_properties: &[BinaryProperty],
   binary_property_name: &str,
@@ -231,79 +146,6 @@ fn get_binary_property(
   })
 }
 
-mod repr {
-  use serde::{Deserialize, Serialize};
-
-  #[derive(Serialize, Deserialize)]
-  pub struct BinaryProperty {
-    pub(crate) name: String,
-    pub(crate) value: Vec<u8>,
-    //pub(crate) propagate: bool,
-    // The "propagate" flag is never serialized, because it is always true, if we are serializing.
-  }
-
-  impl From<BinaryProperty> for super::BinaryProperty {
-    fn from(bp: BinaryProperty) -> super::BinaryProperty {
-      super::BinaryProperty {
-        name: bp.name,
-        value: bp.value.into(),
-        propagate: true,
-      }
-    }
-  }
-
-  impl From<super::BinaryProperty> for BinaryProperty {
-    fn from(bp: super::BinaryProperty) -> BinaryProperty {
-      BinaryProperty {
-        name: bp.name,
-        value: bp.value.into(),
-      }
-    }
-  }
-}
-
-impl BinaryProperty {
-  pub fn serialized_len(&self) -> usize {
-    let first = 4 + self.name.len() + 1;
-    let misalign = first % 4;
-    let align = if misalign > 0 { 4 - misalign } else { 0 };
-    let second = 4 + self.value.len(); // no nul terminator byte here
-    first + align + second
-  }
-}
-
-impl<'a, C: Context> Readable<'a, C> for BinaryProperty {
-  fn read_from<R: Reader<'a, C>>(reader: &mut R) -> Result<Self, C::Error> {
-    let name: StringWithNul = reader.read_value()?;
-
-    read_pad(reader, name.len(), 4)?; // pad according to previous read
-    let value: Vec<u8> = reader.read_value()?;
-
-    Ok(BinaryProperty {
-      name: name.into(),
-      value: value.into(),
-      propagate: true, // since we read this from thw wire, it was propagated
-    })
-  }
-}
-
-// Writing several strings is a bit complicated, because
-// we have to keep track of alignment.
-// Again, alignment comes BEFORE string length, or vector item count, not after
-// string.
-impl<C: Context> Writable<C> for BinaryProperty {
-  fn write_to<T: ?Sized + Writer<C>>(&self, writer: &mut T) -> Result<(), C::Error> {
-    if self.propagate {
-      let name = StringWithNul::from(self.name.clone());
-      writer.write_value(&name)?;
-
-      write_pad(writer, name.len(), 4)?;
-      writer.write_value(&<Vec<u8>>::from(self.value.clone()))?;
-    }
-    Ok(())
-  }
-}
-
 // Tag type from section 7.2.5 of the DDS Security specification (v. 1.1)
 // The silly thing is almost the same as "Property"
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -678,6 +520,30 @@ pub struct EndpointSecurityInfo {
   plugin_endpoint_security_attributes: PluginEndpointSecurityAttributesMask,
 }
 
+impl From<EndpointSecurityAttributes> for EndpointSecurityInfo {
+  fn from(sec_attributes: EndpointSecurityAttributes) -> Self {
+    Self {
+      endpoint_security_attributes: EndpointSecurityAttributesMask::from(sec_attributes.clone()),
+      plugin_endpoint_security_attributes: sec_attributes.plugin_endpoint_attributes,
+    }
+  }
+}
+
+impl EndpointSecurityInfo {
+  // Security spec v1.1 Section 7.2.8: Endpoints are compatible if all of their
+  // security attributes are equal. A mask that is not valid is ignored in the
+  // comparison.
+  pub fn is_compatible_with(&self, other: &Self) -> bool {
+    let attributes_match = !self.endpoint_security_attributes.is_valid()
+      || !other.endpoint_security_attributes.is_valid()
+      || self.endpoint_security_attributes == other.endpoint_security_attributes;
+    let plugin_attributes_match = !self.plugin_endpoint_security_attributes.is_valid()
+      || !other.plugin_endpoint_security_attributes.is_valid()
+      || self.plugin_endpoint_security_attributes == other.plugin_endpoint_security_attributes;
+    attributes_match && plugin_attributes_match
+  }
+}
+
 #[derive(Debug, PartialOrd, PartialEq, Ord, Eq, Clone, Copy, Readable, Writable)]
 #[bitflags]
 #[repr(u32)]
@@ -974,7 +840,7 @@ impl From<ParticipantGenericMessage> for ParticipantStatelessMessage {
 // specification
 //
 // spec: typedef ParticipantVolatileMessageSecure ParticipantGenericMessage;
-#[derive(Serialize, Deserialize)]
+#[derive(Clone, Serialize, Deserialize)]
 pub(crate) struct ParticipantVolatileMessageSecure {
   pub generic: ParticipantGenericMessage,
 }
@@ -997,7 +863,7 @@ use crate::{
   discovery::{sedp_messages::Endpoint_GUID, spdp_participant_data::Participant_GUID},
   structure::rpc,
 };
-use super::access_control::ParticipantSecurityAttributes;
+use super::access_control::{EndpointSecurityAttributes, ParticipantSecurityAttributes};
 
 // This is the transport (message) type for specialized versions above.
 // DDS Security Spec v1.1
@@ -5,11 +5,19 @@ pub(crate) mod cdr_serializer;
 pub mod error;
 pub mod representation_identifier;
 
+#[cfg(feature = "cbor")]
+pub(crate) mod cbor_adapters;
+#[cfg(feature = "json")]
+pub(crate) mod json_adapters;
 pub(crate) mod pl_cdr_adapters;
 
 // public exports
 pub use cdr_serializer::{to_writer_endian, CDRSerializerAdapter, CdrSerializer};
 pub use cdr_deserializer::{deserialize_from_cdr, CDRDeserializerAdapter, CdrDeserializer};
+#[cfg(feature = "json")]
+pub use json_adapters::{JsonDeserializerAdapter, JsonSerializerAdapter};
+#[cfg(feature = "cbor")]
+pub use cbor_adapters::{CborDeserializerAdapter, CborSerializerAdapter};
 pub use byteorder::{BigEndian, LittleEndian};
 pub use error::{Error, Result};
 
@@ -0,0 +1,184 @@
+use std::{io, marker::PhantomData};
+
+use serde::{de::DeserializeOwned, Serialize};
+use bytes::Bytes;
+
+use crate::{
+  dds::{
+    adapters::{no_key, with_key},
+    key::Keyed,
+  },
+  RepresentationIdentifier,
+};
+
+/// Serializes samples as CBOR, using [`RepresentationIdentifier::CBOR`].
+///
+/// CBOR is not an RTPS data representation, so only RustDDS readers using
+/// [`CborDeserializerAdapter`] can read these samples. It is a compact,
+/// self-describing alternative to CDR for RustDDS-to-RustDDS traffic.
+///
+/// Instance keys are taken from the sample value through [`Keyed`], as with
+/// any other adapter, so they do not depend on the encoding.
+pub struct CborSerializerAdapter<D> {
+  phantom: PhantomData<D>,
+}
+
+fn to_cbor<T: Serialize>(value: &T) -> Result<Bytes, ciborium::ser::Error<io::Error>> {
+  let mut buffer = Vec::new();
+  ciborium::ser::into_writer(value, &mut buffer)?;
+  Ok(Bytes::from(buffer))
+}
+
+impl<D> no_key::SerializerAdapter<D> for CborSerializerAdapter<D>
+where
+  D: Serialize,
+{
+  type Error = ciborium::ser::Error<io::Error>;
+
+  fn output_encoding() -> RepresentationIdentifier {
+    RepresentationIdentifier::CBOR
+  }
+
+  fn to_bytes(value: &D) -> Result<Bytes, Self::Error> {
+    to_cbor(value)
+  }
+}
+
+impl<D> with_key::SerializerAdapter<D> for CborSerializerAdapter<D>
+where
+  D: Keyed + Serialize,
+  <D as Keyed>::K: Serialize,
+{
+  fn key_to_bytes(value: &D::K) -> Result<Bytes, Self::Error> {
+    to_cbor(value)
+  }
+}
+
+/// Deserializes samples written with [`CborSerializerAdapter`].
+pub struct CborDeserializerAdapter<D> {
+  phantom: PhantomData<D>,
+}
+
+const REPR_IDS: [RepresentationIdentifier; 1] = [RepresentationIdentifier::CBOR];
+
+impl<D> no_key::DeserializerAdapter<D> for CborDeserializerAdapter<D>
+where
+  D: DeserializeOwned,
+{
+  type Error = ciborium::de::Error<io::Error>;
+
+  fn supported_encodings() -> &'static [RepresentationIdentifier] {
+    &REPR_IDS
+  }
+
+  fn from_bytes(input_bytes: &[u8], _encoding: RepresentationIdentifier) -> Result<D, Self::Error> {
+    ciborium::de::from_reader(input_bytes)
+  }
+}
+
+impl<D> with_key::DeserializerAdapter<D> for CborDeserializerAdapter<D>
+where
+  D: Keyed + DeserializeOwned,
+  <D as Keyed>::K: DeserializeOwned,
+{
+  fn key_from_bytes(
+    input_bytes: &[u8],
+    _encoding: RepresentationIdentifier,
+  ) -> Result<D::K, Self::Error> {
+    ciborium::de::from_reader(input_bytes)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use serde::Deserialize;
+
+  use super::*;
+  use crate::{
+    dds::qos::policy::{History, Reliability},
+    DomainParticipant, QosPolicyBuilder, ReadCondition, TopicKind,
+  };
+
+  #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+  struct Position {
+    x: f64,
+    y: f64,
+  }
+
+  #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+  struct Vehicle {
+    id: u32,
+    name: String,
+    route: Vec<Position>,
+    heading: Option<Position>,
+  }
+
+  impl Keyed for Vehicle {
+    type K = u32;
+    fn key(&self) -> u32 {
+      self.id
+    }
+  }
+
+  #[test]
+  fn cbor_round_trip_through_writer_and_reader() {
+    let domain_id = 76;
+    let dp1 = DomainParticipant::new(domain_id).unwrap();
+    let dp2 = DomainParticipant::new(domain_id).unwrap();
+
+    let qos = QosPolicyBuilder::new()
+      .reliability(Reliability::Reliable {
+        max_blocking_time: crate::Duration::from_secs(1),
+      })
+      .history(History::KeepAll)
+      .build();
+    let topic1 = dp1
+      .create_topic(
+        "CborTopic".to_string(),
+        "Vehicle".to_string(),
+        &qos,
+        TopicKind::WithKey,
+      )
+      .unwrap();
+    let topic2 = dp2
+      .create_topic(
+        "CborTopic".to_string(),
+        "Vehicle".to_string(),
+        &qos,
+        TopicKind::WithKey,
+      )
+      .unwrap();
+    let writer = dp1
+      .create_publisher(&qos)
+      .unwrap()
+      .create_datawriter::<Vehicle, CborSerializerAdapter<Vehicle>>(&topic1, None)
+      .unwrap();
+    let mut reader = dp2
+      .create_subscriber(&qos)
+      .unwrap()
+      .create_datareader::<Vehicle, CborDeserializerAdapter<Vehicle>>(&topic2, None)
+      .unwrap();
+
+    let sample = Vehicle {
+      id: 7,
+      name: "bus".to_string(),
+      route: vec![Position { x: 1.0, y: 2.5 }, Position { x: -3.0, y: 0.0 }],
+      heading: Some(Position { x: 0.0, y: 1.0 }),
+    };
+
+    let mut received = None;
+    crate::test::wait_until(std::time::Duration::from_secs(10), || {
+      writer.write(sample.clone(), None).unwrap();
+      std::thread::sleep(std::time::Duration::from_millis(50));
+      received = reader
+        .take(1, ReadCondition::any())
+        .unwrap()
+        .into_iter()
+        .next()
+        .and_then(|s| s.into_value().value());
+      received.is_some()
+    });
+    assert_eq!(received.as_ref().map(Keyed::key), Some(7));
+    assert_eq!(received, Some(sample));
+  }
+}
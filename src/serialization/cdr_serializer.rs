@@ -7,7 +7,7 @@ use byteorder::{BigEndian, ByteOrder, LittleEndian, WriteBytesExt};
 use crate::{
   dds::{
     adapters::{no_key, with_key},
-    key::Keyed,
+    key::{Keyed, SerializeKey},
   },
   RepresentationIdentifier,
 };
@@ -128,7 +128,7 @@ where
   fn key_to_bytes(value: &D::K) -> Result<Bytes> {
     let size_estimate = std::mem::size_of_val(value) * 2; // TODO: crude estimate
     let mut buffer: Vec<u8> = Vec::with_capacity(size_estimate);
-    to_writer::<D::K, BO, &mut Vec<u8>>(&mut buffer, value)?;
+    to_writer::<_, BO, &mut Vec<u8>>(&mut buffer, &SerializeKey(value))?;
     Ok(Bytes::from(buffer))
   }
 }
@@ -0,0 +1,158 @@
+use std::marker::PhantomData;
+
+use serde::{de::DeserializeOwned, Serialize};
+use bytes::Bytes;
+
+use crate::{
+  dds::{
+    adapters::{no_key, with_key},
+    key::Keyed,
+  },
+  RepresentationIdentifier,
+};
+
+/// Serializes samples as JSON, using [`RepresentationIdentifier::JSON`].
+///
+/// JSON is not an RTPS data representation, so only RustDDS readers using
+/// [`JsonDeserializerAdapter`] can read these samples. This is meant for
+/// debugging and for bridging RustDDS applications to JSON-based tooling.
+pub struct JsonSerializerAdapter<D> {
+  phantom: PhantomData<D>,
+}
+
+impl<D> no_key::SerializerAdapter<D> for JsonSerializerAdapter<D>
+where
+  D: Serialize,
+{
+  type Error = serde_json::Error;
+
+  fn output_encoding() -> RepresentationIdentifier {
+    RepresentationIdentifier::JSON
+  }
+
+  fn to_bytes(value: &D) -> Result<Bytes, serde_json::Error> {
+    serde_json::to_vec(value).map(Bytes::from)
+  }
+}
+
+impl<D> with_key::SerializerAdapter<D> for JsonSerializerAdapter<D>
+where
+  D: Keyed + Serialize,
+  <D as Keyed>::K: Serialize,
+{
+  fn key_to_bytes(value: &D::K) -> Result<Bytes, serde_json::Error> {
+    serde_json::to_vec(value).map(Bytes::from)
+  }
+}
+
+/// Deserializes samples written with [`JsonSerializerAdapter`].
+pub struct JsonDeserializerAdapter<D> {
+  phantom: PhantomData<D>,
+}
+
+const REPR_IDS: [RepresentationIdentifier; 1] = [RepresentationIdentifier::JSON];
+
+impl<D> no_key::DeserializerAdapter<D> for JsonDeserializerAdapter<D>
+where
+  D: DeserializeOwned,
+{
+  type Error = serde_json::Error;
+
+  fn supported_encodings() -> &'static [RepresentationIdentifier] {
+    &REPR_IDS
+  }
+
+  fn from_bytes(
+    input_bytes: &[u8],
+    _encoding: RepresentationIdentifier,
+  ) -> Result<D, serde_json::Error> {
+    serde_json::from_slice(input_bytes)
+  }
+}
+
+impl<D> with_key::DeserializerAdapter<D> for JsonDeserializerAdapter<D>
+where
+  D: Keyed + DeserializeOwned,
+  <D as Keyed>::K: DeserializeOwned,
+{
+  fn key_from_bytes(
+    input_bytes: &[u8],
+    _encoding: RepresentationIdentifier,
+  ) -> Result<D::K, serde_json::Error> {
+    serde_json::from_slice(input_bytes)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{
+    dds::qos::policy::{History, Reliability},
+    test::random_data::RandomData,
+    DomainParticipant, QosPolicyBuilder, ReadCondition, TopicKind,
+  };
+
+  #[test]
+  fn json_round_trip_through_writer_and_reader() {
+    let domain_id = 55;
+    let dp1 = DomainParticipant::new(domain_id).unwrap();
+    let dp2 = DomainParticipant::new(domain_id).unwrap();
+
+    let qos = QosPolicyBuilder::new()
+      .reliability(Reliability::Reliable {
+        max_blocking_time: crate::Duration::from_secs(1),
+      })
+      .history(History::KeepAll)
+      .build();
+    let topic1 = dp1
+      .create_topic(
+        "JsonTopic".to_string(),
+        "RandomData".to_string(),
+        &qos,
+        TopicKind::WithKey,
+      )
+      .unwrap();
+    let topic2 = dp2
+      .create_topic(
+        "JsonTopic".to_string(),
+        "RandomData".to_string(),
+        &qos,
+        TopicKind::WithKey,
+      )
+      .unwrap();
+    let writer = dp1
+      .create_publisher(&qos)
+      .unwrap()
+      .create_datawriter::<RandomData, JsonSerializerAdapter<RandomData>>(&topic1, None)
+      .unwrap();
+    let mut reader = dp2
+      .create_subscriber(&qos)
+      .unwrap()
+      .create_datareader::<RandomData, JsonDeserializerAdapter<RandomData>>(&topic2, None)
+      .unwrap();
+
+    let sample = RandomData {
+      a: 42,
+      b: "JSON \"quoted\" text".to_string(),
+    };
+    assert_eq!(
+      <JsonSerializerAdapter<RandomData> as no_key::SerializerAdapter<_>>::to_bytes(&sample)
+        .unwrap(),
+      Bytes::from_static(br#"{"a":42,"b":"JSON \"quoted\" text"}"#)
+    );
+
+    let mut received = None;
+    crate::test::wait_until(std::time::Duration::from_secs(10), || {
+      writer.write(sample.clone(), None).unwrap();
+      std::thread::sleep(std::time::Duration::from_millis(50));
+      received = reader
+        .take(1, ReadCondition::any())
+        .unwrap()
+        .into_iter()
+        .next()
+        .and_then(|s| s.into_value().value());
+      received.is_some()
+    });
+    assert_eq!(received, Some(sample));
+  }
+}
@@ -50,6 +50,21 @@ impl RepresentationIdentifier {
     bytes: [0x00, 0x04],
   };
 
+  /// JSON, as produced by `JsonSerializerAdapter` when the `json` feature is
+  /// enabled.
+  /// This is not defined by the RTPS specification, and is outside the range
+  /// it uses, so other implementations do not mistake it for CDR.
+  pub const JSON: Self = Self {
+    bytes: [0x80, 0x4a],
+  };
+
+  /// CBOR, as produced by `CborSerializerAdapter` when the `cbor` feature is
+  /// enabled.
+  /// Not defined by the RTPS specification, like [`Self::JSON`].
+  pub const CBOR: Self = Self {
+    bytes: [0x80, 0x43],
+  };
+
   // Reads two bytes to form a `RepresentationIdentifier`
   pub fn from_bytes(bytes: &[u8]) -> io::Result<Self> {
     let mut reader = io::Cursor::new(bytes);
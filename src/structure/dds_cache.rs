@@ -1,17 +1,20 @@
 use std::{
   cmp::max,
-  collections::{BTreeMap, HashMap},
+  collections::{BTreeMap, BTreeSet, HashMap},
   ops::Bound::{Excluded, Included},
-  sync::{Arc, Mutex},
+  sync::{
+    atomic::{AtomicU64, AtomicUsize, Ordering},
+    Arc, Mutex, MutexGuard, Weak,
+  },
 };
 
 #[allow(unused_imports)]
-use log::{debug, error, info, trace};
+use log::{debug, error, info, trace, warn};
 
 use crate::{
   dds::{
     qos::{
-      policy::{History, ResourceLimits},
+      policy::{Durability, History, ResourceLimits},
       QosPolicies,
     },
     typedesc::TypeDesc,
@@ -21,6 +24,18 @@ use crate::{
 };
 use super::cache_change::CacheChange;
 
+/// Memory used by the sample cache of one topic. See
+/// [`DomainParticipant::cache_usage`](crate::DomainParticipant::cache_usage).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TopicCacheUsage {
+  pub topic_name: String,
+  /// Number of samples, including dispose and unregister notifications
+  pub sample_count: usize,
+  /// Sum of the serialized payload sizes of the samples. The actual memory
+  /// use is somewhat larger, because of per-sample bookkeeping.
+  pub payload_bytes: usize,
+}
+
 /// DDSCache contains all cacheChanges that are produced by participant or
 /// received by participant. Each topic that has been published or subscribed to
 /// is contained in a separate TopicCache. One TopicCache contains
@@ -32,15 +47,100 @@ use super::cache_change::CacheChange;
 /// the actual TopicCaches. For a given topic, the Reader/Writer and
 /// DataReader/DataWriter get a clone of the handle and
 /// interact with the TopicCache through this handle.
-#[derive(Debug, Default)]
+#[derive(Debug)]
 pub struct DDSCache {
   topic_caches: HashMap<String, Arc<Mutex<TopicCache>>>,
+  // Limit for the number of topics. Only topics learned from Discovery are
+  // refused when the limit is reached. Local DataReaders and DataWriters
+  // always get their topic.
+  max_topics: usize,
+  // Limit for the total size of samples in all topics. Shared with the
+  // TopicCaches, because they do the eviction when adding samples.
+  memory_budget: Arc<CacheMemoryBudget>,
+}
+
+/// Default for the maximum number of topics in the cache of a
+/// DomainParticipant.
+pub(crate) const DEFAULT_MAX_CACHE_TOPICS: usize = 1024;
+
+impl Default for DDSCache {
+  fn default() -> Self {
+    Self {
+      topic_caches: HashMap::new(),
+      max_topics: DEFAULT_MAX_CACHE_TOPICS,
+      memory_budget: Arc::new(CacheMemoryBudget::default()),
+    }
+  }
 }
 
 impl DDSCache {
   pub fn new() -> Self {
     Self::default()
   }
+
+  pub fn set_max_topics(&mut self, max_topics: usize) {
+    self.max_topics = max_topics;
+  }
+
+  pub fn topic_count(&self) -> usize {
+    self.topic_caches.len()
+  }
+
+  // None means unlimited.
+  pub fn set_max_bytes(&mut self, max_bytes: Option<usize>) {
+    self
+      .memory_budget
+      .max_bytes
+      .store(max_bytes.unwrap_or(usize::MAX), Ordering::Relaxed);
+  }
+
+  // Total payload size of the samples in all topics.
+  #[cfg(test)]
+  pub fn used_bytes(&self) -> usize {
+    self.memory_budget.used_bytes.load(Ordering::Relaxed)
+  }
+
+  // Number of samples and their total payload size in each topic, ordered by
+  // topic name.
+  pub fn cache_usage(&self) -> Vec<TopicCacheUsage> {
+    self
+      .topic_caches
+      .iter()
+      .map(|(topic_name, topic_cache)| {
+        let topic_cache = topic_cache.lock().unwrap();
+        TopicCacheUsage {
+          topic_name: topic_name.clone(),
+          sample_count: topic_cache.changes.len(),
+          payload_bytes: topic_cache.used_bytes,
+        }
+      })
+      .collect()
+  }
+
+  // How many samples have been evicted to keep within max_bytes
+  pub fn evicted_sample_count(&self) -> u64 {
+    self.memory_budget.evicted_samples.load(Ordering::Relaxed)
+  }
+
+  // Like add_new_topic, but for topics learned from remote participants. If
+  // the topic does not exist yet, and the cache already has max_topics
+  // topics, no topic is created and None is returned. This prevents remote
+  // participants from growing the cache without bound.
+  pub(crate) fn add_new_remote_topic(
+    &mut self,
+    topic_name: String,
+    topic_data_type: TypeDesc,
+    qos: &QosPolicies,
+  ) -> Option<Arc<Mutex<TopicCache>>> {
+    if !self.topic_caches.contains_key(&topic_name) && self.topic_caches.len() >= self.max_topics {
+      warn!(
+        "DDSCache topic limit {} reached. Ignoring remote topic {:?}.",
+        self.max_topics, topic_name
+      );
+      return None;
+    }
+    Some(self.add_new_topic(topic_name, topic_data_type, qos))
+  }
   // Insert new topic if it does not exist.
   // If it exists already, update cache size limits.
   // Return a handle to the cache topic.
@@ -52,15 +152,21 @@ impl DDSCache {
     topic_data_type: TypeDesc,
     qos: &QosPolicies,
   ) -> Arc<Mutex<TopicCache>> {
+    let memory_budget = &self.memory_budget;
     let topic_cache_handle = self
       .topic_caches
       .entry(topic_name.clone())
       .and_modify(|tc| tc.lock().unwrap().update_keep_limits(qos))
-      .or_insert(Arc::new(Mutex::new(TopicCache::new(
-        topic_name,
-        topic_data_type,
-        qos,
-      ))));
+      .or_insert_with(|| {
+        let tc = Arc::new(Mutex::new(TopicCache::new(
+          topic_name,
+          topic_data_type,
+          qos,
+          memory_budget.clone(),
+        )));
+        memory_budget.register_topic_cache(&tc);
+        tc
+      });
 
     topic_cache_handle.clone()
   }
@@ -92,6 +198,57 @@ impl DDSCache {
   }
 }
 
+// Participant-wide limit for the total payload size of samples in the
+// DDSCache. When a TopicCache goes over the limit when adding a sample, it
+// evicts the oldest samples of volatile topics, its own or others', until the
+// total is within the limit again.
+#[derive(Debug)]
+pub(crate) struct CacheMemoryBudget {
+  max_bytes: AtomicUsize, // usize::MAX means unlimited
+  used_bytes: AtomicUsize,
+  evicted_samples: AtomicU64,
+  // All TopicCaches, so that eviction can look at other topics also.
+  topic_caches: Mutex<Vec<Weak<Mutex<TopicCache>>>>,
+}
+
+impl Default for CacheMemoryBudget {
+  fn default() -> Self {
+    Self {
+      max_bytes: AtomicUsize::new(usize::MAX),
+      used_bytes: AtomicUsize::new(0),
+      evicted_samples: AtomicU64::new(0),
+      topic_caches: Mutex::new(Vec::new()),
+    }
+  }
+}
+
+impl CacheMemoryBudget {
+  fn register_topic_cache(&self, topic_cache: &Arc<Mutex<TopicCache>>) {
+    let mut topic_caches = self.topic_caches.lock().unwrap();
+    topic_caches.retain(|tc| tc.strong_count() > 0);
+    topic_caches.push(Arc::downgrade(topic_cache));
+  }
+
+  fn topic_caches(&self) -> Vec<Arc<Mutex<TopicCache>>> {
+    self
+      .topic_caches
+      .lock()
+      .unwrap()
+      .iter()
+      .filter_map(Weak::upgrade)
+      .collect()
+  }
+
+  fn is_exceeded(&self) -> bool {
+    self.used_bytes.load(Ordering::Relaxed) > self.max_bytes.load(Ordering::Relaxed)
+  }
+}
+
+// When choosing a sample to evict, this many oldest samples of a topic are
+// scanned for one that is already acknowledged. If there is none, the oldest
+// sample is evicted.
+const EVICTION_SCAN_WINDOW: usize = 64;
+
 #[derive(Debug)]
 pub(crate) struct TopicCache {
   topic_name: String,
@@ -117,10 +274,38 @@ pub(crate) struct TopicCache {
   // Therefore, data before the marker SN can be handed off to a Reliable DataReader.
   // Initially, we consider the marker for each Writer (GUID) to be SequenceNumber::new(1)
   received_reliably_before: BTreeMap<GUID, SequenceNumber>,
+
+  // How many times each Writer has been seen to reset its sequence numbers.
+  // Reliable DataReaders compare this to what they have seen, and forget their
+  // read pointer for the Writer when it changes.
+  sequence_number_resets: BTreeMap<GUID, u32>,
+
+  // Ownership strengths of the matched Writers that have EXCLUSIVE ownership.
+  // DataReaders with EXCLUSIVE ownership pick the owner of each instance by
+  // these. Readers update this when Writers (re-)announce their QoS.
+  writer_ownership_strengths: BTreeMap<GUID, i32>,
+
+  // Liveliness of the Writers, as seen by the Readers. A Writer that is no
+  // longer matched is not alive. Keyed DataReaders use this to detect
+  // instances that no longer have any alive Writers.
+  writer_liveliness: BTreeMap<GUID, bool>,
+
+  // Accounting for the participant-wide memory budget
+  memory_budget: Arc<CacheMemoryBudget>,
+  used_bytes: usize,
+  // Samples of non-volatile topics are never evicted, because late-joining
+  // DataReaders expect to get them.
+  evictable: bool,
+  evicted_count: u64,
 }
 
 impl TopicCache {
-  pub fn new(topic_name: String, topic_data_type: TypeDesc, topic_qos: &QosPolicies) -> Self {
+  pub fn new(
+    topic_name: String,
+    topic_data_type: TypeDesc,
+    topic_qos: &QosPolicies,
+    memory_budget: Arc<CacheMemoryBudget>,
+  ) -> Self {
     let mut new_self = Self {
       topic_name,
       topic_data_type,
@@ -131,6 +316,13 @@ impl TopicCache {
       changes: BTreeMap::new(),
       sequence_numbers: BTreeMap::new(),
       received_reliably_before: BTreeMap::new(),
+      sequence_number_resets: BTreeMap::new(),
+      writer_ownership_strengths: BTreeMap::new(),
+      writer_liveliness: BTreeMap::new(),
+      memory_budget,
+      used_bytes: 0,
+      evictable: true,
+      evicted_count: 0,
     };
 
     new_self.update_keep_limits(topic_qos);
@@ -139,6 +331,10 @@ impl TopicCache {
   }
 
   fn update_keep_limits(&mut self, qos: &QosPolicies) {
+    if !matches!(qos.durability(), None | Some(Durability::Volatile)) {
+      self.evictable = false;
+    }
+
     let min_keep_samples = qos
       .history()
       // default history setting from DDS spec v1.4 Section 2.2.3 "Supported QoS",
@@ -174,6 +370,47 @@ impl TopicCache {
     self.received_reliably_before.insert(writer, sn);
   }
 
+  // The Writer has started numbering its samples again from a lower sequence
+  // number. Samples already in the cache are kept, but they are no longer
+  // indexed by sequence number, so that the new samples are not mistaken for
+  // duplicates of the old ones.
+  pub fn reset_sequence_numbers(&mut self, writer: GUID) {
+    self.sequence_numbers.remove(&writer);
+    self.received_reliably_before.remove(&writer);
+    *self.sequence_number_resets.entry(writer).or_default() += 1;
+  }
+
+  pub fn sequence_number_resets(&self) -> &BTreeMap<GUID, u32> {
+    &self.sequence_number_resets
+  }
+
+  // None means that the Writer does not have EXCLUSIVE ownership, or is no
+  // longer matched.
+  pub fn set_writer_ownership_strength(&mut self, writer: GUID, strength: Option<i32>) {
+    match strength {
+      Some(s) => self.writer_ownership_strengths.insert(writer, s),
+      None => self.writer_ownership_strengths.remove(&writer),
+    };
+  }
+
+  pub fn writer_ownership_strength(&self, writer: GUID) -> Option<i32> {
+    self.writer_ownership_strengths.get(&writer).copied()
+  }
+
+  pub fn set_writer_alive(&mut self, writer: GUID, alive: bool) {
+    self.writer_liveliness.insert(writer, alive);
+  }
+
+  // Writers that have been matched, but are now lost or not alive
+  pub fn lost_writers(&self) -> BTreeSet<GUID> {
+    self
+      .writer_liveliness
+      .iter()
+      .filter(|(_, alive)| !**alive)
+      .map(|(writer, _)| *writer)
+      .collect()
+  }
+
   pub fn get_change(&self, instant: &Timestamp) -> Option<&CacheChange> {
     self.changes.get(instant)
   }
@@ -187,6 +424,100 @@ impl TopicCache {
           self.topic_name, cc_back
         );
       });
+    self.enforce_memory_budget();
+  }
+
+  // Number of samples evicted from this topic because of the memory budget
+  pub fn evicted_count(&self) -> u64 {
+    self.evicted_count
+  }
+
+  fn account_added(&mut self, cc: &CacheChange) {
+    let size = cc.data_value.payload_size();
+    self.used_bytes += size;
+    self
+      .memory_budget
+      .used_bytes
+      .fetch_add(size, Ordering::Relaxed);
+  }
+
+  fn account_removed(&mut self, cc: &CacheChange) {
+    let size = cc.data_value.payload_size();
+    self.used_bytes -= size;
+    self
+      .memory_budget
+      .used_bytes
+      .fetch_sub(size, Ordering::Relaxed);
+  }
+
+  // Evict samples until the memory budget is no longer exceeded, or there is
+  // nothing left to evict. Other topics are only try_lock'ed, because we are
+  // holding the lock of this one. Busy topics are skipped.
+  fn enforce_memory_budget(&mut self) {
+    if !self.memory_budget.is_exceeded() {
+      return;
+    }
+    let others = self.memory_budget.topic_caches();
+    // Our own mutex is locked, so try_lock skips this topic here.
+    let mut other_guards: Vec<MutexGuard<TopicCache>> =
+      others.iter().filter_map(|tc| tc.try_lock().ok()).collect();
+
+    while self.memory_budget.is_exceeded() {
+      // Find the best eviction candidate over all topics. None as index
+      // refers to self.
+      let mut best = self.eviction_candidate().map(|c| (c, None));
+      for (i, other) in other_guards.iter().enumerate() {
+        if let Some(c) = other.eviction_candidate() {
+          let is_better = match best {
+            Some((b, _)) => c < b,
+            None => true,
+          };
+          if is_better {
+            best = Some((c, Some(i)));
+          }
+        }
+      }
+      match best {
+        Some(((_, instant), None)) => self.evict(instant),
+        Some(((_, instant), Some(i))) => other_guards[i].evict(instant),
+        None => {
+          debug!(
+            "DDSCache over memory budget, but nothing can be evicted. topic={:?}",
+            self.topic_name
+          );
+          break;
+        }
+      }
+    }
+  }
+
+  // The sample that this topic would evict next. Acknowledged samples, i.e.
+  // those before the reliably received mark, go first, then the oldest
+  // sample. The bool is false for acknowledged samples, so that the
+  // candidates of different topics can be compared with <.
+  fn eviction_candidate(&self) -> Option<(bool, Timestamp)> {
+    if !self.evictable {
+      return None;
+    }
+    self
+      .changes
+      .iter()
+      .take(EVICTION_SCAN_WINDOW)
+      .find(|(_, cc)| cc.sequence_number < self.reliable_before(cc.writer_guid))
+      .map(|(instant, _)| (false, *instant))
+      .or_else(|| self.changes.keys().next().map(|instant| (true, *instant)))
+  }
+
+  fn evict(&mut self, instant: Timestamp) {
+    if let Some(cc) = self.changes.remove(&instant) {
+      self.remove_sn(instant, &cc);
+      self.account_removed(&cc);
+      self.evicted_count += 1;
+      self
+        .memory_budget
+        .evicted_samples
+        .fetch_add(1, Ordering::Relaxed);
+    }
   }
 
   fn add_change_internal(
@@ -219,6 +550,7 @@ impl TopicCache {
     } else {
       // This is a new (to us) SequenceNumber, this is the default processing path.
       self.insert_sn(*instant, &cache_change);
+      self.account_added(&cache_change);
       self.changes.insert(*instant, cache_change).map(|old_cc| {
         // If this happens, cache changes were created at exactly same instant.
         // This is bad, since we are using instants as keys and assume that they
@@ -227,7 +559,8 @@ impl TopicCache {
           "DDSHistoryCache already contained element with key {:?} !!!",
           instant
         );
-        self.remove_sn(&old_cc);
+        self.remove_sn(*instant, &old_cc);
+        self.account_removed(&old_cc);
         old_cc
       })
     }
@@ -277,7 +610,7 @@ impl TopicCache {
             .unwrap_or(SequenceNumber::zero());
           let upper_bound_exc = self.reliable_before(*guid);
           // make sure lower < upper, so that `.range()` does not panic.
-          let upper_bound_exc = max(upper_bound_exc, lower_bound_exc.plus_1() );
+          let upper_bound_exc = max(upper_bound_exc, lower_bound_exc.plus_1());
           sn_map.range((Excluded(lower_bound_exc), Excluded(upper_bound_exc)))
         }) // we get iterator of Timestamp
         .filter_map(|(_sn, t)| self.get_change(t).map(|cc| (*t, cc))),
@@ -294,11 +627,14 @@ impl TopicCache {
     // received reliably, since no such samples exist.
   }
 
-  fn remove_sn(&mut self, cc: &CacheChange) {
+  fn remove_sn(&mut self, instant: Timestamp, cc: &CacheChange) {
     let mut emptied = false;
 
     self.sequence_numbers.entry(cc.writer_guid).and_modify(|s| {
-      s.remove(&cc.sequence_number);
+      // After a sequence number reset, the same SN may index a newer change.
+      if s.get(&cc.sequence_number) == Some(&instant) {
+        s.remove(&cc.sequence_number);
+      }
       emptied = s.is_empty();
     });
     if emptied {
@@ -306,6 +642,28 @@ impl TopicCache {
     }
   }
 
+  /// Remove changes whose LIFESPAN has expired by `now`
+  pub fn remove_expired_changes(&mut self, now: Timestamp) {
+    let expired: Vec<Timestamp> = self
+      .changes
+      .iter()
+      .filter(|(_, cc)| cc.write_options.expiration().is_some_and(|e| e < now))
+      .map(|(instant, _)| *instant)
+      .collect();
+
+    for instant in expired {
+      self.remove_change(&instant);
+    }
+  }
+
+  /// Remove a single change
+  pub fn remove_change(&mut self, instant: &Timestamp) -> Option<CacheChange> {
+    let cc = self.changes.remove(instant)?;
+    self.remove_sn(*instant, &cc);
+    self.account_removed(&cc);
+    Some(cc)
+  }
+
   /// remove changes before given Timestamp, but keep at least
   /// min_keep_samples.
   /// We must always keep below max_keep_samples.
@@ -345,8 +703,9 @@ impl TopicCache {
     let to_remove = std::mem::replace(&mut self.changes, to_retain);
 
     // update also SequenceNumber map
-    for r in to_remove.values() {
-      self.remove_sn(r);
+    for (instant, r) in &to_remove {
+      self.remove_sn(*instant, r);
+      self.account_removed(r);
     }
   }
 
@@ -355,6 +714,15 @@ impl TopicCache {
   }
 }
 
+impl Drop for TopicCache {
+  fn drop(&mut self) {
+    self
+      .memory_budget
+      .used_bytes
+      .fetch_sub(self.used_bytes, Ordering::Relaxed);
+  }
+}
+
 // -----------------------------------------------------------------------
 // -----------------------------------------------------------------------
 // -----------------------------------------------------------------------
@@ -362,19 +730,142 @@ impl TopicCache {
 #[cfg(test)]
 mod tests {
   use std::{
-    sync::{Arc, RwLock},
+    sync::{Arc, Mutex, RwLock},
     thread,
   };
 
-  use super::DDSCache;
+  use super::{DDSCache, TopicCache};
   use crate::{
     dds::{
-      ddsdata::DDSData, qos::QosPolicies, typedesc::TypeDesc, with_key::datawriter::WriteOptions,
+      ddsdata::DDSData,
+      qos::{policy::Durability, QosPolicies, QosPolicyBuilder},
+      typedesc::TypeDesc,
+      with_key::datawriter::WriteOptions,
     },
     messages::submessages::elements::serialized_payload::SerializedPayload,
-    structure::{cache_change::CacheChange, guid::GUID, sequence_number::SequenceNumber},
+    structure::{
+      cache_change::CacheChange, duration::Duration, guid::GUID, sequence_number::SequenceNumber,
+      time::Timestamp,
+    },
+    RepresentationIdentifier,
   };
 
+  #[test]
+  fn remote_topic_limit() {
+    let mut dds_cache = DDSCache::new();
+    dds_cache.set_max_topics(10);
+    let qos = QosPolicies::qos_none();
+
+    // A flood of topics from remote participants
+    let accepted = (0..100)
+      .filter_map(|i| {
+        dds_cache.add_new_remote_topic(format!("Topic{i}"), TypeDesc::new("T".to_string()), &qos)
+      })
+      .count();
+    assert_eq!(accepted, 10);
+    assert_eq!(dds_cache.topic_count(), 10);
+
+    // Already known topics are still found
+    assert!(dds_cache
+      .add_new_remote_topic("Topic3".to_string(), TypeDesc::new("T".to_string()), &qos)
+      .is_some());
+    assert_eq!(dds_cache.topic_count(), 10);
+
+    // Local topics are not limited
+    dds_cache.add_new_topic("Local".to_string(), TypeDesc::new("T".to_string()), &qos);
+    assert_eq!(dds_cache.topic_count(), 11);
+  }
+
+  #[test]
+  fn memory_budget_evicts_oldest_volatile_samples() {
+    let mut dds_cache = DDSCache::new();
+    dds_cache.set_max_bytes(Some(1000));
+    let volatile_qos = QosPolicies::qos_none();
+    let transient_qos = QosPolicyBuilder::new()
+      .durability(Durability::TransientLocal)
+      .build();
+
+    let topic_a = dds_cache.add_new_topic(
+      "A".to_string(),
+      TypeDesc::new("T".to_string()),
+      &volatile_qos,
+    );
+    let topic_b = dds_cache.add_new_topic(
+      "B".to_string(),
+      TypeDesc::new("T".to_string()),
+      &volatile_qos,
+    );
+    let topic_t = dds_cache.add_new_topic(
+      "T".to_string(),
+      TypeDesc::new("T".to_string()),
+      &transient_qos,
+    );
+
+    let start = Timestamp::now();
+    let mut time = 0;
+    let mut add = |topic: &Arc<Mutex<TopicCache>>, sn: i64| {
+      // 96 bytes of data + 4 bytes of header = 100 bytes
+      let change = CacheChange::new(
+        GUID::GUID_UNKNOWN,
+        SequenceNumber::new(sn),
+        WriteOptions::default(),
+        DDSData::new(SerializedPayload::new(
+          RepresentationIdentifier::CDR_LE,
+          vec![0; 96],
+        )),
+      );
+      time += 1;
+      topic
+        .lock()
+        .unwrap()
+        .add_change(&(start + Duration::from_millis(time)), change);
+    };
+
+    // Transient local samples are never evicted
+    for sn in 1..=3 {
+      add(&topic_t, sn);
+    }
+    // Fill past the budget
+    for sn in 1..=10 {
+      add(&topic_a, sn);
+    }
+    assert_eq!(dds_cache.used_bytes(), 1000);
+    assert_eq!(dds_cache.evicted_sample_count(), 3);
+    assert_eq!(topic_a.lock().unwrap().changes.len(), 7);
+    assert_eq!(topic_a.lock().unwrap().evicted_count(), 3);
+
+    // Samples of other topics are evicted too, if they are older.
+    for sn in 1..=2 {
+      add(&topic_b, sn);
+    }
+    assert_eq!(dds_cache.used_bytes(), 1000);
+    assert_eq!(dds_cache.evicted_sample_count(), 5);
+    assert_eq!(topic_a.lock().unwrap().changes.len(), 5);
+    assert_eq!(topic_b.lock().unwrap().changes.len(), 2);
+    assert_eq!(topic_t.lock().unwrap().changes.len(), 3);
+    // The remaining samples of A are the newest ones
+    assert_eq!(
+      topic_a
+        .lock()
+        .unwrap()
+        .changes
+        .values()
+        .next()
+        .unwrap()
+        .sequence_number,
+      SequenceNumber::new(6)
+    );
+
+    // Acknowledged samples are evicted before older unacknowledged ones.
+    topic_b
+      .lock()
+      .unwrap()
+      .mark_reliably_received_before(GUID::GUID_UNKNOWN, SequenceNumber::new(3));
+    add(&topic_b, 3);
+    assert_eq!(topic_a.lock().unwrap().changes.len(), 5);
+    assert_eq!(topic_b.lock().unwrap().changes.len(), 2);
+  }
+
   #[test]
   fn create_dds_cache_and_topic_cache() {
     // Create DDS cache
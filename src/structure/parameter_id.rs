@@ -56,6 +56,7 @@ impl ParameterId {
   pub const PID_PARTICIPANT_GUID: Self = Self { value: 0x0050 };
   pub const PID_GROUP_GUID: Self = Self { value: 0x0052 };
   pub const PID_GROUP_ENTITYID: Self = Self { value: 0x0053 };
+  pub const PID_COHERENT_SET: Self = Self { value: 0x0056 };
   pub const PID_BUILTIN_ENDPOINT_SET: Self = Self { value: 0x0058 };
   pub const PID_ENDPOINT_GUID: Self = Self { value: 0x005a };
   pub const PID_BUILTIN_ENDPOINT_QOS: Self = Self { value: 0x0077 };
@@ -81,6 +82,10 @@ impl ParameterId {
   // Wireshark calls this "PID_RELATED_ORIGINAL_WRITER_INFO".
   pub const PID_RELATED_SAMPLE_IDENTITY: Self = Self { value: /*0x0083*/ 0x800f };
 
+  // RustDDS vendor-specific. Interpreted only if the vendor is RustDDS.
+  // The largest RTPS message that the participant can receive.
+  pub const PID_RUSTDDS_MAX_MESSAGE_SIZE: Self = Self { value: 0x8001 };
+
   // DDS Security spec v1.1:
 
   // Section 7.4.1.4 Extension to RTPS Standard DCPSParticipants Builtin Topic
@@ -373,6 +378,12 @@ mod tests {
       le = [0x50, 0x00],
       be = [0x00, 0x50]
   },
+  {
+      pid_coherent_set,
+      ParameterId::PID_COHERENT_SET,
+      le = [0x56, 0x00],
+      be = [0x00, 0x56]
+  },
   {
       pid_group_guid,
       ParameterId::PID_GROUP_GUID,
@@ -1,6 +1,24 @@
 #![cfg(test)]
 
+use std::time::{Duration, Instant};
+
 pub(crate) mod random_data;
+mod ros2_mapping;
 pub(crate) mod shape_type;
 pub(crate) mod test_data;
 pub(crate) mod test_properties;
+
+/// Polls `condition` until it holds or `timeout` has passed, e.g. to wait for
+/// discovery to match endpoints. Returns whether the condition held.
+pub(crate) fn wait_until(timeout: Duration, mut condition: impl FnMut() -> bool) -> bool {
+  let deadline = Instant::now() + timeout;
+  loop {
+    if condition() {
+      return true;
+    }
+    if Instant::now() >= deadline {
+      return false;
+    }
+    std::thread::sleep(Duration::from_millis(10));
+  }
+}
@@ -0,0 +1,49 @@
+// Tests of the ROS 2 mapping helpers. They live outside the ros2 module,
+// because the test harness would otherwise refer to deprecated items.
+#![allow(deprecated)]
+
+use crate::{
+  dds::qos::policy::{Durability, History, Reliability},
+  ros2::{
+    builtin_datatypes::Gid, qos_profile, service_reply_topic_name, service_request_topic_name,
+    topic_name, type_name, Profile,
+  },
+  GUID,
+};
+
+#[test]
+fn sensor_data_profile() {
+  let qos = qos_profile(Profile::SensorData);
+  assert_eq!(qos.reliability(), Some(Reliability::BestEffort));
+  assert_eq!(qos.history(), Some(History::KeepLast { depth: 5 }));
+  assert_eq!(qos.durability(), Some(Durability::Volatile));
+}
+
+#[test]
+fn ros2_names() {
+  assert_eq!(topic_name("/chatter"), "rt/chatter");
+  assert_eq!(topic_name("ns/chatter"), "rt/ns/chatter");
+  assert_eq!(
+    service_request_topic_name("/add_two_ints"),
+    "rq/add_two_intsRequest"
+  );
+  assert_eq!(
+    service_reply_topic_name("/add_two_ints"),
+    "rr/add_two_intsReply"
+  );
+  assert_eq!(
+    type_name("std_msgs/msg/String"),
+    "std_msgs::msg::dds_::String_"
+  );
+  assert_eq!(type_name("std_msgs/String"), "std_msgs::msg::dds_::String_");
+  assert_eq!(
+    type_name("example_interfaces/srv/AddTwoInts_Request"),
+    "example_interfaces::srv::dds_::AddTwoInts_Request_"
+  );
+}
+
+#[test]
+fn gid_round_trip() {
+  let guid = GUID::new_participant_guid();
+  assert_eq!(Gid::from(guid).to_guid(), guid);
+}
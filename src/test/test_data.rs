@@ -138,56 +138,37 @@ pub(crate) fn spdp_publication_msg() -> Message {
 
 pub(crate) fn spdp_participant_msg_mod(port: u16) -> Message {
   let mut tdata: Message = spdp_participant_msg();
-  let mut data;
   for submsg in &mut tdata.submessages {
     let mut submsglen = submsg.header.content_length;
     match &mut submsg.body {
       SubmessageBody::Writer(v) => match v {
         WriterSubmessage::Data(d, _) => {
+          let mut payload = d.no_crypto_decoded().serialized_payload.unwrap();
           let mut participant_data: SpdpDiscoveredParticipantData =
             PlCdrDeserializerAdapter::<SpdpDiscoveredParticipantData>::from_bytes(
-              &d.no_crypto_decoded()
-                .serialized_payload
-                .as_ref()
-                .unwrap()
-                .value,
+              &payload.value,
               RepresentationIdentifier::PL_CDR_LE,
             )
             .unwrap();
-          participant_data.metatraffic_unicast_locators[0] =
-            Locator::from(SocketAddr::new("127.0.0.1".parse().unwrap(), port));
+          participant_data.metatraffic_unicast_locators = vec![Locator::from(SocketAddr::new(
+            "127.0.0.1".parse().unwrap(),
+            port,
+          ))];
           participant_data.metatraffic_multicast_locators.clear();
           participant_data.default_unicast_locators.clear();
           participant_data.default_multicast_locators.clear();
 
-          let datalen = d
-            .no_crypto_decoded()
-            .serialized_payload
-            .as_ref()
-            .unwrap()
-            .value
-            .len() as u16;
-          data = participant_data
+          let datalen = payload.value.len() as u16;
+          payload.value = participant_data
             .to_pl_cdr_bytes(RepresentationIdentifier::PL_CDR_LE)
             .unwrap();
-          // data = Bytes::from(
-          //   to_bytes::<SpdpDiscoveredParticipantData,
-          // byteorder::LittleEndian>(&participant_data)     .unwrap(),
-          // );
-          d.no_crypto_decoded()
-            .serialized_payload
-            .as_mut()
-            .unwrap()
-            .value = data.clone();
-          submsglen = submsglen
-            + d
-              .no_crypto_decoded()
-              .serialized_payload
-              .as_ref()
-              .unwrap()
-              .value
-              .len() as u16
-            - datalen;
+          submsglen = submsglen + payload.value.len() as u16 - datalen;
+          // Data carries the payload in serialized form
+          d.encoded_payload = Some(Bytes::from(
+            payload
+              .write_to_vec_with_ctx(Endianness::LittleEndian)
+              .unwrap(),
+          ));
         }
         _ => continue,
       },
@@ -0,0 +1,102 @@
+//! Helpers for testing applications and other DDS implementations against a
+//! live DomainParticipant without relying on network traffic: raw RTPS
+//! packets can be injected into the receive path of a participant, and the
+//! packets it sends can be captured.
+//!
+//! Available with the `test-util` feature. Not meant for production use.
+
+use std::{
+  net::SocketAddr,
+  sync::{Arc, Mutex},
+};
+
+use bytes::Bytes;
+use mio_extras::channel as mio_channel;
+
+use crate::{
+  create_error_dropped,
+  dds::result::{CreateError, CreateResult},
+  network::message_tap::{MessageDirection, MessageTap},
+};
+
+// A packet injected by the application, and its claimed source
+pub(crate) type InjectedPacket = (Bytes, SocketAddr);
+
+/// Feeds raw RTPS packets into the receive path of a DomainParticipant, as if
+/// they had arrived from the network. See
+/// [`DomainParticipant::packet_injector`](crate::DomainParticipant::packet_injector).
+///
+/// Injected packets are handled like packets from the UDP listeners: they are
+/// seen by the [`MessageTap`], checked for integrity, parsed, and routed to
+/// Discovery and the DataReaders. Malformed packets are dropped, as they
+/// would be from the network.
+#[derive(Clone)]
+pub struct PacketInjector {
+  sender: mio_channel::SyncSender<InjectedPacket>,
+}
+
+impl PacketInjector {
+  pub(crate) fn new(sender: mio_channel::SyncSender<InjectedPacket>) -> Self {
+    Self { sender }
+  }
+
+  /// Injects `packet`, as received from `source`. The packet is handled
+  /// asynchronously in the event loop thread of the participant.
+  ///
+  /// Blocks, if the event loop has not yet handled the previously injected
+  /// packets. Returns an error, if the participant has been dropped.
+  pub fn inject(&self, packet: &[u8], source: SocketAddr) -> CreateResult<()> {
+    self
+      .sender
+      .send((Bytes::copy_from_slice(packet), source))
+      .or_else(|_| create_error_dropped!("DomainParticipant event loop is gone"))
+  }
+}
+
+/// Records the packets that a DomainParticipant sends.
+///
+/// Give a clone of this to
+/// [`DomainParticipantBuilder::message_tap`](crate::DomainParticipantBuilder::message_tap)
+/// and read the packets from the original. Only outbound packets are
+/// recorded.
+///
+/// # Examples
+///
+/// ```
+/// # use std::sync::Arc;
+/// # use rustdds::{test_util::PacketCapture, DomainParticipantBuilder};
+/// let capture = PacketCapture::new();
+/// let mut builder = DomainParticipantBuilder::new(0);
+/// builder.message_tap(Arc::new(capture.clone()));
+/// let domain_participant = builder.build().unwrap();
+/// for (destination, packet) in capture.take() {
+///   println!("Sent {} bytes to {destination}", packet.len());
+/// }
+/// ```
+#[derive(Clone, Default)]
+pub struct PacketCapture {
+  packets: Arc<Mutex<CapturedPackets>>,
+}
+
+/// Packets recorded by a [`PacketCapture`], with their destination addresses.
+pub type CapturedPackets = Vec<(SocketAddr, Vec<u8>)>;
+
+impl PacketCapture {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// Removes and returns the packets recorded so far, in the order they were
+  /// sent, together with their destination addresses.
+  pub fn take(&self) -> CapturedPackets {
+    std::mem::take(&mut *self.packets.lock().unwrap())
+  }
+}
+
+impl MessageTap for PacketCapture {
+  fn on_message(&self, direction: MessageDirection, peer: SocketAddr, message: &[u8]) {
+    if direction == MessageDirection::Outbound {
+      self.packets.lock().unwrap().push((peer, message.to_vec()));
+    }
+  }
+}